@@ -18,9 +18,10 @@ pub mod base64 {
         data_encoding::BASE64_MIME,
         data_encoding::BASE64_NOPAD,
     ];
-    #[derive(Debug, Clone, PartialEq, Eq)]
+    #[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
     #[doc = " A container for binary that should be base64 encoded in serialisation. In reverse"]
     #[doc = " when deserializing, will decode from many different types of base64 possible."]
+    #[doc = " It compares and hashes by its bytes, whatever base64 it was decoded from."]
     pub struct Base64Data(pub Vec<u8>);
     impl Base64Data {
         #[doc = " Return is the data is empty."]
@@ -130,6 +131,29 @@ pub mod base64 {
             assert!(Base64Data::try_from("aGVsbG8=").is_ok());
             assert!(Base64Data::try_from("abcdefghij").is_err());
         }
+
+        #[test]
+        fn test_base64_hash_and_order_by_bytes() {
+            let set: std::collections::HashSet<Base64Data> = ["+/8=", "+/8", "-_8"]
+                .iter()
+                .map(|s| Base64Data::try_from(*s).unwrap())
+                .collect();
+            assert_eq!(set.len(), 1);
+            let mut data = vec![
+                Base64Data(vec![2]),
+                Base64Data(vec![1, 2]),
+                Base64Data(vec![1]),
+            ];
+            data.sort();
+            assert_eq!(
+                data,
+                vec![
+                    Base64Data(vec![1]),
+                    Base64Data(vec![1, 2]),
+                    Base64Data(vec![2]),
+                ]
+            );
+        }
     }
 }
 
@@ -216,9 +240,46 @@ pub mod phone_number {
     use std::str::FromStr;
 
     use schemars::JsonSchema;
-    #[doc = " A phone number."]
-    #[derive(Debug, Default, Clone, PartialEq, Hash, Eq)]
+    #[doc = " A phone number. Two phone numbers are equal, and hash the same, when they have"]
+    #[doc = " the same E.164 form and extension, however they were written."]
+    #[derive(Debug, Default, Clone)]
     pub struct PhoneNumber(pub Option<phonenumber::PhoneNumber>);
+    impl PhoneNumber {
+        #[doc = " The number in its E.164 form, like `+15555555555`, or `None` if empty."]
+        pub fn e164(&self) -> Option<String> {
+            self.0
+                .as_ref()
+                .map(|phone| phone.format().mode(phonenumber::Mode::E164).to_string())
+        }
+
+        #[doc = " What makes the number: its E.164 form, as the country code, the national"]
+        #[doc = " number and its leading zeros, and its extension."]
+        fn key(&self) -> Option<(u16, u64, u8, Option<&str>)> {
+            self.0.as_ref().map(|phone| {
+                (
+                    phone.code().value(),
+                    phone.national().value(),
+                    phone.national().zeros(),
+                    phone.extension().map(|extension| extension.as_ref()),
+                )
+            })
+        }
+    }
+
+    impl PartialEq for PhoneNumber {
+        fn eq(&self, other: &Self) -> bool {
+            self.key() == other.key()
+        }
+    }
+
+    impl Eq for PhoneNumber {}
+
+    impl std::hash::Hash for PhoneNumber {
+        fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+            self.key().hash(state)
+        }
+    }
+
     impl From<phonenumber::PhoneNumber> for PhoneNumber {
         fn from(id: phonenumber::PhoneNumber) -> PhoneNumber {
             PhoneNumber(Some(id))
@@ -353,6 +414,45 @@ pub mod phone_number {
             expected_str = "+49 30 12341234";
             assert_eq!(expected_str, serde_json::json!(phone_parsed));
         }
+
+        #[test]
+        fn test_phone_number_hash_by_e164() {
+            let set: std::collections::HashSet<PhoneNumber> = [
+                "+1-555-555-5555",
+                "555-555-5555",
+                "(555) 555 5555",
+                "+15555555555",
+            ]
+            .iter()
+            .map(|phone| phone.parse().unwrap())
+            .collect();
+            assert_eq!(set.len(), 1);
+            assert_eq!(
+                set.iter().next().unwrap().e164().as_deref(),
+                Some("+15555555555")
+            );
+            let raw = PhoneNumber(Some(phonenumber::parse(None, "+1 (555) 555-5555").unwrap()));
+            assert!(set.contains(&raw));
+            assert!(!set.contains(&"+1 510-864-1234".parse().unwrap()));
+            assert!(!set.contains(&PhoneNumber(None)));
+            assert_eq!(PhoneNumber(None), "".parse().unwrap());
+        }
+
+        #[test]
+        fn test_phone_number_extension() {
+            let parse = |phone: &str| PhoneNumber(Some(phonenumber::parse(None, phone).unwrap()));
+            let number = parse("+1 (555) 555-5555");
+            let desk = parse("+1 555-555-5555 ext. 123");
+            assert_eq!(desk, parse("+1 (555) 555-5555 ext 123"));
+            assert_ne!(desk, number);
+            assert_ne!(desk, parse("+1 555-555-5555 ext. 456"));
+            assert_eq!(desk.e164(), number.e164());
+            let set: std::collections::HashSet<PhoneNumber> =
+                vec![number.clone(), desk.clone()].into_iter().collect();
+            assert_eq!(set.len(), 2);
+            assert!(set.contains(&parse("+1 555-555-5555 ext. 123")));
+            assert_ne!(parse("+39 06 1234 5678"), parse("+39 6 1234 5678"));
+        }
     }
 }
 
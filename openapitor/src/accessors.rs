@@ -0,0 +1,383 @@
+//! Accessors for the optional nested objects of the responses, see
+//! `--nested-accessors`.
+//!
+//! The responses nest objects three or four deep, each of them optional, and
+//! getting to a field is a chain of `as_ref()` and `and_then`. For every struct
+//! that is only ever answered, never sent, we generate a method for every field
+//! of its optional nested objects, named by joining the names of the fields on
+//! the way: `customer.balance_cents()` is `customer.balance.cents`, or `None` if
+//! the balance is not set.
+//!
+//! A method that would have the name of a field of the struct, or of another
+//! method, is left out.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::Result;
+use proc_macro2::TokenStream;
+
+/// The numbers and booleans, the accessors return them rather than a reference.
+const COPY_TYPES: &[&str] = &[
+    "bool", "f32", "f64", "i8", "i16", "i32", "i64", "i128", "isize", "u8", "u16", "u32", "u64",
+    "u128", "usize",
+];
+
+/// How a field holds its value.
+enum Field {
+    /// `Option<T>`.
+    Option(syn::Type),
+    /// `Option<Box<T>>`.
+    OptionBox(syn::Type),
+    /// Anything else.
+    Plain(syn::Type),
+}
+
+/// Render the accessors of the response types of `rendered`, the generated types,
+/// for the fields up to `depth` deep.
+pub fn render(
+    rendered: &TokenStream,
+    operations: &[crate::functions::OperationInfo],
+    depth: usize,
+) -> Result<TokenStream> {
+    if depth < 2 {
+        anyhow::bail!(
+            "`--nested-accessors` must be at least 2, the fields of the nested objects are one \
+             deeper than the struct"
+        );
+    }
+
+    let file = syn::parse2::<syn::File>(rendered.clone())
+        .map_err(|e| anyhow::anyhow!("failed to parse the generated types: {}", e))?;
+
+    // The types every type refers to, to know which ones the requests send.
+    let mut structs = BTreeMap::new();
+    let mut references: BTreeMap<String, Vec<&syn::Type>> = BTreeMap::new();
+    for item in &file.items {
+        match item {
+            syn::Item::Struct(s) => {
+                references.insert(
+                    s.ident.to_string(),
+                    s.fields.iter().map(|f| &f.ty).collect(),
+                );
+                if matches!(s.fields, syn::Fields::Named(_)) {
+                    structs.insert(s.ident.to_string(), s);
+                }
+            }
+            syn::Item::Enum(e) => {
+                references.insert(
+                    e.ident.to_string(),
+                    e.variants
+                        .iter()
+                        .flat_map(|v| v.fields.iter().map(|f| &f.ty))
+                        .collect(),
+                );
+            }
+            syn::Item::Type(t) => {
+                references.insert(t.ident.to_string(), vec![&t.ty]);
+            }
+            _ => {}
+        }
+    }
+
+    let named = |types: &mut dyn Iterator<Item = &Option<String>>| -> Result<Vec<String>> {
+        let mut names = Vec::new();
+        for t in types.flatten() {
+            let ty: syn::Type = syn::parse_str(t)
+                .map_err(|e| anyhow::anyhow!("failed to parse the type `{}`: {}", t, e))?;
+            get_type_names(&ty, &mut names);
+        }
+        Ok(names)
+    };
+    let sent = get_reachable(
+        &references,
+        named(&mut operations.iter().map(|o| &o.request_type))?,
+    );
+    let answered = get_reachable(
+        &references,
+        named(&mut operations.iter().map(|o| &o.response_type))?,
+    );
+
+    let mut impls = Vec::new();
+    for item in &file.items {
+        let syn::Item::Struct(s) = item else {
+            continue;
+        };
+        let name = s.ident.to_string();
+        if !answered.contains(&name) || sent.contains(&name) || !structs.contains_key(&name) {
+            continue;
+        }
+
+        let mut chains = Vec::new();
+        get_chains(&structs, s, depth, &mut Vec::new(), &mut chains);
+
+        // Leave out the names of the fields, and the ones more than one chain has.
+        let fields = s
+            .fields
+            .iter()
+            .filter_map(|f| f.ident.as_ref().map(get_name))
+            .collect::<BTreeSet<_>>();
+        let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+        for chain in &chains {
+            *counts.entry(get_chain_name(chain)).or_default() += 1;
+        }
+
+        let methods = chains
+            .iter()
+            .filter(|chain| {
+                let name = get_chain_name(chain);
+                !fields.contains(&name) && counts[&name] == 1
+            })
+            .map(|chain| render_accessor(chain))
+            .collect::<Vec<_>>();
+        if methods.is_empty() {
+            continue;
+        }
+
+        let ident = &s.ident;
+        impls.push(quote! {
+            impl #ident {
+                #(#methods)*
+            }
+        });
+    }
+
+    Ok(quote!(#(#impls)*))
+}
+
+/// The chains of fields of `s` and its optional nested objects, of two fields or
+/// more and up to `depth`.
+fn get_chains<'a>(
+    structs: &BTreeMap<String, &'a syn::ItemStruct>,
+    s: &'a syn::ItemStruct,
+    depth: usize,
+    prefix: &mut Vec<&'a syn::Field>,
+    chains: &mut Vec<Vec<&'a syn::Field>>,
+) {
+    for field in &s.fields {
+        // The deprecated fields get no more uses.
+        if field.attrs.iter().any(|a| a.path().is_ident("deprecated")) {
+            continue;
+        }
+
+        prefix.push(field);
+        if prefix.len() >= 2 {
+            chains.push(prefix.clone());
+        }
+        if prefix.len() < depth {
+            let nested = match get_field(&field.ty) {
+                Field::Option(ty) | Field::OptionBox(ty) => get_struct_name(&ty)
+                    .and_then(|name| structs.get(&name))
+                    .copied(),
+                Field::Plain(_) => None,
+            };
+            if let Some(nested) = nested {
+                get_chains(structs, nested, depth, prefix, chains);
+            }
+        }
+        prefix.pop();
+    }
+}
+
+/// Render the accessor of a chain of fields.
+fn render_accessor(chain: &[&syn::Field]) -> TokenStream {
+    let fn_name = format_ident!("{}", get_chain_name(chain));
+    let path = chain
+        .iter()
+        .filter_map(|f| f.ident.as_ref().map(get_name))
+        .collect::<Vec<_>>()
+        .join(".");
+    let docs = format!(
+        "The `{}` of the nested objects, `None` if one of them is not set.",
+        path
+    );
+
+    // Every field but the last one is an optional object.
+    let (leaf, nested) = chain.split_last().expect("a chain has fields");
+    let mut expr = quote!(self);
+    for field in nested {
+        let ident = &field.ident;
+        expr = match get_field(&field.ty) {
+            Field::OptionBox(_) => quote!(#expr.#ident.as_deref()?),
+            _ => quote!(#expr.#ident.as_ref()?),
+        };
+    }
+
+    let ident = &leaf.ident;
+    let (returns, expr) = match get_field(&leaf.ty) {
+        Field::OptionBox(ty) => (quote!(Option<&#ty>), quote!(#expr.#ident.as_deref())),
+        Field::Option(ty) if is_string(&ty) => {
+            (quote!(Option<&str>), quote!(#expr.#ident.as_deref()))
+        }
+        Field::Option(ty) if is_copy(&ty) => (quote!(Option<#ty>), quote!(#expr.#ident)),
+        Field::Option(ty) => (quote!(Option<&#ty>), quote!(#expr.#ident.as_ref())),
+        Field::Plain(ty) if is_string(&ty) => {
+            (quote!(Option<&str>), quote!(Some(#expr.#ident.as_str())))
+        }
+        Field::Plain(ty) if is_copy(&ty) => (quote!(Option<#ty>), quote!(Some(#expr.#ident))),
+        Field::Plain(ty) => (quote!(Option<&#ty>), quote!(Some(&#expr.#ident))),
+    };
+
+    quote! {
+        #[doc = #docs]
+        pub fn #fn_name(&self) -> #returns {
+            #expr
+        }
+    }
+}
+
+/// The name of an accessor, the names of its fields joined with `_`.
+fn get_chain_name(chain: &[&syn::Field]) -> String {
+    chain
+        .iter()
+        .filter_map(|f| f.ident.as_ref().map(get_name))
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+/// The name of a field, without the `r#` of a keyword.
+fn get_name(ident: &syn::Ident) -> String {
+    ident.to_string().trim_start_matches("r#").to_string()
+}
+
+/// How a field of the type `ty` holds its value.
+fn get_field(ty: &syn::Type) -> Field {
+    match get_generic_arg(ty, "Option") {
+        Some(inner) => match get_generic_arg(&inner, "Box") {
+            Some(boxed) => Field::OptionBox(boxed),
+            None => Field::Option(inner),
+        },
+        None => Field::Plain(ty.clone()),
+    }
+}
+
+/// The `T` of `{wrapper}<T>`.
+fn get_generic_arg(ty: &syn::Type, wrapper: &str) -> Option<syn::Type> {
+    let syn::Type::Path(p) = ty else {
+        return None;
+    };
+    let segment = p.path.segments.last()?;
+    if segment.ident != wrapper {
+        return None;
+    }
+    let syn::PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    match args.args.first()? {
+        syn::GenericArgument::Type(ty) if args.args.len() == 1 => Some(ty.clone()),
+        _ => None,
+    }
+}
+
+/// The name of the type, if it is a plain one like `Thing` or
+/// `crate::types::Thing`.
+fn get_struct_name(ty: &syn::Type) -> Option<String> {
+    let syn::Type::Path(p) = ty else {
+        return None;
+    };
+    let segment = p.path.segments.last()?;
+    if !segment.arguments.is_empty() {
+        return None;
+    }
+    Some(segment.ident.to_string())
+}
+
+fn is_string(ty: &syn::Type) -> bool {
+    get_struct_name(ty).is_some_and(|name| name == "String")
+}
+
+fn is_copy(ty: &syn::Type) -> bool {
+    get_struct_name(ty).is_some_and(|name| COPY_TYPES.contains(&name.as_str()))
+}
+
+/// Every name of a type in `ty`, with the ones of its generic arguments.
+fn get_type_names(ty: &syn::Type, names: &mut Vec<String>) {
+    match ty {
+        syn::Type::Path(p) => {
+            for segment in &p.path.segments {
+                if let syn::PathArguments::AngleBracketed(args) = &segment.arguments {
+                    for arg in &args.args {
+                        if let syn::GenericArgument::Type(ty) = arg {
+                            get_type_names(ty, names);
+                        }
+                    }
+                }
+            }
+            if let Some(segment) = p.path.segments.last() {
+                names.push(segment.ident.to_string());
+            }
+        }
+        syn::Type::Tuple(t) => {
+            for ty in &t.elems {
+                get_type_names(ty, names);
+            }
+        }
+        syn::Type::Array(a) => get_type_names(&a.elem, names),
+        syn::Type::Slice(s) => get_type_names(&s.elem, names),
+        syn::Type::Reference(r) => get_type_names(&r.elem, names),
+        syn::Type::Paren(p) => get_type_names(&p.elem, names),
+        syn::Type::Group(g) => get_type_names(&g.elem, names),
+        _ => {}
+    }
+}
+
+/// The types `roots` are made of, with them.
+fn get_reachable(
+    references: &BTreeMap<String, Vec<&syn::Type>>,
+    roots: Vec<String>,
+) -> BTreeSet<String> {
+    let mut reachable = BTreeSet::new();
+    let mut todo = roots;
+    while let Some(name) = todo.pop() {
+        if !reachable.insert(name.clone()) {
+            continue;
+        }
+        for ty in references.get(&name).into_iter().flatten() {
+            get_type_names(ty, &mut todo);
+        }
+    }
+    reachable
+}
+
+#[cfg(test)]
+mod tests {
+    fn render(depth: usize) -> anyhow::Result<proc_macro2::TokenStream> {
+        let spec =
+            crate::load_json_spec(include_str!("../tests/types/input/nested-accessors.json"))
+                .unwrap();
+        let mut type_space = crate::types::generate_types(&spec, Default::default()).unwrap();
+        let (_, _, operations) =
+            crate::functions::generate_files(&mut type_space, &Default::default()).unwrap();
+        super::render(&type_space.rendered, &operations, depth)
+    }
+
+    #[test]
+    fn test_render_nested_accessors() {
+        let rendered = crate::types::get_text_fmt(&render(3).unwrap()).unwrap();
+        expectorate::assert_contents("tests/types/nested-accessors.rs.gen", &rendered);
+
+        // The types that are sent get none.
+        assert!(!rendered.contains("impl CustomerUpdate"), "{}", rendered);
+        // Nor the names of fields.
+        assert!(!rendered.contains("pub fn billing_email"), "{}", rendered);
+
+        // Less deep, fewer accessors.
+        let shallow = crate::types::get_text_fmt(&render(2).unwrap()).unwrap();
+        assert!(
+            shallow.contains("pub fn billing_plan(&self)"),
+            "{}",
+            shallow
+        );
+        assert!(
+            !shallow.contains("pub fn billing_balance_cents"),
+            "{}",
+            shallow
+        );
+
+        let err = render(1).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "`--nested-accessors` must be at least 2, the fields of the nested objects are one \
+             deeper than the struct"
+        );
+    }
+}
@@ -0,0 +1,429 @@
+//! Variants of the generated types that borrow their strings, see `--borrowed-type`.
+//!
+//! Parsing a big response allocates a `String` for every string in it, even for
+//! the fields the caller drops right away. For each type we are asked for, we
+//! generate a copy in `types::borrowed` whose strings are `Cow<'a, str>`, pointing
+//! into the JSON unless they have escapes. The caller keeps the bytes alive and
+//! parses them with `from_slice`, then `into_owned` gives back the generated type.
+//!
+//! Only the strings borrow: `String`, `Option<String>`, `Vec<String>` and
+//! `Option<Vec<String>>`, and the other borrowed types in the same shapes. Every
+//! other field is the same as in the generated type.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::Result;
+use proc_macro2::TokenStream;
+
+/// How a field wraps the type that can borrow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Shape {
+    Plain,
+    Option,
+    Vec,
+    OptionVec,
+}
+
+/// What a field can borrow.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Inner {
+    /// A string.
+    Str,
+    /// One of the types we borrow, by name.
+    Type(String),
+}
+
+/// Render the `borrowed` module with the variants of the types named `names`.
+pub fn render(rendered: &TokenStream, names: &[String]) -> Result<TokenStream> {
+    if names.is_empty() {
+        return Ok(quote!());
+    }
+
+    let file = syn::parse2::<syn::File>(rendered.clone())
+        .map_err(|e| anyhow::anyhow!("failed to parse the generated types: {}", e))?;
+
+    let mut structs = BTreeMap::new();
+    for name in names {
+        let item = file.items.iter().find(|item| match item {
+            syn::Item::Struct(s) => s.ident == name,
+            syn::Item::Enum(e) => e.ident == name,
+            syn::Item::Type(t) => t.ident == name,
+            _ => false,
+        });
+        match item {
+            Some(syn::Item::Struct(s)) if matches!(s.fields, syn::Fields::Named(_)) => {
+                structs.insert(name.to_string(), s);
+            }
+            Some(_) => anyhow::bail!(
+                "`{}` is not a struct with fields, so it has no borrowed variant",
+                name
+            ),
+            None => anyhow::bail!(
+                "`{}` is given to borrow, but it is not a generated type",
+                name
+            ),
+        }
+    }
+
+    // A type borrows if one of its fields does, which can depend on the other
+    // types we borrow.
+    let mut borrowing = BTreeSet::new();
+    loop {
+        let before = borrowing.len();
+        for (name, s) in &structs {
+            if s.fields
+                .iter()
+                .any(|field| get_borrowed(&field.ty, &borrowing).is_some())
+            {
+                borrowing.insert(name.to_string());
+            }
+        }
+        if borrowing.len() == before {
+            break;
+        }
+    }
+    if let Some(name) = structs.keys().find(|name| !borrowing.contains(*name)) {
+        anyhow::bail!(
+            "`{}` has no strings to borrow, so it does not need a borrowed variant",
+            name
+        );
+    }
+
+    let types = structs
+        .iter()
+        .map(|(name, s)| render_struct(name, s, &borrowing))
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(quote! {
+        pub mod borrowed {
+            //! Variants of some of the generated types whose strings borrow from the JSON
+            //! they are parsed from, so parsing a big response allocates less. The strings
+            //! with escapes are still allocated.
+
+            use std::borrow::Cow;
+
+            #[allow(unused_imports)]
+            use super::*;
+
+            /// A string that borrows from the JSON when it can, `Cow` on its own does not
+            /// in an `Option` or a `Vec`.
+            #[derive(serde::Deserialize)]
+            struct Str<'a>(#[serde(borrow)] Cow<'a, str>);
+
+            #[allow(dead_code)]
+            fn deserialize_option_str<'de: 'a, 'a, D>(
+                deserializer: D,
+            ) -> Result<Option<Cow<'a, str>>, D::Error>
+            where
+                D: serde::Deserializer<'de>,
+            {
+                let value: Option<Str<'de>> = serde::Deserialize::deserialize(deserializer)?;
+                Ok(value.map(|s| s.0))
+            }
+
+            #[allow(dead_code)]
+            fn deserialize_vec_str<'de: 'a, 'a, D>(deserializer: D) -> Result<Vec<Cow<'a, str>>, D::Error>
+            where
+                D: serde::Deserializer<'de>,
+            {
+                let value: Vec<Str<'de>> = serde::Deserialize::deserialize(deserializer)?;
+                Ok(value.into_iter().map(|s| s.0).collect())
+            }
+
+            #[allow(dead_code)]
+            fn deserialize_option_vec_str<'de: 'a, 'a, D>(
+                deserializer: D,
+            ) -> Result<Option<Vec<Cow<'a, str>>>, D::Error>
+            where
+                D: serde::Deserializer<'de>,
+            {
+                let value: Option<Vec<Str<'de>>> = serde::Deserialize::deserialize(deserializer)?;
+                Ok(value.map(|v| v.into_iter().map(|s| s.0).collect()))
+            }
+
+            #(#types)*
+        }
+    })
+}
+
+/// Render the borrowed variant of a struct.
+fn render_struct(
+    name: &str,
+    s: &syn::ItemStruct,
+    borrowing: &BTreeSet<String>,
+) -> Result<TokenStream> {
+    let ident = &s.ident;
+    let docs = s.attrs.iter().filter(|attr| is_kept(attr));
+
+    let mut fields = Vec::new();
+    let mut into_owned = Vec::new();
+    for field in &s.fields {
+        let Some(field_ident) = &field.ident else {
+            anyhow::bail!("`{}` has a field without a name", name);
+        };
+        let attrs = field.attrs.iter().filter(|attr| is_kept(attr));
+
+        match get_borrowed(&field.ty, borrowing) {
+            Some((shape, inner)) => {
+                let inner_ty = match &inner {
+                    Inner::Str => quote!(Cow<'a, str>),
+                    Inner::Type(name) => {
+                        let name = format_ident!("{}", name);
+                        quote!(#name<'a>)
+                    }
+                };
+                let (ty, serde, owned) = match shape {
+                    Shape::Plain => (
+                        inner_ty,
+                        quote!(#[serde(borrow)]),
+                        quote!(self.#field_ident.into_owned()),
+                    ),
+                    Shape::Option => (
+                        quote!(Option<#inner_ty>),
+                        quote!(#[serde(borrow, deserialize_with = "deserialize_option_str")]),
+                        quote!(self.#field_ident.map(|v| v.into_owned())),
+                    ),
+                    Shape::Vec => (
+                        quote!(Vec<#inner_ty>),
+                        quote!(#[serde(borrow, deserialize_with = "deserialize_vec_str")]),
+                        quote!(self.#field_ident.into_iter().map(|v| v.into_owned()).collect()),
+                    ),
+                    Shape::OptionVec => (
+                        quote!(Option<Vec<#inner_ty>>),
+                        quote!(#[serde(borrow, deserialize_with = "deserialize_option_vec_str")]),
+                        quote!(self
+                            .#field_ident
+                            .map(|v| v.into_iter().map(|v| v.into_owned()).collect())),
+                    ),
+                };
+                // The borrowed types borrow through an `Option` or a `Vec` on their own.
+                let serde = match inner {
+                    Inner::Type(_) => quote!(#[serde(borrow)]),
+                    Inner::Str => serde,
+                };
+                fields.push(quote! {
+                    #(#attrs)*
+                    #serde
+                    pub #field_ident: #ty,
+                });
+                into_owned.push(quote!(#field_ident: #owned));
+            }
+            None => {
+                let mut ty = field.ty.clone();
+                qualify(&mut ty, borrowing);
+                fields.push(quote! {
+                    #(#attrs)*
+                    pub #field_ident: #ty,
+                });
+                into_owned.push(quote!(#field_ident: self.#field_ident));
+            }
+        }
+    }
+
+    let from_slice_doc = format!(
+        "Parse a `{}` from JSON, its strings borrow from `bytes`.",
+        name
+    );
+    let into_owned_doc = format!(
+        "Copy the strings that borrow, for a [`super::{}`] that outlives the JSON.",
+        name
+    );
+    Ok(quote! {
+        #(#docs)*
+        #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug, Clone)]
+        pub struct #ident<'a> {
+            #(#fields)*
+        }
+
+        impl<'a> #ident<'a> {
+            #[doc = #from_slice_doc]
+            pub fn from_slice(bytes: &'a [u8]) -> serde_json::Result<Self> {
+                serde_json::from_slice(bytes)
+            }
+
+            #[doc = #into_owned_doc]
+            #[allow(deprecated)]
+            pub fn into_owned(self) -> super::#ident {
+                super::#ident {
+                    #(#into_owned),*
+                }
+            }
+        }
+    })
+}
+
+/// Whether we keep an attribute of the generated type on its borrowed variant,
+/// the docs and the serde ones. The others are for the generated type alone, and
+/// `deprecated` is left to the generated type.
+fn is_kept(attr: &syn::Attribute) -> bool {
+    let path = attr.path();
+    path.is_ident("doc") || path.is_ident("serde") || path.is_ident("cfg_attr")
+}
+
+/// What a field of type `ty` borrows, if anything.
+fn get_borrowed(ty: &syn::Type, borrowing: &BTreeSet<String>) -> Option<(Shape, Inner)> {
+    let inner = |ty: &syn::Type| -> Option<Inner> {
+        let name = get_ident(ty)?;
+        if name == "String" {
+            Some(Inner::Str)
+        } else if borrowing.contains(&name) {
+            Some(Inner::Type(name))
+        } else {
+            None
+        }
+    };
+
+    if let Some(inner) = inner(ty) {
+        return Some((Shape::Plain, inner));
+    }
+    if let Some(arg) = get_generic_arg(ty, "Option") {
+        if let Some(inner) = inner(arg) {
+            return Some((Shape::Option, inner));
+        }
+        if let Some(inner) = get_generic_arg(arg, "Vec").and_then(inner) {
+            return Some((Shape::OptionVec, inner));
+        }
+    }
+    if let Some(inner) = get_generic_arg(ty, "Vec").and_then(inner) {
+        return Some((Shape::Vec, inner));
+    }
+
+    None
+}
+
+/// The name of a type that is a single identifier, like `String`.
+fn get_ident(ty: &syn::Type) -> Option<String> {
+    let syn::Type::Path(path) = ty else {
+        return None;
+    };
+    if path.qself.is_some() {
+        return None;
+    }
+    let ident = path.path.get_ident()?;
+    Some(ident.to_string())
+}
+
+/// The type argument of `Wrapper<T>`, for a type like that.
+fn get_generic_arg<'a>(ty: &'a syn::Type, wrapper: &str) -> Option<&'a syn::Type> {
+    let syn::Type::Path(path) = ty else {
+        return None;
+    };
+    if path.qself.is_some() || path.path.segments.len() != 1 {
+        return None;
+    }
+    let segment = &path.path.segments[0];
+    if segment.ident != wrapper {
+        return None;
+    }
+    let syn::PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    match args.args.iter().collect::<Vec<_>>().as_slice() {
+        [syn::GenericArgument::Type(arg)] => Some(arg),
+        _ => None,
+    }
+}
+
+/// Point the names of the types we borrow at the generated types, in a field
+/// that does not borrow: in the `borrowed` module they are the borrowed variants.
+fn qualify(ty: &mut syn::Type, borrowing: &BTreeSet<String>) {
+    let syn::Type::Path(path) = ty else {
+        return;
+    };
+    if path.qself.is_none()
+        && path
+            .path
+            .get_ident()
+            .is_some_and(|ident| borrowing.contains(&ident.to_string()))
+    {
+        path.path
+            .segments
+            .insert(0, syn::PathSegment::from(format_ident!("super")));
+        return;
+    }
+    for segment in path.path.segments.iter_mut() {
+        if let syn::PathArguments::AngleBracketed(args) = &mut segment.arguments {
+            for arg in args.args.iter_mut() {
+                if let syn::GenericArgument::Type(arg) = arg {
+                    qualify(arg, borrowing);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::{get_borrowed, qualify, Inner, Shape};
+
+    #[test]
+    fn test_get_borrowed() {
+        let borrowing: BTreeSet<String> = ["Thing".to_string()].into();
+        let borrowed = |ty: &str| get_borrowed(&syn::parse_str(ty).unwrap(), &borrowing);
+
+        assert_eq!(borrowed("String"), Some((Shape::Plain, Inner::Str)));
+        assert_eq!(
+            borrowed("Option<String>"),
+            Some((Shape::Option, Inner::Str))
+        );
+        assert_eq!(borrowed("Vec<String>"), Some((Shape::Vec, Inner::Str)));
+        assert_eq!(
+            borrowed("Option<Vec<String>>"),
+            Some((Shape::OptionVec, Inner::Str))
+        );
+        assert_eq!(
+            borrowed("Vec<Thing>"),
+            Some((Shape::Vec, Inner::Type("Thing".to_string())))
+        );
+
+        // The rest is owned.
+        assert_eq!(borrowed("Other"), None);
+        assert_eq!(borrowed("uuid::Uuid"), None);
+        assert_eq!(borrowed("Box<Thing>"), None);
+        assert_eq!(borrowed("std::collections::HashMap<String, String>"), None);
+        assert_eq!(borrowed("Option<Option<String>>"), None);
+    }
+
+    #[test]
+    fn test_qualify() {
+        let borrowing: BTreeSet<String> = ["Thing".to_string()].into();
+        let qualified = |ty: &str| {
+            let mut ty: syn::Type = syn::parse_str(ty).unwrap();
+            qualify(&mut ty, &borrowing);
+            crate::types::get_text(&quote!(#ty)).unwrap()
+        };
+
+        assert_eq!(qualified("Box<Thing>"), "Box<super::Thing>");
+        assert_eq!(
+            qualified("std::collections::HashMap<String, Thing>"),
+            "std::collections::HashMap<String,super::Thing>"
+        );
+        assert_eq!(qualified("Option<Other>"), "Option<Other>");
+    }
+
+    #[test]
+    fn test_render_borrowed() {
+        let spec = crate::load_json_spec(include_str!("../tests/mock.json")).unwrap();
+        let type_space = crate::types::generate_types(&spec, Default::default()).unwrap();
+        let names = ["Thing".to_string(), "ThingResultsPage".to_string()];
+        let rendered = super::render(&type_space.rendered, &names).unwrap();
+        expectorate::assert_contents(
+            "tests/types/mock.borrowed.rs.gen",
+            &crate::types::get_text_fmt(&rendered).unwrap(),
+        );
+
+        // Only the structs with strings to borrow.
+        let err = super::render(&type_space.rendered, &["ThingKind".to_string()]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "`ThingKind` is not a struct with fields, so it has no borrowed variant"
+        );
+        let err = super::render(&type_space.rendered, &["Nope".to_string()]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "`Nope` is given to borrow, but it is not a generated type"
+        );
+    }
+}
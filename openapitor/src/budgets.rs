@@ -0,0 +1,243 @@
+//! The size budgets of the requests, to catch payload bloat when the spec changes.
+//!
+//! With `--generate-budgets` we write the example request body of every operation,
+//! the same ones the docs use, and record how many bytes of JSON it is in
+//! `budgets.json`. Given the `budgets.json` of a previous generation with
+//! `--budget-baseline`, the generation fails, or only warns with
+//! `--budget-warn-only`, when an example grew more than `--budget-threshold`
+//! percent.
+
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::types::exts::ReferenceOrExt;
+
+/// The version of the budgets format, bump this if the format changes in a way
+/// consumers would notice.
+pub const BUDGETS_VERSION: u32 = 1;
+
+/// The sizes of the example requests of a generated library.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Budgets {
+    /// The version of the budgets format.
+    pub budgets_version: u32,
+    /// The operations with a JSON request body, keyed by their id.
+    pub operations: BTreeMap<String, Budget>,
+}
+
+/// The size of the example request of an operation.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Budget {
+    /// The HTTP method of the operation.
+    pub method: String,
+    /// The path of the operation.
+    pub path: String,
+    /// The bytes of the example request body, as compact JSON.
+    pub request_bytes: usize,
+}
+
+/// An operation whose example request grew more than the threshold.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Regression {
+    /// The id of the operation.
+    pub operation: String,
+    /// The bytes of the example request of the baseline.
+    pub before: usize,
+    /// The bytes of the example request now.
+    pub after: usize,
+}
+
+impl std::fmt::Display for Regression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "`{}`: {} -> {} bytes ({:+.1}%)",
+            self.operation,
+            self.before,
+            self.after,
+            growth(self.before, self.after)
+        )
+    }
+}
+
+impl Budgets {
+    /// Measure the example request bodies of the operations of `spec`.
+    pub fn measure(spec: &openapiv3::OpenAPI) -> Result<Self> {
+        let mut operations = BTreeMap::new();
+        for (path, item) in spec.paths.iter() {
+            let item = item.item()?;
+            for (method, op) in item.iter() {
+                let Some(operation_id) = &op.operation_id else {
+                    continue;
+                };
+                let Some(request_body) = &op.request_body else {
+                    continue;
+                };
+                let request_body = request_body.expand(spec)?;
+                let Some(schema) = request_body
+                    .content
+                    .iter()
+                    .find(|(media_type, _)| {
+                        *media_type == "application/json" || media_type.ends_with("+json")
+                    })
+                    .and_then(|(_, media)| media.schema.as_ref())
+                else {
+                    continue;
+                };
+
+                let schema = schema.get_schema_from_reference(spec, true)?;
+                let example =
+                    crate::types::example::generate_example_json_from_schema(&schema, spec)
+                        .map_err(|e| {
+                            anyhow::anyhow!(
+                                "failed to generate the example request of `{}`: {}",
+                                operation_id,
+                                e
+                            )
+                        })?;
+                operations.insert(
+                    operation_id.to_string(),
+                    Budget {
+                        method: method.to_uppercase(),
+                        path: path.to_string(),
+                        request_bytes: serde_json::to_string(&example)?.len(),
+                    },
+                );
+            }
+        }
+
+        Ok(Budgets {
+            budgets_version: BUDGETS_VERSION,
+            operations,
+        })
+    }
+
+    /// Load the budgets of a previous generation from a file.
+    pub fn load<P: AsRef<std::path::Path>>(p: P) -> Result<Self> {
+        let p = p.as_ref();
+        let contents = std::fs::read_to_string(p)
+            .map_err(|e| anyhow::anyhow!("failed to read budgets `{}`: {}", p.display(), e))?;
+        let budgets: Budgets = serde_json::from_str(&contents)
+            .map_err(|e| anyhow::anyhow!("failed to parse budgets `{}`: {}", p.display(), e))?;
+        if budgets.budgets_version > BUDGETS_VERSION {
+            anyhow::bail!(
+                "budgets {} have version {}, but we only understand up to version {}",
+                p.display(),
+                budgets.budgets_version,
+                BUDGETS_VERSION
+            );
+        }
+        Ok(budgets)
+    }
+
+    /// The operations whose example request grew more than `threshold` percent
+    /// since `baseline`. The new operations have no budget yet.
+    pub fn regressions(&self, baseline: &Budgets, threshold: f64) -> Vec<Regression> {
+        self.operations
+            .iter()
+            .filter_map(|(operation, budget)| {
+                let before = baseline.operations.get(operation)?.request_bytes;
+                let after = budget.request_bytes;
+                (growth(before, after) > threshold).then(|| Regression {
+                    operation: operation.to_string(),
+                    before,
+                    after,
+                })
+            })
+            .collect()
+    }
+}
+
+/// How many percent `after` is bigger than `before`.
+fn growth(before: usize, after: usize) -> f64 {
+    if before == 0 {
+        return if after == 0 { 0.0 } else { f64::INFINITY };
+    }
+    (after as f64 - before as f64) * 100.0 / before as f64
+}
+
+/// Measure the requests of `spec`, check them against the baseline of the options
+/// and save them to `budgets.json` in the output directory.
+pub fn check(spec: &openapiv3::OpenAPI, opts: &crate::Opts) -> Result<Budgets> {
+    let budgets = Budgets::measure(spec)?;
+
+    if let Some(baseline) = &opts.budget_baseline {
+        let regressions = budgets.regressions(&Budgets::load(baseline)?, opts.budget_threshold);
+        if !regressions.is_empty() {
+            let report = format!(
+                "the example requests of {} operation(s) grew more than {}% since `{}`:\n{}",
+                regressions.len(),
+                opts.budget_threshold,
+                baseline.display(),
+                regressions
+                    .iter()
+                    .map(|regression| format!("- {}", regression))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            );
+            if opts.budget_warn_only {
+                log::warn!("{}", report);
+            } else {
+                anyhow::bail!("{}", report);
+            }
+        }
+    }
+
+    crate::save(
+        opts.output.join("budgets.json"),
+        &serde_json::to_string_pretty(&budgets)?,
+    )?;
+    Ok(budgets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Budget, Budgets, Regression, BUDGETS_VERSION};
+
+    fn budgets(operations: &[(&str, usize)]) -> Budgets {
+        Budgets {
+            budgets_version: BUDGETS_VERSION,
+            operations: operations
+                .iter()
+                .map(|(operation, request_bytes)| {
+                    (
+                        operation.to_string(),
+                        Budget {
+                            method: "POST".to_string(),
+                            path: format!("/{}", operation),
+                            request_bytes: *request_bytes,
+                        },
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_regressions() {
+        let baseline = budgets(&[("a", 100), ("b", 100), ("c", 0), ("d", 100)]);
+        let current = budgets(&[("a", 110), ("b", 111), ("c", 1), ("d", 50), ("e", 1000)]);
+
+        // Growing by the threshold is fine, shrinking and new operations too.
+        let regressions = current.regressions(&baseline, 10.0);
+        assert_eq!(
+            regressions,
+            [
+                Regression {
+                    operation: "b".to_string(),
+                    before: 100,
+                    after: 111,
+                },
+                Regression {
+                    operation: "c".to_string(),
+                    before: 0,
+                    after: 1,
+                },
+            ]
+        );
+        assert_eq!(regressions[0].to_string(), "`b`: 100 -> 111 bytes (+11.0%)");
+        assert_eq!(regressions[1].to_string(), "`c`: 0 -> 1 bytes (+inf%)");
+    }
+}
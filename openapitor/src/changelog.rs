@@ -0,0 +1,826 @@
+//! Track the public surface of a generated library and describe what changed
+//! between two generations.
+
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use proc_macro2::{Delimiter, TokenStream, TokenTree};
+use serde::{Deserialize, Serialize};
+
+use crate::types::exts::{OperationExt, ReferenceOrExt};
+
+/// The version of the manifest format, bump this if the format changes in a way
+/// consumers would notice.
+pub const MANIFEST_VERSION: u32 = 1;
+
+/// The public functions and types of a generated library.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Manifest {
+    /// The version of the manifest format.
+    pub manifest_version: u32,
+    /// The name of the generated crate.
+    pub name: String,
+    /// The version of the generated crate.
+    pub version: String,
+    /// The functions, keyed by `{tag}::{function}`.
+    pub functions: BTreeMap<String, FunctionSignature>,
+    /// The types, keyed by their name.
+    pub types: BTreeMap<String, TypeShape>,
+}
+
+/// The signature of a generated function.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FunctionSignature {
+    /// The HTTP method of the operation.
+    pub method: String,
+    /// The path of the operation.
+    pub path: String,
+    /// The arguments of the function, not including `self`.
+    pub args: Vec<Field>,
+    /// The return type of the function.
+    pub returns: String,
+    /// If the function is `async`.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub is_async: bool,
+    /// The `cfg` attributes of the function, like `feature="websocket"`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cfgs: Vec<String>,
+}
+
+/// A named and typed field or argument.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Field {
+    /// The name.
+    pub name: String,
+    /// The Rust type.
+    #[serde(rename = "type")]
+    pub type_: String,
+}
+
+/// The shape of a generated type.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TypeShape {
+    /// A struct with named fields.
+    Struct {
+        /// The fields of the struct.
+        fields: Vec<Field>,
+    },
+    /// A tuple struct.
+    Tuple {
+        /// The types of the fields.
+        fields: Vec<String>,
+    },
+    /// An enum.
+    Enum {
+        /// The variants of the enum.
+        variants: Vec<String>,
+    },
+    /// A type alias.
+    Alias {
+        /// The aliased type.
+        target: String,
+    },
+}
+
+/// A single entry in the changelog.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    /// What kind of change this is.
+    pub kind: ChangeKind,
+    /// If this change breaks code using the previous version.
+    pub breaking: bool,
+    /// A human description of the change.
+    pub description: String,
+}
+
+/// The kind of change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ChangeKind {
+    /// Something was added.
+    Added,
+    /// Something was removed.
+    Removed,
+    /// Something was changed.
+    Changed,
+}
+
+impl Manifest {
+    /// Build the manifest from the generated tag files and types.
+    pub fn from_generated(
+        spec: &openapiv3::OpenAPI,
+        opts: &crate::Opts,
+        tag_files: &BTreeMap<String, TokenStream>,
+        types: &TokenStream,
+    ) -> Result<Self> {
+        // Find the method and path for each function.
+        let mut operations: BTreeMap<String, (String, String)> = BTreeMap::new();
+        for (path, item) in spec.paths.iter() {
+            let item = item.item()?;
+            for (method, op) in item.iter() {
+                // We don't generate functions for operations without an id.
+                let Ok(fn_name) = op.get_fn_name() else {
+                    continue;
+                };
+                let key = format!("{}::{}", op.get_tag()?, fn_name);
+                operations.insert(key, (method.to_uppercase(), path.to_string()));
+            }
+        }
+
+        let mut functions = BTreeMap::new();
+        for (tag, content) in tag_files {
+            for (name, signature) in parse_functions(content.clone()) {
+                let key = format!("{}::{}", tag, name);
+                // Stream functions share the operation with the function they page through.
+                let (method, path) = operations
+                    .get(&key)
+                    .or_else(|| operations.get(key.trim_end_matches("_stream")))
+                    .cloned()
+                    .unwrap_or_default();
+                functions.insert(
+                    key,
+                    FunctionSignature {
+                        method,
+                        path,
+                        ..signature
+                    },
+                );
+            }
+        }
+
+        Ok(Manifest {
+            manifest_version: MANIFEST_VERSION,
+            name: opts.name.to_string(),
+            version: opts.target_version.to_string(),
+            functions,
+            types: parse_types(types.clone()),
+        })
+    }
+
+    /// Load a previous manifest from a file.
+    /// The file can either be a manifest or the spec the previous generation was built from.
+    pub fn load<P>(p: P, opts: &crate::Opts) -> Result<Self>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        let p = p.as_ref();
+        let contents = std::fs::read_to_string(p)?;
+        if let Ok(value) = serde_json::from_str::<serde_json::Value>(&contents) {
+            if value.get("manifest_version").is_some() {
+                let manifest: Manifest = serde_json::from_value(value)?;
+                if manifest.manifest_version > MANIFEST_VERSION {
+                    anyhow::bail!(
+                        "manifest {} has version {}, but we only understand up to version {}",
+                        p.display(),
+                        manifest.manifest_version,
+                        MANIFEST_VERSION
+                    );
+                }
+                return Ok(manifest);
+            }
+        }
+
+        // Otherwise this should be a spec, so generate the manifest for it.
+        let spec = crate::load_api(p)?;
+        build_manifest(&spec, opts)
+    }
+}
+
+/// Generate the library for the spec in memory and return its manifest.
+pub fn build_manifest(spec: &openapiv3::OpenAPI, opts: &crate::Opts) -> Result<Manifest> {
+    let _casing = crate::types::casing::scoped(opts);
+    let (spec, _) = crate::types::dedupe_schema_names(spec)?;
+    let (spec, _) = crate::opaque::prepare(&spec, opts)?;
+    let (spec, _) = crate::functions::synthesize_operation_ids(&spec)?;
+    let mut failures = crate::failures::Failures::default();
+    let mut type_space =
+        crate::types::generate_types_collecting(&spec, opts.clone(), &mut failures)?;
+    failures.into_result()?;
+    let (files, _, _) = crate::functions::generate_files(&mut type_space, opts)?;
+    Manifest::from_generated(&spec, opts, &files, &type_space.rendered)
+}
+
+/// Describe the changes between two manifests.
+pub fn diff(old: &Manifest, new: &Manifest) -> Vec<Change> {
+    let mut changes = Vec::new();
+
+    for (name, new_fn) in &new.functions {
+        let Some(old_fn) = old.functions.get(name) else {
+            changes.push(Change {
+                kind: ChangeKind::Added,
+                breaking: false,
+                description: format!(
+                    "Function `{}` for `{} {}`.",
+                    name, new_fn.method, new_fn.path
+                ),
+            });
+            continue;
+        };
+
+        if old_fn.method != new_fn.method || old_fn.path != new_fn.path {
+            changes.push(Change {
+                kind: ChangeKind::Changed,
+                breaking: false,
+                description: format!(
+                    "Function `{}` now calls `{} {}` instead of `{} {}`.",
+                    name, new_fn.method, new_fn.path, old_fn.method, old_fn.path
+                ),
+            });
+        }
+        diff_fields(
+            &mut changes,
+            &format!("Function `{}`", name),
+            "argument",
+            &old_fn.args,
+            &new_fn.args,
+            true,
+        );
+        if old_fn.returns != new_fn.returns {
+            changes.push(Change {
+                kind: ChangeKind::Changed,
+                breaking: true,
+                description: format!(
+                    "Function `{}` now returns `{}` instead of `{}`.",
+                    name, new_fn.returns, old_fn.returns
+                ),
+            });
+        }
+    }
+    for name in old.functions.keys() {
+        if !new.functions.contains_key(name) {
+            changes.push(Change {
+                kind: ChangeKind::Removed,
+                breaking: true,
+                description: format!("Function `{}`.", name),
+            });
+        }
+    }
+
+    for (name, new_type) in &new.types {
+        let Some(old_type) = old.types.get(name) else {
+            changes.push(Change {
+                kind: ChangeKind::Added,
+                breaking: false,
+                description: format!("Type `{}`.", name),
+            });
+            continue;
+        };
+
+        let subject = format!("Type `{}`", name);
+        match (old_type, new_type) {
+            (TypeShape::Struct { fields: old }, TypeShape::Struct { fields: new }) => {
+                diff_fields(&mut changes, &subject, "field", old, new, false);
+            }
+            (TypeShape::Enum { variants: old }, TypeShape::Enum { variants: new }) => {
+                for variant in new.iter().filter(|v| !old.contains(v)) {
+                    // Adding a variant breaks exhaustive matches.
+                    changes.push(Change {
+                        kind: ChangeKind::Changed,
+                        breaking: true,
+                        description: format!("{} has a new variant `{}`.", subject, variant),
+                    });
+                }
+                for variant in old.iter().filter(|v| !new.contains(v)) {
+                    changes.push(Change {
+                        kind: ChangeKind::Changed,
+                        breaking: true,
+                        description: format!(
+                            "{} no longer has the variant `{}`.",
+                            subject, variant
+                        ),
+                    });
+                }
+            }
+            (old, new) if old != new => {
+                changes.push(Change {
+                    kind: ChangeKind::Changed,
+                    breaking: true,
+                    description: format!(
+                        "{} changed from {} to {}.",
+                        subject,
+                        old.describe(),
+                        new.describe()
+                    ),
+                });
+            }
+            _ => {}
+        }
+    }
+    for name in old.types.keys() {
+        if !new.types.contains_key(name) {
+            changes.push(Change {
+                kind: ChangeKind::Removed,
+                breaking: true,
+                description: format!("Type `{}`.", name),
+            });
+        }
+    }
+
+    changes
+}
+
+/// Describe the changes between two lists of fields or arguments.
+fn diff_fields(
+    changes: &mut Vec<Change>,
+    subject: &str,
+    what: &str,
+    old: &[Field],
+    new: &[Field],
+    is_args: bool,
+) {
+    for field in new {
+        match old.iter().find(|f| f.name == field.name) {
+            None => changes.push(Change {
+                kind: ChangeKind::Changed,
+                // New arguments always break callers, new fields only break
+                // struct literals when they are required.
+                breaking: is_args || !field.type_.starts_with("Option<"),
+                description: format!(
+                    "{} has a new {} `{}: {}`.",
+                    subject, what, field.name, field.type_
+                ),
+            }),
+            Some(old_field) if old_field.type_ != field.type_ => changes.push(Change {
+                kind: ChangeKind::Changed,
+                breaking: true,
+                description: format!(
+                    "{} {} `{}` changed type from `{}` to `{}`.",
+                    subject, what, field.name, old_field.type_, field.type_
+                ),
+            }),
+            Some(_) => {}
+        }
+    }
+    for field in old {
+        if !new.iter().any(|f| f.name == field.name) {
+            changes.push(Change {
+                kind: ChangeKind::Changed,
+                breaking: true,
+                description: format!("{} no longer has the {} `{}`.", subject, what, field.name),
+            });
+        }
+    }
+}
+
+impl TypeShape {
+    fn describe(&self) -> &'static str {
+        match self {
+            TypeShape::Struct { .. } => "a struct",
+            TypeShape::Tuple { .. } => "a tuple struct",
+            TypeShape::Enum { .. } => "an enum",
+            TypeShape::Alias { .. } => "a type alias",
+        }
+    }
+}
+
+/// Render a changelog section for the changes between two manifests.
+pub fn render_changelog(old: &Manifest, new: &Manifest, changes: &[Change]) -> String {
+    let mut out = format!("## {} (from {})\n", new.version, old.version);
+
+    if changes.is_empty() {
+        out.push_str("\nNo changes to the public API.\n");
+        return out;
+    }
+
+    let breaking = changes.iter().filter(|c| c.breaking).count();
+    if breaking > 0 {
+        out.push_str(&format!(
+            "\nThis release has {} breaking change{}.\n",
+            breaking,
+            if breaking == 1 { "" } else { "s" }
+        ));
+    }
+
+    for (kind, title) in [
+        (ChangeKind::Added, "Added"),
+        (ChangeKind::Changed, "Changed"),
+        (ChangeKind::Removed, "Removed"),
+    ] {
+        let entries: Vec<&Change> = changes.iter().filter(|c| c.kind == kind).collect();
+        if entries.is_empty() {
+            continue;
+        }
+        out.push_str(&format!("\n### {}\n\n", title));
+        for change in entries {
+            if change.breaking {
+                out.push_str(&format!("- **BREAKING:** {}\n", change.description));
+            } else {
+                out.push_str(&format!("- {}\n", change.description));
+            }
+        }
+    }
+
+    out
+}
+
+/// Add a changelog section to the top of the changelog file, creating it if needed.
+pub fn write_changelog<P>(p: P, section: &str) -> Result<()>
+where
+    P: AsRef<std::path::Path>,
+{
+    const HEADER: &str = "# Changelog\n";
+
+    let p = p.as_ref();
+    let existing = if p.exists() {
+        std::fs::read_to_string(p)?
+    } else {
+        String::new()
+    };
+    let rest = existing
+        .strip_prefix(HEADER)
+        .unwrap_or(&existing)
+        .trim_start();
+
+    let mut out = format!("{}\n{}", HEADER, section);
+    if !rest.is_empty() {
+        out.push('\n');
+        out.push_str(rest);
+    }
+    crate::save(p, &out)
+}
+
+/// Find the top level functions in a token stream and return their name and signature,
+/// without the method and the path.
+fn parse_functions(tokens: TokenStream) -> Vec<(String, FunctionSignature)> {
+    let tokens: Vec<TokenTree> = tokens.into_iter().collect();
+    let mut functions = Vec::new();
+
+    let mut i = 0;
+    while i < tokens.len() {
+        if !is_ident(&tokens[i], "fn") {
+            i += 1;
+            continue;
+        }
+        let Some(TokenTree::Ident(name)) = tokens.get(i + 1) else {
+            i += 1;
+            continue;
+        };
+
+        // Skip any generics to get to the arguments.
+        let mut j = i + 2;
+        while j < tokens.len() && !is_group(&tokens[j], Delimiter::Parenthesis) {
+            j += 1;
+        }
+        let Some(TokenTree::Group(args)) = tokens.get(j) else {
+            break;
+        };
+
+        let args = split_top_level(args.stream())
+            .into_iter()
+            .filter(|arg| !arg.iter().any(|t| is_ident(t, "self")))
+            .filter_map(|arg| parse_field(&arg))
+            .collect();
+
+        // Everything up to the body is the return type.
+        let mut returns = Vec::new();
+        let mut k = j + 1;
+        while k < tokens.len() && !is_group(&tokens[k], Delimiter::Brace) {
+            returns.push(tokens[k].clone());
+            k += 1;
+        }
+        let returns = tokens_to_string(
+            returns
+                .into_iter()
+                .skip_while(|t| is_punct(t, '-') || is_punct(t, '>')),
+        );
+
+        // The qualifiers and the attributes are before the `fn`.
+        let mut is_async = false;
+        let mut cfgs = Vec::new();
+        let mut b = i;
+        while b > 0 {
+            match &tokens[b - 1] {
+                TokenTree::Ident(ident) if ident == "async" => is_async = true,
+                TokenTree::Ident(ident) if ident == "pub" => {}
+                TokenTree::Group(g) if g.delimiter() == Delimiter::Parenthesis => {}
+                TokenTree::Group(g)
+                    if g.delimiter() == Delimiter::Bracket
+                        && b >= 2
+                        && is_punct(&tokens[b - 2], '#') =>
+                {
+                    let attr = g.stream().into_iter().collect::<Vec<_>>();
+                    if let [cfg, TokenTree::Group(predicate)] = attr.as_slice() {
+                        if is_ident(cfg, "cfg") {
+                            cfgs.insert(0, tokens_to_string(predicate.stream()));
+                        }
+                    }
+                    b -= 1;
+                }
+                _ => break,
+            }
+            b -= 1;
+        }
+
+        functions.push((
+            name.to_string(),
+            FunctionSignature {
+                args,
+                returns,
+                is_async,
+                cfgs,
+                ..Default::default()
+            },
+        ));
+        i = k;
+    }
+
+    functions
+}
+
+/// Find the top level types in a token stream.
+fn parse_types(tokens: TokenStream) -> BTreeMap<String, TypeShape> {
+    let tokens: Vec<TokenTree> = tokens.into_iter().collect();
+    let mut types = BTreeMap::new();
+
+    for (i, token) in tokens.iter().enumerate() {
+        let Some(TokenTree::Ident(name)) = tokens.get(i + 1) else {
+            continue;
+        };
+
+        if is_ident(token, "struct") {
+            let shape = match tokens.get(i + 2) {
+                Some(TokenTree::Group(g)) if g.delimiter() == Delimiter::Brace => {
+                    TypeShape::Struct {
+                        fields: split_top_level(g.stream())
+                            .into_iter()
+                            .filter_map(|f| parse_field(&f))
+                            .collect(),
+                    }
+                }
+                Some(TokenTree::Group(g)) if g.delimiter() == Delimiter::Parenthesis => {
+                    TypeShape::Tuple {
+                        fields: split_top_level(g.stream())
+                            .into_iter()
+                            .map(|f| tokens_to_string(strip_visibility(strip_attributes(f))))
+                            .collect(),
+                    }
+                }
+                _ => TypeShape::Struct { fields: vec![] },
+            };
+            types.insert(name.to_string(), shape);
+        } else if is_ident(token, "enum") {
+            if let Some(TokenTree::Group(g)) = tokens.get(i + 2) {
+                types.insert(
+                    name.to_string(),
+                    TypeShape::Enum {
+                        variants: split_top_level(g.stream())
+                            .into_iter()
+                            .map(|v| tokens_to_string(strip_attributes(v)))
+                            .collect(),
+                    },
+                );
+            }
+        } else if is_ident(token, "type") && tokens.get(i + 2).is_some_and(|t| is_punct(t, '=')) {
+            let target = tokens[i + 3..]
+                .iter()
+                .take_while(|t| !is_punct(t, ';'))
+                .cloned()
+                .collect::<Vec<_>>();
+            types.insert(
+                name.to_string(),
+                TypeShape::Alias {
+                    target: tokens_to_string(target),
+                },
+            );
+        }
+    }
+
+    types
+}
+
+/// Parse a `name: Type` field, ignoring any attributes and visibility.
+fn parse_field(tokens: &[TokenTree]) -> Option<Field> {
+    let tokens = strip_visibility(strip_attributes(tokens.to_vec()));
+    let Some(TokenTree::Ident(name)) = tokens.first() else {
+        return None;
+    };
+    if !is_punct(tokens.get(1)?, ':') {
+        return None;
+    }
+    Some(Field {
+        name: name.to_string(),
+        type_: tokens_to_string(tokens[2..].iter().cloned()),
+    })
+}
+
+/// Split a token stream on the commas that are not nested in generics.
+fn split_top_level(tokens: TokenStream) -> Vec<Vec<TokenTree>> {
+    let mut parts = vec![];
+    let mut current = vec![];
+    let mut depth = 0i32;
+    let mut prev_dash = false;
+    for token in tokens {
+        if let TokenTree::Punct(p) = &token {
+            match p.as_char() {
+                '<' => depth += 1,
+                // Don't count the arrow of a function type.
+                '>' if !prev_dash => depth -= 1,
+                ',' if depth == 0 => {
+                    parts.push(std::mem::take(&mut current));
+                    prev_dash = false;
+                    continue;
+                }
+                _ => {}
+            }
+            prev_dash = p.as_char() == '-';
+        } else {
+            prev_dash = false;
+        }
+        current.push(token);
+    }
+    if !current.is_empty() {
+        parts.push(current);
+    }
+    parts
+}
+
+/// Remove the leading `#[...]` attributes.
+fn strip_attributes(mut tokens: Vec<TokenTree>) -> Vec<TokenTree> {
+    while tokens.len() >= 2 && is_punct(&tokens[0], '#') && is_group(&tokens[1], Delimiter::Bracket)
+    {
+        tokens.drain(..2);
+    }
+    tokens
+}
+
+/// Remove a leading `pub` or `pub(...)`.
+fn strip_visibility(mut tokens: Vec<TokenTree>) -> Vec<TokenTree> {
+    if tokens.first().is_some_and(|t| is_ident(t, "pub")) {
+        tokens.remove(0);
+        if tokens
+            .first()
+            .is_some_and(|t| is_group(t, Delimiter::Parenthesis))
+        {
+            tokens.remove(0);
+        }
+    }
+    tokens
+}
+
+/// Render tokens as a compact Rust type, like `Option<Vec<crate::types::Foo>>`.
+fn tokens_to_string<I>(tokens: I) -> String
+where
+    I: IntoIterator<Item = TokenTree>,
+{
+    let raw = tokens.into_iter().collect::<TokenStream>().to_string();
+    let chars: Vec<char> = raw.chars().collect();
+    let is_word = |c: char| c.is_alphanumeric() || c == '_' || c == '\'';
+
+    let mut out = String::new();
+    for (i, c) in chars.iter().enumerate() {
+        if *c == ' ' {
+            // Only keep spaces that separate two words, like `dyn Trait` or `&'a str`.
+            let prev = out.chars().last();
+            let next = chars.get(i + 1);
+            if let (Some(prev), Some(next)) = (prev, next) {
+                if is_word(prev) && is_word(*next) {
+                    out.push(' ');
+                }
+            }
+            continue;
+        }
+        out.push(*c);
+        if *c == ',' {
+            out.push(' ');
+        }
+    }
+    out.trim().to_string()
+}
+
+fn is_ident(token: &TokenTree, s: &str) -> bool {
+    matches!(token, TokenTree::Ident(i) if i == s)
+}
+
+fn is_punct(token: &TokenTree, c: char) -> bool {
+    matches!(token, TokenTree::Punct(p) if p.as_char() == c)
+}
+
+fn is_group(token: &TokenTree, delimiter: Delimiter) -> bool {
+    matches!(token, TokenTree::Group(g) if g.delimiter() == delimiter)
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn manifest(spec: &str, version: &str) -> Manifest {
+        let opts = crate::Opts {
+            name: "changelog".to_string(),
+            target_version: version.to_string(),
+            ..Default::default()
+        };
+        build_manifest(&crate::load_json_spec(spec).unwrap(), &opts).unwrap()
+    }
+
+    #[test]
+    fn test_parse_types() {
+        let types = parse_types(quote! {
+            #[doc = "A thing."]
+            pub struct Thing {
+                #[doc = "The id."]
+                pub id: uuid::Uuid,
+                #[serde(default)]
+                pub tags: Option<Vec<String>>,
+            }
+
+            pub struct Wrapper(pub std::collections::HashMap<String, i64>);
+
+            pub enum Color {
+                #[serde(rename = "red")]
+                Red,
+                Other(String),
+            }
+
+            impl Thing {
+                pub fn inner(&self) -> u8 {
+                    struct NotAType;
+                    0
+                }
+            }
+        });
+
+        assert_eq!(
+            types,
+            BTreeMap::from([
+                (
+                    "Color".to_string(),
+                    TypeShape::Enum {
+                        variants: vec!["Red".to_string(), "Other(String)".to_string()]
+                    }
+                ),
+                (
+                    "Thing".to_string(),
+                    TypeShape::Struct {
+                        fields: vec![
+                            Field {
+                                name: "id".to_string(),
+                                type_: "uuid::Uuid".to_string()
+                            },
+                            Field {
+                                name: "tags".to_string(),
+                                type_: "Option<Vec<String>>".to_string()
+                            }
+                        ]
+                    }
+                ),
+                (
+                    "Wrapper".to_string(),
+                    TypeShape::Tuple {
+                        fields: vec!["std::collections::HashMap<String, i64>".to_string()]
+                    }
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_changelog_between_generations() {
+        let old = manifest(include_str!("../tests/changelog/before.json"), "0.1.0");
+        let new = manifest(include_str!("../tests/changelog/after.json"), "0.2.0");
+
+        let thing = new.functions.get("things::get").unwrap();
+        assert_eq!(thing.method, "GET");
+        assert_eq!(thing.path, "/things/{id}");
+
+        let changes = diff(&old, &new);
+        assert_eq!(
+            changes,
+            vec![
+                Change {
+                    kind: ChangeKind::Added,
+                    breaking: false,
+                    description: "Function `things::delete` for `DELETE /things/{id}`.".to_string(),
+                },
+                Change {
+                    kind: ChangeKind::Changed,
+                    breaking: true,
+                    description: "Type `Thing` field `size` changed type from `i64` to `f64`."
+                        .to_string(),
+                },
+            ]
+        );
+
+        // The manifest format is stable, so make sure it round trips.
+        let json = serde_json::to_string_pretty(&new).unwrap();
+        assert_eq!(serde_json::from_str::<Manifest>(&json).unwrap(), new);
+
+        let changelog = render_changelog(&old, &new, &changes);
+        assert_eq!(
+            changelog,
+            r#"## 0.2.0 (from 0.1.0)
+
+This release has 1 breaking change.
+
+### Added
+
+- Function `things::delete` for `DELETE /things/{id}`.
+
+### Changed
+
+- **BREAKING:** Type `Thing` field `size` changed type from `i64` to `f64`.
+"#
+        );
+    }
+}
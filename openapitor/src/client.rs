@@ -1,18 +1,45 @@
 //! Client templates for our generated library.
 
 /// Generate the base of the API client.
-pub fn generate_client(opts: &crate::Opts) -> String {
+pub fn generate_client(spec: &openapiv3::OpenAPI, opts: &crate::Opts) -> String {
+    let client = format!(
+        "{}{}{}",
+        REPLAY_CLIENT,
+        REQUEST_BUILDER,
+        generate_auth_client(spec, opts)
+    );
+    // The client can set the variables of the url of the server, see `servers`.
+    let client = crate::servers::add_to_client(&client, crate::servers::find(spec));
+    // The crates of the tags send their requests with the internals of the client.
+    if opts.split_by_tag {
+        crate::workspace::publish_client_internals(&client)
+    } else {
+        client
+    }
+}
+
+/// Generate the client for the authentication of the API.
+fn generate_auth_client(spec: &openapiv3::OpenAPI, opts: &crate::Opts) -> String {
+    // The environment variable code goes in last, so the names of the variables are
+    // never mistaken for one of the other placeholders.
     if let Some(token_endpoint) = &opts.token_endpoint {
         // Ensure we also have a user consent endpoint.
         if opts.user_consent_endpoint.is_none() {
             panic!("user_consent_endpoint is required if token_endpoint is provided");
         }
 
+        // The consent url takes the scopes of the spec, if it declares any.
+        let has_scopes = crate::types::scopes::get_oauth2_scopes(spec)
+            .map(|scopes| !scopes.is_empty())
+            .unwrap_or_default();
+        let user_consent_url = if has_scopes {
+            USER_CONSENT_URL_SCOPES
+        } else {
+            USER_CONSENT_URL
+        };
+
         return CLIENT_FUNCTIONS_OAUTH_TOKEN
-            .replace(
-                "ENV_VARIABLE_CODE",
-                &get_env_variable_code_oauth_token(opts),
-            )
+            .replace("USER_CONSENT_URL_FUNCTIONS", user_consent_url)
             .replace("TOKEN_ENDPOINT", token_endpoint.as_ref())
             .replace(
                 "USER_CONSENT_ENDPOINT",
@@ -22,7 +49,23 @@ pub fn generate_client(opts: &crate::Opts) -> String {
                 "TIMEOUT_NUM_SECONDS",
                 &opts.request_timeout_seconds.to_string(),
             )
-            .replace("BASE_URL", opts.base_url.to_string().trim_end_matches('/'));
+            .replace("RATE_LIMIT_HEADER_PREFIX", &opts.rate_limit_header_prefix)
+            .replace("BASE_URL", &opts.trimmed_base_url())
+            .replace(
+                "ENV_VARIABLE_DOCS",
+                &get_env_variable_docs(
+                    opts,
+                    &[
+                        ("The client id", "CLIENT_ID"),
+                        ("The client secret", "CLIENT_SECRET"),
+                        ("The redirect uri", "REDIRECT_URI"),
+                    ],
+                ),
+            )
+            .replace(
+                "ENV_VARIABLE_CODE",
+                &get_env_variable_code_oauth_token(opts),
+            );
     }
 
     if opts.basic_auth {
@@ -31,8 +74,16 @@ pub fn generate_client(opts: &crate::Opts) -> String {
                 "TIMEOUT_NUM_SECONDS",
                 &opts.request_timeout_seconds.to_string(),
             )
-            .replace("ENV_VARIABLE_CODE", &get_env_variable_code_basic_auth(opts))
-            .replace("BASE_URL", opts.base_url.to_string().trim_end_matches('/'));
+            .replace("RATE_LIMIT_HEADER_PREFIX", &opts.rate_limit_header_prefix)
+            .replace("BASE_URL", &opts.trimmed_base_url())
+            .replace(
+                "ENV_VARIABLE_DOCS",
+                &get_env_variable_docs(
+                    opts,
+                    &[("The username", "USERNAME"), ("The password", "PASSWORD")],
+                ),
+            )
+            .replace("ENV_VARIABLE_CODE", &get_env_variable_code_basic_auth(opts));
     }
 
     CLIENT_FUNCTIONS_TOKEN
@@ -40,50 +91,128 @@ pub fn generate_client(opts: &crate::Opts) -> String {
             "TIMEOUT_NUM_SECONDS",
             &opts.request_timeout_seconds.to_string(),
         )
+        .replace("RATE_LIMIT_HEADER_PREFIX", &opts.rate_limit_header_prefix)
+        .replace("BASE_URL", &opts.trimmed_base_url())
+        .replace(
+            "ENV_VARIABLE_DOCS",
+            &get_env_variable_docs(opts, &[("The API token", "API_TOKEN")]),
+        )
         .replace("ENV_VARIABLE_CODE", &get_env_variable_code_token(opts))
-        .replace("BASE_URL", opts.base_url.to_string().trim_end_matches('/'))
 }
 
-fn get_env_variable_code_basic_auth(opts: &crate::Opts) -> String {
-    let start = if let Some(add_env_prefix) = &opts.add_env_prefix {
-        r#"let username = if let Ok(username) = env::var("ENV_VARIABLE_PREFIX_USERNAME") {
-        username
-    } else if let Ok(username) = env::var("ADD_ENV_VARIABLE_PREFIX_USERNAME") {
-        username
-    } else {
-        panic!("must set ENV_VARIABLE_PREFIX_USERNAME or ADD_ENV_VARIABLE_PREFIX_USERNAME");
-    };
-    let password = if let Ok(password) = env::var("ENV_VARIABLE_PREFIX_PASSWORD") {
-        password
-    } else if let Ok(password) = env::var("ADD_ENV_VARIABLE_PREFIX_PASSWORD") {
-        password
-    } else {
-        panic!("must set ENV_VARIABLE_PREFIX_PASSWORD or ADD_ENV_VARIABLE_PREFIX_PASSWORD");
-    };
-    let base_url = if let Ok(base_url) = env::var("ENV_VARIABLE_PREFIX_HOST") {
-        base_url
-    } else if let Ok(base_url) = env::var("ADD_ENV_VARIABLE_PREFIX_HOST") {
-        base_url
-    } else {            
-        "BASE_URL".to_string()
-    };"#
-        .replace(
-            "ADD_ENV_VARIABLE_PREFIX",
-            &crate::template::get_env_variable_prefix(add_env_prefix),
-        )
-        .replace(
-            "ENV_VARIABLE_PREFIX",
-            &crate::template::get_env_variable_prefix(&opts.name),
-        )
-    } else {
-        r#"let username = env::var("ENV_VARIABLE_PREFIX_USERNAME").expect("must set ENV_VARIABLE_PREFIX_USERNAME");
-        let password = env::var("ENV_VARIABLE_PREFIX_PASSWORD").expect("must set ENV_VARIABLE_PREFIX_PASSWORD");
-        let base_url = env::var("ENV_VARIABLE_PREFIX_HOST").unwrap_or("BASE_URL".to_string());"#.replace("ENV_VARIABLE_PREFIX", &crate::template::get_env_variable_prefix(&opts.name))
-    };
+/// A client serving recorded API calls, shared by all the client templates.
+const REPLAY_CLIENT: &str = r#"
+/// A client serving the responses recorded with `Client::set_recorder` instead of
+/// calling the API, for tests that run offline. It is used like the `Client` it
+/// wraps, a call that was not recorded fails.
+#[derive(Clone, Debug)]
+#[cfg(feature = "record")]
+pub struct ReplayClient(Client);
+
+#[cfg(feature = "record")]
+impl ReplayClient {
+    /// Replay the calls recorded in the file at `path` with `client`.
+    pub fn new<P: AsRef<std::path::Path>>(mut client: Client, path: P) -> std::io::Result<Self> {
+        client.record = crate::types::record::Mode::Replay(std::sync::Arc::new(
+            crate::types::record::Replay::open(path)?,
+        ));
+        Ok(ReplayClient(client))
+    }
+}
+
+#[cfg(feature = "record")]
+impl std::ops::Deref for ReplayClient {
+    type Target = Client;
+
+    fn deref(&self) -> &Client {
+        &self.0
+    }
+}
+"#;
+
+/// The raw requests and their errors, the same with or without the middleware of
+/// the `retry` feature, shared by all the client templates.
+const REQUEST_BUILDER: &str = r#"
+/// A request to the API, from `Client::request_raw`. It is sent through the
+/// middleware of the client.
+#[cfg(feature = "requests")]
+pub struct RequestBuilder(
+    #[cfg(feature = "retry")] reqwest_middleware::RequestBuilder,
+    #[cfg(not(feature = "retry"))] reqwest::RequestBuilder,
+);
+
+#[cfg(feature = "requests")]
+impl RequestBuilder {
+    /// Add a header to the request.
+    pub fn header<K, V>(self, key: K, value: V) -> Self
+    where
+        reqwest::header::HeaderName: TryFrom<K>,
+        <reqwest::header::HeaderName as TryFrom<K>>::Error: Into<http::Error>,
+        reqwest::header::HeaderValue: TryFrom<V>,
+        <reqwest::header::HeaderValue as TryFrom<V>>::Error: Into<http::Error>,
+    {
+        RequestBuilder(self.0.header(key, value))
+    }
+
+    /// Add headers to the request.
+    pub fn headers(self, headers: reqwest::header::HeaderMap) -> Self {
+        RequestBuilder(self.0.headers(headers))
+    }
+
+    /// Add the parameters of `query` to the query string of the request.
+    pub fn query<T: serde::Serialize + ?Sized>(self, query: &T) -> Self {
+        RequestBuilder(self.0.query(query))
+    }
+
+    /// Send `json` as the body of the request.
+    pub fn json<T: serde::Serialize + ?Sized>(self, json: &T) -> Self {
+        RequestBuilder(self.0.json(json))
+    }
 
+    /// Set the body of the request.
+    pub fn body<T: Into<reqwest::Body>>(self, body: T) -> Self {
+        RequestBuilder(self.0.body(body))
+    }
+
+    /// Send the request. Responses with an error status are returned as well.
+    pub async fn send(
+        self,
+    ) -> Result<crate::types::error::ApiResponse, crate::types::error::Error> {
+        let response = self.0.send().await.map_err(Client::send_error)?;
+        Ok(crate::types::error::ApiResponse::new(response))
+    }
+}
+
+#[cfg(feature = "requests")]
+impl Client {
+    /// The error of sending a request through the middleware.
+    #[cfg(feature = "retry")]
+    pub(crate) fn send_error(e: reqwest_middleware::Error) -> crate::types::error::Error {
+        match e {
+            reqwest_middleware::Error::Reqwest(e) => {
+                crate::types::error::Error::CommunicationError(e.into())
+            }
+            reqwest_middleware::Error::Middleware(e) => {
+                crate::types::error::Error::CommunicationError(
+                    crate::types::error::TransportError::from_middleware(e),
+                )
+            }
+        }
+    }
+
+    /// The error of sending a request.
+    #[cfg(not(feature = "retry"))]
+    pub(crate) fn send_error(e: reqwest::Error) -> crate::types::error::Error {
+        e.into()
+    }
+}
+"#;
+
+fn get_env_variable_code_basic_auth(opts: &crate::Opts) -> String {
     format!(
         r#"{}
-
+    {}
+    {}
 
     let mut c = Client::new(
         username,
@@ -92,7 +221,17 @@ fn get_env_variable_code_basic_auth(opts: &crate::Opts) -> String {
     c.set_base_url(base_url);
     c
     "#,
-        start
+        get_env_variable_lookup(
+            "username",
+            "the username",
+            &get_env_variable_names(opts, "USERNAME")
+        ),
+        get_env_variable_lookup(
+            "password",
+            "the password",
+            &get_env_variable_names(opts, "PASSWORD")
+        ),
+        get_base_url_lookup(opts),
     )
 }
 
@@ -115,7 +254,24 @@ pub struct Client {
     username: String,
     password: String,
     base_url: String,
-
+    cookies: std::collections::BTreeMap<String, String>,
+    rate_limit: std::sync::Arc<std::sync::Mutex<Option<crate::types::rate_limit::RateLimit>>>,
+    request_id: std::sync::Arc<std::sync::Mutex<Option<String>>>,
+    #[cfg(feature = "compression")]
+    compression: crate::types::compression::CompressionMode,
+    call_options: crate::types::call_options::CallOptions,
+    response_cache: Option<std::sync::Arc<dyn crate::types::cache::ResponseCache>>,
+    #[cfg(feature = "metrics")]
+    metrics: std::sync::Arc<dyn crate::types::metrics::ApiMetrics>,
+    #[cfg(feature = "record")]
+    record: crate::types::record::Mode,
+    max_pages: usize,
+    deprecation_warnings: bool,
+    SERVER_VARIABLES_FIELD
+
+    retry_config: crate::types::retry::RetryConfig,
+    #[cfg(feature = "retry")]
+    reqwest_client: reqwest::Client,
     #[cfg(feature = "retry")]
     client: reqwest_middleware::ClientWithMiddleware,
     #[cfg(not(feature = "retry"))]
@@ -142,26 +298,32 @@ impl Client {
             .build();
         #[cfg(feature = "retry")]
         {
-            // Retry up to 3 times with increasing intervals between attempts.
-            let retry_policy =
-                reqwest_retry::policies::ExponentialBackoff::builder().build_with_max_retries(3);
+            let retry_config = crate::types::retry::RetryConfig::default();
             match client {
                 Ok(c) => {
-                    let client = reqwest_middleware::ClientBuilder::new(c)
-                        // Trace HTTP requests. See the tracing crate to make use of these traces.
-                        .with(reqwest_tracing::TracingMiddleware::default())
-                        // Retry failed requests.
-                        .with(reqwest_conditional_middleware::ConditionalMiddleware::new(
-                            reqwest_retry::RetryTransientMiddleware::new_with_policy(retry_policy),
-                            |req: &reqwest::Request| req.try_clone().is_some(),
-                        ))
-                        .build();
+                    let client = retry_config.middleware(c.clone());
 
                     Client {
                         username: username.to_string(),
                         password: password.to_string(),
                         base_url: "BASE_URL".to_string(),
-
+                        cookies: Default::default(),
+                        rate_limit: Default::default(),
+                        request_id: Default::default(),
+                        #[cfg(feature = "compression")]
+                        compression: Default::default(),
+                        call_options: Default::default(),
+                        response_cache: None,
+                        #[cfg(feature = "metrics")]
+                        metrics: std::sync::Arc::new(crate::types::metrics::NoopMetrics),
+                        #[cfg(feature = "record")]
+                        record: Default::default(),
+                        max_pages: crate::types::paginate::DEFAULT_MAX_PAGES,
+                        deprecation_warnings: true,
+                        SERVER_VARIABLES_DEFAULT
+
+                        retry_config,
+                        reqwest_client: c,
                         client,
                     }
                 }
@@ -174,7 +336,22 @@ impl Client {
                 username: username.to_string(),
                 password: password.to_string(),
                 base_url: "BASE_URL".to_string(),
-
+                cookies: Default::default(),
+                rate_limit: Default::default(),
+                request_id: Default::default(),
+                #[cfg(feature = "compression")]
+                compression: Default::default(),
+                call_options: Default::default(),
+                response_cache: None,
+                #[cfg(feature = "metrics")]
+                metrics: std::sync::Arc::new(crate::types::metrics::NoopMetrics),
+                #[cfg(feature = "record")]
+                record: Default::default(),
+                max_pages: crate::types::paginate::DEFAULT_MAX_PAGES,
+                deprecation_warnings: true,
+                SERVER_VARIABLES_DEFAULT
+
+                retry_config: Default::default(),
                 client,
             }
         }
@@ -189,8 +366,209 @@ impl Client {
         self.base_url = base_url.to_string().trim_end_matches('/').to_string();
     }
 
-    /// Create a new Client struct from the environment variable: `ENV_VARIABLE_PREFIX_USERNAME`
-    /// and `ENV_VARIABLE_PREFIX_PASSWORD`.
+    /// Set the maximum number of pages a paginated stream fetches, the stream fails
+    /// with `Error::TooManyPages` when the server has more. This guards against
+    /// servers that hand out page tokens in a loop, the default is 10 000 pages.
+    pub fn set_max_pages(&mut self, max_pages: usize) {
+        self.max_pages = max_pages;
+    }
+
+    /// Set if the client warns when the API says an operation is deprecated, with
+    /// the `Deprecation` or `Sunset` header of a response. It warns once for every
+    /// operation, the default is to warn.
+    pub fn set_deprecation_warnings(&mut self, enabled: bool) {
+        self.deprecation_warnings = enabled;
+    }
+
+    /// Set how the client retries requests that failed with a transient error, see
+    /// `RetryConfig`. The default retries 3 times, without the `retry` feature
+    /// requests are sent once.
+    pub fn set_retry_config(&mut self, retry_config: crate::types::retry::RetryConfig) {
+        #[cfg(feature = "retry")]
+        {
+            self.client = retry_config.middleware(self.reqwest_client.clone());
+        }
+        self.retry_config = retry_config;
+    }
+
+    /// How the client retries requests that failed with a transient error.
+    pub fn retry_config(&self) -> crate::types::retry::RetryConfig {
+        self.retry_config
+    }
+
+    /// Set how the client compresses the JSON bodies of its requests, see
+    /// `CompressionMode`. The default sends them as they are.
+    #[cfg(feature = "compression")]
+    pub fn set_request_compression(
+        &mut self,
+        compression: crate::types::compression::CompressionMode,
+    ) {
+        self.compression = compression;
+    }
+
+    /// A copy of this client whose calls change their headers as `call_options`
+    /// say, for proxies that want other headers than the API. The copy shares the
+    /// connections and everything else with this client, and this client keeps
+    /// its headers.
+    pub fn with_call_options(&self, call_options: crate::types::call_options::CallOptions) -> Self {
+        Client {
+            call_options,
+            ..self.clone()
+        }
+    }
+
+    /// Set the cache of the responses to `GET` requests, see `ResponseCache`. The
+    /// client answers a `GET` from the cache while the response the server sent is
+    /// fresh, as the `max-age` of its `Cache-Control` header says. The clones of
+    /// this client share the cache. The default caches nothing.
+    pub fn set_response_cache(
+        &mut self,
+        cache: std::sync::Arc<dyn crate::types::cache::ResponseCache>,
+    ) {
+        self.response_cache = Some(cache);
+    }
+
+    /// Set the metrics sink used to record every API call made by the client.
+    #[cfg(feature = "metrics")]
+    pub fn set_metrics(&mut self, metrics: std::sync::Arc<dyn crate::types::metrics::ApiMetrics>) {
+        self.metrics = metrics;
+    }
+
+    /// Append every API call made by the client to the file at `path`, one JSON
+    /// object per line, so it can be replayed with a `ReplayClient`.
+    #[cfg(feature = "record")]
+    pub fn set_recorder<P: AsRef<std::path::Path>>(&mut self, path: P) -> std::io::Result<()> {
+        self.record = crate::types::record::Mode::Record(std::sync::Arc::new(
+            crate::types::record::Recorder::open(path)?,
+        ));
+        Ok(())
+    }
+
+    /// Send the request of an API call, every generated function sends through here.
+    pub(crate) async fn execute(
+        &self,
+        operation_id: &str,
+        request: reqwest::Request,
+    ) -> Result<reqwest::Response, crate::types::error::Error> {
+        let request = self.call_options.apply(request);
+
+        // Answer a `GET` from the cache while the response is fresh.
+        let cache = match (&self.response_cache, crate::types::cache::key(&request)) {
+            (Some(cache), Some(key)) => Some((cache, key)),
+            _ => None,
+        };
+        if let Some((cache, key)) = &cache {
+            if let Some(response) = crate::types::cache::get(cache.as_ref(), key).await {
+                return Ok(response);
+            }
+        }
+
+        // Time the calls that reach the API, with their retries.
+        #[cfg(feature = "metrics")]
+        let call = crate::types::metrics::ApiCall::start(operation_id, request.method());
+
+        // Compress what we send, not what we record, so a recording replays
+        // whatever the compression.
+        let send = |request: reqwest::Request| {
+            #[cfg(feature = "compression")]
+            let request = self.compression.compress(request);
+            let response = self.client.execute(request);
+            async move { response.await.map_err(Self::send_error) }
+        };
+        #[cfg(feature = "record")]
+        let response = self.record.execute(operation_id, &self.base_url, request, send).await;
+        #[cfg(not(feature = "record"))]
+        let response = send(request).await;
+        #[cfg(feature = "metrics")]
+        self.metrics.record(&call.finish(&response));
+        let response = response?;
+
+        // Warn once that the API deprecated the operation, if it says so.
+        if self.deprecation_warnings {
+            crate::types::deprecation::warn_once(operation_id, response.headers());
+        }
+
+        match cache {
+            Some((cache, key)) => crate::types::cache::put(cache.as_ref(), &key, response).await,
+            None => Ok(response),
+        }
+    }
+
+    /// Set a cookie that is sent with every request, for example a session cookie.
+    /// Cookie parameters of an operation take precedence over the cookies set here.
+    ///
+    /// In the browser the `Cookie` header is managed by the browser, so this has no
+    /// effect on wasm.
+    pub fn set_cookie<N, V>(&mut self, name: N, value: V)
+    where
+        N: ToString,
+        V: ToString,
+    {
+        self.cookies.insert(name.to_string(), value.to_string());
+    }
+
+    /// Build the `Cookie` header from the cookies set on the client and the cookie
+    /// parameters of a request. A parameter can be sent more than once, as the
+    /// exploded arrays are.
+    pub(crate) fn cookie_header(&self, cookies: &[(&str, String)]) -> Option<String> {
+        let mut all: std::collections::BTreeMap<&str, Vec<&str>> = self
+            .cookies
+            .iter()
+            .map(|(name, value)| (name.as_str(), vec![value.as_str()]))
+            .collect();
+        let mut params: std::collections::BTreeMap<&str, Vec<&str>> = Default::default();
+        for (name, value) in cookies {
+            params.entry(name).or_default().push(value);
+        }
+        all.extend(params);
+        if all.is_empty() {
+            return None;
+        }
+
+        Some(
+            all.iter()
+                .flat_map(|(name, values)| {
+                    values.iter().map(move |value| format!("{}={}", name, value))
+                })
+                .collect::<Vec<_>>()
+                .join("; "),
+        )
+    }
+
+    /// The rate limit reported by the last response, if the API sent one.
+    /// This is shared by all the clones of this client.
+    pub fn last_rate_limit(&self) -> Option<crate::types::rate_limit::RateLimit> {
+        self.rate_limit.lock().ok().and_then(|rate_limit| *rate_limit)
+    }
+
+    /// The id the server gave the last request, if the API sent one. Support asks
+    /// for it. This is shared by all the clones of this client.
+    pub fn last_request_id(&self) -> Option<String> {
+        self.request_id.lock().ok().and_then(|request_id| request_id.clone())
+    }
+
+    /// Remember the rate limit and the request id reported by a response,
+    /// responses without a rate limit keep the last one. Returns the request id.
+    pub(crate) fn record_response(&self, headers: &reqwest::header::HeaderMap) -> Option<String> {
+        if let Some(rate_limit) =
+            crate::types::rate_limit::RateLimit::from_headers(headers, "RATE_LIMIT_HEADER_PREFIX")
+        {
+            if let Ok(mut last) = self.rate_limit.lock() {
+                *last = Some(rate_limit);
+            }
+        }
+
+        let request_id = headers
+            .get(crate::types::error::REQUEST_ID_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        if let Ok(mut last) = self.request_id.lock() {
+            last.clone_from(&request_id);
+        }
+        request_id
+    }
+
+    ENV_VARIABLE_DOCS
     #[tracing::instrument]
     pub fn new_from_env() -> Self
     {
@@ -204,7 +582,7 @@ impl Client {
         method: reqwest::Method,
         uri: &str,
         body: Option<reqwest::Body>,
-    ) -> anyhow::Result<reqwest_middleware::RequestBuilder>
+    ) -> anyhow::Result<RequestBuilder>
     {
         let u = if uri.starts_with("https://") || uri.starts_with("http://") {
             uri.to_string()
@@ -225,52 +603,25 @@ impl Client {
             reqwest::header::ACCEPT,
             reqwest::header::HeaderValue::from_static("application/json"),
         );
-        req = req.header(
-            reqwest::header::CONTENT_TYPE,
-            reqwest::header::HeaderValue::from_static("application/json"),
-        );
 
+        // Only describe the body if there is one, some gateways reject requests
+        // with a content type and no body.
         if let Some(body) = body {
+            req = req.header(
+                reqwest::header::CONTENT_TYPE,
+                reqwest::header::HeaderValue::from_static("application/json"),
+            );
             req = req.body(body);
         }
 
-        Ok(req)
+        Ok(RequestBuilder(req))
     }
 "#;
 
 fn get_env_variable_code_token(opts: &crate::Opts) -> String {
-    let start = if let Some(add_env_prefix) = &opts.add_env_prefix {
-        r#"let token = if let Ok(token) = env::var("ENV_VARIABLE_PREFIX_API_TOKEN") {
-        token
-    } else if let Ok(token) = env::var("ADD_ENV_VARIABLE_PREFIX_API_TOKEN") {
-        token
-    } else {
-        panic!("must set ENV_VARIABLE_PREFIX_API_TOKEN or ADD_ENV_VARIABLE_PREFIX_API_TOKEN");
-    };
-    let base_url = if let Ok(base_url) = env::var("ENV_VARIABLE_PREFIX_HOST") {
-        base_url
-    } else if let Ok(base_url) = env::var("ADD_ENV_VARIABLE_PREFIX_HOST") {
-        base_url
-    } else {
-        "BASE_URL".to_string()
-    };"#
-        .replace(
-            "ADD_ENV_VARIABLE_PREFIX",
-            &crate::template::get_env_variable_prefix(add_env_prefix),
-        )
-        .replace(
-            "ENV_VARIABLE_PREFIX",
-            &crate::template::get_env_variable_prefix(&opts.name),
-        )
-    } else {
-        r#"let token = env::var("ENV_VARIABLE_PREFIX_API_TOKEN").expect("must set ENV_VARIABLE_PREFIX_API_TOKEN");
-        let base_url = env::var("ENV_VARIABLE_PREFIX_HOST").unwrap_or("BASE_URL".to_string());
-        "#.replace("ENV_VARIABLE_PREFIX", &crate::template::get_env_variable_prefix(&opts.name))
-    };
-
     format!(
         r#"{}
-
+    {}
 
     let mut c = Client::new(
         token,
@@ -278,7 +629,12 @@ fn get_env_variable_code_token(opts: &crate::Opts) -> String {
     c.set_base_url(base_url);
     c
     "#,
-        start
+        get_env_variable_lookup(
+            "token",
+            "the API token",
+            &get_env_variable_names(opts, "API_TOKEN")
+        ),
+        get_base_url_lookup(opts),
     )
 }
 
@@ -300,30 +656,40 @@ static APP_USER_AGENT: &str = concat!(
 pub struct Client {
     token: String,
     base_url: String,
-
+    cookies: std::collections::BTreeMap<String, String>,
+    rate_limit: std::sync::Arc<std::sync::Mutex<Option<crate::types::rate_limit::RateLimit>>>,
+    request_id: std::sync::Arc<std::sync::Mutex<Option<String>>>,
+    #[cfg(feature = "compression")]
+    compression: crate::types::compression::CompressionMode,
+    call_options: crate::types::call_options::CallOptions,
+    response_cache: Option<std::sync::Arc<dyn crate::types::cache::ResponseCache>>,
+    #[cfg(feature = "metrics")]
+    metrics: std::sync::Arc<dyn crate::types::metrics::ApiMetrics>,
+    #[cfg(feature = "record")]
+    record: crate::types::record::Mode,
+    max_pages: usize,
+    deprecation_warnings: bool,
+    SERVER_VARIABLES_FIELD
+
+    retry_config: crate::types::retry::RetryConfig,
+    #[cfg(feature = "retry")]
+    reqwest_client: reqwest::Client,
+    #[cfg(feature = "retry")]
+    #[cfg(not(target_arch = "wasm32"))]
+    reqwest_client_http1_only: reqwest::Client,
     #[cfg(feature = "retry")]
     client: reqwest_middleware::ClientWithMiddleware,
     #[cfg(feature = "retry")]
     #[cfg(not(target_arch = "wasm32"))]
-    #[allow(dead_code)]
     client_http1_only: reqwest_middleware::ClientWithMiddleware,
 
     #[cfg(not(feature = "retry"))]
     client: reqwest::Client,
     #[cfg(not(feature = "retry"))]
     #[cfg(not(target_arch = "wasm32"))]
-    #[allow(dead_code)]
     client_http1_only: reqwest::Client,
 }
 
-/// A request builder.
-#[cfg(feature = "retry")]
-#[cfg(feature = "requests")]
-pub struct RequestBuilder(pub reqwest_middleware::RequestBuilder);
-#[cfg(not(feature = "retry"))]
-#[cfg(feature = "requests")]
-pub struct RequestBuilder(pub reqwest::RequestBuilder);
-
 #[cfg(feature = "requests")]
 impl Client {
     /// Create a new Client struct. It takes a type that can convert into
@@ -342,31 +708,32 @@ impl Client {
     {
         #[cfg(feature = "retry")]
         {
-            // Retry up to 3 times with increasing intervals between attempts.
-            let retry_policy =
-                reqwest_retry::policies::ExponentialBackoff::builder().build_with_max_retries(3);
+            let retry_config = crate::types::retry::RetryConfig::default();
             match (builder_http.build(), builder_websocket.build()) {
                 (Ok(c), Ok(c1)) => {
-                    let client = reqwest_middleware::ClientBuilder::new(c)
-                        // Trace HTTP requests. See the tracing crate to make use of these traces.
-                        .with(reqwest_tracing::TracingMiddleware::default())
-                        // Retry failed requests.
-                        .with(reqwest_conditional_middleware::ConditionalMiddleware::new(
-                            reqwest_retry::RetryTransientMiddleware::new_with_policy(retry_policy),
-                            |req: &reqwest::Request| req.try_clone().is_some(),
-                        ))
-                        .build();
-                    let client_http1_only = reqwest_middleware::ClientBuilder::new(c1)
-                        .with(reqwest_tracing::TracingMiddleware::default())
-                        .with(reqwest_conditional_middleware::ConditionalMiddleware::new(
-                            reqwest_retry::RetryTransientMiddleware::new_with_policy(retry_policy),
-                            |req: &reqwest::Request| req.try_clone().is_some(),
-                        ))
-                        .build();
+                    let client = retry_config.middleware(c.clone());
+                    let client_http1_only = retry_config.middleware(c1.clone());
                     Client {
                         token: token.to_string(),
                         base_url: "BASE_URL".to_string(),
-
+                        cookies: Default::default(),
+                        rate_limit: Default::default(),
+                        request_id: Default::default(),
+                        #[cfg(feature = "compression")]
+                        compression: Default::default(),
+                        call_options: Default::default(),
+                        response_cache: None,
+                        #[cfg(feature = "metrics")]
+                        metrics: std::sync::Arc::new(crate::types::metrics::NoopMetrics),
+                        #[cfg(feature = "record")]
+                        record: Default::default(),
+                        max_pages: crate::types::paginate::DEFAULT_MAX_PAGES,
+                        deprecation_warnings: true,
+                        SERVER_VARIABLES_DEFAULT
+
+                        retry_config,
+                        reqwest_client: c,
+                        reqwest_client_http1_only: c1,
                         client,
                         client_http1_only,
                     }
@@ -380,7 +747,22 @@ impl Client {
                 (Ok(c), Ok(c1)) => Client {
                     token: token.to_string(),
                     base_url: "BASE_URL".to_string(),
-
+                    cookies: Default::default(),
+                    rate_limit: Default::default(),
+                    request_id: Default::default(),
+                    #[cfg(feature = "compression")]
+                    compression: Default::default(),
+                    call_options: Default::default(),
+                    response_cache: None,
+                    #[cfg(feature = "metrics")]
+                    metrics: std::sync::Arc::new(crate::types::metrics::NoopMetrics),
+                    #[cfg(feature = "record")]
+                    record: Default::default(),
+                    max_pages: crate::types::paginate::DEFAULT_MAX_PAGES,
+                    deprecation_warnings: true,
+                    SERVER_VARIABLES_DEFAULT
+
+                    retry_config: Default::default(),
                     client: c,
                     client_http1_only: c1,
                 },
@@ -404,24 +786,30 @@ impl Client {
     {
         #[cfg(feature = "retry")]
         {
-            // Retry up to 3 times with increasing intervals between attempts.
-            let retry_policy =
-                reqwest_retry::policies::ExponentialBackoff::builder().build_with_max_retries(3);
+            let retry_config = crate::types::retry::RetryConfig::default();
             match builder_http.build() {
                 Ok(c) => {
-                    let client = reqwest_middleware::ClientBuilder::new(c)
-                        // Trace HTTP requests. See the tracing crate to make use of these traces.
-                        .with(reqwest_tracing::TracingMiddleware::default())
-                        // Retry failed requests.
-                        .with(reqwest_conditional_middleware::ConditionalMiddleware::new(
-                            reqwest_retry::RetryTransientMiddleware::new_with_policy(retry_policy),
-                            |req: &reqwest::Request| req.try_clone().is_some(),
-                        ))
-                        .build();
+                    let client = retry_config.middleware(c.clone());
                     Client {
                         token: token.to_string(),
                         base_url: "BASE_URL".to_string(),
-
+                        cookies: Default::default(),
+                        rate_limit: Default::default(),
+                        request_id: Default::default(),
+                        #[cfg(feature = "compression")]
+                        compression: Default::default(),
+                        call_options: Default::default(),
+                        response_cache: None,
+                        #[cfg(feature = "metrics")]
+                        metrics: std::sync::Arc::new(crate::types::metrics::NoopMetrics),
+                        #[cfg(feature = "record")]
+                        record: Default::default(),
+                        max_pages: crate::types::paginate::DEFAULT_MAX_PAGES,
+                        deprecation_warnings: true,
+                        SERVER_VARIABLES_DEFAULT
+
+                        retry_config,
+                        reqwest_client: c,
                         client,
                     }
                 }
@@ -434,7 +822,22 @@ impl Client {
                 Ok(c) => Client {
                     token: token.to_string(),
                     base_url: "BASE_URL".to_string(),
-
+                    cookies: Default::default(),
+                    rate_limit: Default::default(),
+                    request_id: Default::default(),
+                    #[cfg(feature = "compression")]
+                    compression: Default::default(),
+                    call_options: Default::default(),
+                    response_cache: None,
+                    #[cfg(feature = "metrics")]
+                    metrics: std::sync::Arc::new(crate::types::metrics::NoopMetrics),
+                    #[cfg(feature = "record")]
+                    record: Default::default(),
+                    max_pages: crate::types::paginate::DEFAULT_MAX_PAGES,
+                    deprecation_warnings: true,
+                    SERVER_VARIABLES_DEFAULT
+
+                    retry_config: Default::default(),
                     client: c,
                 },
                 Err(e) => panic!("creating reqwest client failed: {:?}", e),
@@ -471,6 +874,26 @@ impl Client {
         Self::new_from_reqwest(token, client)
     }
 
+    /// A copy of this client sending `token` instead, for services calling the API
+    /// on behalf of many users. The reqwest clients are handles to the same
+    /// connection pool, so the copy shares it with this client, along with the
+    /// middleware, the metrics, the recorder and the rest of the configuration, like
+    /// the compression of the requests. The last rate limit is its own, since the
+    /// limits are per token, and so is the last request id. The copy has no cookies,
+    /// those of this client, like a session cookie, are of its identity.
+    pub fn with_token_override<T>(&self, token: T) -> Self
+    where
+        T: ToString,
+    {
+        Client {
+            token: token.to_string(),
+            cookies: Default::default(),
+            rate_limit: Default::default(),
+            request_id: Default::default(),
+            ..self.clone()
+        }
+    }
+
     /// Set the base URL for the client to something other than the default: <BASE_URL>.
     #[tracing::instrument]
     pub fn set_base_url<H>(&mut self, base_url: H)
@@ -480,11 +903,285 @@ impl Client {
         self.base_url = base_url.to_string().trim_end_matches('/').to_string();
     }
 
-    /// Create a new Client struct from the environment variable: `ENV_VARIABLE_PREFIX_API_TOKEN`.
+    /// Set the maximum number of pages a paginated stream fetches, the stream fails
+    /// with `Error::TooManyPages` when the server has more. This guards against
+    /// servers that hand out page tokens in a loop, the default is 10 000 pages.
+    pub fn set_max_pages(&mut self, max_pages: usize) {
+        self.max_pages = max_pages;
+    }
+
+    /// Set if the client warns when the API says an operation is deprecated, with
+    /// the `Deprecation` or `Sunset` header of a response. It warns once for every
+    /// operation, the default is to warn.
+    pub fn set_deprecation_warnings(&mut self, enabled: bool) {
+        self.deprecation_warnings = enabled;
+    }
+
+    /// Set how the client retries requests that failed with a transient error, see
+    /// `RetryConfig`. The default retries 3 times, without the `retry` feature
+    /// requests are sent once.
+    pub fn set_retry_config(&mut self, retry_config: crate::types::retry::RetryConfig) {
+        #[cfg(feature = "retry")]
+        {
+            self.client = retry_config.middleware(self.reqwest_client.clone());
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                self.client_http1_only =
+                    retry_config.middleware(self.reqwest_client_http1_only.clone());
+            }
+        }
+        self.retry_config = retry_config;
+    }
+
+    /// How the client retries requests that failed with a transient error.
+    pub fn retry_config(&self) -> crate::types::retry::RetryConfig {
+        self.retry_config
+    }
+
+    /// Set how the client compresses the JSON bodies of its requests, see
+    /// `CompressionMode`. The default sends them as they are.
+    #[cfg(feature = "compression")]
+    pub fn set_request_compression(
+        &mut self,
+        compression: crate::types::compression::CompressionMode,
+    ) {
+        self.compression = compression;
+    }
+
+    /// A copy of this client whose calls change their headers as `call_options`
+    /// say, for proxies that want other headers than the API. The copy shares the
+    /// connections and everything else with this client, and this client keeps
+    /// its headers.
+    pub fn with_call_options(&self, call_options: crate::types::call_options::CallOptions) -> Self {
+        Client {
+            call_options,
+            ..self.clone()
+        }
+    }
+
+    /// Set the cache of the responses to `GET` requests, see `ResponseCache`. The
+    /// client answers a `GET` from the cache while the response the server sent is
+    /// fresh, as the `max-age` of its `Cache-Control` header says. The clones of
+    /// this client share the cache. The default caches nothing.
+    pub fn set_response_cache(
+        &mut self,
+        cache: std::sync::Arc<dyn crate::types::cache::ResponseCache>,
+    ) {
+        self.response_cache = Some(cache);
+    }
+
+    /// Set the metrics sink used to record every API call made by the client.
+    #[cfg(feature = "metrics")]
+    pub fn set_metrics(&mut self, metrics: std::sync::Arc<dyn crate::types::metrics::ApiMetrics>) {
+        self.metrics = metrics;
+    }
+
+    /// Append every API call made by the client to the file at `path`, one JSON
+    /// object per line, so it can be replayed with a `ReplayClient`.
+    #[cfg(feature = "record")]
+    pub fn set_recorder<P: AsRef<std::path::Path>>(&mut self, path: P) -> std::io::Result<()> {
+        self.record = crate::types::record::Mode::Record(std::sync::Arc::new(
+            crate::types::record::Recorder::open(path)?,
+        ));
+        Ok(())
+    }
+
+    /// Send the request of an API call, every generated function sends through here.
+    pub(crate) async fn execute(
+        &self,
+        operation_id: &str,
+        request: reqwest::Request,
+    ) -> Result<reqwest::Response, crate::types::error::Error> {
+        let request = self.call_options.apply(request);
+
+        // Answer a `GET` from the cache while the response is fresh.
+        let cache = match (&self.response_cache, crate::types::cache::key(&request)) {
+            (Some(cache), Some(key)) => Some((cache, key)),
+            _ => None,
+        };
+        if let Some((cache, key)) = &cache {
+            if let Some(response) = crate::types::cache::get(cache.as_ref(), key).await {
+                return Ok(response);
+            }
+        }
+
+        // Time the calls that reach the API, with their retries.
+        #[cfg(feature = "metrics")]
+        let call = crate::types::metrics::ApiCall::start(operation_id, request.method());
+
+        // Compress what we send, not what we record, so a recording replays
+        // whatever the compression.
+        let send = |request: reqwest::Request| {
+            #[cfg(feature = "compression")]
+            let request = self.compression.compress(request);
+            let response = self.client.execute(request);
+            async move { response.await.map_err(Self::send_error) }
+        };
+        #[cfg(feature = "record")]
+        let response = self.record.execute(operation_id, &self.base_url, request, send).await;
+        #[cfg(not(feature = "record"))]
+        let response = send(request).await;
+        #[cfg(feature = "metrics")]
+        self.metrics.record(&call.finish(&response));
+        let response = response?;
+
+        // Warn once that the API deprecated the operation, if it says so.
+        if self.deprecation_warnings {
+            crate::types::deprecation::warn_once(operation_id, response.headers());
+        }
+
+        match cache {
+            Some((cache, key)) => crate::types::cache::put(cache.as_ref(), &key, response).await,
+            None => Ok(response),
+        }
+    }
+
+    /// The headers asking the server to upgrade the connection to a websocket, with
+    /// a new key.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn websocket_handshake_headers() -> reqwest::header::HeaderMap {
+        let key = base64::Engine::encode(
+            &base64::engine::general_purpose::STANDARD,
+            rand::random::<[u8; 16]>(),
+        );
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert(
+            reqwest::header::CONNECTION,
+            reqwest::header::HeaderValue::from_static("Upgrade"),
+        );
+        headers.insert(
+            reqwest::header::UPGRADE,
+            reqwest::header::HeaderValue::from_static("websocket"),
+        );
+        headers.insert(
+            reqwest::header::SEC_WEBSOCKET_VERSION,
+            reqwest::header::HeaderValue::from_static("13"),
+        );
+        if let Ok(key) = reqwest::header::HeaderValue::from_str(&key) {
+            headers.insert(reqwest::header::SEC_WEBSOCKET_KEY, key);
+        }
+        headers
+    }
+
+    /// Upgrade a connection to `path` of the API to a websocket, for the endpoints
+    /// the spec does not describe. It goes through the HTTP/1.1 client of the
+    /// websocket functions, with the token and the cookies of the client.
+    /// `headers` are sent as well, a `Sec-WebSocket-Protocol` for example, but the
+    /// headers of the handshake are the client's.
+    ///
+    /// Wrap the connection in the websocket library of your choice, as in
+    /// `tokio_tungstenite::WebSocketStream::from_raw_socket`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn upgrade_websocket(
+        &self,
+        path: &str,
+        headers: reqwest::header::HeaderMap,
+    ) -> Result<reqwest::Upgraded, crate::types::error::Error> {
+        let mut req = self
+            .client_http1_only
+            .request(
+                http::Method::GET,
+                format!("{}/{}", self.base_url, path.trim_start_matches('/')),
+            )
+            .bearer_auth(&self.token)
+            .headers(headers)
+            .headers(Self::websocket_handshake_headers());
+        if let Some(cookie) = self.cookie_header(&[]) {
+            req = req.header(reqwest::header::COOKIE, cookie);
+        }
+
+        let resp = req.send().await.map_err(Self::send_error)?;
+        self.record_response(resp.headers());
+        if resp.status().is_client_error() || resp.status().is_server_error() {
+            return Err(crate::types::error::Error::UnexpectedResponse(
+                crate::types::error::ApiResponse::new(resp),
+            ));
+        }
+
+        resp.upgrade()
+            .await
+            .map_err(crate::types::error::Error::from)
+    }
+
+    /// Set a cookie that is sent with every request, for example a session cookie.
+    /// Cookie parameters of an operation take precedence over the cookies set here.
+    ///
+    /// In the browser the `Cookie` header is managed by the browser, so this has no
+    /// effect on wasm.
+    pub fn set_cookie<N, V>(&mut self, name: N, value: V)
+    where
+        N: ToString,
+        V: ToString,
+    {
+        self.cookies.insert(name.to_string(), value.to_string());
+    }
+
+    /// Build the `Cookie` header from the cookies set on the client and the cookie
+    /// parameters of a request. A parameter can be sent more than once, as the
+    /// exploded arrays are.
+    pub(crate) fn cookie_header(&self, cookies: &[(&str, String)]) -> Option<String> {
+        let mut all: std::collections::BTreeMap<&str, Vec<&str>> = self
+            .cookies
+            .iter()
+            .map(|(name, value)| (name.as_str(), vec![value.as_str()]))
+            .collect();
+        let mut params: std::collections::BTreeMap<&str, Vec<&str>> = Default::default();
+        for (name, value) in cookies {
+            params.entry(name).or_default().push(value);
+        }
+        all.extend(params);
+        if all.is_empty() {
+            return None;
+        }
+
+        Some(
+            all.iter()
+                .flat_map(|(name, values)| {
+                    values.iter().map(move |value| format!("{}={}", name, value))
+                })
+                .collect::<Vec<_>>()
+                .join("; "),
+        )
+    }
+
+    /// The rate limit reported by the last response, if the API sent one.
+    /// This is shared by all the clones of this client.
+    pub fn last_rate_limit(&self) -> Option<crate::types::rate_limit::RateLimit> {
+        self.rate_limit.lock().ok().and_then(|rate_limit| *rate_limit)
+    }
+
+    /// The id the server gave the last request, if the API sent one. Support asks
+    /// for it. This is shared by all the clones of this client.
+    pub fn last_request_id(&self) -> Option<String> {
+        self.request_id.lock().ok().and_then(|request_id| request_id.clone())
+    }
+
+    /// Remember the rate limit and the request id reported by a response,
+    /// responses without a rate limit keep the last one. Returns the request id.
+    pub(crate) fn record_response(&self, headers: &reqwest::header::HeaderMap) -> Option<String> {
+        if let Some(rate_limit) =
+            crate::types::rate_limit::RateLimit::from_headers(headers, "RATE_LIMIT_HEADER_PREFIX")
+        {
+            if let Ok(mut last) = self.rate_limit.lock() {
+                *last = Some(rate_limit);
+            }
+        }
+
+        let request_id = headers
+            .get(crate::types::error::REQUEST_ID_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        if let Ok(mut last) = self.request_id.lock() {
+            last.clone_from(&request_id);
+        }
+        request_id
+    }
+
+    ENV_VARIABLE_DOCS
     #[tracing::instrument]
     pub fn new_from_env() -> Self
     {
-       ENV_VARIABLE_CODE 
+       ENV_VARIABLE_CODE
     }
 
     /// Create a raw request to our API.
@@ -515,12 +1212,14 @@ impl Client {
             reqwest::header::ACCEPT,
             reqwest::header::HeaderValue::from_static("application/json"),
         );
-        req = req.header(
-            reqwest::header::CONTENT_TYPE,
-            reqwest::header::HeaderValue::from_static("application/json"),
-        );
 
+        // Only describe the body if there is one, some gateways reject requests
+        // with a content type and no body.
         if let Some(body) = body {
+            req = req.header(
+                reqwest::header::CONTENT_TYPE,
+                reqwest::header::HeaderValue::from_static("application/json"),
+            );
             req = req.body(body);
         }
 
@@ -529,65 +1228,179 @@ impl Client {
 "#;
 
 fn get_env_variable_code_oauth_token(opts: &crate::Opts) -> String {
-    let start = if let Some(add_env_prefix) = &opts.add_env_prefix {
-        r#"let client_id = if let Ok(client_id) = env::var("ENV_VARIABLE_PREFIX_CLIENT_ID") {
-            client_id
-        } else {
-            if let Ok(client_id) = env::var("ADD_ENV_VARIABLE_PREFIX_CLIENT_ID") {
-                client_id
-            } else {
-                panic!("must set ENV_VARIABLE_PREFIX_CLIENT_ID or ADD_ENV_VARIABLE_PREFIX_CLIENT_ID");
-            }
-        };
-    };
-    let client_secret = if let Ok(client_secret) = env::var("ENV_VARIABLE_PREFIX_CLIENT_SECRET") {
-        client_secret
-    } else if let Ok(client_secret) = env::var("ADD_ENV_VARIABLE_PREFIX_CLIENT_SECRET") {
-        client_secret
-    } else {
-        panic!("must set ENV_VARIABLE_PREFIX_CLIENT_SECRET or ADD_ENV_VARIABLE_PREFIX_CLIENT_SECRET");
-    };
-    let redirect_uri = if let Ok(redirect_uri) = env::var("ENV_VARIABLE_PREFIX_REDIRECT_URI") {
-        redirect_uri
-    } else if let Ok(redirect_uri) = env::var("ADD_ENV_VARIABLE_PREFIX_REDIRECT_URI") {
-        redirect_uri
-    } else {
-        panic!("must set ENV_VARIABLE_PREFIX_REDIRECT_URI or ADD_ENV_VARIABLE_PREFIX_REDIRECT_URI");
-    };
-    let base_url = if let Ok(base_url) = env::var("ENV_VARIABLE_PREFIX_HOST"){
-        base_url
-    } else if let Ok(base_url) = env::var("ADD_ENV_VARIABLE_PREFIX_HOST") {
-        base_url
-    } else {
-        "BASE_URL".to_string()
-    };"#
-        .replace(
-            "ADD_ENV_VARIABLE_PREFIX",
-            &crate::template::get_env_variable_prefix(add_env_prefix),
-        )
-        .replace(
-            "ENV_VARIABLE_PREFIX",
-            &crate::template::get_env_variable_prefix(&opts.name),
-        )
-    } else {
-        r#"let client_id = env::var("ENV_VARIABLE_PREFIX_CLIENT_ID").expect("must set ENV_VARIABLE_PREFIX_CLIENT_ID");
-        let client_secret = env::var("ENV_VARIABLE_PREFIX_CLIENT_SECRET").expect("must set ENV_VARIABLE_PREFIX_CLIENT_SECRET");
-        let redirect_uri = env::var("ENV_VARIABLE_PREFIX_REDIRECT_URI").expect("must set ENV_VARIABLE_PREFIX_REDIRECT_URI");
-        let base_url = env::var("ENV_VARIABLE_PREFIX_HOST").unwrap_or("BASE_URL".to_string());"#.replace("ENV_VARIABLE_PREFIX", &crate::template::get_env_variable_prefix(&opts.name))
-    };
-
     format!(
         r#"{}
+    {}
+    {}
+    {}
 
-    
     let mut c = Client::new(client_id, client_secret, redirect_uri, token, refresh_token);
     c.set_base_url(base_url);
     c
     "#,
-        start
+        get_env_variable_lookup(
+            "client_id",
+            "the client id",
+            &get_env_variable_names(opts, "CLIENT_ID")
+        ),
+        get_env_variable_lookup(
+            "client_secret",
+            "the client secret",
+            &get_env_variable_names(opts, "CLIENT_SECRET")
+        ),
+        get_env_variable_lookup(
+            "redirect_uri",
+            "the redirect uri",
+            &get_env_variable_names(opts, "REDIRECT_URI")
+        ),
+        get_base_url_lookup(opts),
     )
 }
 
+/// The environment variables holding a setting, in the order we check them: the
+/// one named after the package, then the one with the additional prefix.
+pub fn get_env_variable_names(opts: &crate::Opts, suffix: &str) -> Vec<String> {
+    let mut names = vec![format!(
+        "{}_{}",
+        crate::template::get_env_variable_prefix(&opts.name),
+        suffix
+    )];
+    if let Some(add_env_prefix) = &opts.add_env_prefix {
+        names.push(format!(
+            "{}_{}",
+            crate::template::get_env_variable_prefix(add_env_prefix),
+            suffix
+        ));
+    }
+    names
+}
+
+/// The environment variables holding the base url, in the order we check them.
+/// The ones given with `--base-url-env` come before the ones named after the package.
+pub fn get_base_url_env_variable_names(opts: &crate::Opts) -> Vec<String> {
+    let mut names = opts.base_url_env.clone();
+    names.extend(get_env_variable_names(opts, "HOST"));
+    names
+}
+
+/// Generate the code reading a required setting from the first environment
+/// variable that is set, or panicking with every variable we looked at.
+fn get_env_variable_lookup(var: &str, description: &str, names: &[String]) -> String {
+    format!(
+        r#"let {} = [{}].iter().find_map(|name| env::var(name).ok()).unwrap_or_else(|| panic!("must set {} in one of the environment variables: {}"));"#,
+        var,
+        quote_names(names),
+        description,
+        names.join(", "),
+    )
+}
+
+/// Generate the code reading the base url from the first environment variable that
+/// is set, or falling back to the base url of the spec.
+fn get_base_url_lookup(opts: &crate::Opts) -> String {
+    format!(
+        r#"let base_url = [{}].iter().find_map(|name| env::var(name).ok()).unwrap_or_else(|| "{}".to_string());"#,
+        quote_names(&get_base_url_env_variable_names(opts)),
+        opts.trimmed_base_url(),
+    )
+}
+
+/// Generate the doc comment lines listing the environment variables read by
+/// `new_from_env`, in the order they are checked.
+fn get_env_variable_docs(opts: &crate::Opts, settings: &[(&str, &str)]) -> String {
+    let mut docs = vec![
+        "/// Create a new Client struct from the environment variables. For each".to_string(),
+        "/// setting the first variable that is set wins.".to_string(),
+        "    ///".to_string(),
+    ];
+    for (description, suffix) in settings {
+        docs.push(format!(
+            "    /// - {}: {}",
+            description,
+            backtick_names(&get_env_variable_names(opts, suffix))
+        ));
+    }
+    docs.push(format!(
+        "    /// - The base url: {}, defaults to `{}`",
+        backtick_names(&get_base_url_env_variable_names(opts)),
+        opts.trimmed_base_url(),
+    ));
+    docs.join("\n")
+}
+
+/// Format a list of environment variable names as a Rust array.
+fn quote_names(names: &[String]) -> String {
+    names
+        .iter()
+        .map(|name| format!("{:?}", name))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Format a list of environment variable names for docs.
+fn backtick_names(names: &[String]) -> String {
+    names
+        .iter()
+        .map(|name| format!("`{}`", name))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The consent url of a client taking the scopes as strings.
+const USER_CONSENT_URL: &str = r#"    /// Return a user consent url with an optional set of scopes.
+    /// If no scopes are provided, they will not be passed in the url.
+    pub fn user_consent_url(&self, scopes: &[String]) -> String {
+        let state = uuid::Uuid::new_v4();
+
+        let url = format!(
+            "USER_CONSENT_ENDPOINT?client_id={}&response_type=code&redirect_uri={}&state={}",
+             self.client_id, self.redirect_uri, state
+        );
+
+        if scopes.is_empty() {
+            return url;
+        }
+
+        // Add the scopes.
+        format!("{}&scope={}", url, scopes.join(" "))
+    }
+"#;
+
+/// The consent url of a client for a spec with scopes, it takes the scopes of the
+/// spec, or strings with `user_consent_url_raw`.
+const USER_CONSENT_URL_SCOPES: &str = r#"    /// Return a user consent url with an optional set of scopes.
+    /// If no scopes are provided, they will not be passed in the url.
+    pub fn user_consent_url(
+        &self,
+        scopes: impl IntoIterator<Item = crate::types::scope::Scope>,
+    ) -> String {
+        let scopes = scopes
+            .into_iter()
+            .map(|scope| scope.to_string())
+            .collect::<Vec<_>>();
+        self.user_consent_url_raw(&scopes)
+    }
+
+    /// Return a user consent url with an optional set of scopes, the scopes are not
+    /// checked against the ones of the API.
+    /// If no scopes are provided, they will not be passed in the url.
+    pub fn user_consent_url_raw(&self, scopes: &[String]) -> String {
+        let state = uuid::Uuid::new_v4();
+
+        let url = format!(
+            "USER_CONSENT_ENDPOINT?client_id={}&response_type=code&redirect_uri={}&state={}",
+             self.client_id, self.redirect_uri, state
+        );
+
+        if scopes.is_empty() {
+            return url;
+        }
+
+        // Add the scopes.
+        format!("{}&scope={}", url, scopes.join(" "))
+    }
+"#;
+
 const CLIENT_FUNCTIONS_OAUTH_TOKEN: &str = r#"
 use std::{env, sync::Arc, convert::TryInto, ops::Add, time::{Duration, Instant}};
 
@@ -607,6 +1420,20 @@ static APP_USER_AGENT: &str = concat!(
 #[cfg(feature = "requests")]
 pub struct Client {
     base_url: String,
+    cookies: std::collections::BTreeMap<String, String>,
+    rate_limit: std::sync::Arc<std::sync::Mutex<Option<crate::types::rate_limit::RateLimit>>>,
+    request_id: std::sync::Arc<std::sync::Mutex<Option<String>>>,
+    #[cfg(feature = "compression")]
+    compression: crate::types::compression::CompressionMode,
+    call_options: crate::types::call_options::CallOptions,
+    response_cache: Option<std::sync::Arc<dyn crate::types::cache::ResponseCache>>,
+    #[cfg(feature = "metrics")]
+    metrics: std::sync::Arc<dyn crate::types::metrics::ApiMetrics>,
+    #[cfg(feature = "record")]
+    record: crate::types::record::Mode,
+    max_pages: usize,
+    deprecation_warnings: bool,
+    SERVER_VARIABLES_FIELD
     token: Arc<tokio::sync::RwLock<InnerToken>>,
     client_id: String,
     client_secret: String,
@@ -614,6 +1441,9 @@ pub struct Client {
 
     auto_refresh: bool,
 
+    retry_config: crate::types::retry::RetryConfig,
+    #[cfg(feature = "retry")]
+    reqwest_client: reqwest::Client,
     #[cfg(feature = "retry")]
     client: reqwest_middleware::ClientWithMiddleware,
     #[cfg(not(feature = "retry"))]
@@ -693,23 +1523,27 @@ impl Client {
 
         #[cfg(feature = "retry")]
         {
-            // Retry up to 3 times with increasing intervals between attempts.
-            let retry_policy =
-                reqwest_retry::policies::ExponentialBackoff::builder().build_with_max_retries(3);
+            let retry_config = crate::types::retry::RetryConfig::default();
             match client {
                 Ok(c) => {
-                    let client = reqwest_middleware::ClientBuilder::new(c)
-                        // Trace HTTP requests. See the tracing crate to make use of these traces.
-                        .with(reqwest_tracing::TracingMiddleware::default())
-                        // Retry failed requests.
-                        .with(reqwest_conditional_middleware::ConditionalMiddleware::new(
-                            reqwest_retry::RetryTransientMiddleware::new_with_policy(retry_policy),
-                            |req: &reqwest::Request| req.try_clone().is_some(),
-                        ))
-                        .build();
+                    let client = retry_config.middleware(c.clone());
 
                     Client {
                         base_url: "BASE_URL".to_string(),
+                        cookies: Default::default(),
+                        rate_limit: Default::default(),
+                        request_id: Default::default(),
+                        #[cfg(feature = "compression")]
+                        compression: Default::default(),
+                        call_options: Default::default(),
+                        response_cache: None,
+                        #[cfg(feature = "metrics")]
+                        metrics: std::sync::Arc::new(crate::types::metrics::NoopMetrics),
+                        #[cfg(feature = "record")]
+                        record: Default::default(),
+                        max_pages: crate::types::paginate::DEFAULT_MAX_PAGES,
+                        deprecation_warnings: true,
+                        SERVER_VARIABLES_DEFAULT
                         client_id: client_id.to_string(),
                         client_secret: client_secret.to_string(),
                         redirect_uri: redirect_uri.to_string(),
@@ -720,6 +1554,8 @@ impl Client {
                         })),
 
                         auto_refresh: false,
+                        retry_config,
+                        reqwest_client: c,
                         client,
                     }
                 }
@@ -730,6 +1566,20 @@ impl Client {
         {
             Client {
                 base_url: "BASE_URL".to_string(),
+                cookies: Default::default(),
+                rate_limit: Default::default(),
+                request_id: Default::default(),
+                #[cfg(feature = "compression")]
+                compression: Default::default(),
+                call_options: Default::default(),
+                response_cache: None,
+                #[cfg(feature = "metrics")]
+                metrics: std::sync::Arc::new(crate::types::metrics::NoopMetrics),
+                #[cfg(feature = "record")]
+                record: Default::default(),
+                max_pages: crate::types::paginate::DEFAULT_MAX_PAGES,
+                deprecation_warnings: true,
+                SERVER_VARIABLES_DEFAULT
                 client_id: client_id.to_string(),
                 client_secret: client_secret.to_string(),
                 redirect_uri: redirect_uri.to_string(),
@@ -740,6 +1590,7 @@ impl Client {
                 })),
 
                 auto_refresh: false,
+                retry_config: Default::default(),
                 client,
             }
         }
@@ -754,6 +1605,208 @@ impl Client {
         self.base_url = base_url.to_string().trim_end_matches('/').to_string();
     }
 
+    /// Set the maximum number of pages a paginated stream fetches, the stream fails
+    /// with `Error::TooManyPages` when the server has more. This guards against
+    /// servers that hand out page tokens in a loop, the default is 10 000 pages.
+    pub fn set_max_pages(&mut self, max_pages: usize) {
+        self.max_pages = max_pages;
+    }
+
+    /// Set if the client warns when the API says an operation is deprecated, with
+    /// the `Deprecation` or `Sunset` header of a response. It warns once for every
+    /// operation, the default is to warn.
+    pub fn set_deprecation_warnings(&mut self, enabled: bool) {
+        self.deprecation_warnings = enabled;
+    }
+
+    /// Set how the client retries requests that failed with a transient error, see
+    /// `RetryConfig`. The default retries 3 times, without the `retry` feature
+    /// requests are sent once.
+    pub fn set_retry_config(&mut self, retry_config: crate::types::retry::RetryConfig) {
+        #[cfg(feature = "retry")]
+        {
+            self.client = retry_config.middleware(self.reqwest_client.clone());
+        }
+        self.retry_config = retry_config;
+    }
+
+    /// How the client retries requests that failed with a transient error.
+    pub fn retry_config(&self) -> crate::types::retry::RetryConfig {
+        self.retry_config
+    }
+
+    /// Set how the client compresses the JSON bodies of its requests, see
+    /// `CompressionMode`. The default sends them as they are.
+    #[cfg(feature = "compression")]
+    pub fn set_request_compression(
+        &mut self,
+        compression: crate::types::compression::CompressionMode,
+    ) {
+        self.compression = compression;
+    }
+
+    /// A copy of this client whose calls change their headers as `call_options`
+    /// say, for proxies that want other headers than the API. The copy shares the
+    /// connections and everything else with this client, and this client keeps
+    /// its headers.
+    pub fn with_call_options(&self, call_options: crate::types::call_options::CallOptions) -> Self {
+        Client {
+            call_options,
+            ..self.clone()
+        }
+    }
+
+    /// Set the cache of the responses to `GET` requests, see `ResponseCache`. The
+    /// client answers a `GET` from the cache while the response the server sent is
+    /// fresh, as the `max-age` of its `Cache-Control` header says. The clones of
+    /// this client share the cache. The default caches nothing.
+    pub fn set_response_cache(
+        &mut self,
+        cache: std::sync::Arc<dyn crate::types::cache::ResponseCache>,
+    ) {
+        self.response_cache = Some(cache);
+    }
+
+    /// Set the metrics sink used to record every API call made by the client.
+    #[cfg(feature = "metrics")]
+    pub fn set_metrics(&mut self, metrics: std::sync::Arc<dyn crate::types::metrics::ApiMetrics>) {
+        self.metrics = metrics;
+    }
+
+    /// Append every API call made by the client to the file at `path`, one JSON
+    /// object per line, so it can be replayed with a `ReplayClient`.
+    #[cfg(feature = "record")]
+    pub fn set_recorder<P: AsRef<std::path::Path>>(&mut self, path: P) -> std::io::Result<()> {
+        self.record = crate::types::record::Mode::Record(std::sync::Arc::new(
+            crate::types::record::Recorder::open(path)?,
+        ));
+        Ok(())
+    }
+
+    /// Send the request of an API call, every generated function sends through here.
+    pub(crate) async fn execute(
+        &self,
+        operation_id: &str,
+        request: reqwest::Request,
+    ) -> Result<reqwest::Response, crate::types::error::Error> {
+        let request = self.call_options.apply(request);
+
+        // Answer a `GET` from the cache while the response is fresh.
+        let cache = match (&self.response_cache, crate::types::cache::key(&request)) {
+            (Some(cache), Some(key)) => Some((cache, key)),
+            _ => None,
+        };
+        if let Some((cache, key)) = &cache {
+            if let Some(response) = crate::types::cache::get(cache.as_ref(), key).await {
+                return Ok(response);
+            }
+        }
+
+        // Time the calls that reach the API, with their retries.
+        #[cfg(feature = "metrics")]
+        let call = crate::types::metrics::ApiCall::start(operation_id, request.method());
+
+        // Compress what we send, not what we record, so a recording replays
+        // whatever the compression.
+        let send = |request: reqwest::Request| {
+            #[cfg(feature = "compression")]
+            let request = self.compression.compress(request);
+            let response = self.client.execute(request);
+            async move { response.await.map_err(Self::send_error) }
+        };
+        #[cfg(feature = "record")]
+        let response = self.record.execute(operation_id, &self.base_url, request, send).await;
+        #[cfg(not(feature = "record"))]
+        let response = send(request).await;
+        #[cfg(feature = "metrics")]
+        self.metrics.record(&call.finish(&response));
+        let response = response?;
+
+        // Warn once that the API deprecated the operation, if it says so.
+        if self.deprecation_warnings {
+            crate::types::deprecation::warn_once(operation_id, response.headers());
+        }
+
+        match cache {
+            Some((cache, key)) => crate::types::cache::put(cache.as_ref(), &key, response).await,
+            None => Ok(response),
+        }
+    }
+
+    /// Set a cookie that is sent with every request, for example a session cookie.
+    /// Cookie parameters of an operation take precedence over the cookies set here.
+    ///
+    /// In the browser the `Cookie` header is managed by the browser, so this has no
+    /// effect on wasm.
+    pub fn set_cookie<N, V>(&mut self, name: N, value: V)
+    where
+        N: ToString,
+        V: ToString,
+    {
+        self.cookies.insert(name.to_string(), value.to_string());
+    }
+
+    /// Build the `Cookie` header from the cookies set on the client and the cookie
+    /// parameters of a request. A parameter can be sent more than once, as the
+    /// exploded arrays are.
+    pub(crate) fn cookie_header(&self, cookies: &[(&str, String)]) -> Option<String> {
+        let mut all: std::collections::BTreeMap<&str, Vec<&str>> = self
+            .cookies
+            .iter()
+            .map(|(name, value)| (name.as_str(), vec![value.as_str()]))
+            .collect();
+        let mut params: std::collections::BTreeMap<&str, Vec<&str>> = Default::default();
+        for (name, value) in cookies {
+            params.entry(name).or_default().push(value);
+        }
+        all.extend(params);
+        if all.is_empty() {
+            return None;
+        }
+
+        Some(
+            all.iter()
+                .flat_map(|(name, values)| {
+                    values.iter().map(move |value| format!("{}={}", name, value))
+                })
+                .collect::<Vec<_>>()
+                .join("; "),
+        )
+    }
+
+    /// The rate limit reported by the last response, if the API sent one.
+    /// This is shared by all the clones of this client.
+    pub fn last_rate_limit(&self) -> Option<crate::types::rate_limit::RateLimit> {
+        self.rate_limit.lock().ok().and_then(|rate_limit| *rate_limit)
+    }
+
+    /// The id the server gave the last request, if the API sent one. Support asks
+    /// for it. This is shared by all the clones of this client.
+    pub fn last_request_id(&self) -> Option<String> {
+        self.request_id.lock().ok().and_then(|request_id| request_id.clone())
+    }
+
+    /// Remember the rate limit and the request id reported by a response,
+    /// responses without a rate limit keep the last one. Returns the request id.
+    pub(crate) fn record_response(&self, headers: &reqwest::header::HeaderMap) -> Option<String> {
+        if let Some(rate_limit) =
+            crate::types::rate_limit::RateLimit::from_headers(headers, "RATE_LIMIT_HEADER_PREFIX")
+        {
+            if let Ok(mut last) = self.rate_limit.lock() {
+                *last = Some(rate_limit);
+            }
+        }
+
+        let request_id = headers
+            .get(crate::types::error::REQUEST_ID_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        if let Ok(mut last) = self.request_id.lock() {
+            last.clone_from(&request_id);
+        }
+        request_id
+    }
+
     /// Enables or disables the automatic refreshing of access tokens upon expiration
     #[tracing::instrument]
     pub fn set_auto_access_token_refresh(&mut self, enabled: bool) -> &mut Self {
@@ -821,10 +1874,7 @@ impl Client {
         seconds_valid.map(|seconds_valid| Instant::now().add(seconds_valid))
     }
 
-    /// Create a new Client struct from the environment variables:
-    ///     - `ENV_VARIABLE_PREFIX_CLIENT_ID`
-    ///     - `ENV_VARIABLE_PREFIX_CLIENT_SECRET`
-    ///     - `ENV_VARIABLE_PREFIX_REDIRECT_URI`
+    ENV_VARIABLE_DOCS
     #[tracing::instrument]
     pub fn new_from_env<T, R>(token: T, refresh_token: R) -> Self
     where
@@ -834,23 +1884,7 @@ impl Client {
         ENV_VARIABLE_CODE
     }
 
-    /// Return a user consent url with an optional set of scopes.
-    /// If no scopes are provided, they will not be passed in the url.
-    pub fn user_consent_url(&self, scopes: &[String]) -> String {
-        let state = uuid::Uuid::new_v4();
-
-        let url = format!(
-            "USER_CONSENT_ENDPOINT?client_id={}&response_type=code&redirect_uri={}&state={}",
-             self.client_id, self.redirect_uri, state
-        );
-
-        if scopes.is_empty() {
-            return url;
-        }
-
-        // Add the scopes.
-        format!("{}&scope={}", url, scopes.join(" "))
-    }
+USER_CONSENT_URL_FUNCTIONS
 
     /// Refresh an access token from a refresh token. Client must have a refresh token
     /// for this to work.
@@ -944,7 +1978,7 @@ impl Client {
         method: reqwest::Method,
         uri: &str,
         body: Option<reqwest::Body>,
-    ) -> anyhow::Result<reqwest_middleware::RequestBuilder>
+    ) -> anyhow::Result<RequestBuilder>
     {
         if self.auto_refresh {
             let expired = self.is_expired().await;
@@ -992,15 +2026,17 @@ impl Client {
             reqwest::header::ACCEPT,
             reqwest::header::HeaderValue::from_static("application/json"),
         );
-        req = req.header(
-            reqwest::header::CONTENT_TYPE,
-            reqwest::header::HeaderValue::from_static("application/json"),
-        );
 
+        // Only describe the body if there is one, some gateways reject requests
+        // with a content type and no body.
         if let Some(body) = body {
+            req = req.header(
+                reqwest::header::CONTENT_TYPE,
+                reqwest::header::HeaderValue::from_static("application/json"),
+            );
             req = req.body(body);
         }
 
-        Ok(req)
+        Ok(RequestBuilder(req))
     }
 "#;
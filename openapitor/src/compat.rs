@@ -0,0 +1,377 @@
+//! Deprecated aliases for what a generation renamed, see `--rename-shims`.
+//!
+//! A schema renamed in the spec, or by a change to our naming, breaks the code
+//! of the consumers with no hint of the new name. Given the manifest of the
+//! previous generation, see `changelog::Manifest`, we pair the types that are gone
+//! with the new types of the same shape, whatever their names, and keep the old
+//! names in the `compat` module as deprecated aliases. A function renamed in its
+//! tag, with the same operation and the same signature, keeps its old name as a
+//! deprecated method calling the new one. Consumers get warnings for a release
+//! instead of errors.
+//!
+//! We only pair what is unambiguous: a type that is gone with the single new type
+//! of its shape, when no other type that is gone has that shape.
+
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+
+use crate::changelog::{FunctionSignature, Manifest, TypeShape};
+
+/// The name of the module with the aliases.
+pub const MODULE: &str = "compat";
+
+/// What a generation renamed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Renames {
+    /// The new names of the types, by their old name.
+    pub types: BTreeMap<String, String>,
+    /// The new names of the functions, by their old `{tag}::{function}`.
+    pub functions: BTreeMap<String, String>,
+}
+
+impl Renames {
+    /// Pair what is gone from `old` with what is new in `new`.
+    pub fn find(old: &Manifest, new: &Manifest) -> Self {
+        // A renamed type changes the shapes of the types using it, so we pair again
+        // with the new names until nothing else pairs.
+        let mut types = BTreeMap::new();
+        loop {
+            let gone = old
+                .types
+                .iter()
+                .filter(|(name, _)| !new.types.contains_key(*name) && !types.contains_key(*name))
+                .map(|(name, shape)| (name.as_str(), rename_shape(shape, &types)))
+                .collect::<Vec<_>>();
+            let added = new
+                .types
+                .iter()
+                .filter(|(name, _)| {
+                    !old.types.contains_key(*name) && !types.values().any(|n| n == *name)
+                })
+                .map(|(name, shape)| (name.as_str(), shape.clone()))
+                .collect::<Vec<_>>();
+            let pairs = unique_pairs(&gone, &added);
+            if pairs.is_empty() {
+                break;
+            }
+            types.extend(pairs);
+        }
+
+        // The functions only pair in their tag, the tag struct of another one is not
+        // where the consumers call them.
+        let gone = old
+            .functions
+            .iter()
+            .filter(|(key, _)| !new.functions.contains_key(*key))
+            .map(|(key, signature)| (key.as_str(), rename_signature(signature, &types)))
+            .collect::<Vec<_>>();
+        let added = new
+            .functions
+            .iter()
+            .filter(|(key, _)| !old.functions.contains_key(*key))
+            .map(|(key, signature)| (key.as_str(), comparable(signature)))
+            .collect::<Vec<_>>();
+        let functions = unique_pairs(&gone, &added)
+            .into_iter()
+            .filter(|(old, new)| tag_of(old) == tag_of(new))
+            .collect();
+
+        Renames { types, functions }
+    }
+
+    /// If nothing was renamed.
+    pub fn is_empty(&self) -> bool {
+        self.types.is_empty() && self.functions.is_empty()
+    }
+
+    /// Render the `compat` module. The functions are methods of the structs of the
+    /// tags, so they have no shims when the tags are crates of their own.
+    pub fn render(
+        &self,
+        new: &Manifest,
+        module_map: &crate::modules::ModuleMap,
+        split_by_tag: bool,
+    ) -> Result<TokenStream> {
+        let docs =
+            "Deprecated aliases for the types and the functions the last generation renamed, \
+                    they go away with the next one.";
+
+        let mut aliases = Vec::new();
+        for (old, new) in &self.types {
+            let old = format_ident!("{}", old);
+            let note = format!("renamed to `{}`", new);
+            let new = format_ident!("{}", new);
+            aliases.push(quote!(
+                #[deprecated(note = #note)]
+                pub type #old = crate::types::#new;
+            ));
+        }
+
+        let mut methods = BTreeMap::<&str, Vec<TokenStream>>::new();
+        for (old, new_key) in &self.functions {
+            let signature = &new.functions[new_key];
+            // The streams and the builders borrow the client in ways the manifest does
+            // not keep.
+            if split_by_tag || !signature.is_async {
+                continue;
+            }
+            let old = format_ident!("{}", old.rsplit("::").next().unwrap_or(old));
+            let function = new_key.rsplit("::").next().unwrap_or(new_key);
+            let note = format!("renamed to `{}`", function);
+            let function = format_ident!("{}", function);
+            let args = signature
+                .args
+                .iter()
+                .map(|arg| {
+                    let name = format_ident!("{}", arg.name);
+                    let type_ = parse_tokens(&arg.type_)?;
+                    Ok(quote!(#name: #type_))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let names = signature
+                .args
+                .iter()
+                .map(|arg| format_ident!("{}", arg.name));
+            let returns = parse_tokens(&signature.returns)?;
+            let cfgs = signature
+                .cfgs
+                .iter()
+                .map(|cfg| parse_tokens(cfg))
+                .collect::<Result<Vec<_>>>()?;
+            methods.entry(tag_of(new_key)).or_default().push(quote!(
+                #(#[cfg(#cfgs)])*
+                #[deprecated(note = #note)]
+                pub async fn #old<'a>(&'a self, #(#args),*) -> #returns {
+                    self.#function(#(#names),*).await
+                }
+            ));
+        }
+        let impls = methods.into_iter().map(|(tag, methods)| {
+            let path = module_map
+                .path(tag)
+                .into_iter()
+                .map(|segment| format_ident!("{}", segment));
+            let name = format_ident!("{}", module_map.struct_name(tag));
+            quote!(
+                #[cfg(feature = "requests")]
+                impl crate::#(#path::)*#name {
+                    #(#methods)*
+                }
+            )
+        });
+
+        Ok(quote!(
+            #![doc = #docs]
+
+            #(#aliases)*
+
+            #(#impls)*
+        ))
+    }
+}
+
+/// The tag of a `{tag}::{function}` key.
+fn tag_of(key: &str) -> &str {
+    key.split("::").next().unwrap_or(key)
+}
+
+/// Pair every item that is gone with the single added item that is the same,
+/// when no other item that is gone is the same too.
+fn unique_pairs<T: PartialEq>(gone: &[(&str, T)], added: &[(&str, T)]) -> Vec<(String, String)> {
+    gone.iter()
+        .filter_map(|(old, shape)| {
+            let mut candidates = added.iter().filter(|(_, s)| s == shape);
+            let (new, _) = candidates.next()?;
+            if candidates.next().is_some() || gone.iter().filter(|(_, s)| s == shape).count() > 1 {
+                return None;
+            }
+            Some((old.to_string(), new.to_string()))
+        })
+        .collect()
+}
+
+/// The parts of a signature that must be the same for a function to be renamed.
+fn comparable(signature: &FunctionSignature) -> FunctionSignature {
+    FunctionSignature {
+        method: signature.method.clone(),
+        path: signature.path.clone(),
+        args: signature.args.clone(),
+        returns: signature.returns.clone(),
+        ..Default::default()
+    }
+}
+
+/// A signature of the previous generation, with the types renamed.
+fn rename_signature(
+    signature: &FunctionSignature,
+    types: &BTreeMap<String, String>,
+) -> FunctionSignature {
+    let mut signature = comparable(signature);
+    for arg in &mut signature.args {
+        arg.type_ = rename_idents(&arg.type_, types);
+    }
+    signature.returns = rename_idents(&signature.returns, types);
+    signature
+}
+
+/// A shape of the previous generation, with the types renamed.
+fn rename_shape(shape: &TypeShape, types: &BTreeMap<String, String>) -> TypeShape {
+    let mut shape = shape.clone();
+    match &mut shape {
+        TypeShape::Struct { fields } => {
+            for field in fields {
+                field.type_ = rename_idents(&field.type_, types);
+            }
+        }
+        TypeShape::Tuple { fields } => {
+            for field in fields {
+                *field = rename_idents(field, types);
+            }
+        }
+        TypeShape::Enum { variants } => {
+            for variant in variants {
+                *variant = rename_idents(variant, types);
+            }
+        }
+        TypeShape::Alias { target } => *target = rename_idents(target, types),
+    }
+    shape
+}
+
+/// Replace the identifiers in a type of the manifest, like `Vec<Thing>`.
+fn rename_idents(s: &str, renames: &BTreeMap<String, String>) -> String {
+    if renames.is_empty() {
+        return s.to_string();
+    }
+
+    let mut out = String::new();
+    let mut ident = String::new();
+    for c in s.chars().chain(std::iter::once('\0')) {
+        if c.is_alphanumeric() || c == '_' {
+            ident.push(c);
+            continue;
+        }
+        out.push_str(renames.get(&ident).unwrap_or(&ident));
+        ident.clear();
+        if c != '\0' {
+            out.push(c);
+        }
+    }
+    out
+}
+
+fn parse_tokens(s: &str) -> Result<TokenStream> {
+    s.parse::<TokenStream>()
+        .map_err(|e| anyhow::anyhow!("failed to parse `{}` of the manifest: {}", s, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use pretty_assertions::assert_eq;
+
+    use super::Renames;
+    use crate::changelog::{build_manifest, Field, Manifest, TypeShape};
+
+    fn manifest(spec: &str) -> Manifest {
+        let opts = crate::Opts {
+            name: "compat".to_string(),
+            target_version: "1.0.0".to_string(),
+            ..Default::default()
+        };
+        build_manifest(&crate::load_json_spec(spec).unwrap(), &opts).unwrap()
+    }
+
+    #[test]
+    fn test_renames_between_generations() {
+        let old = manifest(include_str!("../tests/compat/before.json"));
+        let new = manifest(include_str!("../tests/compat/after.json"));
+
+        // The manifest of the previous generation is read back from its file.
+        let json = serde_json::to_string_pretty(&old).unwrap();
+        let old = serde_json::from_str::<Manifest>(&json).unwrap();
+        assert_eq!(old, manifest(include_str!("../tests/compat/before.json")));
+
+        let renames = Renames::find(&old, &new);
+        assert_eq!(
+            renames.types,
+            BTreeMap::from([
+                ("Part".to_string(), "Component".to_string()),
+                ("Widget".to_string(), "Gadget".to_string()),
+            ])
+        );
+        assert_eq!(
+            renames.functions,
+            BTreeMap::from([
+                ("widgets::get".to_string(), "widgets::fetch".to_string()),
+                (
+                    "widgets::get_batch".to_string(),
+                    "widgets::fetch_batch".to_string()
+                ),
+            ])
+        );
+
+        let compat = crate::types::get_text_fmt(
+            &renames
+                .render(
+                    &new,
+                    &crate::modules::ModuleMap::new(&Default::default()).unwrap(),
+                    false,
+                )
+                .unwrap(),
+        )
+        .unwrap();
+        assert!(
+            compat.contains(
+                "#[deprecated(note = \"renamed to `Gadget`\")]\npub type Widget = crate::types::Gadget;"
+            ),
+            "{}",
+            compat
+        );
+        assert!(
+            compat.contains("impl crate::widgets::Widgets {"),
+            "{}",
+            compat
+        );
+        assert!(compat.contains("self.fetch(id).await"), "{}", compat);
+        // The batches are streams, they have no shims.
+        assert!(!compat.contains("get_batch"), "{}", compat);
+
+        // Nothing is renamed from a generation to the same one.
+        assert!(Renames::find(&new, &new).is_empty());
+    }
+
+    #[test]
+    fn test_ambiguous_renames() {
+        let empty = TypeShape::Struct { fields: vec![] };
+        let named = TypeShape::Struct {
+            fields: vec![Field {
+                name: "name".to_string(),
+                type_: "String".to_string(),
+            }],
+        };
+        let old = Manifest {
+            types: BTreeMap::from([
+                ("Empty".to_string(), empty.clone()),
+                ("Nothing".to_string(), empty.clone()),
+                ("Named".to_string(), named.clone()),
+            ]),
+            ..Default::default()
+        };
+        let new = Manifest {
+            types: BTreeMap::from([
+                ("Void".to_string(), empty),
+                ("Labeled".to_string(), named.clone()),
+                ("Titled".to_string(), named),
+            ]),
+            ..Default::default()
+        };
+
+        // Two types that are gone with the same shape, and two new ones.
+        assert!(Renames::find(&old, &new).is_empty());
+    }
+}
@@ -0,0 +1,457 @@
+//! The command line of openapitor, and its config file.
+//!
+//! Every option of `Opts` is a flag and a key of the TOML file of `--config`,
+//! named like its field: `split_types = "tag"` for `--split-types tag`. The flags
+//! override the keys of the file, and the keys of the file override the defaults
+//! of the flags. The paths of the file are relative to the directory openapitor
+//! runs in, like the ones of the flags.
+//!
+//! `--print-config-template` prints a file with every key, its docs and its
+//! default, to start from.
+
+use std::collections::BTreeSet;
+
+use anyhow::Result;
+use clap::{CommandFactory, FromArgMatches};
+
+use crate::Opts;
+
+/// The options without a default, they must be flags or keys of the config file.
+const REQUIRED: &[&str] = &[
+    "input",
+    "name",
+    "target_version",
+    "description",
+    "request_timeout_seconds",
+];
+
+/// The options only valid with another one, as the `requires` of their flags.
+const REQUIRES: &[(&str, &str)] = &[
+    ("budget_baseline", "generate_budgets"),
+    ("smoke_tests", "generate_smoke_tests"),
+    ("fuzz_types", "generate_fuzz"),
+];
+
+/// The values of the template for the options without a default.
+const EXAMPLES: &[(&str, &str)] = &[
+    ("input", r#""spec.json""#),
+    ("name", r#""example""#),
+    ("target_version", r#""0.1.0""#),
+    ("description", r#""A client for the Example API.""#),
+    ("request_timeout_seconds", "60"),
+    ("base_url", r#""https://api.example.com""#),
+    ("spec_url", r#""https://example.com/openapi.json""#),
+    ("repo_name", r#""example/example.rs""#),
+    ("token_endpoint", r#""https://example.com/oauth2/token""#),
+    (
+        "user_consent_endpoint",
+        r#""https://example.com/oauth2/authorize""#,
+    ),
+    ("date_time_format", r#""%Y-%m-%dT%H:%M:%S%.f%:z""#),
+    ("add_env_prefix", r#""EXAMPLE""#),
+    ("changelog_from", r#""example.rs.manifest.json""#),
+    ("interop_map", r#""interop.toml""#),
+    ("update_helpers", r#""update-helpers.toml""#),
+    ("session_config", r#""session.toml""#),
+    ("max_description_length", "500"),
+    ("nested_accessors", "2"),
+    ("budget_baseline", r#""budgets.json""#),
+    ("smoke_tests", r#""smoke-tests.toml""#),
+    ("poll_helpers", r#""poll-helpers.toml""#),
+    ("append_to_types", r#""hooks/types.rs""#),
+    ("rename_shims", r#""example.rs.manifest.json""#),
+];
+
+/// The command line of openapitor.
+#[derive(clap::Parser, Debug, Clone)]
+#[command(version = clap::crate_version!(), author = clap::crate_authors!("\n"))]
+pub struct Cli {
+    /// A TOML file with the options, named like the flags with `_` for `-`, see
+    /// `--print-config-template`. The flags override its values.
+    #[arg(long)]
+    pub config: Option<std::path::PathBuf>,
+
+    /// Print a config file with every option, its docs and its default, then exit.
+    #[arg(long)]
+    pub print_config_template: bool,
+
+    /// The input OpenAPI definition document (JSON | YAML).
+    #[arg(short, long)]
+    pub input: Option<std::path::PathBuf>,
+
+    /// The crate name for our generated client.
+    #[arg(short, long)]
+    pub name: Option<String>,
+
+    /// The crate version for our generated client.
+    #[arg(short, long)]
+    pub target_version: Option<String>,
+
+    /// The crate description for our generated client.
+    #[arg(short, long)]
+    pub description: Option<String>,
+
+    /// Default timeout on the client
+    #[arg(long)]
+    pub request_timeout_seconds: Option<u64>,
+
+    /// The options with a default.
+    #[command(flatten)]
+    pub opts: Opts,
+}
+
+impl Cli {
+    /// The options of the flags and of the config file, `matches` are the ones the
+    /// flags were parsed from.
+    pub fn into_opts(self, matches: &clap::ArgMatches) -> Result<Opts> {
+        let mut values = match serde_json::to_value(&self.opts)? {
+            serde_json::Value::Object(values) => values,
+            _ => anyhow::bail!("the options are not an object"),
+        };
+
+        // The keys of the file override the defaults of the flags, not the flags.
+        let file = match &self.config {
+            Some(path) => read(path)?,
+            None => Default::default(),
+        };
+        for (key, value) in &file {
+            if matches.value_source(key) != Some(clap::parser::ValueSource::CommandLine) {
+                values.insert(key.to_string(), value.clone());
+            }
+        }
+
+        let required = [
+            ("input", serde_json::to_value(&self.input)?),
+            ("name", serde_json::to_value(&self.name)?),
+            (
+                "target_version",
+                serde_json::to_value(&self.target_version)?,
+            ),
+            ("description", serde_json::to_value(&self.description)?),
+            (
+                "request_timeout_seconds",
+                serde_json::to_value(self.request_timeout_seconds)?,
+            ),
+        ];
+        for (key, value) in required {
+            if !value.is_null() {
+                values.insert(key.to_string(), value);
+            } else if !file.contains_key(key) {
+                anyhow::bail!(
+                    "missing `{}`, give it with `--{}` or in the config file",
+                    key,
+                    key.replace('_', "-")
+                );
+            }
+        }
+
+        for (key, requires) in REQUIRES {
+            let given = match &values[*key] {
+                serde_json::Value::Null => false,
+                serde_json::Value::Array(values) => !values.is_empty(),
+                _ => true,
+            };
+            if given && values[*requires] != serde_json::Value::Bool(true) {
+                anyhow::bail!("`{}` requires `{}`", key, requires);
+            }
+        }
+
+        Ok(serde_json::from_value(serde_json::Value::Object(values))?)
+    }
+}
+
+/// The keys of a config file. Each key is checked on its own, so the errors name
+/// the key.
+pub fn read<P: AsRef<std::path::Path>>(p: P) -> Result<serde_json::Map<String, serde_json::Value>> {
+    let p = p.as_ref();
+    let contents = std::fs::read_to_string(p)
+        .map_err(|e| anyhow::anyhow!("failed to read config `{}`: {}", p.display(), e))?;
+    let table: toml::Table = toml::from_str(&contents)
+        .map_err(|e| anyhow::anyhow!("failed to parse config `{}`: {}", p.display(), e))?;
+
+    let keys = keys()?;
+    let mut values = serde_json::Map::new();
+    for (key, value) in table {
+        if !keys.contains(&key) {
+            anyhow::bail!(
+                "invalid config `{}`: `{}` is not an option, see `--print-config-template`",
+                p.display(),
+                key
+            );
+        }
+        let value = serde_json::to_value(value)?;
+        let mut only = serde_json::Map::new();
+        only.insert(key.to_string(), value.clone());
+        serde_json::from_value::<Opts>(serde_json::Value::Object(only))
+            .map_err(|e| anyhow::anyhow!("invalid config `{}`: `{}`: {}", p.display(), key, e))?;
+        values.insert(key, value);
+    }
+
+    Ok(values)
+}
+
+/// The keys of the config file, the fields of `Opts`.
+fn keys() -> Result<BTreeSet<String>> {
+    match serde_json::to_value(Opts::default())? {
+        serde_json::Value::Object(values) => Ok(values.keys().cloned().collect()),
+        _ => anyhow::bail!("the options are not an object"),
+    }
+}
+
+/// A config file with every option, its docs and its default, or an example if it
+/// has none. The options without a default are set, the others are commented out.
+pub fn template() -> Result<String> {
+    let defaults = Cli::from_arg_matches(&Cli::command().try_get_matches_from(["openapitor"])?)?;
+    let defaults = serde_json::to_value(&defaults.opts)?;
+    let keys = keys()?;
+
+    let mut template = String::from(
+        "# The options of openapitor, give the file with `--config`. The keys are the\n\
+         # flags with `_` for `-`, and the flags override them. The paths are relative\n\
+         # to the directory openapitor runs in.\n",
+    );
+    for arg in Cli::command().get_arguments() {
+        let key = arg.get_id().as_str();
+        if !keys.contains(key) {
+            continue;
+        }
+        let help = arg
+            .get_long_help()
+            .or_else(|| arg.get_help())
+            .map(|help| help.to_string())
+            .unwrap_or_default();
+        template.push('\n');
+        for line in wrap(&help, 78) {
+            template.push_str(&format!("# {}\n", line).replace("# \n", "#\n"));
+        }
+
+        let example = EXAMPLES.iter().find(|(k, _)| *k == key);
+        if let (true, Some((_, example))) = (REQUIRED.contains(&key), example) {
+            template.push_str(&format!("{} = {}\n", key, example));
+        } else if let Some((_, example)) = example {
+            template.push_str(&format!("# {} = {}\n", key, example));
+        } else {
+            let value = toml::Value::try_from(&defaults[key])
+                .map_err(|e| anyhow::anyhow!("failed to write the default of `{}`: {}", key, e))?;
+            template.push_str(&format!("# {} = {}\n", key, value));
+        }
+    }
+
+    Ok(template)
+}
+
+/// Wrap the paragraphs of `text` at `width` columns, with an empty line between
+/// them.
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    for (i, paragraph) in text.split("\n\n").enumerate() {
+        if i > 0 {
+            lines.push(String::new());
+        }
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            if !line.is_empty() && line.len() + 1 + word.len() > width {
+                lines.push(std::mem::take(&mut line));
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(word);
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::{CommandFactory, FromArgMatches};
+    use pretty_assertions::assert_eq;
+
+    use super::Cli;
+
+    /// The options of a command line, with a config file of `config` if any. The
+    /// file is removed once it is read.
+    fn opts(args: &[&str], config: Option<&str>) -> anyhow::Result<crate::Opts> {
+        let mut args = args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        args.insert(0, "openapitor".to_string());
+        let mut path = None;
+        if let Some(config) = config {
+            let config_path =
+                std::env::temp_dir().join(format!("openapitor-{}.toml", uuid::Uuid::new_v4()));
+            std::fs::write(&config_path, config)?;
+            args.push(format!("--config={}", config_path.display()));
+            path = Some(config_path);
+        }
+        let opts = Cli::command()
+            .try_get_matches_from(args)
+            .map_err(anyhow::Error::from)
+            .and_then(|matches| Cli::from_arg_matches(&matches)?.into_opts(&matches));
+        if let Some(path) = path {
+            std::fs::remove_file(path)?;
+        }
+        opts
+    }
+
+    const REQUIRED: &str = r#"
+input = "spec.json"
+name = "from-file"
+target_version = "1.0.0"
+description = "A client."
+request_timeout_seconds = 30
+"#;
+
+    #[test]
+    fn test_config_precedence() {
+        let config = format!(
+            "{}{}",
+            REQUIRED,
+            r#"
+split_types = "tag"
+rate_limit_header_prefix = "X-Limit"
+request_id_header = "X-Trace-Id"
+wait_params = ["block"]
+"#
+        );
+
+        let opts = opts(&[], Some(&config)).unwrap();
+        assert_eq!(opts.name, "from-file");
+        assert_eq!(opts.request_timeout_seconds, 30);
+        assert_eq!(opts.split_types, crate::TypesSplit::Tag);
+        assert_eq!(opts.rate_limit_header_prefix, "X-Limit");
+        assert_eq!(opts.wait_params, vec!["block".to_string()]);
+        // The keys left out keep the defaults of the flags.
+        assert_eq!(opts.base_url, None);
+        assert_eq!(opts.output, std::path::PathBuf::from("."));
+
+        // The flags override the file, even with the value of their default.
+        let opts = super::tests::opts(
+            &[
+                "--name",
+                "from-cli",
+                "--split-types",
+                "alphabetical",
+                "--request-id-header",
+                "X-Request-Id",
+            ],
+            Some(&config),
+        )
+        .unwrap();
+        assert_eq!(opts.name, "from-cli");
+        assert_eq!(opts.split_types, crate::TypesSplit::Alphabetical);
+        assert_eq!(opts.request_id_header, "X-Request-Id");
+        assert_eq!(opts.rate_limit_header_prefix, "X-Limit");
+        assert_eq!(opts.target_version, "1.0.0");
+
+        // The required options are flags, or keys of the file.
+        let opts = super::tests::opts(
+            &[
+                "-i",
+                "spec.json",
+                "-n",
+                "cli",
+                "-t",
+                "1.0.0",
+                "-d",
+                "A client.",
+            ],
+            None,
+        );
+        assert_eq!(
+            opts.unwrap_err().to_string(),
+            "missing `request_timeout_seconds`, give it with `--request-timeout-seconds` or \
+             in the config file"
+        );
+
+        // The file has the `requires` of the flags.
+        assert_eq!(
+            super::tests::opts(
+                &[],
+                Some(&format!("{}budget_baseline = \"budgets.json\"", REQUIRED))
+            )
+            .unwrap_err()
+            .to_string(),
+            "`budget_baseline` requires `generate_budgets`"
+        );
+    }
+
+    #[test]
+    fn test_config_errors_name_the_key() {
+        let err = opts(&[], Some(&format!("{}split_type = \"tag\"", REQUIRED))).unwrap_err();
+        assert!(
+            err.to_string()
+                .ends_with("`split_type` is not an option, see `--print-config-template`"),
+            "{}",
+            err
+        );
+
+        let err = opts(&[], Some("request_timeout_seconds = \"soon\"")).unwrap_err();
+        assert!(
+            err.to_string().ends_with(
+                "`request_timeout_seconds`: invalid type: string \"soon\", expected u64"
+            ),
+            "{}",
+            err
+        );
+
+        let err = opts(&[], Some("split_types = \"tags\"")).unwrap_err();
+        assert!(
+            err.to_string().ends_with(
+                "`split_types`: unknown variant `tags`, expected one of `none`, `alphabetical`, \
+                 `tag`"
+            ),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_config_template() {
+        let template = super::template().unwrap();
+
+        // Every option is in the template.
+        for key in super::keys().unwrap() {
+            assert!(
+                template.contains(&format!("\n{} = ", key))
+                    || template.contains(&format!("\n# {} = ", key)),
+                "`{}` is not in the template",
+                key
+            );
+        }
+        assert!(
+            template.contains(
+                "\n# The input OpenAPI definition document (JSON | YAML)\ninput = \"spec.json\"\n"
+            ),
+            "{}",
+            template
+        );
+        assert!(
+            template.contains("\n# split_types = \"none\"\n"),
+            "{}",
+            template
+        );
+
+        // The template is a valid config, with the defaults of the flags.
+        let opts = opts(&[], Some(&template)).unwrap();
+        assert_eq!(opts.name, "example");
+        assert_eq!(
+            opts.wait_params,
+            vec!["wait".to_string(), "Prefer".to_string()]
+        );
+
+        // Every example is a valid value.
+        for (key, example) in super::EXAMPLES {
+            let mut config = REQUIRED
+                .lines()
+                .filter(|line| !line.starts_with(&format!("{} =", key)))
+                .collect::<Vec<_>>()
+                .join("\n");
+            config.push_str(&format!("\n{} = {}\n", key, example));
+            super::tests::opts(
+                &["--generate-budgets", "--generate-smoke-tests"],
+                Some(&config),
+            )
+            .unwrap_or_else(|e| panic!("{}: {}", key, e));
+        }
+    }
+}
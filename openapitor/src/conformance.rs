@@ -0,0 +1,280 @@
+//! The wire compatibility tests of `--generate-conformance-tests`.
+//!
+//! The spec has JSON examples of its schemas, responses and request bodies. Each
+//! one becomes a test that reads it into the generated type and writes it back,
+//! so every release of the crate proves it still accepts and produces the JSON
+//! the spec documents. The tests go in `src/conformance_tests.rs`, which is
+//! generated again every time, next to the `tests` module we leave alone.
+
+use std::{collections::BTreeSet, str::FromStr};
+
+use anyhow::Result;
+use proc_macro2::TokenStream;
+
+use crate::types::exts::ReferenceOrExt;
+
+/// An example of the spec, and the type it is the JSON of.
+struct Example {
+    /// The name of its test.
+    test: String,
+    /// What the example is of, for the failures.
+    schema: String,
+    /// The generated type.
+    type_name: TokenStream,
+    /// Where it is in the spec.
+    pointer: String,
+    /// The JSON.
+    value: serde_json::Value,
+}
+
+/// Render the tests of the examples of `spec`.
+pub fn render(spec: &openapiv3::OpenAPI) -> Result<TokenStream> {
+    let docs = quote! {
+        //! The wire compatibility tests, one for every JSON example of the spec: the
+        //! example must read into its type and be written back the same. Generated
+        //! with `--generate-conformance-tests`, the changes to this file are lost.
+    };
+    let examples = get_examples(spec)?;
+    if examples.is_empty() {
+        return Ok(docs);
+    }
+
+    let check = TokenStream::from_str(include_str!("types/conformance.rs"))
+        .map_err(|e| anyhow::anyhow!("{}", e))?;
+    let mut names = BTreeSet::new();
+    let tests = examples.iter().map(|example| {
+        // Two examples can have the same name once they are Rust names.
+        let mut test = example.test.clone();
+        let mut n = 2;
+        while !names.insert(test.clone()) {
+            test = format!("{}_{}", example.test, n);
+            n += 1;
+        }
+        let test = format_ident!("{}", test);
+        let Example {
+            schema,
+            type_name,
+            pointer,
+            value,
+            ..
+        } = example;
+        let json = serde_json::to_string_pretty(value).unwrap_or_default();
+
+        quote! {
+            #[test]
+            fn #test() {
+                check::assert_round_trip::<#type_name>(#schema, #pointer, #json);
+            }
+        }
+    });
+
+    Ok(quote! {
+        #docs
+
+        mod check {
+            #check
+        }
+
+        #(#tests)*
+    })
+}
+
+/// The examples of `spec` we have a type for: the ones of the schemas, and the ones
+/// of the JSON responses and request bodies whose schema is one of them or an
+/// array of one.
+fn get_examples(spec: &openapiv3::OpenAPI) -> Result<Vec<Example>> {
+    let mut examples = Vec::new();
+
+    if let Some(components) = &spec.components {
+        for (name, schema) in &components.schemas {
+            let openapiv3::ReferenceOr::Item(schema) = schema else {
+                continue;
+            };
+            let Some(value) = &schema.schema_data.example else {
+                continue;
+            };
+            examples.push(Example {
+                test: format!("test_{}_example", crate::types::casing::to_snake_case(name)),
+                schema: name.to_string(),
+                type_name: crate::types::get_type_name_from_reference(name, spec, false)?,
+                pointer: format!("#/components/schemas/{}/example", escape(name)),
+                value: value.clone(),
+            });
+        }
+    }
+
+    for (path, item) in spec.paths.iter() {
+        let item = item.item()?;
+        for (method, op) in item.iter() {
+            let Some(operation_id) = &op.operation_id else {
+                continue;
+            };
+            let operation = crate::types::casing::to_snake_case(operation_id);
+            let pointer = format!("#/paths/{}/{}", escape(path), method);
+
+            let mut contents = Vec::new();
+            if let Some(body) = &op.request_body {
+                contents.push((
+                    format!("{}_request", operation),
+                    format!("{}/requestBody", pointer),
+                    body.expand(spec)?.content,
+                ));
+            }
+            let responses = op
+                .responses
+                .responses
+                .iter()
+                .map(|(status, response)| (status.to_string(), response))
+                .chain(
+                    op.responses
+                        .default
+                        .iter()
+                        .map(|response| ("default".to_string(), response)),
+                );
+            for (status, response) in responses {
+                contents.push((
+                    format!("{}_{}_response", operation, status.to_lowercase()),
+                    format!("{}/responses/{}", pointer, status),
+                    response.expand(spec)?.content,
+                ));
+            }
+
+            for (test, pointer, content) in contents {
+                for (media_type, media) in &content {
+                    if media_type != "application/json" && !media_type.ends_with("+json") {
+                        continue;
+                    }
+                    let Some((schema, type_name)) = get_type(spec, media.schema.as_ref())? else {
+                        continue;
+                    };
+                    let pointer = format!("{}/content/{}", pointer, escape(media_type));
+                    for (name, value, pointer) in get_media_examples(spec, media, &pointer)? {
+                        let test = match name {
+                            Some(name) => format!(
+                                "test_{}_{}_example",
+                                test,
+                                crate::types::casing::to_snake_case(&name)
+                            ),
+                            None => format!("test_{}_example", test),
+                        };
+                        examples.push(Example {
+                            test,
+                            schema: schema.clone(),
+                            type_name: type_name.clone(),
+                            pointer,
+                            value,
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(examples)
+}
+
+/// The examples of a media type, with their names and where they are in the spec.
+fn get_media_examples(
+    spec: &openapiv3::OpenAPI,
+    media: &openapiv3::MediaType,
+    pointer: &str,
+) -> Result<Vec<(Option<String>, serde_json::Value, String)>> {
+    let mut examples = Vec::new();
+    if let Some(value) = &media.example {
+        examples.push((None, value.clone(), format!("{}/example", pointer)));
+    }
+    for (name, value) in crate::functions::get_named_examples(spec, &media.examples)? {
+        let pointer = format!("{}/examples/{}/value", pointer, escape(&name));
+        examples.push((Some(name), value, pointer));
+    }
+    Ok(examples)
+}
+
+/// The name and the generated type of a schema that is a reference, or an array
+/// of one. The other schemas have types named after their operation, or none.
+fn get_type(
+    spec: &openapiv3::OpenAPI,
+    schema: Option<&openapiv3::ReferenceOr<openapiv3::Schema>>,
+) -> Result<Option<(String, TokenStream)>> {
+    let Some(schema) = schema else {
+        return Ok(None);
+    };
+    if let openapiv3::ReferenceOr::Reference { .. } = schema {
+        let name = schema.reference()?;
+        let type_name = crate::types::get_type_name_from_reference(&name, spec, false)?;
+        return Ok(Some((name, type_name)));
+    }
+
+    let schema = schema.item()?;
+    let openapiv3::SchemaKind::Type(openapiv3::Type::Array(array)) = &schema.schema_kind else {
+        return Ok(None);
+    };
+    let Some(items @ openapiv3::ReferenceOr::Reference { .. }) =
+        array.items.as_ref().map(|items| items.clone().unbox())
+    else {
+        return Ok(None);
+    };
+    let name = items.reference()?;
+    let type_name = crate::types::get_type_name_from_reference(&name, spec, false)?;
+    Ok(Some((format!("[{}]", name), quote!(Vec<#type_name>))))
+}
+
+/// Escape a key for a JSON pointer.
+fn escape(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::get_examples;
+    use crate::types::exts::TokenStreamExt;
+
+    #[test]
+    fn test_renamed_schema_examples() {
+        let mut spec: serde_json::Value =
+            serde_json::from_str(include_str!("../tests/case-collision.json")).unwrap();
+        spec["components"]["schemas"]["OauthToken"]["example"] =
+            serde_json::json!({"providerToken": "abc", "provider": "github"});
+        spec["paths"]["/session"]["get"]["responses"]["200"]["content"]["application/json"]
+            ["examples"] = serde_json::json!({"signed in": {"value": {}}});
+        let spec = crate::load_json_spec(&spec.to_string()).unwrap();
+
+        // The examples go with their schemas once they are renamed.
+        let (spec, renamed) = crate::types::dedupe_schema_names(&spec).unwrap();
+        let examples = get_examples(&spec)
+            .unwrap()
+            .into_iter()
+            .map(|example| {
+                (
+                    example.test,
+                    example.schema,
+                    example.type_name.rendered().unwrap(),
+                    example.pointer,
+                )
+            })
+            .collect::<Vec<_>>();
+        let token = &renamed["OauthToken"];
+        assert_eq!(
+            examples,
+            [
+                (
+                    format!(
+                        "test_{}_example",
+                        crate::types::casing::to_snake_case(token)
+                    ),
+                    token.to_string(),
+                    format!("crate::types::{}", token),
+                    format!("#/components/schemas/{}/example", token),
+                ),
+                (
+                    "test_get_session_200_response_signed_in_example".to_string(),
+                    "Session".to_string(),
+                    "crate::types::Session".to_string(),
+                    "#/paths/~1session/get/responses/200/content/application~1json/examples/\
+                     signed in/value"
+                        .to_string(),
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,163 @@
+//! The OAuth 2.0 device authorization grant of RFC 8628.
+//!
+//! When the spec has the two operations of the grant, we generate a `device_flow`
+//! function on their tag, next to them, that runs the whole flow: it asks for the
+//! codes, hands them back to show the user, then polls for the token with the
+//! interval of the server until the user authorizes the device, denies it, or the
+//! codes expire. The flow itself is in the `device_flow` module of the crate, see
+//! `types/device_flow.rs`.
+//!
+//! We find the operations by their form bodies: the one for the codes takes a
+//! `client_id` on a path with `device` in it, the one for the token takes a
+//! `device_code` and a `grant_type`, and both have the same tag.
+
+use std::str::FromStr;
+
+use anyhow::Result;
+use quote::quote;
+
+use crate::types::exts::{OperationExt, ReferenceOrExt};
+
+/// The operations of the device flow of a spec.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceFlowOperations {
+    /// The tag of both operations, it gets the `device_flow` function.
+    pub tag: String,
+    /// The path of the operation that hands out the codes.
+    pub auth_path: String,
+    /// The path of the operation that we poll for the token.
+    pub token_path: String,
+}
+
+/// The operations of the device flow of the spec, if it has them and the client
+/// can run the flow: the flow sends its requests with the internals of a client
+/// that has a plain token.
+pub fn find(spec: &openapiv3::OpenAPI, opts: &crate::Opts) -> Result<Option<DeviceFlowOperations>> {
+    if opts.types_only || opts.split_by_tag || opts.basic_auth || opts.token_endpoint.is_some() {
+        return Ok(None);
+    }
+
+    let mut auth = None;
+    let mut token = None;
+    for (path, item) in spec.paths.iter() {
+        let item = item.item()?;
+        let Some(op) = &item.post else {
+            continue;
+        };
+        let fields = get_form_fields(spec, op)?;
+        let has = |field: &str| fields.iter().any(|f| f == field);
+        if has("device_code") && has("grant_type") {
+            token.get_or_insert((op.get_tag()?, path.to_string()));
+        } else if has("client_id") && !has("grant_type") && path.contains("device") {
+            auth.get_or_insert((op.get_tag()?, path.to_string()));
+        }
+    }
+
+    Ok(match (auth, token) {
+        (Some((tag, auth_path)), Some((token_tag, token_path))) if tag == token_tag => {
+            Some(DeviceFlowOperations {
+                tag,
+                auth_path,
+                token_path,
+            })
+        }
+        _ => None,
+    })
+}
+
+/// The names of the fields of the form body of an operation, none if it does not
+/// take a form.
+fn get_form_fields(spec: &openapiv3::OpenAPI, op: &openapiv3::Operation) -> Result<Vec<String>> {
+    let Some(request_body) = &op.request_body else {
+        return Ok(Vec::new());
+    };
+    let request_body = request_body.expand(spec)?;
+    let Some(schema) = request_body
+        .content
+        .get("application/x-www-form-urlencoded")
+        .and_then(|content| content.schema.as_ref())
+    else {
+        return Ok(Vec::new());
+    };
+
+    Ok(
+        match schema.get_schema_from_reference(spec, true)?.schema_kind {
+            openapiv3::SchemaKind::Type(openapiv3::Type::Object(o)) => {
+                o.properties.keys().cloned().collect()
+            }
+            _ => Vec::new(),
+        },
+    )
+}
+
+/// Render the `device_flow` module of the crate.
+pub fn render_module() -> Result<proc_macro2::TokenStream> {
+    let file = include_str!("types/device_flow.rs");
+    proc_macro2::TokenStream::from_str(file).map_err(|e| anyhow::anyhow!("{}", e))
+}
+
+/// Render the `device_flow` function of the tag of the operations.
+pub fn render_function(
+    spec: &openapiv3::OpenAPI,
+    operations: &DeviceFlowOperations,
+) -> Result<proc_macro2::TokenStream> {
+    let auth_path =
+        crate::functions::get_url_path(spec, &operations.auth_path, &http::Method::POST)?;
+    let token_path =
+        crate::functions::get_url_path(spec, &operations.token_path, &http::Method::POST)?;
+    let docs = format!(
+        "Start the OAuth 2.0 device authorization grant with `POST {}`, the flow has \
+         the code to show the user, then `wait` polls `POST {}` until they authorize \
+         the device.",
+        operations.auth_path, operations.token_path
+    );
+
+    Ok(quote! {
+        #[doc = #docs]
+        #[cfg(not(target_arch = "wasm32"))]
+        pub async fn device_flow<C>(
+            &self,
+            client_id: C,
+        ) -> Result<crate::device_flow::DeviceFlow, crate::device_flow::DeviceFlowError>
+        where
+            C: std::fmt::Display,
+        {
+            crate::device_flow::DeviceFlow::start(
+                self.client.clone(),
+                #auth_path,
+                #token_path,
+                client_id.to_string(),
+            )
+            .await
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DeviceFlowOperations;
+
+    #[test]
+    fn test_find_device_flow() {
+        let spec = crate::load_json_spec(include_str!("../../spec.json")).unwrap();
+        assert_eq!(
+            super::find(&spec, &Default::default()).unwrap(),
+            Some(DeviceFlowOperations {
+                tag: "oauth2".to_string(),
+                auth_path: "/oauth2/device/auth".to_string(),
+                token_path: "/oauth2/device/token".to_string(),
+            })
+        );
+
+        // The flow needs a client with a plain token.
+        let opts = crate::Opts {
+            basic_auth: true,
+            ..Default::default()
+        };
+        assert_eq!(super::find(&spec, &opts).unwrap(), None);
+
+        // A spec without the operations has no flow.
+        let spec = crate::load_json_spec(include_str!("../tests/datetime.json")).unwrap();
+        assert_eq!(super::find(&spec, &Default::default()).unwrap(), None);
+    }
+}
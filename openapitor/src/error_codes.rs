@@ -0,0 +1,314 @@
+//! The codes of the error responses, as an enum rather than strings.
+//!
+//! The schema of the error responses often has an `error_code` field, and the spec
+//! may list its values, either in the field or in a schema of its own:
+//!
+//! ```json
+//! "error_code": {"type": "string", "enum": ["not_found", "rate_limited"]}
+//! ```
+//!
+//! Its enum gets the `x-rust-catch-all` extension, so it has an `Unknown(String)`
+//! variant for the codes the API adds later, and `crate::types::error::ErrorCode`
+//! is that enum rather than `String`. An enum in the field itself becomes a schema
+//! of its own first, `ErrorCode`, so it has a name we know.
+//!
+//! Any string enum can have the extension, not only the codes of the errors.
+
+use anyhow::Result;
+
+use crate::types::exts::ReferenceOrExt;
+
+/// The field of the errors with their code.
+pub const PROPERTY: &str = "error_code";
+
+/// The extension of the string enums with an `Unknown(String)` variant.
+pub const CATCH_ALL: &str = "x-rust-catch-all";
+
+/// The codes whose class we know, see `crate::types::error::ErrorClass`, by the
+/// name of its variant. A crate only classifies the ones its spec declares.
+pub const CLASSES: &[(&str, &[&str])] = &[
+    (
+        "Transient",
+        &["internal_api", "internal_engine", "connection_problem"],
+    ),
+    ("AuthExpired", &["auth_token_missing", "auth_token_invalid"]),
+    (
+        "ClientBug",
+        &["bad_request", "invalid_json", "invalid_bson", "wrong_protocol"],
+    ),
+];
+
+/// The error schema of the spec with codes, and the schema of its codes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorCodes {
+    /// The schema of the error responses.
+    pub error: String,
+    /// The schema of the enum of the codes.
+    pub codes: String,
+}
+
+/// Returns `true` if a string enum has the catch-all extension.
+pub fn is_catch_all(data: &openapiv3::SchemaData) -> bool {
+    data.extensions.get(CATCH_ALL) == Some(&serde_json::Value::Bool(true))
+}
+
+/// The error schema with codes, the first one of the error responses of the
+/// operations whose `error_code` is an enum, in a schema of its own. See `prepare`
+/// for an enum in the field itself.
+pub fn find(spec: &openapiv3::OpenAPI) -> Result<Option<ErrorCodes>> {
+    for error in get_error_schemas(spec)? {
+        let Some(openapiv3::ReferenceOr::Reference { reference }) =
+            get_code_property(spec, &error)?
+        else {
+            continue;
+        };
+        let codes = reference.trim_start_matches("#/components/schemas/");
+        if is_enum(spec, &get_schema(spec, codes)?)? {
+            return Ok(Some(ErrorCodes {
+                error,
+                codes: codes.to_string(),
+            }));
+        }
+    }
+    Ok(None)
+}
+
+/// The codes of the errors the spec declares, the values of the enum of `find`, or
+/// else of an `ErrorCode` enum, for the specs that list their codes without typing
+/// the field of the errors with them.
+pub fn declared(spec: &openapiv3::OpenAPI) -> Result<Vec<String>> {
+    let name = match find(spec)? {
+        Some(error_codes) => error_codes.codes,
+        None => "ErrorCode".to_string(),
+    };
+    let Some(schema) = spec
+        .components
+        .as_ref()
+        .and_then(|components| components.schemas.get(&name))
+    else {
+        return Ok(Vec::new());
+    };
+    let schema = schema.expand(spec)?;
+    if !is_enum(spec, &schema)? {
+        return Ok(Vec::new());
+    }
+
+    let mut codes = Vec::new();
+    match &schema.schema_kind {
+        openapiv3::SchemaKind::Type(openapiv3::Type::String(s)) => {
+            codes.extend(s.enumeration.iter().flatten().cloned());
+        }
+        openapiv3::SchemaKind::OneOf { one_of } => {
+            for one_of in one_of {
+                if let openapiv3::SchemaKind::Type(openapiv3::Type::String(s)) =
+                    one_of.get_schema_from_reference(spec, true)?.schema_kind
+                {
+                    codes.extend(s.enumeration.into_iter().flatten());
+                }
+            }
+        }
+        _ => {}
+    }
+    Ok(codes)
+}
+
+/// The spec with the enum of the codes of the errors in a schema of its own, with
+/// the catch-all extension. Nothing changes if the errors have no codes.
+pub fn prepare(spec: &openapiv3::OpenAPI) -> Result<openapiv3::OpenAPI> {
+    let mut spec = spec.clone();
+    for error in get_error_schemas(&spec)? {
+        // The enum is in the field, it moves to a schema of its own.
+        if let Some(openapiv3::ReferenceOr::Item(schema)) = get_code_property(&spec, &error)? {
+            if !is_enum(&spec, &schema)? {
+                continue;
+            }
+            let codes = get_codes_schema_name(&spec, &error)?;
+            let components = spec.components.get_or_insert_with(Default::default);
+            components
+                .schemas
+                .insert(codes.clone(), openapiv3::ReferenceOr::Item(*schema));
+            if let Some(openapiv3::ReferenceOr::Item(openapiv3::Schema {
+                schema_kind: openapiv3::SchemaKind::Type(openapiv3::Type::Object(o)),
+                ..
+            })) = components.schemas.get_mut(&error)
+            {
+                o.properties.insert(
+                    PROPERTY.to_string(),
+                    openapiv3::ReferenceOr::Reference {
+                        reference: format!("#/components/schemas/{}", codes),
+                    },
+                );
+            }
+        }
+    }
+
+    if let Some(error_codes) = find(&spec)? {
+        if let Some(openapiv3::ReferenceOr::Item(codes)) = spec
+            .components
+            .as_mut()
+            .and_then(|components| components.schemas.get_mut(&error_codes.codes))
+        {
+            codes
+                .schema_data
+                .extensions
+                .insert(CATCH_ALL.to_string(), serde_json::Value::Bool(true));
+        }
+    }
+
+    Ok(spec)
+}
+
+/// The name of the schema of an enum of codes moved out of its field, `ErrorCode`
+/// unless the spec has it already.
+fn get_codes_schema_name(spec: &openapiv3::OpenAPI, error: &str) -> Result<String> {
+    let schemas = spec
+        .components
+        .as_ref()
+        .map(|components| &components.schemas);
+    for name in ["ErrorCode".to_string(), format!("{}ErrorCode", error)] {
+        if !schemas.is_some_and(|schemas| schemas.contains_key(&name)) {
+            return Ok(name);
+        }
+    }
+    anyhow::bail!(
+        "the `{}` of `{}` needs a schema of its own, but `ErrorCode` and `{}ErrorCode` are taken",
+        PROPERTY,
+        error,
+        error
+    )
+}
+
+/// The schemas of the JSON bodies of the error responses of the operations, the
+/// `default` ones and the ones with a `4xx` or `5xx` status, in order.
+fn get_error_schemas(spec: &openapiv3::OpenAPI) -> Result<Vec<String>> {
+    let mut schemas = Vec::new();
+    for (_, item) in spec.paths.iter() {
+        for (_, op) in item.item()?.iter() {
+            let responses = op
+                .responses
+                .responses
+                .iter()
+                .filter(|(status, _)| is_error_status(status))
+                .map(|(_, response)| response)
+                .chain(op.responses.default.as_ref());
+            for response in responses {
+                let response = response.expand(spec)?;
+                let Some(openapiv3::ReferenceOr::Reference { reference }) = response
+                    .content
+                    .get("application/json")
+                    .and_then(|content| content.schema.as_ref())
+                else {
+                    continue;
+                };
+                let name = reference
+                    .trim_start_matches("#/components/schemas/")
+                    .to_string();
+                if !schemas.contains(&name) {
+                    schemas.push(name);
+                }
+            }
+        }
+    }
+    Ok(schemas)
+}
+
+fn is_error_status(status: &openapiv3::StatusCode) -> bool {
+    match status {
+        openapiv3::StatusCode::Code(code) => *code >= 400,
+        openapiv3::StatusCode::Range(range) => *range >= 4,
+    }
+}
+
+fn get_schema(spec: &openapiv3::OpenAPI, name: &str) -> Result<openapiv3::Schema> {
+    spec.components
+        .as_ref()
+        .and_then(|components| components.schemas.get(name))
+        .ok_or_else(|| anyhow::anyhow!("schema `{}` not found in components", name))?
+        .expand(spec)
+}
+
+/// The `error_code` field of an error schema, if it is an object with one.
+fn get_code_property(
+    spec: &openapiv3::OpenAPI,
+    error: &str,
+) -> Result<Option<openapiv3::ReferenceOr<Box<openapiv3::Schema>>>> {
+    let openapiv3::SchemaKind::Type(openapiv3::Type::Object(o)) =
+        get_schema(spec, error)?.schema_kind
+    else {
+        return Ok(None);
+    };
+    Ok(o.properties.get(PROPERTY).cloned())
+}
+
+/// Returns `true` if the schema is a string enum, or a `oneOf` of strings with one
+/// value each, which we render as one too.
+fn is_enum(spec: &openapiv3::OpenAPI, schema: &openapiv3::Schema) -> Result<bool> {
+    Ok(match &schema.schema_kind {
+        openapiv3::SchemaKind::Type(openapiv3::Type::String(s)) => !s.enumeration.is_empty(),
+        openapiv3::SchemaKind::OneOf { one_of } if !one_of.is_empty() => {
+            for one_of in one_of {
+                let schema = one_of.get_schema_from_reference(spec, true)?;
+                if !matches!(
+                    &schema.schema_kind,
+                    openapiv3::SchemaKind::Type(openapiv3::Type::String(s)) if s.enumeration.len() == 1
+                ) {
+                    return Ok(false);
+                }
+            }
+            true
+        }
+        _ => false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::ErrorCodes;
+
+    #[test]
+    fn test_error_codes() {
+        // The codes are a `oneOf` in a schema of their own.
+        let spec =
+            crate::load_json_spec(include_str!("../tests/types/input/error-codes.json")).unwrap();
+        let error_codes = ErrorCodes {
+            error: "Error".to_string(),
+            codes: "ErrorCode".to_string(),
+        };
+        assert_eq!(super::find(&spec).unwrap(), Some(error_codes.clone()));
+        let spec = super::prepare(&spec).unwrap();
+        assert_eq!(super::find(&spec).unwrap(), Some(error_codes.clone()));
+
+        let mut type_space = crate::types::TypeSpace {
+            types: indexmap::map::IndexMap::new(),
+            spec: spec.clone(),
+            rendered: quote::quote!(),
+            opts: Default::default(),
+        };
+        for (name, schema) in &spec.components.as_ref().unwrap().schemas {
+            type_space
+                .render_schema(name, schema.as_item().unwrap())
+                .unwrap();
+        }
+        expectorate::assert_contents(
+            "tests/types/error-codes.rs.gen",
+            &crate::types::get_text_fmt(&type_space.rendered).unwrap(),
+        );
+
+        // The codes are in the field, they move to a schema of their own.
+        let spec = crate::load_json_spec(include_str!("../tests/mock.json")).unwrap();
+        assert_eq!(super::find(&spec).unwrap(), None);
+        let spec = super::prepare(&spec).unwrap();
+        assert_eq!(super::find(&spec).unwrap(), Some(error_codes));
+        let codes = spec.components.as_ref().unwrap().schemas["ErrorCode"]
+            .as_item()
+            .unwrap();
+        assert!(super::is_catch_all(&codes.schema_data));
+
+        // The codes are strings, there is no enum.
+        let spec = crate::load_json_spec(include_str!("../../spec.json")).unwrap();
+        assert_eq!(super::find(&spec).unwrap(), None);
+        assert_eq!(super::prepare(&spec).unwrap(), spec);
+    }
+}
@@ -0,0 +1,415 @@
+//! The schemas and the operations we could not generate.
+//!
+//! One bad schema used to stop the generation, and hide every other bad one until
+//! it was fixed. The types and the functions now go on past a failure and we
+//! report all of them at once, with what depends on them: a component that
+//! references a failed one, and an operation that references either, fail too.
+//!
+//! With `--keep-going` the failures are warnings instead, they are left out of the
+//! spec and the library is generated from what is left, with the list of what was
+//! left out in `failures.json`.
+
+use std::collections::BTreeSet;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+/// The version of the report format, bump this if the format changes in a way
+/// consumers would notice.
+pub const FAILURES_VERSION: u32 = 1;
+
+/// The sections of the components that can reference one another.
+const SECTIONS: &[&str] = &[
+    "schemas",
+    "parameters",
+    "responses",
+    "requestBodies",
+    "headers",
+];
+
+/// What we could not generate.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Item {
+    /// A component of the spec, by its reference like `#/components/schemas/Thing`.
+    Component {
+        /// The reference of the component.
+        reference: String,
+    },
+    /// An operation of the spec.
+    Operation {
+        /// The method, like `GET`.
+        method: String,
+        /// The path template, like `/things/{id}`.
+        path: String,
+        /// The id of the operation, if it has one.
+        operation_id: Option<String>,
+    },
+}
+
+impl std::fmt::Display for Item {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Item::Component { reference } => write!(f, "`{}`", reference),
+            Item::Operation {
+                method,
+                path,
+                operation_id: Some(operation_id),
+            } => write!(f, "{} {} (`{}`)", method, path, operation_id),
+            Item::Operation {
+                method,
+                path,
+                operation_id: None,
+            } => write!(f, "{} {}", method, path),
+        }
+    }
+}
+
+/// A schema or an operation we could not generate, and why.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Failure {
+    /// What failed.
+    #[serde(flatten)]
+    pub item: Item,
+    /// Why, the error of the generation or the failure it depends on.
+    pub error: String,
+}
+
+/// Every failure of a generation.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Failures {
+    /// The failures, in the order we ran into them.
+    pub failures: Vec<Failure>,
+    /// The references of the components that failed, or depend on one that did.
+    dropped: BTreeSet<String>,
+}
+
+/// The report of `--keep-going`, saved as `failures.json`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Report {
+    /// The version of the report format, see `FAILURES_VERSION`.
+    pub failures_version: u32,
+    /// What was left out of the library.
+    pub failures: Vec<Failure>,
+}
+
+impl std::fmt::Display for Failures {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} schemas and operations could not be generated:",
+            self.failures.len()
+        )?;
+        for failure in &self.failures {
+            write!(f, "\n  - {}: {}", failure.item, failure.error)?;
+        }
+        Ok(())
+    }
+}
+
+impl Failures {
+    /// If nothing failed.
+    pub fn is_empty(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    /// Record that the component at `reference` failed to generate.
+    pub fn component(&mut self, reference: &str, error: &anyhow::Error) {
+        self.dropped.insert(reference.to_string());
+        self.failures.push(Failure {
+            item: Item::Component {
+                reference: reference.to_string(),
+            },
+            error: format!("{:#}", error),
+        });
+    }
+
+    /// Record that an operation failed to generate.
+    pub fn operation(
+        &mut self,
+        path: &str,
+        method: &http::Method,
+        op: &openapiv3::Operation,
+        error: &anyhow::Error,
+    ) {
+        self.failures.push(Failure {
+            item: Item::Operation {
+                method: method.to_string(),
+                path: path.to_string(),
+                operation_id: op.operation_id.clone(),
+            },
+            error: format!("{:#}", error),
+        });
+    }
+
+    /// Record the components that reference a failed one, and the ones that
+    /// reference those, until none is left.
+    pub fn add_dependents(&mut self, spec: &openapiv3::OpenAPI) -> Result<()> {
+        let Some(components) = &spec.components else {
+            return Ok(());
+        };
+        let components = serde_json::to_value(components)?;
+
+        let mut references = Vec::new();
+        for section in SECTIONS {
+            let Some(items) = components.get(section).and_then(|items| items.as_object()) else {
+                continue;
+            };
+            for (name, item) in items {
+                references.push((format!("#/components/{}/{}", section, name), item));
+            }
+        }
+
+        loop {
+            let mut changed = false;
+            for (reference, item) in &references {
+                if self.dropped.contains(reference) {
+                    continue;
+                }
+                if let Some(dependency) = self.dropped_reference(item) {
+                    let error = anyhow::anyhow!(
+                        "depends on `{}`, which could not be generated",
+                        dependency
+                    );
+                    self.component(reference, &error);
+                    changed = true;
+                }
+            }
+            if !changed {
+                return Ok(());
+            }
+        }
+    }
+
+    /// The failed component an operation references, with the parameters of its
+    /// path, if any.
+    pub fn dropped_dependency(
+        &self,
+        op: &openapiv3::Operation,
+        path_params: &[openapiv3::ReferenceOr<openapiv3::Parameter>],
+    ) -> Result<Option<String>> {
+        if self.dropped.is_empty() {
+            return Ok(None);
+        }
+        Ok(self
+            .dropped_reference(&serde_json::to_value(op)?)
+            .or_else(|| {
+                serde_json::to_value(path_params)
+                    .ok()
+                    .and_then(|params| self.dropped_reference(&params))
+            }))
+    }
+
+    /// The first `$ref` of `value` to a failed component.
+    fn dropped_reference(&self, value: &serde_json::Value) -> Option<String> {
+        match value {
+            serde_json::Value::Object(object) => {
+                object
+                    .iter()
+                    .find_map(|(key, value)| match (key.as_str(), value) {
+                        ("$ref", serde_json::Value::String(reference))
+                            if self.dropped.contains(reference) =>
+                        {
+                            Some(reference.to_string())
+                        }
+                        _ => self.dropped_reference(value),
+                    })
+            }
+            serde_json::Value::Array(values) => values
+                .iter()
+                .find_map(|value| self.dropped_reference(value)),
+            _ => None,
+        }
+    }
+
+    /// Fail with every failure, if there are any.
+    pub fn into_result(self) -> Result<()> {
+        if self.is_empty() {
+            return Ok(());
+        }
+        anyhow::bail!("{}", self)
+    }
+
+    /// The report of the failures, for `failures.json`.
+    pub fn report(&self) -> Report {
+        Report {
+            failures_version: FAILURES_VERSION,
+            failures: self.failures.clone(),
+        }
+    }
+
+    /// The spec without the components and the operations that failed, a path
+    /// left without operations goes too.
+    pub fn prune(&self, spec: &openapiv3::OpenAPI) -> Result<openapiv3::OpenAPI> {
+        let mut spec = spec.clone();
+
+        if let Some(components) = &mut spec.components {
+            let dropped = |section: &str, name: &str| {
+                self.dropped
+                    .contains(&format!("#/components/{}/{}", section, name))
+            };
+            components
+                .schemas
+                .retain(|name, _| !dropped("schemas", name));
+            components
+                .parameters
+                .retain(|name, _| !dropped("parameters", name));
+            components
+                .responses
+                .retain(|name, _| !dropped("responses", name));
+            components
+                .request_bodies
+                .retain(|name, _| !dropped("requestBodies", name));
+            components
+                .headers
+                .retain(|name, _| !dropped("headers", name));
+        }
+
+        for failure in &self.failures {
+            let Item::Operation { method, path, .. } = &failure.item else {
+                continue;
+            };
+            let Some(openapiv3::ReferenceOr::Item(item)) = spec.paths.paths.get_mut(path) else {
+                continue;
+            };
+            match method.as_str() {
+                "GET" => item.get = None,
+                "PUT" => item.put = None,
+                "POST" => item.post = None,
+                "DELETE" => item.delete = None,
+                "HEAD" => item.head = None,
+                "PATCH" => item.patch = None,
+                "OPTIONS" => item.options = None,
+                "TRACE" => item.trace = None,
+                _ => {}
+            }
+        }
+        spec.paths.paths.retain(|_, item| match item {
+            openapiv3::ReferenceOr::Item(item) => item.iter().next().is_some(),
+            openapiv3::ReferenceOr::Reference { .. } => true,
+        });
+
+        Ok(spec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Item;
+
+    fn load() -> openapiv3::OpenAPI {
+        serde_json::from_str(include_str!("../tests/types/input/failures.json")).unwrap()
+    }
+
+    /// Generate the types and the functions of `spec`, with every failure.
+    fn generate(spec: &openapiv3::OpenAPI) -> super::Failures {
+        let mut failures = super::Failures::default();
+        let mut type_space =
+            crate::types::generate_types_collecting(spec, Default::default(), &mut failures)
+                .unwrap();
+        crate::functions::generate_files_collecting(
+            &mut type_space,
+            &Default::default(),
+            &mut failures,
+        )
+        .unwrap();
+        failures
+    }
+
+    #[test]
+    fn test_failures() {
+        let failures = generate(&load());
+
+        let items = failures
+            .failures
+            .iter()
+            .map(|failure| failure.item.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            items,
+            vec![
+                "`#/components/schemas/Negated`",
+                "`#/components/schemas/Unit`",
+                "`#/components/schemas/Measurement`",
+                "GET /measurements/{id} (`get_measurement`)",
+                "POST /negations (`create_negation`)",
+            ]
+        );
+        assert_eq!(
+            failures.failures[2].error,
+            "depends on `#/components/schemas/Unit`, which could not be generated"
+        );
+        assert_eq!(
+            failures.failures[3].error,
+            "depends on `#/components/schemas/Measurement`, which could not be generated"
+        );
+
+        // Without `--keep-going` the error lists them all.
+        let error = failures.clone().into_result().unwrap_err().to_string();
+        assert!(
+            error.starts_with("5 schemas and operations could not be generated:"),
+            "{}",
+            error
+        );
+        for item in &items {
+            assert!(error.contains(item.as_str()), "{}", error);
+        }
+
+        // The types alone fail with every failed schema.
+        let error = crate::types::generate_types(&load(), Default::default())
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.contains("`#/components/schemas/Negated`"),
+            "{}",
+            error
+        );
+        assert!(error.contains("`#/components/schemas/Unit`"), "{}", error);
+
+        let report = serde_json::to_value(failures.report()).unwrap();
+        assert_eq!(report["failures_version"], super::FAILURES_VERSION);
+        assert_eq!(
+            report["failures"][3],
+            serde_json::json!({
+                "kind": "operation",
+                "method": "GET",
+                "path": "/measurements/{id}",
+                "operation_id": "get_measurement",
+                "error": "depends on `#/components/schemas/Measurement`, which could not be generated",
+            })
+        );
+    }
+
+    #[test]
+    fn test_failures_prune() {
+        let spec = load();
+        let failures = generate(&spec);
+        let pruned = failures.prune(&spec).unwrap();
+
+        // What is left generates, without a trace of what failed.
+        let pruned_failures = generate(&pruned);
+        assert!(pruned_failures.is_empty(), "{}", pruned_failures);
+        let mut type_space = crate::types::generate_types(&pruned, Default::default()).unwrap();
+        let (files, _, operations) =
+            crate::functions::generate_files(&mut type_space, &Default::default()).unwrap();
+        let types = type_space.render().unwrap();
+        assert!(types.contains("pub struct Thing"));
+        for name in ["Negated", "Unit", "Measurement"] {
+            assert!(!types.contains(&format!(" {} {{", name)), "{}", name);
+        }
+        assert_eq!(
+            operations
+                .iter()
+                .map(|operation| operation.function.as_str())
+                .collect::<Vec<_>>(),
+            vec!["get"]
+        );
+        assert_eq!(files.len(), 1);
+        assert!(!pruned.paths.paths.contains_key("/negations"));
+
+        assert!(matches!(
+            &failures.failures[0].item,
+            Item::Component { reference } if reference == "#/components/schemas/Negated"
+        ));
+    }
+}
@@ -1,7 +1,7 @@
 //! Utilities for generating rust functions from an OpenAPI spec.
 
 use std::{
-    collections::{BTreeMap, HashMap},
+    collections::{BTreeMap, BTreeSet, HashMap},
     fmt::Write as _,
 };
 
@@ -9,11 +9,11 @@ use anyhow::Result;
 use proc_macro2::TokenStream;
 
 use crate::types::{
+    docs::sanitize_description,
     exts::{
         OperationExt, ParameterSchemaOrContentExt, ReferenceOrExt, SchemaRenderExt, StatusCodeExt,
         TokenStreamExt,
     },
-    sanitize_indents,
 };
 
 /// Returns example
@@ -43,31 +43,40 @@ fn generate_websocket_fn(
         quote!(,#(#a),*)
     };
 
-    let url_path = name.trim_start_matches('/');
+    let url_path = get_url_path(&type_space.spec, name, method)?;
     let method_ident = format_ident!("{}", method.to_string());
 
     // Let's get the path parameters.
     let path_params = get_path_params(type_space, op, global_params)?;
-    let clean_url = clean_url_from(&path_params)?;
+    let date_time_format = type_space.opts.date_time_format.is_some();
+    let clean_url = clean_url_from(&path_params, date_time_format)?;
 
     // Let's get the query parameters.
     let query_params = get_query_params(type_space, op, global_params)?;
-    let query_params_code = gen_query_params_code(&query_params, false)?;
+    let query_params_code = gen_query_params_code(&query_params, false, date_time_format)?;
+
+    // Let's get the header and cookie parameters.
+    let header_params_code = gen_header_params_code(
+        &get_header_params(type_space, op, global_params)?,
+        &get_exploded_params(&get_header_params_schema(
+            op,
+            &type_space.spec,
+            global_params,
+        )?),
+    )?;
+    let cookie_params_code = gen_cookie_params_code(
+        &get_cookie_params(type_space, op, global_params)?,
+        &get_exploded_params(&get_cookie_params_schema(
+            op,
+            &type_space.spec,
+            global_params,
+        )?),
+    )?;
 
     let auth_code = generate_auth_code(opts)?;
 
     let websocket_headers = quote! {
-        req = req
-            .header(reqwest::header::CONNECTION, "Upgrade")
-            .header(reqwest::header::UPGRADE, "websocket")
-            .header(reqwest::header::SEC_WEBSOCKET_VERSION, "13")
-            .header(
-                reqwest::header::SEC_WEBSOCKET_KEY,
-                base64::Engine::encode(
-                    &base64::engine::general_purpose::STANDARD,
-                    rand::random::<[u8; 16]>(),
-                ),
-            );
+        req = req.headers(crate::Client::websocket_handshake_headers());
     };
 
     let function_body = quote! {
@@ -80,42 +89,149 @@ fn generate_websocket_fn(
 
         #query_params_code
 
+        #header_params_code
+
+        #cookie_params_code
+
         #websocket_headers
 
-        let resp = req.send().await?;
+        let resp = req.send().await.map_err(crate::Client::send_error)?;
+        self.client.record_response(resp.headers());
         if resp.status().is_client_error() || resp.status().is_server_error() {
-            return Err(crate::types::error::Error::UnexpectedResponse(resp));
+            return Err(crate::types::error::Error::UnexpectedResponse(
+                crate::types::error::ApiResponse::new(resp),
+            ));
         }
 
         let headers = resp.headers().clone();
         // TODO: This isn't really a request error, but the response was already consumed.
         // So we can't use Error::UnexpectedResponse.
-        let upgraded = resp.upgrade().await.map_err(crate::types::error::Error::RequestError)?;
+        let upgraded = resp.upgrade().await.map_err(crate::types::error::Error::from)?;
         Ok((upgraded, headers))
     };
 
+    // The managed connection needs to open the connection again when it reconnects,
+    // so it owns its arguments.
+    let builder_fn_name_ident = format_ident!("{}_builder", fn_name);
+    let builder_docs = format!(
+        "Build a managed connection to `{}`, that answers pings and can reconnect.\n\nSee \
+         [`Self::{}`] for the arguments and the low-level connection.",
+        name, fn_name
+    );
+    let mut owned_args = Vec::new();
+    let mut to_owned_args = Vec::new();
+    let mut clone_args = Vec::new();
+    let mut call_args = Vec::new();
+    for (k, v) in raw_args.iter() {
+        let n = format_ident!("{}", crate::types::clean_property_name(k));
+        if v.to_string() == quote!(&'a str).to_string() {
+            owned_args.push(quote!(#n: String));
+            call_args.push(quote!(&#n));
+        } else if is_display_string_param(v)? {
+            owned_args.push(quote!(#n: #v));
+            to_owned_args.push(quote!(let #n = #n.to_string();));
+            call_args.push(quote!(&#n));
+        } else if v.to_string() == quote!(Option<&'a str>).to_string() {
+            owned_args.push(quote!(#n: Option<&str>));
+            to_owned_args.push(quote!(let #n = #n.map(str::to_string);));
+            call_args.push(quote!(#n.as_deref()));
+        } else if let Some(item) = get_slice_param_item(v)? {
+            if v.is_option()? {
+                owned_args.push(quote!(#n: Option<Vec<#item>>));
+                call_args.push(quote!(#n.as_deref()));
+            } else {
+                owned_args.push(quote!(#n: Vec<#item>));
+                call_args.push(quote!(&#n));
+            }
+        } else {
+            owned_args.push(quote!(#n: #v));
+            call_args.push(quote!(#n));
+        }
+        clone_args.push(quote!(let #n = #n.clone();));
+    }
+
+    let doc_aliases = get_fn_doc_aliases(name, op, &fn_name);
+
     let function = quote! {
         #[doc = #docs]
+        #doc_aliases
         #[tracing::instrument]
         #[cfg(not(target_arch = "wasm32"))]
         pub async fn #fn_name_ident<'a>(&'a self #args) -> Result<#response_type, crate::types::error::Error> {
             #function_body
         }
+
+        #[doc = #builder_docs]
+        #[cfg(feature = "websocket")]
+        #[cfg(not(target_arch = "wasm32"))]
+        pub fn #builder_fn_name_ident(&self #(, #owned_args)*) -> crate::types::websocket::WebSocketBuilder {
+            let this = self.clone();
+            #(#to_owned_args)*
+            crate::types::websocket::WebSocketBuilder::new(move || {
+                let this = this.clone();
+                #(#clone_args)*
+                Box::pin(async move { this.#fn_name_ident(#(#call_args),*).await })
+            })
+        }
     };
 
     // TODO: Build actual example
     Ok((function, Default::default()))
 }
 
-/// Generate functions for each path operation.
+/// Return the aliases of the function of an operation, so the docs search finds it
+/// by its `operationId` and by its path.
+fn get_fn_doc_aliases(
+    name: &str,
+    op: &openapiv3::Operation,
+    fn_name: &str,
+) -> proc_macro2::TokenStream {
+    let operation_id = match &op.operation_id {
+        Some(operation_id) => crate::types::get_doc_alias(operation_id, fn_name),
+        None => quote!(),
+    };
+    let path = crate::types::get_doc_alias(&format!("/{}", name.trim_matches('/')), fn_name);
+
+    quote! {
+        #operation_id
+        #path
+    }
+}
+
+/// Generate functions for each path operation, the error lists every operation
+/// that failed.
 pub fn generate_files(
     type_space: &mut crate::types::TypeSpace,
     opts: &crate::Opts,
 ) -> Result<(
     BTreeMap<String, proc_macro2::TokenStream>,
     openapiv3::OpenAPI,
+    Vec<OperationInfo>,
+)> {
+    let mut failures = crate::failures::Failures::default();
+    let generated = generate_files_collecting(type_space, opts, &mut failures)?;
+    failures.into_result()?;
+    Ok(generated)
+}
+
+/// Generate functions for each path operation, going on past the operations that
+/// fail, or reference a component in `failures`, and adding them to `failures`.
+pub fn generate_files_collecting(
+    type_space: &mut crate::types::TypeSpace,
+    opts: &crate::Opts,
+    failures: &mut crate::failures::Failures,
+) -> Result<(
+    BTreeMap<String, proc_macro2::TokenStream>,
+    openapiv3::OpenAPI,
+    Vec<OperationInfo>,
 )> {
+    // Name the functions with the casing of the options.
+    let _casing = crate::types::casing::scoped(opts);
+
+    check_fn_name_overrides(&type_space.spec)?;
+
     let mut tag_files: BTreeMap<String, proc_macro2::TokenStream> = Default::default();
+    let mut operations: Vec<OperationInfo> = Default::default();
 
     // Make a spec we can modify for the docs.
     let mut new_spec = type_space.spec.clone();
@@ -138,10 +254,15 @@ pub fn generate_files(
             };
 
             let tag = op.get_tag()?;
-            let example = if op.extensions.contains_key("x-dropshot-websocket") {
+            let example = if op.is_websocket() {
                 let (function, example) =
                     generate_websocket_fn(type_space, name, method, op, global_params, opts)?;
                 add_fn_to_tag(&mut tag_files, &tag, &function)?;
+                operations.push(OperationInfo {
+                    streaming: true,
+                    response_type: Some(quote!((reqwest::Upgraded, http::HeaderMap)).rendered()?),
+                    ..OperationInfo::new(name, method, op, op.get_fn_name()?)?
+                });
                 example
             } else {
                 // Get the docs.
@@ -153,13 +274,7 @@ pub fn generate_files(
 
                 // Get the response for the function.
                 let response_type =
-                    if let Some(response) = get_response_type(type_space, name, method, op)? {
-                        let t = response.type_name;
-                        quote!(#t)
-                    } else {
-                        // We don't have a response, so we'll return `()`.
-                        quote!(())
-                    };
+                    get_fn_response_type(type_space, name, method, op, global_params)?;
 
                 // Get the function args.
                 let raw_args = get_args(name, method, type_space, op, global_params)?;
@@ -175,15 +290,21 @@ pub fn generate_files(
                 };
 
                 // Get the request body for the function if there is one.
-                let request_body = if let Some(rb) = get_request_body(type_space, name, method, op)?
-                {
-                    let t = rb.type_name;
-
-                    if is_multipart(type_space, name, method, op)? && !multipart_has_body(&t)? {
-                        // We don't have a request body, so we'll return nothing.
-                        quote!()
+                let request_type = match get_request_body(type_space, name, method, op)? {
+                    Some(rb)
+                        if !is_multipart(type_space, name, method, op)?
+                            || multipart_has_body(&rb.type_name)? =>
+                    {
+                        Some(rb.type_name)
+                    }
+                    // We don't have a request body.
+                    _ => None,
+                };
+                let request_body = if let Some(t) = &request_type {
+                    // We add the comma at the front, so it works.
+                    if is_optional_request_body(&type_space.spec, op)? {
+                        quote!(, body: Option<&#t>)
                     } else {
-                        // We add the comma at the front, so it works.
                         quote!(, body: &#t)
                     }
                 } else {
@@ -192,8 +313,34 @@ pub fn generate_files(
                 };
 
                 // Get the function body.
-                let function_body =
-                    get_function_body(type_space, name, method, op, false, opts, global_params)?;
+                let function_body = get_function_body(
+                    type_space,
+                    name,
+                    method,
+                    op,
+                    RequestKind::Single,
+                    opts,
+                    global_params,
+                )?;
+
+                // If the operation can wait until it is done, generate a function that
+                // waits.
+                let wait_function = if get_wait_param(type_space, op, global_params)?.is_some() {
+                    let (wait_function, wait_response_type) = generate_wait_fn(
+                        type_space,
+                        name,
+                        method,
+                        op,
+                        &docs,
+                        &args,
+                        &request_body,
+                        opts,
+                        global_params,
+                    )?;
+                    Some((wait_function, wait_response_type))
+                } else {
+                    None
+                };
 
                 let example_code_fn = generate_example_code_fn(
                     type_space,
@@ -225,8 +372,19 @@ pub fn generate_files(
                     docs, rust_doc_example_code_fn
                 );
 
+                let doc_aliases = get_fn_doc_aliases(name, op, &fn_name);
+                let deprecated = get_deprecated_attr(op);
+                // The functions calling it are deprecated with it.
+                let allow_deprecated = if deprecated.is_empty() {
+                    quote!()
+                } else {
+                    quote!(#[allow(deprecated)])
+                };
+
                 let function = quote! {
                     #[doc = #docs]
+                    #doc_aliases
+                    #deprecated
                     #[tracing::instrument]
                     pub async fn #fn_name_ident<'a>(&'a self #args #request_body) -> Result<#response_type, crate::types::error::Error> {
                         #function_body
@@ -235,6 +393,38 @@ pub fn generate_files(
 
                 add_fn_to_tag(&mut tag_files, &tag, &function)?;
 
+                // Keep what we know about the function for the table of operations.
+                let paginated =
+                    get_pagination_properties(name, method, op, &type_space.spec)?.has_stream();
+                let request_type = request_type.map(|t| t.rendered()).transpose()?;
+                operations.push(OperationInfo {
+                    paginated,
+                    request_type: request_type.clone(),
+                    response_type: OperationInfo::type_name(&response_type)?,
+                    ..OperationInfo::new(name, method, op, fn_name.to_string())?
+                });
+                if let Some((wait_function, wait_response_type)) = wait_function {
+                    add_fn_to_tag(&mut tag_files, &tag, &wait_function)?;
+                    operations.push(OperationInfo {
+                        request_type,
+                        response_type: OperationInfo::type_name(&wait_response_type)?,
+                        ..OperationInfo::new(name, method, op, format!("{}_wait", fn_name))?
+                    });
+                }
+
+                // If the operation gets a resource by its id, get many at once.
+                if let Some(batch_function) = generate_batch_fn(
+                    type_space,
+                    name,
+                    method,
+                    op,
+                    &raw_args,
+                    &response_type,
+                    global_params,
+                )? {
+                    add_fn_to_tag(&mut tag_files, &tag, &batch_function)?;
+                }
+
                 // Let's pause here and update our spec with the new function.
                 // Add the docs to our spec.
                 // let new_operation = op.clone();
@@ -242,14 +432,16 @@ pub fn generate_files(
 
                 example.insert("example".to_string(), example_code_fn);
 
+                // The tag can be nested in other modules, see `modules`.
+                let module_map = crate::modules::ModuleMap::new(opts)?;
                 example.insert(
                     "libDocsLink".to_string(),
                     format!(
                         "https://docs.rs/{}/latest/{}/{}/struct.{}.html#method.{}",
                         opts.name,
                         opts.name,
-                        tag,
-                        crate::types::proper_name(&tag),
+                        module_map.path(&tag).join("/"),
+                        module_map.struct_name(&tag),
                         fn_name
                     ),
                 );
@@ -257,12 +449,12 @@ pub fn generate_files(
                 // Let's check if this function can be paginated.
                 let pagination_properties =
                     get_pagination_properties(name, method, op, &type_space.spec)?;
-                if pagination_properties.can_paginate() {
+                if pagination_properties.has_stream() {
                     // If we can paginate we should generate a paginated stream function.
                     let stream_fn_name_ident = format_ident!("{}_stream", fn_name);
 
                     // Get the inner args for the function.
-                    let page_param_str = pagination_properties.page_param_str()?;
+                    let page_param_str = pagination_properties.stream_page_param_str()?;
 
                     // Make sure if we have args, we start with a comma.
                     // Get the args again without the page param.
@@ -280,15 +472,23 @@ pub fn generate_files(
                         quote!(,#(#a),*)
                     };
 
+                    // The stream outlives the call, so it owns the strings taken as
+                    // `impl std::fmt::Display`, and every page gets a copy.
+                    let mut owned_params = Vec::new();
                     let inner_args = if raw_args.is_empty() {
                         quote!()
                     } else {
                         let mut a = Vec::new();
-                        for (k, _v) in raw_args.iter() {
+                        for (k, v) in raw_args.iter() {
                             // Skip the next page arg.
                             if k != &page_param_str {
                                 let n = format_ident!("{}", k);
-                                a.push(quote!(#n))
+                                if is_display_string_param(v)? {
+                                    a.push(quote!(#n.clone()));
+                                    owned_params.push(n);
+                                } else {
+                                    a.push(quote!(#n))
+                                }
                             } else {
                                 // Make the arg none for our page parameter.
                                 a.push(quote!(None))
@@ -304,55 +504,194 @@ pub fn generate_files(
                         quote!(,body)
                     };
 
-                    let paginated_function_body =
-                        get_function_body(type_space, name, method, op, true, opts, global_params)?;
-
-                    let item_type = pagination_properties.item_type(false)?;
-
-                    let function = quote! {
-                        #[doc = #docs]
-                        #[tracing::instrument]
-                        #[cfg(not(feature = "js"))]
-                        pub fn #stream_fn_name_ident<'a>(&'a self #min_args #request_body) -> impl futures::Stream<Item = Result<#item_type, crate::types::error::Error>> + Unpin + '_  {
-                            use futures::{StreamExt, TryFutureExt, TryStreamExt};
-                            use crate::types::paginate::Pagination;
-
-                            // Get the result from our main function.
-                            self.#fn_name_ident(#inner_args #body_arg)
-                                .map_ok(move |result| {
-                                    let items = futures::stream::iter(result.items().into_iter().map(Ok));
-
-                                    // Get the next pages.
-                                    let next_pages = futures::stream::try_unfold(
-                                        (None, result),
-                                        move |(prev_page_token, new_result)| async move {
-                                            if new_result.has_more_pages() && !new_result.items().is_empty() && prev_page_token != new_result.next_page_token() {
-                                                // Get the next page, we modify the request directly,
-                                                // so that if we want to generate an API that uses
-                                                // Link headers or any other weird shit it works.
-                                                async {
-                                                    #paginated_function_body
-                                                }.map_ok(|result: #response_type| {
-                                                    Some((futures::stream::iter(
-                                                            result.items().into_iter().map(Ok),
-                                                        ),
-                                                        ( new_result.next_page_token(), result),
-                                                    ))
-                                                })
-                                                .await
-                                            } else {
-                                                // We have no more pages.
-                                                Ok(None)
-                                            }
-                                        }
-                                    )
-                                    .try_flatten();
+                    let function = if pagination_properties.link_header {
+                        let link_function_body = get_function_body(
+                            type_space,
+                            name,
+                            method,
+                            op,
+                            RequestKind::LinkPage,
+                            opts,
+                            global_params,
+                        )?;
 
-                                    items.chain(next_pages)
+                        // The response is the array of the items.
+                        let item_type: TokenStream = response_type
+                            .rendered()?
+                            .trim_start_matches("Vec<")
+                            .trim_end_matches('>')
+                            .parse()
+                            .map_err(|e| anyhow::anyhow!("{}", e))?;
+
+                        // The url of the `Link` header has the page, not our param.
+                        let page_param = pagination_properties.page_param.as_ref().map(|(k, t)| {
+                            let k = format_ident!("{}", crate::types::clean_property_name(k));
+                            quote!(let #k: #t = None;)
+                        });
+
+                        let next_page = quote! {
+                            async move {
+                                #(let #owned_params = #owned_params.as_str();)*
+                                #page_param
+                                let (page_link, pages) = match state {
+                                    Some(state) => state,
+                                    // We have no more pages.
+                                    None => return Ok(None),
+                                };
+
+                                // In case the server loops over more than one link.
+                                if page_link.is_some() && pages >= self.client.max_pages {
+                                    return Err(crate::types::error::Error::TooManyPages {
+                                        max_pages: self.client.max_pages,
+                                        next_page_token: page_link,
+                                    });
+                                }
+
+                                // Get the page, the first one with the arguments and
+                                // the next ones at the url of the `Link` header.
+                                async {
+                                    #link_function_body
+                                }.map_ok(|(result, next_page_link): (#response_type, Option<String>)| {
+                                    let next_page = match next_page_link {
+                                        Some(link) if !result.is_empty() && page_link.as_ref() != Some(&link) => {
+                                            Some((Some(link), pages + 1))
+                                        }
+                                        _ => None,
+                                    };
+                                    Some((futures::stream::iter(result.into_iter().map(Ok)), next_page))
                                 })
-                                .try_flatten_stream()
+                                .await
+                            }
+                        };
+                        let next_page = if owned_params.is_empty() {
+                            next_page
+                        } else {
+                            quote!({
+                                #(let #owned_params = #owned_params.clone();)*
+                                #next_page
+                            })
+                        };
+
+                        quote! {
+                            #[doc = #docs]
+                            #deprecated
+                            #allow_deprecated
+                            #[tracing::instrument]
+                            #[cfg(not(feature = "js"))]
+                            pub fn #stream_fn_name_ident<'a>(&'a self #min_args #request_body) -> impl futures::Stream<Item = Result<#item_type, crate::types::error::Error>> + Unpin + '_  {
+                                use futures::{StreamExt, TryFutureExt, TryStreamExt};
+
+                                #(let #owned_params = #owned_params.to_string();)*
+
+                                // Get the pages one after the other, we stop when the
+                                // server links back to the page we just got, it would
+                                // loop forever.
+                                futures::stream::try_unfold(
+                                    Some((None, 0)),
+                                    move |state: Option<(Option<String>, usize)>| #next_page
+                                )
+                                .try_flatten()
                                 .boxed()
                             }
+                        }
+                    } else {
+                        let paginated_function_body = get_function_body(
+                            type_space,
+                            name,
+                            method,
+                            op,
+                            RequestKind::NextPage,
+                            opts,
+                            global_params,
+                        )?;
+
+                        let item_type = pagination_properties.item_type(false)?;
+
+                        // The stream only keeps the token of the next page, the items
+                        // are moved out of every page.
+                        let take_page = quote!({
+                            let next_page_token =
+                                if result.has_more_pages() && !result.items().is_empty() {
+                                    result.next_page_token()
+                                } else {
+                                    None
+                                };
+                            (
+                                next_page_token,
+                                futures::stream::iter(result.into_items().into_iter().map(Ok)),
+                            )
+                        });
+
+                        let next_page = quote! {
+                            async move {
+                                #(let #owned_params = #owned_params.as_str();)*
+                                let next_page_token = match next_page_token {
+                                    Some(token) if prev_page_token.as_ref() != Some(&token) => token,
+                                    // We have no more pages.
+                                    _ => return Ok(None),
+                                };
+
+                                // In case the server loops over more than one token.
+                                if pages >= self.client.max_pages {
+                                    return Err(crate::types::error::Error::TooManyPages {
+                                        max_pages: self.client.max_pages,
+                                        next_page_token: Some(next_page_token),
+                                    });
+                                }
+
+                                // Get the next page, we modify the request directly,
+                                // so that if we want to generate an API that uses
+                                // Link headers or any other weird shit it works.
+                                async {
+                                    #paginated_function_body
+                                }.map_ok(|result: #response_type| {
+                                    let (token, items) = #take_page;
+                                    Some((items, (Some(next_page_token.clone()), token, pages + 1)))
+                                })
+                                .await
+                            }
+                        };
+                        let next_page = if owned_params.is_empty() {
+                            next_page
+                        } else {
+                            quote!({
+                                #(let #owned_params = #owned_params.clone();)*
+                                #next_page
+                            })
+                        };
+
+                        quote! {
+                            #[doc = #docs]
+                            #deprecated
+                            #allow_deprecated
+                            #[tracing::instrument]
+                            #[cfg(not(feature = "js"))]
+                            pub fn #stream_fn_name_ident<'a>(&'a self #min_args #request_body) -> impl futures::Stream<Item = Result<#item_type, crate::types::error::Error>> + Unpin + '_  {
+                                use futures::{StreamExt, TryFutureExt, TryStreamExt};
+                                use crate::types::paginate::Pagination;
+
+                                #(let #owned_params = #owned_params.to_string();)*
+
+                                // Get the result from our main function.
+                                self.#fn_name_ident(#inner_args #body_arg)
+                                    .map_ok(move |result| {
+                                        #(let #owned_params = #owned_params.clone();)*
+                                        let (next_page_token, items) = #take_page;
+
+                                        // Get the next pages, we stop when the server gives us
+                                        // back the token we just used, it would loop forever.
+                                        let next_pages = futures::stream::try_unfold(
+                                            (None, next_page_token, 1),
+                                            move |(prev_page_token, next_page_token, pages): (Option<String>, Option<String>, usize)| #next_page
+                                        )
+                                        .try_flatten();
+
+                                        items.chain(next_pages)
+                                    })
+                                    .try_flatten_stream()
+                                    .boxed()
+                                }
+                        }
                     };
 
                     add_fn_to_tag(&mut tag_files, &tag, &function)?;
@@ -365,6 +704,23 @@ pub fn generate_files(
             new_operation
                 .extensions
                 .insert("x-rust".to_string(), serde_json::json!(example));
+            // Record the parameter types we had to name after the operation, so they
+            // are easy to find.
+            let renamed_params = get_renamed_param_types(type_space, op, global_params)?;
+            if !renamed_params.is_empty() {
+                for (param, t_name) in &renamed_params {
+                    log::info!(
+                        "the type of parameter `{}` of `{}` is `{}`, another schema has its name",
+                        param,
+                        op.get_fn_name()?,
+                        t_name
+                    );
+                }
+                new_operation.extensions.insert(
+                    "x-rust-renamed-params".to_string(),
+                    serde_json::json!(renamed_params),
+                );
+            }
             match method.clone() {
                 http::Method::GET => {
                     new_path.get = Some(new_operation);
@@ -381,6 +737,15 @@ pub fn generate_files(
                 http::Method::DELETE => {
                     new_path.delete = Some(new_operation);
                 }
+                http::Method::HEAD => {
+                    new_path.head = Some(new_operation);
+                }
+                http::Method::OPTIONS => {
+                    new_path.options = Some(new_operation);
+                }
+                http::Method::TRACE => {
+                    new_path.trace = Some(new_operation);
+                }
                 _ => {}
             }
             new_spec.paths.paths.insert(
@@ -391,217 +756,1753 @@ pub fn generate_files(
             Ok(())
         };
 
-        gen(
-            name.as_str(),
-            &http::Method::GET,
-            op.get.as_ref(),
-            &op.parameters,
-        )?;
-        gen(
-            name.as_str(),
-            &http::Method::PUT,
-            op.put.as_ref(),
-            &op.parameters,
-        )?;
-        gen(
-            name.as_str(),
-            &http::Method::POST,
-            op.post.as_ref(),
-            &op.parameters,
-        )?;
-        gen(
-            name.as_str(),
-            &http::Method::DELETE,
-            op.delete.as_ref(),
-            &op.parameters,
-        )?;
-        gen(
-            name.as_str(),
-            &http::Method::HEAD,
-            op.head.as_ref(),
-            &op.parameters,
-        )?;
-        gen(
-            name.as_str(),
-            &http::Method::PATCH,
-            op.patch.as_ref(),
-            &op.parameters,
-        )?;
-        gen(
-            name.as_str(),
-            &http::Method::TRACE,
-            op.trace.as_ref(),
-            &op.parameters,
-        )?;
+        for (method, operation) in [
+            (http::Method::GET, op.get.as_ref()),
+            (http::Method::PUT, op.put.as_ref()),
+            (http::Method::POST, op.post.as_ref()),
+            (http::Method::DELETE, op.delete.as_ref()),
+            (http::Method::HEAD, op.head.as_ref()),
+            (http::Method::PATCH, op.patch.as_ref()),
+            (http::Method::OPTIONS, op.options.as_ref()),
+            (http::Method::TRACE, op.trace.as_ref()),
+        ] {
+            let Some(operation) = operation else {
+                continue;
+            };
+
+            // An operation that references a failed schema can't be generated either,
+            // and one that fails does not stop the others.
+            let result = match failures.dropped_dependency(operation, &op.parameters)? {
+                Some(dependency) => Err(anyhow::anyhow!(
+                    "depends on `{}`, which could not be generated",
+                    dependency
+                )),
+                None => gen(name.as_str(), &method, Some(operation), &op.parameters),
+            };
+            if let Err(err) = result {
+                failures.operation(name, &method, operation, &err);
+            }
+        }
+    }
+
+    generate_update_helpers(type_space, opts, &mut tag_files, &mut operations)?;
+    generate_poll_helpers(type_space, opts, &mut tag_files, &mut operations)?;
+    generate_upload_helpers(type_space, opts, &mut tag_files, &mut operations)?;
+
+    // The tag of the operations of the device flow gets a function running it.
+    if let Some(device_flow) = crate::device_flow::find(&type_space.spec, opts)? {
+        let function = crate::device_flow::render_function(&type_space.spec, &device_flow)?;
+        add_fn_to_tag(&mut tag_files, &device_flow.tag, &function)?;
+        let auth = type_space
+            .spec
+            .paths
+            .paths
+            .get(&device_flow.auth_path)
+            .and_then(|item| item.as_item())
+            .and_then(|item| item.post.as_ref())
+            .ok_or_else(|| anyhow::anyhow!("`POST {}` not found", device_flow.auth_path))?;
+        operations.push(OperationInfo {
+            response_type: Some("crate::device_flow::DeviceFlow".to_string()),
+            ..OperationInfo::new(
+                &device_flow.auth_path,
+                &http::Method::POST,
+                auth,
+                "device_flow".to_string(),
+            )?
+        });
     }
 
-    Ok((tag_files, new_spec))
+    Ok((tag_files, new_spec, operations))
 }
 
-/// Generate the docs for the given operation.
-fn generate_docs(
-    type_space: &mut crate::types::TypeSpace,
-    name: &str,
-    method: &http::Method,
-    op: &openapiv3::Operation,
-    global_params: &[openapiv3::ReferenceOr<openapiv3::Parameter>],
-) -> Result<String> {
-    let mut docs = if let Some(summary) = &op.summary {
-        summary.to_string()
-    } else {
-        format!("Perform a `{}` request to `{}`.", method, name)
-    };
+/// What we know about a function that sends a request, for the `operations` table
+/// of the generated crate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OperationInfo {
+    /// The name of the function.
+    pub function: String,
+    /// The tag of the operation, the module of the function.
+    pub tag: String,
+    /// The id of the operation, or the name of its function if it has none.
+    pub operation_id: String,
+    /// The method, like `GET`.
+    pub method: String,
+    /// The path template, like `/things/{id}`.
+    pub path: String,
+    /// If there is also a `_stream` function that goes through every page.
+    pub paginated: bool,
+    /// If it opens a websocket.
+    pub streaming: bool,
+    /// The type of the body the function sends, if any.
+    pub request_type: Option<String>,
+    /// The type the function returns, if not `()`.
+    pub response_type: Option<String>,
+}
 
-    if let Some(description) = &op.description {
-        docs.push_str("\n\n");
-        let description_sanitized = sanitize_indents(description, name.to_string());
-        docs.push_str(&description_sanitized.replace("```", "```ignore"));
+impl OperationInfo {
+    fn new(
+        path: &str,
+        method: &http::Method,
+        op: &openapiv3::Operation,
+        function: String,
+    ) -> Result<Self> {
+        Ok(OperationInfo {
+            tag: op.get_tag()?,
+            operation_id: match &op.operation_id {
+                Some(operation_id) => operation_id.to_string(),
+                None => op.get_fn_name()?,
+            },
+            method: method.to_string(),
+            path: path.to_string(),
+            function,
+            paginated: false,
+            streaming: false,
+            request_type: None,
+            response_type: None,
+        })
     }
 
-    // Document the params.
-    let mut params = get_path_params_schema(op, &type_space.spec, global_params)?;
-    params.append(&mut get_query_params_schema(
-        op,
-        &type_space.spec,
-        global_params,
-    )?);
-
-    let params_types = get_args(name, method, type_space, op, global_params)?;
-
-    if !params.is_empty() {
-        docs.push_str("\n\n**Parameters:**\n");
+    /// The name of a type, `None` for `()`.
+    fn type_name(t: &proc_macro2::TokenStream) -> Result<Option<String>> {
+        let rendered = t.rendered()?;
+        Ok(if rendered == "()" {
+            None
+        } else {
+            Some(rendered)
+        })
     }
-    for (name, (_schema, parameter_data)) in params {
-        // Get the type of the param.
-        let param_type = params_types.get(&name).ok_or_else(|| {
-            // This should not happen since both call the same functions.
-            anyhow::anyhow!(
-                "Could not find type for param `{}` in operation `{}`",
-                name,
-                name
-            )
-        })?;
-        let mut param_docs = format!(
-            "- `{}: {}`",
-            crate::types::clean_property_name(&name),
-            param_type.rendered()?
-        );
-        if let Some(description) = &parameter_data.description {
-            if !description.trim().is_empty() {
-                param_docs.push_str(": ");
-                let description_sanitized = sanitize_indents(description, "".to_string());
-                param_docs.push_str(&description_sanitized);
+}
+
+/// Render the `operations` function of the generated crate, with the table of every
+/// function that sends a request.
+pub fn render_operations(operations: &[OperationInfo]) -> proc_macro2::TokenStream {
+    let option = |t: &Option<String>| match t {
+        Some(t) => quote!(Some(#t)),
+        None => quote!(None),
+    };
+    let operations = operations.iter().map(|operation| {
+        let OperationInfo {
+            function,
+            tag,
+            operation_id,
+            method,
+            path,
+            paginated,
+            streaming,
+            request_type,
+            response_type,
+        } = operation;
+        let request_type = option(request_type);
+        let response_type = option(response_type);
+        quote! {
+            OperationInfo {
+                function: #function,
+                tag: #tag,
+                operation_id: #operation_id,
+                method: #method,
+                path: #path,
+                paginated: #paginated,
+                streaming: #streaming,
+                request_type: #request_type,
+                response_type: #response_type,
             }
         }
-        if parameter_data.required {
-            param_docs.push_str(" (required)");
+    });
+
+    quote! {
+        /// An API operation, and the function of the client that sends it.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub struct OperationInfo {
+            /// The name of the function, in the module of the tag.
+            pub function: &'static str,
+            /// The tag of the operation.
+            pub tag: &'static str,
+            /// The id of the operation.
+            pub operation_id: &'static str,
+            /// The method, like `GET`.
+            pub method: &'static str,
+            /// The path template, like `/things/{id}`.
+            pub path: &'static str,
+            /// If there is also a `_stream` function that goes through every page.
+            pub paginated: bool,
+            /// If it opens a websocket.
+            pub streaming: bool,
+            /// The type of the body the function sends, if any, like
+            /// `crate::types::Thing`.
+            pub request_type: Option<&'static str>,
+            /// The type the function returns, if not `()`.
+            pub response_type: Option<&'static str>,
         }
-        docs.push('\n');
-        docs.push_str(&param_docs);
-    }
-
-    if op.deprecated {
-        docs.push_str("\n\n");
-        docs.push_str("**NOTE:** This operation is marked as deprecated.");
-    }
 
-    if let Some(external_docs) = &op.external_docs {
-        docs.push_str("\n\n");
-        if let Some(description) = &external_docs.description {
-            write!(
-                docs,
-                "See <{}|{}> for more information.",
-                external_docs.url, description
-            )?;
-        } else {
-            write!(docs, "See <{}> for more information.", external_docs.url)?;
+        /// Every function of the client that sends a request. An operation can have
+        /// more than one, like `create` and `create_wait`, so its id can show up more
+        /// than once.
+        pub fn operations() -> &'static [OperationInfo] {
+            &[#(#operations),*]
         }
     }
-
-    Ok(docs)
 }
 
-struct RequestOrResponse {
-    media_type: String,
-    type_name: proc_macro2::TokenStream,
+/// An operation we can build an update helper from.
+struct HelperOperation {
+    path: String,
+    op: openapiv3::Operation,
+    global_params: Vec<openapiv3::ReferenceOr<openapiv3::Parameter>>,
 }
 
-/// Return the response type for the operation.
-fn get_response_type(
+/// Generate the read-modify-write helpers for the pairs of `GET` and `PUT` operations
+/// on the same resource, see `crate::update_helpers`.
+fn generate_update_helpers(
     type_space: &mut crate::types::TypeSpace,
-    name: &str,
-    method: &http::Method,
-    op: &openapiv3::Operation,
-) -> Result<Option<RequestOrResponse>> {
-    for (status_code, response) in &op.responses.responses {
-        // We only care if the response is a success since this is for the function
-        // to return upon success.
-        if status_code.is_success() {
-            // Then let's get the type for the response.
-            let response = response.expand(&type_space.spec)?;
-
-            // Iterate over all the media types and return the first response.
-            for (media_type, content) in &response.content {
-                if let Some(s) = &content.schema {
-                    let t = match s {
-                        openapiv3::ReferenceOr::Reference { .. } => {
-                            crate::types::get_type_name_from_reference(
-                                &s.reference()?,
-                                &type_space.spec,
-                                false,
-                            )?
-                        }
-                        openapiv3::ReferenceOr::Item(s) => {
-                            let on_the_fly_type = crate::types::get_type_name_for_schema(
-                                &generate_name_for_fn_schema(name, method, s, op, "Response"),
-                                s,
-                                &type_space.spec,
-                                false,
-                            )?;
-
-                            // Make sure we generate the object.
-                            type_space.render_schema(&on_the_fly_type.to_string(), s)?;
-
-                            on_the_fly_type
-                        }
-                    };
+    opts: &crate::Opts,
+    tag_files: &mut BTreeMap<String, proc_macro2::TokenStream>,
+    operations: &mut Vec<OperationInfo>,
+) -> Result<()> {
+    let config = match &opts.update_helpers {
+        Some(path) => crate::update_helpers::UpdateHelpers::load(path)?,
+        None => Default::default(),
+    };
 
-                    // Return early since we found the type.
-                    return Ok(Some(RequestOrResponse {
-                        media_type: media_type.to_string(),
-                        type_name: t,
-                    }));
-                }
+    // Collect the operations by operation id, and the functions of every tag.
+    let mut gets: BTreeMap<String, HelperOperation> = Default::default();
+    let mut puts: BTreeMap<String, HelperOperation> = Default::default();
+    let mut fn_names: BTreeSet<(String, String)> = Default::default();
+    for (name, path) in type_space.spec.paths.iter() {
+        let item = path.item()?;
+        for (method, op) in [
+            (http::Method::GET, &item.get),
+            (http::Method::PUT, &item.put),
+            (http::Method::POST, &item.post),
+            (http::Method::DELETE, &item.delete),
+            (http::Method::HEAD, &item.head),
+            (http::Method::PATCH, &item.patch),
+            (http::Method::OPTIONS, &item.options),
+            (http::Method::TRACE, &item.trace),
+        ] {
+            let Some(op) = op else {
+                continue;
+            };
+            fn_names.insert((op.get_tag()?, op.get_fn_name()?));
+            let Some(operation_id) = op.operation_id.clone() else {
+                continue;
+            };
+            let operation = HelperOperation {
+                path: name.to_string(),
+                op: op.clone(),
+                global_params: item.parameters.clone(),
+            };
+            if method == http::Method::GET {
+                gets.insert(operation_id, operation);
+            } else if method == http::Method::PUT {
+                puts.insert(operation_id, operation);
             }
         }
     }
+    config.check_operations(
+        &gets.keys().cloned().collect(),
+        &puts.keys().cloned().collect(),
+    )?;
+
+    for (put_name, put) in &puts {
+        let same_path_get = gets
+            .iter()
+            .find(|(_, get)| get.path == put.path)
+            .map(|(get_name, _)| get_name.as_str());
+        let (get_name, configured) = match config.pairing(put_name, same_path_get) {
+            Some(crate::update_helpers::Pairing::Guessed(get_name)) => (get_name, false),
+            Some(crate::update_helpers::Pairing::Configured(get_name)) => (get_name, true),
+            None => continue,
+        };
+        let get = &gets[get_name];
+
+        let resource = match get_update_helper_resource(type_space, get, put)? {
+            Ok(resource) => resource,
+            Err(reason) if configured => anyhow::bail!(
+                "cannot generate an update helper for `{}` with `{}`: {}",
+                put_name,
+                get_name,
+                reason
+            ),
+            Err(_) => continue,
+        };
 
-    // We couldn't find a type for the response.
-    Ok(None)
-}
-
-/// Return the schema name for the type.
-/// We use this for populating the name of the type, if there is not one.
-fn generate_name_for_fn_schema(
-    name: &str,
-    method: &http::Method,
-    schema: &openapiv3::Schema,
-    op: &openapiv3::Operation,
-    addition: &str,
-) -> String {
-    if let Some(title) = &schema.schema_data.title {
-        return title.to_string();
-    }
-
-    if let Some(operation_id) = &op.operation_id {
-        return format!("{} {}", operation_id, addition);
-    }
+        let tag = put.op.get_tag()?;
+        let fn_name = format!("{}_with", put.op.get_fn_name()?);
+        if fn_names.contains(&(tag.to_string(), fn_name.to_string())) {
+            anyhow::bail!(
+                "cannot generate the update helper `{}`, there is already an operation with that name",
+                fn_name
+            );
+        }
+        let fn_name_ident = format_ident!("{}", fn_name);
 
-    format!("{} {} {}", name, method, addition)
-}
+        let args = get_args(
+            &put.path,
+            &http::Method::PUT,
+            type_space,
+            &put.op,
+            &put.global_params,
+        )?;
+        let args = args.iter().map(|(k, v)| {
+            let n = format_ident!("{}", crate::types::clean_property_name(k));
+            quote!(#n: #v)
+        });
+
+        let get_body = get_function_body(
+            type_space,
+            &get.path,
+            &http::Method::GET,
+            &get.op,
+            RequestKind::Conditional,
+            opts,
+            &get.global_params,
+        )?;
+        let put_body = get_function_body(
+            type_space,
+            &put.path,
+            &http::Method::PUT,
+            &put.op,
+            RequestKind::Conditional,
+            opts,
+            &put.global_params,
+        )?;
+
+        // The PUT operation may take its body as an option.
+        let body_ref = if is_optional_request_body(&type_space.spec, &put.op)? {
+            quote!(Some(&body))
+        } else {
+            quote!(&body)
+        };
+
+        let module_map = crate::modules::ModuleMap::new(opts)?;
+        let link = |op: &openapiv3::Operation| -> Result<String> {
+            let fn_name = op.get_fn_name()?;
+            let tag = op.get_tag()?;
+            Ok(format!(
+                "[`{}`](crate::{}::{}::{})",
+                fn_name,
+                module_map.path(&tag).join("::"),
+                module_map.struct_name(&tag),
+                fn_name
+            ))
+        };
+        let max_attempts = config.max_attempts;
+        let docs = format!(
+            r#"Fetch the `{}` with {}, apply `f` to it and send it back with {}.
+
+**This makes more than one request.** The update is sent with `If-Match` set to the `ETag` of what we fetched, if the server sent one. When the server answers `409 Conflict` or `412 Precondition Failed`, because the resource changed in the meantime, we fetch it again and call `f` again, up to {} times in total. So `f` should only change the resource."#,
+            resource.rendered()?.trim_start_matches("crate::types::"),
+            link(&get.op)?,
+            link(&put.op)?,
+            max_attempts
+        );
+
+        let function = quote! {
+            #[doc = #docs]
+            #[tracing::instrument(skip(f))]
+            pub async fn #fn_name_ident<'a, F>(&'a self, #(#args,)* mut f: F) -> Result<#resource, crate::types::error::Error>
+            where
+                F: FnMut(&mut #resource),
+            {
+                let mut attempt = 1;
+                loop {
+                    // Fetch the resource.
+                    let fetched: Result<(#resource, Option<reqwest::header::HeaderValue>), crate::types::error::Error> = async {
+                        #get_body
+                    }
+                    .await;
+                    let (mut body, if_match) = fetched?;
+
+                    f(&mut body);
+
+                    // Send it back.
+                    let body = #body_ref;
+                    let if_match = if_match.as_ref();
+                    let result: Result<#resource, crate::types::error::Error> = async {
+                        #put_body
+                    }
+                    .await;
+
+                    match result {
+                        Err(err)
+                            if attempt < #max_attempts
+                                && matches!(
+                                    err.status(),
+                                    Some(reqwest::StatusCode::CONFLICT)
+                                        | Some(reqwest::StatusCode::PRECONDITION_FAILED)
+                                ) =>
+                        {
+                            // Someone else changed the resource, start over.
+                            attempt += 1;
+                        }
+                        result => return result,
+                    }
+                }
+            }
+        };
+
+        add_fn_to_tag(tag_files, &tag, &function)?;
+        operations.push(OperationInfo {
+            request_type: Some(resource.rendered()?),
+            response_type: Some(resource.rendered()?),
+            ..OperationInfo::new(&put.path, &http::Method::PUT, &put.op, fn_name)?
+        });
+    }
+
+    Ok(())
+}
+
+/// Return the resource of an update helper, or why the operations don't fit. The
+/// `GET` operation must return the JSON body the `PUT` operation takes and returns,
+/// and they must take the same arguments.
+fn get_update_helper_resource(
+    type_space: &mut crate::types::TypeSpace,
+    get: &HelperOperation,
+    put: &HelperOperation,
+) -> Result<std::result::Result<TokenStream, String>> {
+    if get.op.is_websocket() {
+        return Ok(Err("the GET operation is a websocket".to_string()));
+    }
+    if get_pagination_properties(&get.path, &http::Method::GET, &get.op, &type_space.spec)?
+        .has_stream()
+    {
+        return Ok(Err("the GET operation is paginated".to_string()));
+    }
+
+    let is_json =
+        |media_type: &str| media_type == "application/json" || media_type.ends_with("+json");
+    let fetched = match get_response_type(type_space, &get.path, &http::Method::GET, &get.op)? {
+        Some(response) if is_json(&response.media_type) => response.type_name,
+        _ => return Ok(Err("the GET operation does not return JSON".to_string())),
+    };
+    match get_request_body(type_space, &put.path, &http::Method::PUT, &put.op)? {
+        Some(body) if body.media_type == "application/json" => {
+            if body.type_name.rendered()? != fetched.rendered()? {
+                return Ok(Err(format!(
+                    "the PUT operation takes `{}`, the GET operation returns `{}`",
+                    body.type_name.rendered()?,
+                    fetched.rendered()?
+                )));
+            }
+        }
+        _ => return Ok(Err("the PUT operation does not take JSON".to_string())),
+    }
+    match get_response_type(type_space, &put.path, &http::Method::PUT, &put.op)? {
+        Some(response)
+            if is_json(&response.media_type)
+                && response.type_name.rendered()? == fetched.rendered()? => {}
+        _ => {
+            return Ok(Err(format!(
+                "the PUT operation does not return `{}`",
+                fetched.rendered()?
+            )))
+        }
+    }
+
+    let rendered_args = |args: BTreeMap<String, TokenStream>| -> Result<Vec<(String, String)>> {
+        args.into_iter()
+            .map(|(k, v)| Ok((k, v.rendered()?)))
+            .collect()
+    };
+    let fetch_args = rendered_args(get_args(
+        &get.path,
+        &http::Method::GET,
+        type_space,
+        &get.op,
+        &get.global_params,
+    )?)?;
+    let replace_args = rendered_args(get_args(
+        &put.path,
+        &http::Method::PUT,
+        type_space,
+        &put.op,
+        &put.global_params,
+    )?)?;
+    if fetch_args != replace_args {
+        return Ok(Err("the operations take different arguments".to_string()));
+    }
+
+    Ok(Ok(fetched))
+}
+
+/// Generate the helpers creating a job with a `POST` operation and polling it with
+/// a `GET` operation until it is done, see `crate::poll_helpers`.
+fn generate_poll_helpers(
+    type_space: &mut crate::types::TypeSpace,
+    opts: &crate::Opts,
+    tag_files: &mut BTreeMap<String, proc_macro2::TokenStream>,
+    operations: &mut Vec<OperationInfo>,
+) -> Result<()> {
+    let config = match &opts.poll_helpers {
+        Some(path) => crate::poll_helpers::PollHelpers::load(path)?,
+        None => Default::default(),
+    };
+
+    // Collect the operations by operation id, and the functions of every tag.
+    let mut gets: BTreeMap<String, HelperOperation> = Default::default();
+    let mut posts: BTreeMap<String, HelperOperation> = Default::default();
+    let mut fn_names: BTreeSet<(String, String)> = Default::default();
+    for (name, path) in type_space.spec.paths.iter() {
+        let item = path.item()?;
+        for (method, op) in [
+            (http::Method::GET, &item.get),
+            (http::Method::PUT, &item.put),
+            (http::Method::POST, &item.post),
+            (http::Method::DELETE, &item.delete),
+            (http::Method::HEAD, &item.head),
+            (http::Method::PATCH, &item.patch),
+            (http::Method::OPTIONS, &item.options),
+            (http::Method::TRACE, &item.trace),
+        ] {
+            let Some(op) = op else {
+                continue;
+            };
+            fn_names.insert((op.get_tag()?, op.get_fn_name()?));
+            let Some(operation_id) = op.operation_id.clone() else {
+                continue;
+            };
+            let operation = HelperOperation {
+                path: name.to_string(),
+                op: op.clone(),
+                global_params: item.parameters.clone(),
+            };
+            if method == http::Method::GET {
+                gets.insert(operation_id, operation);
+            } else if method == http::Method::POST {
+                posts.insert(operation_id, operation);
+            }
+        }
+    }
+    config.check_operations(
+        &gets.keys().cloned().collect(),
+        &posts.keys().cloned().collect(),
+    )?;
+
+    // The resources the `GET` operations return.
+    let mut polled: BTreeMap<String, String> = Default::default();
+    for (get_name, get) in &gets {
+        if let Some(response) =
+            get_response_type(type_space, &get.path, &http::Method::GET, &get.op)?
+        {
+            if is_json_media_type(&response.media_type) {
+                polled.insert(get_name.to_string(), response.type_name.rendered()?);
+            }
+        }
+    }
+
+    for (post_name, post) in &posts {
+        if post.op.is_websocket() {
+            continue;
+        }
+        let created = match get_returned_response_type(
+            type_space,
+            &post.path,
+            &http::Method::POST,
+            &post.op,
+            RequestKind::Single,
+            &post.global_params,
+        )? {
+            Some(response) if is_json_media_type(&response.media_type) => {
+                Some(response.type_name.rendered()?)
+            }
+            _ => None,
+        };
+        let guessed = polled
+            .iter()
+            .filter(|(_, resource)| Some(*resource) == created.as_ref())
+            .map(|(get_name, _)| get_name.as_str())
+            .collect::<Vec<_>>();
+        let (get_name, configured) = match config.pairing(post_name, &guessed) {
+            Some(crate::update_helpers::Pairing::Guessed(get_name)) => (get_name, false),
+            Some(crate::update_helpers::Pairing::Configured(get_name)) => (get_name, true),
+            None => continue,
+        };
+        let get = &gets[get_name];
+
+        let (resource, id) = match get_poll_helper_resource(type_space, &config, get, post)? {
+            Ok(resource) => resource,
+            Err(reason) if configured => anyhow::bail!(
+                "cannot generate a poll helper for `{}` with `{}`: {}",
+                post_name,
+                get_name,
+                reason
+            ),
+            Err(_) => continue,
+        };
+
+        let tag = post.op.get_tag()?;
+        let fn_name = format!("{}_and_wait", post.op.get_fn_name()?);
+        if fn_names.contains(&(tag.to_string(), fn_name.to_string())) {
+            anyhow::bail!(
+                "cannot generate the poll helper `{}`, there is already an operation with that name",
+                fn_name
+            );
+        }
+        let fn_name_ident = format_ident!("{}", fn_name);
+        let create_ident = format_ident!("{}", post.op.get_fn_name()?);
+
+        let args = get_args(
+            &post.path,
+            &http::Method::POST,
+            type_space,
+            &post.op,
+            &post.global_params,
+        )?;
+        let arg_names = args
+            .keys()
+            .map(|k| format_ident!("{}", crate::types::clean_property_name(k)))
+            .collect::<Vec<_>>();
+        let args = args.iter().map(|(k, v)| {
+            let n = format_ident!("{}", crate::types::clean_property_name(k));
+            quote!(#n: #v)
+        });
+
+        // The `POST` operation takes the same body as its own function.
+        let request_type =
+            match get_request_body(type_space, &post.path, &http::Method::POST, &post.op)? {
+                Some(rb)
+                    if !is_multipart(type_space, &post.path, &http::Method::POST, &post.op)?
+                        || multipart_has_body(&rb.type_name)? =>
+                {
+                    Some(rb.type_name)
+                }
+                _ => None,
+            };
+        let (request_body, body_arg) = match &request_type {
+            Some(t) if is_optional_request_body(&type_space.spec, &post.op)? => {
+                (quote!(body: Option<&#t>,), quote!(body))
+            }
+            Some(t) => (quote!(body: &#t,), quote!(body)),
+            None => (quote!(), quote!()),
+        };
+
+        let get_body = get_function_body(
+            type_space,
+            &get.path,
+            &http::Method::GET,
+            &get.op,
+            RequestKind::Poll,
+            opts,
+            &get.global_params,
+        )?;
+
+        let status_ident = format_ident!("{}", crate::types::clean_property_name(&config.status));
+        let id_ident = format_ident!("{}", crate::types::clean_property_name(&id));
+        let completed = &config.completed;
+        let failed = &config.failed;
+
+        // The helper is deprecated with the operation it calls.
+        let deprecated = get_deprecated_attr(&post.op);
+        let allow_deprecated = if deprecated.is_empty() {
+            quote!()
+        } else {
+            quote!(#[allow(deprecated)])
+        };
+
+        let module_map = crate::modules::ModuleMap::new(opts)?;
+        let link = |op: &openapiv3::Operation| -> Result<String> {
+            let fn_name = op.get_fn_name()?;
+            let tag = op.get_tag()?;
+            Ok(format!(
+                "[`{}`](crate::{}::{}::{})",
+                fn_name,
+                module_map.path(&tag).join("::"),
+                module_map.struct_name(&tag),
+                fn_name
+            ))
+        };
+        let statuses = |statuses: &[String]| {
+            statuses
+                .iter()
+                .map(|status| format!("`{}`", status))
+                .collect::<Vec<_>>()
+                .join(" or ")
+        };
+        let docs = format!(
+            r#"Create a `{}` with {}, then poll it with {} until it is done.
+
+**This makes more than one request.** The first poll is `options.initial_interval` after the `{}` is created, every wait is `options.multiplier` times longer than the previous one up to `options.max_interval`, or longer if the server asks for it with `Retry-After`. Returns the `{}` once its `{}` is {}, or a `PollError` with its last `{}` once it is {} or after `options.timeout`."#,
+            resource.rendered()?.trim_start_matches("crate::types::"),
+            link(&post.op)?,
+            link(&get.op)?,
+            resource.rendered()?.trim_start_matches("crate::types::"),
+            resource.rendered()?.trim_start_matches("crate::types::"),
+            config.status,
+            statuses(completed),
+            config.status,
+            statuses(failed),
+        );
+
+        let function = quote! {
+            #[doc = #docs]
+            #[cfg(not(target_arch = "wasm32"))]
+            #deprecated
+            #allow_deprecated
+            #[tracing::instrument]
+            pub async fn #fn_name_ident<'a>(&'a self, #(#args,)* #request_body options: crate::types::poll::PollOptions) -> Result<#resource, crate::types::poll::PollError<#resource>> {
+                let mut backoff = crate::types::poll::Backoff::new(options);
+                let mut last = self.#create_ident(#(#arg_names,)* #body_arg).await?;
+                let mut retry_after = None;
+                loop {
+                    let status = last.#status_ident.to_string();
+                    if [#(#completed),*].contains(&status.as_str()) {
+                        return Ok(last);
+                    }
+                    if [#(#failed),*].contains(&status.as_str()) {
+                        return Err(crate::types::poll::PollError::Failed {
+                            status,
+                            resource: Box::new(last),
+                        });
+                    }
+                    let Some(delay) = backoff.next_delay(retry_after) else {
+                        return Err(crate::types::poll::PollError::TimedOut {
+                            status,
+                            resource: Box::new(last),
+                        });
+                    };
+                    tokio::time::sleep(delay).await;
+
+                    // Poll the job.
+                    let #id_ident = &last.id;
+                    let polled: Result<(#resource, Option<std::time::Duration>), crate::types::error::Error> = async {
+                        #get_body
+                    }
+                    .await;
+                    (last, retry_after) = polled?;
+                }
+            }
+        };
+
+        add_fn_to_tag(tag_files, &tag, &function)?;
+        operations.push(OperationInfo {
+            request_type: request_type.map(|t| t.rendered()).transpose()?,
+            response_type: Some(resource.rendered()?),
+            ..OperationInfo::new(&post.path, &http::Method::POST, &post.op, fn_name)?
+        });
+    }
+
+    Ok(())
+}
+
+/// Return the resource of a poll helper, with the name of the argument of the
+/// `GET` operation taking its id, or why the operations don't fit. The `GET`
+/// operation must return the JSON resource the `POST` operation returns, and take
+/// only its `id`. The resource must always have its `id` and a status that can be
+/// the completed and the failed ones.
+fn get_poll_helper_resource(
+    type_space: &mut crate::types::TypeSpace,
+    config: &crate::poll_helpers::PollHelpers,
+    get: &HelperOperation,
+    post: &HelperOperation,
+) -> Result<std::result::Result<(TokenStream, String), String>> {
+    if get.op.is_websocket() {
+        return Ok(Err("the GET operation is a websocket".to_string()));
+    }
+    if get_pagination_properties(&get.path, &http::Method::GET, &get.op, &type_space.spec)?
+        .has_stream()
+    {
+        return Ok(Err("the GET operation is paginated".to_string()));
+    }
+
+    let polled = match get_response_type(type_space, &get.path, &http::Method::GET, &get.op)? {
+        Some(response) if is_json_media_type(&response.media_type) => response.type_name,
+        _ => return Ok(Err("the GET operation does not return JSON".to_string())),
+    };
+    match get_returned_response_type(
+        type_space,
+        &post.path,
+        &http::Method::POST,
+        &post.op,
+        RequestKind::Single,
+        &post.global_params,
+    )? {
+        Some(response)
+            if is_json_media_type(&response.media_type)
+                && response.type_name.rendered()? == polled.rendered()? => {}
+        _ => {
+            return Ok(Err(format!(
+                "the POST operation does not return `{}`",
+                polled.rendered()?
+            )))
+        }
+    }
+
+    // The id is the only argument of the `GET` operation, in its path.
+    let args = get_args(
+        &get.path,
+        &http::Method::GET,
+        type_space,
+        &get.op,
+        &get.global_params,
+    )?;
+    let path_params = get_path_params(type_space, &get.op, &get.global_params)?;
+    let (id, id_type) = match args.into_iter().collect::<Vec<_>>().as_slice() {
+        [(name, t)] if path_params.contains_key(name) => (name.to_string(), t.clone()),
+        _ => {
+            return Ok(Err(
+                "the GET operation does not take only the id in its path".to_string(),
+            ))
+        }
+    };
+
+    // The resource has its id and its status.
+    let spec = &type_space.spec;
+    let Some(schema) = get_response_schema(spec, &get.op)? else {
+        return Ok(Err("the GET operation does not return a schema".to_string()));
+    };
+    let object = match &schema.schema_kind {
+        openapiv3::SchemaKind::Type(openapiv3::Type::Object(object)) => object,
+        _ => return Ok(Err("the resource is not an object".to_string())),
+    };
+
+    let Some(id_field_type) = get_id_field_type(spec, object)? else {
+        return Ok(Err("the resource does not always have an `id`".to_string()));
+    };
+    if takes_id(&id_type, &id_field_type)?.is_none() {
+        return Ok(Err(format!(
+            "the GET operation takes `{}`, the id of the resource is `{}`",
+            id_type.rendered()?,
+            id_field_type.rendered()?
+        )));
+    }
+
+    let Some(status_schema) = get_required_property(spec, object, &config.status)? else {
+        return Ok(Err(format!(
+            "the resource does not always have a `{}`",
+            config.status
+        )));
+    };
+    let statuses = get_string_values(spec, &openapiv3::ReferenceOr::Item(status_schema))?;
+    if !config.knows_statuses(&statuses) {
+        return Ok(Err(format!(
+            "the `{}` of the resource is not one of the completed and failed statuses",
+            config.status
+        )));
+    }
+
+    Ok(Ok((polled, id)))
+}
+
+/// The schema of a property an object always has, and never as `null`.
+fn get_required_property(
+    spec: &openapiv3::OpenAPI,
+    object: &openapiv3::ObjectType,
+    name: &str,
+) -> Result<Option<openapiv3::Schema>> {
+    if !object.required.iter().any(|required| required == name) {
+        return Ok(None);
+    }
+    let Some(property) = object.properties.get(name) else {
+        return Ok(None);
+    };
+    let property = property.clone().unbox();
+    let schema = match &property {
+        openapiv3::ReferenceOr::Reference { .. } => {
+            property.get_schema_from_reference(spec, false)?
+        }
+        openapiv3::ReferenceOr::Item(schema) => schema.clone(),
+    };
+    Ok((!schema.schema_data.nullable).then_some(schema))
+}
+
+/// The type of the `id` of a resource, if it always has one.
+fn get_id_field_type(
+    spec: &openapiv3::OpenAPI,
+    object: &openapiv3::ObjectType,
+) -> Result<Option<TokenStream>> {
+    let Some(id_schema) = get_required_property(spec, object, "id")? else {
+        return Ok(None);
+    };
+    Ok(Some(match &object.properties["id"] {
+        openapiv3::ReferenceOr::Reference { .. } => crate::types::get_type_name_from_reference(
+            &object.properties["id"].reference()?,
+            spec,
+            false,
+        )?,
+        openapiv3::ReferenceOr::Item(_) => {
+            crate::types::get_type_name_for_schema("id", &id_schema, spec, false)?
+        }
+    }))
+}
+
+/// How a function taking a parameter of type `param_type` is given the `id` of a
+/// resource bound to `id`, by reference for a string, `None` if it can't be.
+fn takes_id(param_type: &TokenStream, id_field_type: &TokenStream) -> Result<Option<TokenStream>> {
+    if param_type.is_string()? || is_display_string_param(param_type)? {
+        Ok((id_field_type.rendered()? == "String").then(|| quote!(id)))
+    } else {
+        Ok((id_field_type.rendered()? == param_type.rendered()?).then(|| quote!(id.clone())))
+    }
+}
+
+/// Generate the helpers uploading a file in chunks to an upload session, see
+/// `crate::upload_sessions`.
+fn generate_upload_helpers(
+    type_space: &mut crate::types::TypeSpace,
+    opts: &crate::Opts,
+    tag_files: &mut BTreeMap<String, proc_macro2::TokenStream>,
+    operations: &mut Vec<OperationInfo>,
+) -> Result<()> {
+    let sessions = crate::upload_sessions::find(&type_space.spec)?;
+    if sessions.is_empty() {
+        return Ok(());
+    }
+
+    // Collect the operations by operation id, and the functions of every tag.
+    let mut by_id: BTreeMap<String, (http::Method, HelperOperation)> = Default::default();
+    let mut fn_names: BTreeSet<(String, String)> = Default::default();
+    for (name, path) in type_space.spec.paths.iter() {
+        let item = path.item()?;
+        for (method, op) in [
+            (http::Method::GET, &item.get),
+            (http::Method::PUT, &item.put),
+            (http::Method::POST, &item.post),
+            (http::Method::DELETE, &item.delete),
+            (http::Method::HEAD, &item.head),
+            (http::Method::PATCH, &item.patch),
+            (http::Method::OPTIONS, &item.options),
+            (http::Method::TRACE, &item.trace),
+        ] {
+            let Some(op) = op else {
+                continue;
+            };
+            fn_names.insert((op.get_tag()?, op.get_fn_name()?));
+            let Some(operation_id) = op.operation_id.clone() else {
+                continue;
+            };
+            let operation = HelperOperation {
+                path: name.to_string(),
+                op: op.clone(),
+                global_params: item.parameters.clone(),
+            };
+            by_id.insert(operation_id, (method, operation));
+        }
+    }
+
+    let module_map = crate::modules::ModuleMap::new(opts)?;
+    for session in sessions {
+        let (create_method, create) = &by_id[&session.create];
+        let (chunk_method, chunk) = &by_id[&session.chunk];
+        let (finalize_method, finalize) = &by_id[&session.finalize];
+        let calls = get_upload_session_calls(
+            type_space,
+            (create_method, create),
+            (chunk_method, chunk),
+            (finalize_method, finalize),
+        )?
+        .map_err(|reason| {
+            anyhow::anyhow!(
+                "cannot generate `{}` for the upload session of `{}`: {}",
+                session.helper,
+                session.create,
+                reason
+            )
+        })?;
+
+        let tag = create.op.get_tag()?;
+        if fn_names.contains(&(tag.to_string(), session.helper.to_string())) {
+            anyhow::bail!(
+                "cannot generate the upload helper `{}`, there is already an operation with that name",
+                session.helper
+            );
+        }
+        fn_names.insert((tag.to_string(), session.helper.to_string()));
+        let fn_name_ident = format_ident!("{}", session.helper);
+        let create_ident = format_ident!("{}", create.op.get_fn_name()?);
+        let chunk_ident = format_ident!("{}", chunk.op.get_fn_name()?);
+        let finalize_ident = format_ident!("{}", finalize.op.get_fn_name()?);
+
+        // The helper takes the arguments of the operation creating the session.
+        let args = get_args(
+            &create.path,
+            create_method,
+            type_space,
+            &create.op,
+            &create.global_params,
+        )?;
+        if let Some(arg) = args.keys().find(|k| {
+            ["source", "chunk_size", "concurrency", "progress"]
+                .contains(&crate::types::clean_property_name(k).as_str())
+        }) {
+            anyhow::bail!(
+                "cannot generate `{}` for the upload session of `{}`: its argument `{}` is one of the helper",
+                session.helper,
+                session.create,
+                arg
+            );
+        }
+        let arg_names = args
+            .keys()
+            .map(|k| format_ident!("{}", crate::types::clean_property_name(k)))
+            .collect::<Vec<_>>();
+        let args = args.iter().map(|(k, v)| {
+            let n = format_ident!("{}", crate::types::clean_property_name(k));
+            quote!(#n: #v)
+        });
+        let request_type =
+            match get_request_body(type_space, &create.path, create_method, &create.op)? {
+                Some(rb)
+                    if !is_multipart(type_space, &create.path, create_method, &create.op)?
+                        || multipart_has_body(&rb.type_name)? =>
+                {
+                    Some(rb.type_name)
+                }
+                _ => None,
+            };
+        let (request_body, body_arg) = match &request_type {
+            Some(t) if is_optional_request_body(&type_space.spec, &create.op)? => {
+                (quote!(body: Option<&#t>,), quote!(body))
+            }
+            Some(t) => (quote!(body: &#t,), quote!(body)),
+            None => (quote!(), quote!()),
+        };
+
+        let finalized = get_fn_response_type(
+            type_space,
+            &finalize.path,
+            finalize_method,
+            &finalize.op,
+            &finalize.global_params,
+        )?;
+        let chunk_args = &calls.chunk_args;
+        let finalize_args = &calls.finalize_args;
+
+        // The helper is deprecated with any of the operations it calls.
+        let deprecated = vec![&create.op, &chunk.op, &finalize.op]
+            .into_iter()
+            .map(get_deprecated_attr)
+            .find(|attr| !attr.is_empty())
+            .unwrap_or_default();
+        let allow_deprecated = if deprecated.is_empty() {
+            quote!()
+        } else {
+            quote!(#[allow(deprecated)])
+        };
+
+        let link = |op: &openapiv3::Operation| -> Result<String> {
+            let fn_name = op.get_fn_name()?;
+            let tag = op.get_tag()?;
+            Ok(format!(
+                "[`{}`](crate::{}::{}::{})",
+                fn_name,
+                module_map.path(&tag).join("::"),
+                module_map.struct_name(&tag),
+                fn_name
+            ))
+        };
+        let docs = format!(
+            r#"Upload `source` to a new upload session: create it with {}, send the file in chunks of `chunk_size` bytes with {}, up to `concurrency` at a time, then finalize it with {}.
+
+**This makes more than one request.** Every chunk has its `Content-Range`, the last one with the size of the file. A chunk failing with an error worth retrying is sent again on its own, up to `crate::types::upload::MAX_ATTEMPTS` times, any other failure stops the upload. `progress` is called after every chunk that is uploaded."#,
+            link(&create.op)?,
+            link(&chunk.op)?,
+            link(&finalize.op)?,
+        );
+
+        let function = quote! {
+            #[doc = #docs]
+            #[cfg(not(target_arch = "wasm32"))]
+            #deprecated
+            #allow_deprecated
+            #[tracing::instrument(skip(source, progress))]
+            pub async fn #fn_name_ident<'a, R, P>(&'a self, #(#args,)* #request_body source: R, chunk_size: usize, concurrency: usize, progress: P) -> Result<#finalized, crate::types::upload::UploadError>
+            where
+                R: tokio::io::AsyncRead + Unpin,
+                P: FnMut(crate::types::upload::UploadProgress),
+            {
+                let session = self.#create_ident(#(#arg_names,)* #body_arg).await?;
+                let id = &session.id;
+                crate::types::upload::upload_chunks(source, chunk_size, concurrency, progress, |chunk| async move {
+                    self.#chunk_ident(#(#chunk_args),*).await?;
+                    Ok(())
+                })
+                .await?;
+                Ok(self.#finalize_ident(#(#finalize_args),*).await?)
+            }
+        };
+
+        add_fn_to_tag(tag_files, &tag, &function)?;
+        operations.push(OperationInfo {
+            request_type: request_type.map(|t| t.rendered()).transpose()?,
+            response_type: Some(finalized.rendered()?),
+            ..OperationInfo::new(
+                &create.path,
+                create_method,
+                &create.op,
+                session.helper.to_string(),
+            )?
+        });
+    }
+
+    Ok(())
+}
+
+/// The arguments the upload helper calls the operations of a session with.
+struct UploadSessionCalls {
+    /// The arguments of the function sending a chunk.
+    chunk_args: Vec<TokenStream>,
+    /// The arguments of the function finalizing the session.
+    finalize_args: Vec<TokenStream>,
+}
+
+/// How the upload helper calls the operations of a session, or why the
+/// operations don't fit, see `crate::upload_sessions`. The session and the chunk
+/// are bound to `id` and `chunk`.
+fn get_upload_session_calls(
+    type_space: &mut crate::types::TypeSpace,
+    (create_method, create): (&http::Method, &HelperOperation),
+    (chunk_method, chunk): (&http::Method, &HelperOperation),
+    (finalize_method, finalize): (&http::Method, &HelperOperation),
+) -> Result<std::result::Result<UploadSessionCalls, String>> {
+    for (role, op) in [
+        ("creating", create),
+        ("chunk", chunk),
+        ("finalizing", finalize),
+    ] {
+        if op.op.is_websocket() {
+            return Ok(Err(format!("the {} operation is a websocket", role)));
+        }
+    }
+
+    // The session always has its id.
+    let create_type = get_returned_response_type(
+        type_space,
+        &create.path,
+        create_method,
+        &create.op,
+        RequestKind::Single,
+        &create.global_params,
+    )?;
+    let schema = match (
+        &create_type,
+        get_response_schema(&type_space.spec, &create.op)?,
+    ) {
+        (Some(response), Some(schema)) if is_json_media_type(&response.media_type) => schema,
+        _ => {
+            return Ok(Err(
+                "the creating operation does not return JSON".to_string()
+            ))
+        }
+    };
+    let id_field_type = match &schema.schema_kind {
+        openapiv3::SchemaKind::Type(openapiv3::Type::Object(object)) => {
+            get_id_field_type(&type_space.spec, object)?
+        }
+        _ => None,
+    };
+    let Some(id_field_type) = id_field_type else {
+        return Ok(Err("the session does not always have an `id`".to_string()));
+    };
+
+    // The chunk operation takes the id in its path, the range and the chunk.
+    let args = get_args(
+        &chunk.path,
+        chunk_method,
+        type_space,
+        &chunk.op,
+        &chunk.global_params,
+    )?;
+    let path_params = get_path_params(type_space, &chunk.op, &chunk.global_params)?;
+    let mut chunk_args = Vec::new();
+    let mut has_range = false;
+    for (name, t) in &args {
+        if path_params.contains_key(name) && path_params.len() == 1 {
+            match takes_id(t, &id_field_type)? {
+                Some(id) => chunk_args.push(id),
+                None => {
+                    return Ok(Err(format!(
+                        "the chunk operation takes `{}`, the id of the session is `{}`",
+                        t.rendered()?,
+                        id_field_type.rendered()?
+                    )))
+                }
+            }
+        } else if name.eq_ignore_ascii_case("content-range")
+            && (t.is_string()? || is_display_string_param(t)?)
+        {
+            has_range = true;
+            chunk_args.push(quote!(&chunk.content_range()));
+        } else {
+            return Ok(Err(format!(
+                "the chunk operation takes `{}`, it only takes the id of the session in its \
+                 path and the `Content-Range` header",
+                name
+            )));
+        }
+    }
+    if !has_range || chunk_args.len() != 2 {
+        return Ok(Err(
+            "the chunk operation does not take the id of the session in its path and the \
+             `Content-Range` header"
+                .to_string(),
+        ));
+    }
+    match get_request_body(type_space, &chunk.path, chunk_method, &chunk.op)? {
+        Some(rb)
+            if rb.media_type == "application/octet-stream"
+                && !is_optional_request_body(&type_space.spec, &chunk.op)? =>
+        {
+            chunk_args.push(quote!(&chunk.data));
+        }
+        _ => {
+            return Ok(Err(
+                "the chunk operation does not take an `application/octet-stream` body".to_string(),
+            ))
+        }
+    }
+
+    // The finalizing operation takes the id alone.
+    let args = get_args(
+        &finalize.path,
+        finalize_method,
+        type_space,
+        &finalize.op,
+        &finalize.global_params,
+    )?;
+    let path_params = get_path_params(type_space, &finalize.op, &finalize.global_params)?;
+    let finalize_id = match args.into_iter().collect::<Vec<_>>().as_slice() {
+        [(name, t)] if path_params.contains_key(name) => takes_id(t, &id_field_type)?,
+        _ => None,
+    };
+    let Some(finalize_id) = finalize_id else {
+        return Ok(Err(
+            "the finalizing operation does not take only the id of the session in its path"
+                .to_string(),
+        ));
+    };
+    if get_request_body(type_space, &finalize.path, finalize_method, &finalize.op)?.is_some() {
+        return Ok(Err("the finalizing operation takes a body".to_string()));
+    }
+
+    Ok(Ok(UploadSessionCalls {
+        chunk_args,
+        finalize_args: vec![finalize_id],
+    }))
+}
+
+/// The schema of the first successful JSON response of an operation.
+fn get_response_schema(
+    spec: &openapiv3::OpenAPI,
+    op: &openapiv3::Operation,
+) -> Result<Option<openapiv3::Schema>> {
+    for (status_code, response) in &op.responses.responses {
+        if !status_code.is_success() {
+            continue;
+        }
+        let response = response.expand(spec)?;
+        for (media_type, content) in &response.content {
+            if let Some(schema) = &content.schema {
+                if !is_json_media_type(media_type) {
+                    return Ok(None);
+                }
+                return Ok(Some(match schema {
+                    openapiv3::ReferenceOr::Reference { .. } => {
+                        schema.get_schema_from_reference(spec, false)?
+                    }
+                    openapiv3::ReferenceOr::Item(schema) => schema.clone(),
+                }));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// The string values a schema can take, through its references, an `allOf` of one
+/// schema and a `oneOf` of enums. Empty if it can be any string.
+fn get_string_values(
+    spec: &openapiv3::OpenAPI,
+    schema: &openapiv3::ReferenceOr<openapiv3::Schema>,
+) -> Result<Vec<String>> {
+    let schema = match schema {
+        openapiv3::ReferenceOr::Reference { .. } => {
+            schema.get_schema_from_reference(spec, false)?
+        }
+        openapiv3::ReferenceOr::Item(schema) => schema.clone(),
+    };
+    match &schema.schema_kind {
+        openapiv3::SchemaKind::Type(openapiv3::Type::String(s)) => {
+            Ok(s.enumeration.iter().flatten().cloned().collect())
+        }
+        openapiv3::SchemaKind::AllOf { all_of } if all_of.len() == 1 => {
+            get_string_values(spec, &all_of[0])
+        }
+        openapiv3::SchemaKind::OneOf { one_of } => {
+            let mut values = Vec::new();
+            for schema in one_of {
+                values.extend(get_string_values(spec, schema)?);
+            }
+            Ok(values)
+        }
+        _ => Ok(Vec::new()),
+    }
+}
+
+/// Generate the docs for the given operation.
+fn generate_docs(
+    type_space: &mut crate::types::TypeSpace,
+    name: &str,
+    method: &http::Method,
+    op: &openapiv3::Operation,
+    global_params: &[openapiv3::ReferenceOr<openapiv3::Parameter>],
+) -> Result<String> {
+    let mut docs = if let Some(summary) = &op.summary {
+        summary.to_string()
+    } else {
+        format!("Perform a `{}` request to `{}`.", method, name)
+    };
+
+    if let Some(description) = &op.description {
+        docs.push_str("\n\n");
+        docs.push_str(&sanitize_description(description, name, type_space));
+    }
+
+    // Document the params.
+    let params = get_all_params_schema(type_space, op, global_params)?;
+
+    let params_types = get_args(name, method, type_space, op, global_params)?;
+
+    if !params.is_empty() {
+        docs.push_str("\n\n**Parameters:**\n");
+    }
+    for (name, (_schema, parameter_data)) in params {
+        // Get the type of the param.
+        let param_type = params_types.get(&name).ok_or_else(|| {
+            // This should not happen since both call the same functions.
+            anyhow::anyhow!(
+                "Could not find type for param `{}` in operation `{}`",
+                name,
+                name
+            )
+        })?;
+        let param_type = if is_display_string_param(param_type)? {
+            DISPLAY_STRING_PARAM.to_string()
+        } else {
+            param_type.rendered()?
+        };
+        let mut param_docs = format!(
+            "- `{}: {}`",
+            crate::types::clean_property_name(&name),
+            param_type
+        );
+        if let Some(description) = &parameter_data.description {
+            if !description.trim().is_empty() {
+                param_docs.push_str(": ");
+                param_docs.push_str(&sanitize_description(description, "", type_space));
+            }
+        }
+        if parameter_data.required {
+            param_docs.push_str(" (required)");
+        }
+        if parameter_data.deprecated == Some(true) {
+            param_docs.push_str(" (deprecated)");
+        }
+        docs.push('\n');
+        docs.push_str(&param_docs);
+    }
+
+    // Document the limits on the attachments.
+    if is_multipart(type_space, name, method, op)? {
+        let limits = get_attachment_limits(&type_space.spec, op)?;
+        if !limits.is_empty() {
+            let mut rules = Vec::new();
+            if let Some(max_items) = limits.max_items {
+                rules.push(format!("at most {} attachments", max_items));
+            }
+            if let Some(max_size_bytes) = limits.max_size_bytes {
+                rules.push(format!("each at most {} bytes", max_size_bytes));
+            }
+            write!(docs, "\n\n**Attachments:** {}.", rules.join(", "))?;
+        }
+        if let Some(options) = get_multipart_options(&type_space.spec, op)? {
+            let files = options
+                .files
+                .iter()
+                .map(|file| format!("`{}`", file))
+                .collect::<Vec<_>>();
+            write!(
+                docs,
+                "\n\n**Parts:** the body is sent as the `{}` part, the attachments are named {}.",
+                options.name,
+                files.join(" or ")
+            )?;
+        }
+    }
+
+    // Document the body we send for the function, when it takes none.
+    match get_empty_object_body(&type_space.spec, op)? {
+        Some(true) => {
+            docs.push_str("\n\n**Body:** always `{}`, the operation takes an empty object.")
+        }
+        Some(false) => {
+            docs.push_str("\n\n**Body:** none, the operation takes an optional empty object.")
+        }
+        None => {}
+    }
+    if is_optional_request_body(&type_space.spec, op)? {
+        docs.push_str(
+            "\n\n**Body:** optional, `None` sends no body and no `Content-Type`, which is not the same as sending an empty object.",
+        );
+    }
+
+    // Document the scopes the access token needs.
+    let required_scopes = crate::types::scopes::get_required_scopes(&type_space.spec, op)?;
+    if !required_scopes.is_empty() {
+        let alternatives = required_scopes
+            .iter()
+            .map(|scopes| {
+                scopes
+                    .iter()
+                    .map(|scope| format!("`{}`", scope))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            })
+            .collect::<Vec<_>>();
+        write!(docs, "\n\n**Scopes:** {}.", alternatives.join(" or "))?;
+    }
+
+    // Document what we return when there is no body.
+    if returns_headers(&type_space.spec, method, op)? {
+        docs.push_str("\n\n**Returns:** the headers of the response, since it has no body.");
+        let mut headers = Vec::new();
+        for (status_code, response) in &op.responses.responses {
+            if !status_code.is_success() {
+                continue;
+            }
+            for (header, h) in &response.expand(&type_space.spec)?.headers {
+                let mut header_docs = format!("- `{}`", header);
+                if let Some(description) = h.as_item().and_then(|h| h.description.as_ref()) {
+                    write!(
+                        header_docs,
+                        ": {}",
+                        sanitize_description(description, "", type_space)
+                    )?;
+                }
+                headers.push(header_docs);
+            }
+        }
+        if !headers.is_empty() {
+            docs.push_str("\n\n");
+            docs.push_str(&headers.join("\n"));
+        }
+    }
+
+    let (sunset, replaced_by) = get_sunset(op);
+    if op.deprecated || sunset.is_some() || replaced_by.is_some() {
+        docs.push_str("\n\n");
+        docs.push_str("**NOTE:** This operation is marked as deprecated.");
+        if let Some(sunset) = sunset {
+            write!(docs, " It will be removed on {}.", sunset)?;
+        }
+        if let Some(replaced_by) = replaced_by {
+            write!(docs, " Use {} instead.", replaced_by)?;
+        }
+    }
+
+    if let Some(external_docs) = &op.external_docs {
+        docs.push_str("\n\n");
+        if let Some(description) = &external_docs.description {
+            write!(
+                docs,
+                "See <{}|{}> for more information.",
+                external_docs.url, description
+            )?;
+        } else {
+            write!(docs, "See <{}> for more information.", external_docs.url)?;
+        }
+    }
+
+    Ok(docs)
+}
+
+/// When a deprecated operation goes away and what replaces it, from its `x-sunset`
+/// and `x-replaced-by` extensions.
+fn get_sunset(op: &openapiv3::Operation) -> (Option<&str>, Option<&str>) {
+    let get = |extension: &str| op.extensions.get(extension).and_then(|v| v.as_str());
+    (get("x-sunset"), get("x-replaced-by"))
+}
+
+/// The `#[deprecated]` of the functions of an operation that has a sunset or a
+/// replacement. The operations only marked as deprecated keep the note of their
+/// docs, their callers would get warnings without knowing what to do about them.
+fn get_deprecated_attr(op: &openapiv3::Operation) -> TokenStream {
+    let note = match get_sunset(op) {
+        (Some(sunset), Some(replaced_by)) => {
+            format!("will be removed on {}, use {} instead", sunset, replaced_by)
+        }
+        (Some(sunset), None) => format!("will be removed on {}", sunset),
+        (None, Some(replaced_by)) => format!("use {} instead", replaced_by),
+        (None, None) => return quote!(),
+    };
+    quote!(#[deprecated(note = #note)])
+}
+
+struct RequestOrResponse {
+    media_type: String,
+    type_name: proc_macro2::TokenStream,
+}
+
+/// Return the response type for the operation.
+fn get_response_type(
+    type_space: &mut crate::types::TypeSpace,
+    name: &str,
+    method: &http::Method,
+    op: &openapiv3::Operation,
+) -> Result<Option<RequestOrResponse>> {
+    // We only care if the response is a success since this is for the function
+    // to return upon success.
+    get_response_type_where(type_space, name, method, op, |status_code| {
+        status_code.is_success()
+    })
+}
+
+/// Get the response a function returns. An operation that can wait returns what it
+/// answers right away, `202 Accepted`, and its `_wait` function what it answers
+/// once it is done.
+fn get_returned_response_type(
+    type_space: &mut crate::types::TypeSpace,
+    name: &str,
+    method: &http::Method,
+    op: &openapiv3::Operation,
+    kind: RequestKind,
+    global_params: &[openapiv3::ReferenceOr<openapiv3::Parameter>],
+) -> Result<Option<RequestOrResponse>> {
+    if kind == RequestKind::Conditional
+        || kind == RequestKind::Poll
+        || get_wait_param(type_space, op, global_params)?.is_none()
+    {
+        return get_response_type(type_space, name, method, op);
+    }
+
+    let accepted = openapiv3::StatusCode::Code(202);
+    if kind == RequestKind::Wait {
+        get_response_type_where(type_space, name, method, op, |status_code| {
+            status_code.is_success() && *status_code != accepted
+        })
+    } else {
+        get_response_type_where(type_space, name, method, op, |status_code| {
+            *status_code == accepted
+        })
+    }
+}
+
+/// Get the type of the first response where `select` returns true for the status.
+fn get_response_type_where<F>(
+    type_space: &mut crate::types::TypeSpace,
+    name: &str,
+    method: &http::Method,
+    op: &openapiv3::Operation,
+    select: F,
+) -> Result<Option<RequestOrResponse>>
+where
+    F: Fn(&openapiv3::StatusCode) -> bool,
+{
+    for (status_code, response) in &op.responses.responses {
+        if select(status_code) {
+            // Then let's get the type for the response.
+            let response = response.expand(&type_space.spec)?;
+
+            // Iterate over all the media types and return the first response.
+            for (media_type, content) in &response.content {
+                if let Some(s) = &content.schema {
+                    let t = match s {
+                        openapiv3::ReferenceOr::Reference { .. } => {
+                            crate::types::get_type_name_from_reference(
+                                &s.reference()?,
+                                &type_space.spec,
+                                false,
+                            )?
+                        }
+                        openapiv3::ReferenceOr::Item(s) => {
+                            let on_the_fly_type = crate::types::get_type_name_for_schema(
+                                &generate_name_for_fn_schema(name, method, s, op, "Response"),
+                                s,
+                                &type_space.spec,
+                                false,
+                            )?;
+
+                            // Make sure we generate the object.
+                            type_space.render_schema(&on_the_fly_type.to_string(), s)?;
+
+                            on_the_fly_type
+                        }
+                    };
+
+                    // A file is a download, with its name, if we are asked to.
+                    let t = if type_space.opts.download_responses
+                        && !is_json_media_type(media_type)
+                        && t.rendered()? == "bytes::Bytes"
+                    {
+                        quote!(crate::types::download::Download)
+                    } else {
+                        t
+                    };
+
+                    // Return early since we found the type.
+                    return Ok(Some(RequestOrResponse {
+                        media_type: media_type.to_string(),
+                        type_name: t,
+                    }));
+                }
+            }
+        }
+    }
+
+    // We couldn't find a type for the response.
+    Ok(None)
+}
+
+/// Return `true` if the responses of `media_type` are parsed as JSON.
+fn is_json_media_type(media_type: &str) -> bool {
+    media_type == "application/json"
+        || media_type == "application/vnd.github.v3.object"
+        || media_type.ends_with("+json")
+}
+
+/// Returns the variant of `ErrorBody` and the type of the `default` response of
+/// an operation, when it is JSON and refers to a schema. It is the body of the
+/// errors with a status the operation does not list on its own.
+pub fn get_default_error_type(
+    spec: &openapiv3::OpenAPI,
+    op: &openapiv3::Operation,
+) -> Result<Option<(proc_macro2::Ident, proc_macro2::TokenStream)>> {
+    // The `default` response of a websocket is what it sends.
+    if op.is_websocket() {
+        return Ok(None);
+    }
+    let Some(response) = &op.responses.default else {
+        return Ok(None);
+    };
+    let response = response.expand(spec)?;
+    let Some(content) = response.content.get("application/json") else {
+        return Ok(None);
+    };
+    let Some(schema @ openapiv3::ReferenceOr::Reference { .. }) = &content.schema else {
+        return Ok(None);
+    };
+
+    // The variant is named after the type, so it has to be one.
+    let name = schema.reference()?;
+    let variant = crate::types::get_type_name_from_reference(&name, spec, true)?.rendered()?;
+    let Ok(variant) = syn::parse_str::<proc_macro2::Ident>(&variant) else {
+        return Ok(None);
+    };
+    let type_name = crate::types::get_type_name_from_reference(&name, spec, false)?;
+
+    Ok(Some((variant, type_name)))
+}
+
+/// The type the function of an operation returns: the headers, the body, or `()`
+/// when there is neither.
+fn get_fn_response_type(
+    type_space: &mut crate::types::TypeSpace,
+    name: &str,
+    method: &http::Method,
+    op: &openapiv3::Operation,
+    global_params: &[openapiv3::ReferenceOr<openapiv3::Parameter>],
+) -> Result<TokenStream> {
+    Ok(if returns_headers(&type_space.spec, method, op)? {
+        quote!(http::HeaderMap)
+    } else if let Some(response) = get_returned_response_type(
+        type_space,
+        name,
+        method,
+        op,
+        RequestKind::Single,
+        global_params,
+    )? {
+        let t = response.type_name;
+        quote!(#t)
+    } else {
+        // We don't have a response, so we'll return `()`.
+        quote!(())
+    })
+}
+
+/// Returns true if the function returns the headers of the response rather than a
+/// body. A `HEAD` response never has a body, and an `OPTIONS` response without a
+/// documented body is all about its headers, e.g. `Allow`.
+fn returns_headers(
+    spec: &openapiv3::OpenAPI,
+    method: &http::Method,
+    op: &openapiv3::Operation,
+) -> Result<bool> {
+    if method == http::Method::HEAD {
+        return Ok(true);
+    }
+    if method != http::Method::OPTIONS {
+        return Ok(false);
+    }
+
+    for (status_code, response) in &op.responses.responses {
+        if status_code.is_success()
+            && response
+                .expand(spec)?
+                .content
+                .values()
+                .any(|content| content.schema.is_some())
+        {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+/// Return the value of the `Accept` header for the operation.
+/// This is the media type we parse the response as, or if we don't parse the
+/// response, every media type of the successful responses.
+fn get_accept_header(
+    spec: &openapiv3::OpenAPI,
+    op: &openapiv3::Operation,
+    response_type: &Option<RequestOrResponse>,
+) -> Result<Option<String>> {
+    if let Some(response_type) = response_type {
+        return Ok(Some(response_type.media_type.to_string()));
+    }
+
+    let mut media_types: Vec<String> = Vec::new();
+    for (status_code, response) in &op.responses.responses {
+        if !status_code.is_success() {
+            continue;
+        }
+
+        let response = response.expand(spec)?;
+        for media_type in response.content.keys() {
+            if !media_types.contains(media_type) {
+                media_types.push(media_type.to_string());
+            }
+        }
+    }
+
+    if media_types.is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(media_types.join(", ")))
+    }
+}
+
+/// Return the schema name for the type.
+/// We use this for populating the name of the type, if there is not one.
+fn generate_name_for_fn_schema(
+    name: &str,
+    method: &http::Method,
+    schema: &openapiv3::Schema,
+    op: &openapiv3::Operation,
+    addition: &str,
+) -> String {
+    if let Some(title) = &schema.schema_data.title {
+        return title.to_string();
+    }
+
+    if let Some(operation_id) = &op.operation_id {
+        return format!("{} {}", operation_id, addition);
+    }
+
+    format!("{} {} {}", name, method, addition)
+}
 
 /// Return the function arguments for the operation.
-fn get_args(
+pub(crate) fn get_args(
     name: &str,
     method: &http::Method,
     type_space: &mut crate::types::TypeSpace,
@@ -610,9 +2511,15 @@ fn get_args(
 ) -> Result<BTreeMap<String, proc_macro2::TokenStream>> {
     let path_params = get_path_params(type_space, op, global_params)?;
     let query_params = get_query_params(type_space, op, global_params)?;
+    let header_params = get_header_params(type_space, op, global_params)?;
+    let cookie_params = get_cookie_params(type_space, op, global_params)?;
 
-    let mut args: BTreeMap<String, proc_macro2::TokenStream> =
-        path_params.into_iter().chain(query_params).collect();
+    let mut args: BTreeMap<String, proc_macro2::TokenStream> = path_params
+        .into_iter()
+        .chain(query_params)
+        .chain(header_params)
+        .chain(cookie_params)
+        .collect();
 
     // Add attachments if we have a multipart request.
     if is_multipart(type_space, name, method, op)? {
@@ -625,6 +2532,33 @@ fn get_args(
     Ok(args)
 }
 
+/// The type of the required string parameters with `--display-string-params`.
+const DISPLAY_STRING_PARAM: &str = "impl std::fmt::Display + std::fmt::Debug";
+
+/// Get the type a function takes a parameter of type `t` as.
+fn get_param_arg_type(
+    type_space: &crate::types::TypeSpace,
+    t: &proc_macro2::TokenStream,
+) -> Result<proc_macro2::TokenStream> {
+    if !type_space.opts.display_string_params {
+        return t.get_parameter_value();
+    }
+
+    match t.rendered()?.as_str() {
+        "String" => DISPLAY_STRING_PARAM
+            .parse()
+            .map_err(|e| anyhow::anyhow!("{}", e)),
+        "Option<String>" => Ok(quote!(Option<&'a str>)),
+        _ => Ok(t.clone()),
+    }
+}
+
+/// Returns true if the parameter is a required string taken as
+/// `impl std::fmt::Display`.
+fn is_display_string_param(t: &proc_macro2::TokenStream) -> Result<bool> {
+    Ok(t.rendered()? == DISPLAY_STRING_PARAM.replace(' ', ""))
+}
+
 fn is_multipart(
     type_space: &mut crate::types::TypeSpace,
     name: &str,
@@ -640,6 +2574,377 @@ fn is_multipart(
     Ok(false)
 }
 
+/// The limits a multipart request body declares on its attachments.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+struct AttachmentLimits {
+    /// The most attachments allowed, from `maxItems` on the attachments array.
+    max_items: Option<usize>,
+    /// The largest size of a single attachment in bytes, from `x-max-size-bytes`.
+    max_size_bytes: Option<u64>,
+}
+
+impl AttachmentLimits {
+    fn is_empty(&self) -> bool {
+        self.max_items.is_none() && self.max_size_bytes.is_none()
+    }
+}
+
+/// Return the limits on the attachments of a multipart operation.
+///
+/// The count comes from `maxItems` on the body itself if it is an array, or on its
+/// first array of binary strings if it is an object. The size comes from an
+/// `x-max-size-bytes` extension, the most specific of the array items, the array,
+/// the body schema or the media type wins.
+fn get_attachment_limits(
+    spec: &openapiv3::OpenAPI,
+    op: &openapiv3::Operation,
+) -> Result<AttachmentLimits> {
+    let Some(request_body) = &op.request_body else {
+        return Ok(AttachmentLimits::default());
+    };
+    let request_body = request_body.expand(spec)?;
+    let Some(content) = request_body.content.get("multipart/form-data") else {
+        return Ok(AttachmentLimits::default());
+    };
+
+    let max_size_bytes = |extensions: &indexmap::IndexMap<String, serde_json::Value>| {
+        extensions.get("x-max-size-bytes").and_then(|v| v.as_u64())
+    };
+
+    let mut limits = AttachmentLimits {
+        max_items: None,
+        max_size_bytes: max_size_bytes(&content.extensions),
+    };
+
+    let Some(schema) = &content.schema else {
+        return Ok(limits);
+    };
+    let schema = schema.expand(spec)?;
+    if let Some(size) = max_size_bytes(&schema.schema_data.extensions) {
+        limits.max_size_bytes = Some(size);
+    }
+
+    let array = match &schema.schema_kind {
+        openapiv3::SchemaKind::Type(openapiv3::Type::Array(array)) => Some(array.clone()),
+        openapiv3::SchemaKind::Type(openapiv3::Type::Object(object)) => {
+            let mut found = None;
+            for property in object.properties.values() {
+                let property = property.get_schema_from_reference(spec, true)?;
+                if let openapiv3::SchemaKind::Type(openapiv3::Type::Array(array)) =
+                    &property.schema_kind
+                {
+                    if is_binary_items(spec, array)? {
+                        if let Some(size) = max_size_bytes(&property.schema_data.extensions) {
+                            limits.max_size_bytes = Some(size);
+                        }
+                        found = Some(array.clone());
+                        break;
+                    }
+                }
+            }
+            found
+        }
+        _ => None,
+    };
+
+    if let Some(array) = array {
+        limits.max_items = array.max_items;
+        if let Some(items) = &array.items {
+            if let Some(size) = max_size_bytes(&items.expand(spec)?.schema_data.extensions) {
+                limits.max_size_bytes = Some(size);
+            }
+        }
+    }
+
+    Ok(limits)
+}
+
+/// Returns true if the items of the array are binary strings, i.e. files.
+fn is_binary_items(spec: &openapiv3::OpenAPI, array: &openapiv3::ArrayType) -> Result<bool> {
+    let Some(items) = &array.items else {
+        return Ok(false);
+    };
+
+    Ok(matches!(
+        items.expand(spec)?.schema_kind,
+        openapiv3::SchemaKind::Type(openapiv3::Type::String(openapiv3::StringType {
+            format: openapiv3::VariantOrUnknownOrEmpty::Item(openapiv3::StringFormat::Binary),
+            ..
+        }))
+    ))
+}
+
+/// Returns true if the schema is a binary string, i.e. a file.
+fn is_binary(schema: &openapiv3::Schema) -> bool {
+    matches!(
+        schema.schema_kind,
+        openapiv3::SchemaKind::Type(openapiv3::Type::String(openapiv3::StringType {
+            format: openapiv3::VariantOrUnknownOrEmpty::Item(openapiv3::StringFormat::Binary),
+            ..
+        }))
+    )
+}
+
+/// A multipart request body made of typed options, sent as a JSON part, and of
+/// files.
+struct MultipartOptions {
+    /// The name of the JSON part, the property of the options.
+    name: String,
+    /// The schema of the options, a reference.
+    schema: openapiv3::ReferenceOr<openapiv3::Schema>,
+    /// The content type of the JSON part, from the `encoding` of the spec.
+    content_type: String,
+    /// The names of the file parts.
+    files: Vec<String>,
+}
+
+/// Returns the options of a multipart request body whose schema is an object with
+/// one property referencing an object schema, and binary properties for the files.
+/// The function then takes the options, and we assemble the parts.
+fn get_multipart_options(
+    spec: &openapiv3::OpenAPI,
+    op: &openapiv3::Operation,
+) -> Result<Option<MultipartOptions>> {
+    let Some(request_body) = &op.request_body else {
+        return Ok(None);
+    };
+    let request_body = request_body.expand(spec)?;
+
+    // The first media type with a schema is the one we send.
+    let Some((media_type, content)) = request_body
+        .content
+        .iter()
+        .find(|(_, content)| content.schema.is_some())
+    else {
+        return Ok(None);
+    };
+    if media_type != "multipart/form-data" {
+        return Ok(None);
+    }
+    let Some(schema) = &content.schema else {
+        return Ok(None);
+    };
+    let openapiv3::SchemaKind::Type(openapiv3::Type::Object(object)) =
+        schema.expand(spec)?.schema_kind
+    else {
+        return Ok(None);
+    };
+
+    let mut options = None;
+    let mut files = Vec::new();
+    for (name, property) in &object.properties {
+        let expanded = property.get_schema_from_reference(spec, true)?;
+        let is_file = match &expanded.schema_kind {
+            openapiv3::SchemaKind::Type(openapiv3::Type::Array(array)) => {
+                is_binary_items(spec, array)?
+            }
+            _ => is_binary(&expanded),
+        };
+        if is_file {
+            files.push(name.to_string());
+            continue;
+        }
+
+        // Inline options have no type of their own to take.
+        let (openapiv3::ReferenceOr::Reference { reference }, None) = (property, &options) else {
+            return Ok(None);
+        };
+        let openapiv3::SchemaKind::Type(openapiv3::Type::Object(_)) = expanded.schema_kind else {
+            return Ok(None);
+        };
+        options = Some((
+            name.to_string(),
+            openapiv3::ReferenceOr::Reference {
+                reference: reference.to_string(),
+            },
+        ));
+    }
+
+    let Some((name, schema)) = options else {
+        return Ok(None);
+    };
+    if files.is_empty() {
+        return Ok(None);
+    }
+    let content_type = content
+        .encoding
+        .get(&name)
+        .and_then(|encoding| encoding.content_type.clone())
+        .unwrap_or_else(|| "application/json".to_string());
+
+    Ok(Some(MultipartOptions {
+        name,
+        schema,
+        content_type,
+        files,
+    }))
+}
+
+/// Generate the code that checks the attachments against the declared limits, so
+/// we fail before uploading anything.
+fn gen_attachment_limits_code(limits: &AttachmentLimits) -> TokenStream {
+    let max_items = limits.max_items.map(|max_items| {
+        let max_items = proc_macro2::Literal::usize_unsuffixed(max_items);
+        quote! {
+            if attachments.len() > #max_items {
+                return Err(crate::types::error::Error::InvalidRequest(format!(
+                    "at most {} attachments are allowed, got {}",
+                    #max_items,
+                    attachments.len()
+                )));
+            }
+        }
+    });
+
+    let max_size_bytes = limits.max_size_bytes.map(|max_size_bytes| {
+        let max_size_bytes = proc_macro2::Literal::u64_unsuffixed(max_size_bytes);
+        quote! {
+            for attachment in &attachments {
+                if let Some(size) = attachment.size_hint() {
+                    if size > #max_size_bytes {
+                        return Err(crate::types::error::Error::InvalidRequest(format!(
+                            "attachment `{}` is {} bytes, the limit is {} bytes",
+                            attachment.name,
+                            size,
+                            #max_size_bytes
+                        )));
+                    }
+                }
+            }
+        }
+    });
+
+    quote! {
+        #max_items
+        #max_size_bytes
+    }
+}
+
+/// Generate the code that checks the arrays of unique items in the request body,
+/// so we fail before sending duplicates. Arrays rendered as sets are unique already.
+fn gen_unique_items_code(
+    type_space: &crate::types::TypeSpace,
+    op: &openapiv3::Operation,
+) -> Result<TokenStream> {
+    if !type_space.opts.unique_items_as_sets {
+        return Ok(quote!());
+    }
+    let Some(request_body) = &op.request_body else {
+        return Ok(quote!());
+    };
+    let request_body = request_body.expand(&type_space.spec)?;
+
+    // This is the same request body we take as an argument.
+    let Some((media_type, schema)) = request_body
+        .content
+        .iter()
+        .find_map(|(media_type, content)| Some((media_type, content.schema.as_ref()?)))
+    else {
+        return Ok(quote!());
+    };
+    if media_type != "application/json" && media_type != "application/x-www-form-urlencoded" {
+        return Ok(quote!());
+    }
+
+    if let openapiv3::ReferenceOr::Item(s) = schema {
+        if crate::types::get_unique_items(s).is_some() {
+            return for_request_body(
+                &type_space.spec,
+                op,
+                quote! {
+                    crate::types::error::check_unique_items("body", body)?;
+                },
+            );
+        }
+    }
+
+    // The body type has a method checking its arrays.
+    if let openapiv3::SchemaKind::Type(openapiv3::Type::Object(o)) =
+        &schema.expand(&type_space.spec)?.schema_kind
+    {
+        if o.properties.iter().any(|(k, v)| {
+            type_space.get_property_unique_items(&crate::types::clean_property_name(k), v)
+                == Some(crate::types::UniqueItems::Validate)
+        }) {
+            return for_request_body(
+                &type_space.spec,
+                op,
+                quote! {
+                    body.check_unique_items()?;
+                },
+            );
+        }
+    }
+
+    Ok(quote!())
+}
+
+/// Run `check` on the request body, only if there is one when the function takes
+/// it as an option, see `is_optional_request_body`.
+fn for_request_body(
+    spec: &openapiv3::OpenAPI,
+    op: &openapiv3::Operation,
+    check: TokenStream,
+) -> Result<TokenStream> {
+    if is_optional_request_body(spec, op)? {
+        Ok(quote! {
+            if let Some(body) = body {
+                #check
+            }
+        })
+    } else {
+        Ok(check)
+    }
+}
+
+/// Generate the code that checks the request body against the constraints of the
+/// spec, see `Opts::validate_requests`.
+fn gen_validate_code(
+    type_space: &crate::types::TypeSpace,
+    op: &openapiv3::Operation,
+) -> Result<TokenStream> {
+    if !type_space.opts.validate_requests {
+        return Ok(quote!());
+    }
+    let Some(request_body) = &op.request_body else {
+        return Ok(quote!());
+    };
+    let request_body = request_body.expand(&type_space.spec)?;
+
+    // This is the same request body we take as an argument.
+    let Some((media_type, schema)) = request_body
+        .content
+        .iter()
+        .find_map(|(media_type, content)| Some((media_type, content.schema.as_ref()?)))
+    else {
+        return Ok(quote!());
+    };
+    if media_type != "application/json" && media_type != "application/x-www-form-urlencoded" {
+        return Ok(quote!());
+    }
+
+    // The body type has a `validate` method if it has constraints to check.
+    if let openapiv3::SchemaKind::Type(openapiv3::Type::Object(o)) =
+        &schema.expand(&type_space.spec)?.schema_kind
+    {
+        if !type_space.get_validation_checks(o)?.is_empty() {
+            return for_request_body(
+                &type_space.spec,
+                op,
+                quote! {
+                    body.validate().map_err(|errors| {
+                        crate::types::error::Error::InvalidRequest(
+                            crate::types::validation::describe(&errors),
+                        )
+                    })?;
+                },
+            );
+        }
+    }
+
+    Ok(quote!())
+}
+
 /// Return the request body type for the operation.
 fn get_request_body(
     type_space: &mut crate::types::TypeSpace,
@@ -647,6 +2952,23 @@ fn get_request_body(
     method: &http::Method,
     op: &openapiv3::Operation,
 ) -> Result<Option<RequestOrResponse>> {
+    // The function doesn't take an empty object, we send it ourselves.
+    if get_empty_object_body(&type_space.spec, op)?.is_some() {
+        return Ok(None);
+    }
+
+    // The function takes the options of a multipart body, not the whole body.
+    if let Some(options) = get_multipart_options(&type_space.spec, op)? {
+        return Ok(Some(RequestOrResponse {
+            media_type: "multipart/form-data".to_string(),
+            type_name: crate::types::get_type_name_from_reference(
+                &options.schema.reference()?,
+                &type_space.spec,
+                false,
+            )?,
+        }));
+    }
+
     if let Some(request_body) = &op.request_body {
         // Then let's get the type for the response.
         let request_body = request_body.expand(&type_space.spec)?;
@@ -692,13 +3014,101 @@ fn get_request_body(
     Ok(None)
 }
 
+/// Returns `Some(required)` if the request body of the operation is a JSON object
+/// without properties, it can only be `{}`. The function doesn't take it, and sends
+/// `{}` if it is required and nothing otherwise.
+fn get_empty_object_body(
+    spec: &openapiv3::OpenAPI,
+    op: &openapiv3::Operation,
+) -> Result<Option<bool>> {
+    let Some(request_body) = &op.request_body else {
+        return Ok(None);
+    };
+    let request_body = request_body.expand(spec)?;
+
+    // The first media type with a schema is the one we send.
+    let Some((media_type, schema)) = request_body
+        .content
+        .iter()
+        .find_map(|(media_type, content)| Some((media_type, content.schema.as_ref()?)))
+    else {
+        return Ok(None);
+    };
+    if media_type != "application/json" {
+        return Ok(None);
+    }
+
+    let is_empty = match schema.expand(spec)?.schema_kind {
+        openapiv3::SchemaKind::Type(openapiv3::Type::Object(o)) => {
+            o.properties.is_empty()
+                && !matches!(
+                    o.additional_properties,
+                    Some(openapiv3::AdditionalProperties::Schema(_))
+                )
+        }
+        _ => false,
+    };
+
+    Ok(is_empty.then_some(request_body.required))
+}
+
+/// Returns `true` if the function takes the request body of the operation as an
+/// `Option<&T>`, because the spec does not require it. `None` sends no body and no
+/// `Content-Type`, which the server can treat differently than an empty object.
+/// Empty objects and multipart bodies are not taken as an option.
+fn is_optional_request_body(spec: &openapiv3::OpenAPI, op: &openapiv3::Operation) -> Result<bool> {
+    let Some(request_body) = &op.request_body else {
+        return Ok(false);
+    };
+    if get_empty_object_body(spec, op)?.is_some() || get_multipart_options(spec, op)?.is_some() {
+        return Ok(false);
+    }
+    let request_body = request_body.expand(spec)?;
+
+    // The first media type with a schema is the one we send.
+    let Some(media_type) = request_body
+        .content
+        .iter()
+        .find_map(|(media_type, content)| content.schema.as_ref().map(|_| media_type))
+    else {
+        return Ok(false);
+    };
+
+    Ok(!request_body.required && media_type != "multipart/form-data")
+}
+
 /// Return the request body type example for the operation.
 fn get_request_body_example(
     type_space: &crate::types::TypeSpace,
     name: &str,
     method: &http::Method,
     op: &openapiv3::Operation,
+    example_key: Option<&str>,
 ) -> Result<Option<RequestOrResponse>> {
+    // The function doesn't take an empty object.
+    if get_empty_object_body(&type_space.spec, op)?.is_some() {
+        return Ok(None);
+    }
+
+    // The function takes the options of a multipart body, not the whole body.
+    if let Some(options) = get_multipart_options(&type_space.spec, op)? {
+        let schema = options.schema;
+        let name = crate::types::get_type_name_from_reference(
+            &schema.reference()?,
+            &type_space.spec,
+            true,
+        )?;
+        return Ok(Some(RequestOrResponse {
+            media_type: "multipart/form-data".to_string(),
+            type_name: crate::types::example::generate_example_rust_from_schema(
+                type_space,
+                &name.rendered()?,
+                &schema.expand(&type_space.spec)?,
+                false,
+            )?,
+        }));
+    }
+
     if let Some(request_body) = &op.request_body {
         // Then let's get the type for the response.
         let request_body = request_body.expand(&type_space.spec)?;
@@ -706,6 +3116,37 @@ fn get_request_body_example(
         // Iterate over all the media types and return the first request.
         for (media_type, content) in &request_body.content {
             if let Some(s) = &content.schema {
+                // The example of the spec is read into the type as it is.
+                let is_json = media_type == "application/json" || media_type.ends_with("+json");
+                let example = pick_example(
+                    &type_space.spec,
+                    &content.examples,
+                    &content.example,
+                    example_key,
+                )?;
+                if let Some(example) = example.filter(|_| is_json) {
+                    let t = match s {
+                        openapiv3::ReferenceOr::Reference { .. } => {
+                            crate::types::get_type_name_from_reference(
+                                &s.reference()?,
+                                &type_space.spec,
+                                false,
+                            )?
+                        }
+                        openapiv3::ReferenceOr::Item(s) => crate::types::get_type_name_for_schema(
+                            &generate_name_for_fn_schema(name, method, s, op, "Request Body"),
+                            s,
+                            &type_space.spec,
+                            false,
+                        )?,
+                    };
+                    let example = serde_json::to_string(&example)?;
+                    return Ok(Some(RequestOrResponse {
+                        media_type: media_type.to_string(),
+                        type_name: quote!(serde_json::from_str::<#t>(#example)?),
+                    }));
+                }
+
                 let t = match s {
                     openapiv3::ReferenceOr::Reference { .. } => {
                         let name = crate::types::get_type_name_from_reference(
@@ -745,6 +3186,81 @@ fn get_request_body_example(
     Ok(None)
 }
 
+/// The named examples of a parameter or a media type, with the references looked
+/// up. The ones with only an `externalValue` are somewhere else, we leave them out.
+pub(crate) fn get_named_examples(
+    spec: &openapiv3::OpenAPI,
+    examples: &indexmap::IndexMap<String, openapiv3::ReferenceOr<openapiv3::Example>>,
+) -> Result<Vec<(String, serde_json::Value)>> {
+    let mut values = Vec::new();
+    for (name, example) in examples {
+        let example = match example {
+            openapiv3::ReferenceOr::Item(example) => example.clone(),
+            openapiv3::ReferenceOr::Reference { reference } => {
+                let name = reference.trim_start_matches("#/components/examples/");
+                spec.components
+                    .as_ref()
+                    .and_then(|components| components.examples.get(name))
+                    .and_then(|example| example.as_item())
+                    .cloned()
+                    .ok_or_else(|| anyhow::anyhow!("example `{}` not found", reference))?
+            }
+        };
+        if let Some(value) = example.value {
+            values.push((name.to_string(), value));
+        }
+    }
+    Ok(values)
+}
+
+/// The example of a parameter or a media type to use in the example code: the
+/// named one called `key`, else the first named one, else the unnamed one.
+fn pick_example(
+    spec: &openapiv3::OpenAPI,
+    examples: &indexmap::IndexMap<String, openapiv3::ReferenceOr<openapiv3::Example>>,
+    example: &Option<serde_json::Value>,
+    key: Option<&str>,
+) -> Result<Option<serde_json::Value>> {
+    let examples = get_named_examples(spec, examples)?;
+    let named = examples
+        .iter()
+        .find(|(name, _)| Some(name.as_str()) == key)
+        .or_else(|| examples.first())
+        .map(|(_, value)| value.clone());
+    Ok(named.or_else(|| example.clone()))
+}
+
+/// The name of the named examples the example code of an operation takes its
+/// values from, the same for the body and all the parameters, so they go
+/// together: the first one of the body, else the first one of a parameter.
+fn get_example_key(
+    spec: &openapiv3::OpenAPI,
+    op: &openapiv3::Operation,
+    global_params: &[openapiv3::ReferenceOr<openapiv3::Parameter>],
+) -> Result<Option<String>> {
+    if let Some(request_body) = &op.request_body {
+        let request_body = request_body.expand(spec)?;
+        if let Some(content) = request_body
+            .content
+            .values()
+            .find(|content| content.schema.is_some())
+        {
+            if let Some((name, _)) = get_named_examples(spec, &content.examples)?.first() {
+                return Ok(Some(name.to_string()));
+            }
+        }
+    }
+
+    for parameter in op.get_parameters(spec, global_params)? {
+        let parameter_data = parameter.parameter_data_ref();
+        if let Some((name, _)) = get_named_examples(spec, &parameter_data.examples)?.first() {
+            return Ok(Some(name.to_string()));
+        }
+    }
+
+    Ok(None)
+}
+
 /// Return the function arguments for the operation.
 fn get_example_args(
     name: &str,
@@ -752,13 +3268,15 @@ fn get_example_args(
     type_space: &crate::types::TypeSpace,
     op: &openapiv3::Operation,
     global_params: &[openapiv3::ReferenceOr<openapiv3::Parameter>],
+    example_key: Option<&str>,
 ) -> Result<BTreeMap<String, proc_macro2::TokenStream>> {
-    let mut params = get_path_params_schema(op, &type_space.spec, global_params)?;
-    params.append(&mut get_query_params_schema(
-        op,
-        &type_space.spec,
-        global_params,
-    )?);
+    let params = get_all_params_schema(type_space, op, global_params)?;
+    // The arrays of the header and cookie params are taken as slices.
+    let slice_params: BTreeSet<String> =
+        get_header_params_schema(op, &type_space.spec, global_params)?
+            .into_keys()
+            .chain(get_cookie_params_schema(op, &type_space.spec, global_params)?.into_keys())
+            .collect();
 
     let mut new_params: BTreeMap<String, proc_macro2::TokenStream> = Default::default();
 
@@ -771,44 +3289,56 @@ fn get_example_args(
                 true,
             )?,
             openapiv3::ReferenceOr::Item(s) => {
-                let mut t_name =
-                    crate::types::get_type_name_for_schema(&name, s, &type_space.spec, true)?;
-                // Check if we should render the schema.
                 if schema.should_render()? {
-                    // Check if we already have a type with this name.
-                    if let Some(rendered) = type_space
-                        .types
-                        .get(&t_name.strip_option()?.strip_vec()?.rendered()?)
-                    {
-                        if rendered != s {
-                            // Update the name of the type.
-                            t_name = crate::types::get_type_name_for_schema(
-                                &format!("{} {}", op.get_fn_name()?, name),
-                                s,
-                                &type_space.spec,
-                                true,
-                            )?;
-                        }
-                    }
+                    get_param_type_name(type_space, op, &name, s, true)?.0
+                } else {
+                    crate::types::get_type_name_for_schema(&name, s, &type_space.spec, true)?
                 }
-
-                t_name
             }
         };
 
-        // Let's get the example rust code for the schema.
-        let mut example = crate::types::example::generate_example_rust_from_schema(
-            type_space,
-            &t.rendered()?,
-            &schema.expand(&type_space.spec)?,
-            false,
+        // Let's get the example rust code for the schema, the one of the spec if it
+        // has one we can write.
+        let expanded = schema.expand(&type_space.spec)?;
+        let value = pick_example(
+            &type_space.spec,
+            &parameter_data.examples,
+            &parameter_data.example,
+            example_key,
         )?;
+        let example = match value {
+            Some(value) => crate::types::example::generate_example_rust_from_value(
+                type_space,
+                &t.rendered()?,
+                &expanded,
+                &value,
+                false,
+            )?,
+            None => None,
+        };
+        let mut example = match example {
+            Some(example) => example,
+            None => crate::types::example::generate_example_rust_from_schema(
+                type_space,
+                &t.rendered()?,
+                &expanded,
+                false,
+            )?,
+        };
+
+        if slice_params.contains(&name) && t.strip_option()?.is_vec()? {
+            example = quote!(&#example);
+        }
 
         if !parameter_data.required
             && !example
                 .rendered()?
                 .starts_with("crate::types::phone_number::PhoneNumber")
         {
+            // The optional strings are borrowed with `--display-string-params`.
+            if type_space.opts.display_string_params && t.strip_option()?.is_string()? {
+                example = example.strip_to_string()?;
+            }
             example = quote!(Some(#example));
         } else if t.is_string()? {
             // Fix the parameter to be a &str, if it is a String.
@@ -821,11 +3351,15 @@ fn get_example_args(
     // Add attachments if we have a multipart request.
     if let Some(request_body) = get_request_body(&mut type_space.clone(), name, method, op)? {
         if request_body.media_type.as_str() == "multipart/form-data" {
+            // Name the file after a part of the spec, if it has them.
+            let file = get_multipart_options(&type_space.spec, op)?
+                .and_then(|options| options.files.into_iter().next())
+                .unwrap_or_else(|| "thing".to_string());
             new_params.insert(
                 "attachments".to_string(),
                 quote! {
                     vec![crate::types::multipart::Attachment {
-                        name: "thing".to_string(),
+                        name: #file.to_string(),
                         filename: Some("myfile.json".to_string()),
                         content_type: Some("application/json".to_string()),
                         data: std::fs::read("myfile.json").unwrap(),
@@ -861,16 +3395,8 @@ fn get_path_params_schema(
         ),
     > = Default::default();
 
-    let mut parameters = op.parameters.clone();
-    let mut global_params = global_params.to_vec();
-    // Add in our global_params.
-    parameters.append(&mut global_params);
-
-    // Let's get the arguments for the function.
-    for parameter in &parameters {
-        // Get the parameter.
-        let parameter = parameter.expand(spec)?;
-
+    // The parameters of the operation override the ones of its path.
+    for parameter in op.get_parameters(spec, global_params)? {
         // Get the data for the parameter.
         // We only care about path parameters, currently.
         if let openapiv3::Parameter::Path {
@@ -908,27 +3434,13 @@ fn get_path_params(
                 false,
             )?,
             openapiv3::ReferenceOr::Item(ref s) => {
-                let mut t_name =
-                    crate::types::get_type_name_for_schema(&name, s, &type_space.spec, false)?;
-                // Check if we should render the schema.
                 if schema.should_render()? {
-                    // Check if we already have a type with this name.
-                    if let Some(rendered) = type_space.types.get(&t_name.rendered()?) {
-                        if rendered != s {
-                            // Update the name of the type.
-                            t_name = crate::types::get_type_name_for_schema(
-                                &format!("{} {}", op.get_fn_name()?, name),
-                                s,
-                                &type_space.spec,
-                                false,
-                            )?;
-                        }
-                    }
-
+                    let (t_name, _) = get_param_type_name(type_space, op, &name, s, false)?;
                     type_space.render_schema(&t_name.rendered()?, s)?;
+                    t_name
+                } else {
+                    crate::types::get_type_name_for_schema(&name, s, &type_space.spec, false)?
                 }
-
-                t_name
             }
         };
 
@@ -938,12 +3450,81 @@ fn get_path_params(
         }
 
         // Add path parameter to our list.
-        path_params.insert(name, t.get_parameter_value()?);
+        path_params.insert(name, get_param_arg_type(type_space, &t)?);
     }
 
     Ok(path_params)
 }
 
+/// A query parameter or header that makes an operation wait until it is done, see
+/// `Opts::wait_params`.
+struct WaitParam {
+    /// The name of the parameter.
+    name: String,
+    /// If it is sent as a header rather than in the query.
+    header: bool,
+    /// The value that makes the operation wait, for the `timeout` of the function.
+    value: proc_macro2::TokenStream,
+    /// The value the function sends itself, if any.
+    immediate: Option<proc_macro2::TokenStream>,
+}
+
+/// Returns true if the parameter makes the operation wait until it is done. Only
+/// the operations that can answer `202 Accepted` wait.
+fn is_wait_param(opts: &crate::Opts, op: &openapiv3::Operation, name: &str) -> bool {
+    op.responses
+        .responses
+        .contains_key(&openapiv3::StatusCode::Code(202))
+        && opts
+            .wait_params
+            .iter()
+            .any(|wait_param| wait_param.eq_ignore_ascii_case(name))
+}
+
+/// Return the parameter that makes the operation wait until it is done, if it takes
+/// one.
+fn get_wait_param(
+    type_space: &crate::types::TypeSpace,
+    op: &openapiv3::Operation,
+    global_params: &[openapiv3::ReferenceOr<openapiv3::Parameter>],
+) -> Result<Option<WaitParam>> {
+    let query_params = get_query_params_schema(op, &type_space.spec, global_params)?;
+    let header_params = get_header_params_schema(op, &type_space.spec, global_params)?;
+    let params = query_params
+        .into_iter()
+        .map(|param| (param, false))
+        .chain(header_params.into_iter().map(|param| (param, true)));
+
+    for ((name, (schema, _)), header) in params {
+        if !is_wait_param(&type_space.opts, op, &name) {
+            continue;
+        }
+
+        let schema = schema.expand(&type_space.spec)?;
+        let (value, immediate) = match schema.schema_kind {
+            openapiv3::SchemaKind::Type(openapiv3::Type::Boolean { .. }) => (quote!("true"), None),
+            openapiv3::SchemaKind::Type(openapiv3::Type::Integer(_))
+            | openapiv3::SchemaKind::Type(openapiv3::Type::Number(_)) => {
+                (quote!(timeout.as_secs().to_string()), None)
+            }
+            // Like `Prefer: wait=30`, and `Prefer: respond-async` to not wait.
+            _ => (
+                quote!(format!("wait={}", timeout.as_secs())),
+                header.then(|| quote!("respond-async")),
+            ),
+        };
+
+        return Ok(Some(WaitParam {
+            name,
+            header,
+            value,
+            immediate,
+        }));
+    }
+
+    Ok(None)
+}
+
 /// Return the query params for the operation.
 fn get_query_params_schema(
     op: &openapiv3::Operation,
@@ -957,9 +3538,247 @@ fn get_query_params_schema(
             openapiv3::ParameterData,
         ),
     >,
-> {
-    // Let's get the path parameters.
-    let mut query_params: BTreeMap<
+> {
+    // Let's get the path parameters.
+    let mut query_params: BTreeMap<
+        String,
+        (
+            openapiv3::ReferenceOr<openapiv3::Schema>,
+            openapiv3::ParameterData,
+        ),
+    > = Default::default();
+
+    // The parameters of the operation override the ones of its path.
+    for parameter in op.get_parameters(spec, global_params)? {
+        // Get the data for the parameter.
+        // We only care about query parameters, currently.
+        if let openapiv3::Parameter::Query {
+            parameter_data,
+            style: _,
+            allow_reserved: _,
+            allow_empty_value: _,
+        } = parameter
+        {
+            // Get the schema for the parameter.
+            let schema = parameter_data.format.schema()?;
+
+            // Add query parameter to our list.
+            query_params.insert(parameter_data.name.to_string(), (schema, parameter_data));
+        }
+    }
+
+    Ok(query_params)
+}
+
+/// Return the query params for the operation.
+fn get_query_params(
+    type_space: &mut crate::types::TypeSpace,
+    op: &openapiv3::Operation,
+    global_params: &[openapiv3::ReferenceOr<openapiv3::Parameter>],
+) -> Result<BTreeMap<String, proc_macro2::TokenStream>> {
+    let params = get_query_params_schema(op, &type_space.spec, global_params)?;
+    let params = without_wait_params(type_space, op, params);
+    get_params_types(type_space, op, params)
+}
+
+/// Return the header params for the operation.
+fn get_header_params_schema(
+    op: &openapiv3::Operation,
+    spec: &openapiv3::OpenAPI,
+    global_params: &[openapiv3::ReferenceOr<openapiv3::Parameter>],
+) -> Result<
+    BTreeMap<
+        String,
+        (
+            openapiv3::ReferenceOr<openapiv3::Schema>,
+            openapiv3::ParameterData,
+        ),
+    >,
+> {
+    get_params_schema_where(op, spec, global_params, |parameter| match parameter {
+        openapiv3::Parameter::Header {
+            parameter_data,
+            style: _,
+        } => Some(parameter_data),
+        _ => None,
+    })
+}
+
+/// Return the header params for the operation.
+fn get_header_params(
+    type_space: &mut crate::types::TypeSpace,
+    op: &openapiv3::Operation,
+    global_params: &[openapiv3::ReferenceOr<openapiv3::Parameter>],
+) -> Result<BTreeMap<String, proc_macro2::TokenStream>> {
+    let params = get_header_params_schema(op, &type_space.spec, global_params)?;
+    let params = without_wait_params(type_space, op, params);
+    as_slice_params(get_params_types(type_space, op, params)?)
+}
+
+/// Return the cookie params for the operation.
+fn get_cookie_params_schema(
+    op: &openapiv3::Operation,
+    spec: &openapiv3::OpenAPI,
+    global_params: &[openapiv3::ReferenceOr<openapiv3::Parameter>],
+) -> Result<
+    BTreeMap<
+        String,
+        (
+            openapiv3::ReferenceOr<openapiv3::Schema>,
+            openapiv3::ParameterData,
+        ),
+    >,
+> {
+    get_params_schema_where(op, spec, global_params, |parameter| match parameter {
+        openapiv3::Parameter::Cookie {
+            parameter_data,
+            style: _,
+        } => Some(parameter_data),
+        _ => None,
+    })
+}
+
+/// Return the cookie params for the operation.
+fn get_cookie_params(
+    type_space: &mut crate::types::TypeSpace,
+    op: &openapiv3::Operation,
+    global_params: &[openapiv3::ReferenceOr<openapiv3::Parameter>],
+) -> Result<BTreeMap<String, proc_macro2::TokenStream>> {
+    let params = get_cookie_params_schema(op, &type_space.spec, global_params)?;
+    as_slice_params(get_params_types(type_space, op, params)?)
+}
+
+/// Take the arrays of header and cookie params as slices, `&'a [T]`, they are only
+/// read to build the header.
+fn as_slice_params(
+    params: BTreeMap<String, proc_macro2::TokenStream>,
+) -> Result<BTreeMap<String, proc_macro2::TokenStream>> {
+    params
+        .into_iter()
+        .map(|(name, t)| {
+            let rendered = t.rendered()?;
+            let item = |vec: &str| -> Result<proc_macro2::TokenStream> {
+                vec.strip_prefix("Vec<")
+                    .and_then(|vec| vec.strip_suffix('>'))
+                    .unwrap_or(vec)
+                    .parse()
+                    .map_err(|e| anyhow::anyhow!("{}", e))
+            };
+            let t = if t.is_vec()? {
+                let item = item(&rendered)?;
+                quote!(&'a [#item])
+            } else if t.is_option_vec()? {
+                let item = item(
+                    rendered
+                        .strip_prefix("Option<")
+                        .and_then(|vec| vec.strip_suffix('>'))
+                        .unwrap_or(&rendered),
+                )?;
+                quote!(Option<&'a [#item]>)
+            } else {
+                t
+            };
+            Ok((name, t))
+        })
+        .collect()
+}
+
+/// The item type of a param taken as a slice, `&'a [T]` or `Option<&'a [T]>`.
+fn get_slice_param_item(t: &proc_macro2::TokenStream) -> Result<Option<proc_macro2::TokenStream>> {
+    let rendered = t.rendered()?;
+    let rendered = rendered
+        .strip_prefix("Option<")
+        .and_then(|t| t.strip_suffix('>'))
+        .unwrap_or(&rendered);
+    rendered
+        .strip_prefix("&'a[")
+        .and_then(|t| t.strip_suffix(']'))
+        .map(|item| item.parse().map_err(|e| anyhow::anyhow!("{}", e)))
+        .transpose()
+}
+
+/// The names of the params sent once for each item of their array, with
+/// `explode: true`. The others are sent once, with the items joined by commas.
+fn get_exploded_params(
+    params: &BTreeMap<
+        String,
+        (
+            openapiv3::ReferenceOr<openapiv3::Schema>,
+            openapiv3::ParameterData,
+        ),
+    >,
+) -> BTreeSet<String> {
+    params
+        .iter()
+        .filter(|(_, (_, parameter_data))| parameter_data.explode == Some(true))
+        .map(|(name, _)| name.to_string())
+        .collect()
+}
+
+/// Return every param the function of the operation takes, wherever it is sent.
+fn get_all_params_schema(
+    type_space: &crate::types::TypeSpace,
+    op: &openapiv3::Operation,
+    global_params: &[openapiv3::ReferenceOr<openapiv3::Parameter>],
+) -> Result<
+    BTreeMap<
+        String,
+        (
+            openapiv3::ReferenceOr<openapiv3::Schema>,
+            openapiv3::ParameterData,
+        ),
+    >,
+> {
+    let spec = &type_space.spec;
+    let mut params = get_path_params_schema(op, spec, global_params)?;
+    params.append(&mut without_wait_params(
+        type_space,
+        op,
+        get_query_params_schema(op, spec, global_params)?,
+    ));
+    params.append(&mut without_wait_params(
+        type_space,
+        op,
+        get_header_params_schema(op, spec, global_params)?,
+    ));
+    params.append(&mut get_cookie_params_schema(op, spec, global_params)?);
+
+    Ok(params)
+}
+
+/// Leave out the params that make the operation wait, the `_wait` function sets
+/// them.
+fn without_wait_params<V>(
+    type_space: &crate::types::TypeSpace,
+    op: &openapiv3::Operation,
+    params: BTreeMap<String, V>,
+) -> BTreeMap<String, V> {
+    params
+        .into_iter()
+        .filter(|(name, _)| !is_wait_param(&type_space.opts, op, name))
+        .collect()
+}
+
+/// Return the params for the operation where `select` returns the data of the
+/// parameter.
+fn get_params_schema_where<F>(
+    op: &openapiv3::Operation,
+    spec: &openapiv3::OpenAPI,
+    global_params: &[openapiv3::ReferenceOr<openapiv3::Parameter>],
+    select: F,
+) -> Result<
+    BTreeMap<
+        String,
+        (
+            openapiv3::ReferenceOr<openapiv3::Schema>,
+            openapiv3::ParameterData,
+        ),
+    >,
+>
+where
+    F: Fn(openapiv3::Parameter) -> Option<openapiv3::ParameterData>,
+{
+    let mut params: BTreeMap<
         String,
         (
             openapiv3::ReferenceOr<openapiv3::Schema>,
@@ -967,45 +3786,113 @@ fn get_query_params_schema(
         ),
     > = Default::default();
 
-    let mut parameters = op.parameters.clone();
-    let mut global_params = global_params.to_vec();
-    // Add in our global_params.
-    parameters.append(&mut global_params);
-
-    // Let's get the arguments for the function.
-    for parameter in &parameters {
-        // Get the parameter.
-        let parameter = parameter.expand(spec)?;
-
-        // Get the data for the parameter.
-        // We only care about query parameters, currently.
-        if let openapiv3::Parameter::Query {
-            parameter_data,
-            style: _,
-            allow_reserved: _,
-            allow_empty_value: _,
-        } = parameter
-        {
+    // The parameters of the operation override the ones of its path.
+    for parameter in op.get_parameters(spec, global_params)? {
+        if let Some(parameter_data) = select(parameter) {
             // Get the schema for the parameter.
             let schema = parameter_data.format.schema()?;
 
-            // Add query parameter to our list.
-            query_params.insert(parameter_data.name.to_string(), (schema, parameter_data));
+            params.insert(parameter_data.name.to_string(), (schema, parameter_data));
         }
     }
 
-    Ok(query_params)
+    Ok(params)
 }
 
-/// Return the query params for the operation.
-fn get_query_params(
-    type_space: &mut crate::types::TypeSpace,
+/// Return the types of the params for the operation.
+/// The type of the inline schema `s` of the parameter `name`, named after the
+/// parameter. When another schema has that name already, the type is named after
+/// the operation too, as a property is named after its object: `status` of `list`
+/// is a `ListStatus`. Also returns if the type was renamed.
+fn get_param_type_name(
+    type_space: &crate::types::TypeSpace,
+    op: &openapiv3::Operation,
+    name: &str,
+    s: &openapiv3::Schema,
+    in_crate: bool,
+) -> Result<(proc_macro2::TokenStream, bool)> {
+    let t_name = crate::types::get_type_name_for_schema(name, s, &type_space.spec, in_crate)?;
+    if !is_param_type_name_taken(type_space, &t_name, s)? {
+        return Ok((t_name, false));
+    }
+
+    let scoped = format!("{} {}", op.get_fn_name()?, name);
+    let mut t_name =
+        crate::types::get_type_name_for_schema(&scoped, s, &type_space.spec, in_crate)?;
+    // The name of the operation and the parameter can be taken too.
+    let mut n = 2;
+    while is_param_type_name_taken(type_space, &t_name, s)? {
+        t_name = crate::types::get_type_name_for_schema(
+            &format!("{} {}", scoped, n),
+            s,
+            &type_space.spec,
+            in_crate,
+        )?;
+        n += 1;
+    }
+    Ok((t_name, true))
+}
+
+/// Returns true if a schema other than the parameter schema `s` is rendered as the
+/// type `t` already.
+fn is_param_type_name_taken(
+    type_space: &crate::types::TypeSpace,
+    t: &proc_macro2::TokenStream,
+    s: &openapiv3::Schema,
+) -> Result<bool> {
+    let name = t
+        .strip_option()?
+        .strip_vec()?
+        .rendered()?
+        .replace("crate::types::", "");
+    // The type of an array is the type of its items.
+    let rendered_schema = match &s.schema_kind {
+        openapiv3::SchemaKind::Type(openapiv3::Type::Array(openapiv3::ArrayType {
+            items: Some(openapiv3::ReferenceOr::Item(items)),
+            ..
+        })) => items.as_ref(),
+        _ => s,
+    };
+    Ok(type_space
+        .types
+        .get(&name)
+        .is_some_and(|rendered| rendered != rendered_schema))
+}
+
+/// The types of the parameters of the operation named after the operation, since
+/// another schema had the name of the parameter. Keyed by the parameter.
+fn get_renamed_param_types(
+    type_space: &crate::types::TypeSpace,
     op: &openapiv3::Operation,
     global_params: &[openapiv3::ReferenceOr<openapiv3::Parameter>],
-) -> Result<BTreeMap<String, proc_macro2::TokenStream>> {
-    let params = get_query_params_schema(op, &type_space.spec, global_params)?;
+) -> Result<BTreeMap<String, String>> {
+    let mut renamed = BTreeMap::new();
+    for (name, (schema, _)) in get_all_params_schema(type_space, op, global_params)? {
+        let openapiv3::ReferenceOr::Item(s) = &schema else {
+            continue;
+        };
+        if !schema.should_render()? {
+            continue;
+        }
+        if let (t_name, true) = get_param_type_name(type_space, op, &name, s, true)? {
+            renamed.insert(name, t_name.strip_option()?.strip_vec()?.rendered()?);
+        }
+    }
+    Ok(renamed)
+}
 
-    let mut query_params: BTreeMap<String, proc_macro2::TokenStream> = Default::default();
+fn get_params_types(
+    type_space: &mut crate::types::TypeSpace,
+    op: &openapiv3::Operation,
+    params: BTreeMap<
+        String,
+        (
+            openapiv3::ReferenceOr<openapiv3::Schema>,
+            openapiv3::ParameterData,
+        ),
+    >,
+) -> Result<BTreeMap<String, proc_macro2::TokenStream>> {
+    let mut params_types: BTreeMap<String, proc_macro2::TokenStream> = Default::default();
 
     for (name, (schema, parameter_data)) in params {
         // Get the type for the parameter.
@@ -1016,33 +3903,13 @@ fn get_query_params(
                 false,
             )?,
             openapiv3::ReferenceOr::Item(ref s) => {
-                let mut t_name =
-                    crate::types::get_type_name_for_schema(&name, s, &type_space.spec, false)?;
-                // Check if we should render the schema.
                 if schema.should_render()? {
-                    // Check if we already have a type with this name.
-                    if let Some(rendered) = type_space.types.get(
-                        &t_name
-                            .strip_option()?
-                            .strip_vec()?
-                            .rendered()?
-                            .replace("crate::types::", ""),
-                    ) {
-                        if rendered != s {
-                            // Update the name of the type.
-                            t_name = crate::types::get_type_name_for_schema(
-                                &format!("{} {}", op.get_fn_name()?, name),
-                                s,
-                                &type_space.spec,
-                                false,
-                            )?;
-                        }
-                    }
-
+                    let (t_name, _) = get_param_type_name(type_space, op, &name, s, false)?;
                     type_space.render_schema(&t_name.rendered()?, s)?;
+                    t_name
+                } else {
+                    crate::types::get_type_name_for_schema(&name, s, &type_space.spec, false)?
                 }
-
-                t_name
             }
         };
 
@@ -1051,14 +3918,21 @@ fn get_query_params(
             t = quote!(Option<#t>);
         }
 
-        // Add query parameter to our list.
-        query_params.insert(name, t.get_parameter_value()?);
+        // Add the parameter to our list.
+        params_types.insert(name, get_param_arg_type(type_space, &t)?);
     }
 
-    Ok(query_params)
+    Ok(params_types)
 }
 
-fn clean_url_from(path_params: &BTreeMap<String, TokenStream>) -> Result<TokenStream> {
+/// The type of the date-time parameters, with `--date-time-format` we format them
+/// like the fields of the bodies, see `crate::template::generate_utils`.
+const DATE_TIME_PARAM: &str = "crate::types::datetime::DateTime";
+
+fn clean_url_from(
+    path_params: &BTreeMap<String, TokenStream>,
+    date_time_format: bool,
+) -> Result<TokenStream> {
     if path_params.is_empty() {
         return Ok(quote!());
     }
@@ -1068,14 +3942,16 @@ fn clean_url_from(path_params: &BTreeMap<String, TokenStream>) -> Result<TokenSt
         let cleaned_name = crate::types::clean_property_name(name);
         let name_ident = format_ident!("{}", cleaned_name);
 
-        clean_string = if t.is_string()? {
-            quote! {
-                #clean_string.replace(#url_string, #name_ident)
-            }
+        // Encode the values, so they match back with `crate::types::paths`.
+        let value = if t.is_string()? {
+            quote!(#name_ident)
+        } else if date_time_format && crate::types::get_text(t)? == DATE_TIME_PARAM {
+            quote!(&crate::utils::date_time_format::format(&#name_ident))
         } else {
-            quote! {
-                #clean_string.replace(#url_string, &format!("{}", #name_ident))
-            }
+            quote!(&format!("{}", #name_ident))
+        };
+        clean_string = quote! {
+            #clean_string.replace(#url_string, &crate::types::paths::encode_param(#value))
         };
     }
     Ok(clean_string)
@@ -1084,6 +3960,7 @@ fn clean_url_from(path_params: &BTreeMap<String, TokenStream>) -> Result<TokenSt
 fn gen_query_params_code(
     query_params: &BTreeMap<String, TokenStream>,
     paginated: bool,
+    date_time_format: bool,
 ) -> Result<TokenStream> {
     if query_params.is_empty() || paginated {
         return Ok(quote!());
@@ -1106,6 +3983,14 @@ fn gen_query_params_code(
                 required_params.push(quote! {
                    (#name, #name_ident)
                 })
+            } else if type_text == "&'astr" {
+                required_params.push(quote! {
+                   (#name, #name_ident.to_string())
+                })
+            } else if date_time_format && type_text == DATE_TIME_PARAM {
+                required_params.push(quote! {
+                   (#name, crate::utils::date_time_format::format(&#name_ident))
+                })
             } else {
                 required_params.push(quote! {
                    (#name, format!("{}", #name_ident))
@@ -1117,6 +4002,18 @@ fn gen_query_params_code(
                     query_params.push((#name, p));
                 }
             })
+        } else if type_text == "Option<&'astr>" {
+            optional_params.push(quote! {
+                if let Some(p) = #name_ident {
+                    query_params.push((#name, p.to_string()));
+                }
+            })
+        } else if date_time_format && type_text == format!("Option<{}>", DATE_TIME_PARAM) {
+            optional_params.push(quote! {
+                if let Some(p) = #name_ident {
+                    query_params.push((#name, crate::utils::date_time_format::format(&p)));
+                }
+            })
         } else if type_text == "crate::types::phone_number::PhoneNumber" {
             optional_params.push(quote! {
                 if let Some(p) = #name_ident.0 {
@@ -1150,6 +4047,183 @@ fn gen_query_params_code(
     })
 }
 
+/// The code checking the items of the array param `name_ident`, the values to send
+/// end up in `values`. A value with a comma in it cannot be told apart from two.
+fn gen_param_values_code(name: &str, name_ident: &TokenStream) -> TokenStream {
+    quote! {
+        let values = #name_ident.iter().map(|value| value.to_string()).collect::<Vec<_>>();
+        if let Some(value) = values.iter().find(|value| value.contains(',')) {
+            return Err(crate::types::error::Error::InvalidRequest(format!(
+                "the values of `{}` cannot contain a comma, got {:?}",
+                #name, value
+            )));
+        }
+    }
+}
+
+fn gen_header_params_code(
+    header_params: &BTreeMap<String, TokenStream>,
+    exploded: &BTreeSet<String>,
+) -> Result<TokenStream> {
+    let mut headers = Vec::new();
+    for (name, t) in header_params {
+        let cleaned_name = crate::types::clean_property_name(name);
+        let name_ident = format_ident!("{}", cleaned_name);
+
+        headers.push(if get_slice_param_item(t)?.is_some() {
+            let items = if t.is_option()? {
+                quote!(p)
+            } else {
+                quote!(#name_ident)
+            };
+            let values = gen_param_values_code(name, &items);
+            // Every header sent for an exploded array holds one item.
+            let send = if exploded.contains(name) {
+                quote! {
+                    for value in values {
+                        req = req.header(#name, value);
+                    }
+                }
+            } else {
+                quote!(req = req.header(#name, values.join(","));)
+            };
+            if t.is_option()? {
+                quote! {
+                    if let Some(p) = #name_ident {
+                        #values
+                        #send
+                    }
+                }
+            } else {
+                quote! {
+                    {
+                        #values
+                        #send
+                    }
+                }
+            }
+        } else if matches!(
+            crate::types::get_text(t)?.as_str(),
+            "Option<String>" | "Option<&'astr>"
+        ) {
+            quote! {
+                if let Some(p) = #name_ident {
+                    req = req.header(#name, p);
+                }
+            }
+        } else if t.is_option()? {
+            quote! {
+                if let Some(p) = #name_ident {
+                    req = req.header(#name, format!("{}", p));
+                }
+            }
+        } else if t.is_string()? {
+            quote! {
+                req = req.header(#name, #name_ident);
+            }
+        } else {
+            quote! {
+                req = req.header(#name, format!("{}", #name_ident));
+            }
+        });
+    }
+
+    Ok(quote! {
+        #(#headers)*
+    })
+}
+
+fn gen_cookie_params_code(
+    cookie_params: &BTreeMap<String, TokenStream>,
+    exploded: &BTreeSet<String>,
+) -> Result<TokenStream> {
+    // Every request sends the cookies set on the client, even if it has no cookie
+    // params of its own.
+    if cookie_params.is_empty() {
+        return Ok(quote! {
+            if let Some(cookie) = self.client.cookie_header(&[]) {
+                req = req.header(reqwest::header::COOKIE, cookie);
+            }
+        });
+    }
+
+    let mut required_cookies = Vec::new();
+    let mut optional_cookies = Vec::new();
+    for (name, t) in cookie_params {
+        let cleaned_name = crate::types::clean_property_name(name);
+        let name_ident = format_ident!("{}", cleaned_name);
+
+        let type_text = crate::types::get_text(t)?;
+        if get_slice_param_item(t)?.is_some() {
+            let items = if t.is_option()? {
+                quote!(p)
+            } else {
+                quote!(#name_ident)
+            };
+            let values = gen_param_values_code(name, &items);
+            let push = if exploded.contains(name) {
+                quote! {
+                    for value in values {
+                        cookies.push((#name, value));
+                    }
+                }
+            } else {
+                quote!(cookies.push((#name, values.join(",")));)
+            };
+            optional_cookies.push(if t.is_option()? {
+                quote! {
+                    if let Some(p) = #name_ident {
+                        #values
+                        #push
+                    }
+                }
+            } else {
+                quote! {
+                    {
+                        #values
+                        #push
+                    }
+                }
+            });
+        } else if type_text == "Option<String>" {
+            optional_cookies.push(quote! {
+                if let Some(p) = #name_ident {
+                    cookies.push((#name, p));
+                }
+            })
+        } else if type_text == "Option<&'astr>" {
+            optional_cookies.push(quote! {
+                if let Some(p) = #name_ident {
+                    cookies.push((#name, p.to_string()));
+                }
+            })
+        } else if t.is_option()? {
+            optional_cookies.push(quote! {
+                if let Some(p) = #name_ident {
+                    cookies.push((#name, format!("{}", p)));
+                }
+            })
+        } else {
+            required_cookies.push(quote! {
+               (#name, #name_ident.to_string())
+            })
+        }
+    }
+
+    let is_mut = if optional_cookies.is_empty() {
+        quote!()
+    } else {
+        quote!(mut)
+    };
+    Ok(quote! {
+        let #is_mut cookies = vec![ #(#required_cookies),* ];
+        #(#optional_cookies)*
+        if let Some(cookie) = self.client.cookie_header(&cookies) {
+            req = req.header(reqwest::header::COOKIE, cookie);
+        }
+    })
+}
+
 fn generate_auth_code(opts: &crate::Opts) -> Result<TokenStream> {
     let out = if opts.token_endpoint.is_some() {
         quote!(req = req.bearer_auth(&self.client.token.read().await.access_token);)
@@ -1161,26 +4235,266 @@ fn generate_auth_code(opts: &crate::Opts) -> Result<TokenStream> {
     Ok(out)
 }
 
+/// Generate the `_wait` function of an operation that can wait until it is done.
+/// Returns the function and the type it returns.
+#[allow(clippy::too_many_arguments)]
+fn generate_wait_fn(
+    type_space: &mut crate::types::TypeSpace,
+    name: &str,
+    method: &http::Method,
+    op: &openapiv3::Operation,
+    docs: &str,
+    args: &proc_macro2::TokenStream,
+    request_body: &proc_macro2::TokenStream,
+    opts: &crate::Opts,
+    global_params: &[openapiv3::ReferenceOr<openapiv3::Parameter>],
+) -> Result<(proc_macro2::TokenStream, proc_macro2::TokenStream)> {
+    let fn_name = op.get_fn_name()?;
+    let wait_fn_name = format!("{}_wait", fn_name);
+    let wait_fn_name_ident = format_ident!("{}", wait_fn_name);
+
+    let response_type = match get_returned_response_type(
+        type_space,
+        name,
+        method,
+        op,
+        RequestKind::Wait,
+        global_params,
+    )? {
+        Some(response) => {
+            let t = response.type_name;
+            quote!(#t)
+        }
+        None => quote!(()),
+    };
+
+    let docs = format!(
+        "{}\n\nThis waits until the operation is done, for up to `timeout`, rather than answer `202 Accepted` like [`{}`](Self::{}) does. It is an error if it is still not done.",
+        docs, fn_name, fn_name
+    );
+
+    let function_body = get_function_body(
+        type_space,
+        name,
+        method,
+        op,
+        RequestKind::Wait,
+        opts,
+        global_params,
+    )?;
+
+    let deprecated = get_deprecated_attr(op);
+    let function = quote! {
+        #[doc = #docs]
+        #deprecated
+        #[tracing::instrument]
+        pub async fn #wait_fn_name_ident<'a>(&'a self #args #request_body, timeout: std::time::Duration) -> Result<#response_type, crate::types::error::Error> {
+            #function_body
+        }
+    };
+
+    Ok((function, response_type))
+}
+
+/// Generate the `_batch` function of a `GET` of a resource by its id, as in
+/// `GET /things/{id}`, which gets many of them concurrently. The operation must
+/// take no other required parameter, and must not be paginated or wait.
+#[allow(clippy::too_many_arguments)]
+fn generate_batch_fn(
+    type_space: &mut crate::types::TypeSpace,
+    name: &str,
+    method: &http::Method,
+    op: &openapiv3::Operation,
+    raw_args: &BTreeMap<String, proc_macro2::TokenStream>,
+    response_type: &proc_macro2::TokenStream,
+    global_params: &[openapiv3::ReferenceOr<openapiv3::Parameter>],
+) -> Result<Option<proc_macro2::TokenStream>> {
+    if method != http::Method::GET
+        || get_request_body(type_space, name, method, op)?.is_some()
+        || get_pagination_properties(name, method, op, &type_space.spec)?.has_stream()
+        || get_wait_param(type_space, op, global_params)?.is_some()
+        || response_type.is_empty()
+        || response_type.rendered()? == "()"
+    {
+        return Ok(None);
+    }
+
+    // The id is the only path parameter, and the end of the path.
+    let path_params = get_path_params(type_space, op, global_params)?;
+    let mut path_params = path_params.into_iter();
+    let (Some((id_name, id_type)), None) = (path_params.next(), path_params.next()) else {
+        return Ok(None);
+    };
+    if !name.ends_with(&format!("/{{{}}}", id_name)) {
+        return Ok(None);
+    }
+
+    // The strings are owned by the stream, everything else is as the function
+    // takes it.
+    let id_ident = format_ident!("{}", crate::types::clean_property_name(&id_name));
+    let (id_type, id_arg) = if id_type.is_string()? || is_display_string_param(&id_type)? {
+        (quote!(String), quote!(&#id_ident))
+    } else {
+        (id_type.clone(), quote!(#id_ident.clone()))
+    };
+
+    // The other parameters must be optional, we leave them out.
+    let mut args = Vec::new();
+    for (k, v) in raw_args {
+        if *k == id_name {
+            args.push(id_arg.clone());
+        } else if v.rendered()?.starts_with("Option<") {
+            args.push(quote!(None));
+        } else {
+            return Ok(None);
+        }
+    }
+
+    let fn_name = op.get_fn_name()?;
+    let fn_name_ident = format_ident!("{}", fn_name);
+    let batch_fn_name_ident = format_ident!("{}_batch", fn_name);
+    let docs = format!(
+        "Get many at once with [`{}`](Self::{}), sending at most `concurrency` requests at a \
+         time (at least one).\n\nThe results come in the order the requests finish, each with \
+         the `{}` it is for. A failed request does not stop the others, its error is its \
+         result.",
+        fn_name, fn_name, id_name
+    );
+
+    let deprecated = get_deprecated_attr(op);
+    let allow_deprecated = if deprecated.is_empty() {
+        quote!()
+    } else {
+        quote!(#[allow(deprecated)])
+    };
+    Ok(Some(quote! {
+        #[doc = #docs]
+        #deprecated
+        #allow_deprecated
+        pub fn #batch_fn_name_ident<'a, I>(
+            &'a self,
+            ids: I,
+            concurrency: usize,
+        ) -> impl futures::Stream<Item = (#id_type, Result<#response_type, crate::types::error::Error>)> + 'a
+        where
+            I: IntoIterator<Item = #id_type>,
+            I::IntoIter: 'a,
+        {
+            use futures::StreamExt;
+
+            futures::stream::iter(ids)
+                .map(move |#id_ident| async move {
+                    let result = self.#fn_name_ident(#(#args),*).await;
+                    (#id_ident, result)
+                })
+                .buffer_unordered(concurrency.max(1))
+        }
+    }))
+}
+
+/// The path of the operation in the url the function builds: the constant of the
+/// `paths` module, without its leading `/`.
+pub(crate) fn get_url_path(
+    spec: &openapiv3::OpenAPI,
+    name: &str,
+    method: &http::Method,
+) -> Result<proc_macro2::TokenStream> {
+    let consts = crate::types::get_path_consts(spec)?;
+    match consts.get(&(method.to_string(), name.to_string())) {
+        Some(constant) => {
+            let constant = format_ident!("{}", constant);
+            Ok(quote!(crate::types::paths::#constant.trim_start_matches('/')))
+        }
+        None => {
+            let path = name.trim_start_matches('/');
+            Ok(quote!(#path))
+        }
+    }
+}
+
+/// How the request in a function body is sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RequestKind {
+    /// On its own, by the function of the operation.
+    Single,
+    /// For the next page of a paginated stream.
+    NextPage,
+    /// For a page of the stream of an operation that answers with a bare array, at
+    /// the url of the `Link` header of the previous page, it also returns that of
+    /// the next page.
+    LinkPage,
+    /// By an update helper, a `GET` also returns the `ETag` of the response and a
+    /// `PUT` sends `if_match` as the `If-Match` header.
+    Conditional,
+    /// By the `_wait` function of an operation that can wait until it is done, for
+    /// up to `timeout`.
+    Wait,
+    /// By a poll helper, a `GET` also returns the wait the server asks for with
+    /// `Retry-After`.
+    Poll,
+}
+
 /// Return the function body for the operation.
 fn get_function_body(
     type_space: &mut crate::types::TypeSpace,
     name: &str,
     method: &http::Method,
     op: &openapiv3::Operation,
-    paginated: bool,
+    kind: RequestKind,
     opts: &crate::Opts,
     global_params: &[openapiv3::ReferenceOr<openapiv3::Parameter>],
 ) -> Result<proc_macro2::TokenStream> {
-    let path = name.trim_start_matches('/');
+    let path = get_url_path(&type_space.spec, name, method)?;
     let method_ident = format_ident!("{}", method.to_string());
 
+    // The stream of the next pages borrows its own copy of the strings taken as
+    // `impl std::fmt::Display`.
+    let borrowed = |params: BTreeMap<String, TokenStream>| -> Result<_> {
+        if kind != RequestKind::NextPage && kind != RequestKind::LinkPage {
+            return Ok(params);
+        }
+        params
+            .into_iter()
+            .map(|(k, v)| {
+                if is_display_string_param(&v)? {
+                    Ok((k, quote!(&'a str)))
+                } else {
+                    Ok((k, v))
+                }
+            })
+            .collect()
+    };
+
     // Let's get the path parameters.
-    let path_params = get_path_params(type_space, op, global_params)?;
-    let clean_url = clean_url_from(&path_params)?;
+    let path_params = borrowed(get_path_params(type_space, op, global_params)?)?;
+    let date_time_format = type_space.opts.date_time_format.is_some();
+    let clean_url = clean_url_from(&path_params, date_time_format)?;
 
     // Let's get the query parameters.
     let query_params = get_query_params(type_space, op, global_params)?;
-    let query_params_code = gen_query_params_code(&query_params, paginated)?;
+    let query_params_code = gen_query_params_code(
+        &query_params,
+        kind == RequestKind::NextPage,
+        date_time_format,
+    )?;
+
+    // Let's get the header and cookie parameters.
+    let header_params_code = gen_header_params_code(
+        &borrowed(get_header_params(type_space, op, global_params)?)?,
+        &get_exploded_params(&get_header_params_schema(
+            op,
+            &type_space.spec,
+            global_params,
+        )?),
+    )?;
+    let cookie_params_code = gen_cookie_params_code(
+        &borrowed(get_cookie_params(type_space, op, global_params)?)?,
+        &get_exploded_params(&get_cookie_params_schema(
+            op,
+            &type_space.spec,
+            global_params,
+        )?),
+    )?;
 
     // Get if there is a request body.
     let request_body = if let Some(request_body) = get_request_body(type_space, name, method, op)? {
@@ -1210,7 +4524,45 @@ fn get_function_body(
                     .rendered()?
                     .replace("crate::types::", "")
                     .to_lowercase();
-                if !multipart_has_body(&request_body.type_name)? {
+                if let Some(options) = get_multipart_options(&type_space.spec, op)? {
+                    // The options are their own JSON part, next to the files.
+                    let part_name = &options.name;
+                    let part_filename = format!("{}.json", options.name);
+                    let content_type = &options.content_type;
+                    let files = &options.files;
+                    let expected = options
+                        .files
+                        .iter()
+                        .map(|file| format!("`{}`", file))
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    quote! {
+                        use std::convert::TryInto;
+                        // Create the multipart form.
+                        let mut form = reqwest::multipart::Form::new();
+
+                        // Add the options to the form.
+                        let mut json_part = reqwest::multipart::Part::text(serde_json::to_string(&body)?);
+                        json_part = json_part.file_name(#part_filename);
+                        json_part = json_part.mime_str(#content_type)?;
+                        form = form.part(#part_name, json_part);
+
+                        // For each of the files add them to the form, as the parts of the spec.
+                        for attachment in attachments {
+                            if ![#(#files),*].contains(&attachment.name.as_str()) {
+                                return Err(crate::types::error::Error::InvalidRequest(format!(
+                                    "attachment `{}` is not a file of the request, expected one of {}",
+                                    attachment.name,
+                                    #expected
+                                )));
+                            }
+                            form = form.part(attachment.name.clone(), attachment.try_into()?);
+                        }
+
+                        // Add to the request.
+                        req = req.multipart(form);
+                    }
+                } else if !multipart_has_body(&request_body.type_name)? {
                     // We don't add the body to the form.
                     quote! {
                         use std::convert::TryInto;
@@ -1262,15 +4614,89 @@ fn get_function_body(
                 }
             }
         }
+    } else if get_empty_object_body(&type_space.spec, op)? == Some(true) {
+        quote! {
+            // The operation takes an empty object.
+            req = req.json(&serde_json::json!({}));
+        }
     } else {
         // Do nothing.
         quote!()
     };
 
-    // TODO: we should add the headers.
+    // An optional body is only sent if there is one, without it the request has no
+    // body and no `Content-Type`.
+    let request_body = if is_optional_request_body(&type_space.spec, op)? {
+        quote! {
+            if let Some(body) = body {
+                #request_body
+            }
+        }
+    } else {
+        request_body
+    };
+
+    // Ask the operation to wait, or not to.
+    let wait_param = get_wait_param(type_space, op, global_params)?;
+    let wait = match (&wait_param, kind) {
+        (
+            Some(WaitParam {
+                name,
+                header,
+                value,
+                ..
+            }),
+            RequestKind::Wait,
+        ) => {
+            let send = if *header {
+                quote!(req = req.header(#name, #value);)
+            } else {
+                quote!(req = req.query(&[(#name, #value)]);)
+            };
+            let timeout_seconds =
+                proc_macro2::Literal::u64_unsuffixed(opts.request_timeout_seconds);
+            quote! {
+                // Wait until the operation is done.
+                #send
+                req = req.timeout(timeout + std::time::Duration::from_secs(#timeout_seconds));
+            }
+        }
+        (
+            Some(WaitParam {
+                name,
+                immediate: Some(immediate),
+                ..
+            }),
+            _,
+        ) => quote! {
+            // Do not wait until the operation is done.
+            req = req.header(#name, #immediate);
+        },
+        _ => quote!(),
+    };
 
     // Get the response if there is one.
-    let response = if let Some(response) = get_response_type(type_space, name, method, op)? {
+    let response_type =
+        get_returned_response_type(type_space, name, method, op, kind, global_params)?;
+    let page_type = response_type
+        .as_ref()
+        .map(|response| response.type_name.clone())
+        .unwrap_or_else(|| quote!(()));
+
+    // Ask for the media type we are going to parse.
+    let accept = if let Some(accept) = get_accept_header(&type_space.spec, op, &response_type)? {
+        quote! {
+            req = req.header(reqwest::header::ACCEPT, #accept);
+        }
+    } else {
+        quote!()
+    };
+
+    let response = if returns_headers(&type_space.spec, method, op)? {
+        quote! {
+            Ok(resp.headers().clone())
+        }
+    } else if let Some(response) = response_type {
         match response.media_type.as_str() {
             "application/json" => {
                 quote! {
@@ -1279,7 +4705,7 @@ fn get_function_body(
 
                     // Parse the json response.
                     // Return a human error.
-                    serde_json::from_str(&text).map_err(|err| crate::types::error::Error::from_serde_error(format_serde_error::SerdeError::new(text.to_string(), err), status))
+                    serde_json::from_str(&text).map_err(|err| crate::types::error::Error::from_serde_error(format_serde_error::SerdeError::new(text.to_string(), err), status, request_id))
                 }
             }
             "application/vnd.github.v3.object" => {
@@ -1289,26 +4715,48 @@ fn get_function_body(
 
                     // Parse the json response.
                     // Return a human error.
-                    serde_json::from_str(&text).map_err(|err| crate::types::error::Error::from_serde_error(format_serde_error::SerdeError::new(text.to_string(), err), status))
+                    serde_json::from_str(&text).map_err(|err| crate::types::error::Error::from_serde_error(format_serde_error::SerdeError::new(text.to_string(), err), status, request_id))
                 }
             }
-            "application/scim+json" => {
+            media_type if media_type.ends_with("+json") => {
                 quote! {
                     // Get the text for the response.
                     let text = resp.text().await.unwrap_or_default();
 
                     // Parse the json response.
                     // Return a human error.
-                    serde_json::from_str(&text).map_err(|err| crate::types::error::Error::from_serde_error(format_serde_error::SerdeError::new(text.to_string(), err), status))
+                    serde_json::from_str(&text).map_err(|err| crate::types::error::Error::from_serde_error(format_serde_error::SerdeError::new(text.to_string(), err), status, request_id))
                 }
             }
             _ => {
-                if response.type_name.is_string()? {
+                let type_name = response.type_name.rendered()?;
+                if type_name == "bytes::Bytes" {
+                    quote! {
+                        // Get the bytes of the file.
+                        Ok(resp.bytes().await?)
+                    }
+                } else if type_name == "crate::types::download::Download" {
+                    quote! {
+                        // Get the file with its name.
+                        Ok(crate::types::download::Download::from_response(resp).await?)
+                    }
+                } else if response.type_name.is_string()? {
                     quote! {
                         // Get the text for the response.
                         let text = resp.text().await?;
 
-                        Ok(text)
+                        // Some servers send a JSON string whatever the content type,
+                        // so strip the quotes if there are any.
+                        Ok(serde_json::from_str::<String>(&text).unwrap_or(text))
+                    }
+                } else if response.type_name.is_scalar()? {
+                    quote! {
+                        // Get the text for the response.
+                        let text = resp.text().await.unwrap_or_default();
+
+                        // A plain number or boolean is valid json.
+                        // Return a human error.
+                        serde_json::from_str(text.trim()).map_err(|err| crate::types::error::Error::from_serde_error(format_serde_error::SerdeError::new(text.to_string(), err), status, request_id))
                     }
                 } else {
                     anyhow::bail!(
@@ -1323,26 +4771,165 @@ fn get_function_body(
         quote!(Ok(()))
     };
 
-    let send_request = if paginated {
+    // The update helpers only replace what they fetched.
+    let (keep_etag, response, if_match) = match (kind, method) {
+        (RequestKind::Conditional, &http::Method::GET) => (
+            quote! {
+                // Keep the version of what we fetch.
+                let etag = resp.headers().get(reqwest::header::ETAG).cloned();
+            },
+            quote! {
+                let value = { #response }?;
+                Ok((value, etag))
+            },
+            quote!(),
+        ),
+        (RequestKind::Poll, _) => (
+            quote! {
+                // Keep the wait the server asks for.
+                let retry_after = crate::types::poll::retry_after(resp.headers());
+            },
+            quote! {
+                let value = { #response }?;
+                Ok((value, retry_after))
+            },
+            quote!(),
+        ),
+        (RequestKind::LinkPage, _) => (
+            quote! {
+                // Keep the url of the next page.
+                let next_page_link = crate::types::paginate::next_page_link(resp.headers());
+            },
+            quote! {
+                let value = { #response }?;
+                Ok((value, next_page_link))
+            },
+            quote!(),
+        ),
+        (RequestKind::Conditional, _) => (
+            quote!(),
+            response,
+            quote! {
+                // Only replace the version we fetched.
+                if let Some(if_match) = if_match {
+                    req = req.header(reqwest::header::IF_MATCH, if_match.clone());
+                }
+            },
+        ),
+        _ => (quote!(), response, quote!()),
+    };
+
+    // Every request goes through the client, so it can be recorded or replayed.
+    let operation_id = match &op.operation_id {
+        Some(operation_id) => operation_id.to_string(),
+        None => op.get_fn_name()?,
+    };
+    let send_request = if kind == RequestKind::NextPage {
+        // A token nested in an object of the page, like `meta.next_cursor`, has
+        // no query parameter of its name, we send it back as the page parameter
+        // of the operation.
+        let pagination_properties = get_pagination_properties(name, method, op, &type_space.spec)?;
+        let next_page_request = match (
+            &pagination_properties.next_page_parent,
+            &pagination_properties.page_param,
+        ) {
+            (Some(_), Some((page_param, _))) => quote!(
+                request.url_mut().query_pairs_mut().append_pair(#page_param, &next_page_token);
+            ),
+            _ => quote!(
+                request = #page_type::next_page_request(request, &next_page_token)?;
+            ),
+        };
         quote!(
             // Build the request.
             let mut request = req.build()?;
             // Now we will modify the request to add the pagination.
-            request = new_result.next_page(request)?;
+            #next_page_request
+            // Now we will execute the request.
+            let resp = self.client.execute(#operation_id, request).await?;
+        )
+    } else if kind == RequestKind::LinkPage {
+        quote!(
+            // Build the request.
+            let mut request = req.build()?;
+            // Get the page at the url of the previous one, if it is not the first.
+            if let Some(page_link) = &page_link {
+                request = crate::types::paginate::link_page_request(request, page_link)?;
+            }
             // Now we will execute the request.
-            let resp = self.client.client.execute(request).await?;
+            let resp = self.client.execute(#operation_id, request).await?;
         )
     } else {
-        // Do nothing.
         quote!(
             // Send the request.
-            let resp = req.send().await?;
+            let request = req.build()?;
+            let resp = self.client.execute(#operation_id, request).await?;
         )
     };
 
     let auth_code = generate_auth_code(opts)?;
 
+    // Check the attachments before we build the request.
+    let attachment_limits = if is_multipart(type_space, name, method, op)? {
+        gen_attachment_limits_code(&get_attachment_limits(&type_space.spec, op)?)
+    } else {
+        quote!()
+    };
+
+    // Check the arrays of unique items before we build the request.
+    let unique_items = gen_unique_items_code(type_space, op)?;
+
+    // Check the constraints of the body before we build the request.
+    let validate = gen_validate_code(type_space, op)?;
+
+    // The errors with a status the operation does not list on its own have the
+    // body of its `default` response, if we can't parse it we keep the text. The
+    // operations share the functions that decode them, one for every type.
+    let error = match get_default_error_type(&type_space.spec, op)? {
+        Some((variant, _)) => {
+            let listed = op
+                .responses
+                .responses
+                .keys()
+                .filter_map(|status_code| match status_code {
+                    openapiv3::StatusCode::Code(code) if !(200..300).contains(code) => {
+                        let code = proc_macro2::Literal::u16_unsuffixed(*code);
+                        Some(quote!(#code..=#code))
+                    }
+                    openapiv3::StatusCode::Range(range) if *range != 2 => {
+                        let start = proc_macro2::Literal::u16_unsuffixed(range * 100);
+                        let end = proc_macro2::Literal::u16_unsuffixed(range * 100 + 99);
+                        Some(quote!(#start..=#end))
+                    }
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+            let fn_name = crate::types::get_error_response_fn_name(&variant);
+
+            quote!(crate::types::error::Error::#fn_name(resp, request_id, &[#(#listed),*]).await)
+        }
+        None => quote!(crate::types::error::Error::from_response(resp, request_id).await),
+    };
+
+    // The operation is not done if it still answers `202 Accepted` after waiting.
+    let still_accepted = if kind == RequestKind::Wait {
+        quote! {
+            if status == reqwest::StatusCode::ACCEPTED {
+                return Err(crate::types::error::Error::UnexpectedResponse(
+                    crate::types::error::ApiResponse::new(resp),
+                ));
+            }
+        }
+    } else {
+        quote!()
+    };
+
     Ok(quote! {
+        #attachment_limits
+
+        #unique_items
+        #validate
+
         let mut req = self.client.client.request(
             http::Method::#method_ident,
             format!("{}/{}", self.client.base_url, #path #clean_url),
@@ -1351,25 +4938,161 @@ fn get_function_body(
         // Add in our authentication.
         #auth_code
 
+        #accept
+
         #query_params_code
 
+        #header_params_code
+
+        #cookie_params_code
+
+        #if_match
+
+        #wait
+
         #request_body
 
         #send_request
 
+        // Remember the rate limit and the request id the API reported.
+        let request_id = self.client.record_response(resp.headers());
+
+        #keep_etag
+
         // Get the response status.
         let status = resp.status();
 
+        #still_accepted
+
         if status.is_success() {
             #response
         } else {
-            // Try to decode the error.
-            let text = resp.text().await.unwrap_or_default();
-            Err(crate::types::error::Error::Server{body:text.to_string(), status})
+            Err(#error)
         }
     })
 }
 
+/// Make sure a function named by an `x-rust-name` does not take the name of
+/// another function of its tag.
+fn check_fn_name_overrides(spec: &openapiv3::OpenAPI) -> Result<()> {
+    let mut functions: BTreeMap<(String, String), (String, bool)> = BTreeMap::new();
+    for (path, item) in spec.paths.iter() {
+        for (method, op) in item.item()?.iter() {
+            let key = (op.get_tag()?, op.get_fn_name()?);
+            let operation = format!("{} {}", method.to_uppercase(), path);
+            let named = op.extensions.contains_key("x-rust-name");
+            match functions.get(&key) {
+                Some((other, other_named)) if named || *other_named => anyhow::bail!(
+                    "`{}` and `{}` would both be the function `{}` of the `{}` tag, check their \
+                     `x-rust-name`",
+                    other,
+                    operation,
+                    key.1,
+                    key.0
+                ),
+                Some(_) => {}
+                None => {
+                    functions.insert(key, (operation, named));
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Give an operation id to the operations the spec left without one, so they
+/// still get a function. The id comes from the method and the path, like
+/// `get_users_by_id_projects` for `GET /users/{id}/projects`, and gets a numeric
+/// suffix (`get_users_2`) if another operation already has it.
+///
+/// Returns the updated spec along with a map of the operations, as in
+/// `GET /users/{id}`, to the ids we gave them.
+pub fn synthesize_operation_ids(
+    spec: &openapiv3::OpenAPI,
+) -> Result<(openapiv3::OpenAPI, BTreeMap<String, String>)> {
+    let mut spec = spec.clone();
+    let mut synthesized: BTreeMap<String, String> = BTreeMap::new();
+
+    let has_id =
+        |op: &openapiv3::Operation| op.operation_id.as_deref().is_some_and(|id| !id.is_empty());
+
+    // Reserve the ids of the spec, ours never take one of them.
+    let mut taken: std::collections::HashSet<String> = Default::default();
+    for (_, item) in spec.paths.iter() {
+        for (_, op) in item.item()?.iter() {
+            if has_id(op) {
+                taken.insert(crate::types::casing::to_snake_case(
+                    op.operation_id.as_deref().unwrap_or_default(),
+                ));
+            }
+        }
+    }
+
+    for (path, item) in spec.paths.paths.iter_mut() {
+        let openapiv3::ReferenceOr::Item(item) = item else {
+            anyhow::bail!("reference not supported here: path {}", path);
+        };
+        for (method, op) in [
+            ("get", &mut item.get),
+            ("put", &mut item.put),
+            ("post", &mut item.post),
+            ("delete", &mut item.delete),
+            ("options", &mut item.options),
+            ("head", &mut item.head),
+            ("patch", &mut item.patch),
+            ("trace", &mut item.trace),
+        ] {
+            let Some(op) = op else {
+                continue;
+            };
+            if has_id(op) {
+                continue;
+            }
+
+            let name = synthesize_fn_name(method, path);
+            let mut id = name.clone();
+            let mut n = 2;
+            while !taken.insert(id.clone()) {
+                id = format!("{}_{}", name, n);
+                n += 1;
+            }
+
+            let operation = format!("{} {}", method.to_uppercase(), path);
+            log::warn!("`{}` has no operation id, we named it `{}`", operation, id);
+            op.operation_id = Some(id.clone());
+            synthesized.insert(operation, id);
+        }
+    }
+
+    Ok((spec, synthesized))
+}
+
+/// The name of the function of an operation without an id, from its method and
+/// its path. The parameters of the path are named `by_{param}`.
+fn synthesize_fn_name(method: &str, path: &str) -> String {
+    let mut parts = vec![method.to_lowercase()];
+    for segment in path.split('/').filter(|segment| !segment.is_empty()) {
+        // A segment can mix parameters and text, as in `{id}.json`.
+        let mut segment = segment.to_string();
+        while let (Some(start), Some(end)) = (segment.find('{'), segment.find('}')) {
+            if end < start {
+                break;
+            }
+            let param = crate::types::casing::to_snake_case(&segment[start + 1..end]);
+            segment.replace_range(start..=end, &format!("_by_{}_", param));
+        }
+        parts.push(crate::types::casing::to_snake_case(&segment));
+    }
+
+    parts
+        .join("_")
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
 /// Check if a operation is paginated.
 fn get_pagination_properties(
     name: &str,
@@ -1426,17 +5149,37 @@ fn generate_example_code_fn(
     let example_fn_name_ident = format_ident!("example_{}_{}", tag, fn_name);
 
     let tag_ident = format_ident!("{}", tag);
+    // The tag can be nested in other modules, see `modules`.
+    let tag_accessor = crate::modules::ModuleMap::new(opts)?.accessor(tag);
 
     let mut function_start = quote!();
     let mut print_result = quote!();
-    if let Some(response) = get_response_type(type_space, name, method, op)? {
+    if returns_headers(&type_space.spec, method, op)? {
+        function_start = quote!(let result: http::HeaderMap = );
+        print_result = quote!(println!("{:?}", result););
+    } else if let Some(response) = get_returned_response_type(
+        type_space,
+        name,
+        method,
+        op,
+        RequestKind::Single,
+        global_params,
+    )? {
         let t = response.type_name;
         function_start = quote!(let result: #t = );
         print_result = quote!(println!("{:?}", result););
     }
 
-    // Get the function args.
-    let raw_args = get_example_args(name, method, type_space, op, global_params)?;
+    // Get the function args, with the examples of the spec if it has some.
+    let example_key = get_example_key(&type_space.spec, op, global_params)?;
+    let raw_args = get_example_args(
+        name,
+        method,
+        type_space,
+        op,
+        global_params,
+        example_key.as_deref(),
+    )?;
     let args = if raw_args.is_empty() {
         quote!()
     } else {
@@ -1445,11 +5188,15 @@ fn generate_example_code_fn(
     };
 
     // Get the request body for the function if there is one.
-    let request_body = if let Some(rb) = get_request_body_example(type_space, name, method, op)? {
+    let request_body = if let Some(rb) =
+        get_request_body_example(type_space, name, method, op, example_key.as_deref())?
+    {
         let t = rb.type_name;
         if is_multipart(type_space, name, method, op)? && !multipart_has_body(&t)? {
             // We don't have a request body, so we'll return nothing.
             quote!()
+        } else if is_optional_request_body(&type_space.spec, op)? {
+            quote!(Some(&#t))
         } else {
             // We add the comma at the front, so it works.
             quote!(&#t)
@@ -1477,7 +5224,7 @@ fn generate_example_code_fn(
         async fn #example_fn_name_ident() -> anyhow::Result<()> {
             #client_code
 
-            #function_start client.#tag_ident().#fn_name_ident(#args #request_body).await?;
+            #function_start client.#tag_accessor.#fn_name_ident(#args #request_body).await?;
 
             #print_result
 
@@ -1487,13 +5234,13 @@ fn generate_example_code_fn(
 
     // Let's check if this function can be paginated.
     let pagination_properties = get_pagination_properties(name, method, op, &type_space.spec)?;
-    if pagination_properties.can_paginate() {
+    if pagination_properties.has_stream() {
         // We need to generate the stream function as well.
         let stream_fn_name_ident = format_ident!("{}_stream", fn_name);
         let example_stream_fn_name_ident = format_ident!("example_{}_{}_stream", tag, fn_name);
 
         // We want all the args except for the page_token.
-        let page_param_str = pagination_properties.page_param_str()?;
+        let page_param_str = pagination_properties.stream_page_param_str()?;
         let mut min_args = if raw_args.is_empty() {
             quote!()
         } else {
@@ -1518,7 +5265,7 @@ fn generate_example_code_fn(
             async fn #example_stream_fn_name_ident() -> anyhow::Result<()> {
                 #client_code
 
-                let mut #tag_ident =  client.#tag_ident();
+                let mut #tag_ident =  client.#tag_accessor;
                 let mut stream = #tag_ident.#stream_fn_name_ident(#min_args #request_body);
 
                 // Loop over the items in the stream.
@@ -1698,4 +5445,550 @@ mod tests {
             "meta_info"
         );
     }
+
+    #[test]
+    fn test_multipart_options() {
+        let spec = crate::load_json_spec(include_str!("../tests/mock.json")).unwrap();
+        let op = |path: &str| {
+            spec.paths.paths[path]
+                .as_item()
+                .unwrap()
+                .post
+                .clone()
+                .unwrap()
+        };
+
+        let options = super::get_multipart_options(&spec, &op("/things/{id}/import"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(options.name, "options");
+        assert_eq!(
+            options.schema,
+            openapiv3::ReferenceOr::ref_("#/components/schemas/ThingImportOptions")
+        );
+        assert_eq!(options.content_type, "application/json");
+        assert_eq!(options.files, ["model", "textures"]);
+
+        // The description of the files is not options, it is sent in the body.
+        assert!(
+            super::get_multipart_options(&spec, &op("/things/{id}/files"))
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_synthesize_fn_name() {
+        for (method, path, expected) in [
+            ("get", "/", "get"),
+            ("get", "/users", "get_users"),
+            ("GET", "/users/", "get_users"),
+            ("get", "/users/{id}", "get_users_by_id"),
+            ("get", "/users/{id}/projects", "get_users_by_id_projects"),
+            (
+                "delete",
+                "/orgs/{orgId}/users/{user_id}/",
+                "delete_orgs_by_org_id_users_by_user_id",
+            ),
+            (
+                "post",
+                "/file/conversion/{src}/{dst}",
+                "post_file_conversion_by_src_by_dst",
+            ),
+            ("get", "/reports/{id}.json", "get_reports_by_id_json"),
+            ("put", "/api-keys/v1/rotate", "put_api_keys_v_1_rotate"),
+        ] {
+            assert_eq!(
+                super::synthesize_fn_name(method, path),
+                expected,
+                "{} {}",
+                method,
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn test_synthesize_operation_ids() {
+        let spec = crate::load_json_spec(include_str!("../tests/no-operation-ids.json")).unwrap();
+        let (spec, synthesized) = super::synthesize_operation_ids(&spec).unwrap();
+
+        assert_eq!(
+            synthesized.into_iter().collect::<Vec<_>>(),
+            [
+                ("DELETE /users/{id}", "delete_users_by_id"),
+                ("GET /users", "get_users"),
+                ("GET /users/", "get_users_2"),
+                ("GET /users/{id}", "get_users_by_id_2"),
+                ("GET /users/{id}/projects", "get_users_by_id_projects"),
+            ]
+            .map(|(operation, id)| (operation.to_string(), id.to_string()))
+        );
+
+        // Every operation has a function now.
+        let mut type_space = crate::types::generate_types(&spec, Default::default()).unwrap();
+        let (files, _, _) = super::generate_files(&mut type_space, &Default::default()).unwrap();
+        let functions = files["users"].to_string();
+        for name in [
+            "get_by_id",
+            "get_by_id_2",
+            "get",
+            "get_2",
+            "delete_by_id",
+            "get_by_id_projects",
+        ] {
+            assert!(
+                functions.contains(&format!("pub async fn {} ", name)),
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn test_update_helpers() {
+        let spec: openapiv3::OpenAPI =
+            serde_json::from_str(include_str!("../tests/types/input/update-helpers.json")).unwrap();
+        let generate = |opts: &crate::Opts| {
+            let mut type_space = crate::types::generate_types(&spec, opts.clone()).unwrap();
+            super::generate_files(&mut type_space, opts).map(|(files, _, _)| files)
+        };
+
+        // The org is fetched and replaced on the same path, the user is not replaced
+        // by a user and the settings are only paired in the config.
+        let files = generate(&crate::Opts {
+            update_helpers: Some("tests/types/input/update-helpers.toml".into()),
+            ..Default::default()
+        })
+        .unwrap();
+        expectorate::assert_contents(
+            "tests/types/update-helpers.rs.gen",
+            &format!(
+                "{}\n{}",
+                rustfmt_wrapper::rustfmt(files["orgs"].to_string()).unwrap(),
+                rustfmt_wrapper::rustfmt(files["users"].to_string()).unwrap()
+            ),
+        );
+        let files = generate(&Default::default()).unwrap();
+        assert!(files["orgs"].to_string().contains("update_with"));
+        assert!(!files["users"].to_string().contains("_with"));
+
+        // A pair in the config must fit.
+        let config =
+            std::env::temp_dir().join(format!("update-helpers-{}.toml", uuid::Uuid::new_v4()));
+        std::fs::write(&config, "pairs = { update_user = \"get_user\" }").unwrap();
+        let err = generate(&crate::Opts {
+            update_helpers: Some(config.clone()),
+            ..Default::default()
+        })
+        .unwrap_err();
+        std::fs::remove_file(&config).unwrap();
+        assert_eq!(
+            err.to_string(),
+            "cannot generate an update helper for `update_user` with `get_user`: the PUT operation takes `crate::types::UpdateUser`, the GET operation returns `crate::types::User`"
+        );
+    }
+
+    #[test]
+    fn test_poll_helpers() {
+        // The text-to-CAD prompts are polled until they are done.
+        let spec = crate::load_json_spec(include_str!("../../spec.json")).unwrap();
+        let mut type_space = crate::types::generate_types(&spec, Default::default()).unwrap();
+        let (files, _, _) = super::generate_files(&mut type_space, &Default::default()).unwrap();
+        let helpers = files
+            .iter()
+            .flat_map(|(tag, file)| {
+                file.to_string()
+                    .split("pub async fn ")
+                    .skip(1)
+                    .filter_map(|f| f.split_once(' ').map(|(name, _)| name.to_string()))
+                    .filter(|name| name.ends_with("_and_wait"))
+                    .map(|name| format!("{}::{}", tag, name))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(helpers, vec!["ml::create_text_to_cad_and_wait"]);
+
+        let spec = crate::load_json_spec(include_str!("../tests/mock.json")).unwrap();
+        let generate = |config: &str| {
+            let path =
+                std::env::temp_dir().join(format!("poll-helpers-{}.toml", uuid::Uuid::new_v4()));
+            std::fs::write(&path, config).unwrap();
+            let opts = crate::Opts {
+                poll_helpers: Some(path.clone()),
+                ..Default::default()
+            };
+            let mut type_space = crate::types::generate_types(&spec, opts.clone()).unwrap();
+            let files = super::generate_files(&mut type_space, &opts).map(|(files, _, _)| files);
+            std::fs::remove_file(&path).unwrap();
+            files
+        };
+
+        // The scans have the statuses we look for, unless we look for others.
+        assert!(generate("").unwrap()["things"]
+            .to_string()
+            .contains("create_scan_and_wait"));
+        assert!(!generate(r#"completed = ["done"]"#).unwrap()["things"]
+            .to_string()
+            .contains("_and_wait"));
+
+        // A pair in the config must fit.
+        let err = generate("pairs = { render_thing = \"get_thing_scan\" }").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "cannot generate a poll helper for `render_thing` with `get_thing_scan`: the POST operation does not return `crate::types::ThingScan`"
+        );
+    }
+
+    #[test]
+    fn test_operations() {
+        let spec = crate::load_json_spec(include_str!("../tests/mock.json")).unwrap();
+        let opts = crate::Opts {
+            update_helpers: Some("tests/update-helpers.toml".into()),
+            ..Default::default()
+        };
+        let mut type_space = crate::types::generate_types(&spec, opts.clone()).unwrap();
+        let (files, _, operations) = super::generate_files(&mut type_space, &opts).unwrap();
+
+        // Every function that sends a request is in the table.
+        let functions: usize = files
+            .values()
+            .map(|file| file.to_string().matches("pub async fn").count())
+            .sum();
+        assert_eq!(operations.len(), functions);
+
+        let find = |function: &str| {
+            operations
+                .iter()
+                .find(|operation| operation.function == function)
+                .unwrap()
+        };
+        assert!(find("list").paginated);
+        assert!(find("ws").streaming);
+        // The POST on the path of the websocket is a plain request.
+        let ws_command = find("ws_command");
+        assert!(!ws_command.streaming);
+        assert_eq!(ws_command.method, "POST");
+        assert_eq!(
+            ws_command.response_type.as_deref(),
+            Some("crate::types::Thing")
+        );
+        let render = find("render");
+        let render_wait = find("render_wait");
+        assert_eq!(render.operation_id, render_wait.operation_id);
+        assert_eq!(render.method, "POST");
+        assert_eq!(render.path, "/things/{id}/render");
+        assert_eq!(
+            render.response_type.as_deref(),
+            Some("crate::types::ThingJob")
+        );
+        assert_eq!(
+            render_wait.response_type.as_deref(),
+            Some("crate::types::ThingRender")
+        );
+        assert_eq!(find("replace_with").operation_id, "replace_thing");
+
+        let rendered = super::render_operations(&operations).to_string();
+        assert!(rendered.contains("pub fn operations () -> & 'static [OperationInfo]"));
+    }
+
+    #[test]
+    fn test_deprecated_sunset() {
+        let spec = crate::load_json_spec(include_str!("../tests/mock.json")).unwrap();
+        let mut type_space = crate::types::generate_types(&spec, Default::default()).unwrap();
+        let (files, _, _) = super::generate_files(&mut type_space, &Default::default()).unwrap();
+        let things = rustfmt_wrapper::rustfmt(files["things"].to_string()).unwrap();
+
+        let start = things.find("Get the stats of a thing.").unwrap();
+        let end = start + things[start..].find("pub async fn get_stats").unwrap();
+        let function = &things[start..end];
+        assert!(
+            function.contains(
+                "**NOTE:** This operation is marked as deprecated. It will be removed on \
+                 2025-06-01. Use `check_thing` instead."
+            ),
+            "{}",
+            function
+        );
+        assert!(
+            function.contains(
+                "#[deprecated(note = \"will be removed on 2025-06-01, use `check_thing` instead\")]"
+            ),
+            "{}",
+            function
+        );
+
+        // Only the operations with a sunset or a replacement are deprecated in rust.
+        assert_eq!(things.matches("#[deprecated").count(), 1);
+    }
+
+    #[test]
+    fn test_acronym_casing_kittycad() {
+        let spec = crate::load_json_spec(include_str!("../../spec.json")).unwrap();
+        let opts = crate::Opts {
+            acronym_casing: true,
+            ..Default::default()
+        };
+        let mut type_space = crate::types::generate_types(&spec, opts.clone()).unwrap();
+        let (files, _, _) = super::generate_files(&mut type_space, &opts).unwrap();
+
+        // The types are the same, the fields of the spec have no acronyms.
+        let mut output = String::new();
+        for (tag, file) in files {
+            output.push_str(&format!("// {}.rs\n\n", tag));
+            output.push_str(&rustfmt_wrapper::rustfmt(file.to_string()).unwrap());
+        }
+        expectorate::assert_contents("tests/types/kittycad.acronym-casing.rs.gen", &output);
+    }
+
+    #[test]
+    fn test_display_string_params() {
+        let spec = crate::load_json_spec(include_str!("../tests/mock.json")).unwrap();
+        let opts = crate::Opts {
+            display_string_params: true,
+            ..Default::default()
+        };
+        let mut type_space = crate::types::generate_types(&spec, opts.clone()).unwrap();
+        let (files, _, _) = super::generate_files(&mut type_space, &opts).unwrap();
+
+        expectorate::assert_contents(
+            "tests/types/mock.display-string-params.rs.gen",
+            &rustfmt_wrapper::rustfmt(files["things"].to_string()).unwrap(),
+        );
+    }
+
+    #[test]
+    fn test_param_type_named_after_operation() {
+        let spec = crate::load_json_spec(include_str!(
+            "../tests/types/input/param-title-collision.json"
+        ))
+        .unwrap();
+
+        // Leave out the runtime modules, we only care about the names.
+        let mut type_space = crate::types::TypeSpace {
+            types: indexmap::map::IndexMap::new(),
+            spec: spec.clone(),
+            rendered: quote!(),
+            opts: Default::default(),
+        };
+        for (name, schema) in &spec.components.as_ref().unwrap().schemas {
+            type_space
+                .render_schema(name, schema.as_item().unwrap())
+                .unwrap();
+        }
+        let (files, modified_spec, _) =
+            super::generate_files(&mut type_space, &Default::default()).unwrap();
+
+        let types = crate::types::get_text_fmt(&type_space.rendered).unwrap();
+        let jobs = rustfmt_wrapper::rustfmt(files["jobs"].to_string()).unwrap();
+        expectorate::assert_contents(
+            "tests/types/param-title-collision.rs.gen",
+            &format!("{}\n{}", types, jobs),
+        );
+
+        // The enums of the parameters are there next to the `Status` of the
+        // components, and `CountStatus` is taken too.
+        for name in [
+            "Status",
+            "CountStatus",
+            "ListStatus",
+            "CountStatus2",
+            "Kinds",
+        ] {
+            assert!(
+                types.contains(&format!("pub enum {} {{", name))
+                    || types.contains(&format!("pub struct {} {{", name)),
+                "{}",
+                name
+            );
+        }
+        assert!(jobs.contains("status: Option<crate::types::ListStatus>,"));
+        assert!(jobs.contains("status: crate::types::CountStatus2,"));
+        // The items of an array keep the name of the parameter.
+        assert!(jobs.contains("kinds: Option<Vec<crate::types::Kinds>>,"));
+
+        // The renames are in the spec we hand back.
+        let renamed = |path: &str| {
+            modified_spec.paths.paths[path]
+                .as_item()
+                .unwrap()
+                .get
+                .as_ref()
+                .unwrap()
+                .extensions
+                .get("x-rust-renamed-params")
+                .cloned()
+        };
+        assert_eq!(
+            renamed("/jobs"),
+            Some(serde_json::json!({ "status": "ListStatus" }))
+        );
+        assert_eq!(
+            renamed("/jobs/{status}/count"),
+            Some(serde_json::json!({ "status": "CountStatus2" }))
+        );
+    }
+
+    #[test]
+    fn test_bare_array_responses() {
+        let spec =
+            crate::load_json_spec(include_str!("../tests/types/input/bare-arrays.json")).unwrap();
+        let mut type_space = crate::types::TypeSpace {
+            types: indexmap::map::IndexMap::new(),
+            spec: spec.clone(),
+            rendered: quote!(),
+            opts: Default::default(),
+        };
+        let (files, _, _) = super::generate_files(&mut type_space, &Default::default()).unwrap();
+        let widgets = rustfmt_wrapper::rustfmt(files["widgets"].to_string()).unwrap();
+        expectorate::assert_contents("tests/types/bare-arrays.rs.gen", &widgets);
+
+        // The functions return the arrays, of the items we render.
+        assert!(widgets.contains("Result<Vec<crate::types::Widget>, crate::types::error::Error>"));
+        assert!(widgets.contains(
+            "Result<Vec<crate::types::ListGadgetsResponse>, crate::types::error::Error>"
+        ));
+        assert!(widgets.contains("Result<Vec<String>, crate::types::error::Error>"));
+        assert!(crate::types::get_text_fmt(&type_space.rendered)
+            .unwrap()
+            .contains("pub struct ListGadgetsResponse"));
+
+        // The arrays with a `Link` header are paginated, not the others.
+        assert!(widgets.contains("pub fn list_stream<'a>"));
+        assert!(widgets.contains("pub fn list_gadgets_stream<'a>"));
+        assert!(!widgets.contains("pub fn list_labels_stream<'a>"));
+    }
+
+    #[test]
+    fn test_meta_pagination() {
+        let spec = crate::load_json_spec(include_str!("../tests/types/input/meta-pagination.json"))
+            .unwrap();
+        let mut type_space = crate::types::generate_types(&spec, Default::default()).unwrap();
+        let (files, _, _) = super::generate_files(&mut type_space, &Default::default()).unwrap();
+        let orders = rustfmt_wrapper::rustfmt(files["orders"].to_string()).unwrap();
+        let types = crate::types::get_text_fmt(&type_space.rendered).unwrap();
+        expectorate::assert_contents("tests/types/meta-pagination.rs.gen", &orders);
+
+        // The cursors are read through the meta, whatever is optional.
+        assert!(orders.contains("pub fn list_stream<'a>"));
+        assert!(orders.contains("pub fn list_refunds_stream<'a>"));
+        assert!(types.contains("Some(self.meta.next_cursor.clone())"));
+        assert!(types.contains(
+            "self.meta\n            .as_ref()\n            .and_then(|meta| meta.next_cursor.clone())"
+        ));
+
+        // And sent back as the cursor of the operation.
+        assert!(orders.contains(".append_pair(\"cursor\", &next_page_token)"));
+    }
+
+    #[test]
+    fn test_optional_request_body() {
+        let spec =
+            crate::load_json_spec(include_str!("../tests/types/input/optional-body.json")).unwrap();
+        let mut type_space = crate::types::TypeSpace {
+            types: indexmap::map::IndexMap::new(),
+            spec: spec.clone(),
+            rendered: quote!(),
+            opts: Default::default(),
+        };
+        let (files, _, _) = super::generate_files(&mut type_space, &Default::default()).unwrap();
+        let foos = rustfmt_wrapper::rustfmt(files["foos"].to_string()).unwrap();
+        expectorate::assert_contents("tests/types/optional-body.rs.gen", &foos);
+
+        // The optional body is an option, sent only if there is one, not the required one.
+        assert!(
+            foos.contains("body: Option<&crate::types::FooPatch>"),
+            "{}",
+            foos
+        );
+        assert!(foos.contains("body: &crate::types::Foo,"), "{}", foos);
+        assert!(foos.contains("if let Some(body) = body {"), "{}", foos);
+        assert!(foos.contains("Some(&::types::FooPatch {"), "{}", foos);
+        assert!(foos.contains("**Body:** optional"), "{}", foos);
+    }
+
+    #[test]
+    fn test_named_examples() {
+        let spec = crate::load_json_spec(include_str!("../tests/types/input/named-examples.json"))
+            .unwrap();
+        let mut type_space = crate::types::TypeSpace {
+            types: indexmap::map::IndexMap::new(),
+            spec: spec.clone(),
+            rendered: quote!(),
+            opts: Default::default(),
+        };
+        let (files, _, _) = super::generate_files(&mut type_space, &Default::default()).unwrap();
+        let exports = rustfmt_wrapper::rustfmt(files["exports"].to_string()).unwrap();
+        expectorate::assert_contents("tests/types/named-examples.rs.gen", &exports);
+
+        // The body and the parameters all take the first example of the body, from
+        // the components, even where it is not the first of the parameter.
+        assert!(
+            exports.contains("serde_json::from_str::<::types::ExportOptions>("),
+            "{}",
+            exports
+        );
+        assert!(exports.contains(r#"coords\\\":\\\"zup"#), "{}", exports);
+        assert!(
+            exports.contains("::types::FileFormat::Step,"),
+            "{}",
+            exports
+        );
+        assert!(exports.contains("Some(25.4 as f64)"), "{}", exports);
+        assert!(!exports.contains("FileFormat::Gltf"), "{}", exports);
+
+        // The unnamed example is used too, and the rest are made up.
+        assert!(exports.contains("Some(10 as u32)"), "{}", exports);
+        assert!(
+            exports.contains("Some(::types::FileFormat::Step)"),
+            "{}",
+            exports
+        );
+    }
+
+    #[test]
+    fn test_referenced_params() {
+        let spec =
+            crate::load_json_spec(include_str!("../tests/types/input/referenced-params.json"))
+                .unwrap();
+        let mut type_space = crate::types::TypeSpace {
+            types: indexmap::map::IndexMap::new(),
+            spec: spec.clone(),
+            rendered: quote!(),
+            opts: Default::default(),
+        };
+        let (files, _, _) = super::generate_files(&mut type_space, &Default::default()).unwrap();
+        let projects = rustfmt_wrapper::rustfmt(files["projects"].to_string()).unwrap();
+        expectorate::assert_contents("tests/types/referenced-params.rs.gen", &projects);
+
+        // A parameter by reference is the same as inline.
+        let signature = |name: &str| {
+            let start = projects
+                .find(&format!("pub async fn {}<'a>(", name))
+                .unwrap();
+            let end = start + projects[start..].find(") -> ").unwrap();
+            projects[start..end].to_string()
+        };
+        let inline = signature("list_things_inline");
+        assert_eq!(
+            signature("list_things_referenced"),
+            inline.replace("list_things_inline", "list_things_referenced")
+        );
+        assert!(inline.contains("project: &'a str,"), "{}", inline);
+        assert!(signature("list_things_aliased").contains("project: &'a str,"));
+
+        // And documented the same.
+        let docs = "- `project: &'astr`: The project of the things. (required)";
+        assert_eq!(projects.matches(docs).count(), 4, "{}", projects);
+        assert_eq!(
+            projects
+                .matches("- `limit: Option<i64>`: The most things to return. (deprecated)")
+                .count(),
+            2
+        );
+
+        // The operation makes the project of its path required.
+        let in_org = signature("list_things_in_org");
+        assert!(in_org.contains("project: &'a str,"), "{}", in_org);
+    }
 }
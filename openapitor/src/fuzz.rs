@@ -0,0 +1,283 @@
+//! The fuzz targets of `--generate-fuzz`.
+//!
+//! The crate gets a `fuzz/` directory for `cargo fuzz`, a crate of its own with
+//! its own workspace so the crate itself does not change. It has two targets for
+//! the `--fuzz-type`s, by default the tagged unions with the most variants:
+//!
+//! - `deserialize` reads arbitrary bytes into every type, it only fails if that
+//!   panics.
+//! - `round_trip` builds arbitrary JSON documents, reads them into every type,
+//!   and checks that what it read is the same once written and read again.
+
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use proc_macro2::TokenStream;
+
+/// How many types the targets read when none are given.
+pub const DEFAULT_FUZZ_TYPES: usize = 5;
+
+/// The types the fuzz targets read: the ones of the options, or the enums of
+/// `rendered`, the generated types, with data in their variants and the most
+/// variants.
+pub fn get_fuzz_types(rendered: &TokenStream, opts: &crate::Opts) -> Result<Vec<String>> {
+    let file = syn::parse2::<syn::File>(rendered.clone())
+        .map_err(|e| anyhow::anyhow!("failed to parse the generated types: {}", e))?;
+
+    // The types we can round-trip, with their number of variants.
+    let mut types = BTreeMap::new();
+    for item in &file.items {
+        let (ident, attrs, variants) = match item {
+            syn::Item::Struct(s) => (&s.ident, &s.attrs, None),
+            syn::Item::Enum(e) => (&e.ident, &e.attrs, Some(&e.variants)),
+            _ => continue,
+        };
+        let derives = attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("derive"))
+            .map(|attr| quote!(#attr).to_string().replace(' ', ""))
+            .collect::<String>();
+        if ["serde::Serialize", "serde::Deserialize", "PartialEq"]
+            .iter()
+            .all(|derive| derives.contains(derive))
+        {
+            types.insert(ident.to_string(), variants);
+        }
+    }
+
+    if !opts.fuzz_types.is_empty() {
+        for name in &opts.fuzz_types {
+            if !types.contains_key(name) {
+                anyhow::bail!(
+                    "`--fuzz-type` `{}` is not one of the generated types that serialize and \
+                     compare",
+                    name
+                );
+            }
+        }
+        return Ok(opts.fuzz_types.clone());
+    }
+
+    let mut unions = types
+        .into_iter()
+        .filter_map(|(name, variants)| {
+            let variants = variants?;
+            variants
+                .iter()
+                .any(|v| !v.fields.is_empty())
+                .then_some((name, variants.len()))
+        })
+        .collect::<Vec<_>>();
+    unions.sort_by(|(a, a_len), (b, b_len)| b_len.cmp(a_len).then_with(|| a.cmp(b)));
+    Ok(unions
+        .into_iter()
+        .take(DEFAULT_FUZZ_TYPES)
+        .map(|(name, _)| name)
+        .collect())
+}
+
+/// Write the fuzz crate, in `fuzz/` of the output.
+pub fn generate(rendered: &TokenStream, opts: &crate::Opts) -> Result<()> {
+    let types = get_fuzz_types(rendered, opts)?;
+    if types.is_empty() {
+        anyhow::bail!("`--generate-fuzz` found no types to fuzz, give some with `--fuzz-type`");
+    }
+
+    let dir = opts.output.join("fuzz");
+    std::fs::create_dir_all(dir.join("fuzz_targets"))?;
+    crate::save(dir.join("Cargo.toml"), &generate_cargo_toml(opts))?;
+    crate::save(
+        dir.join(".gitignore"),
+        "target\ncorpus\nartifacts\ncoverage\n",
+    )?;
+
+    let package = format_ident!("{}", opts.code_package_name());
+    let types = types
+        .iter()
+        .map(|name| {
+            let name = format_ident!("{}", name);
+            quote!(#package::types::#name)
+        })
+        .collect::<Vec<_>>();
+
+    let deserialize = quote! {
+        #![no_main]
+
+        use libfuzzer_sys::fuzz_target;
+
+        fuzz_target!(|data: &[u8]| {
+            #(let _ = serde_json::from_slice::<#types>(data);)*
+        });
+    };
+    crate::save_rust(
+        dir.join("fuzz_targets").join("deserialize.rs"),
+        &deserialize,
+        opts,
+    )?;
+
+    let round_trip = quote! {
+        #![no_main]
+
+        use libfuzzer_sys::fuzz_target;
+
+        /// A JSON document, so the fuzzer builds documents rather than bytes.
+        #[derive(Debug, arbitrary::Arbitrary)]
+        enum Json {
+            Null,
+            Bool(bool),
+            Integer(i64),
+            Float(f64),
+            String(String),
+            Array(Vec<Json>),
+            Object(Vec<(String, Json)>),
+        }
+
+        impl From<Json> for serde_json::Value {
+            fn from(json: Json) -> Self {
+                match json {
+                    Json::Null => serde_json::Value::Null,
+                    Json::Bool(b) => serde_json::Value::Bool(b),
+                    Json::Integer(i) => serde_json::Value::from(i),
+                    // JSON has no NaN or infinity, `from` makes them null.
+                    Json::Float(f) => serde_json::Value::from(f),
+                    Json::String(s) => serde_json::Value::String(s),
+                    Json::Array(a) => serde_json::Value::Array(a.into_iter().map(Into::into).collect()),
+                    Json::Object(o) => serde_json::Value::Object(
+                        o.into_iter().map(|(k, v)| (k, v.into())).collect(),
+                    ),
+                }
+            }
+        }
+
+        /// Read `value` into a `T`, and check it is the same once written and read
+        /// again.
+        fn round_trip<T>(value: &serde_json::Value)
+        where
+            T: serde::de::DeserializeOwned + serde::Serialize + PartialEq + std::fmt::Debug,
+        {
+            let Ok(read) = serde_json::from_value::<T>(value.clone()) else {
+                return;
+            };
+            let written = serde_json::to_value(&read).expect("failed to write what we read");
+            let read_again = serde_json::from_value::<T>(written)
+                .expect("failed to read what we wrote");
+            assert_eq!(read, read_again);
+        }
+
+        fuzz_target!(|json: Json| {
+            let value = serde_json::Value::from(json);
+            #(round_trip::<#types>(&value);)*
+        });
+    };
+    crate::save_rust(
+        dir.join("fuzz_targets").join("round_trip.rs"),
+        &round_trip,
+        opts,
+    )
+}
+
+/// The manifest of the fuzz crate, the crate depends on the generated one.
+fn generate_cargo_toml(opts: &crate::Opts) -> String {
+    let package = opts.package_name();
+    let mut toml = format!(
+        r#"[package]
+name = "{package}-fuzz"
+version = "0.0.0"
+publish = false
+edition = "2021"
+
+[package.metadata]
+cargo-fuzz = true
+
+[dependencies]
+arbitrary = {{ version = "1", features = ["derive"] }}
+libfuzzer-sys = "0.4"
+serde = "1"
+serde_json = "1"
+{package} = {{ path = ".." }}
+
+# The fuzz crate is not a part of the workspace of the crate.
+[workspace]
+members = ["."]
+"#
+    );
+    for target in ["deserialize", "round_trip"] {
+        toml.push_str(&format!(
+            r#"
+[[bin]]
+name = "{target}"
+path = "fuzz_targets/{target}.rs"
+test = false
+doc = false
+bench = false
+"#
+        ));
+    }
+    toml
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_get_fuzz_types() {
+        let rendered = quote! {
+            #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug, Clone)]
+            #[serde(tag = "type")]
+            pub enum Small {
+                A { a: i64 },
+                B { b: String },
+            }
+
+            #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug, Clone)]
+            #[serde(tag = "type")]
+            pub enum Large {
+                A { a: i64 },
+                B { b: String },
+                C {},
+            }
+
+            // Only names, not a union.
+            #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug, Clone)]
+            pub enum Unit {
+                A,
+                B,
+                C,
+                D,
+            }
+
+            // Can't be compared after the round trip.
+            #[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+            pub enum Incomparable {
+                A(i64),
+                B(i64),
+                C(i64),
+                D(i64),
+            }
+
+            #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug, Clone)]
+            pub struct Merged {
+                pub a: i64,
+            }
+        };
+
+        assert_eq!(
+            super::get_fuzz_types(&rendered, &Default::default()).unwrap(),
+            vec!["Large".to_string(), "Small".to_string()]
+        );
+
+        // The types we are given replace the unions.
+        let opts = crate::Opts {
+            fuzz_types: vec!["Merged".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            super::get_fuzz_types(&rendered, &opts).unwrap(),
+            vec!["Merged".to_string()]
+        );
+        let opts = crate::Opts {
+            fuzz_types: vec!["Incomparable".to_string()],
+            ..Default::default()
+        };
+        assert!(super::get_fuzz_types(&rendered, &opts).is_err());
+    }
+}
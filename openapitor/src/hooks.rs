@@ -0,0 +1,299 @@
+//! Code of our own in the generated crate, see `--include-module` and
+//! `--append-to-types`.
+//!
+//! The orphan rules keep some impls out of other crates, like a `From` between a
+//! generated type and a type of ours, so they have to live in the generated crate.
+//! The generation deletes whatever it did not write, except for the modules
+//! below, so nothing written there by hand survives the next run.
+//!
+//! With `--include-module convert=hooks/convert.rs` we copy the file to
+//! `src/convert.rs` at every generation and declare `mod convert;` in `lib.rs`,
+//! `--include-pub-module` declares it `pub`. With `--append-to-types
+//! hooks/types.rs` the file goes at the end of the types module as it is, for the
+//! impls that need its private items. The files must parse, so a broken one fails
+//! the generation before we write anything.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+
+/// A module of `--include-module` or `--include-pub-module`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IncludedModule {
+    /// The name of the module.
+    pub name: String,
+    /// The file we copy to `src/{name}.rs`.
+    pub path: PathBuf,
+    /// If the module is declared `pub`.
+    pub public: bool,
+}
+
+impl IncludedModule {
+    /// Parse an `--include-module` like `convert=hooks/convert.rs`.
+    fn parse(arg: &str, public: bool) -> Result<Self> {
+        let flag = if public {
+            "--include-pub-module"
+        } else {
+            "--include-module"
+        };
+        let Some((name, path)) = arg.split_once('=') else {
+            anyhow::bail!(
+                "`{}` `{}` must be a module name and a file, like `convert=hooks/convert.rs`",
+                flag,
+                arg
+            );
+        };
+        let name = name.trim();
+        if syn::parse_str::<syn::Ident>(name).is_err()
+            || crate::types::casing::to_snake_case(name) != name
+        {
+            anyhow::bail!(
+                "`{}` `{}` must name a snake case module, not `{}`",
+                flag,
+                arg,
+                name
+            );
+        }
+        if crate::RESERVED_MODULES.contains(&name) || GENERATED_MODULES.contains(&name) {
+            anyhow::bail!(
+                "`{}` `{}` names `{}`, the crate keeps it for itself",
+                flag,
+                arg,
+                name
+            );
+        }
+
+        Ok(IncludedModule {
+            name: name.to_string(),
+            path: PathBuf::from(path.trim()),
+            public,
+        })
+    }
+
+    /// The declaration of the module in `lib.rs`.
+    pub fn declaration(&self) -> String {
+        if self.public {
+            format!("pub mod {};", self.name)
+        } else {
+            format!("mod {};", self.name)
+        }
+    }
+}
+
+/// The other modules we generate in `src/`, the ones of the tags aside.
+const GENERATED_MODULES: &[&str] = &[
+    "lib",
+    "device_flow",
+    "conformance_tests",
+    "smoke_tests",
+    crate::compat::MODULE,
+];
+
+/// The modules of the options, without reading their files.
+pub fn included_modules(opts: &crate::Opts) -> Result<Vec<IncludedModule>> {
+    let modules = opts
+        .include_modules
+        .iter()
+        .map(|arg| IncludedModule::parse(arg, false))
+        .chain(
+            opts.include_pub_modules
+                .iter()
+                .map(|arg| IncludedModule::parse(arg, true)),
+        )
+        .collect::<Result<Vec<_>>>()?;
+    for (i, module) in modules.iter().enumerate() {
+        if modules[..i].iter().any(|other| other.name == module.name) {
+            anyhow::bail!("the module `{}` is included more than once", module.name);
+        }
+    }
+
+    Ok(modules)
+}
+
+/// The code of our own, read and checked.
+#[derive(Debug, Clone, Default)]
+pub struct Hooks {
+    /// The included modules, with the contents of their files.
+    pub modules: Vec<(IncludedModule, String)>,
+    /// The code appended to the types module.
+    pub types: Option<String>,
+}
+
+impl Hooks {
+    /// Read the files of the options and make sure they parse, and that no module
+    /// takes the name of the module of a tag, see `clean_tag_name`.
+    pub fn load(opts: &crate::Opts, tag_modules: &[String]) -> Result<Self> {
+        let modules = included_modules(opts)?
+            .into_iter()
+            .map(|module| {
+                if tag_modules.contains(&module.name) {
+                    anyhow::bail!(
+                        "the included module `{}` has the name of the module of a tag",
+                        module.name
+                    );
+                }
+                let contents = read_rust(&module.path)?;
+                Ok((module, contents))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let types = opts.append_to_types.as_deref().map(read_rust).transpose()?;
+
+        Ok(Hooks { modules, types })
+    }
+
+    /// If `name` is the file of an included module, in `src/`.
+    pub fn is_included(&self, name: &str) -> bool {
+        self.modules.iter().any(|(module, _)| module.name == name)
+    }
+
+    /// Copy the included modules to `src`.
+    pub fn save(&self, src: &Path) -> Result<()> {
+        for (module, contents) in &self.modules {
+            crate::save(src.join(format!("{}.rs", module.name)), contents)?;
+        }
+
+        Ok(())
+    }
+
+    /// Append the code of `--append-to-types` to the file of the types module.
+    pub fn append_to_types(&self, types_file: &Path) -> Result<()> {
+        let Some(types) = &self.types else {
+            return Ok(());
+        };
+        let mut contents = std::fs::read_to_string(types_file)?;
+        contents.push('\n');
+        contents.push_str(types);
+        crate::save(types_file, &contents)
+    }
+}
+
+/// Read a file of Rust code, it must parse.
+fn read_rust(path: &Path) -> Result<String> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("failed to read `{}`: {}", path.display(), e))?;
+    if let Err(e) = syn::parse_file(&contents) {
+        let start = e.span().start();
+        anyhow::bail!(
+            "`{}` does not parse: {} at line {}, column {}",
+            path.display(),
+            e,
+            start.line,
+            start.column + 1
+        );
+    }
+
+    Ok(contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{included_modules, Hooks, IncludedModule};
+
+    fn opts(include_modules: &[&str], include_pub_modules: &[&str]) -> crate::Opts {
+        crate::Opts {
+            include_modules: include_modules.iter().map(|m| m.to_string()).collect(),
+            include_pub_modules: include_pub_modules.iter().map(|m| m.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_included_modules() {
+        let modules = included_modules(&opts(
+            &["convert=hooks/convert.rs"],
+            &["ext = hooks/ext.rs"],
+        ))
+        .unwrap();
+        assert_eq!(
+            modules,
+            vec![
+                IncludedModule {
+                    name: "convert".to_string(),
+                    path: "hooks/convert.rs".into(),
+                    public: false,
+                },
+                IncludedModule {
+                    name: "ext".to_string(),
+                    path: "hooks/ext.rs".into(),
+                    public: true,
+                },
+            ]
+        );
+        assert_eq!(modules[0].declaration(), "mod convert;");
+        assert_eq!(modules[1].declaration(), "pub mod ext;");
+
+        for (include, pub_include, err) in [
+            (
+                &["convert"][..],
+                &[][..],
+                "`--include-module` `convert` must be a module name and a file, like `convert=hooks/convert.rs`",
+            ),
+            (
+                &["Convert=c.rs"],
+                &[],
+                "`--include-module` `Convert=c.rs` must name a snake case module, not `Convert`",
+            ),
+            (
+                &[],
+                &["types=t.rs"],
+                "`--include-pub-module` `types=t.rs` names `types`, the crate keeps it for itself",
+            ),
+            (
+                &["lib=l.rs"],
+                &[],
+                "`--include-module` `lib=l.rs` names `lib`, the crate keeps it for itself",
+            ),
+            (
+                &["convert=a.rs"],
+                &["convert=b.rs"],
+                "the module `convert` is included more than once",
+            ),
+        ] {
+            assert_eq!(
+                included_modules(&opts(include, pub_include))
+                    .unwrap_err()
+                    .to_string(),
+                err
+            );
+        }
+    }
+
+    #[test]
+    fn test_load_hooks() {
+        let dir = std::env::temp_dir().join(format!("hooks-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let good = dir.join("good.rs");
+        std::fs::write(&good, "pub fn answer() -> u8 { 42 }\n").unwrap();
+        let bad = dir.join("bad.rs");
+        std::fs::write(&bad, "pub fn answer() -> { 42 }\n").unwrap();
+
+        let include = |path: &std::path::Path| opts(&[&format!("answer={}", path.display())], &[]);
+        let hooks = Hooks::load(&include(&good), &[]).unwrap();
+        assert!(hooks.is_included("answer"));
+        assert!(!hooks.is_included("types"));
+
+        let err = Hooks::load(&include(&good), &["answer".to_string()])
+            .unwrap_err()
+            .to_string();
+        assert_eq!(
+            err,
+            "the included module `answer` has the name of the module of a tag"
+        );
+
+        let err = Hooks::load(&include(&bad), &[]).unwrap_err().to_string();
+        assert!(err.ends_with("at line 1, column 20"), "{}", err);
+
+        let err = Hooks::load(
+            &crate::Opts {
+                append_to_types: Some(bad.clone()),
+                ..Default::default()
+            },
+            &[],
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("does not parse"), "{}", err);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,626 @@
+//! Conversions between the generated types and the types of another crate.
+//!
+//! The `--interop-map` file maps generated type names to the paths of external
+//! types, for example:
+//!
+//! ```toml
+//! [dependencies]
+//! kittycad-modeling-cmds = { version = "0.2", features = ["websocket"] }
+//!
+//! [types]
+//! Point3D = "kittycad_modeling_cmds::shared::Point3d"
+//! Thing = { path = "other::Item", renames = { name = "title" } }
+//! ```
+//!
+//! The dependencies are added to the generated crate as optional, behind the
+//! `interop` feature. For every mapped type we generate `From<Generated> for External`
+//! and `TryFrom<External> for Generated`, field by field and variant by variant, using
+//! the same names unless they are renamed. The reverse is fallible since the external
+//! enums can have variants we don't know about.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::Result;
+use proc_macro2::TokenStream;
+use serde::Deserialize;
+
+/// The mapping from generated types to the types of other crates.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct InteropMap {
+    /// The crates with the external types, written like in `Cargo.toml`.
+    #[serde(default)]
+    pub dependencies: BTreeMap<String, toml::Value>,
+    /// The external type for each generated type, by the name of the generated type.
+    #[serde(default)]
+    pub types: BTreeMap<String, InteropType>,
+}
+
+/// The external type a generated type converts to and from.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum InteropType {
+    /// The path of the external type.
+    Path(String),
+    /// The path of the external type, and the names of its fields or variants that
+    /// differ from ours.
+    Table {
+        /// The path of the external type.
+        path: String,
+        /// The external name of a field or variant, by our name.
+        #[serde(default)]
+        renames: BTreeMap<String, String>,
+    },
+}
+
+impl InteropType {
+    /// The path of the external type.
+    pub fn path(&self) -> &str {
+        match self {
+            InteropType::Path(path) => path,
+            InteropType::Table { path, .. } => path,
+        }
+    }
+
+    /// The external name of one of our fields or variants.
+    fn rename<'a>(&'a self, name: &'a str) -> &'a str {
+        match self {
+            InteropType::Path(_) => name,
+            InteropType::Table { renames, .. } => renames.get(name).map_or(name, |r| r.as_str()),
+        }
+    }
+
+    fn renames(&self) -> impl Iterator<Item = &String> {
+        match self {
+            InteropType::Path(_) => None,
+            InteropType::Table { renames, .. } => Some(renames.keys()),
+        }
+        .into_iter()
+        .flatten()
+    }
+}
+
+impl InteropMap {
+    /// Read the map from a file, `path` dependencies are relative to the file.
+    pub fn load<P: AsRef<std::path::Path>>(p: P) -> Result<Self> {
+        let p = p.as_ref();
+        let contents = std::fs::read_to_string(p)
+            .map_err(|e| anyhow::anyhow!("failed to read interop map `{}`: {}", p.display(), e))?;
+        let mut map: InteropMap = toml::from_str(&contents)
+            .map_err(|e| anyhow::anyhow!("failed to parse interop map `{}`: {}", p.display(), e))?;
+
+        let dir = p.parent().unwrap_or_else(|| std::path::Path::new("."));
+        let dir = std::fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf());
+        for dependency in map.dependencies.values_mut() {
+            if let Some(toml::Value::String(path)) = dependency.get_mut("path") {
+                *path = dir.join(&*path).display().to_string();
+            }
+        }
+
+        Ok(map)
+    }
+
+    /// The lines to add to the `[dependencies]` of the generated crate.
+    pub fn cargo_dependencies(&self) -> Result<String> {
+        let mut lines = String::new();
+        for (name, dependency) in &self.dependencies {
+            let mut table = match dependency {
+                toml::Value::String(version) => {
+                    let mut table = toml::Table::new();
+                    table.insert("version".to_string(), version.clone().into());
+                    table
+                }
+                toml::Value::Table(table) => table.clone(),
+                _ => anyhow::bail!("invalid interop dependency `{}`: {}", name, dependency),
+            };
+            table.insert("optional".to_string(), true.into());
+
+            let fields: Vec<String> = table
+                .iter()
+                .map(|(key, value)| format!("{} = {}", key, value))
+                .collect();
+            lines.push_str(&format!("{} = {{ {} }}\n", name, fields.join(", ")));
+        }
+
+        Ok(lines)
+    }
+
+    /// The dependencies enabled by the `interop` feature of the generated crate.
+    pub fn cargo_feature(&self) -> String {
+        let deps: Vec<String> = self
+            .dependencies
+            .keys()
+            .map(|name| format!(r#""dep:{}""#, name))
+            .collect();
+        format!("interop = [{}]", deps.join(", "))
+    }
+
+    /// Render the conversions for the rendered types.
+    pub fn render(&self, rendered: &TokenStream) -> Result<TokenStream> {
+        let file = syn::parse2::<syn::File>(rendered.clone())
+            .map_err(|e| anyhow::anyhow!("failed to parse the generated types: {}", e))?;
+
+        // Every type we generate, mapped or not.
+        let mut items = BTreeMap::new();
+        for item in &file.items {
+            let name = match item {
+                syn::Item::Struct(s) => s.ident.to_string(),
+                syn::Item::Enum(e) => e.ident.to_string(),
+                syn::Item::Type(t) => t.ident.to_string(),
+                syn::Item::Union(u) => u.ident.to_string(),
+                _ => continue,
+            };
+            items.insert(name, item);
+        }
+        let converter = Converter {
+            map: self,
+            generated: items.keys().cloned().collect(),
+        };
+
+        let mut impls = Vec::new();
+        for (name, external) in &self.types {
+            let Some(item) = items.get(name) else {
+                anyhow::bail!(
+                    "the interop map has `{}`, which is not a generated type",
+                    name
+                );
+            };
+            let ours = format_ident!("{}", name);
+            let ours = quote!(crate::types::#ours);
+            let theirs: syn::Path = syn::parse_str(external.path()).map_err(|e| {
+                anyhow::anyhow!("invalid path `{}` for `{}`: {}", external.path(), name, e)
+            })?;
+
+            let (from, try_from) = match item {
+                syn::Item::Struct(s) => {
+                    check_renames(
+                        name,
+                        external,
+                        s.fields.iter().filter_map(|f| f.ident.as_ref()),
+                    )?;
+                    (
+                        converter.convert_fields(name, &s.fields, external, false)?,
+                        converter.convert_fields(name, &s.fields, external, true)?,
+                    )
+                }
+                syn::Item::Enum(e) => {
+                    check_renames(name, external, e.variants.iter().map(|v| &v.ident))?;
+                    (
+                        converter.convert_variants(name, e, &ours, &theirs, external, false)?,
+                        converter.convert_variants(name, e, &theirs, &ours, external, true)?,
+                    )
+                }
+                _ => anyhow::bail!(
+                    "`{}` is not a struct or an enum, so it cannot be converted",
+                    name
+                ),
+            };
+
+            impls.push(quote! {
+                impl From<#ours> for #theirs {
+                    fn from(value: #ours) -> Self {
+                        #from
+                    }
+                }
+
+                impl TryFrom<#theirs> for #ours {
+                    type Error = InteropError;
+
+                    fn try_from(value: #theirs) -> Result<Self, Self::Error> {
+                        #try_from
+                    }
+                }
+            });
+        }
+
+        Ok(quote! {
+            #[cfg(feature = "interop")]
+            pub mod interop {
+                //! Conversions between the generated types and the types of other crates.
+
+                /// The error when a value of another crate has no generated equivalent.
+                #[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+                #[error("cannot convert to `{type_name}`: {reason}")]
+                pub struct InteropError {
+                    /// The generated type we tried to convert to.
+                    pub type_name: &'static str,
+                    /// Why we could not convert.
+                    pub reason: String,
+                }
+
+                #(#impls)*
+            }
+        })
+    }
+}
+
+/// Make sure every renamed field or variant exists.
+fn check_renames<'a>(
+    name: &str,
+    external: &InteropType,
+    idents: impl Iterator<Item = &'a syn::Ident>,
+) -> Result<()> {
+    let idents: BTreeSet<String> = idents.map(|i| i.to_string()).collect();
+    for rename in external.renames() {
+        if !idents.contains(rename) {
+            anyhow::bail!(
+                "the interop map renames `{}` of `{}`, which has no such field or variant",
+                rename,
+                name
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Generates the conversions of values, the fallible direction goes from the
+/// external types to ours.
+struct Converter<'a> {
+    map: &'a InteropMap,
+    /// The names of every generated type.
+    generated: BTreeSet<String>,
+}
+
+impl Converter<'_> {
+    /// Build the struct from the fields of `value`, the other struct.
+    fn convert_fields(
+        &self,
+        name: &str,
+        fields: &syn::Fields,
+        external: &InteropType,
+        fallible: bool,
+    ) -> Result<TokenStream> {
+        let rename = |ident: &syn::Ident| -> syn::Ident {
+            format_ident!("{}", external.rename(&ident.to_string()))
+        };
+
+        let body = match fields {
+            syn::Fields::Named(named) => {
+                let mut values = Vec::new();
+                for field in &named.named {
+                    let ours = field.ident.as_ref().expect("named fields have names");
+                    let (source, target) = if fallible {
+                        (rename(ours), ours.clone())
+                    } else {
+                        (ours.clone(), rename(ours))
+                    };
+                    let value = self.convert_value(
+                        &format!("{}.{}", name, ours),
+                        &field.ty,
+                        quote!(value.#source),
+                        fallible,
+                    )?;
+                    values.push(quote!(#target: #value));
+                }
+                quote!(Self { #(#values),* })
+            }
+            syn::Fields::Unnamed(unnamed) => {
+                let mut values = Vec::new();
+                for (i, field) in unnamed.unnamed.iter().enumerate() {
+                    let index = syn::Index::from(i);
+                    values.push(self.convert_value(
+                        &format!("{}.{}", name, i),
+                        &field.ty,
+                        quote!(value.#index),
+                        fallible,
+                    )?);
+                }
+                quote!(Self(#(#values),*))
+            }
+            syn::Fields::Unit => quote!(Self),
+        };
+
+        if fallible {
+            Ok(quote!(Ok(#body)))
+        } else {
+            Ok(body)
+        }
+    }
+
+    /// Match every variant of `value`, a `from`, to the same variant of `to`.
+    fn convert_variants(
+        &self,
+        name: &str,
+        e: &syn::ItemEnum,
+        from: &dyn quote::ToTokens,
+        to: &dyn quote::ToTokens,
+        external: &InteropType,
+        fallible: bool,
+    ) -> Result<TokenStream> {
+        let mut arms = Vec::new();
+        for variant in &e.variants {
+            let ours = &variant.ident;
+            let theirs = format_ident!("{}", external.rename(&ours.to_string()));
+            let (source, target) = if fallible {
+                (&theirs, ours)
+            } else {
+                (ours, &theirs)
+            };
+
+            let (pattern, value) = match &variant.fields {
+                syn::Fields::Named(named) => {
+                    let idents: Vec<&syn::Ident> = named
+                        .named
+                        .iter()
+                        .map(|f| f.ident.as_ref().expect("named fields have names"))
+                        .collect();
+                    let mut values = Vec::new();
+                    for (field, ident) in named.named.iter().zip(&idents) {
+                        let value = self.convert_value(
+                            &format!("{}::{}.{}", name, ours, ident),
+                            &field.ty,
+                            quote!(#ident),
+                            fallible,
+                        )?;
+                        if value.to_string() == ident.to_string() {
+                            values.push(quote!(#ident));
+                        } else {
+                            values.push(quote!(#ident: #value));
+                        }
+                    }
+                    (
+                        quote!(#from::#source { #(#idents),* }),
+                        quote!(#to::#target { #(#values),* }),
+                    )
+                }
+                syn::Fields::Unnamed(unnamed) => {
+                    let idents: Vec<syn::Ident> = (0..unnamed.unnamed.len())
+                        .map(|i| format_ident!("v{}", i))
+                        .collect();
+                    let mut values = Vec::new();
+                    for (i, (field, ident)) in unnamed.unnamed.iter().zip(&idents).enumerate() {
+                        values.push(self.convert_value(
+                            &format!("{}::{}.{}", name, ours, i),
+                            &field.ty,
+                            quote!(#ident),
+                            fallible,
+                        )?);
+                    }
+                    (
+                        quote!(#from::#source(#(#idents),*)),
+                        quote!(#to::#target(#(#values),*)),
+                    )
+                }
+                syn::Fields::Unit => (quote!(#from::#source), quote!(#to::#target)),
+            };
+
+            if fallible {
+                arms.push(quote!(#pattern => Ok(#value)));
+            } else {
+                arms.push(quote!(#pattern => #value));
+            }
+        }
+
+        if fallible {
+            // The external enum can have variants we don't know about, if it doesn't
+            // this arm is unreachable.
+            Ok(quote! {
+                #[allow(unreachable_patterns)]
+                match value {
+                    #(#arms,)*
+                    _ => Err(InteropError {
+                        type_name: #name,
+                        reason: "the variant does not exist in the generated type".to_string(),
+                    }),
+                }
+            })
+        } else {
+            Ok(quote! {
+                match value {
+                    #(#arms,)*
+                }
+            })
+        }
+    }
+
+    /// Convert a value of a field, the types we don't map must be the same on both
+    /// sides.
+    fn convert_value(
+        &self,
+        field: &str,
+        ty: &syn::Type,
+        value: TokenStream,
+        fallible: bool,
+    ) -> Result<TokenStream> {
+        match self.convert(field, ty, value.clone(), fallible)? {
+            None => Ok(value),
+            Some(conversion) if fallible => Ok(quote!(#conversion?)),
+            Some(conversion) => Ok(conversion),
+        }
+    }
+
+    /// Returns the conversion of `value` of type `ty`, or `None` if it has no mapped
+    /// types in it. The fallible conversions return a `Result`.
+    fn convert(
+        &self,
+        field: &str,
+        ty: &syn::Type,
+        value: TokenStream,
+        fallible: bool,
+    ) -> Result<Option<TokenStream>> {
+        let syn::Type::Path(path) = ty else {
+            return Ok(None);
+        };
+        let Some(segment) = path.path.segments.last() else {
+            return Ok(None);
+        };
+        let ident = segment.ident.to_string();
+
+        if self.generated.contains(&ident) {
+            if !self.map.types.contains_key(&ident) {
+                anyhow::bail!(
+                    "`{}` is a `{}`, which is not in the interop map",
+                    field,
+                    ident
+                );
+            }
+            return Ok(Some(if fallible {
+                quote!(#value.try_into())
+            } else {
+                quote!(#value.into())
+            }));
+        }
+
+        // Look inside the containers.
+        let args: Vec<&syn::Type> = match &segment.arguments {
+            syn::PathArguments::AngleBracketed(args) => args
+                .args
+                .iter()
+                .filter_map(|arg| match arg {
+                    syn::GenericArgument::Type(t) => Some(t),
+                    _ => None,
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+        let inner = match args.last() {
+            Some(inner) => self.convert(field, inner, quote!(v), fallible)?,
+            None => None,
+        };
+        let Some(inner) = inner else {
+            return Ok(None);
+        };
+
+        let conversion = match (ident.as_str(), args.len(), fallible) {
+            ("Option", 1, false) => quote!(#value.map(|v| #inner)),
+            ("Option", 1, true) => quote!(#value.map(|v| #inner).transpose()),
+            ("Box", 1, false) => quote!(Box::new((*#value).into())),
+            ("Box", 1, true) => quote!((*#value).try_into().map(Box::new)),
+            ("Vec" | "HashSet" | "BTreeSet" | "IndexSet", 1, false) => {
+                quote!(#value.into_iter().map(|v| #inner).collect())
+            }
+            ("Vec" | "HashSet" | "BTreeSet" | "IndexSet", 1, true) => {
+                quote!(#value.into_iter().map(|v| #inner).collect::<Result<_, _>>())
+            }
+            ("HashMap" | "BTreeMap" | "IndexMap", 2, false) => {
+                quote!(#value.into_iter().map(|(k, v)| (k, #inner)).collect())
+            }
+            ("HashMap" | "BTreeMap" | "IndexMap", 2, true) => {
+                quote!(#value.into_iter().map(|(k, v)| #inner.map(|v| (k, v))).collect::<Result<_, _>>())
+            }
+            _ => anyhow::bail!(
+                "`{}` is a `{}`, which we don't know how to convert",
+                field,
+                quote!(#ty)
+            ),
+        };
+
+        Ok(Some(conversion))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::InteropMap;
+
+    fn map(contents: &str) -> InteropMap {
+        toml::from_str(contents).unwrap()
+    }
+
+    fn render_err(contents: &str, rendered: proc_macro2::TokenStream) -> String {
+        map(contents).render(&rendered).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn test_load_interop_map() {
+        let map = InteropMap::load("tests/interop.toml").unwrap();
+        assert_eq!(map.types["ThingKind"].path(), "mock_interop::Kind");
+        assert_eq!(map.types["Thing"].rename("name"), "title");
+        assert_eq!(map.types["Thing"].rename("id"), "id");
+
+        // The path is relative to the map.
+        let dependencies = map.cargo_dependencies().unwrap();
+        let expected = std::fs::canonicalize("tests/interop").unwrap();
+        assert_eq!(
+            dependencies,
+            format!(
+                "mock-interop = {{ optional = true, path = \"{}\" }}\n",
+                expected.display()
+            )
+        );
+        assert_eq!(map.cargo_feature(), r#"interop = ["dep:mock-interop"]"#);
+    }
+
+    #[test]
+    fn test_interop_dependency_version() {
+        let map = map(r#"
+            [dependencies]
+            other = "1.2"
+            "#);
+        assert_eq!(
+            map.cargo_dependencies().unwrap(),
+            "other = { optional = true, version = \"1.2\" }\n"
+        );
+    }
+
+    #[test]
+    fn test_interop_fails_on_unmatched_types() {
+        let rendered = quote! {
+            pub struct Thing {
+                pub name: String,
+                pub kind: Kind,
+            }
+
+            pub enum Kind {
+                Big,
+                Small,
+            }
+
+            pub type Alias = String;
+        };
+
+        // Every generated type inside a mapped type must be mapped too.
+        assert_eq!(
+            render_err(
+                r#"
+                [types]
+                Thing = "other::Thing"
+                "#,
+                rendered.clone(),
+            ),
+            "`Thing.kind` is a `Kind`, which is not in the interop map"
+        );
+        assert_eq!(
+            render_err(
+                r#"
+                [types]
+                Missing = "other::Missing"
+                "#,
+                rendered.clone(),
+            ),
+            "the interop map has `Missing`, which is not a generated type"
+        );
+        assert_eq!(
+            render_err(
+                r#"
+                [types]
+                Kind = { path = "other::Kind", renames = { Medium = "M" } }
+                "#,
+                rendered.clone(),
+            ),
+            "the interop map renames `Medium` of `Kind`, which has no such field or variant"
+        );
+        assert_eq!(
+            render_err(
+                r#"
+                [types]
+                Alias = "other::Alias"
+                "#,
+                rendered.clone(),
+            ),
+            "`Alias` is not a struct or an enum, so it cannot be converted"
+        );
+
+        let rendered = map(r#"
+            [types]
+            Thing = "other::Thing"
+            Kind = { path = "other::Kind", renames = { Big = "Large" } }
+            "#)
+        .render(&rendered)
+        .unwrap()
+        .to_string();
+        assert!(rendered.contains("kind : value . kind . into ()"));
+        assert!(rendered.contains("crate :: types :: Kind :: Big => other :: Kind :: Large"));
+    }
+}
@@ -1,20 +1,48 @@
 //! A library for generating rust client sdks from OpenAPI specs.
 #![deny(missing_docs)]
 
+pub mod accessors;
+pub mod borrowed;
+pub mod budgets;
+pub mod changelog;
 pub mod client;
+pub mod compat;
+pub mod config;
+pub mod conformance;
+pub mod device_flow;
+pub mod error_codes;
+pub mod failures;
 pub mod functions;
+pub mod fuzz;
+pub mod hooks;
+pub mod interop;
+pub mod modules;
+pub mod no_std;
+pub mod opaque;
+pub mod openapi31;
+pub mod poll_helpers;
+pub mod servers;
+pub mod session;
+pub mod smoke;
 pub mod template;
 #[cfg(test)]
 mod tests;
 pub mod types;
+pub mod update_helpers;
+pub mod upload_sessions;
+pub mod watch;
+pub mod workspace;
 
 #[macro_use]
 extern crate quote;
 
-use std::{collections::HashMap, fs, io::Write};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    io::Write,
+};
 
 use anyhow::Result;
-use clap::Parser;
 use slog::Drain;
 
 use crate::types::exts::ReferenceOrExt;
@@ -35,14 +63,86 @@ where
     Ok(())
 }
 
+/// Generated code that does not parse, found before we wrote it.
+#[derive(Debug, thiserror::Error)]
+#[error("generated code for `{}` does not parse: {error}", file.display())]
+pub struct InvalidCodeError {
+    /// The file we were about to write.
+    pub file: std::path::PathBuf,
+    /// Where and why the code failed to parse.
+    #[source]
+    pub error: crate::types::ParseError,
+    /// In debug mode, the file the unformatted code was dumped to.
+    pub dump: Option<std::path::PathBuf>,
+}
+
+/// Check the generated code parses, then format and save it.
+/// If it does not parse, nothing is written to `p`.
+fn save_rust<P>(p: P, output: &proc_macro2::TokenStream, opts: &Opts) -> Result<()>
+where
+    P: AsRef<std::path::Path>,
+{
+    let p = p.as_ref();
+    if let Err(error) = crate::types::validate_code(output) {
+        // Keep the raw tokens around so the line and column of the error mean
+        // something.
+        let dump = if opts.debug {
+            let dump = p.with_extension("rs.tokens");
+            crate::save(&dump, &output.to_string())?;
+            Some(dump)
+        } else {
+            None
+        };
+
+        return Err(InvalidCodeError {
+            file: p.to_path_buf(),
+            error,
+            dump,
+        }
+        .into());
+    }
+
+    crate::save(p, &crate::types::get_text_fmt(output)?)
+}
+
+/// The version of a spec, to tell OpenAPI 3.1 from 3.0.
+#[derive(serde::Deserialize)]
+struct SpecVersion {
+    openapi: Option<serde_json::Value>,
+}
+
+impl SpecVersion {
+    fn is_openapi_31(&self) -> bool {
+        openapi31::is_openapi_31(self.openapi.as_ref().and_then(|v| v.as_str()))
+    }
+}
+
 /// Parse an OpenAPI v3 spec JSON string as an OpenAPI struct.
+/// OpenAPI 3.1 specs are converted to 3.0 first.
 pub fn load_json_spec(s: &str) -> Result<openapiv3::OpenAPI> {
-    serde_json::from_str(s).map_err(|e| anyhow::anyhow!(e))
+    let version: SpecVersion = serde_json::from_str(s)?;
+    if !version.is_openapi_31() {
+        return serde_json::from_str(s).map_err(|e| anyhow::anyhow!(e));
+    }
+
+    let mut spec: serde_json::Value = serde_json::from_str(s)?;
+    openapi31::down_convert(&mut spec)?;
+    serde_json::from_value(spec).map_err(|e| anyhow::anyhow!(e))
 }
 
 /// Parse an OpenAPI v3 spec YAML string as an OpenAPI struct.
+/// OpenAPI 3.1 specs are converted to 3.0 first.
 pub fn load_yaml_spec(s: &str) -> Result<openapiv3::OpenAPI> {
-    serde_yaml::from_str(s).map_err(|e| anyhow::anyhow!(e))
+    let version: SpecVersion = serde_yaml::from_str(s)?;
+    if !version.is_openapi_31() {
+        return serde_yaml::from_str(s).map_err(|e| anyhow::anyhow!(e));
+    }
+
+    // Through JSON, which has the numeric keys of YAML as strings.
+    let spec: serde_yaml::Value = serde_yaml::from_str(s)?;
+    let mut spec = serde_json::to_value(spec)?;
+    openapi31::down_convert(&mut spec)?;
+    serde_json::from_value(spec).map_err(|e| anyhow::anyhow!(e))
 }
 
 /// Parse a file as an OpenAPI spec.
@@ -74,7 +174,7 @@ fn internal_generate(spec: &openapiv3::OpenAPI, opts: &Opts) -> Result<String> {
      * Deal with any dependencies we require to produce this client.
      */
     a("#![allow(elided_named_lifetimes)]");
-    a("#![allow(missing_docs)]"); // TODO: Make this a deny.
+    a(lint_attributes(opts)); // TODO: Deny `missing_docs` without `--strict-lints` too.
     a("#![allow(unused_imports)]");
     a("#![allow(clippy::needless_lifetimes)]"); // TODO: Fix this.
     a("#![allow(clippy::too_many_arguments)]"); // TODO: Fix this.
@@ -82,28 +182,149 @@ fn internal_generate(spec: &openapiv3::OpenAPI, opts: &Opts) -> Result<String> {
     a("#![cfg_attr(docsrs, feature(doc_cfg))]");
     a("");
 
+    let workspace = crate::workspace::Workspace::new(opts);
+
     // Write our persistent modules.
     for module in persistent_modules() {
         if module == "tests" {
             a("#[cfg(test)]");
         }
         a(&format!("mod {module};"));
-        // Ensure that if there's no file, an empty file is created.
-        std::process::Command::new("touch")
-            .arg(format!("{}/src/{module}.rs", opts.output.display()))
-            .spawn()?
-            .wait()?;
     }
 
-    // Hopefully there is never a "tag" named after these reserved libs.
-    a("pub mod types;");
+    // Our own modules, see `hooks`.
+    for module in crate::hooks::included_modules(opts)? {
+        a(&module.declaration());
+    }
 
-    // If we have any options that would require us to generate a utils file
-    // we need include it in the client.
-    if opts.needs_utils_file() {
-        a("pub mod utils;");
+    // The aliases for what the generation renamed, see `compat`.
+    if opts.rename_shims.is_some() {
+        a(&format!("pub mod {};", crate::compat::MODULE));
     }
 
+    if let Some(workspace) = &workspace {
+        // The types are a crate of their own.
+        for module in workspace.core_modules(opts) {
+            a(&module);
+        }
+    } else {
+        // The tags named like these get other modules, see `clean_tag_name`.
+        a("pub mod types;");
+
+        // If we have any options that would require us to generate a utils file
+        // we need include it in the client.
+        if opts.needs_utils_file() {
+            a("pub mod utils;");
+        }
+    }
+
+    // The tests of the examples of the spec, see `conformance`.
+    if opts.generate_conformance_tests {
+        a("#[cfg(test)]");
+        a("mod conformance_tests;");
+    }
+
+    // The smoke tests against the real API, see `smoke`.
+    if opts.generate_smoke_tests {
+        a("#[cfg(all(test, feature = \"requests\"))]");
+        a("mod smoke_tests;");
+    }
+
+    // A crate of only the types has no client.
+    if opts.types_only {
+        return Ok(out);
+    }
+
+    // The OAuth 2.0 device flow, see `device_flow`.
+    if crate::device_flow::find(spec, opts)?.is_some() {
+        a("#[cfg(feature = \"requests\")]");
+        a("#[cfg(not(target_arch = \"wasm32\"))]");
+        a("pub mod device_flow;");
+    }
+
+    // The tags of the operations.
+    let tags = get_tags_with_paths(spec)?;
+
+    // The tags that are nested in other modules, see `modules`.
+    let module_map = crate::modules::ModuleMap::new(opts)?;
+    let tree = module_map.tree(&tags)?;
+
+    /*
+     * Import the module for each tag.
+     * Tags are how functions are grouped.
+     */
+    for tag in tags.iter() {
+        // Continue if this tag is a crate of its own, or in the module of a group.
+        if workspace.is_some() || module_map.is_mapped(&clean_tag_name(&tag.name)) {
+            continue;
+        }
+
+        let docs = get_tag_module_docs(tag, opts);
+        if !docs.is_empty() {
+            a(&format!("/// {}", docs.replace('\n', "\n/// "),));
+        }
+        a("#[cfg(feature = \"requests\")]");
+        a(&format!("pub mod {};", clean_tag_name(&tag.name)));
+    }
+    for (name, child) in &tree.children {
+        let (docs, _) = crate::modules::get_child_docs(name, child, opts);
+        if !docs.is_empty() {
+            a(&format!("/// {}", docs.replace('\n', "\n/// "),));
+        }
+        a("#[cfg(feature = \"requests\")]");
+        a(&format!("pub mod {};", name));
+    }
+
+    a("");
+
+    // Print the client template.
+    a(&crate::client::generate_client(spec, opts));
+
+    a("");
+
+    /*
+     * Generate a function for each tag.
+     * Tags are how functions are grouped.
+     */
+    let mut accessors = Vec::new();
+    for tag in tags.iter() {
+        // Continue if this tag is in the module of a group, the crate of a tag has its
+        // own trait for this.
+        let module = clean_tag_name(&tag.name);
+        if workspace.is_some() || module_map.is_mapped(&module) {
+            continue;
+        }
+        accessors.push((module, get_tag_accessor_docs(tag)));
+    }
+    if workspace.is_none() {
+        for (name, child) in &tree.children {
+            let (_, docs) = crate::modules::get_child_docs(name, child, opts);
+            accessors.push((name.to_string(), docs));
+        }
+    }
+    for (module, docs) in accessors {
+        a(&format!(
+            r#"/// {}
+               pub fn {}(&self) -> {}::{} {{
+                    {}::{}::new(self.clone())
+               }}"#,
+            docs.replace('\n', "\n/// "),
+            module,
+            module,
+            types::proper_name(&module),
+            module,
+            types::proper_name(&module),
+        ));
+        a("");
+    }
+
+    a("}");
+
+    Ok(out)
+}
+
+/// The tags of the operations of the spec, with the ones the spec does not list.
+fn get_tags_with_paths(spec: &openapiv3::OpenAPI) -> Result<Vec<openapiv3::Tag>> {
     // First get the tags for all the paths, then later we can ignore tags that
     // have no paths.
     let default_tag = "default".to_string();
@@ -134,6 +355,7 @@ fn internal_generate(spec: &openapiv3::OpenAPI, opts: &Opts) -> Result<String> {
         get_tags(op.delete.as_ref())?;
         get_tags(op.head.as_ref())?;
         get_tags(op.patch.as_ref())?;
+        get_tags(op.options.as_ref())?;
         get_tags(op.trace.as_ref())?;
     }
 
@@ -157,87 +379,93 @@ fn internal_generate(spec: &openapiv3::OpenAPI, opts: &Opts) -> Result<String> {
         }
     }
 
-    /*
-     * Import the module for each tag.
-     * Tags are how functions are grouped.
-     */
-    for tag in tags.iter() {
-        if !tags_with_paths.contains(&tag.name) {
-            // Continue if this tag has no paths.
-            continue;
-        }
-
-        let mut docs = "".to_string();
-        if let Some(d) = &tag.description {
-            docs = format!("{}.", d.trim_end_matches('.'));
-        }
-        if let Some(e) = &tag.external_docs {
-            if !e.url.is_empty() {
-                docs = format!("{}\n\nFROM: <{}>", docs, e.url);
-            }
-        }
-        docs = docs.trim().to_string();
+    Ok(tags
+        .into_iter()
+        .filter(|tag| tags_with_paths.contains(&tag.name))
+        .collect())
+}
 
-        if !docs.is_empty() {
-            a(&format!("/// {}", docs.replace('\n', "\n/// "),));
+/// The docs of the module of a tag.
+fn get_tag_module_docs(tag: &openapiv3::Tag, opts: &Opts) -> String {
+    let mut docs = "".to_string();
+    if let Some(d) = &tag.description {
+        docs = format!("{}.", d.trim_end_matches('.'));
+    }
+    if let Some(e) = &tag.external_docs {
+        if !e.url.is_empty() {
+            docs = format!("{}\n\nFROM: <{}>", docs, e.url);
         }
-        a("#[cfg(feature = \"requests\")]");
-        a(&format!("pub mod {};", clean_tag_name(&tag.name)));
     }
+    docs = docs.trim().to_string();
 
-    a("");
-
-    // Print the client template.
-    a(&crate::client::generate_client(opts));
+    if let Some(module) = reserved_module(&tag.name) {
+        let note = format!(
+            "The `{}` tag, renamed since the crate keeps `{}` for itself.",
+            tag.name, module
+        );
+        docs = if docs.is_empty() {
+            note
+        } else {
+            format!("{}\n\n{}", docs, note)
+        };
+    }
+    if docs.is_empty() && opts.strict_lints {
+        docs = format!("The operations of the `{}` tag.", tag.name);
+    }
+    docs
+}
 
-    a("");
+/// The attributes of the lints of a generated crate, see `Opts::strict_lints`.
+pub(crate) fn lint_attributes(opts: &Opts) -> &'static str {
+    if opts.strict_lints {
+        "#![forbid(unsafe_code)]\n#![deny(missing_docs)]"
+    } else {
+        "#![allow(missing_docs)]"
+    }
+}
 
-    /*
-     * Generate a function for each tag.
-     * Tags are how functions are grouped.
-     */
-    for tag in tags.iter() {
-        if !tags_with_paths.contains(&tag.name) {
-            // Continue if this tag has no paths.
-            continue;
+/// The docs of the function of the client that hands out the operations of a tag.
+fn get_tag_accessor_docs(tag: &openapiv3::Tag) -> String {
+    let mut docs = format!(
+        "Return a reference to an interface that provides access to {} operations.",
+        tag.name
+    );
+    if let Some(d) = &tag.description {
+        docs = format!("{}.", d.trim_end_matches('.'));
+    }
+    if let Some(e) = &tag.external_docs {
+        if !e.url.is_empty() {
+            docs = format!("{}\n\nFROM: <{}>", docs, e.url);
         }
+    }
+    docs
+}
 
-        let mut docs = format!(
-            "Return a reference to an interface that provides access to {} operations.",
-            tag.name
-        );
-        if let Some(d) = &tag.description {
-            docs = format!("{}.", d.trim_end_matches('.'));
-        }
-        if let Some(e) = &tag.external_docs {
-            if !e.url.is_empty() {
-                docs = format!("{}\n\nFROM: <{}>", docs, e.url);
-            }
-        }
+/// The names a tag can't have for its module, see `clean_tag_name`: the modules of
+/// the crate, and `client`, whose module would have a `Client` of its own next to
+/// the `crate::Client` it uses.
+const RESERVED_MODULES: &[&str] = &["client", "methods", "tests", "types", "utils"];
 
-        a(&format!(
-            r#"/// {}
-               pub fn {}(&self) -> {}::{} {{
-                    {}::{}::new(self.clone())
-               }}"#,
-            docs.replace('\n', "\n/// "),
-            clean_tag_name(&tag.name),
-            clean_tag_name(&tag.name),
-            types::proper_name(&tag.name),
-            clean_tag_name(&tag.name),
-            types::proper_name(&tag.name),
-        ));
-        a("");
+/// Clean a tag name. The tags with a reserved name get an `_api` suffix, so
+/// `types` is `types_api`.
+fn clean_tag_name(s: &str) -> String {
+    match reserved_module(s) {
+        Some(module) => format!("{}_api", module),
+        None => tag_module_name(s),
     }
+}
 
-    a("}");
-
-    Ok(out)
+/// The reserved name a tag would have for its module, if it is one of them.
+fn reserved_module(s: &str) -> Option<String> {
+    let module = tag_module_name(s);
+    RESERVED_MODULES
+        .contains(&module.as_str())
+        .then_some(module)
 }
 
-/// Clean a tag name.
-fn clean_tag_name(s: &str) -> String {
-    let result = inflector::cases::snakecase::to_snake_case(s);
+/// The name of the module of a tag, before it is renamed.
+fn tag_module_name(s: &str) -> String {
+    let result = crate::types::casing::to_snake_case(s);
 
     if result == "oauth_2" {
         "oauth2".to_string()
@@ -246,48 +474,277 @@ fn clean_tag_name(s: &str) -> String {
     }
 }
 
+/// The tags with a reserved name, and the modules they get instead.
+fn get_renamed_tags(spec: &openapiv3::OpenAPI) -> Result<BTreeMap<String, String>> {
+    let mut renamed = BTreeMap::new();
+    for (_, item) in spec.paths.iter() {
+        for (_, op) in item.item()?.iter() {
+            if let Some(tag) = op.tags.first() {
+                if reserved_module(tag).is_some() {
+                    renamed.insert(tag.to_string(), clean_tag_name(tag));
+                }
+            }
+        }
+    }
+    Ok(renamed)
+}
+
 /// Generate the client library.
 pub fn generate(spec: &openapiv3::OpenAPI, opts: &Opts) -> Result<()> {
+    generate_library(spec, opts, true).map(|_| ())
+}
+
+/// Generate the client library and return its manifest. Without `polish` the
+/// output is not run through `cargo fmt` or clippy, for the quick generations of
+/// `--watch`.
+fn generate_library(
+    spec: &openapiv3::OpenAPI,
+    opts: &Opts,
+    polish: bool,
+) -> Result<crate::changelog::Manifest> {
+    // Name everything with the casing of the options.
+    let _casing = crate::types::casing::scoped(opts);
+
+    // Find the dangling references before anything trips on them.
+    crate::types::references::check_references(spec)?;
+
+    // Rename any schemas that would collide once they are Rust names.
+    // Everything below works off of the renamed spec.
+    let (spec, renamed_schemas) = crate::types::dedupe_schema_names(spec)?;
+    // Leave the schemas of `--opaque-type` out, see `opaque`.
+    let (spec, opaque) = crate::opaque::prepare(&spec, opts)?;
+    // Name the operations the spec left without an id.
+    let (spec, synthesized_operation_ids) = crate::functions::synthesize_operation_ids(&spec)?;
+    let spec = &spec;
+
+    // Without a base url we use the one of the servers of the spec.
+    let opts = &crate::servers::with_base_url(spec, opts)?;
+
+    // The custom date-time format parses into `chrono` types.
+    if opts.date_time_format.is_some() && opts.datetime_crate != DateTimeCrate::Chrono {
+        anyhow::bail!("`--date-time-format` is only supported with `--datetime-crate chrono`");
+    }
+    if let Some(format) = &opts.date_time_format {
+        crate::template::check_date_time_format(format)?;
+    }
+
+    // The session wraps a websocket connection of the client.
+    if opts.types_only && opts.session_config.is_some() {
+        anyhow::bail!("`--session-config` is not supported with `--types-only`");
+    }
+
+    // A crate of only the types has no tags to split by.
+    if opts.types_only && opts.split_by_tag {
+        anyhow::bail!("`--split-by-tag` is not supported with `--types-only`");
+    }
+
+    // The smoke tests call the operations of the client of the crate.
+    if opts.generate_smoke_tests && (opts.types_only || opts.split_by_tag) {
+        anyhow::bail!(
+            "`--generate-smoke-tests` is not supported with `--types-only` or `--split-by-tag`"
+        );
+    }
+
+    // The crates of the tags are not nested in one another.
+    if !opts.module_map.is_empty() && opts.split_by_tag {
+        anyhow::bail!("`--module-map` is not supported with `--split-by-tag`");
+    }
+
+    // The fuzz targets read the types of the crate, not of a crate of their own.
+    if opts.generate_fuzz && opts.split_by_tag {
+        anyhow::bail!("`--generate-fuzz` is not supported with `--split-by-tag`");
+    }
+
+    // Generate the types and the functions before we write anything, so every schema
+    // and operation that fails is reported at once.
+    let mut failures = crate::failures::Failures::default();
+    let mut type_space =
+        crate::types::generate_types_collecting(spec, opts.clone(), &mut failures)?;
+    let mut generated =
+        crate::functions::generate_files_collecting(&mut type_space, opts, &mut failures)?;
+    if !opts.keep_going {
+        failures.clone().into_result()?;
+    }
+    // With `--keep-going` we leave out what failed, and generate from what is left.
+    let pruned;
+    let spec = if failures.is_empty() {
+        spec
+    } else {
+        for failure in &failures.failures {
+            log::warn!("skipping {}: {}", failure.item, failure.error);
+        }
+        pruned = failures.prune(spec)?;
+        let mut left = crate::failures::Failures::default();
+        type_space = crate::types::generate_types_collecting(&pruned, opts.clone(), &mut left)?;
+        generated = crate::functions::generate_files_collecting(&mut type_space, opts, &mut left)?;
+        left.into_result()?;
+        &pruned
+    };
+
+    let workspace = crate::workspace::Workspace::new(opts);
+
+    // Read the interop map first, so a bad one fails before we write anything.
+    let interop = opts
+        .interop_map
+        .as_ref()
+        .map(crate::interop::InteropMap::load)
+        .transpose()?;
+    // Read our own code too, it has to parse, see `hooks`.
+    let tags = get_tags_with_paths(spec)?;
+    let tag_modules = tags
+        .iter()
+        .map(|tag| clean_tag_name(&tag.name))
+        .chain(
+            crate::modules::ModuleMap::new(opts)?
+                .tree(&tags)?
+                .children
+                .into_keys(),
+        )
+        .collect::<Vec<_>>();
+    let hooks = crate::hooks::Hooks::load(opts, &tag_modules)?;
+    // The previous generation, before we write over its manifest, see `compat`.
+    let previous = match &opts.rename_shims {
+        Some(_) if tag_modules.iter().any(|m| m == crate::compat::MODULE) => {
+            anyhow::bail!(
+                "`--rename-shims` needs the `{}` module, a tag has it",
+                crate::compat::MODULE
+            );
+        }
+        Some(p) => Some(crate::changelog::Manifest::load(p, opts)?),
+        None => None,
+    };
+    let session = opts
+        .session_config
+        .as_ref()
+        .map(crate::session::SessionConfig::load)
+        .transpose()?
+        .map(|session| session.render(spec, &renamed_schemas))
+        .transpose()?;
+
     // Generate the client.
     let out = crate::internal_generate(spec, opts)?;
 
     // Create the top-level crate directory:
-    fs::create_dir_all(&opts.output)?;
+    let library_dir = library_dir(opts);
+    fs::create_dir_all(&library_dir)?;
+
+    if opts.keep_going {
+        crate::save(
+            opts.output.join("failures.json"),
+            &serde_json::to_string_pretty(&failures.report())?,
+        )?;
+    }
+
+    if !renamed_schemas.is_empty() {
+        log::warn!(
+            "renamed {} schema(s), see `x-rust-renamed-schemas` in the spec patch: {}",
+            renamed_schemas.len(),
+            renamed_schemas
+                .iter()
+                .map(|(old, new)| format!("`{}` => `{}`", old, new))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+
+    if !opts.opaque_types.is_empty() {
+        log::info!(
+            "made {} schema(s) opaque, {} type(s) avoided: {}",
+            opaque.opaque.len() + opaque.unreachable.len(),
+            opaque.types_avoided,
+            opaque
+                .opaque
+                .iter()
+                .chain(opaque.unreachable.iter())
+                .map(|name| format!("`{}`", name))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        crate::save(
+            opts.output.join("opaque.json"),
+            &serde_json::to_string_pretty(&opaque)?,
+        )?;
+    }
+
+    // Check the sizes of the requests before we write the crate, see `budgets`.
+    if opts.generate_budgets {
+        crate::budgets::check(spec, opts)?;
+    }
 
     // Write the Cargo.toml file:
-    let mut toml = opts.output.clone();
+    let mut toml = library_dir.clone();
     toml.push("Cargo.toml");
-    let tomlout = generate_cargo_toml(opts);
-    crate::save(&toml, tomlout.as_str())?;
+    let tomlout = generate_cargo_toml(spec, opts, interop.as_ref())?;
+    match &workspace {
+        Some(workspace) => crate::save(&toml, &workspace.core_cargo_toml(&tomlout, opts)?)?,
+        None => crate::save(&toml, tomlout.as_str())?,
+    }
 
     /*
      * Generate our documentation for the library.
      */
     let docs = crate::template::generate_docs(spec, opts)?;
-    let mut readme = opts.output.clone();
+    let mut readme = library_dir.clone();
     readme.push("README.md");
-    crate::save(
-        readme,
-        // Add a title to the README.md so it looks nicer in GitHub.
-        &format!(
-            "# `{}`\n\n{}",
-            opts.name,
-            docs.replace("//! ", "").replace("//!", "").as_str()
-        ),
-    )?;
+    crate::save(readme, &crate::template::generate_readme(&docs, opts))?;
 
     // Create the src/ directory.
-    let mut src = opts.output.clone();
-    src.push("src");
+    let src = library_dir.join("src");
     fs::create_dir_all(&src)?;
 
+    // Ensure that if there's no file for a persistent module, an empty file is
+    // created.
+    for module in persistent_modules() {
+        let file = src.join(format!("{module}.rs"));
+        if !file.exists() {
+            crate::save(file, "")?;
+        }
+    }
+
+    // The types go in a crate of their own when we split by tag.
+    let types_src = match &workspace {
+        Some(workspace) => {
+            let types_dir = workspace.crate_dir(&workspace.types_package());
+            let types_src = types_dir.join("src");
+            fs::create_dir_all(&types_src)?;
+            // The split types are all generated so we start them over.
+            if types_src.join("types").exists() {
+                fs::remove_dir_all(types_src.join("types"))?;
+            }
+            crate::save(
+                types_dir.join("Cargo.toml"),
+                &workspace.types_cargo_toml(&tomlout, opts)?,
+            )?;
+            crate::save(
+                types_dir.join("README.md"),
+                &workspace.readme(
+                    &workspace.types_package(),
+                    &format!("The types of the `{}` crate.", opts.name),
+                ),
+            )?;
+            crate::save(types_src.join("lib.rs"), &workspace.types_lib_rs(opts))?;
+            types_src
+        }
+        None => src.clone(),
+    };
+
+    // The modules the tags are nested in, see `modules`.
+    let module_map = crate::modules::ModuleMap::new(opts)?;
+    let tags = get_tags_with_paths(spec)?;
+    let module_tree = module_map.tree(&tags)?;
+
     // Clean up any old files we might have.
     // Walk the src/ directory and delete any files that aren't a persistent module.
     let src_list = fs::read_dir(&src)?;
     for file in src_list {
         let file = file?;
-        // Return early if it is a directory.
+        // Return early if it is a directory, the split types and the modules of the
+        // nested tags are all generated so we start them over.
         if file.file_type()?.is_dir() {
+            let name = file.file_name().to_string_lossy().to_string();
+            if name == "types" || module_tree.children.contains_key(&name) {
+                fs::remove_dir_all(file.path())?;
+            }
             continue;
         }
         // Get the file name.
@@ -301,7 +758,7 @@ pub fn generate(spec: &openapiv3::OpenAPI, opts: &Opts) -> Result<()> {
             .to_string();
 
         let persistent_modules = persistent_modules();
-        if persistent_modules.contains(&file_name.as_str()) {
+        if persistent_modules.contains(&file_name.as_str()) || hooks.is_included(&file_name) {
             continue;
         }
 
@@ -309,37 +766,130 @@ pub fn generate(spec: &openapiv3::OpenAPI, opts: &Opts) -> Result<()> {
         fs::remove_file(file.path())?;
     }
 
-    // Create the Rust source file containing the generated client.
-    let lib = format!("{}\n{}", docs, out);
-    let mut librs = src.clone();
-    librs.push("lib.rs");
-    crate::save(librs, lib.as_str())?;
+    hooks.save(&src)?;
 
     if let Some(utils) = crate::template::generate_utils(opts) {
-        let mut utilsrs = src.clone();
+        let mut utilsrs = types_src.clone();
         utilsrs.push("utils.rs");
         crate::save(utilsrs, utils.as_str())?;
     }
 
-    // Create the Rust source types file containing the generated types.
-    let mut type_space = crate::types::generate_types(spec, opts.clone())?;
+    if opts.generate_conformance_tests {
+        let tests = crate::conformance::render(spec)?;
+        crate::save_rust(src.join("conformance_tests.rs"), &tests, opts)?;
+    }
+
+    // The types and the functions we generated first.
+    let (files, modified_spec, operations) = generated;
 
-    // Create the Rust source files for each of the tags functions.
-    let (files, modified_spec) = crate::functions::generate_files(&mut type_space, opts)?;
+    if !opts.types_only {
+        crate::modules::save_groups(&src, &module_tree, opts)?;
+    }
+
+    if crate::device_flow::find(spec, opts)?.is_some() {
+        let device_flow = crate::device_flow::render_module()?;
+        crate::save_rust(src.join("device_flow.rs"), &device_flow, opts)?;
+    }
+
+    if opts.generate_smoke_tests {
+        let tests = crate::smoke::generate(&type_space, opts)?;
+        crate::save_rust(src.join("smoke_tests.rs"), &tests, opts)?;
+    }
+
+    if opts.generate_fuzz {
+        crate::fuzz::generate(&type_space.rendered, opts)?;
+    }
+
+    // A crate of only the types still gets the types the functions add, but none of
+    // the functions.
+    let files = if opts.types_only {
+        Default::default()
+    } else {
+        files
+    };
+
+    // Create the Rust source file containing the generated client, and the table of
+    // its operations.
+    let lib = if opts.types_only {
+        format!("{}\n{}", docs, out)
+    } else {
+        let operations =
+            crate::types::get_text_fmt(&crate::functions::render_operations(&operations))?;
+        format!("{}\n{}\n{}", docs, out, operations)
+    };
+    let mut librs = src.clone();
+    librs.push("lib.rs");
+    crate::save(librs, lib.as_str())?;
+
+    // Record the public surface of the library, so the next generation can tell
+    // what changed.
+    let manifest =
+        crate::changelog::Manifest::from_generated(spec, opts, &files, &type_space.rendered)?;
+    if let Some(previous) = &previous {
+        let renames = crate::compat::Renames::find(previous, &manifest);
+        for (old, new) in renames.types.iter().chain(renames.functions.iter()) {
+            log::info!(
+                "`{}` is renamed to `{}`, keeping a deprecated alias",
+                old,
+                new
+            );
+        }
+        let compat = renames.render(&manifest, &module_map, workspace.is_some())?;
+        crate::save_rust(
+            src.join(format!("{}.rs", crate::compat::MODULE)),
+            &compat,
+            opts,
+        )?;
+    }
     // We have a map of our files, let's write to them.
-    for (f, content) in files {
-        let mut tagrs = src.clone();
-        tagrs.push(format!("{}.rs", f));
-        let proper_tag_name = crate::types::proper_name(&f);
+    for (f, content) in &files {
+        let tag_src = match &workspace {
+            Some(workspace) => {
+                let tag_dir = workspace.crate_dir(&workspace.tag_package(f));
+                fs::create_dir_all(tag_dir.join("src"))?;
+                let description = format!("The `{}` operations of the `{}` crate.", f, opts.name);
+                crate::save(
+                    tag_dir.join("Cargo.toml"),
+                    &workspace.tag_cargo_toml(&tomlout, f, opts)?,
+                )?;
+                crate::save(
+                    tag_dir.join("README.md"),
+                    &workspace.readme(&workspace.tag_package(f), &description),
+                )?;
+                crate::save(
+                    tag_dir.join("src").join("lib.rs"),
+                    &workspace.tag_lib_rs(f, get_tag_description(spec, f), opts),
+                )?;
+                tag_dir.join("src")
+            }
+            None => src.clone(),
+        };
+        let tagrs = module_map.file(&tag_src, f);
+        let proper_tag_name = module_map.struct_name(f);
         let proper_tag_name_ident = format_ident!("{}", proper_tag_name);
+        let session = match &session {
+            Some((tag, session)) if tag == f => session.clone(),
+            _ => quote!(),
+        };
+        let (struct_docs, client_docs) = if opts.strict_lints {
+            let docs = format!("The `{}` operations.", f);
+            (
+                quote!(#[doc = #docs]),
+                quote!(#[doc = "The client the operations are sent with."]),
+            )
+        } else {
+            (quote!(), quote!())
+        };
 
         let output = quote! {
             use anyhow::Result;
 
             use crate::Client;
 
+            #struct_docs
             #[derive(Clone, Debug)]
             pub struct #proper_tag_name_ident {
+                #client_docs
                 pub client: Client,
             }
 
@@ -351,66 +901,182 @@ pub fn generate(spec: &openapiv3::OpenAPI, opts: &Opts) -> Result<()> {
 
                 #content
             }
+
+            #session
         };
-        // TODO: make fmt
-        crate::save(tagrs, &crate::types::get_text_fmt(&output)?)?;
+        crate::save_rust(tagrs, &output, opts)?;
+    }
+    if let Some(workspace) = &workspace {
+        crate::save(
+            opts.output.join("Cargo.toml"),
+            &workspace.cargo_toml(&files.keys().cloned().collect::<Vec<_>>()),
+        )?;
     }
 
     // Save the types, now that we've run the functions.
-    let mut typesrs = src.clone();
-    typesrs.push("types.rs");
-    crate::save(
-        typesrs,
-        crate::types::get_text_fmt(&type_space.rendered)?.as_str(),
-    )?;
+    let rendered = &type_space.rendered;
+    let schema_registry = type_space.render_schema_registry();
+    let interop = match &interop {
+        Some(interop) => interop.render(rendered)?,
+        None => quote!(),
+    };
+    let borrowed = crate::borrowed::render(rendered, &opts.borrowed_types)?;
+    let accessors = match opts.nested_accessors {
+        Some(depth) => crate::accessors::render(rendered, &operations, depth)?,
+        None => quote!(),
+    };
+    let core = if opts.no_std_core {
+        Some(crate::no_std::render(rendered)?)
+    } else {
+        None
+    };
+    // The module is a file of its own, so `no_std` crates can include it.
+    let core_mod = if core.is_some() {
+        quote!(
+            pub mod core;
+        )
+    } else {
+        quote!()
+    };
+    let mut rendered = quote! {
+        #rendered
+
+        #schema_registry
+
+        #interop
+
+        #borrowed
+
+        #accessors
+
+        #core_mod
+    };
+    // The client of the core crate uses the helpers of the types crate.
+    if workspace.is_some() {
+        rendered = crate::workspace::publish_crate_items(rendered);
+    }
+    if opts.split_types == TypesSplit::None {
+        let mut typesrs = types_src.clone();
+        typesrs.push("types.rs");
+        crate::save_rust(&typesrs, &rendered, opts)?;
+        hooks.append_to_types(&typesrs)?;
+    } else {
+        let split = crate::types::split::split_types(&rendered, &files, opts.split_types)?;
+        let mut types_dir = types_src.clone();
+        types_dir.push("types");
+        fs::create_dir_all(&types_dir)?;
+        crate::save_rust(types_dir.join("mod.rs"), &split.module, opts)?;
+        hooks.append_to_types(&types_dir.join("mod.rs"))?;
+        for (name, content) in &split.files {
+            crate::save_rust(types_dir.join(format!("{}.rs", name)), content, opts)?;
+        }
+    }
+    if let Some(core) = &core {
+        let types_dir = types_src.join("types");
+        fs::create_dir_all(&types_dir)?;
+        crate::save_rust(types_dir.join("core.rs"), core, opts)?;
+    }
 
-    // Run fmt in our output directory.
-    run_cargo_fmt(opts)?;
+    if polish {
+        // Run fmt in our output directory.
+        run_cargo_fmt(opts)?;
 
-    // Run clippy in our output directory.
-    if opts.clippy_fix {
-        run_cargo_clippy(opts)?;
+        // Run clippy in our output directory.
+        if opts.clippy_fix {
+            run_cargo_clippy(opts)?;
+        }
     }
 
-    // Also add our installation information to the modified_spec.
-    let mut extension: HashMap<String, String> = HashMap::new();
-    extension.insert(
-        "install".to_string(),
-        format!(
-            "[dependencies]\n{} = \"{}\"",
-            opts.name.replace('_', "-").to_lowercase(),
-            opts.target_version
-        ),
-    );
-    extension.insert(
-        "client".to_string(),
-        crate::functions::generate_example_client(opts),
-    );
+    // The patch adds the examples of the client to the spec, a crate of only the
+    // types has none.
+    if !opts.types_only {
+        // Also add our installation information to the modified_spec.
+        let mut extension: HashMap<String, String> = HashMap::new();
+        extension.insert(
+            "install".to_string(),
+            format!(
+                "[dependencies]\n{} = \"{}\"",
+                opts.name.replace('_', "-").to_lowercase(),
+                opts.target_version
+            ),
+        );
+        extension.insert(
+            "client".to_string(),
+            crate::functions::generate_example_client(opts),
+        );
 
-    // Add in our version information
-    let mut modified_spec = modified_spec;
-    modified_spec
-        .info
-        .extensions
-        .insert("x-rust".to_string(), serde_json::json!(extension));
-
-    // Create a JSON patch file with our changes.
-    let patch = json_patch::diff(
-        &serde_json::to_value(spec)?,
-        &serde_json::to_value(modified_spec)?,
+        // Add in our version information
+        let mut modified_spec = modified_spec;
+        modified_spec
+            .info
+            .extensions
+            .insert("x-rust".to_string(), serde_json::json!(extension));
+        // Record any schemas we had to rename, so they are easy to find.
+        if !renamed_schemas.is_empty() {
+            modified_spec.info.extensions.insert(
+                "x-rust-renamed-schemas".to_string(),
+                serde_json::json!(renamed_schemas),
+            );
+        }
+        // Record the tags with a reserved name, their modules have other names.
+        let renamed_tags = get_renamed_tags(spec)?;
+        if !renamed_tags.is_empty() {
+            modified_spec.info.extensions.insert(
+                "x-rust-renamed-tags".to_string(),
+                serde_json::json!(renamed_tags),
+            );
+        }
+        // Record the operation ids we made up, so the spec can get real ones.
+        if !synthesized_operation_ids.is_empty() {
+            modified_spec.info.extensions.insert(
+                "x-rust-synthesized-operation-ids".to_string(),
+                serde_json::json!(synthesized_operation_ids),
+            );
+        }
+
+        // Create a JSON patch file with our changes.
+        let patch = json_patch::diff(
+            &serde_json::to_value(spec)?,
+            &serde_json::to_value(modified_spec)?,
+        );
+        // Save our patch file.
+        let mut patch_file = opts.output.clone();
+        patch_file.push(format!("{}.rs.patch.json", opts.name));
+        crate::save(&patch_file, &serde_json::to_string_pretty(&patch)?)?;
+        log::info!("Patch file has been saved to {}", patch_file.display());
+    }
+
+    // Save our manifest file.
+    let mut manifest_file = opts.output.clone();
+    manifest_file.push(format!("{}.rs.manifest.json", opts.name));
+    crate::save(&manifest_file, &serde_json::to_string_pretty(&manifest)?)?;
+    log::info!(
+        "Manifest file has been saved to {}",
+        manifest_file.display()
     );
-    // Save our patch file.
-    let mut patch_file = opts.output.clone();
-    patch_file.push(format!("{}.rs.patch.json", opts.name));
-    crate::save(&patch_file, &serde_json::to_string_pretty(&patch)?)?;
-    log::info!("Patch file has been saved to {}", patch_file.display());
 
-    Ok(())
+    // Write the changelog against the previous generation.
+    if let Some(changelog_from) = &opts.changelog_from {
+        let previous = crate::changelog::Manifest::load(changelog_from, opts)?;
+        let changes = crate::changelog::diff(&previous, &manifest);
+        if !changes.is_empty() {
+            let mut changelog_file = opts.output.clone();
+            changelog_file.push("CHANGELOG.generated.md");
+            crate::changelog::write_changelog(
+                &changelog_file,
+                &crate::changelog::render_changelog(&previous, &manifest, &changes),
+            )?;
+            log::info!("Changelog has been saved to {}", changelog_file.display());
+        }
+    }
+
+    Ok(manifest)
 }
 
-/// The options for our generator.
-#[derive(Parser, Debug, Clone)]
-#[command(version = clap::crate_version!(), author = clap::crate_authors!("\n"))]
+/// The options for our generator, from the flags of `config::Cli` and its config
+/// file. The keys of the config file are the names of the fields.
+#[derive(clap::Args, Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct Opts {
     /// Print debug info.
     #[arg(short = 'D', long)]
@@ -422,27 +1088,28 @@ pub struct Opts {
 
     /// The input OpenAPI definition document (JSON | YAML).
     // TODO: We could also load from a URL.
-    #[arg(short, long, required = true)]
+    #[arg(skip)]
     pub input: std::path::PathBuf,
 
     /// The output directory for our generated client.
-    #[arg(short, long, default_value = ".", required = true)]
+    #[arg(short, long, default_value = ".")]
     pub output: std::path::PathBuf,
 
-    /// The base url for the API.
-    #[arg(short, long, required = true)]
-    pub base_url: url::Url,
+    /// The base url for the API, defaults to the url of the first server of the
+    /// spec with the defaults of its variables.
+    #[arg(short, long)]
+    pub base_url: Option<url::Url>,
 
     /// The crate name for our generated client.
-    #[arg(short, long, required = true)]
+    #[arg(skip)]
     pub name: String,
 
     /// The crate version for our generated client.
-    #[arg(short, long, required = true)]
+    #[arg(skip)]
     pub target_version: String,
 
     /// The crate description for our generated client.
-    #[arg(short, long, required = true)]
+    #[arg(skip)]
     pub description: String,
 
     /// The link to a hosted version of the spec.
@@ -461,7 +1128,8 @@ pub struct Opts {
     #[arg(long)]
     pub user_consent_endpoint: Option<url::Url>,
 
-    /// The date-time format for the API, defaults to Rust rfc3339 parser
+    /// The date-time format for the API, defaults to Rust rfc3339 parser. It is
+    /// used for the fields of the types and for the query and path parameters.
     #[arg(long)]
     pub date_time_format: Option<String>,
 
@@ -470,7 +1138,7 @@ pub struct Opts {
     pub basic_auth: bool,
 
     /// Default timeout on the client
-    #[arg(long)]
+    #[arg(skip)]
     pub request_timeout_seconds: u64,
 
     /// An additional env variable prefix (the default is the name of the package).
@@ -478,9 +1146,322 @@ pub struct Opts {
     #[arg(long)]
     pub add_env_prefix: Option<String>,
 
+    /// An environment variable to read the base url from in `new_from_env`, can be
+    /// given more than once. These are checked in order, before the ones named after
+    /// the package.
+    #[arg(long)]
+    pub base_url_env: Vec<String>,
+
     /// Run clippy --fix on the output code
     #[arg(long, default_value = "false")]
     pub clippy_fix: bool,
+
+    /// The manifest or spec of the previous generation, if given we write the changes
+    /// since then to `CHANGELOG.generated.md`.
+    #[arg(long)]
+    pub changelog_from: Option<std::path::PathBuf>,
+
+    /// Watch the input spec and generate the library again whenever it changes,
+    /// without `cargo fmt` or clippy, until Ctrl-C.
+    #[arg(long)]
+    pub watch: bool,
+
+    /// The crate backing the generated date and time types.
+    #[arg(long, value_enum, default_value_t = DateTimeCrate::Chrono)]
+    pub datetime_crate: DateTimeCrate,
+
+    /// The prefix of the rate limit headers, we read `{prefix}-Limit`,
+    /// `{prefix}-Remaining` and `{prefix}-Reset` from every response.
+    #[arg(long, default_value = "X-RateLimit")]
+    pub rate_limit_header_prefix: String,
+
+    /// The header of the responses with the id the server gave the request.
+    #[arg(long, default_value = "X-Request-Id")]
+    pub request_id_header: String,
+
+    /// Split the generated types into multiple files under `types/`, grouped by
+    /// the first letter of their name or by the tag of the operations using them.
+    #[arg(long, value_enum, default_value_t = TypesSplit::None)]
+    pub split_types: TypesSplit,
+
+    /// Honor `uniqueItems` on arrays: arrays of items that can be hashed become an
+    /// `indexmap::IndexSet`, the others are checked for duplicates before we send
+    /// them.
+    #[arg(long)]
+    pub unique_items_as_sets: bool,
+
+    /// A TOML file mapping generated types to the types of other crates, we generate
+    /// the conversions between them behind the `interop` feature.
+    #[arg(long)]
+    pub interop_map: Option<std::path::PathBuf>,
+
+    /// A TOML file overriding which `GET` and `PUT` operations we pair to generate
+    /// the read-modify-write `{put}_with` helpers.
+    #[arg(long)]
+    pub update_helpers: Option<std::path::PathBuf>,
+
+    /// A TOML file listing the commands of a websocket operation that must come
+    /// before others, we generate a `Session` next to the operation that checks the
+    /// order before sending.
+    #[arg(long)]
+    pub session_config: Option<std::path::PathBuf>,
+
+    /// Truncate the descriptions longer than this many characters, the docs then
+    /// point to the spec for the rest.
+    #[arg(long)]
+    pub max_description_length: Option<usize>,
+
+    /// Take the required string parameters of the functions as
+    /// `impl std::fmt::Display` and the optional ones as `Option<&str>`, so callers
+    /// pass a `&str`, a `String` or a UUID as is. Only the signatures change, we
+    /// send the same requests.
+    #[arg(long)]
+    pub display_string_params: bool,
+
+    /// A query parameter or header that makes an operation block until it is done
+    /// rather than answer `202 Accepted`, can be given more than once. The function
+    /// of an operation that can answer `202 Accepted` and takes one leaves it out,
+    /// and we generate a `{fn}_wait` function that sets it: a boolean to `true`, a
+    /// number to the timeout in seconds, and anything else to `wait={seconds}` as in
+    /// `Prefer: wait=30`, a header the function itself sets to `respond-async`.
+    #[arg(long = "wait-param", default_values_t = default_wait_params())]
+    pub wait_params: Vec<String>,
+
+    /// Only generate the types, for sharing them without a client: no client, no
+    /// tag modules, no request helpers, and none of their dependencies.
+    #[arg(long)]
+    pub types_only: bool,
+
+    /// Keep the acronyms of `--acronym` as one word in the names of the functions,
+    /// the fields and the tags: `oauth2_client` rather than `o_auth_2_client`. This
+    /// renames public items of the generated crate.
+    #[arg(long)]
+    pub acronym_casing: bool,
+
+    /// An acronym kept as one word with `--acronym-casing`, can be given more than
+    /// once. The ones given replace the default list.
+    #[arg(long = "acronym", default_values_t = default_acronyms())]
+    pub acronyms: Vec<String>,
+
+    /// Return the binary responses of the operations, as in
+    /// `application/octet-stream`, as a `Download` of the bytes with the file name of
+    /// the `Content-Disposition` header and the content type, rather than the bytes
+    /// alone. This changes the return types of these functions.
+    #[arg(long)]
+    pub download_responses: bool,
+
+    /// Parse the values of the string enums whatever their case, as in `"STEP"` for
+    /// `step`, for payloads from older versions of the API or from proxies that
+    /// change the case. We still send the values as they are in the spec.
+    #[arg(long)]
+    pub case_insensitive_enums: bool,
+
+    /// Keep the string fields of `format: password` or `writeOnly` in a
+    /// `types::secret::Secret`, whose `Debug` is `***` and that is wiped from memory
+    /// when dropped, with the `zeroize` feature of the generated crate. The `Display`
+    /// and the tables of the types show `***` for them too.
+    #[arg(long)]
+    pub secret_fields: bool,
+
+    /// Declare the fields of the structs in the order of their names in the JSON,
+    /// rather than in the order of the spec. Serde writes the fields in the order
+    /// they are declared, so the JSON of a value stays byte for byte the same when
+    /// the spec reorders the properties, for the bodies that are signed. The keys of
+    /// the maps keep the order they are inserted in.
+    #[arg(long)]
+    pub sort_fields: bool,
+
+    /// A type to generate a variant of in `types::borrowed` whose strings borrow
+    /// from the JSON it is parsed from, for the big responses on hot paths. Can be
+    /// given more than once.
+    #[arg(long = "borrowed-type")]
+    pub borrowed_types: Vec<String>,
+
+    /// A schema to leave as a `serde_json::Value` rather than generate types for,
+    /// like the big ones only ever passed through, can be given more than once and
+    /// have `*` wildcards. The schemas only it references get no types either, see
+    /// `opaque`.
+    #[arg(long = "opaque-type")]
+    pub opaque_types: Vec<String>,
+
+    /// Generate methods on the types that are only ever answered, never sent, for
+    /// the fields of their optional nested objects up to `DEPTH` fields deep, like
+    /// `customer.balance_cents()` for `customer.balance.cents`.
+    #[arg(long = "nested-accessors", value_name = "DEPTH")]
+    pub nested_accessors: Option<usize>,
+
+    /// Copy the types that need neither `std` nor any crate but `serde`, the ones
+    /// of only numbers, strings, lists, maps and each other, into `types::core`, a
+    /// module that compiles on its own in `no_std` crates with `alloc`.
+    #[arg(long)]
+    pub no_std_core: bool,
+
+    /// Generate a test for every JSON example of the schemas, responses and request
+    /// bodies of the spec, that reads it into its type and checks it is written
+    /// back the same. They go in `src/conformance_tests.rs`.
+    #[arg(long)]
+    pub generate_conformance_tests: bool,
+
+    /// Generate a workspace rather than a crate: `{name}-types` with the types,
+    /// `{name}-core` with the client, and a `{name}-{tag}` crate for every tag,
+    /// with its operations and a `{Tag}Ext` trait to get them from the client.
+    #[arg(long)]
+    pub split_by_tag: bool,
+
+    /// Record the size of the example request body of every operation in
+    /// `budgets.json`, to catch requests growing when the spec changes.
+    #[arg(long)]
+    pub generate_budgets: bool,
+
+    /// The `budgets.json` of a previous generation, with `--generate-budgets` the
+    /// generation fails if an example request grew more than `--budget-threshold`.
+    #[arg(long, requires = "generate_budgets")]
+    pub budget_baseline: Option<std::path::PathBuf>,
+
+    /// How many percent an example request can grow since `--budget-baseline`.
+    #[arg(long, default_value_t = 10.0)]
+    pub budget_threshold: f64,
+
+    /// Only warn about the example requests that grew past `--budget-threshold`,
+    /// rather than fail the generation.
+    #[arg(long)]
+    pub budget_warn_only: bool,
+
+    /// Generate an ignored test for every tag that calls one of its operations
+    /// that is safe to call against the real API, with the credentials of the env.
+    /// They go in `src/smoke_tests.rs`, and what each tag got in `smoke-tests.json`.
+    #[arg(long)]
+    pub generate_smoke_tests: bool,
+
+    /// A TOML file overriding the operations of `--generate-smoke-tests`, see
+    /// `smoke`.
+    #[arg(long, requires = "generate_smoke_tests")]
+    pub smoke_tests: Option<std::path::PathBuf>,
+
+    /// Generate a crate for `cargo fuzz` in `fuzz/`, with a target reading arbitrary
+    /// bytes into the `--fuzz-type`s and one round-tripping them through serde.
+    #[arg(long)]
+    pub generate_fuzz: bool,
+
+    /// A type the targets of `--generate-fuzz` read, can be given more than once.
+    /// Defaults to the tagged unions with the most variants, see `fuzz`.
+    #[arg(long = "fuzz-type", requires = "generate_fuzz")]
+    pub fuzz_types: Vec<String>,
+
+    /// Nest the module of a tag in another module, like `org-members=org::members`
+    /// for `client.org().members()`, can be given more than once. See `modules`.
+    #[arg(long = "module-map")]
+    pub module_map: Vec<String>,
+
+    /// Metadata of the schemas compared when deciding if two schemas with the same
+    /// name are the same type, can be given more than once. By default only their
+    /// shape is, see `types::equality`.
+    #[arg(long = "compare-schema-metadata", value_enum)]
+    pub compared_schema_metadata: Vec<crate::types::equality::SchemaMetadata>,
+
+    /// Generate a `validate` function on the objects checking the lengths and the
+    /// bounds of the spec, and call it on the request bodies before sending them, so
+    /// a body the server would reject is an `Error::InvalidRequest` listing every
+    /// field that breaks a constraint.
+    #[arg(long)]
+    pub validate_requests: bool,
+
+    /// Leave out the schemas and the operations that fail to generate, and what
+    /// depends on them, with a warning, rather than fail the generation. What was
+    /// left out is listed in `failures.json`.
+    #[arg(long)]
+    pub keep_going: bool,
+
+    /// A TOML file overriding which `POST` and `GET` operations we pair, and which
+    /// statuses we look for, to generate the `{post}_and_wait` helpers polling a job
+    /// until it is done.
+    #[arg(long)]
+    pub poll_helpers: Option<std::path::PathBuf>,
+
+    /// A module of our own, like `convert=hooks/convert.rs`, copied to `src/` and
+    /// declared in `lib.rs` at every generation, can be given more than once. See
+    /// `hooks`.
+    #[arg(long = "include-module")]
+    pub include_modules: Vec<String>,
+
+    /// Like `--include-module`, for a module declared `pub`.
+    #[arg(long = "include-pub-module")]
+    pub include_pub_modules: Vec<String>,
+
+    /// A file of Rust code appended as it is to the types module at every
+    /// generation, for the impls that need its private items. See `hooks`.
+    #[arg(long)]
+    pub append_to_types: Option<std::path::PathBuf>,
+
+    /// The manifest or spec of the previous generation, if given we keep the names
+    /// of the types and the functions renamed since then as deprecated aliases in
+    /// a `compat` module. See `compat`.
+    #[arg(long)]
+    pub rename_shims: Option<std::path::PathBuf>,
+
+    /// Forbid `unsafe` code and deny missing docs in the generated crate, the items
+    /// the spec does not describe get docs of their own, like "Struct generated
+    /// from schema `Foo`.".
+    #[arg(long)]
+    pub strict_lints: bool,
+}
+
+/// The parameters that make an operation wait by default, see `Opts::wait_params`.
+fn default_wait_params() -> Vec<String> {
+    vec!["wait".to_string(), "Prefer".to_string()]
+}
+
+/// The acronyms kept as one word by default, see `Opts::acronyms`.
+fn default_acronyms() -> Vec<String> {
+    crate::types::casing::DEFAULT_ACRONYMS
+        .iter()
+        .map(|acronym| acronym.to_string())
+        .collect()
+}
+
+/// The crates we can back the generated date and time types with.
+#[derive(
+    clap::ValueEnum,
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum DateTimeCrate {
+    /// Use `chrono`, the types are aliases of the `chrono` types.
+    #[default]
+    Chrono,
+    /// Use `time`, the types wrap the `time` types.
+    Time,
+}
+
+/// How to split the generated types into files.
+#[derive(
+    clap::ValueEnum,
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum TypesSplit {
+    /// Put all the types in `types.rs`.
+    #[default]
+    None,
+    /// Group the types by the first letter of their name.
+    Alphabetical,
+    /// Group the types by the tag of the operations using them, types used by more
+    /// than one tag go in `types/shared.rs`.
+    Tag,
 }
 
 impl Opts {
@@ -523,6 +1504,15 @@ impl Opts {
         inflector::cases::snakecase::to_snake_case(&self.name)
     }
 
+    /// Get the base url of the API without its trailing slash, see
+    /// `servers::with_base_url` for the one of a spec.
+    pub fn trimmed_base_url(&self) -> String {
+        self.base_url
+            .as_ref()
+            .map(|base_url| base_url.to_string().trim_end_matches('/').to_string())
+            .unwrap_or_default()
+    }
+
     /// Get whether these options require a utils.rs file to be generated.
     pub fn needs_utils_file(&self) -> bool {
         self.date_time_format.is_some()
@@ -536,7 +1526,7 @@ impl Default for Opts {
             json: Default::default(),
             input: Default::default(),
             output: Default::default(),
-            base_url: url::Url::parse("http://example.com").unwrap(),
+            base_url: Some(url::Url::parse("http://example.com").unwrap()),
             name: Default::default(),
             target_version: Default::default(),
             description: Default::default(),
@@ -548,18 +1538,94 @@ impl Default for Opts {
             basic_auth: Default::default(),
             clippy_fix: false,
             add_env_prefix: Default::default(),
+            base_url_env: Default::default(),
             request_timeout_seconds: 60,
+            changelog_from: Default::default(),
+            watch: false,
+            datetime_crate: Default::default(),
+            rate_limit_header_prefix: "X-RateLimit".to_string(),
+            request_id_header: "X-Request-Id".to_string(),
+            split_types: Default::default(),
+            unique_items_as_sets: Default::default(),
+            interop_map: Default::default(),
+            update_helpers: Default::default(),
+            session_config: Default::default(),
+            max_description_length: Default::default(),
+            display_string_params: Default::default(),
+            wait_params: default_wait_params(),
+            types_only: Default::default(),
+            acronym_casing: Default::default(),
+            acronyms: default_acronyms(),
+            download_responses: Default::default(),
+            case_insensitive_enums: Default::default(),
+            secret_fields: Default::default(),
+            sort_fields: Default::default(),
+            borrowed_types: Default::default(),
+            opaque_types: Default::default(),
+            nested_accessors: None,
+            no_std_core: Default::default(),
+            generate_conformance_tests: Default::default(),
+            generate_budgets: Default::default(),
+            budget_baseline: Default::default(),
+            budget_threshold: 10.0,
+            budget_warn_only: Default::default(),
+            generate_smoke_tests: Default::default(),
+            smoke_tests: Default::default(),
+            generate_fuzz: Default::default(),
+            fuzz_types: Default::default(),
+            module_map: Default::default(),
+            compared_schema_metadata: Default::default(),
+            validate_requests: Default::default(),
+            keep_going: Default::default(),
+            poll_helpers: Default::default(),
+            include_modules: Default::default(),
+            include_pub_modules: Default::default(),
+            append_to_types: Default::default(),
+            rename_shims: Default::default(),
+            strict_lints: Default::default(),
+            split_by_tag: Default::default(),
         }
     }
 }
 
+/// The directory of the crate with the client: the output directory, or the core
+/// crate of the workspace with `--split-by-tag`.
+fn library_dir(opts: &Opts) -> std::path::PathBuf {
+    match crate::workspace::Workspace::new(opts) {
+        Some(workspace) => workspace.crate_dir(&workspace.core_package()),
+        None => opts.output.clone(),
+    }
+}
+
+/// The description of the tag with the module `module`, if the spec has one.
+fn get_tag_description<'a>(spec: &'a openapiv3::OpenAPI, module: &str) -> Option<&'a str> {
+    spec.tags
+        .iter()
+        .find(|tag| clean_tag_name(&tag.name) == module)
+        .and_then(|tag| tag.description.as_deref())
+}
+
 /// Return a list of the persistent modules.
 /// These are modules we do not nuke at generation time.
 fn persistent_modules() -> Vec<&'static str> {
     vec!["tests", "methods"]
 }
 
-fn generate_cargo_toml(opts: &Opts) -> String {
+/// The description of the crate: the one of the options, or else the title of
+/// the spec, since crates.io rejects an empty one.
+pub fn get_crate_description(spec: &openapiv3::OpenAPI, opts: &Opts) -> String {
+    [opts.description.trim(), spec.info.title.trim()]
+        .iter()
+        .find(|description| !description.is_empty())
+        .map(|description| description.to_string())
+        .unwrap_or_else(|| format!("A generated client for the {} API.", opts.name))
+}
+
+fn generate_cargo_toml(
+    spec: &openapiv3::OpenAPI,
+    opts: &Opts,
+    interop: Option<&crate::interop::InteropMap>,
+) -> Result<String> {
     let repo_info = if let Some(repo) = &opts.repo_name {
         let output = if opts.output.display().to_string() == "." {
             "".to_string()
@@ -575,10 +1641,121 @@ fn generate_cargo_toml(opts: &Opts) -> String {
         "".to_string()
     };
 
-    format!(
+    // The crate backing the date and time types.
+    let (schemars_chrono, time_dep, chrono_dep, chrono_wasm_dep) = match opts.datetime_crate {
+        DateTimeCrate::Chrono => (
+            r#""chrono", "#,
+            "",
+            r#"chrono = { version = "0.4", default-features = false, features = ["now", "serde", "std"] }
+"#,
+            r#"chrono = { version = "0.4", default-features = false, features = ["serde", "std"] }
+"#,
+        ),
+        DateTimeCrate::Time => (
+            "",
+            r#"time = { version = "0.3.37", features = ["formatting", "macros", "parsing"] }
+"#,
+            "",
+            "",
+        ),
+    };
+
+    // The sets for arrays of unique items.
+    let (schemars_indexmap, indexmap_dep) = if opts.unique_items_as_sets {
+        (
+            r#""indexmap2", "#,
+            r#"indexmap = { version = "2", features = ["serde"] }
+"#,
+        )
+    } else {
+        ("", "")
+    };
+
+    // The wiping of the secrets from memory.
+    let (zeroize_dep, zeroize_feature, zeroize_default) = if opts.secret_fields {
+        (
+            r#"zeroize = { version = "1", optional = true }
+"#,
+            r#"zeroize = ["dep:zeroize"]
+"#,
+            r#", "zeroize""#,
+        )
+    } else {
+        ("", "", "")
+    };
+
+    let types_only_default = zeroize_default.trim_start_matches(", ");
+
+    // The crates with the types we convert to and from.
+    let (interop_deps, interop_feature) = match interop {
+        Some(interop) => (
+            interop.cargo_dependencies()?,
+            format!("{}\n", interop.cargo_feature()),
+        ),
+        None => (String::new(), String::new()),
+    };
+
+    // Quoted and escaped for TOML.
+    let description = toml::Value::String(get_crate_description(spec, opts)).to_string();
+
+    // A crate of only the types needs none of the dependencies of the client.
+    if opts.types_only {
+        return Ok(format!(
+            r#"[package]
+name = "{}"
+description = {}
+version = "{}"
+documentation = "https://docs.rs/{}"
+readme = "README.md"
+{}
+edition = "2021"
+license = "MIT"
+
+[dependencies]
+anyhow = "1"
+base64 = "0.22"
+bigdecimal = {{ version = "0.4", features = ["serde"] }}
+bytes = {{ version = "1", features = ["serde"] }}
+data-encoding = "^2.3.2"
+{indexmap_dep}itertools = "0.13.0"
+parse-display = "0.10.0"
+phonenumber = "0.3.5"
+schemars = {{ version = "0.8.17", features = ["bigdecimal04", "bytes", {schemars_chrono}{schemars_indexmap}"url", "uuid1"] }}
+serde = {{ version = "1", features = ["derive"] }}
+serde_bytes = "0.11"
+serde_json = "1"
+tabled = {{ version = "0.17.0", features = ["ansi"], optional = true }}
+thiserror = "2"
+{time_dep}url = {{ version = "2", features = ["serde"] }}
+uuid = {{ version = "1", features = ["serde", "v4", "v7"] }}
+{zeroize_dep}{interop_deps}
+[target.'cfg(not(target_arch = "wasm32"))'.dependencies]
+{chrono_dep}
+[target.'cfg(target_arch = "wasm32")'.dependencies]
+{chrono_wasm_dep}
+[dev-dependencies]
+expectorate = "1"
+pretty_assertions = "1"
+
+[features]
+default = [{types_only_default}]
+tabled = ["dep:tabled"]
+js = ["uuid/js"]
+registry = []
+lenient = []
+{zeroize_feature}{interop_feature}
+[package.metadata.docs.rs]
+all-features = true
+rustdoc-args = ["--cfg", "docsrs"]
+"#,
+            opts.name, description, opts.target_version, opts.name, repo_info,
+        ));
+    }
+
+    Ok(format!(
         r#"[package]
 name = "{}"
-description = "{}"
+description = {}
 version = "{}"
 documentation = "https://docs.rs/{}"
 readme = "README.md"
@@ -595,10 +1772,11 @@ bytes = {{ version = "1", features = ["serde"] }}
 clap = {{ version = "4.2.4", features = ["cargo", "derive", "env", "unicode"], optional = true }}
 data-encoding = "^2.3.2"
 dirs = {{ version = "^5.0.1", optional = true }}
+flate2 = {{ version = "1", optional = true }}
 format_serde_error = {{ version = "^0.3.0", optional = true }}
 futures = {{ version = "0.3.26", optional = true }}
 http = {{ version = "1", optional = true }}
-itertools = "0.13.0"
+{indexmap_dep}itertools = "0.13.0"
 log = {{ version = "^0.4", features = ["serde"], optional = true }}
 mime_guess = "2.0.4"
 parse-display = "0.10.0"
@@ -609,24 +1787,23 @@ reqwest-conditional-middleware = {{ version = "0.4", optional = true }}
 reqwest-middleware = {{ version = "0.4", optional = true, features = ["json", "multipart", "http2", "rustls-tls"] }}
 reqwest-retry = {{ version = "0.7", optional = true }}
 reqwest-tracing = {{ version = "0.5.4", features = ["opentelemetry_0_24"], optional = true }}
-schemars = {{ version = "0.8.17", features = ["bigdecimal04", "bytes", "chrono", "url", "uuid1"] }}
+schemars = {{ version = "0.8.17", features = ["bigdecimal04", "bytes", {schemars_chrono}{schemars_indexmap}"url", "uuid1"] }}
 serde = {{ version = "1", features = ["derive"] }}
 serde_bytes = "0.11"
 serde_json = "1"
 serde_urlencoded = {{ version = "^0.7", optional = true }}
 tabled = {{ version = "0.17.0", features = ["ansi"], optional = true }}
 thiserror = "2"
-tracing = {{ version = "^0.1", optional = true }}
+{time_dep}tracing = {{ version = "^0.1", optional = true }}
 url = {{ version = "2", features = ["serde"] }}
 uuid = {{ version = "1", features = ["serde", "v4", "v7"] }}
-
+{zeroize_dep}{interop_deps}
 [target.'cfg(not(target_arch = "wasm32"))'.dependencies]
-chrono = {{ version = "0.4", default-features = false, features = ["now", "serde", "std"] }}
-tokio = {{ version = "1.38.0", features = ["rt", "macros"] }}
+{chrono_dep}tokio = {{ version = "1.38.0", features = ["io-util", "rt", "macros", "time"] }}
+tokio-tungstenite = {{ version = "0.24", optional = true }}
 
 [target.'cfg(target_arch = "wasm32")'.dependencies]
-chrono = {{ version = "0.4", default-features = false, features = ["serde", "std"] }}
-
+{chrono_wasm_dep}
 [dev-dependencies]
 expectorate = "1"
 futures-util = "^0.3.26"
@@ -636,19 +1813,25 @@ tokio = {{ version = "1.38.0", features = ["rt", "macros"] }}
 tokio-tungstenite = "0.24"
 
 [features]
-default = ["requests", "retry"]
+default = ["requests", "retry"{zeroize_default}]
 clap = ["dep:clap"]
 tabled = ["dep:tabled"]
 requests = ["dep:async-trait", "dep:format_serde_error", "dep:futures", "dep:http", "dep:log", "dep:rand", "dep:reqwest", "dep:serde_urlencoded", "dep:tracing"]
 retry = ["dep:reqwest-conditional-middleware", "dep:reqwest-retry", "dep:reqwest-middleware", "dep:reqwest-tracing"]
 js = ["uuid/js"]
-
+metrics = ["requests"]
+record = ["requests"]
+compression = ["requests", "dep:flate2"]
+websocket = ["requests", "dep:tokio-tungstenite"]
+registry = []
+lenient = []
+{zeroize_feature}{interop_feature}
 [package.metadata.docs.rs]
 all-features = true
 rustdoc-args = ["--cfg", "docsrs"]
 "#,
-        opts.name, opts.description, opts.target_version, opts.name, repo_info,
-    )
+        opts.name, description, opts.target_version, opts.name, repo_info,
+    ))
 }
 
 fn run_cargo_fmt(opts: &Opts) -> Result<()> {
@@ -719,5 +1902,5 @@ fn run_cargo_clippy(opts: &Opts) -> Result<()> {
 #[test]
 fn verify_cli() {
     use clap::CommandFactory;
-    Opts::command().debug_assert();
+    crate::config::Cli::command().debug_assert();
 }
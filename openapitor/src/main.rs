@@ -1,11 +1,17 @@
 use anyhow::Result;
-use clap::Parser;
+use clap::{CommandFactory, FromArgMatches};
 
 fn main() -> Result<()> {
-    println!("Starting openapitor version {}", env!("CARGO_PKG_VERSION"));
+    // Parse the command line arguments, and the config file they name.
+    let matches = openapitor::config::Cli::command().get_matches();
+    let cli = openapitor::config::Cli::from_arg_matches(&matches)?;
+    if cli.print_config_template {
+        print!("{}", openapitor::config::template()?);
+        return Ok(());
+    }
 
-    // Parse the command line arguments.
-    let opts = openapitor::Opts::parse();
+    println!("Starting openapitor version {}", env!("CARGO_PKG_VERSION"));
+    let opts = cli.into_opts(&matches)?;
 
     // Setup our logger.
     let drain = opts.create_logger();
@@ -14,6 +20,11 @@ fn main() -> Result<()> {
     slog_scope::set_global_logger(logger).cancel_reset();
     slog_stdlog::init()?;
 
+    // Generate the library again whenever the spec changes.
+    if opts.watch {
+        return openapitor::watch::watch(&opts);
+    }
+
     // Let's read the spec from the file.
     let spec = openapitor::load_api(&opts.input)?;
 
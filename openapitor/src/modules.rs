@@ -0,0 +1,380 @@
+//! The module paths of the tags, see `--module-map`.
+//!
+//! The module of a tag is its name in snake case, the operations of `org-members`
+//! are in `org_members`. With `--module-map org-members=org::members` they are in
+//! `org/members.rs` instead, and `org/mod.rs` has an `Org` handing out the
+//! `Members` of the tag, so the client gets to them with `client.org().members()`.
+//! The tags that are not mapped keep their module.
+
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use proc_macro2::TokenStream;
+
+/// The module paths of the mapped tags.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ModuleMap {
+    /// The module of a tag, see `clean_tag_name`, to the segments of its path.
+    paths: BTreeMap<String, Vec<String>>,
+}
+
+/// A module of the tree of the mapped tags.
+#[derive(Debug, Default)]
+pub struct Group<'a> {
+    /// The modules under this one: the tags and the other groups.
+    pub children: BTreeMap<String, Child<'a>>,
+}
+
+/// A module under a group.
+#[derive(Debug)]
+pub enum Child<'a> {
+    /// The module of a tag.
+    Tag(&'a openapiv3::Tag),
+    /// A group of other modules.
+    Group(Group<'a>),
+}
+
+impl ModuleMap {
+    /// Parse the `--module-map`s of the options, like `org-members=org::members`.
+    pub fn new(opts: &crate::Opts) -> Result<Self> {
+        let mut paths: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for mapping in &opts.module_map {
+            let Some((tag, path)) = mapping.split_once('=') else {
+                anyhow::bail!(
+                    "`--module-map` `{}` must be a tag and a module path, like \
+                     `org-members=org::members`",
+                    mapping
+                );
+            };
+            let segments = path
+                .split("::")
+                .map(|segment| segment.trim().to_string())
+                .collect::<Vec<_>>();
+            for segment in &segments {
+                if syn::parse_str::<syn::Ident>(segment).is_err()
+                    || crate::types::casing::to_snake_case(segment) != *segment
+                {
+                    anyhow::bail!(
+                        "`--module-map` `{}` must map to snake case module names, not `{}`",
+                        mapping,
+                        segment
+                    );
+                }
+            }
+            if crate::RESERVED_MODULES.contains(&segments[0].as_str()) {
+                anyhow::bail!(
+                    "`--module-map` `{}` maps to `{}`, the crate keeps it for itself",
+                    mapping,
+                    segments[0]
+                );
+            }
+            if paths
+                .insert(crate::clean_tag_name(tag.trim()), segments)
+                .is_some()
+            {
+                anyhow::bail!(
+                    "`--module-map` maps the tag `{}` more than once",
+                    tag.trim()
+                );
+            }
+        }
+
+        // A module is either a tag or a group of them, never both.
+        for (tag, path) in &paths {
+            for (other, other_path) in &paths {
+                if tag != other && other_path.starts_with(path) {
+                    anyhow::bail!(
+                        "`--module-map` maps `{}` to `{}`, which is the module of `{}`",
+                        other,
+                        other_path.join("::"),
+                        tag
+                    );
+                }
+            }
+        }
+
+        Ok(ModuleMap { paths })
+    }
+
+    /// If the module of a tag is mapped to another path.
+    pub fn is_mapped(&self, module: &str) -> bool {
+        self.paths.contains_key(module)
+    }
+
+    /// The segments of the module path of a tag.
+    pub fn path(&self, module: &str) -> Vec<String> {
+        self.paths
+            .get(module)
+            .cloned()
+            .unwrap_or_else(|| vec![module.to_string()])
+    }
+
+    /// The name of the struct of the operations of a tag, like `Members`.
+    pub fn struct_name(&self, module: &str) -> String {
+        crate::types::proper_name(self.path(module).last().unwrap())
+    }
+
+    /// The calls on the client that get to the operations of a tag, like
+    /// `org().members()`.
+    pub fn accessor(&self, module: &str) -> TokenStream {
+        let calls = self.path(module).into_iter().map(|segment| {
+            let segment = format_ident!("{}", segment);
+            quote!(#segment())
+        });
+        quote!(#(#calls).*)
+    }
+
+    /// The file of the operations of a tag, under `src`.
+    pub fn file(&self, src: &std::path::Path, module: &str) -> std::path::PathBuf {
+        let mut file = src.to_path_buf();
+        file.extend(self.path(module));
+        file.with_extension("rs")
+    }
+
+    /// The tree of the modules of the mapped `tags`, a tag that is not mapped is
+    /// not in it. Fails if a tag that is not mapped has the module of a group.
+    pub fn tree<'a>(&self, tags: &'a [openapiv3::Tag]) -> Result<Group<'a>> {
+        let mut root = Group::default();
+        for tag in tags {
+            let module = crate::clean_tag_name(&tag.name);
+            let Some(path) = self.paths.get(&module) else {
+                continue;
+            };
+            let (last, parents) = path.split_last().unwrap();
+            let mut group = &mut root;
+            for parent in parents {
+                let child = group
+                    .children
+                    .entry(parent.to_string())
+                    .or_insert_with(|| Child::Group(Group::default()));
+                group = match child {
+                    Child::Group(group) => group,
+                    // `new` makes sure a tag is never the parent of another.
+                    Child::Tag(_) => unreachable!(),
+                };
+            }
+            group.children.insert(last.to_string(), Child::Tag(tag));
+        }
+
+        for tag in tags {
+            let module = crate::clean_tag_name(&tag.name);
+            if !self.paths.contains_key(&module) && root.children.contains_key(&module) {
+                anyhow::bail!(
+                    "the tag `{}` has the module `{}`, which `--module-map` uses for other tags, \
+                     map it too",
+                    tag.name,
+                    module
+                );
+            }
+        }
+
+        Ok(root)
+    }
+}
+
+/// Write the `mod.rs` of every group of `tree`, under `dir`.
+pub fn save_groups(dir: &std::path::Path, tree: &Group, opts: &crate::Opts) -> Result<()> {
+    for (name, child) in &tree.children {
+        let Child::Group(group) = child else {
+            continue;
+        };
+        let dir = dir.join(name);
+        std::fs::create_dir_all(&dir)?;
+        crate::save_rust(dir.join("mod.rs"), &render_group(name, group, opts), opts)?;
+        save_groups(&dir, group, opts)?;
+    }
+    Ok(())
+}
+
+/// The docs of the module of a child of a group, and of the function that gets
+/// to it.
+pub fn get_child_docs(name: &str, child: &Child, opts: &crate::Opts) -> (String, String) {
+    match child {
+        Child::Tag(tag) => (
+            crate::get_tag_module_docs(tag, opts),
+            crate::get_tag_accessor_docs(tag),
+        ),
+        Child::Group(_) => (
+            format!("The `{}` operations.", name),
+            format!(
+                "Return a reference to an interface that provides access to {} operations.",
+                name
+            ),
+        ),
+    }
+}
+
+/// Render the `mod.rs` of a group: its modules, and a struct handing them out.
+fn render_group(name: &str, group: &Group, opts: &crate::Opts) -> TokenStream {
+    let struct_ident = format_ident!("{}", crate::types::proper_name(name));
+    let docs = format!("The `{}` operations.", name);
+    let client_docs = opts
+        .strict_lints
+        .then(|| quote!(#[doc = "The client the operations are sent with."]));
+
+    let mut modules = Vec::new();
+    let mut accessors = Vec::new();
+    for (child_name, child) in &group.children {
+        let (module_docs, accessor_docs) = get_child_docs(child_name, child, opts);
+        let module_docs = (!module_docs.is_empty()).then(|| quote!(#[doc = #module_docs]));
+        let child_ident = format_ident!("{}", child_name);
+        let child_struct = format_ident!("{}", crate::types::proper_name(child_name));
+        modules.push(quote! {
+            #module_docs
+            pub mod #child_ident;
+        });
+        accessors.push(quote! {
+            #[doc = #accessor_docs]
+            pub fn #child_ident(&self) -> #child_ident::#child_struct {
+                #child_ident::#child_struct::new(self.client.clone())
+            }
+        });
+    }
+
+    quote! {
+        #(#modules)*
+
+        use crate::Client;
+
+        #[doc = #docs]
+        #[derive(Clone, Debug)]
+        pub struct #struct_ident {
+            #client_docs
+            pub client: Client,
+        }
+
+        impl #struct_ident {
+            #[doc(hidden)]
+            pub fn new(client: Client) -> Self {
+                Self { client }
+            }
+
+            #(#accessors)*
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ModuleMap;
+
+    fn module_map(mappings: &[&str]) -> anyhow::Result<ModuleMap> {
+        ModuleMap::new(&crate::Opts {
+            module_map: mappings.iter().map(|m| m.to_string()).collect(),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_module_map() {
+        let map = module_map(&["org-members=org::members", "Org Billing=org::billing"]).unwrap();
+        assert_eq!(map.path("org_members"), vec!["org", "members"]);
+        assert_eq!(map.path("org_billing"), vec!["org", "billing"]);
+        assert_eq!(map.path("users"), vec!["users"]);
+        assert_eq!(map.struct_name("org_members"), "Members");
+        assert_eq!(
+            map.accessor("org_members").to_string(),
+            quote!(org().members()).to_string()
+        );
+        assert_eq!(
+            map.file(std::path::Path::new("src"), "org_members"),
+            std::path::Path::new("src/org/members.rs")
+        );
+
+        for (mappings, error) in [
+            (&["org-members"][..], "must be a tag and a module path"),
+            (
+                &["org-members=org::Members"],
+                "must map to snake case module names",
+            ),
+            (
+                &["org-members=types::members"],
+                "the crate keeps it for itself",
+            ),
+            (
+                &["org-members=org", "org-billing=org::billing"],
+                "which is the module of",
+            ),
+            (&["org-members=a", "org_members=b"], "more than once"),
+        ] {
+            let err = module_map(mappings).unwrap_err().to_string();
+            assert!(err.contains(error), "{}", err);
+        }
+    }
+
+    #[test]
+    fn test_module_tree() {
+        let map = module_map(&["org-members=org::members"]).unwrap();
+        let tag = |name: &str| openapiv3::Tag {
+            name: name.to_string(),
+            ..Default::default()
+        };
+
+        let tags = vec![tag("org-members"), tag("users")];
+        let tree = map.tree(&tags).unwrap();
+        assert_eq!(tree.children.keys().collect::<Vec<_>>(), vec!["org"]);
+
+        // A tag that is not mapped can't be in the module of a group.
+        let tags = vec![tag("org-members"), tag("org")];
+        assert!(map.tree(&tags).is_err());
+    }
+
+    #[test]
+    fn test_render_module_map() {
+        let spec =
+            crate::load_json_spec(include_str!("../tests/types/input/module-map.json")).unwrap();
+        let opts = crate::Opts {
+            module_map: vec![
+                "org-members=org::members".to_string(),
+                "org-billing=org::billing".to_string(),
+            ],
+            ..Default::default()
+        };
+        let map = ModuleMap::new(&opts).unwrap();
+        let tags = crate::get_tags_with_paths(&spec).unwrap();
+        let tree = map.tree(&tags).unwrap();
+        assert_eq!(tree.children.keys().collect::<Vec<_>>(), vec!["org"]);
+        let super::Child::Group(org) = &tree.children["org"] else {
+            panic!("`org` is not a group");
+        };
+        let org = crate::types::get_text_fmt(&super::render_group("org", org, &Default::default()))
+            .unwrap();
+        expectorate::assert_contents("tests/types/module-map.rs.gen", &org);
+
+        // The examples of the spec get to the operations through the groups.
+        let mut type_space = crate::types::TypeSpace {
+            types: indexmap::map::IndexMap::new(),
+            spec: spec.clone(),
+            rendered: quote!(),
+            opts: opts.clone(),
+        };
+        let (files, spec, _) = crate::functions::generate_files(&mut type_space, &opts).unwrap();
+        assert_eq!(
+            files.keys().collect::<Vec<_>>(),
+            vec!["org_billing", "org_members", "users"]
+        );
+        let example = |path: &str| {
+            spec.paths.paths[path]
+                .as_item()
+                .unwrap()
+                .get
+                .as_ref()
+                .unwrap()
+                .extensions["x-rust"]
+                .to_string()
+        };
+        let members = example("/org/members");
+        assert!(
+            members.contains("client.org().members().list()"),
+            "{}",
+            members
+        );
+        assert!(
+            members.contains("/org/members/struct.Members.html#method.list"),
+            "{}",
+            members
+        );
+        let user = example("/users/{id}");
+        assert!(user.contains("client.users().get("), "{}", user);
+    }
+}
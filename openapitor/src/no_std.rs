@@ -0,0 +1,394 @@
+//! The types that work on `no_std` targets, see `--no-std-core`.
+//!
+//! A type qualifies when its fields are only primitives, strings, `Vec`s,
+//! `Option`s, `Box`es, maps with string keys, and other types that qualify. The
+//! ones with dates, UUIDs, URLs, bytes, JSON values, phone numbers or a serde of
+//! their own are left out. Copies of the types that qualify, with only the serde
+//! derives, go in `types::core`, a module that needs nothing but `alloc` and
+//! `serde`: std users get it with the other types, and `no_std` crates include its
+//! file on its own.
+
+use std::collections::BTreeSet;
+
+use anyhow::Result;
+use proc_macro2::TokenStream;
+
+/// The primitive types, the same on `no_std`.
+const PRIMITIVES: &[&str] = &[
+    "bool", "char", "f32", "f64", "i8", "i16", "i32", "i64", "i128", "isize", "u8", "u16", "u32",
+    "u64", "u128", "usize",
+];
+
+/// The derives we keep, the ones of `core` and serde.
+const DERIVES: &[&str] = &[
+    "Clone",
+    "Copy",
+    "Debug",
+    "Default",
+    "Eq",
+    "Hash",
+    "Ord",
+    "PartialEq",
+    "PartialOrd",
+];
+
+/// Render the `types::core` module, with the types of `rendered` that qualify.
+pub fn render(rendered: &TokenStream) -> Result<TokenStream> {
+    let file = syn::parse2::<syn::File>(rendered.clone())
+        .map_err(|e| anyhow::anyhow!("failed to parse the generated types: {}", e))?;
+    let core = core_types(&file);
+
+    let mut items = Vec::new();
+    for item in &file.items {
+        let item = match item {
+            syn::Item::Struct(s) if core.contains(&s.ident.to_string()) => {
+                let mut s = s.clone();
+                s.attrs = core_attrs(&s.attrs)?;
+                for field in s.fields.iter_mut() {
+                    to_core_field(field, &core)?;
+                }
+                syn::Item::Struct(s)
+            }
+            syn::Item::Enum(e) if core.contains(&e.ident.to_string()) => {
+                let mut e = e.clone();
+                e.attrs = core_attrs(&e.attrs)?;
+                for variant in e.variants.iter_mut() {
+                    variant.attrs.retain(is_kept);
+                    for field in variant.fields.iter_mut() {
+                        to_core_field(field, &core)?;
+                    }
+                }
+                syn::Item::Enum(e)
+            }
+            _ => continue,
+        };
+        items.push(item);
+    }
+
+    Ok(quote! {
+        //! The types that need neither `std` nor any crate but `serde`, for `no_std`
+        //! targets with `alloc`. They are copies of the generated types of the same
+        //! names, with the serde derives alone.
+        //!
+        //! This file compiles on its own: a `no_std` crate can include it with
+        //! `#[path = "…/src/types/core.rs"] mod types;` and depend on `serde` with
+        //! `default-features = false, features = ["alloc", "derive"]`.
+
+        extern crate alloc;
+
+        #(#items)*
+    })
+}
+
+/// The names of the types that qualify for `types::core`.
+pub fn core_types(file: &syn::File) -> BTreeSet<String> {
+    // Start from every struct and enum, and leave out the ones with a field that
+    // does not qualify until none are left out: a type can refer to itself.
+    let candidates = file
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            syn::Item::Struct(s) if s.generics.params.is_empty() && has_serde(&s.attrs) => {
+                let fields = s.fields.iter().collect::<Vec<_>>();
+                let attrs = s.attrs.iter().chain(fields.iter().flat_map(|f| &f.attrs));
+                let is_core = attrs.into_iter().all(is_core_serde);
+                is_core.then(|| (s.ident.to_string(), fields))
+            }
+            syn::Item::Enum(e) if e.generics.params.is_empty() && has_serde(&e.attrs) => {
+                let fields = e
+                    .variants
+                    .iter()
+                    .flat_map(|v| v.fields.iter())
+                    .collect::<Vec<_>>();
+                let attrs = e
+                    .attrs
+                    .iter()
+                    .chain(e.variants.iter().flat_map(|v| &v.attrs))
+                    .chain(fields.iter().flat_map(|f| &f.attrs));
+                let is_core = attrs.into_iter().all(is_core_serde);
+                is_core.then(|| (e.ident.to_string(), fields))
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    let mut core = candidates
+        .iter()
+        .map(|(name, _)| name.to_string())
+        .collect::<BTreeSet<_>>();
+    loop {
+        let before = core.len();
+        for (name, fields) in &candidates {
+            if core.contains(name)
+                && !fields
+                    .iter()
+                    .all(|field| to_core(&field.ty, &core).is_some())
+            {
+                core.remove(name);
+            }
+        }
+        if core.len() == before {
+            return core;
+        }
+    }
+}
+
+/// The type on `no_std` of a field of type `ty`, if it has one.
+fn to_core(ty: &syn::Type, core: &BTreeSet<String>) -> Option<TokenStream> {
+    let syn::Type::Path(path) = ty else {
+        return None;
+    };
+    if path.qself.is_some() {
+        return None;
+    }
+    let segments = path
+        .path
+        .segments
+        .iter()
+        .map(|segment| segment.ident.to_string())
+        .collect::<Vec<_>>();
+    let last = path.path.segments.last()?;
+    let args = match &last.arguments {
+        syn::PathArguments::None => Vec::new(),
+        syn::PathArguments::AngleBracketed(args) => args
+            .args
+            .iter()
+            .map(|arg| match arg {
+                syn::GenericArgument::Type(ty) => Some(ty),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()?,
+        syn::PathArguments::Parenthesized(_) => return None,
+    };
+    let segments = segments.iter().map(|s| s.as_str()).collect::<Vec<_>>();
+
+    match (segments.as_slice(), args.as_slice()) {
+        ([name], []) if PRIMITIVES.contains(name) => {
+            let ident = format_ident!("{}", name);
+            Some(quote!(#ident))
+        }
+        (["String"] | ["std", "string", "String"], []) => Some(quote!(alloc::string::String)),
+        ([name], []) if core.contains(*name) => {
+            let ident = format_ident!("{}", name);
+            Some(quote!(#ident))
+        }
+        (["Option"] | ["std", "option", "Option"], [inner]) => {
+            let inner = to_core(inner, core)?;
+            Some(quote!(Option<#inner>))
+        }
+        (["Vec"] | ["std", "vec", "Vec"], [inner]) => {
+            let inner = to_core(inner, core)?;
+            Some(quote!(alloc::vec::Vec<#inner>))
+        }
+        (["Box"] | ["std", "boxed", "Box"], [inner]) => {
+            let inner = to_core(inner, core)?;
+            Some(quote!(alloc::boxed::Box<#inner>))
+        }
+        (
+            ["HashMap" | "BTreeMap"] | ["std", "collections", "HashMap" | "BTreeMap"],
+            [key, value],
+        ) => {
+            // The keys are strings on the wire, a `BTreeMap` needs nothing else.
+            let key = to_core(key, core)?;
+            if key.to_string() != quote!(alloc::string::String).to_string() {
+                return None;
+            }
+            let value = to_core(value, core)?;
+            Some(quote!(alloc::collections::BTreeMap<#key, #value>))
+        }
+        _ => None,
+    }
+}
+
+/// Change a field to its `no_std` type, and keep its docs and serde alone.
+fn to_core_field(field: &mut syn::Field, core: &BTreeSet<String>) -> Result<()> {
+    let ty = &field.ty;
+    let core_ty = to_core(ty, core)
+        .ok_or_else(|| anyhow::anyhow!("`{}` has no `no_std` type", quote!(#ty)))?;
+    field.ty = syn::parse2(core_ty)?;
+    field.attrs.retain(is_kept);
+    Ok(())
+}
+
+/// The attributes of a type in `types::core`: its docs and serde, and the derives
+/// that need nothing but `core` and serde.
+fn core_attrs(attrs: &[syn::Attribute]) -> Result<Vec<syn::Attribute>> {
+    let mut derives = Vec::new();
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("derive")) {
+        attr.parse_nested_meta(|meta| {
+            if let Some(ident) = meta.path.get_ident() {
+                if DERIVES.contains(&ident.to_string().as_str()) {
+                    derives.push(ident.clone());
+                }
+            }
+            Ok(())
+        })?;
+    }
+
+    let mut core: Vec<syn::Attribute> =
+        attrs.iter().filter(|attr| is_kept(attr)).cloned().collect();
+    let derive: syn::Attribute = syn::parse_quote!(
+        #[derive(serde::Serialize, serde::Deserialize, #(#derives),*)]
+    );
+    // After the docs, like the derives of the generated types.
+    let docs = core
+        .iter()
+        .take_while(|attr| attr.path().is_ident("doc"))
+        .count();
+    core.insert(docs, derive);
+    Ok(core)
+}
+
+/// Whether an attribute stays on the `no_std` copy: the docs and serde.
+fn is_kept(attr: &syn::Attribute) -> bool {
+    attr.path().is_ident("doc") || attr.path().is_ident("serde")
+}
+
+/// Whether a type derives both serde traits, rather than having a serde of its
+/// own we could not copy.
+fn has_serde(attrs: &[syn::Attribute]) -> bool {
+    let mut serialize = false;
+    let mut deserialize = false;
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("derive")) {
+        let _ = attr.parse_nested_meta(|meta| {
+            let path = quote::ToTokens::to_token_stream(&meta.path).to_string();
+            serialize |= path == "serde :: Serialize";
+            deserialize |= path == "serde :: Deserialize";
+            Ok(())
+        });
+    }
+    serialize && deserialize
+}
+
+/// Whether a serde attribute works on `no_std` as it is, without functions of
+/// other modules or crates.
+fn is_core_serde(attr: &syn::Attribute) -> bool {
+    if !attr.path().is_ident("serde") {
+        // The features can add a serde of their own, like `lenient`, we leave them out.
+        return true;
+    }
+    let mut is_core = true;
+    let _ = attr.parse_nested_meta(|meta| {
+        let Some(name) = meta.path.get_ident().map(|ident| ident.to_string()) else {
+            return Ok(());
+        };
+        match name.as_str() {
+            "with" | "serialize_with" | "deserialize_with" | "from" | "try_from" | "into"
+            | "remote" | "bound" => is_core = false,
+            // A function for the default, not `Default::default`.
+            "default" if meta.input.peek(syn::Token![=]) => is_core = false,
+            _ => {}
+        }
+        // Skip the value.
+        if meta.input.peek(syn::Token![=]) {
+            meta.value()?.parse::<syn::Expr>()?;
+        } else if meta.input.peek(syn::token::Paren) {
+            let _content;
+            syn::parenthesized!(_content in meta.input);
+        }
+        Ok(())
+    });
+    is_core
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::{core_types, render, to_core};
+
+    #[test]
+    fn test_to_core() {
+        let core: BTreeSet<String> = ["Point".to_string()].into();
+        let to_core = |ty: &str| {
+            to_core(&syn::parse_str(ty).unwrap(), &core)
+                .map(|ty| crate::types::get_text(&ty).unwrap())
+        };
+
+        assert_eq!(to_core("f64").as_deref(), Some("f64"));
+        assert_eq!(to_core("String").as_deref(), Some("alloc::string::String"));
+        assert_eq!(
+            to_core("Option<Vec<Point>>").as_deref(),
+            Some("Option<alloc::vec::Vec<Point>>")
+        );
+        assert_eq!(
+            to_core("std::collections::HashMap<String, Box<Point>>").as_deref(),
+            Some("alloc::collections::BTreeMap<alloc::string::String,alloc::boxed::Box<Point>>")
+        );
+
+        // The rest needs `std` or other crates.
+        for ty in [
+            "uuid::Uuid",
+            "chrono::DateTime<chrono::Utc>",
+            "url::Url",
+            "serde_json::Value",
+            "base64::Base64Data",
+            "Other",
+            "std::collections::HashMap<Point, String>",
+            "Vec<bytes::Bytes>",
+        ] {
+            assert_eq!(to_core(ty), None, "{}", ty);
+        }
+    }
+
+    #[test]
+    fn test_core_types() {
+        let file: syn::File = syn::parse_quote! {
+            #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug, Clone, schemars::JsonSchema)]
+            pub struct Point {
+                pub x: f64,
+                #[cfg_attr(feature = "lenient", serde(deserialize_with = "crate::types::lenient::deserialize"))]
+                pub y: f64,
+            }
+
+            #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug, Clone)]
+            pub struct Path {
+                pub points: Vec<Point>,
+                #[serde(default, skip_serializing_if = "Option::is_none")]
+                pub next: Option<Box<Path>>,
+            }
+
+            #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug, Clone)]
+            pub struct Stamped {
+                pub point: Point,
+                pub at: chrono::DateTime<chrono::Utc>,
+            }
+
+            #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug, Clone)]
+            pub struct UsesStamped {
+                pub stamped: Stamped,
+            }
+
+            #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug, Clone)]
+            pub struct Bytes {
+                #[serde(serialize_with = "serde_bytes::serialize", deserialize_with = "serde_bytes::deserialize")]
+                pub data: Vec<u8>,
+            }
+
+            #[derive(serde::Serialize, PartialEq, Debug, Clone)]
+            pub enum OwnDeserialize {
+                A,
+            }
+        };
+
+        assert_eq!(
+            core_types(&file).into_iter().collect::<Vec<_>>(),
+            ["Path", "Point"]
+        );
+
+        let rendered = crate::types::get_text_fmt(&render(&quote!(#file)).unwrap()).unwrap();
+        assert!(
+            rendered.contains(
+                "#[derive(serde :: Serialize, serde :: Deserialize, PartialEq, Debug, Clone)]\npub struct Point {"
+            ),
+            "{}",
+            rendered
+        );
+        assert!(!rendered.contains("lenient"), "{}", rendered);
+        assert!(
+            rendered.contains("pub next: Option<alloc::boxed::Box<Path>>,"),
+            "{}",
+            rendered
+        );
+    }
+}
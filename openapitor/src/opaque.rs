@@ -0,0 +1,380 @@
+//! The schemas left as `serde_json::Value`, rather than types of their own.
+//!
+//! Some schemas are big, change with every version of the spec, and are only ever
+//! passed through as they are, like the debug dumps of an engine. With
+//! `--opaque-type DebugDump`, or a pattern like `--opaque-type 'Engine*'`, the
+//! schema gets no type: every reference to it, in a field, a list, a `oneOf` or a
+//! response, is a `serde_json::Value`. The schemas only it references get no type
+//! either, the ones the rest of the spec references still do.
+//!
+//! What was left out, and how many types it would have been, is in `opaque.json`
+//! in the output directory.
+
+use std::collections::BTreeSet;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::types::exts::ReferenceOrExt;
+
+/// The version of the report format, bump this if the format changes in a way
+/// consumers would notice.
+pub const OPAQUE_VERSION: u32 = 1;
+
+/// The extension of the inline schemas standing for a reference to an opaque
+/// schema, with its name.
+pub const EXTENSION: &str = "x-rust-opaque";
+
+const SCHEMAS: &str = "#/components/schemas/";
+
+/// The schemas we did not generate types for.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Report {
+    /// The version of the report format.
+    pub opaque_version: u32,
+    /// The schemas matching an `--opaque-type`.
+    pub opaque: Vec<String>,
+    /// The schemas only the opaque ones reference.
+    pub unreachable: Vec<String>,
+    /// The types of all those schemas and of the schemas inline in them.
+    pub types_avoided: usize,
+}
+
+/// Returns `true` if an inline schema stands for a reference to an opaque schema.
+pub fn is_opaque(data: &openapiv3::SchemaData) -> bool {
+    data.extensions.contains_key(EXTENSION)
+}
+
+/// The name of the opaque schema an inline schema stands for, if it does.
+pub fn get_name(data: &openapiv3::SchemaData) -> Option<&str> {
+    data.extensions
+        .get(EXTENSION)
+        .and_then(|name| name.as_str())
+}
+
+/// Returns `true` if the name of a schema matches a pattern of `--opaque-type`,
+/// where `*` matches any characters and `?` one.
+pub fn matches(pattern: &str, name: &str) -> Result<bool> {
+    let pattern = regex::escape(pattern)
+        .replace("\\*", ".*")
+        .replace("\\?", ".");
+    Ok(regex::Regex::new(&format!("^{}$", pattern))?.is_match(name))
+}
+
+/// The spec without the opaque schemas and the ones only they reference, with an
+/// inline schema of the extension in place of every reference to them.
+pub fn prepare(
+    spec: &openapiv3::OpenAPI,
+    opts: &crate::Opts,
+) -> Result<(openapiv3::OpenAPI, Report)> {
+    let mut report = Report {
+        opaque_version: OPAQUE_VERSION,
+        ..Default::default()
+    };
+    let Some(components) = &spec.components else {
+        return Ok((spec.clone(), report));
+    };
+
+    for pattern in &opts.opaque_types {
+        let mut found = false;
+        for name in components.schemas.keys() {
+            if matches(pattern, name)? {
+                found = true;
+                if !report.opaque.contains(name) {
+                    report.opaque.push(name.to_string());
+                }
+            }
+        }
+        if !found {
+            log::warn!("`--opaque-type {}` matches no schema", pattern);
+        }
+    }
+    if report.opaque.is_empty() {
+        return Ok((spec.clone(), report));
+    }
+
+    // What each schema references, and what the rest of the spec does.
+    let mut serialized = serde_json::to_value(spec)?;
+    let schemas = serialized["components"]["schemas"]
+        .as_object_mut()
+        .map(std::mem::take)
+        .unwrap_or_default();
+    let references = |name: &str| {
+        let mut references = BTreeSet::new();
+        if let Some(schema) = schemas.get(name) {
+            get_references(schema, &mut references);
+        }
+        references
+    };
+    let behind_opaque = reach(
+        report.opaque.iter().flat_map(|name| references(name)),
+        &report.opaque,
+        references,
+    );
+    let mut roots = BTreeSet::new();
+    get_references(&serialized, &mut roots);
+    roots.extend(
+        schemas
+            .keys()
+            .filter(|name| !behind_opaque.contains(*name) && !report.opaque.contains(*name))
+            .cloned(),
+    );
+    let kept = reach(roots, &report.opaque, references);
+    report.unreachable = schemas
+        .keys()
+        .filter(|name| behind_opaque.contains(*name) && !kept.contains(*name))
+        .filter(|name| !report.opaque.contains(*name))
+        .cloned()
+        .collect();
+    report.types_avoided = count_types(
+        spec,
+        opts,
+        report.opaque.iter().chain(report.unreachable.iter()),
+    )?;
+
+    // Put the schemas back without the ones we leave out, and the references to
+    // the opaque ones inline.
+    serialized["components"]["schemas"] = serde_json::Value::Object(
+        schemas
+            .into_iter()
+            .filter(|(name, _)| !report.opaque.contains(name) && !report.unreachable.contains(name))
+            .collect(),
+    );
+    replace_references(&mut serialized, spec, &report.opaque)?;
+
+    Ok((serde_json::from_value(serialized)?, report))
+}
+
+/// The schemas reachable from `roots` through references, without going through
+/// the opaque ones.
+fn reach(
+    roots: impl IntoIterator<Item = String>,
+    opaque: &[String],
+    references: impl Fn(&str) -> BTreeSet<String>,
+) -> BTreeSet<String> {
+    let mut reached = BTreeSet::new();
+    let mut todo = roots.into_iter().collect::<Vec<_>>();
+    while let Some(name) = todo.pop() {
+        if !reached.insert(name.to_string()) || opaque.contains(&name) {
+            continue;
+        }
+        todo.extend(references(&name));
+    }
+    reached
+}
+
+/// The names of the schemas `value` references.
+fn get_references(value: &serde_json::Value, references: &mut BTreeSet<String>) {
+    match value {
+        serde_json::Value::Object(object) => {
+            for (key, value) in object {
+                match (key.as_str(), value) {
+                    ("$ref", serde_json::Value::String(reference)) => {
+                        if let Some(name) = reference.strip_prefix(SCHEMAS) {
+                            references.insert(name.to_string());
+                        }
+                    }
+                    _ => get_references(value, references),
+                }
+            }
+        }
+        serde_json::Value::Array(values) => {
+            for value in values {
+                get_references(value, references);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Replace the references to the opaque schemas with an inline schema of the
+/// extension, with the description of the schema.
+fn replace_references(
+    value: &mut serde_json::Value,
+    spec: &openapiv3::OpenAPI,
+    opaque: &[String],
+) -> Result<()> {
+    match value {
+        serde_json::Value::Object(object) => {
+            let name = match object.get("$ref") {
+                Some(serde_json::Value::String(reference)) => reference
+                    .strip_prefix(SCHEMAS)
+                    .filter(|name| opaque.iter().any(|opaque| opaque == name))
+                    .map(|name| name.to_string()),
+                _ => None,
+            };
+            match name {
+                Some(name) => {
+                    object.remove("$ref");
+                    let schema = openapiv3::ReferenceOr::<openapiv3::Schema>::Reference {
+                        reference: format!("{}{}", SCHEMAS, name),
+                    }
+                    .expand(spec)?;
+                    if let Some(description) = schema.schema_data.description {
+                        object.insert("description".to_string(), description.into());
+                    }
+                    object.insert(EXTENSION.to_string(), name.into());
+                }
+                None => {
+                    for value in object.values_mut() {
+                        replace_references(value, spec, opaque)?;
+                    }
+                }
+            }
+        }
+        serde_json::Value::Array(values) => {
+            for value in values {
+                replace_references(value, spec, opaque)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// How many types the schemas would have been, with the ones inline in them.
+fn count_types<'a>(
+    spec: &openapiv3::OpenAPI,
+    opts: &crate::Opts,
+    names: impl Iterator<Item = &'a String>,
+) -> Result<usize> {
+    let mut type_space = crate::types::TypeSpace {
+        types: indexmap::map::IndexMap::new(),
+        spec: spec.clone(),
+        rendered: quote::quote!(),
+        opts: opts.clone(),
+    };
+    for name in names {
+        let schema = openapiv3::ReferenceOr::<openapiv3::Schema>::Reference {
+            reference: format!("{}{}", SCHEMAS, name),
+        }
+        .expand(spec)?;
+        // A schema we could not generate is no type to count.
+        if let Err(err) = type_space.render_schema(name, &schema) {
+            log::debug!("counting the types of opaque `{}`: {}", name, err);
+        }
+    }
+    let file = syn::parse2::<syn::File>(type_space.rendered)?;
+    Ok(file
+        .items
+        .iter()
+        .filter(|item| {
+            matches!(
+                item,
+                syn::Item::Struct(_) | syn::Item::Enum(_) | syn::Item::Type(_)
+            )
+        })
+        .count())
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::{Report, OPAQUE_VERSION};
+
+    #[test]
+    fn test_matches() {
+        assert!(super::matches("EngineDump", "EngineDump").unwrap());
+        assert!(!super::matches("EngineDump", "EngineDumps").unwrap());
+        assert!(super::matches("Engine*", "EngineDump").unwrap());
+        assert!(super::matches("*Dump", "EngineDump").unwrap());
+        assert!(super::matches("Engine?ump", "EngineDump").unwrap());
+        assert!(!super::matches("Engine.ump", "EngineDump").unwrap());
+    }
+
+    #[test]
+    fn test_opaque_types() {
+        let spec = crate::load_json_spec(include_str!("../tests/types/input/opaque.json")).unwrap();
+        let opts = crate::Opts {
+            opaque_types: vec!["Engine*".to_string()],
+            ..Default::default()
+        };
+        let (spec, report) = super::prepare(&spec, &opts).unwrap();
+        assert_eq!(
+            report,
+            Report {
+                opaque_version: OPAQUE_VERSION,
+                opaque: vec!["EngineDump".to_string()],
+                unreachable: vec!["SceneGraph".to_string(), "SceneNode".to_string()],
+                types_avoided: 5,
+            }
+        );
+
+        let mut type_space = crate::types::TypeSpace {
+            types: indexmap::map::IndexMap::new(),
+            spec: spec.clone(),
+            rendered: quote::quote!(),
+            opts,
+        };
+        for (name, schema) in &spec.components.as_ref().unwrap().schemas {
+            type_space
+                .render_schema(name, schema.as_item().unwrap())
+                .unwrap();
+        }
+        let rendered = crate::types::get_text_fmt(&type_space.rendered).unwrap();
+        expectorate::assert_contents("tests/types/opaque.rs.gen", &rendered);
+
+        // The opaque schema, what it alone references and what is inline in it
+        // have no types, what the rest of the spec references still does.
+        for absent in [
+            "struct EngineDump",
+            "SceneGraph",
+            "SceneNode",
+            "Stats",
+            "Mode",
+            "Gpu",
+        ] {
+            assert!(!rendered.contains(absent), "`{}` was generated", absent);
+        }
+        assert!(rendered.contains("pub enum Unit"));
+        assert!(rendered.contains("pub dump: Option<serde_json::Value>"));
+        assert!(rendered.contains("pub history: Vec<serde_json::Value>"));
+        assert!(rendered.contains("EngineDump(serde_json::Value)"));
+
+        // The responses are values too.
+        let op = spec.paths.paths["/sessions/{id}/dump"]
+            .as_item()
+            .unwrap()
+            .get
+            .as_ref()
+            .unwrap();
+        let schema = op.responses.responses[&openapiv3::StatusCode::Code(200)]
+            .as_item()
+            .unwrap()
+            .content["application/json"]
+            .schema
+            .as_ref()
+            .unwrap()
+            .as_item()
+            .unwrap();
+        assert_eq!(
+            crate::types::get_type_name_for_schema("", schema, &spec, false)
+                .unwrap()
+                .to_string(),
+            "serde_json :: Value"
+        );
+    }
+
+    #[test]
+    fn test_no_opaque_types() {
+        let spec = crate::load_json_spec(include_str!("../tests/types/input/opaque.json")).unwrap();
+
+        // Without a pattern matching a schema nothing changes.
+        for opaque_types in [vec![], vec!["Engine".to_string()]] {
+            let opts = crate::Opts {
+                opaque_types,
+                ..Default::default()
+            };
+            let (prepared, report) = super::prepare(&spec, &opts).unwrap();
+            assert_eq!(prepared, spec);
+            assert_eq!(
+                report,
+                Report {
+                    opaque_version: OPAQUE_VERSION,
+                    ..Default::default()
+                }
+            );
+        }
+    }
+}
@@ -0,0 +1,589 @@
+//! Read OpenAPI 3.1 documents by converting them to OpenAPI 3.0.
+//!
+//! The `openapiv3` crate only understands 3.0, so before parsing a 3.1 document we
+//! rewrite the JSON Schema constructs that have a 3.0 equivalent:
+//! - the `null` in `type` arrays, and the `oneOf` and `anyOf` members that only allow
+//!   `null`, become `nullable: true`,
+//! - `examples` arrays become `example`, and `const` a single value `enum`,
+//! - the numeric `exclusiveMinimum` and `exclusiveMaximum` become the 3.0 booleans,
+//! - `contentEncoding: base64` becomes `format: byte`, and `contentMediaType`
+//!   `format: binary`,
+//! - a `$ref` with siblings becomes an `allOf` of the reference,
+//! - `webhooks` move to the `x-webhooks` extension, we don't generate them.
+//!
+//! The constructs 3.0 has no equivalent for are errors, pointing at where they are.
+
+use anyhow::Result;
+use serde_json::{json, Map, Value};
+
+/// The JSON Schema dialect of OpenAPI 3.1, the only one we support.
+const OAS_DIALECT: &str = "https://spec.openapis.org/oas/3.1/dialect/base";
+
+/// The JSON Schema keywords 3.0 has no equivalent for.
+const UNSUPPORTED_KEYWORDS: &[&str] = &[
+    "$defs",
+    "$dynamicAnchor",
+    "$dynamicRef",
+    "contains",
+    "dependentRequired",
+    "dependentSchemas",
+    "else",
+    "if",
+    "patternProperties",
+    "prefixItems",
+    "propertyNames",
+    "then",
+    "unevaluatedItems",
+    "unevaluatedProperties",
+];
+
+/// The JSON Schema keywords we drop, they change nothing for the generated code.
+const IGNORED_KEYWORDS: &[&str] = &["$anchor", "$comment", "$id", "$schema"];
+
+/// The methods of the operations of a path item.
+const METHODS: &[&str] = &[
+    "get", "put", "post", "delete", "options", "head", "patch", "trace",
+];
+
+/// Tell if the `openapi` version of a document is 3.1.
+pub fn is_openapi_31(version: Option<&str>) -> bool {
+    version.is_some_and(|version| version.starts_with("3.1"))
+}
+
+/// Convert an OpenAPI 3.1 document to OpenAPI 3.0, in place.
+pub fn down_convert(spec: &mut Value) -> Result<()> {
+    let doc = spec
+        .as_object_mut()
+        .ok_or_else(|| anyhow::anyhow!("the spec is not an object"))?;
+    doc.insert("openapi".to_string(), json!("3.0.3"));
+
+    if let Some(dialect) = doc.remove("jsonSchemaDialect") {
+        if dialect != OAS_DIALECT {
+            anyhow::bail!(
+                "the spec uses the JSON Schema dialect {}, only `{}` is supported",
+                dialect,
+                OAS_DIALECT
+            );
+        }
+    }
+    if let Some(webhooks) = doc.remove("webhooks") {
+        doc.insert("x-webhooks".to_string(), webhooks);
+    }
+
+    // The paths are optional in 3.1.
+    let paths = doc.entry("paths").or_insert_with(|| json!({}));
+    for (path, item) in objects(Some(paths)) {
+        convert_path_item(&pointer("#/paths", path), item)?;
+    }
+
+    let Some(components) = doc.get_mut("components").and_then(Value::as_object_mut) else {
+        return Ok(());
+    };
+    if components.contains_key("pathItems") {
+        anyhow::bail!(
+            "`#/components/pathItems` is not supported by OpenAPI 3.0, inline the path items"
+        );
+    }
+    for (name, schema) in objects(components.get_mut("schemas")) {
+        convert_schema(&pointer("#/components/schemas", name), schema)?;
+    }
+    for (name, parameter) in objects(components.get_mut("parameters")) {
+        convert_parameter(&pointer("#/components/parameters", name), parameter)?;
+    }
+    for (name, body) in objects(components.get_mut("requestBodies")) {
+        convert_content(&pointer("#/components/requestBodies", name), body)?;
+    }
+    for (name, response) in objects(components.get_mut("responses")) {
+        convert_response(&pointer("#/components/responses", name), response)?;
+    }
+    for (name, header) in objects(components.get_mut("headers")) {
+        convert_parameter(&pointer("#/components/headers", name), header)?;
+    }
+
+    Ok(())
+}
+
+/// The entries of a JSON object, none if it is not one.
+fn objects(value: Option<&mut Value>) -> impl Iterator<Item = (&String, &mut Value)> {
+    value
+        .and_then(Value::as_object_mut)
+        .into_iter()
+        .flat_map(|map| map.iter_mut())
+}
+
+/// The JSON pointer to `key` in the object at `parent`.
+fn pointer(parent: &str, key: &str) -> String {
+    format!("{}/{}", parent, key.replace('~', "~0").replace('/', "~1"))
+}
+
+fn convert_path_item(at: &str, item: &mut Value) -> Result<()> {
+    for (i, parameter) in arrays(item.get_mut("parameters")) {
+        convert_parameter(&format!("{}/parameters/{}", at, i), parameter)?;
+    }
+
+    for method in METHODS {
+        let Some(operation) = item.get_mut(*method).and_then(Value::as_object_mut) else {
+            continue;
+        };
+        let at = format!("{}/{}", at, method);
+        for (i, parameter) in arrays(operation.get_mut("parameters")) {
+            convert_parameter(&format!("{}/parameters/{}", at, i), parameter)?;
+        }
+        if let Some(body) = operation.get_mut("requestBody") {
+            convert_content(&format!("{}/requestBody", at), body)?;
+        }
+        // The responses are optional in 3.1.
+        let responses = operation.entry("responses").or_insert_with(|| json!({}));
+        for (status, response) in objects(Some(responses)) {
+            convert_response(&pointer(&format!("{}/responses", at), status), response)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// The items of a JSON array with their index, none if it is not one.
+fn arrays(value: Option<&mut Value>) -> impl Iterator<Item = (usize, &mut Value)> {
+    value
+        .and_then(Value::as_array_mut)
+        .into_iter()
+        .flat_map(|items| items.iter_mut().enumerate())
+}
+
+/// Convert a parameter or a header, they both have a schema or a content.
+fn convert_parameter(at: &str, parameter: &mut Value) -> Result<()> {
+    if let Some(schema) = parameter.get_mut("schema") {
+        convert_schema(&format!("{}/schema", at), schema)?;
+    }
+    convert_content(at, parameter)
+}
+
+fn convert_response(at: &str, response: &mut Value) -> Result<()> {
+    for (name, header) in objects(response.get_mut("headers")) {
+        convert_parameter(&pointer(&format!("{}/headers", at), name), header)?;
+    }
+    convert_content(at, response)
+}
+
+/// Convert the schemas of the `content` of a parameter, request body or response.
+fn convert_content(at: &str, value: &mut Value) -> Result<()> {
+    for (media_type, content) in objects(value.get_mut("content")) {
+        if let Some(schema) = content.get_mut("schema") {
+            let at = pointer(&format!("{}/content", at), media_type);
+            convert_schema(&format!("{}/schema", at), schema)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn convert_schema(at: &str, schema: &mut Value) -> Result<()> {
+    // JSON Schema allows `true` for any value, `false` for none.
+    match schema {
+        Value::Bool(true) => *schema = json!({}),
+        Value::Bool(false) => {
+            anyhow::bail!(
+                "`{}` is the `false` schema, which OpenAPI 3.0 has no equivalent for",
+                at
+            )
+        }
+        _ => {}
+    }
+    let Some(s) = schema.as_object_mut() else {
+        return Ok(());
+    };
+
+    if let Some(keyword) = UNSUPPORTED_KEYWORDS.iter().find(|k| s.contains_key(**k)) {
+        anyhow::bail!(
+            "`{}` uses `{}`, which OpenAPI 3.0 has no equivalent for",
+            at,
+            keyword
+        );
+    }
+    for keyword in IGNORED_KEYWORDS {
+        s.remove(*keyword);
+    }
+
+    // The siblings of a reference are ignored in 3.0, unless it is in an all of.
+    if s.len() > 1 {
+        if let Some(reference) = s.remove("$ref") {
+            all_of(s).insert(0, json!({ "$ref": reference }));
+        }
+    }
+
+    for (name, property) in objects(s.get_mut("properties")) {
+        convert_schema(&pointer(&format!("{}/properties", at), name), property)?;
+    }
+    for keyword in ["items", "additionalProperties", "not"] {
+        // A boolean `additionalProperties` is fine in 3.0.
+        if let Some(inner) = s
+            .get_mut(keyword)
+            .filter(|inner| keyword != "additionalProperties" || inner.is_object())
+        {
+            convert_schema(&format!("{}/{}", at, keyword), inner)?;
+        }
+    }
+    for keyword in ["allOf", "anyOf", "oneOf"] {
+        // The members that only allow `null` are removed below.
+        for (i, member) in arrays(s.get_mut(keyword)) {
+            if keyword == "allOf" || !is_null(member) {
+                convert_schema(&format!("{}/{}/{}", at, keyword, i), member)?;
+            }
+        }
+    }
+
+    let mut nullable = false;
+    for keyword in ["anyOf", "oneOf"] {
+        let Some(members) = s.get_mut(keyword).and_then(Value::as_array_mut) else {
+            continue;
+        };
+        let count = members.len();
+        members.retain(|member| !is_null(member));
+        if members.len() == count {
+            continue;
+        }
+
+        nullable = true;
+        match members.pop() {
+            None => anyhow::bail!(
+                "`{}` can only be null, which OpenAPI 3.0 has no type for",
+                at
+            ),
+            // Put the last member in place of the one of, it's the same.
+            Some(member) if members.is_empty() => {
+                s.remove(keyword);
+                match member {
+                    Value::Object(member) if !member.contains_key("$ref") => {
+                        for (key, value) in member {
+                            s.entry(key).or_insert(value);
+                        }
+                    }
+                    member => all_of(s).push(member),
+                }
+            }
+            Some(member) => members.push(member),
+        }
+    }
+
+    match s.get("type") {
+        Some(Value::Array(types)) => {
+            let mut types = types.clone();
+            let count = types.len();
+            types.retain(|t| t != "null");
+            nullable |= types.len() < count;
+            match types.len() {
+                0 => anyhow::bail!("`{}` can only be null, which OpenAPI 3.0 has no type for", at),
+                1 => {
+                    s.insert("type".to_string(), types.remove(0));
+                }
+                _ => anyhow::bail!(
+                    "`{}` has the types {}, OpenAPI 3.0 schemas have a single type, use a `oneOf` instead",
+                    at,
+                    Value::Array(types)
+                ),
+            }
+        }
+        Some(t) if t == "null" => {
+            anyhow::bail!(
+                "`{}` can only be null, which OpenAPI 3.0 has no type for",
+                at
+            )
+        }
+        _ => {}
+    }
+    if nullable {
+        s.insert("nullable".to_string(), json!(true));
+    }
+
+    if let Some(value) = s.remove("const") {
+        match s.get("enum") {
+            Some(values) if *values != json!([value]) => {
+                anyhow::bail!("`{}` has a `const` that is not its only `enum` value", at)
+            }
+            _ => {
+                s.insert("enum".to_string(), json!([value]));
+            }
+        }
+    }
+
+    if let Some(Value::Array(examples)) = s.get("examples") {
+        let example = examples.first().cloned();
+        s.remove("examples");
+        if let Some(example) = example {
+            s.entry("example").or_insert(example);
+        }
+    }
+
+    convert_exclusive_bound(s, "exclusiveMinimum", "minimum", |bound, exclusive| {
+        bound > exclusive
+    });
+    convert_exclusive_bound(s, "exclusiveMaximum", "maximum", |bound, exclusive| {
+        bound < exclusive
+    });
+
+    if let Some(encoding) = s.remove("contentEncoding") {
+        if encoding != "base64" {
+            anyhow::bail!(
+                "`{}` uses the content encoding {}, only `base64` is supported",
+                at,
+                encoding
+            );
+        }
+        s.entry("format").or_insert_with(|| json!("byte"));
+    }
+    if s.remove("contentMediaType").is_some() {
+        s.entry("format").or_insert_with(|| json!("binary"));
+    }
+
+    Ok(())
+}
+
+/// Tell if a schema only allows `null`.
+fn is_null(schema: &Value) -> bool {
+    schema.get("type") == Some(&json!("null"))
+}
+
+/// The `allOf` of a schema, added if needed.
+fn all_of(s: &mut Map<String, Value>) -> &mut Vec<Value> {
+    let all_of = s.entry("allOf").or_insert_with(|| json!([]));
+    if !all_of.is_array() {
+        *all_of = json!([]);
+    }
+    all_of.as_array_mut().unwrap()
+}
+
+/// Turn a numeric exclusive bound into the bound and the 3.0 boolean, unless the
+/// inclusive bound is already stricter.
+fn convert_exclusive_bound(
+    s: &mut Map<String, Value>,
+    exclusive: &str,
+    inclusive: &str,
+    is_stricter: fn(f64, f64) -> bool,
+) {
+    let Some(value) = s.get(exclusive).filter(|v| v.is_number()).cloned() else {
+        return;
+    };
+
+    let bound = s.get(inclusive).and_then(Value::as_f64);
+    match (bound, value.as_f64()) {
+        (Some(bound), Some(value)) if is_stricter(bound, value) => {
+            s.remove(exclusive);
+        }
+        _ => {
+            s.insert(inclusive.to_string(), value);
+            s.insert(exclusive.to_string(), json!(true));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::down_convert;
+
+    /// Convert a document with `schema` as the schema `Thing`.
+    fn convert_schema(schema: serde_json::Value) -> anyhow::Result<serde_json::Value> {
+        let mut spec = json!({
+            "openapi": "3.1.0",
+            "info": {"title": "Things", "version": "1.0.0"},
+            "components": {"schemas": {"Thing": schema}}
+        });
+        down_convert(&mut spec)?;
+        Ok(spec["components"]["schemas"]["Thing"].take())
+    }
+
+    #[test]
+    fn test_convert_schemas() {
+        assert_eq!(
+            convert_schema(json!({
+                "type": "object",
+                "properties": {
+                    "name": {"type": ["string", "null"], "examples": ["Rex", "Max"]},
+                    "kind": {"const": "dog", "$comment": "always a dog"},
+                    "age": {"type": "integer", "exclusiveMinimum": 0, "maximum": 30, "exclusiveMaximum": 40},
+                    "photo": {"type": "string", "contentEncoding": "base64"},
+                    "file": {"type": "string", "contentMediaType": "application/pdf"},
+                    "owner": {"$ref": "#/components/schemas/Owner", "description": "The owner."},
+                    "vet": {"oneOf": [{"$ref": "#/components/schemas/Vet"}, {"type": "null"}]},
+                    "nickname": {"anyOf": [{"type": "string"}, {"type": "null"}], "description": "A nickname."},
+                    "tags": {"type": "array", "items": {"type": ["integer", "null"]}},
+                    "extra": true
+                }
+            }))
+            .unwrap(),
+            json!({
+                "type": "object",
+                "properties": {
+                    "name": {"type": "string", "nullable": true, "example": "Rex"},
+                    "kind": {"enum": ["dog"]},
+                    "age": {"type": "integer", "minimum": 0, "exclusiveMinimum": true, "maximum": 30},
+                    "photo": {"type": "string", "format": "byte"},
+                    "file": {"type": "string", "format": "binary"},
+                    "owner": {"allOf": [{"$ref": "#/components/schemas/Owner"}], "description": "The owner."},
+                    "vet": {"allOf": [{"$ref": "#/components/schemas/Vet"}], "nullable": true},
+                    "nickname": {"type": "string", "description": "A nickname.", "nullable": true},
+                    "tags": {"type": "array", "items": {"type": "integer", "nullable": true}},
+                    "extra": {}
+                }
+            })
+        );
+
+        // A plain reference is left alone, and so are the members of real one ofs.
+        assert_eq!(
+            convert_schema(json!({"$ref": "#/components/schemas/Owner"})).unwrap(),
+            json!({"$ref": "#/components/schemas/Owner"})
+        );
+        assert_eq!(
+            convert_schema(
+                json!({"oneOf": [{"type": "string"}, {"type": "integer"}, {"type": "null"}]})
+            )
+            .unwrap(),
+            json!({"oneOf": [{"type": "string"}, {"type": "integer"}], "nullable": true})
+        );
+    }
+
+    #[test]
+    fn test_convert_document() {
+        let mut spec = json!({
+            "openapi": "3.1.0",
+            "jsonSchemaDialect": "https://spec.openapis.org/oas/3.1/dialect/base",
+            "info": {"title": "Things", "version": "1.0.0"},
+            "webhooks": {"newThing": {"post": {"responses": {"200": {"description": "ok"}}}}},
+            "paths": {
+                "/things/{id}": {
+                    "parameters": [{"name": "id", "in": "path", "required": true, "schema": {"type": ["string"]}}],
+                    "get": {
+                        "responses": {
+                            "200": {
+                                "description": "ok",
+                                "headers": {"ETag": {"schema": {"type": ["string", "null"]}}},
+                                "content": {"application/json": {"schema": {"type": ["integer", "null"]}}}
+                            }
+                        }
+                    },
+                    "delete": {}
+                }
+            }
+        });
+        down_convert(&mut spec).unwrap();
+
+        assert_eq!(spec["openapi"], "3.0.3");
+        assert_eq!(spec.get("jsonSchemaDialect"), None);
+        assert_eq!(spec.get("webhooks"), None);
+        assert!(spec["x-webhooks"]["newThing"].is_object());
+        let item = &spec["paths"]["/things/{id}"];
+        assert_eq!(item["parameters"][0]["schema"], json!({"type": "string"}));
+        let response = &item["get"]["responses"]["200"];
+        assert_eq!(
+            response["headers"]["ETag"]["schema"],
+            json!({"type": "string", "nullable": true})
+        );
+        assert_eq!(
+            response["content"]["application/json"]["schema"],
+            json!({"type": "integer", "nullable": true})
+        );
+        assert_eq!(item["delete"]["responses"], json!({}));
+        serde_json::from_value::<openapiv3::OpenAPI>(spec).unwrap();
+
+        // The paths are optional.
+        let mut spec = json!({"openapi": "3.1.0", "info": {"title": "Things", "version": "1.0.0"}});
+        down_convert(&mut spec).unwrap();
+        serde_json::from_value::<openapiv3::OpenAPI>(spec).unwrap();
+    }
+
+    #[test]
+    fn test_unsupported_constructs() {
+        let err = |schema: serde_json::Value| convert_schema(schema).unwrap_err().to_string();
+
+        assert_eq!(
+            err(json!({"type": "object", "properties": {"point": {"type": "array", "prefixItems": [{"type": "number"}]}}})),
+            "`#/components/schemas/Thing/properties/point` uses `prefixItems`, which OpenAPI 3.0 has no equivalent for"
+        );
+        assert_eq!(
+            err(json!({"type": ["string", "integer"]})),
+            "`#/components/schemas/Thing` has the types [\"string\",\"integer\"], OpenAPI 3.0 schemas have a single type, use a `oneOf` instead"
+        );
+        assert_eq!(
+            err(json!({"type": "null"})),
+            "`#/components/schemas/Thing` can only be null, which OpenAPI 3.0 has no type for"
+        );
+        assert_eq!(
+            err(json!({"type": "array", "items": false})),
+            "`#/components/schemas/Thing/items` is the `false` schema, which OpenAPI 3.0 has no equivalent for"
+        );
+        assert_eq!(
+            err(json!({"type": "string", "contentEncoding": "base32"})),
+            "`#/components/schemas/Thing` uses the content encoding \"base32\", only `base64` is supported"
+        );
+
+        let mut spec = json!({
+            "openapi": "3.1.0",
+            "info": {"title": "Things", "version": "1.0.0"},
+            "components": {"pathItems": {}}
+        });
+        assert_eq!(
+            down_convert(&mut spec).unwrap_err().to_string(),
+            "`#/components/pathItems` is not supported by OpenAPI 3.0, inline the path items"
+        );
+    }
+
+    #[test]
+    fn test_load_yaml_openapi_31() {
+        let spec = crate::load_yaml_spec(
+            r#"
+openapi: 3.1.0
+info:
+  title: Things
+  version: 1.0.0
+paths:
+  /things:
+    get:
+      responses:
+        200:
+          description: ok
+          content:
+            application/json:
+              schema:
+                type: [string, "null"]
+"#,
+        )
+        .unwrap();
+        let response = spec.paths.paths["/things"]
+            .as_item()
+            .unwrap()
+            .get
+            .as_ref()
+            .unwrap();
+        let response = response.responses.responses[&openapiv3::StatusCode::Code(200)]
+            .as_item()
+            .unwrap();
+        let schema = response.content["application/json"]
+            .schema
+            .as_ref()
+            .unwrap();
+        assert!(schema.as_item().unwrap().schema_data.nullable);
+    }
+
+    #[test]
+    fn test_generate_like_openapi_30() {
+        let generate = |spec: &openapiv3::OpenAPI| {
+            let opts = crate::Opts::default();
+            let mut type_space = crate::types::generate_types(spec, opts.clone()).unwrap();
+            let (files, _, _) = crate::functions::generate_files(&mut type_space, &opts).unwrap();
+            (
+                type_space.rendered.to_string(),
+                files
+                    .into_iter()
+                    .map(|(tag, file)| (tag, file.to_string()))
+                    .collect::<Vec<_>>(),
+            )
+        };
+
+        let spec_31 = crate::load_json_spec(include_str!("../tests/openapi-3.1.json")).unwrap();
+        let spec_30 = crate::load_json_spec(include_str!("../tests/openapi-3.0.json")).unwrap();
+        assert_eq!(spec_31, spec_30);
+        assert_eq!(generate(&spec_31), generate(&spec_30));
+    }
+}
@@ -0,0 +1,257 @@
+//! Helpers waiting for the jobs that report their status on the resource they
+//! create.
+//!
+//! For every `POST` operation returning a resource with an `id` and a `status`,
+//! when a `GET` operation takes that `id` alone and returns the same resource, we
+//! generate `{post}_and_wait`: it creates the job, then polls it with a growing
+//! wait until its status is one of the `completed` or `failed` ones, or until the
+//! timeout of its `PollOptions`. The server can ask for a longer wait with
+//! `Retry-After`. The helper returns the completed resource, or a `PollError`
+//! with the last status.
+//!
+//! The `--poll-helpers` file overrides the pairing and the statuses, for example:
+//!
+//! ```toml
+//! # Only generate the helpers for the pairs below.
+//! heuristics = false
+//! # Never generate helpers for these `POST` operations.
+//! skip = ["create_org"]
+//! # The field with the status of the resource, the default is `status`.
+//! status = "state"
+//! # The statuses of a job that is done, the defaults are below.
+//! completed = ["completed"]
+//! failed = ["failed"]
+//!
+//! # The `GET` operation polling the job of a `POST` operation, by operation id.
+//! [pairs]
+//! create_text_to_cad = "get_text_to_cad_model_for_user"
+//! ```
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::Result;
+use serde::Deserialize;
+
+/// How we pair the operations for the poll helpers.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PollHelpers {
+    /// Pair the operations returning the same resource by themselves.
+    #[serde(default = "default_heuristics")]
+    pub heuristics: bool,
+    /// The `POST` operations to never generate helpers for, by operation id.
+    #[serde(default)]
+    pub skip: BTreeSet<String>,
+    /// The field with the status of the resource.
+    #[serde(default = "default_status")]
+    pub status: String,
+    /// The statuses of a job that is done.
+    #[serde(default = "default_completed")]
+    pub completed: Vec<String>,
+    /// The statuses of a job that failed.
+    #[serde(default = "default_failed")]
+    pub failed: Vec<String>,
+    /// The `GET` operation for a `POST` operation, by operation id.
+    #[serde(default)]
+    pub pairs: BTreeMap<String, String>,
+}
+
+fn default_heuristics() -> bool {
+    true
+}
+
+fn default_status() -> String {
+    "status".to_string()
+}
+
+fn default_completed() -> Vec<String> {
+    vec!["completed".to_string()]
+}
+
+fn default_failed() -> Vec<String> {
+    vec!["failed".to_string()]
+}
+
+impl Default for PollHelpers {
+    fn default() -> Self {
+        Self {
+            heuristics: default_heuristics(),
+            skip: Default::default(),
+            status: default_status(),
+            completed: default_completed(),
+            failed: default_failed(),
+            pairs: Default::default(),
+        }
+    }
+}
+
+impl PollHelpers {
+    /// Read the config from a file.
+    pub fn load<P: AsRef<std::path::Path>>(p: P) -> Result<Self> {
+        let p = p.as_ref();
+        let contents = std::fs::read_to_string(p)
+            .map_err(|e| anyhow::anyhow!("failed to read poll helpers `{}`: {}", p.display(), e))?;
+        let config: PollHelpers = toml::from_str(&contents).map_err(|e| {
+            anyhow::anyhow!("failed to parse poll helpers `{}`: {}", p.display(), e)
+        })?;
+        if config.completed.is_empty() || config.failed.is_empty() {
+            anyhow::bail!(
+                "invalid poll helpers `{}`: `completed` and `failed` need a status each",
+                p.display()
+            );
+        }
+        if let Some(status) = config
+            .completed
+            .iter()
+            .find(|status| config.failed.contains(status))
+        {
+            anyhow::bail!(
+                "invalid poll helpers `{}`: `{}` is both completed and failed",
+                p.display(),
+                status
+            );
+        }
+
+        Ok(config)
+    }
+
+    /// The `GET` operation to poll the job of the `POST` operation `post` with,
+    /// given the candidates the heuristics found. Like the update helpers, a
+    /// configured pair must fit, a guessed one is skipped if it does not.
+    pub fn pairing<'a>(
+        &'a self,
+        post: &str,
+        guessed: &[&'a str],
+    ) -> Option<crate::update_helpers::Pairing<'a>> {
+        use crate::update_helpers::Pairing;
+
+        if self.skip.contains(post) {
+            None
+        } else if let Some(get) = self.pairs.get(post) {
+            Some(Pairing::Configured(get))
+        } else if self.heuristics && guessed.len() == 1 {
+            // More than one candidate is ambiguous, it needs a pair.
+            Some(Pairing::Guessed(guessed[0]))
+        } else {
+            None
+        }
+    }
+
+    /// Make sure every operation in the config exists, `gets` and `posts` are the
+    /// operation ids in the spec.
+    pub fn check_operations(
+        &self,
+        gets: &BTreeSet<String>,
+        posts: &BTreeSet<String>,
+    ) -> Result<()> {
+        for post in self.skip.iter().chain(self.pairs.keys()) {
+            if !posts.contains(post) {
+                anyhow::bail!(
+                    "the poll helpers have `{}`, which is not a POST operation",
+                    post
+                );
+            }
+        }
+        for get in self.pairs.values() {
+            if !gets.contains(get) {
+                anyhow::bail!(
+                    "the poll helpers have `{}`, which is not a GET operation",
+                    get
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// If the statuses a resource can have include the ones of a job that is done
+    /// and of one that failed, so the helper can tell them apart.
+    pub fn knows_statuses(&self, statuses: &[String]) -> bool {
+        self.completed
+            .iter()
+            .chain(self.failed.iter())
+            .all(|status| statuses.contains(status))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PollHelpers;
+    use crate::update_helpers::Pairing;
+
+    fn config(contents: &str) -> PollHelpers {
+        toml::from_str(contents).unwrap()
+    }
+
+    #[test]
+    fn test_poll_helpers_pairing() {
+        let default = PollHelpers::default();
+        assert_eq!(default, config(""));
+        assert_eq!(default.status, "status");
+        assert_eq!(
+            default.pairing("create_scan", &["get_scan"]),
+            Some(Pairing::Guessed("get_scan"))
+        );
+        assert_eq!(default.pairing("create_scan", &[]), None);
+        assert_eq!(
+            default.pairing("create_scan", &["get_scan", "get_user_scan"]),
+            None
+        );
+
+        let config = config(
+            r#"
+            heuristics = false
+            skip = ["create_org"]
+
+            [pairs]
+            create_scan = "get_user_scan"
+            "#,
+        );
+        assert_eq!(config.pairing("create_org", &["get_org"]), None);
+        assert_eq!(config.pairing("create_user", &["get_user"]), None);
+        assert_eq!(
+            config.pairing("create_scan", &["get_scan", "get_user_scan"]),
+            Some(Pairing::Configured("get_user_scan"))
+        );
+    }
+
+    #[test]
+    fn test_poll_helpers_statuses() {
+        let statuses = ["queued", "in_progress", "completed", "failed"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>();
+        assert!(PollHelpers::default().knows_statuses(&statuses));
+        assert!(!config(r#"completed = ["done"]"#).knows_statuses(&statuses));
+    }
+
+    #[test]
+    fn test_poll_helpers_unknown_operations() {
+        let gets = vec!["get_scan".to_string()].into_iter().collect();
+        let posts = vec!["create_scan".to_string()].into_iter().collect();
+
+        assert!(config(r#"skip = ["create_scan"]"#)
+            .check_operations(&gets, &posts)
+            .is_ok());
+        assert_eq!(
+            config("pairs = { create_scan = \"get_scans\" }")
+                .check_operations(&gets, &posts)
+                .unwrap_err()
+                .to_string(),
+            "the poll helpers have `get_scans`, which is not a GET operation"
+        );
+    }
+
+    #[test]
+    fn test_load_poll_helpers() {
+        let path = std::env::temp_dir().join(format!("poll-helpers-{}.toml", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "completed = [\"done\"]\nfailed = [\"done\"]").unwrap();
+        let err = PollHelpers::load(&path).unwrap_err().to_string();
+        std::fs::remove_file(&path).unwrap();
+        assert!(
+            err.ends_with("`done` is both completed and failed"),
+            "{}",
+            err
+        );
+    }
+}
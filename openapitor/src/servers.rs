@@ -0,0 +1,250 @@
+//! The servers of the spec, with their templated URLs.
+//!
+//! Without `--base-url`, the base URL of the client is the URL of the first server
+//! of the spec, with the defaults of its variables. When that URL has variables,
+//! like `https://{region}.api.example.com`, the client gets a `set_server_variable`
+//! function that expands it again with another value, checked against the values
+//! the variable can take. The expansion is in the `servers` module of the types,
+//! see `types/servers.rs`.
+
+use std::str::FromStr;
+
+use anyhow::Result;
+use quote::quote;
+
+/// The server of the spec the client can set the variables of, the first one if
+/// its URL has variables.
+pub fn find(spec: &openapiv3::OpenAPI) -> Option<&openapiv3::Server> {
+    spec.servers.first().filter(|server| {
+        server
+            .variables
+            .as_ref()
+            .is_some_and(|variables| !variables.is_empty())
+    })
+}
+
+/// The URL of the first server of the spec, with the defaults of its variables.
+pub fn default_base_url(spec: &openapiv3::OpenAPI) -> Result<url::Url> {
+    let Some(server) = spec.servers.first() else {
+        anyhow::bail!("the spec has no servers, pass the base url with `--base-url`");
+    };
+
+    let values = server
+        .variables
+        .iter()
+        .flatten()
+        .map(|(name, variable)| (name.to_string(), variable.default.to_string()))
+        .collect();
+    let url = crate::types::servers::expand(&server.url, &[], &values);
+    url::Url::parse(&url).map_err(|e| {
+        anyhow::anyhow!(
+            "the url `{}` of the server of the spec is not a base url, pass one with \
+             `--base-url`: {}",
+            url,
+            e
+        )
+    })
+}
+
+/// The options with the base url of the spec, if they have none.
+pub fn with_base_url(spec: &openapiv3::OpenAPI, opts: &crate::Opts) -> Result<crate::Opts> {
+    let mut opts = opts.clone();
+    if opts.base_url.is_none() {
+        opts.base_url = Some(default_base_url(spec)?);
+    }
+    Ok(opts)
+}
+
+/// Render the `servers` module of the types, with the URL and the variables of
+/// the server.
+pub fn render_module(server: &openapiv3::Server) -> Result<proc_macro2::TokenStream> {
+    let file = include_str!("types/servers.rs");
+    let stream = proc_macro2::TokenStream::from_str(file).map_err(|e| anyhow::anyhow!("{}", e))?;
+
+    let url = &server.url;
+    let variables = server.variables.iter().flatten().map(|(name, variable)| {
+        let default = &variable.default;
+        let allowed = &variable.enumeration;
+        quote! {
+            ServerVariable {
+                name: #name,
+                default: #default,
+                allowed: &[#(#allowed),*],
+            }
+        }
+    });
+
+    Ok(quote!(
+        pub mod servers {
+            #stream
+
+            /// The templated URL of the server.
+            pub const URL: &str = #url;
+
+            /// The variables of the URL of the server.
+            pub const VARIABLES: &[ServerVariable] = &[#(#variables),*];
+        }
+    ))
+}
+
+/// Fill in the placeholders of the server variables of the client: the field
+/// of the values that were set, its default in the constructors, and the
+/// `set_server_variable` function. Without a server with variables the
+/// placeholders go away.
+pub fn add_to_client(client: &str, server: Option<&openapiv3::Server>) -> String {
+    let (field, default) = match server {
+        Some(_) => (
+            "server_variables: std::collections::BTreeMap<String, String>,",
+            "server_variables: Default::default(),",
+        ),
+        None => ("", ""),
+    };
+    let mut out = replace_line(client, "SERVER_VARIABLES_FIELD", field);
+    out = replace_line(&out, "SERVER_VARIABLES_DEFAULT", default);
+
+    if let Some(server) = server {
+        out.push_str(&SET_SERVER_VARIABLE.replace("SERVER_URL", &server.url));
+    }
+    out
+}
+
+/// Replace the lines of `text` that are only `placeholder` with `value` at the
+/// same indentation, or remove them if `value` is empty.
+fn replace_line(text: &str, placeholder: &str, value: &str) -> String {
+    text.split_inclusive('\n')
+        .filter_map(|line| {
+            if line.trim() != placeholder {
+                return Some(line.to_string());
+            }
+            if value.is_empty() {
+                return None;
+            }
+            let indent = &line[..line.len() - line.trim_start().len()];
+            Some(format!("{}{}\n", indent, value))
+        })
+        .collect()
+}
+
+/// The function of the client setting a variable of the URL of the server, it goes
+/// at the end of the `impl Client` the client leaves open.
+const SET_SERVER_VARIABLE: &str = r#"
+    /// Set a variable of the URL of the server, like its region, then expand the
+    /// URL into the base URL of the client again: `SERVER_URL`. Fails if the URL
+    /// has no such variable, if the variable can't take the value, or if the base
+    /// URL is not the URL of the server, like one set with `set_base_url`, which
+    /// stays as it is.
+    pub fn set_server_variable<N, V>(
+        &mut self,
+        name: N,
+        value: V,
+    ) -> Result<(), crate::types::servers::ServerVariableError>
+    where
+        N: ToString,
+        V: ToString,
+    {
+        let (name, value) = (name.to_string(), value.to_string());
+        crate::types::servers::check(crate::types::servers::VARIABLES, &name, &value)?;
+        let current = crate::types::servers::expand(
+            crate::types::servers::URL,
+            crate::types::servers::VARIABLES,
+            &self.server_variables,
+        );
+        if self.base_url != current {
+            return Err(crate::types::servers::ServerVariableError::CustomBaseUrl(
+                self.base_url.clone(),
+            ));
+        }
+        self.server_variables.insert(name, value);
+        self.base_url = crate::types::servers::expand(
+            crate::types::servers::URL,
+            crate::types::servers::VARIABLES,
+            &self.server_variables,
+        );
+        Ok(())
+    }
+"#;
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_default_base_url() {
+        let spec =
+            crate::load_json_spec(include_str!("../tests/types/input/servers.json")).unwrap();
+        assert_eq!(
+            super::default_base_url(&spec).unwrap().as_str(),
+            "https://us-east.api.example.com/v1"
+        );
+        assert!(super::find(&spec).is_some());
+
+        // The base url of the options wins over the one of the spec.
+        let opts = crate::Opts {
+            base_url: Some("https://api.example.com".parse().unwrap()),
+            ..Default::default()
+        };
+        assert_eq!(
+            super::with_base_url(&spec, &opts)
+                .unwrap()
+                .trimmed_base_url(),
+            "https://api.example.com"
+        );
+        let opts = crate::Opts {
+            base_url: None,
+            ..Default::default()
+        };
+        assert_eq!(
+            super::with_base_url(&spec, &opts)
+                .unwrap()
+                .trimmed_base_url(),
+            "https://us-east.api.example.com/v1"
+        );
+
+        // A spec without servers needs a base url.
+        let spec = crate::load_json_spec(include_str!("../tests/datetime.json")).unwrap();
+        assert!(super::default_base_url(&spec).is_err());
+        assert!(super::find(&spec).is_none());
+    }
+
+    #[test]
+    fn test_render_servers() {
+        let spec =
+            crate::load_json_spec(include_str!("../tests/types/input/servers.json")).unwrap();
+        let server = super::find(&spec).unwrap();
+        let module = crate::types::get_text_fmt(&super::render_module(server).unwrap()).unwrap();
+        expectorate::assert_contents("tests/types/servers.rs.gen", &module);
+
+        // Every variant of the client remembers the variables it was given.
+        for opts in [
+            Default::default(),
+            crate::Opts {
+                basic_auth: true,
+                ..Default::default()
+            },
+            crate::Opts {
+                token_endpoint: Some("https://example.com/oauth/token".parse().unwrap()),
+                user_consent_endpoint: Some("https://example.com/oauth/consent".parse().unwrap()),
+                ..Default::default()
+            },
+        ] {
+            let client = crate::client::generate_client(&spec, &opts);
+            assert!(!client.contains("SERVER_VARIABLES"));
+            assert_eq!(
+                client
+                    .matches("server_variables: Default::default(),")
+                    .count(),
+                client
+                    .matches("max_pages: crate::types::paginate::DEFAULT_MAX_PAGES,")
+                    .count()
+            );
+            assert!(client
+                .contains("    server_variables: std::collections::BTreeMap<String, String>,\n"));
+            assert!(client.contains("`https://{region}.api.example.com/{basePath}`"));
+        }
+
+        // A client without variables has none of it.
+        let mut spec = crate::load_json_spec(include_str!("../tests/mock.json")).unwrap();
+        spec.servers.clear();
+        let client = crate::client::generate_client(&spec, &Default::default());
+        assert!(!client.contains("server_variables"));
+        assert!(!client.contains("SERVER_VARIABLES"));
+    }
+}
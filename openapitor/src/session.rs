@@ -0,0 +1,330 @@
+//! A websocket session that checks the order of the commands before sending them.
+//!
+//! Some websocket protocols need commands to be sent before others, which the spec
+//! cannot say. The `--session-config` file lists them, for example:
+//!
+//! ```toml
+//! # The websocket operation, by operation id.
+//! operation = "modeling_commands_ws"
+//! # The schema of the messages we send.
+//! request = "WebSocketRequest"
+//! # The oneOf of the commands, a command is named by its tag.
+//! commands = "ModelingCmd"
+//! # The key of the commands in the messages, at any depth. The default is `cmd`.
+//! command_field = "cmd"
+//! # The commands that must come before every other command.
+//! before_all = ["set_scene_units"]
+//!
+//! # The commands that must come before a command.
+//! [prerequisites]
+//! extend_path = ["start_path"]
+//! extrude = ["start_path", "close_path"]
+//! ```
+//!
+//! We generate a `Session` in the module of the operation, wrapping the managed
+//! websocket connection. It refuses to send a message with a command before the
+//! commands that must come before it, see `crate::types::session` in the generated
+//! crate.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::Result;
+use proc_macro2::TokenStream;
+use serde::Deserialize;
+
+use crate::types::exts::{OperationExt, ReferenceOrExt};
+
+/// The order of the commands sent over a websocket operation.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SessionConfig {
+    /// The websocket operation, by operation id.
+    pub operation: String,
+    /// The schema of the messages we send.
+    pub request: String,
+    /// The oneOf of the commands.
+    pub commands: String,
+    /// The key of the commands in the messages.
+    #[serde(default = "default_command_field")]
+    pub command_field: String,
+    /// The commands that must come before every other command, by tag.
+    #[serde(default)]
+    pub before_all: BTreeSet<String>,
+    /// The commands that must come before a command, by the tag of the command.
+    #[serde(default)]
+    pub prerequisites: BTreeMap<String, BTreeSet<String>>,
+}
+
+fn default_command_field() -> String {
+    "cmd".to_string()
+}
+
+impl SessionConfig {
+    /// Read the config from a file.
+    pub fn load<P: AsRef<std::path::Path>>(p: P) -> Result<Self> {
+        let p = p.as_ref();
+        let contents = std::fs::read_to_string(p).map_err(|e| {
+            anyhow::anyhow!("failed to read session config `{}`: {}", p.display(), e)
+        })?;
+        toml::from_str(&contents)
+            .map_err(|e| anyhow::anyhow!("failed to parse session config `{}`: {}", p.display(), e))
+    }
+
+    /// Make sure the commands that must come first can ever be sent, a command can
+    /// not end up needing itself.
+    pub fn check_cycles(&self) -> Result<()> {
+        fn visit<'a>(
+            config: &'a SessionConfig,
+            command: &'a str,
+            path: &mut Vec<&'a str>,
+            done: &mut BTreeSet<&'a str>,
+        ) -> Result<()> {
+            if let Some(start) = path.iter().position(|c| *c == command) {
+                let mut cycle = path[start..].to_vec();
+                cycle.push(command);
+                anyhow::bail!(
+                    "the session config has a cycle of prerequisites: {}",
+                    cycle.join(" -> ")
+                );
+            }
+            if !done.insert(command) {
+                return Ok(());
+            }
+            path.push(command);
+            for prerequisite in config.prerequisites.get(command).into_iter().flatten() {
+                visit(config, prerequisite, path, done)?;
+            }
+            path.pop();
+            Ok(())
+        }
+
+        let mut done = BTreeSet::new();
+        for command in self.prerequisites.keys() {
+            visit(self, command, &mut Vec::new(), &mut done)?;
+        }
+        Ok(())
+    }
+
+    /// Generate the `Session` for the operation, returns the tag of the operation
+    /// since the session goes in its module. `renamed` are the schemas we renamed,
+    /// the config uses the names in the spec.
+    pub fn render(
+        &self,
+        spec: &openapiv3::OpenAPI,
+        renamed: &BTreeMap<String, String>,
+    ) -> Result<(String, TokenStream)> {
+        let (tag, fn_name) = self.get_operation(spec)?;
+        let request = self.get_schema_name(spec, renamed, &self.request)?;
+        let commands = self.get_schema_name(spec, renamed, &self.commands)?;
+        let (tag_field, tags) = get_command_tags(spec, &commands)?;
+
+        for command in self
+            .before_all
+            .iter()
+            .chain(self.prerequisites.keys())
+            .chain(self.prerequisites.values().flatten())
+        {
+            if !tags.contains(command) {
+                anyhow::bail!(
+                    "the session config has `{}`, which is not a command of `{}`",
+                    command,
+                    self.commands
+                );
+            }
+        }
+        self.check_cycles()?;
+
+        let request_ident = format_ident!("{}", crate::types::proper_name(&request));
+        let command_field = &self.command_field;
+        let before_all = self.before_all.iter();
+        let prerequisites = self.prerequisites.iter().map(|(command, prerequisites)| {
+            let prerequisites = prerequisites.iter();
+            quote!((#command, &[#(#prerequisites),*]))
+        });
+
+        let tag_ident = format_ident!("{}", crate::types::proper_name(&tag));
+        let order_doc = format!(
+            "The order of the `{}` commands sent over [`{}`]({}::{}), from the session config.",
+            crate::types::proper_name(&commands),
+            fn_name,
+            tag_ident,
+            fn_name
+        );
+        let session_doc = format!(
+            "A connection of [`{}`]({}::{}) that refuses to send a command before the commands \
+             that must come before it.",
+            fn_name, tag_ident, fn_name
+        );
+
+        Ok((
+            tag,
+            quote! {
+                #[doc = #order_doc]
+                #[derive(Debug, Clone, Copy)]
+                #[cfg(feature = "websocket")]
+                #[cfg(not(target_arch = "wasm32"))]
+                pub struct SessionOrder;
+
+                #[cfg(feature = "websocket")]
+                #[cfg(not(target_arch = "wasm32"))]
+                impl crate::types::session::Order for SessionOrder {
+                    type Request = crate::types::#request_ident;
+
+                    const COMMAND_FIELD: &'static str = #command_field;
+                    const TAG: &'static str = #tag_field;
+                    const BEFORE_ALL: &'static [&'static str] = &[#(#before_all),*];
+                    const PREREQUISITES: &'static [(&'static str, &'static [&'static str])] =
+                        &[#(#prerequisites),*];
+                }
+
+                #[doc = #session_doc]
+                #[cfg(feature = "websocket")]
+                #[cfg(not(target_arch = "wasm32"))]
+                pub type Session = crate::types::session::Session<SessionOrder>;
+            },
+        ))
+    }
+
+    /// The tag and the function name of the websocket operation.
+    fn get_operation(&self, spec: &openapiv3::OpenAPI) -> Result<(String, String)> {
+        for (_, item) in spec.paths.iter() {
+            let item = item.item()?;
+            for (_, op) in item.iter() {
+                if op.operation_id.as_deref() != Some(self.operation.as_str()) {
+                    continue;
+                }
+                if !op.is_websocket() {
+                    break;
+                }
+                return Ok((op.get_tag()?, op.get_fn_name()?));
+            }
+        }
+
+        anyhow::bail!(
+            "the session config has `{}`, which is not a websocket operation",
+            self.operation
+        )
+    }
+
+    /// The name of a schema once we renamed it.
+    fn get_schema_name(
+        &self,
+        spec: &openapiv3::OpenAPI,
+        renamed: &BTreeMap<String, String>,
+        name: &str,
+    ) -> Result<String> {
+        let name = renamed.get(name).map(String::as_str).unwrap_or(name);
+        match &spec.components {
+            Some(components) if components.schemas.contains_key(name) => Ok(name.to_string()),
+            _ => anyhow::bail!("the session config has `{}`, which is not a schema", name),
+        }
+    }
+}
+
+/// The name of the tag of the commands, and the tag of every command.
+fn get_command_tags(spec: &openapiv3::OpenAPI, name: &str) -> Result<(String, BTreeSet<String>)> {
+    let not_tagged = || {
+        anyhow::anyhow!(
+            "the commands `{}` of the session config must be a oneOf of objects tagged by one \
+             property",
+            name
+        )
+    };
+
+    let schema = openapiv3::ReferenceOr::<openapiv3::Schema>::Reference {
+        reference: format!("#/components/schemas/{}", name),
+    }
+    .expand(spec)?;
+    let openapiv3::SchemaKind::OneOf { one_of } = &schema.schema_kind else {
+        return Err(not_tagged());
+    };
+    let tag = crate::types::get_one_of_tag(one_of, spec)?
+        .tag
+        .ok_or_else(not_tagged)?;
+
+    let mut tags = BTreeSet::new();
+    for one_of in one_of {
+        let schema = one_of.get_schema_from_reference(spec, true)?;
+        let openapiv3::SchemaKind::Type(openapiv3::Type::Object(o)) = &schema.schema_kind else {
+            return Err(not_tagged());
+        };
+        let property = o
+            .properties
+            .get(&tag)
+            .ok_or_else(not_tagged)?
+            .get_schema_from_reference(spec, true)?;
+        let openapiv3::SchemaKind::Type(openapiv3::Type::String(s)) = &property.schema_kind else {
+            return Err(not_tagged());
+        };
+        match s.enumeration.as_slice() {
+            [Some(value)] => tags.insert(value.to_string()),
+            _ => return Err(not_tagged()),
+        };
+    }
+
+    Ok((tag, tags))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SessionConfig;
+
+    fn config(contents: &str) -> SessionConfig {
+        toml::from_str(contents).unwrap()
+    }
+
+    fn spec() -> openapiv3::OpenAPI {
+        crate::load_json_spec(include_str!("../tests/mock.json")).unwrap()
+    }
+
+    const CONFIG: &str = include_str!("../tests/session.toml");
+
+    #[test]
+    fn test_session_config() {
+        let config = config(CONFIG);
+        assert_eq!(config.command_field, "cmd");
+
+        let (tag, rendered) = config.render(&spec(), &Default::default()).unwrap();
+        assert_eq!(tag, "things");
+        let rendered = crate::types::get_text_fmt(&rendered).unwrap();
+        assert!(rendered.contains("type Request = crate::types::ThingRequest;"));
+        assert!(rendered.contains(r#"const TAG: &'static str = "type";"#));
+        assert!(rendered.contains(r#"&[("draw", &["set_units"])]"#));
+    }
+
+    #[test]
+    fn test_session_config_errors() {
+        let error = |contents: &str| {
+            config(contents)
+                .render(&spec(), &Default::default())
+                .unwrap_err()
+                .to_string()
+        };
+
+        assert_eq!(
+            error(&CONFIG.replace("things_ws", "list_things")),
+            "the session config has `list_things`, which is not a websocket operation"
+        );
+        assert_eq!(
+            error(&CONFIG.replace("\"ThingRequest\"", "\"Nope\"")),
+            "the session config has `Nope`, which is not a schema"
+        );
+        assert_eq!(
+            error(&CONFIG.replace("\"ThingCommand\"", "\"Thing\"")),
+            "the commands `Thing` of the session config must be a oneOf of objects tagged by \
+             one property"
+        );
+        assert_eq!(
+            error(&CONFIG.replace("set_units", "set_colors")),
+            "the session config has `set_colors`, which is not a command of `ThingCommand`"
+        );
+        assert_eq!(
+            error(&format!(
+                "{}set_units = [\"end_session\"]\nend_session = [\"draw\"]\n",
+                CONFIG
+            )),
+            "the session config has a cycle of prerequisites: draw -> set_units -> end_session \
+             -> draw"
+        );
+    }
+}
@@ -0,0 +1,470 @@
+//! The smoke tests of `--generate-smoke-tests`.
+//!
+//! For every tag we pick an operation that is safe to call against the real API:
+//! a `GET` without a body or required arguments, and without a word like `delete`
+//! or `revoke` in its path or id, the one with the shortest path first. It becomes
+//! an ignored test in `src/smoke_tests.rs` that builds the client from the env and
+//! calls it, the test only fails if the response does not read into the types.
+//! Running them with `--ignored` and a real token is up to the crate.
+//!
+//! The `--smoke-tests` file overrides the heuristics, for example:
+//!
+//! ```toml
+//! # Leave these tags without a smoke test.
+//! skip = ["payments"]
+//!
+//! # The operation to call for a tag, by operation id.
+//! [operations]
+//! users = "get_user_self"
+//! ```
+//!
+//! What each tag got, and why, is recorded in `smoke-tests.json`.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::Result;
+use proc_macro2::TokenStream;
+use serde::{Deserialize, Serialize};
+
+use crate::types::exts::{OperationExt, ReferenceOrExt, TokenStreamExt};
+
+/// The version of the report format, bump this if the format changes in a way
+/// consumers would notice.
+pub const SMOKE_TESTS_VERSION: u32 = 1;
+
+/// The words of a path or an operation id that make an operation unsafe to call,
+/// even with a `GET`.
+const DESTRUCTIVE_WORDS: &[&str] = &[
+    "cancel",
+    "delete",
+    "destroy",
+    "disable",
+    "kill",
+    "logout",
+    "purge",
+    "reboot",
+    "remove",
+    "reset",
+    "restart",
+    "revoke",
+    "shutdown",
+    "stop",
+    "unsubscribe",
+];
+
+/// How we pick the operations of the smoke tests.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SmokeTestsConfig {
+    /// The tags to leave without a smoke test.
+    #[serde(default)]
+    pub skip: BTreeSet<String>,
+    /// The operation to call for a tag, by operation id.
+    #[serde(default)]
+    pub operations: BTreeMap<String, String>,
+}
+
+impl SmokeTestsConfig {
+    /// Read the config from a file.
+    pub fn load<P: AsRef<std::path::Path>>(p: P) -> Result<Self> {
+        let p = p.as_ref();
+        let contents = std::fs::read_to_string(p)
+            .map_err(|e| anyhow::anyhow!("failed to read smoke tests `{}`: {}", p.display(), e))?;
+        toml::from_str(&contents)
+            .map_err(|e| anyhow::anyhow!("failed to parse smoke tests `{}`: {}", p.display(), e))
+    }
+}
+
+/// The smoke tests of a generated library, by tag.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SmokeTests {
+    /// The version of the report format.
+    pub smoke_tests_version: u32,
+    /// What every tag got.
+    pub tags: BTreeMap<String, SmokeTest>,
+}
+
+/// The smoke test of a tag.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "selection", rename_all = "snake_case")]
+pub enum SmokeTest {
+    /// The heuristics picked the operation.
+    Heuristics {
+        /// The id of the operation.
+        operation_id: String,
+        /// The path of the operation.
+        path: String,
+    },
+    /// The config picked the operation.
+    Configured {
+        /// The id of the operation.
+        operation_id: String,
+        /// The path of the operation.
+        path: String,
+    },
+    /// The config left the tag out.
+    Skipped,
+    /// No operation of the tag is safe to call.
+    NoCandidate,
+}
+
+/// An operation of a tag we could call.
+struct Candidate {
+    operation_id: String,
+    path: String,
+    method: http::Method,
+    op: openapiv3::Operation,
+    global_params: Vec<openapiv3::ReferenceOr<openapiv3::Parameter>>,
+}
+
+impl Candidate {
+    /// Why we can't call the operation without knowing anything about the API,
+    /// if we can't.
+    fn unsafe_reason(&self, type_space: &mut crate::types::TypeSpace) -> Result<Option<String>> {
+        if self.method != http::Method::GET {
+            return Ok(Some(format!("it is a `{}`", self.method)));
+        }
+        if self.op.request_body.is_some() {
+            return Ok(Some("it takes a body".to_string()));
+        }
+        if self.op.is_websocket() {
+            return Ok(Some("it is a websocket".to_string()));
+        }
+        let args = crate::functions::get_args(
+            &self.path,
+            &self.method,
+            type_space,
+            &self.op,
+            &self.global_params,
+        )?;
+        for (name, t) in args {
+            if !t.is_option()? {
+                return Ok(Some(format!("it takes the argument `{}`", name)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Whether the path or the id of the operation has a destructive word.
+    fn is_destructive(&self) -> bool {
+        let words = format!("{} {}", self.path, self.operation_id).to_lowercase();
+        words
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .any(|word| DESTRUCTIVE_WORDS.contains(&word))
+    }
+}
+
+/// The operations of `spec` by tag.
+fn get_candidates(spec: &openapiv3::OpenAPI) -> Result<BTreeMap<String, Vec<Candidate>>> {
+    let mut candidates: BTreeMap<String, Vec<Candidate>> = BTreeMap::new();
+    for (path, item) in spec.paths.iter() {
+        let item = item.item()?;
+        for (method, op) in item.iter() {
+            let Some(operation_id) = &op.operation_id else {
+                continue;
+            };
+            candidates
+                .entry(op.get_tag()?)
+                .or_default()
+                .push(Candidate {
+                    operation_id: operation_id.to_string(),
+                    path: path.to_string(),
+                    method: method.to_uppercase().parse()?,
+                    op: op.clone(),
+                    global_params: item.parameters.clone(),
+                });
+        }
+    }
+
+    Ok(candidates)
+}
+
+/// Pick the operation of the smoke test of every tag.
+pub fn select(
+    type_space: &mut crate::types::TypeSpace,
+    config: &SmokeTestsConfig,
+) -> Result<SmokeTests> {
+    let candidates = get_candidates(&type_space.spec.clone())?;
+
+    // Make sure every tag and operation of the config exists.
+    for tag in config.skip.iter().chain(config.operations.keys()) {
+        if !candidates.contains_key(tag) {
+            anyhow::bail!(
+                "the smoke tests have the tag `{}`, which has no operations",
+                tag
+            );
+        }
+    }
+
+    let mut tags = BTreeMap::new();
+    for (tag, mut operations) in candidates {
+        let test = if config.skip.contains(&tag) {
+            SmokeTest::Skipped
+        } else if let Some(operation_id) = config.operations.get(&tag) {
+            let Some(candidate) = operations
+                .iter()
+                .find(|candidate| &candidate.operation_id == operation_id)
+            else {
+                anyhow::bail!(
+                    "the smoke test of the tag `{}` calls `{}`, which is not an operation of the tag",
+                    tag,
+                    operation_id
+                );
+            };
+            if let Some(reason) = candidate.unsafe_reason(type_space)? {
+                anyhow::bail!(
+                    "the smoke test of the tag `{}` can't call `{}`: {}",
+                    tag,
+                    operation_id,
+                    reason
+                );
+            }
+            SmokeTest::Configured {
+                operation_id: operation_id.to_string(),
+                path: candidate.path.to_string(),
+            }
+        } else {
+            // The shortest paths are the most likely to be a `/ping` or a `/me`.
+            operations.sort_by(|a, b| {
+                (a.path.matches('/').count(), &a.path, &a.operation_id).cmp(&(
+                    b.path.matches('/').count(),
+                    &b.path,
+                    &b.operation_id,
+                ))
+            });
+            let mut picked = None;
+            for candidate in operations {
+                if !candidate.is_destructive() && candidate.unsafe_reason(type_space)?.is_none() {
+                    picked = Some(candidate);
+                    break;
+                }
+            }
+            match picked {
+                Some(candidate) => SmokeTest::Heuristics {
+                    operation_id: candidate.operation_id,
+                    path: candidate.path,
+                },
+                None => SmokeTest::NoCandidate,
+            }
+        };
+        tags.insert(tag, test);
+    }
+
+    Ok(SmokeTests {
+        smoke_tests_version: SMOKE_TESTS_VERSION,
+        tags,
+    })
+}
+
+/// Render the smoke tests of `tests`.
+pub fn render(
+    type_space: &mut crate::types::TypeSpace,
+    tests: &SmokeTests,
+    opts: &crate::Opts,
+) -> Result<TokenStream> {
+    let docs = quote! {
+        //! The smoke tests, one for every tag, calling an operation that is safe to call
+        //! against the real API. They are ignored, run them with `--ignored` and the
+        //! credentials in the env. Generated with `--generate-smoke-tests`, the
+        //! changes to this file are lost.
+    };
+
+    // Without the credentials there is nothing to smoke, we skip rather than fail.
+    let suffix = if opts.basic_auth {
+        "USERNAME"
+    } else {
+        "API_TOKEN"
+    };
+    let env_names = crate::client::get_env_variable_names(opts, suffix);
+    let missing = format!("skipping, none of {} is set", env_names.join(", "));
+
+    let candidates = get_candidates(&type_space.spec.clone())?;
+    let mut rendered = Vec::new();
+    for (tag, test) in &tests.tags {
+        let (SmokeTest::Heuristics { operation_id, .. }
+        | SmokeTest::Configured { operation_id, .. }) = test
+        else {
+            continue;
+        };
+        let Some(candidate) = candidates
+            .get(tag)
+            .and_then(|operations| operations.iter().find(|c| &c.operation_id == operation_id))
+        else {
+            anyhow::bail!(
+                "the smoke test of the tag `{}` calls `{}`, which does not exist",
+                tag,
+                operation_id
+            );
+        };
+
+        let args = crate::functions::get_args(
+            &candidate.path,
+            &candidate.method,
+            type_space,
+            &candidate.op,
+            &candidate.global_params,
+        )?
+        .into_iter()
+        .map(|_| quote!(None));
+        let test_ident = format_ident!("smoke_{}", tag);
+        let tag_accessor = crate::modules::ModuleMap::new(opts)?.accessor(tag);
+        let fn_ident = candidate.op.get_fn_name_ident()?;
+        let call = format!("{} {}", candidate.method, candidate.path);
+
+        rendered.push(quote! {
+            #[tokio::test]
+            #[ignore]
+            async fn #test_ident() {
+                if ![#(#env_names),*].iter().any(|name| std::env::var(name).is_ok()) {
+                    eprintln!(#missing);
+                    return;
+                }
+
+                let client = crate::Client::new_from_env();
+                let result = client.#tag_accessor.#fn_ident(#(#args),*).await;
+                // An error of the API is fine, a response that does not read into the
+                // types is not.
+                if let Err(
+                    err @ (crate::types::error::Error::SerdeError { .. }
+                    | crate::types::error::Error::InvalidResponsePayload { .. }),
+                ) = &result
+                {
+                    panic!("{}: {}", #call, err);
+                }
+            }
+        });
+    }
+
+    Ok(quote! {
+        #docs
+
+        #(#rendered)*
+    })
+}
+
+/// Pick and render the smoke tests of the options, and save the report to
+/// `smoke-tests.json` in the output directory.
+pub fn generate(type_space: &crate::types::TypeSpace, opts: &crate::Opts) -> Result<TokenStream> {
+    if opts.token_endpoint.is_some() {
+        anyhow::bail!(
+            "--generate-smoke-tests needs a client that reads its credentials from the env, \
+             not one with --token-endpoint"
+        );
+    }
+
+    let config = match &opts.smoke_tests {
+        Some(path) => SmokeTestsConfig::load(path)?,
+        None => Default::default(),
+    };
+    // The arguments of the operations are worked out again, keep the types of the
+    // library as they are.
+    let mut type_space = type_space.clone();
+    let tests = select(&mut type_space, &config)?;
+    crate::save(
+        opts.output.join("smoke-tests.json"),
+        &serde_json::to_string_pretty(&tests)?,
+    )?;
+    render(&mut type_space, &tests, opts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{select, SmokeTest, SmokeTestsConfig};
+
+    fn spec() -> openapiv3::OpenAPI {
+        serde_json::from_str(include_str!("../tests/types/input/smoke-tests.json")).unwrap()
+    }
+
+    fn picked(config: &str) -> anyhow::Result<Vec<(String, String)>> {
+        let config: SmokeTestsConfig = toml::from_str(config).unwrap();
+        let mut type_space = crate::types::generate_types(&spec(), Default::default()).unwrap();
+        Ok(select(&mut type_space, &config)?
+            .tags
+            .into_iter()
+            .map(|(tag, test)| {
+                let picked = match test {
+                    SmokeTest::Heuristics { operation_id, .. } => operation_id,
+                    SmokeTest::Configured { operation_id, .. } => {
+                        format!("{} (configured)", operation_id)
+                    }
+                    SmokeTest::Skipped => "skipped".to_string(),
+                    SmokeTest::NoCandidate => "none".to_string(),
+                };
+                (tag, picked)
+            })
+            .collect())
+    }
+
+    #[test]
+    fn test_smoke_tests_heuristics() {
+        // The reset is destructive even as a `GET`, a thing needs its id, and so does
+        // the only user.
+        assert_eq!(
+            picked("").unwrap(),
+            vec![
+                ("meta".to_string(), "ping".to_string()),
+                ("payments".to_string(), "list_payments".to_string()),
+                ("things".to_string(), "list_things".to_string()),
+                ("users".to_string(), "none".to_string()),
+            ]
+        );
+
+        let mut type_space = crate::types::generate_types(&spec(), Default::default()).unwrap();
+        let tests = select(&mut type_space, &Default::default()).unwrap();
+        let opts = crate::Opts {
+            name: "smoke".to_string(),
+            ..Default::default()
+        };
+        let rendered = super::render(&mut type_space, &tests, &opts).unwrap();
+        expectorate::assert_contents(
+            "tests/types/smoke-tests.rs.gen",
+            &rustfmt_wrapper::rustfmt(rendered.to_string()).unwrap(),
+        );
+    }
+
+    #[test]
+    fn test_smoke_tests_config() {
+        assert_eq!(
+            picked(
+                r#"
+                skip = ["payments"]
+
+                [operations]
+                meta = "ping"
+                "#
+            )
+            .unwrap(),
+            vec![
+                ("meta".to_string(), "ping (configured)".to_string()),
+                ("payments".to_string(), "skipped".to_string()),
+                ("things".to_string(), "list_things".to_string()),
+                ("users".to_string(), "none".to_string()),
+            ]
+        );
+
+        // The operations of the config must be of the tag and safe to call.
+        assert_eq!(
+            picked("operations = { things = \"ping\" }")
+                .unwrap_err()
+                .to_string(),
+            "the smoke test of the tag `things` calls `ping`, which is not an operation of the tag"
+        );
+        assert_eq!(
+            picked("operations = { users = \"get_user\" }")
+                .unwrap_err()
+                .to_string(),
+            "the smoke test of the tag `users` can't call `get_user`: it takes the argument `id`"
+        );
+        assert_eq!(
+            picked("operations = { things = \"create_thing\" }")
+                .unwrap_err()
+                .to_string(),
+            "the smoke test of the tag `things` can't call `create_thing`: it is a `POST`"
+        );
+        assert_eq!(
+            picked("skip = [\"widgets\"]").unwrap_err().to_string(),
+            "the smoke tests have the tag `widgets`, which has no operations"
+        );
+        assert!(toml::from_str::<SmokeTestsConfig>("tags = []").is_err());
+    }
+}
@@ -1,149 +1,120 @@
 //! Templates for our generated client libraries.
 
-use std::fmt::Write as _;
-
 use anyhow::Result;
 use inflector::cases::screamingsnakecase::to_screaming_snake_case;
 
 fn generate_docs_openapi_info(spec: &openapiv3::OpenAPI, opts: &crate::Opts) -> Result<String> {
-    let mut desc = String::new();
-    if let Some(d) = &spec.info.description {
-        desc = d.replace('\n', "\n//! ");
-    }
+    // The paragraphs of the docs, the ones the spec has nothing for are left out.
+    let mut paragraphs = vec![
+        crate::get_crate_description(spec, opts).replace('\n', "\n//! "),
+        format!(
+            "[![docs.rs](https://docs.rs/{}/badge.svg)](https://docs.rs/{})",
+            opts.package_name(),
+            opts.package_name()
+        ),
+    ];
 
-    let mut tos = String::new();
+    let mut api_details = Vec::new();
+    if let Some(d) = spec.info.description.as_deref().map(str::trim) {
+        if !d.is_empty() {
+            api_details.push(d.replace('\n', "\n//! "));
+        }
+    }
     if let Some(t) = &spec.info.terms_of_service {
-        tos = format!("[API Terms of Service]({})", t);
+        api_details.push(format!("[API Terms of Service]({})", t));
     }
-
-    let mut contact = String::new();
     if let Some(c) = &spec.info.contact {
-        let mut num = 1;
-        let mut name = String::new();
-        if let Some(n) = &c.name {
-            contact.push_str("| name ");
-            name = n.to_string();
-            num += 1;
-        }
-        let mut url = String::new();
-        if let Some(u) = &c.url {
-            contact.push_str("| url ");
-            url = u.to_string();
-            num += 1;
-        }
-        let mut email = String::new();
-        if let Some(e) = &c.email {
-            contact.push_str("| email ");
-            email = e.to_string();
-            num += 1;
-        }
-        if !contact.is_empty() {
-            contact.push('|');
-            contact = format!(
-                r#"//! {}
-//! "#,
-                contact
-            );
-            for _ in 1..num {
-                contact.push_str("|----");
-            }
-            contact.push_str("|\n//! ");
-        }
-
-        if !name.is_empty() {
-            write!(contact, "| {} ", name)?;
+        let contact = generate_docs_table(&[
+            ("name", c.name.clone()),
+            ("url", c.url.as_ref().map(|u| format!("<{}>", u))),
+            ("email", c.email.clone()),
+        ]);
+        if let Some(contact) = contact {
+            api_details.push("### Contact".to_string());
+            api_details.push(contact);
         }
-        if !url.is_empty() {
-            write!(contact, "| <{}> ", url)?;
-        }
-        if !email.is_empty() {
-            write!(contact, "| {} ", email)?;
-        }
-        if !contact.is_empty() {
-            contact.push_str("|\n//! ");
-        }
-
-        contact = format!("### Contact\n//!\n//! \n{}", contact);
     }
-
-    let mut license = String::new();
     if let Some(l) = &spec.info.license {
-        license.push_str("| name ");
-
-        let mut url = String::new();
-        if let Some(u) = &l.url {
-            license.push_str("| url ");
-            url = u.to_string();
+        let license = generate_docs_table(&[
+            ("name", Some(l.name.clone())),
+            ("url", l.url.as_ref().map(|u| format!("<{}>", u))),
+        ]);
+        if let Some(license) = license {
+            api_details.push("### License".to_string());
+            api_details.push(license);
         }
-        license.push('|');
-        license = format!(
-            r#"//! {}
-//! "#,
-            license
-        );
-
-        license.push_str("|----");
-        if !url.is_empty() {
-            license.push_str("|----");
-        }
-        license.push_str("|\n//! ");
-
-        write!(license, "| {} ", l.name)?;
-        if !url.is_empty() {
-            write!(license, "| <{}> ", url)?;
-        }
-        license.push_str("|\n//! ");
+    }
+    if !api_details.is_empty() {
+        paragraphs.push("## API Details".to_string());
+        paragraphs.extend(api_details);
+    }
 
-        license = format!("### License\n//!\n//! \n{}", license);
+    paragraphs.push("## Client Details".to_string());
+    if let Some(link) = &opts.spec_url {
+        paragraphs.push(format!(
+            "This client is generated from the [OpenAPI specs]({}) based on API spec version \
+             `{}`. This way it will remain up to date as features are added.",
+            link, spec.info.version
+        ));
     }
+    paragraphs.push(
+        "The documentation for the crate is generated\n//! along with the code to make this \
+         library easy to use."
+            .to_string(),
+    );
 
-    let api_version = format!("based on API spec version `{}`", spec.info.version);
+    Ok(format!("//! {}", paragraphs.join("\n//!\n//! ")))
+}
 
-    let spec_link_blurb = if let Some(link) = &opts.spec_url {
-        format!(
-            "This client is generated from the [OpenAPI specs]({}) {}. This way it will remain up \
-             to date as features are added.",
-            link, api_version
-        )
-    } else {
-        "".to_string()
-    };
+/// Generate a markdown table of one row, with a column for each of the cells
+/// that are set, or nothing if none are.
+fn generate_docs_table(cells: &[(&str, Option<String>)]) -> Option<String> {
+    let cells = cells
+        .iter()
+        .filter_map(|(header, cell)| {
+            let cell = cell.as_deref()?.trim();
+            (!cell.is_empty()).then(|| (*header, cell.replace('|', "\\|")))
+        })
+        .collect::<Vec<_>>();
+    if cells.is_empty() {
+        return None;
+    }
 
-    Ok(format!(
-        r#"//! {}
-//!
-//! [![docs.rs](https://docs.rs/{}/badge.svg)](https://docs.rs/{})
-//!
-//! ## API Details
-//!
-//! {}
-//!
-//! {}
-//!
-//! {}
-//! {}
-//!
-//! ## Client Details
-//!
-//! {}
-//!
-//! The documentation for the crate is generated
-//! along with the code to make this library easy to use.
-//! "#,
-        opts.description,
-        opts.package_name(),
-        opts.package_name(),
-        desc,
-        tos,
-        contact,
-        license,
-        spec_link_blurb,
-    ))
+    let headers = cells
+        .iter()
+        .map(|(header, _)| format!("| {} ", header))
+        .collect::<String>();
+    let rule = "|----".repeat(cells.len());
+    let row = cells
+        .iter()
+        .map(|(_, cell)| format!("| {} ", cell))
+        .collect::<String>();
+    Some(format!("{}|\n//! {}|\n//! {}|", headers, rule, row))
 }
 
 /// Generate the main docs for our client library.
 pub fn generate_docs(spec: &openapiv3::OpenAPI, opts: &crate::Opts) -> Result<String> {
     let info = generate_docs_openapi_info(spec, opts)?;
+    if opts.types_only {
+        return Ok(format!(
+            r#"{}
+//!
+//! To install the library, add the following to your `Cargo.toml` file.
+//!
+//! ```toml
+//! [dependencies]
+//! {} = "{}"
+//! ```
+//!
+//! This library only has the types of the API, in the `types` module, to share
+//! the data without a client. They serialize to the same JSON as the API sends.
+//!"#,
+            info,
+            opts.package_name(),
+            opts.target_version,
+        ));
+    }
+
     if opts.token_endpoint.is_some() {
         return Ok(format!(
             r#"{}
@@ -177,7 +148,8 @@ pub fn generate_docs(spec: &openapiv3::OpenAPI, opts: &crate::Opts) -> Result<St
 //!
 //! - `{}_CLIENT_ID`
 //! - `{}_CLIENT_SECRET`
-//! - `{}_REDIRECT_URI`
+//! - `{}_REDIRECT_URI`{}
+//!
 //! {}
 //!
 //! And then you can create a client from the environment.
@@ -197,7 +169,8 @@ pub fn generate_docs(spec: &openapiv3::OpenAPI, opts: &crate::Opts) -> Result<St
             get_env_variable_prefix(&opts.name),
             if let Some(add_env_prefix) = &opts.add_env_prefix {
                 format!(
-                    r#"- `{}_CLIENT_ID`
+                    r#"
+//! - `{}_CLIENT_ID`
 //! - `{}_CLIENT_SECRET`
 //! - `{}_REDIRECT_URI`"#,
                     get_env_variable_prefix(add_env_prefix),
@@ -207,6 +180,7 @@ pub fn generate_docs(spec: &openapiv3::OpenAPI, opts: &crate::Opts) -> Result<St
             } else {
                 "".to_string()
             },
+            generate_docs_base_url_env(opts),
             opts.code_package_name(),
         ));
     }
@@ -240,7 +214,8 @@ pub fn generate_docs(spec: &openapiv3::OpenAPI, opts: &crate::Opts) -> Result<St
 //! the client in the environment:
 //!
 //! - `{}_USERNAME`
-//! - `{}_PASSWORD`
+//! - `{}_PASSWORD`{}
+//!
 //! {}
 //!
 //! And then you can create a client from the environment.
@@ -259,7 +234,8 @@ pub fn generate_docs(spec: &openapiv3::OpenAPI, opts: &crate::Opts) -> Result<St
             get_env_variable_prefix(&opts.name),
             if let Some(add_env_prefix) = &opts.add_env_prefix {
                 format!(
-                    r#"- `{}_USERNAME`
+                    r#"
+//! - `{}_USERNAME`
 //! - `{}_PASSWORD`"#,
                     get_env_variable_prefix(add_env_prefix),
                     get_env_variable_prefix(add_env_prefix)
@@ -267,6 +243,7 @@ pub fn generate_docs(spec: &openapiv3::OpenAPI, opts: &crate::Opts) -> Result<St
             } else {
                 "".to_string()
             },
+            generate_docs_base_url_env(opts),
             opts.code_package_name(),
         ));
     }
@@ -297,7 +274,8 @@ pub fn generate_docs(spec: &openapiv3::OpenAPI, opts: &crate::Opts) -> Result<St
 //! Alternatively, the library can search for most of the variables required for
 //! the client in the environment:
 //!
-//! - `{}_API_TOKEN`
+//! - `{}_API_TOKEN`{}
+//!
 //! {}
 //!
 //! And then you can create a client from the environment.
@@ -315,16 +293,44 @@ pub fn generate_docs(spec: &openapiv3::OpenAPI, opts: &crate::Opts) -> Result<St
         get_env_variable_prefix(&opts.name),
         if let Some(add_env_prefix) = &opts.add_env_prefix {
             format!(
-                r#"- `{}_API_TOKEN`"#,
+                r#"
+//! - `{}_API_TOKEN`"#,
                 get_env_variable_prefix(add_env_prefix),
             )
         } else {
             "".to_string()
         },
+        generate_docs_base_url_env(opts),
         opts.code_package_name(),
     ))
 }
 
+/// Generate the README.md of our client library from its main docs.
+pub fn generate_readme(docs: &str, opts: &crate::Opts) -> String {
+    // Add a title to the README.md so it looks nicer in GitHub.
+    format!(
+        "# `{}`\n\n{}",
+        opts.name,
+        docs.replace("//! ", "").replace("//!", "")
+    )
+}
+
+/// Generate the docs for where the client reads the base url from.
+fn generate_docs_base_url_env(opts: &crate::Opts) -> String {
+    format!(
+        r#"The base url is read from the first of these that is set, and defaults to
+//! `{}`:
+//!
+//! - {}"#,
+        opts.trimmed_base_url(),
+        crate::client::get_base_url_env_variable_names(opts)
+            .iter()
+            .map(|name| format!("`{}`", name))
+            .collect::<Vec<_>>()
+            .join("\n//! - "),
+    )
+}
+
 /// Get the prefix of the environment variables.
 pub fn get_env_variable_prefix(name: &str) -> String {
     to_screaming_snake_case(name)
@@ -366,6 +372,11 @@ pub mod date_time_format {{
             }}
         }}
     }}
+
+    /// Format a date-time for a query or a path, the way the API expects it.
+    pub fn format(t: &DateTime<Utc>) -> String {{
+        t.format(FORMAT).to_string()
+    }}
 }}
 
 pub mod nullable_date_time_format {{
@@ -407,3 +418,103 @@ pub mod nullable_date_time_format {{
         format = date_time_format,
     )})
 }
+
+/// Make sure `--date-time-format` is a `chrono` format, the generated crate would
+/// panic formatting a date-time with a broken one.
+pub fn check_date_time_format(format: &str) -> Result<()> {
+    use chrono::format::{Item, StrftimeItems};
+
+    if StrftimeItems::new(format).any(|item| item == Item::Error) {
+        anyhow::bail!(
+            "`--date-time-format` `{}` is not a valid chrono format",
+            format
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    fn readme(spec: serde_json::Value, opts: &crate::Opts) -> String {
+        let spec = crate::load_json_spec(&spec.to_string()).unwrap();
+        super::generate_readme(&super::generate_docs(&spec, opts).unwrap(), opts)
+    }
+
+    #[test]
+    fn test_readme_minimal_spec() {
+        // Nothing but what a spec must have: no description, contact or license.
+        let spec = serde_json::json!({
+            "openapi": "3.0.3",
+            "info": {"title": "Internal", "version": "1.0.0"},
+            "paths": {}
+        });
+        let opts = crate::Opts {
+            name: "internal".to_string(),
+            target_version: "0.1.0".to_string(),
+            ..Default::default()
+        };
+
+        expectorate::assert_contents("tests/readme/minimal.md.gen", &readme(spec, &opts));
+    }
+
+    #[test]
+    fn test_readme_full_spec() {
+        let spec = serde_json::json!({
+            "openapi": "3.0.3",
+            "info": {
+                "title": "Widgets",
+                "description": "The API of the widgets.\n\nIt makes widgets.",
+                "termsOfService": "https://widgets.example.com/terms",
+                "contact": {
+                    "name": "Widgets | Support",
+                    "url": "https://widgets.example.com",
+                    "email": "support@widgets.example.com"
+                },
+                "license": {"name": "Apache 2.0", "url": "https://www.apache.org/licenses/LICENSE-2.0"},
+                "version": "2.1.0"
+            },
+            "paths": {}
+        });
+        let opts = crate::Opts {
+            name: "widgets".to_string(),
+            target_version: "0.2.0".to_string(),
+            description: "A client for the widgets.".to_string(),
+            spec_url: Some("https://widgets.example.com/openapi.json".to_string()),
+            ..Default::default()
+        };
+
+        expectorate::assert_contents("tests/readme/full.md.gen", &readme(spec, &opts));
+    }
+
+    #[test]
+    fn test_readme_empty_contact() {
+        // A contact without any of its fields is no contact.
+        let spec = serde_json::json!({
+            "openapi": "3.0.3",
+            "info": {"title": "Internal", "description": "  ", "contact": {}, "version": "1.0.0"},
+            "paths": {}
+        });
+        let opts = crate::Opts {
+            name: "internal".to_string(),
+            ..Default::default()
+        };
+
+        let readme = readme(spec, &opts);
+        assert!(!readme.contains("## API Details"), "{}", readme);
+        assert!(!readme.contains("### Contact"), "{}", readme);
+        assert!(!readme.contains('|'), "{}", readme);
+    }
+
+    #[test]
+    fn test_check_date_time_format() {
+        assert!(super::check_date_time_format("%Y-%m-%dT%H:%M:%S").is_ok());
+        assert!(super::check_date_time_format("%Y%m%dT%H%M%SZ").is_ok());
+        assert_eq!(
+            super::check_date_time_format("%Y-%m-%Q")
+                .unwrap_err()
+                .to_string(),
+            "`--date-time-format` `%Y-%m-%Q` is not a valid chrono format"
+        );
+    }
+}
@@ -1,6 +1,9 @@
 use anyhow::Result;
 use test_context::{test_context, TestContext as BlockingTestContext};
 
+mod fixtures;
+mod public_api;
+
 struct TestContext {
     tmp_dir: std::path::PathBuf,
 }
@@ -39,7 +42,7 @@ fn test_kittycad_generation(ctx: &mut TestContext) {
         json: false,
         input: ctx.tmp_dir.clone(),
         output: ctx.tmp_dir.clone(),
-        base_url: "https://api.kittycad.io".parse().unwrap(),
+        base_url: Some("https://api.kittycad.io".parse().unwrap()),
         name: "kittycad".to_string(),
         target_version: "1.0.0".to_string(),
         description: "KittyCAD is a tool for generating 3D models of cats.".to_string(),
@@ -89,7 +92,7 @@ fn test_github_generation(ctx: &mut TestContext) {
         json: false,
         input: ctx.tmp_dir.clone(),
         output: ctx.tmp_dir.clone(),
-        base_url: "https://api.github.com".parse().unwrap(),
+        base_url: Some("https://api.github.com".parse().unwrap()),
         name: "octorust".to_string(),
         target_version: "1.0.0".to_string(),
         description: "GitHub is where we push our code and you do too!".to_string(),
@@ -122,7 +125,7 @@ fn test_oxide_generation(ctx: &mut TestContext) {
         json: false,
         input: ctx.tmp_dir.clone(),
         output: ctx.tmp_dir.clone(),
-        base_url: "https://api.oxide.computer".parse().unwrap(),
+        base_url: Some("https://api.oxide.computer".parse().unwrap()),
         name: "oxide".to_string(),
         target_version: "1.0.0".to_string(),
         description: "Oxide builds computers.".to_string(),
@@ -137,8 +140,12 @@ fn test_oxide_generation(ctx: &mut TestContext) {
     // Load our spec.
     let spec = crate::load_json_spec(include_str!("../tests/oxide.json")).unwrap();
 
-    // Move our test file to our output directory.
-    let test_file = include_str!("../tests/library/oxide.tests.rs");
+    // Move our test file to our output directory, with the test of the enums.
+    let test_file = format!(
+        "{}{}",
+        include_str!("../tests/library/oxide.tests.rs"),
+        enum_wire_values_test(&spec).unwrap()
+    );
     // Write our temporary file.
     let test_file_path = ctx.tmp_dir.join("src").join("tests.rs");
     std::fs::write(test_file_path, test_file).unwrap();
@@ -158,7 +165,7 @@ fn test_remote_generation(ctx: &mut TestContext) {
         json: false,
         input: ctx.tmp_dir.clone(),
         output: ctx.tmp_dir.clone(),
-        base_url: "https://api.remote.com".parse().unwrap(),
+        base_url: Some("https://api.remote.com".parse().unwrap()),
         name: "remote-api".to_string(),
         target_version: "1.0.0".to_string(),
         description: "HR crap!".to_string(),
@@ -192,7 +199,7 @@ fn test_gusto_generation(ctx: &mut TestContext) {
         json: false,
         input: ctx.tmp_dir.clone(),
         output: ctx.tmp_dir.clone(),
-        base_url: "https://api.gusto.com".parse().unwrap(),
+        base_url: Some("https://api.gusto.com".parse().unwrap()),
         name: "gusto-api".to_string(),
         target_version: "1.0.0".to_string(),
         description: "HR crap!".to_string(),
@@ -228,7 +235,7 @@ fn test_ramp_generation(ctx: &mut TestContext) {
         json: false,
         input: ctx.tmp_dir.clone(),
         output: ctx.tmp_dir.clone(),
-        base_url: "https://api.ramp.com".parse().unwrap(),
+        base_url: Some("https://api.ramp.com".parse().unwrap()),
         name: "ramp-api".to_string(),
         target_version: "1.0.0".to_string(),
         description: " crap!".to_string(),
@@ -267,7 +274,7 @@ fn test_commonroom_generation(ctx: &mut TestContext) {
         json: false,
         input: ctx.tmp_dir.clone(),
         output: ctx.tmp_dir.clone(),
-        base_url: "https://api.commonroom.io/community/v1".parse().unwrap(),
+        base_url: Some("https://api.commonroom.io/community/v1".parse().unwrap()),
         name: "commonroom-api".to_string(),
         target_version: "1.0.0".to_string(),
         description: " crap!".to_string(),
@@ -294,32 +301,1406 @@ fn test_commonroom_generation(ctx: &mut TestContext) {
     run_cargo_test(&opts).unwrap();
 }
 
-fn run_cargo_test(opts: &crate::Opts) -> Result<()> {
-    log::info!("Running `cargo test`...");
+#[test_context(TestContext)]
+#[test]
+fn test_case_collision_generation(ctx: &mut TestContext) {
+    let opts = crate::Opts {
+        debug: true,
+        json: false,
+        input: ctx.tmp_dir.clone(),
+        output: ctx.tmp_dir.clone(),
+        base_url: Some("https://api.example.com".parse().unwrap()),
+        name: "case-collision".to_string(),
+        target_version: "1.0.0".to_string(),
+        description: "Schemas that only differ by case.".to_string(),
+        ..Default::default()
+    };
 
-    // Shell out and run cargo clippy on the output directory.
-    let output = if opts.output.display().to_string() == "." {
-        "".to_string()
-    } else {
-        opts.output.display().to_string()
+    // Load our spec.
+    let spec = crate::load_json_spec(include_str!("../tests/case-collision.json")).unwrap();
+
+    // Move our test file to our output directory.
+    let test_file = include_str!("../tests/library/case-collision.tests.rs");
+    // Write our temporary file.
+    let test_file_path = ctx.tmp_dir.join("src").join("tests.rs");
+    std::fs::write(test_file_path, test_file).unwrap();
+
+    // Generate the library.
+    crate::generate(&spec, &opts).unwrap();
+
+    // Run tests.
+    run_cargo_test(&opts).unwrap();
+}
+
+#[test_context(TestContext)]
+#[test]
+fn test_no_operation_ids_generation(ctx: &mut TestContext) {
+    let opts = crate::Opts {
+        debug: true,
+        json: false,
+        input: ctx.tmp_dir.clone(),
+        output: ctx.tmp_dir.clone(),
+        base_url: Some("https://api.example.com".parse().unwrap()),
+        name: "no-operation-ids".to_string(),
+        target_version: "1.0.0".to_string(),
+        description: "Operations without an operation id.".to_string(),
+        ..Default::default()
     };
 
-    let mut cmd = std::process::Command::new("cargo");
-    cmd.args(["test", "--quiet"])
-        .current_dir(output)
-        // So that we can run fresh and not fail.
-        .env("EXPECTORATE", "overwrite");
+    // Load our spec.
+    let spec = crate::load_json_spec(include_str!("../tests/no-operation-ids.json")).unwrap();
 
-    let output = cmd.output()?;
-    if !output.status.success() {
-        let stderr = String::from_utf8(output.stderr).unwrap();
-        let stdout = String::from_utf8(output.stdout).unwrap();
-        eprintln!("Stderr:");
-        eprintln!("{stderr}");
-        eprintln!("Stdout:");
-        eprintln!("{stdout}");
-        anyhow::bail!("cargo test failed, see above");
+    // Move our test file to our output directory.
+    let test_file = include_str!("../tests/library/no-operation-ids.tests.rs");
+    // Write our temporary file.
+    let test_file_path = ctx.tmp_dir.join("src").join("tests.rs");
+    std::fs::write(test_file_path, test_file).unwrap();
+
+    // Generate the library.
+    crate::generate(&spec, &opts).unwrap();
+
+    // The names we made up are in the patch, for the authors of the spec.
+    let patch =
+        std::fs::read_to_string(ctx.tmp_dir.join("no-operation-ids.rs.patch.json")).unwrap();
+    assert!(patch.contains("x-rust-synthesized-operation-ids"));
+    assert!(patch.contains(r#""GET /users/": "get_users_2""#));
+
+    // Run tests.
+    run_cargo_test(&opts).unwrap();
+}
+
+#[test_context(TestContext)]
+#[test]
+fn test_reserved_tags_generation(ctx: &mut TestContext) {
+    let opts = crate::Opts {
+        debug: true,
+        json: false,
+        input: ctx.tmp_dir.clone(),
+        output: ctx.tmp_dir.clone(),
+        base_url: Some("https://api.example.com".parse().unwrap()),
+        name: "reserved-tags".to_string(),
+        target_version: "1.0.0".to_string(),
+        description: "Tags named like the modules of the crate.".to_string(),
+        ..Default::default()
+    };
+
+    // Load our spec.
+    let spec = crate::load_json_spec(include_str!("../tests/reserved-tags.json")).unwrap();
+
+    // Move our test file to our output directory.
+    let test_file = include_str!("../tests/library/reserved-tags.tests.rs");
+    // Write our temporary file.
+    let test_file_path = ctx.tmp_dir.join("src").join("tests.rs");
+    std::fs::write(test_file_path, test_file).unwrap();
+
+    // Generate the library.
+    crate::generate(&spec, &opts).unwrap();
+
+    // The renamed tags are in the docs of their modules and in the patch.
+    let src = ctx.tmp_dir.join("src");
+    assert!(src.join("types_api.rs").exists());
+    assert!(src.join("client_api.rs").exists());
+    let lib = std::fs::read_to_string(src.join("lib.rs")).unwrap();
+    assert!(lib.contains("/// The `types` tag, renamed since the crate keeps `types` for itself."));
+    assert_eq!(lib.matches("pub mod types;").count(), 1);
+    let patch = std::fs::read_to_string(ctx.tmp_dir.join("reserved-tags.rs.patch.json")).unwrap();
+    assert!(patch.contains("x-rust-renamed-tags"));
+    assert!(patch.contains(r#""client": "client_api""#));
+
+    // Run tests.
+    run_cargo_test(&opts).unwrap();
+}
+
+#[test]
+fn test_clean_tag_name() {
+    assert_eq!(crate::clean_tag_name("API Calls"), "api_calls");
+    assert_eq!(crate::clean_tag_name("oauth 2"), "oauth2");
+
+    // The reserved names get a suffix, whatever their case.
+    assert_eq!(crate::clean_tag_name("types"), "types_api");
+    assert_eq!(crate::clean_tag_name("Client"), "client_api");
+    assert_eq!(crate::clean_tag_name("utils"), "utils_api");
+    assert_eq!(crate::clean_tag_name("types-of-things"), "types_of_things");
+}
+
+#[test]
+fn test_cargo_toml_without_description() {
+    let spec = crate::load_json_spec(
+        &serde_json::json!({
+            "openapi": "3.0.3",
+            "info": {"title": "The \"Internal\" API", "version": "1.0.0"},
+            "paths": {}
+        })
+        .to_string(),
+    )
+    .unwrap();
+
+    for (description, types_only, expected) in [
+        ("", false, r#"The "Internal" API"#),
+        ("  ", true, r#"The "Internal" API"#),
+        ("Widgets, \"quoted\"", false, r#"Widgets, "quoted""#),
+    ] {
+        let opts = crate::Opts {
+            name: "internal".to_string(),
+            target_version: "0.1.0".to_string(),
+            description: description.to_string(),
+            types_only,
+            ..Default::default()
+        };
+        let toml: toml::Value = crate::generate_cargo_toml(&spec, &opts, None)
+            .unwrap()
+            .parse()
+            .unwrap();
+
+        // crates.io wants both a description and a license.
+        assert_eq!(toml["package"]["description"].as_str(), Some(expected));
+        assert_eq!(toml["package"]["license"].as_str(), Some("MIT"));
     }
 
-    Ok(())
+    // Not even a title.
+    let mut spec = spec;
+    spec.info.title = String::new();
+    let opts = crate::Opts {
+        name: "internal".to_string(),
+        ..Default::default()
+    };
+    assert_eq!(
+        crate::get_crate_description(&spec, &opts),
+        "A generated client for the internal API."
+    );
+}
+
+#[test_context(TestContext)]
+#[test]
+fn test_descriptions_generation(ctx: &mut TestContext) {
+    let opts = crate::Opts {
+        debug: true,
+        json: false,
+        input: ctx.tmp_dir.clone(),
+        output: ctx.tmp_dir.clone(),
+        base_url: Some("https://api.example.com".parse().unwrap()),
+        name: "descriptions".to_string(),
+        target_version: "1.0.0".to_string(),
+        description: "Descriptions that break rustdoc.".to_string(),
+        spec_url: Some("https://example.com/descriptions.json".to_string()),
+        max_description_length: Some(2000),
+        ..Default::default()
+    };
+
+    // Load our spec.
+    let spec = crate::load_json_spec(include_str!("../tests/descriptions.json")).unwrap();
+
+    // Move our test file to our output directory.
+    let test_file = include_str!("../tests/library/descriptions.tests.rs");
+    // Write our temporary file.
+    let test_file_path = ctx.tmp_dir.join("src").join("tests.rs");
+    std::fs::write(test_file_path, test_file).unwrap();
+
+    // Generate the library.
+    crate::generate(&spec, &opts).unwrap();
+
+    let types = std::fs::read_to_string(ctx.tmp_dir.join("src").join("types.rs")).unwrap();
+    assert!(types.contains(
+        "*Truncated, the full description is in [the spec](https://example.com/descriptions.json).*"
+    ));
+    assert!(!types.contains("Paragraph 40"));
+
+    // Run tests, none of the descriptions is a doctest and every link resolves.
+    run_cargo_test(&opts).unwrap();
+    run_cargo_doc(&opts).unwrap();
+}
+
+#[test_context(TestContext)]
+#[test]
+fn test_datetime_generation(ctx: &mut TestContext) {
+    let opts = crate::Opts {
+        debug: true,
+        json: false,
+        input: ctx.tmp_dir.clone(),
+        output: ctx.tmp_dir.clone(),
+        base_url: Some("https://api.example.com".parse().unwrap()),
+        name: "datetime".to_string(),
+        target_version: "1.0.0".to_string(),
+        description: "Every date, time and id format we support.".to_string(),
+        ..Default::default()
+    };
+
+    // Load our spec.
+    let spec = crate::load_json_spec(include_str!("../tests/datetime.json")).unwrap();
+
+    // Move our test file to our output directory.
+    let test_file = include_str!("../tests/library/datetime.tests.rs");
+    // Write our temporary file.
+    let test_file_path = ctx.tmp_dir.join("src").join("tests.rs");
+    std::fs::write(test_file_path, test_file).unwrap();
+
+    // Generate the library.
+    crate::generate(&spec, &opts).unwrap();
+
+    // Run tests.
+    run_cargo_test(&opts).unwrap();
+}
+
+#[test_context(TestContext)]
+#[test]
+fn test_datetime_generation_with_format(ctx: &mut TestContext) {
+    let opts = crate::Opts {
+        debug: true,
+        json: false,
+        input: ctx.tmp_dir.clone(),
+        output: ctx.tmp_dir.clone(),
+        base_url: Some("https://api.example.com".parse().unwrap()),
+        name: "datetime".to_string(),
+        target_version: "1.0.0".to_string(),
+        description: "Every date, time and id format we support.".to_string(),
+        date_time_format: Some("%Y%m%dT%H%M%SZ".to_string()),
+        ..Default::default()
+    };
+
+    // Load our spec.
+    let spec = crate::load_json_spec(include_str!("../tests/datetime.json")).unwrap();
+
+    // A broken format fails the generation, not the requests.
+    let err = crate::generate(
+        &spec,
+        &crate::Opts {
+            date_time_format: Some("%Y-%m-%Q".to_string()),
+            ..opts.clone()
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "`--date-time-format` `%Y-%m-%Q` is not a valid chrono format"
+    );
+
+    // Move our test file to our output directory.
+    let test_file = include_str!("../tests/library/datetime-format.tests.rs");
+    // Write our temporary file.
+    let test_file_path = ctx.tmp_dir.join("src").join("tests.rs");
+    std::fs::write(test_file_path, test_file).unwrap();
+
+    // Generate the library.
+    crate::generate(&spec, &opts).unwrap();
+
+    // Run tests.
+    run_cargo_test(&opts).unwrap();
+}
+
+#[test_context(TestContext)]
+#[test]
+fn test_rename_shims_generation(ctx: &mut TestContext) {
+    let opts = crate::Opts {
+        debug: true,
+        json: false,
+        input: ctx.tmp_dir.clone(),
+        output: ctx.tmp_dir.clone(),
+        base_url: Some("https://api.example.com".parse().unwrap()),
+        name: "compat".to_string(),
+        target_version: "1.0.0".to_string(),
+        description: "An API that renamed some of its schemas.".to_string(),
+        ..Default::default()
+    };
+
+    // The first generation has the old names.
+    let before = crate::load_json_spec(include_str!("../tests/compat/before.json")).unwrap();
+    crate::generate(&before, &opts).unwrap();
+    let manifest = ctx.tmp_dir.join("compat.rs.manifest.json");
+    assert!(manifest.exists());
+
+    // Move our test file to our output directory.
+    let test_file = include_str!("../tests/library/compat.tests.rs");
+    // Write our temporary file.
+    let test_file_path = ctx.tmp_dir.join("src").join("tests.rs");
+    std::fs::write(test_file_path, test_file).unwrap();
+
+    // The next one reads the manifest of the first before it writes its own.
+    let after = crate::load_json_spec(include_str!("../tests/compat/after.json")).unwrap();
+    let opts = crate::Opts {
+        target_version: "1.1.0".to_string(),
+        rename_shims: Some(manifest),
+        ..opts
+    };
+    crate::generate(&after, &opts).unwrap();
+
+    let lib = std::fs::read_to_string(ctx.tmp_dir.join("src").join("lib.rs")).unwrap();
+    assert!(lib.contains("\npub mod compat;\n"));
+    let compat = std::fs::read_to_string(ctx.tmp_dir.join("src").join("compat.rs")).unwrap();
+    assert!(compat.contains("pub type Widget = crate::types::Gadget;"));
+    assert!(compat.contains("pub type Part = crate::types::Component;"));
+
+    // Run tests, the old names still work.
+    run_cargo_test(&opts).unwrap();
+}
+
+#[test_context(TestContext)]
+#[test]
+fn test_oauth_scopes_generation(ctx: &mut TestContext) {
+    let opts = crate::Opts {
+        debug: true,
+        json: false,
+        input: ctx.tmp_dir.clone(),
+        output: ctx.tmp_dir.clone(),
+        base_url: Some("https://api.example.com".parse().unwrap()),
+        name: "oauth-scopes".to_string(),
+        target_version: "1.0.0".to_string(),
+        description: "An API with scoped OAuth 2.0 access tokens.".to_string(),
+        token_endpoint: Some("https://example.com/oauth/token".parse().unwrap()),
+        user_consent_endpoint: Some("https://example.com/oauth/authorize".parse().unwrap()),
+        ..Default::default()
+    };
+
+    // Load our spec.
+    let spec =
+        crate::load_json_spec(include_str!("../tests/types/input/oauth-scopes.json")).unwrap();
+
+    // Move our test file to our output directory.
+    let test_file = include_str!("../tests/library/oauth-scopes.tests.rs");
+    // Write our temporary file.
+    let test_file_path = ctx.tmp_dir.join("src").join("tests.rs");
+    std::fs::write(test_file_path, test_file).unwrap();
+
+    // Generate the library.
+    crate::generate(&spec, &opts).unwrap();
+
+    // Run tests.
+    run_cargo_test(&opts).unwrap();
+}
+
+#[test_context(TestContext)]
+#[test]
+fn test_opaque_types_generation(ctx: &mut TestContext) {
+    let opts = crate::Opts {
+        debug: true,
+        json: false,
+        input: ctx.tmp_dir.clone(),
+        output: ctx.tmp_dir.clone(),
+        base_url: Some("https://api.example.com".parse().unwrap()),
+        name: "opaque".to_string(),
+        target_version: "1.0.0".to_string(),
+        description: "An API passing the debug dumps of its engine through.".to_string(),
+        opaque_types: vec!["Engine*".to_string()],
+        ..Default::default()
+    };
+
+    // Load our spec.
+    let spec = crate::load_json_spec(include_str!("../tests/types/input/opaque.json")).unwrap();
+
+    // Move our test file to our output directory.
+    let test_file = include_str!("../tests/library/opaque.tests.rs");
+    // Write our temporary file.
+    let test_file_path = ctx.tmp_dir.join("src").join("tests.rs");
+    std::fs::write(test_file_path, test_file).unwrap();
+
+    // Generate the library.
+    crate::generate(&spec, &opts).unwrap();
+
+    // What was left out is in the report.
+    let report: crate::opaque::Report =
+        serde_json::from_str(&std::fs::read_to_string(ctx.tmp_dir.join("opaque.json")).unwrap())
+            .unwrap();
+    assert_eq!(report.opaque, vec!["EngineDump".to_string()]);
+    assert_eq!(
+        report.unreachable,
+        vec!["SceneGraph".to_string(), "SceneNode".to_string()]
+    );
+    assert_eq!(report.types_avoided, 5);
+    let types = std::fs::read_to_string(ctx.tmp_dir.join("src").join("types.rs")).unwrap();
+    assert!(!types.contains("pub struct EngineDump"));
+    assert!(!types.contains("pub struct SceneNode"));
+
+    // Run tests.
+    run_cargo_test(&opts).unwrap();
+}
+
+#[test_context(TestContext)]
+#[test]
+fn test_datetime_generation_with_time(ctx: &mut TestContext) {
+    let opts = crate::Opts {
+        debug: true,
+        json: false,
+        input: ctx.tmp_dir.clone(),
+        output: ctx.tmp_dir.clone(),
+        base_url: Some("https://api.example.com".parse().unwrap()),
+        name: "datetime".to_string(),
+        target_version: "1.0.0".to_string(),
+        description: "Every date, time and id format we support.".to_string(),
+        datetime_crate: crate::DateTimeCrate::Time,
+        ..Default::default()
+    };
+
+    // Load our spec.
+    let spec = crate::load_json_spec(include_str!("../tests/datetime.json")).unwrap();
+
+    // Move our test file to our output directory.
+    let test_file = include_str!("../tests/library/datetime.tests.rs");
+    // Write our temporary file.
+    let test_file_path = ctx.tmp_dir.join("src").join("tests.rs");
+    std::fs::write(test_file_path, test_file).unwrap();
+
+    // Generate the library.
+    crate::generate(&spec, &opts).unwrap();
+
+    // The generated crate should not depend on chrono at all.
+    let cargo_toml = std::fs::read_to_string(ctx.tmp_dir.join("Cargo.toml")).unwrap();
+    assert!(!cargo_toml.contains("chrono"));
+
+    // Run tests.
+    run_cargo_test(&opts).unwrap();
+}
+
+#[test_context(TestContext)]
+#[test]
+fn test_mock_generation(ctx: &mut TestContext) {
+    let opts = crate::Opts {
+        debug: true,
+        json: false,
+        input: ctx.tmp_dir.clone(),
+        output: ctx.tmp_dir.clone(),
+        base_url: None,
+        name: "mock".to_string(),
+        target_version: "1.0.0".to_string(),
+        description: "A library we run against a mock server.".to_string(),
+        base_url_env: vec!["ACME_API_BASE_URL".to_string(), "ACME_HOST".to_string()],
+        unique_items_as_sets: true,
+        validate_requests: true,
+        interop_map: Some(
+            std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/interop.toml"),
+        ),
+        update_helpers: Some(
+            std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/update-helpers.toml"),
+        ),
+        session_config: Some(
+            std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/session.toml"),
+        ),
+        download_responses: true,
+        case_insensitive_enums: true,
+        secret_fields: true,
+        generate_smoke_tests: true,
+        borrowed_types: vec!["Thing".to_string(), "ThingResultsPage".to_string()],
+        nested_accessors: Some(3),
+        no_std_core: true,
+        ..Default::default()
+    };
+
+    // Load our spec.
+    let spec = crate::load_json_spec(include_str!("../tests/mock.json")).unwrap();
+
+    // Count the functions that send a request, the table of operations must have
+    // every one of them.
+    let mut type_space = crate::types::generate_types(&spec, opts.clone()).unwrap();
+    let (files, _, _) = crate::functions::generate_files(&mut type_space, &opts).unwrap();
+    let functions: usize = files
+        .values()
+        .map(|file| file.to_string().matches("pub async fn").count())
+        .sum();
+
+    // Move our test file to our output directory, with the test of the enums.
+    let test_file = format!(
+        "{}\n#[test]\nfn test_operations_table_is_complete() {{\n    assert_eq!(crate::operations().len(), {});\n}}\n{}",
+        include_str!("../tests/library/mock.tests.rs"),
+        functions,
+        enum_wire_values_test(&spec).unwrap()
+    );
+    // Write our temporary file.
+    let test_file_path = ctx.tmp_dir.join("src").join("tests.rs");
+    std::fs::write(test_file_path, test_file).unwrap();
+
+    // Generate the library.
+    crate::generate(&spec, &opts).unwrap();
+
+    // Run tests, with all the optional runtime features.
+    run_cargo_test_with_features(
+        &opts,
+        &[
+            "compression",
+            "interop",
+            "metrics",
+            "record",
+            "registry",
+            "websocket",
+        ],
+    )
+    .unwrap();
+
+    // And with the lenient deserializers, the strict ones are the default.
+    run_cargo_test_with_features(&opts, &["lenient"]).unwrap();
+}
+
+#[test_context(TestContext)]
+#[test]
+fn test_display_string_params_generation(ctx: &mut TestContext) {
+    let opts = crate::Opts {
+        debug: true,
+        json: false,
+        input: ctx.tmp_dir.clone(),
+        output: ctx.tmp_dir.clone(),
+        base_url: Some("https://api.example.com".parse().unwrap()),
+        name: "mock".to_string(),
+        target_version: "1.0.0".to_string(),
+        description: "A library taking its strings as they are.".to_string(),
+        display_string_params: true,
+        ..Default::default()
+    };
+
+    // Load our spec.
+    let spec = crate::load_json_spec(include_str!("../tests/mock.json")).unwrap();
+
+    // Move our test file to our output directory.
+    let test_file = include_str!("../tests/library/display-string-params.tests.rs");
+    // Write our temporary file.
+    let test_file_path = ctx.tmp_dir.join("src").join("tests.rs");
+    std::fs::write(test_file_path, test_file).unwrap();
+
+    // Generate the library.
+    crate::generate(&spec, &opts).unwrap();
+
+    // Run tests, the examples in the docs pass their strings as they are too.
+    run_cargo_test_with_features(&opts, &["websocket"]).unwrap();
+}
+
+#[test_context(TestContext)]
+#[test]
+fn test_split_types_generation(ctx: &mut TestContext) {
+    let opts = crate::Opts {
+        input: ctx.tmp_dir.clone(),
+        output: ctx.tmp_dir.clone(),
+        base_url: Some("https://api.example.com".parse().unwrap()),
+        name: "mock".to_string(),
+        target_version: "1.0.0".to_string(),
+        description: "A library we run against a mock server.".to_string(),
+        split_types: crate::TypesSplit::Tag,
+        ..Default::default()
+    };
+    let spec = crate::load_json_spec(include_str!("../tests/mock.json")).unwrap();
+    let src = ctx.tmp_dir.join("src");
+
+    crate::generate(&spec, &opts).unwrap();
+    assert!(!src.join("types.rs").exists());
+    assert!(src.join("types").join("mod.rs").exists());
+    assert!(src.join("types").join("things.rs").exists());
+
+    // Going back to a single file removes the directory, so the module is not
+    // ambiguous.
+    crate::generate(
+        &spec,
+        &crate::Opts {
+            split_types: crate::TypesSplit::None,
+            ..opts.clone()
+        },
+    )
+    .unwrap();
+    assert!(src.join("types.rs").exists());
+    assert!(!src.join("types").exists());
+}
+
+#[test_context(TestContext)]
+#[test]
+fn test_include_module_generation(ctx: &mut TestContext) {
+    let hooks = ctx.tmp_dir.join("hooks");
+    std::fs::create_dir_all(&hooks).unwrap();
+    std::fs::write(
+        hooks.join("convert.rs"),
+        "impl From<crate::types::Thing> for String {\n    fn from(thing: crate::types::Thing) -> Self {\n        thing.id\n    }\n}\n",
+    )
+    .unwrap();
+    std::fs::write(hooks.join("ext.rs"), "pub fn answer() -> u8 {\n    42\n}\n").unwrap();
+    std::fs::write(
+        hooks.join("types.rs"),
+        "impl Thing {\n    pub fn is_good(&self) -> bool {\n        self.id == \"good\"\n    }\n}\n",
+    )
+    .unwrap();
+    let opts = crate::Opts {
+        input: ctx.tmp_dir.clone(),
+        output: ctx.tmp_dir.clone(),
+        base_url: Some("https://api.example.com".parse().unwrap()),
+        name: "mock".to_string(),
+        target_version: "1.0.0".to_string(),
+        description: "A library we run against a mock server.".to_string(),
+        include_modules: vec![format!("convert={}", hooks.join("convert.rs").display())],
+        include_pub_modules: vec![format!("ext={}", hooks.join("ext.rs").display())],
+        append_to_types: Some(hooks.join("types.rs")),
+        ..Default::default()
+    };
+    let spec = crate::load_json_spec(include_str!("../tests/mock.json")).unwrap();
+    let src = ctx.tmp_dir.join("src");
+
+    // The modules survive the generations, and the types get the code every time.
+    for _ in 0..2 {
+        crate::generate(&spec, &opts).unwrap();
+        assert!(src.join("convert.rs").exists());
+        assert!(src.join("ext.rs").exists());
+        let lib = std::fs::read_to_string(src.join("lib.rs")).unwrap();
+        assert!(lib.contains("\nmod convert;\n"), "{}", lib);
+        assert!(lib.contains("\npub mod ext;\n"), "{}", lib);
+        let types = std::fs::read_to_string(src.join("types.rs")).unwrap();
+        assert_eq!(types.matches("pub fn is_good(&self)").count(), 1);
+    }
+
+    // A module we no longer include is deleted like any other file.
+    crate::generate(
+        &spec,
+        &crate::Opts {
+            include_pub_modules: vec![],
+            ..opts.clone()
+        },
+    )
+    .unwrap();
+    assert!(src.join("convert.rs").exists());
+    assert!(!src.join("ext.rs").exists());
+
+    // A file that does not parse fails before we write anything.
+    std::fs::write(hooks.join("types.rs"), "impl Thing {").unwrap();
+    let err = crate::generate(&spec, &opts).unwrap_err().to_string();
+    assert!(err.contains("does not parse"), "{}", err);
+}
+
+#[test_context(TestContext)]
+#[test]
+fn test_kittycad_types_only_generation(ctx: &mut TestContext) {
+    let opts = crate::Opts {
+        debug: true,
+        json: false,
+        input: ctx.tmp_dir.clone(),
+        output: ctx.tmp_dir.clone(),
+        base_url: Some("https://api.kittycad.io".parse().unwrap()),
+        name: "kittycad-types".to_string(),
+        target_version: "1.0.0".to_string(),
+        description: "The types of the KittyCAD API.".to_string(),
+        types_only: true,
+        ..Default::default()
+    };
+
+    // Load our spec.
+    let spec = crate::load_json_spec(include_str!("../../spec.json")).unwrap();
+
+    // Generate the library.
+    crate::generate(&spec, &opts).unwrap();
+
+    // Only the types, none of the client.
+    let cargo_toml = std::fs::read_to_string(ctx.tmp_dir.join("Cargo.toml")).unwrap();
+    for dependency in ["reqwest", "clap", "tokio", "requests"] {
+        assert!(!cargo_toml.contains(dependency), "{}", dependency);
+    }
+    let src = ctx.tmp_dir.join("src");
+    let mut files = std::fs::read_dir(&src)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect::<Vec<_>>();
+    files.sort();
+    assert_eq!(files, ["lib.rs", "methods.rs", "tests.rs", "types.rs"]);
+    let types = std::fs::read_to_string(src.join("types.rs")).unwrap();
+    assert!(!types.contains("feature = \"requests\""));
+    assert!(!types.contains("clap"));
+
+    // Check it builds with the reduced dependencies, with every feature.
+    run_cargo_check(&opts).unwrap();
+}
+
+#[test_context(TestContext)]
+#[test]
+fn test_kittycad_strict_lints_generation(ctx: &mut TestContext) {
+    let opts = crate::Opts {
+        debug: true,
+        json: false,
+        input: ctx.tmp_dir.clone(),
+        output: ctx.tmp_dir.clone(),
+        base_url: Some("https://api.kittycad.io".parse().unwrap()),
+        name: "kittycad".to_string(),
+        target_version: "1.0.0".to_string(),
+        description: "KittyCAD is a tool for generating 3D models of cats.".to_string(),
+        strict_lints: true,
+        ..Default::default()
+    };
+
+    // Load our spec.
+    let spec = crate::load_json_spec(include_str!("../../spec.json")).unwrap();
+
+    // Generate the library.
+    crate::generate(&spec, &opts).unwrap();
+
+    let src = ctx.tmp_dir.join("src");
+    let lib = std::fs::read_to_string(src.join("lib.rs")).unwrap();
+    assert!(lib.contains("#![forbid(unsafe_code)]\n#![deny(missing_docs)]"));
+    assert!(!lib.contains("#![allow(missing_docs)]"));
+
+    // What the spec does not describe gets docs made up from its name.
+    let types = std::fs::read_to_string(src.join("types.rs")).unwrap();
+    assert!(types.contains(r#"#[doc = "Variant `none` of `AnnotationLineEnd`."]"#));
+    assert!(types.contains(r#"#[doc = "Field `count` of `ApiCallQueryGroup`."]"#));
+    let users = std::fs::read_to_string(src.join("users.rs")).unwrap();
+    assert!(users.contains(r#"#[doc = "The client the operations are sent with."]"#));
+
+    // The docs build, and every public item has some.
+    run_cargo_strict_lints(&opts).unwrap();
+}
+
+#[test_context(TestContext)]
+#[test]
+fn test_kittycad_no_std_core_generation(ctx: &mut TestContext) {
+    let opts = crate::Opts {
+        debug: true,
+        json: false,
+        input: ctx.tmp_dir.clone(),
+        output: ctx.tmp_dir.clone(),
+        base_url: Some("https://api.kittycad.io".parse().unwrap()),
+        name: "kittycad-types".to_string(),
+        target_version: "1.0.0".to_string(),
+        description: "The types of the KittyCAD API.".to_string(),
+        types_only: true,
+        no_std_core: true,
+        ..Default::default()
+    };
+
+    // Load our spec.
+    let spec = crate::load_json_spec(include_str!("../../spec.json")).unwrap();
+
+    // Generate the library.
+    crate::generate(&spec, &opts).unwrap();
+
+    // The simple types are in, the ones with dates or UUIDs are not.
+    let core = std::fs::read_to_string(ctx.tmp_dir.join("src/types/core.rs")).unwrap();
+    assert!(core.contains("pub struct Point3D {"));
+    assert!(core.contains("pub enum UnitLength {"));
+    for name in ["ApiCallWithPrice", "UnitLengthConversion", "User"] {
+        assert!(
+            !core.contains(&format!("pub struct {} {{", name)),
+            "{}",
+            name
+        );
+    }
+    assert!(!core.contains("std::"), "{}", core);
+
+    // The std crate has it as `types::core`.
+    run_cargo_check(&opts).unwrap();
+
+    // And a `no_std` crate builds with it on its own.
+    let no_std = ctx.tmp_dir.join("no-std");
+    std::fs::create_dir_all(no_std.join("src")).unwrap();
+    std::fs::write(
+        no_std.join("Cargo.toml"),
+        r#"[package]
+name = "kittycad-no-std"
+version = "0.1.0"
+edition = "2021"
+
+[dependencies]
+serde = { version = "1", default-features = false, features = ["alloc", "derive"] }
+
+[workspace]
+"#,
+    )
+    .unwrap();
+    std::fs::write(
+        no_std.join("src/lib.rs"),
+        "#![no_std]\n\n#[path = \"../../src/types/core.rs\"]\npub mod types;\n",
+    )
+    .unwrap();
+    run_cargo_check(&crate::Opts {
+        output: no_std,
+        ..opts
+    })
+    .unwrap();
+}
+
+#[test_context(TestContext)]
+#[test]
+fn test_kittycad_conformance_tests_generation(ctx: &mut TestContext) {
+    let opts = crate::Opts {
+        debug: true,
+        json: false,
+        input: ctx.tmp_dir.clone(),
+        output: ctx.tmp_dir.clone(),
+        base_url: Some("https://api.kittycad.io".parse().unwrap()),
+        name: "kittycad-types".to_string(),
+        target_version: "1.0.0".to_string(),
+        description: "The types of the KittyCAD API.".to_string(),
+        types_only: true,
+        generate_conformance_tests: true,
+        ..Default::default()
+    };
+
+    // The spec has no examples yet, so we give it one of every kind.
+    let mut spec: serde_json::Value =
+        serde_json::from_str(include_str!("../../spec.json")).unwrap();
+    let schemas = &mut spec["components"]["schemas"];
+    schemas["Point3d"]["example"] = serde_json::json!({"x": 1.0, "y": 2.5, "z": -3});
+    schemas["UnitLength"]["example"] = serde_json::json!("cm");
+    spec["paths"]["/unit/conversion/length/{input_unit}/{output_unit}"]["get"]["responses"]
+        ["200"]["content"]["application/json"]["example"] = serde_json::json!({
+        "id": "4ad3c6a2-7b5f-4b3c-9d8e-3a1e5f2b6c7d",
+        "user_id": "9c1e2b3a-4d5f-4e6a-8b7c-1d2e3f4a5b6c",
+        "created_at": "2024-08-20T10:00:00.000+00:00",
+        "updated_at": "2024-08-20T10:00:01Z",
+        "completed_at": "2024-08-20T10:00:01Z",
+        "input": 3,
+        "input_unit": "ft",
+        "output": 0.9144,
+        "output_unit": "m",
+        "status": "completed"
+    });
+    spec["paths"]["/user"]["put"]["requestBody"]["content"]["application/json"]["examples"] =
+        serde_json::json!({"minimal": {"$ref": "#/components/examples/MinimalUser"}});
+    spec["components"]["examples"] = serde_json::json!({
+        "MinimalUser": {"value": {"image": "https://example.com/me.png", "company": "KittyCAD"}}
+    });
+    let spec = crate::load_json_spec(&spec.to_string()).unwrap();
+
+    // The enums display as their values on the wire too.
+    std::fs::write(
+        ctx.tmp_dir.join("src").join("tests.rs"),
+        enum_wire_values_test(&spec).unwrap(),
+    )
+    .unwrap();
+
+    // Generate the library.
+    crate::generate(&spec, &opts).unwrap();
+
+    let tests =
+        std::fs::read_to_string(ctx.tmp_dir.join("src").join("conformance_tests.rs")).unwrap();
+    for test in [
+        "fn test_point_3d_example()",
+        "fn test_unit_length_example()",
+        "fn test_get_length_unit_conversion_200_response_example()",
+        "fn test_update_user_self_request_minimal_example()",
+    ] {
+        assert!(tests.contains(test), "{}", test);
+    }
+
+    // Run the tests of the examples.
+    run_cargo_test(&opts).unwrap();
+}
+
+#[test_context(TestContext)]
+#[test]
+fn test_kittycad_fuzz_generation(ctx: &mut TestContext) {
+    let opts = crate::Opts {
+        debug: true,
+        json: false,
+        input: ctx.tmp_dir.clone(),
+        output: ctx.tmp_dir.clone(),
+        base_url: Some("https://api.kittycad.io".parse().unwrap()),
+        name: "kittycad-types".to_string(),
+        target_version: "1.0.0".to_string(),
+        description: "The types of the KittyCAD API.".to_string(),
+        types_only: true,
+        generate_fuzz: true,
+        ..Default::default()
+    };
+
+    // Load our spec.
+    let spec = crate::load_json_spec(include_str!("../../spec.json")).unwrap();
+
+    // Generate the library.
+    crate::generate(&spec, &opts).unwrap();
+
+    // The biggest union of the spec is one of the types we fuzz.
+    let round_trip =
+        std::fs::read_to_string(ctx.tmp_dir.join("fuzz/fuzz_targets/round_trip.rs")).unwrap();
+    assert!(
+        round_trip.contains("round_trip::<kittycad_types::types::ModelingCmd>(&value);"),
+        "{}",
+        round_trip
+    );
+
+    // The fuzz crate builds, running it is up to `cargo fuzz`.
+    run_cargo_check(&crate::Opts {
+        output: ctx.tmp_dir.join("fuzz"),
+        ..opts
+    })
+    .unwrap();
+}
+
+#[test_context(TestContext)]
+#[test]
+fn test_kittycad_split_by_tag_generation(ctx: &mut TestContext) {
+    let opts = crate::Opts {
+        debug: true,
+        json: false,
+        input: ctx.tmp_dir.clone(),
+        output: ctx.tmp_dir.clone(),
+        base_url: Some("https://api.kittycad.io".parse().unwrap()),
+        name: "kittycad".to_string(),
+        target_version: "1.0.0".to_string(),
+        description: "KittyCAD is a tool for generating 3D models of cats.".to_string(),
+        split_by_tag: true,
+        ..Default::default()
+    };
+
+    let spec = crate::load_json_spec(include_str!("../../spec.json")).unwrap();
+
+    // Generate the workspace.
+    crate::generate(&spec, &opts).unwrap();
+
+    let workspace = std::fs::read_to_string(ctx.tmp_dir.join("Cargo.toml")).unwrap();
+    for member in [
+        "kittycad-core",
+        "kittycad-types",
+        "kittycad-file",
+        "kittycad-modeling",
+    ] {
+        assert!(workspace.contains(&format!("\"{}\"", member)), "{}", member);
+    }
+
+    // The client has the types of the types crate, and none of the tags.
+    let core = std::fs::read_to_string(ctx.tmp_dir.join("kittycad-core/src/lib.rs")).unwrap();
+    assert!(core.contains("pub use kittycad_types::types;"));
+    assert!(!core.contains("pub mod types;"));
+    assert!(!core.contains("pub mod file;"));
+    assert!(!core.contains("pub fn file(&self)"));
+
+    // The tag crates get their operations from the client with a trait.
+    let file = std::fs::read_to_string(ctx.tmp_dir.join("kittycad-file/src/lib.rs")).unwrap();
+    assert!(file.contains("pub trait FileExt {"));
+    assert!(ctx.tmp_dir.join("kittycad-file/src/file.rs").exists());
+    assert!(!ctx.tmp_dir.join("kittycad-core/src/file.rs").exists());
+
+    // Check the whole workspace.
+    run_cargo_check(&opts).unwrap();
+}
+
+#[test]
+fn test_types_only_split_by_tag() {
+    let spec = crate::load_json_spec(include_str!("../tests/mock.json")).unwrap();
+    let opts = crate::Opts {
+        types_only: true,
+        split_by_tag: true,
+        ..Default::default()
+    };
+
+    assert_eq!(
+        crate::generate(&spec, &opts).unwrap_err().to_string(),
+        "`--split-by-tag` is not supported with `--types-only`"
+    );
+}
+
+#[test_context(TestContext)]
+#[test]
+fn test_kittycad_budgets(ctx: &mut TestContext) {
+    let mut spec: serde_json::Value =
+        serde_json::from_str(include_str!("../../spec.json")).unwrap();
+    let opts = crate::Opts {
+        output: ctx.tmp_dir.clone(),
+        name: "kittycad".to_string(),
+        generate_budgets: true,
+        ..Default::default()
+    };
+
+    // The first generation is the baseline.
+    let baseline =
+        crate::budgets::check(&crate::load_json_spec(&spec.to_string()).unwrap(), &opts).unwrap();
+    assert!(baseline.operations["update_user_self"].request_bytes > 0);
+    let baseline_file = ctx.tmp_dir.join("baseline.json");
+    std::fs::rename(ctx.tmp_dir.join("budgets.json"), &baseline_file).unwrap();
+
+    // Nothing changed.
+    let opts = crate::Opts {
+        budget_baseline: Some(baseline_file),
+        ..opts
+    };
+    let spec_unchanged = crate::load_json_spec(&spec.to_string()).unwrap();
+    assert_eq!(
+        crate::budgets::check(&spec_unchanged, &opts).unwrap(),
+        baseline
+    );
+
+    // A new field with a big default bloats the request.
+    spec["components"]["schemas"]["UpdateUser"]["properties"]["preferences"] = serde_json::json!({
+        "type": "string",
+        "default": "x".repeat(2048),
+        "minLength": 2048,
+        "maxLength": 2049
+    });
+    let spec = crate::load_json_spec(&spec.to_string()).unwrap();
+    let err = crate::budgets::check(&spec, &opts).unwrap_err().to_string();
+    assert!(err.contains("grew more than 10% since"), "{}", err);
+    assert!(err.contains("- `update_user_self`: "), "{}", err);
+
+    // Unless we only warn, then the new sizes are saved.
+    let opts = crate::Opts {
+        budget_warn_only: true,
+        ..opts
+    };
+    let budgets = crate::budgets::check(&spec, &opts).unwrap();
+    let saved: crate::budgets::Budgets =
+        serde_json::from_str(&std::fs::read_to_string(ctx.tmp_dir.join("budgets.json")).unwrap())
+            .unwrap();
+    assert_eq!(saved, budgets);
+    assert!(budgets.operations["update_user_self"].request_bytes > 2048);
+}
+
+#[test]
+fn test_types_only_session_config() {
+    let spec = crate::load_json_spec(include_str!("../tests/mock.json")).unwrap();
+    let opts = crate::Opts {
+        types_only: true,
+        session_config: Some(
+            std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/session.toml"),
+        ),
+        ..Default::default()
+    };
+
+    assert_eq!(
+        crate::generate(&spec, &opts).unwrap_err().to_string(),
+        "`--session-config` is not supported with `--types-only`"
+    );
+}
+
+#[test]
+fn test_rate_limit_header_prefix() {
+    let opts = crate::Opts {
+        rate_limit_header_prefix: "RateLimit".to_string(),
+        ..Default::default()
+    };
+
+    let client = crate::client::generate_client(&Default::default(), &opts);
+    assert!(client.contains(r#"RateLimit::from_headers(headers, "RateLimit")"#));
+    assert!(!client.contains("RATE_LIMIT_HEADER_PREFIX"));
+}
+
+#[test]
+fn test_request_id_header() {
+    let spec = crate::load_json_spec(include_str!("../tests/mock.json")).unwrap();
+    let opts = crate::Opts {
+        request_id_header: "Request-Id".to_string(),
+        ..Default::default()
+    };
+
+    let types = crate::types::generate_types(&spec, opts)
+        .unwrap()
+        .render()
+        .unwrap();
+    assert!(types.contains(r#"pub const REQUEST_ID_HEADER: &str = "Request-Id";"#));
+}
+
+#[test]
+fn test_base_url_env() {
+    let opts = crate::Opts {
+        name: "kittycad".to_string(),
+        add_env_prefix: Some("zoo".to_string()),
+        base_url_env: vec!["ACME_API_BASE_URL".to_string()],
+        ..Default::default()
+    };
+
+    let lookup = r#"["ACME_API_BASE_URL", "KITTYCAD_HOST", "ZOO_HOST"]"#;
+    for opts in [
+        opts.clone(),
+        crate::Opts {
+            basic_auth: true,
+            ..opts.clone()
+        },
+        crate::Opts {
+            token_endpoint: Some("https://example.com/token".parse().unwrap()),
+            user_consent_endpoint: Some("https://example.com/consent".parse().unwrap()),
+            ..opts.clone()
+        },
+    ] {
+        let client = crate::client::generate_client(&Default::default(), &opts);
+        assert!(client.contains(lookup), "{}", client);
+        assert!(!client.contains("ENV_VARIABLE"));
+        // The template leaves the `impl Client` open for the generated functions.
+        syn::parse_file(&format!("{}}}", client)).unwrap();
+    }
+
+    let client = crate::client::generate_client(&Default::default(), &opts);
+    assert!(client.contains(
+        "must set the API token in one of the environment variables: KITTYCAD_API_TOKEN, ZOO_API_TOKEN"
+    ));
+    assert!(client.contains(
+        "/// - The base url: `ACME_API_BASE_URL`, `KITTYCAD_HOST`, `ZOO_HOST`, defaults to"
+    ));
+}
+
+/// A test of the crate that every string enum of the spec displays, parses and
+/// serializes as its values on the wire, to add to the tests of the crate.
+fn enum_wire_values_test(spec: &openapiv3::OpenAPI) -> Result<String> {
+    let (spec, _) = crate::types::dedupe_schema_names(spec)?;
+    let mut checks = String::new();
+    for (name, schema) in spec.components.iter().flat_map(|c| &c.schemas) {
+        let Some(schema) = schema.as_item() else {
+            continue;
+        };
+        let openapiv3::SchemaKind::Type(openapiv3::Type::String(s)) = &schema.schema_kind else {
+            continue;
+        };
+        let type_name = crate::types::get_type_name_from_reference(name, &spec, false)?;
+        for wire in s.enumeration.iter().flatten() {
+            checks.push_str(&format!(
+                "    check::<{}>({:?});\n",
+                type_name.to_string().replace(' ', ""),
+                wire
+            ));
+        }
+    }
+
+    Ok(format!(
+        r#"
+#[test]
+fn test_enums_display_their_wire_values() {{
+    fn check<T>(wire: &str)
+    where
+        T: serde::Serialize
+            + serde::de::DeserializeOwned
+            + std::fmt::Display
+            + std::str::FromStr
+            + PartialEq
+            + std::fmt::Debug,
+        T::Err: std::fmt::Debug,
+    {{
+        let value: T = serde_json::from_value(serde_json::json!(wire)).unwrap();
+        assert_eq!(value.to_string(), wire);
+        assert_eq!(wire.parse::<T>().unwrap(), value);
+        assert_eq!(serde_json::to_value(&value).unwrap(), serde_json::json!(wire));
+    }}
+
+{}}}
+"#,
+        checks
+    ))
+}
+
+fn run_cargo_test(opts: &crate::Opts) -> Result<()> {
+    run_cargo_test_with_features(opts, &[])
+}
+
+fn run_cargo_test_with_features(opts: &crate::Opts, features: &[&str]) -> Result<()> {
+    log::info!("Running `cargo test`...");
+
+    // Shell out and run cargo clippy on the output directory.
+    let output = if opts.output.display().to_string() == "." {
+        "".to_string()
+    } else {
+        opts.output.display().to_string()
+    };
+
+    let mut cmd = std::process::Command::new("cargo");
+    cmd.args(["test", "--quiet"]);
+    if !features.is_empty() {
+        cmd.args(["--features", &features.join(",")]);
+    }
+    cmd.current_dir(output)
+        // So that we can run fresh and not fail.
+        .env("EXPECTORATE", "overwrite");
+
+    let output = cmd.output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        eprintln!("Stderr:");
+        eprintln!("{stderr}");
+        eprintln!("Stdout:");
+        eprintln!("{stdout}");
+        anyhow::bail!("cargo test failed, see above");
+    }
+
+    Ok(())
+}
+
+fn run_cargo_check(opts: &crate::Opts) -> Result<()> {
+    log::info!("Running `cargo check`...");
+
+    let output = std::process::Command::new("cargo")
+        .args(["check", "--quiet", "--all-targets", "--all-features"])
+        .current_dir(&opts.output)
+        .output()?;
+    if !output.status.success() {
+        eprintln!("Stderr:");
+        eprintln!("{}", String::from_utf8_lossy(&output.stderr));
+        eprintln!("Stdout:");
+        eprintln!("{}", String::from_utf8_lossy(&output.stdout));
+        anyhow::bail!("cargo check failed, see above");
+    }
+
+    Ok(())
+}
+
+/// Run the doctests and build the docs, denying the broken links.
+fn run_cargo_doc(opts: &crate::Opts) -> Result<()> {
+    log::info!("Running `cargo test --doc` and `cargo doc`...");
+
+    for args in [
+        vec!["test", "--doc", "--quiet"],
+        vec!["doc", "--no-deps", "--quiet"],
+    ] {
+        let output = std::process::Command::new("cargo")
+            .args(&args)
+            .current_dir(&opts.output)
+            .env(
+                "RUSTDOCFLAGS",
+                "-D rustdoc::broken_intra_doc_links -D rustdoc::invalid_codeblock_attributes",
+            )
+            .output()?;
+        if !output.status.success() {
+            eprintln!("Stderr:");
+            eprintln!("{}", String::from_utf8_lossy(&output.stderr));
+            eprintln!("Stdout:");
+            eprintln!("{}", String::from_utf8_lossy(&output.stdout));
+            anyhow::bail!("cargo {} failed, see above", args.join(" "));
+        }
+    }
+
+    Ok(())
+}
+
+/// Build the docs, and run clippy denying the missing docs, for `--strict-lints`.
+fn run_cargo_strict_lints(opts: &crate::Opts) -> Result<()> {
+    log::info!("Running `cargo doc` and `cargo clippy`...");
+
+    for args in [
+        vec!["doc", "--no-deps", "--quiet"],
+        vec![
+            "clippy",
+            "--quiet",
+            "--all-targets",
+            "--all-features",
+            "--",
+            "-D",
+            "missing_docs",
+        ],
+    ] {
+        let output = std::process::Command::new("cargo")
+            .args(&args)
+            .current_dir(&opts.output)
+            .output()?;
+        if !output.status.success() {
+            eprintln!("Stderr:");
+            eprintln!("{}", String::from_utf8_lossy(&output.stderr));
+            eprintln!("Stdout:");
+            eprintln!("{}", String::from_utf8_lossy(&output.stdout));
+            anyhow::bail!("cargo {} failed, see above", args.join(" "));
+        }
+    }
+
+    Ok(())
+}
+
+#[test_context(TestContext)]
+#[test]
+fn test_invalid_code_is_not_saved(ctx: &mut TestContext) {
+    let output = quote!(pub fn ok() {}, stray_ident);
+
+    let opts = crate::Opts {
+        debug: true,
+        ..Default::default()
+    };
+    let file = ctx.tmp_dir.join("src").join("broken.rs");
+    let err = crate::save_rust(&file, &output, &opts).unwrap_err();
+    let err = err.downcast_ref::<crate::InvalidCodeError>().unwrap();
+
+    assert_eq!(err.file, file);
+    assert_eq!(err.error.line, 1);
+    assert!(err.error.column > 0);
+    assert!(!file.exists());
+
+    // In debug mode the unformatted code is kept next to where the file would be.
+    let dump = err.dump.as_ref().unwrap();
+    assert_eq!(std::fs::read_to_string(dump).unwrap(), output.to_string());
+
+    // Valid code is saved as usual.
+    crate::save_rust(
+        &file,
+        &quote!(
+            pub fn ok() {}
+        ),
+        &opts,
+    )
+    .unwrap();
+    assert!(std::fs::read_to_string(&file)
+        .unwrap()
+        .contains("pub fn ok() {}"));
+}
+
+/// A spec with one operation getting a `Thing` with `fields`.
+fn watched_spec(fields: &[&str]) -> String {
+    let properties: serde_json::Map<String, serde_json::Value> = fields
+        .iter()
+        .map(|field| (field.to_string(), serde_json::json!({ "type": "string" })))
+        .collect();
+    serde_json::json!({
+        "openapi": "3.0.3",
+        "info": { "title": "Things", "version": "1.0.0" },
+        "paths": {
+            "/things/{id}": {
+                "get": {
+                    "tags": ["things"],
+                    "operationId": "get_thing",
+                    "parameters": [{
+                        "in": "path",
+                        "name": "id",
+                        "required": true,
+                        "schema": { "type": "string" }
+                    }],
+                    "responses": {
+                        "200": {
+                            "description": "the thing",
+                            "content": {
+                                "application/json": {
+                                    "schema": { "$ref": "#/components/schemas/Thing" }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        },
+        "components": {
+            "schemas": {
+                "Thing": { "type": "object", "properties": properties }
+            }
+        }
+    })
+    .to_string()
+}
+
+#[test_context(TestContext)]
+#[test]
+fn test_watch_generates_on_change(ctx: &mut TestContext) {
+    let spec = ctx.tmp_dir.join("things.json");
+    std::fs::write(&spec, watched_spec(&["name"])).unwrap();
+    let opts = crate::Opts {
+        input: spec.clone(),
+        output: ctx.tmp_dir.join("things"),
+        name: "things".to_string(),
+        ..Default::default()
+    };
+
+    let (events, received) = std::sync::mpsc::channel();
+    let stop = events.clone();
+    let _watcher = crate::watch::watch_spec(&spec, events).unwrap();
+    let (summaries, generated) = std::sync::mpsc::channel();
+    let watch = std::thread::spawn(move || {
+        let mut generator = crate::watch::Generator::new(opts);
+        summaries.send(generator.generate()).unwrap();
+        crate::watch::run(&received, std::time::Duration::from_millis(100), || {
+            summaries.send(generator.generate()).unwrap()
+        })
+    });
+    let next_summary = || {
+        generated
+            .recv_timeout(std::time::Duration::from_secs(60))
+            .unwrap()
+    };
+
+    let summary = next_summary();
+    assert!(summary.starts_with("Generated "), "{}", summary);
+    assert!(ctx.tmp_dir.join("things/src/lib.rs").exists());
+
+    // The changes to the public API are listed.
+    std::fs::write(&spec, watched_spec(&["name", "size"])).unwrap();
+    let summary = next_summary();
+    assert!(summary.starts_with("Generated "), "{}", summary);
+    assert!(
+        summary.contains("- Type `Thing` has a new field `size: Option<String>`."),
+        "{}",
+        summary
+    );
+
+    // A broken spec is reported, and the watch goes on.
+    std::fs::write(&spec, "{ not a spec").unwrap();
+    let summary = next_summary();
+    assert!(summary.starts_with("Generating "), "{}", summary);
+    assert!(summary.contains(" failed after "), "{}", summary);
+
+    stop.send(crate::watch::WatchEvent::Stop).unwrap();
+    assert_eq!(watch.join().unwrap(), 2);
 }
@@ -0,0 +1,568 @@
+//! Compile the fixtures in `tests/types`.
+//!
+//! The fixtures only tell us when the output changes, not if it still builds. So
+//! we generate a crate for every spec the fixtures come from, add the fixtures
+//! that are only a few types as modules of their own, and `cargo check` all of
+//! the crates in one scratch workspace.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+
+/// The features we check the crates with, everything but the ones that need
+/// more than the spec (`interop`) or another target (`js`).
+const FEATURES: &[&str] = &[
+    "clap",
+    "tabled",
+    "metrics",
+    "record",
+    "registry",
+    "websocket",
+];
+
+/// The fixtures we do not compile, and why.
+const SKIPPED: &[(&str, &str)] = &[
+    (
+        "github.rs.gen",
+        "generating the github types loops, so the fixture is not kept up to date",
+    ),
+    (
+        "split-by-tag.client.rs.gen",
+        "it is the part of a client we publish to the tag crates, not a whole one",
+    ),
+];
+
+/// A crate generated from a spec, and the fixtures it compiles.
+struct Case {
+    /// The name of the crate, and of its directory in the workspace.
+    name: &'static str,
+    /// The spec, as JSON.
+    spec: &'static str,
+    /// The options on top of the defaults.
+    opts: crate::Opts,
+    /// The fixtures that are a part of the crate as it is generated, their tests
+    /// make sure they are the same.
+    generated: &'static [&'static str],
+    /// The fixtures with types rendered on their own, each one becomes a module
+    /// next to the generated types.
+    types: &'static [&'static str],
+}
+
+fn cases() -> Vec<Case> {
+    let input = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/types/input");
+
+    vec![
+        Case {
+            name: "fixtures_kittycad",
+            spec: include_str!("../../../spec.json"),
+            opts: Default::default(),
+            generated: &["kittycad.rs.gen"],
+            types: &[
+                "kittycad.account-provider-output.rs.gen",
+                "kittycad.async-api-call-output.rs.gen",
+                "kittycad.drawing-cmd-output.rs.gen",
+                "kittycad.subscription-tier-type.rs.gen",
+                "standalone.modeling-cmd.rs.gen",
+            ],
+        },
+        Case {
+            name: "fixtures_kittycad_date_time",
+            spec: include_str!("../../../spec.json"),
+            opts: crate::Opts {
+                date_time_format: Some("%Y-%m-%dT%H:%M:%S".to_string()),
+                ..Default::default()
+            },
+            generated: &[],
+            types: &["kittycad.file-density-date-time-override-output.rs.gen"],
+        },
+        Case {
+            name: "fixtures_kittycad_acronym_casing",
+            spec: include_str!("../../../spec.json"),
+            opts: crate::Opts {
+                acronym_casing: true,
+                ..Default::default()
+            },
+            generated: &["kittycad.acronym-casing.rs.gen"],
+            types: &[],
+        },
+        Case {
+            name: "fixtures_oxide",
+            spec: include_str!("../../tests/oxide.json"),
+            opts: Default::default(),
+            generated: &["oxide.rs.gen"],
+            types: &[
+                "oxide.digest.rs.gen",
+                "oxide.ip-net.rs.gen",
+                "oxide.router-route.rs.gen",
+                "oxide.vpc-filewall-rule-target.rs.gen",
+                "standalone.router-route.rs.gen",
+            ],
+        },
+        Case {
+            name: "fixtures_mock",
+            spec: include_str!("../../tests/mock.json"),
+            opts: Default::default(),
+            generated: &[],
+            types: &[
+                "mock.named-thing.rs.gen",
+                "mock.shelf.rs.gen",
+                "age-range.rs.gen",
+                "case-insensitive-enums.rs.gen",
+                "mock.borrowed.rs.gen",
+                "tagged-any-of.rs.gen",
+            ],
+        },
+        Case {
+            name: "fixtures_mock_display_string_params",
+            spec: include_str!("../../tests/mock.json"),
+            opts: crate::Opts {
+                display_string_params: true,
+                ..Default::default()
+            },
+            generated: &["mock.display-string-params.rs.gen"],
+            types: &[],
+        },
+        Case {
+            name: "fixtures_mock_secret_fields",
+            spec: include_str!("../../tests/mock.json"),
+            opts: crate::Opts {
+                secret_fields: true,
+                ..Default::default()
+            },
+            generated: &[],
+            types: &["secret-fields.rs.gen"],
+        },
+        Case {
+            name: "fixtures_mock_split_by_tag",
+            spec: include_str!("../../tests/mock.json"),
+            opts: crate::Opts {
+                split_types: crate::TypesSplit::Tag,
+                ..Default::default()
+            },
+            generated: &["mock.split-by-tag.rs.gen"],
+            types: &[],
+        },
+        Case {
+            name: "fixtures_acronym_casing",
+            spec: include_str!("../../tests/types/input/acronyms.json"),
+            opts: crate::Opts {
+                acronym_casing: true,
+                ..Default::default()
+            },
+            generated: &["acronym-casing.rs.gen"],
+            types: &[],
+        },
+        Case {
+            name: "fixtures_bare_arrays",
+            spec: include_str!("../../tests/types/input/bare-arrays.json"),
+            opts: Default::default(),
+            generated: &["bare-arrays.rs.gen"],
+            types: &[],
+        },
+        Case {
+            name: "fixtures_datetime",
+            spec: include_str!("../../tests/datetime.json"),
+            opts: Default::default(),
+            generated: &["datetime.rs.gen"],
+            types: &[],
+        },
+        Case {
+            name: "fixtures_default_error",
+            spec: include_str!("../../tests/types/input/default-error.json"),
+            opts: Default::default(),
+            generated: &["default-error.rs.gen"],
+            types: &[],
+        },
+        Case {
+            name: "fixtures_error_codes",
+            spec: include_str!("../../tests/types/input/error-codes.json"),
+            opts: Default::default(),
+            generated: &[],
+            types: &["error-codes.rs.gen"],
+        },
+        Case {
+            name: "fixtures_head_options",
+            spec: include_str!("../../tests/types/input/head-options.json"),
+            opts: Default::default(),
+            generated: &["head-options.rs.gen"],
+            types: &[],
+        },
+        Case {
+            name: "fixtures_meta_pagination",
+            spec: include_str!("../../tests/types/input/meta-pagination.json"),
+            opts: Default::default(),
+            generated: &["meta-pagination.rs.gen"],
+            types: &[],
+        },
+        Case {
+            name: "fixtures_module_map",
+            spec: include_str!("../../tests/types/input/module-map.json"),
+            opts: crate::Opts {
+                module_map: vec![
+                    "org-members=org::members".to_string(),
+                    "org-billing=org::billing".to_string(),
+                ],
+                ..Default::default()
+            },
+            generated: &["module-map.rs.gen"],
+            types: &[],
+        },
+        Case {
+            name: "fixtures_named_examples",
+            spec: include_str!("../../tests/types/input/named-examples.json"),
+            opts: Default::default(),
+            generated: &["named-examples.rs.gen"],
+            types: &[],
+        },
+        Case {
+            name: "fixtures_nested_accessors",
+            spec: include_str!("../../tests/types/input/nested-accessors.json"),
+            opts: Default::default(),
+            generated: &[],
+            types: &["nested-accessors.rs.gen"],
+        },
+        Case {
+            name: "fixtures_oauth_scopes",
+            spec: include_str!("../../tests/types/input/oauth-scopes.json"),
+            opts: crate::Opts {
+                token_endpoint: Some("https://example.com/oauth/token".parse().unwrap()),
+                user_consent_endpoint: Some("https://example.com/oauth/authorize".parse().unwrap()),
+                ..Default::default()
+            },
+            generated: &["oauth-scopes.rs.gen"],
+            types: &[],
+        },
+        Case {
+            name: "fixtures_opaque",
+            spec: include_str!("../../tests/types/input/opaque.json"),
+            opts: crate::Opts {
+                opaque_types: vec!["Engine*".to_string()],
+                ..Default::default()
+            },
+            generated: &[],
+            types: &["opaque.rs.gen"],
+        },
+        Case {
+            name: "fixtures_optional_body",
+            spec: include_str!("../../tests/types/input/optional-body.json"),
+            opts: Default::default(),
+            generated: &["optional-body.rs.gen"],
+            types: &[],
+        },
+        Case {
+            name: "fixtures_param_title_collision",
+            spec: include_str!("../../tests/types/input/param-title-collision.json"),
+            opts: Default::default(),
+            generated: &["param-title-collision.rs.gen"],
+            types: &[],
+        },
+        Case {
+            name: "fixtures_referenced_params",
+            spec: include_str!("../../tests/types/input/referenced-params.json"),
+            opts: Default::default(),
+            generated: &["referenced-params.rs.gen"],
+            types: &[],
+        },
+        Case {
+            name: "fixtures_rust_name",
+            spec: include_str!("../../tests/types/input/rust-name.json"),
+            opts: Default::default(),
+            generated: &["rust-name.rs.gen"],
+            types: &[],
+        },
+        Case {
+            name: "fixtures_schema_metadata",
+            spec: include_str!("../../tests/types/input/schema-metadata.json"),
+            opts: Default::default(),
+            generated: &[],
+            types: &["schema-metadata.rs.gen"],
+        },
+        Case {
+            name: "fixtures_servers",
+            spec: include_str!("../../tests/types/input/servers.json"),
+            opts: crate::Opts {
+                base_url: None,
+                ..Default::default()
+            },
+            generated: &[],
+            types: &["servers.rs.gen"],
+        },
+        Case {
+            name: "fixtures_smoke_tests",
+            spec: include_str!("../../tests/types/input/smoke-tests.json"),
+            opts: crate::Opts {
+                generate_smoke_tests: true,
+                ..Default::default()
+            },
+            generated: &["smoke-tests.rs.gen"],
+            types: &[],
+        },
+        Case {
+            name: "fixtures_unique_items",
+            spec: include_str!("../../tests/types/input/unique-items.json"),
+            opts: crate::Opts {
+                unique_items_as_sets: true,
+                ..Default::default()
+            },
+            generated: &["unique-items.rs.gen"],
+            types: &[],
+        },
+        Case {
+            name: "fixtures_update_helpers",
+            spec: include_str!("../../tests/types/input/update-helpers.json"),
+            opts: crate::Opts {
+                update_helpers: Some(input.join("update-helpers.toml")),
+                ..Default::default()
+            },
+            generated: &["update-helpers.rs.gen"],
+            types: &[],
+        },
+        Case {
+            name: "fixtures_websocket",
+            spec: include_str!("../../tests/types/input/websocket.json"),
+            opts: Default::default(),
+            generated: &["websocket.rs.gen"],
+            types: &[],
+        },
+    ]
+}
+
+/// The name of the module for a fixture, `oxide.ip-net.rs.gen` is `oxide_ip_net`.
+fn module_name(fixture: &str) -> String {
+    fixture
+        .trim_end_matches(".rs.gen")
+        .replace(|c: char| !c.is_ascii_alphanumeric(), "_")
+}
+
+/// Generate the crate for a case in the workspace, with its type fixtures.
+fn generate_case(case: &Case, workspace: &Path, fixtures: &Path) -> Result<()> {
+    let dir = workspace.join(case.name);
+    std::fs::create_dir_all(dir.join("src"))?;
+
+    let opts = crate::Opts {
+        input: dir.clone(),
+        output: dir.clone(),
+        base_url: Some("https://api.example.com".parse()?),
+        name: case.name.to_string(),
+        target_version: "1.0.0".to_string(),
+        description: format!("Compiles the fixtures of {}.", case.name),
+        ..case.opts.clone()
+    };
+    let spec = crate::load_json_spec(case.spec)?;
+    crate::generate(&spec, &opts)?;
+
+    if case.types.is_empty() {
+        return Ok(());
+    }
+
+    // The types refer to the others by name, the way they do in `types.rs`.
+    let modules = dir.join("src").join("fixtures");
+    std::fs::create_dir_all(&modules)?;
+    let mut declarations = String::new();
+    for fixture in case.types {
+        let module = module_name(fixture);
+        let content = std::fs::read_to_string(fixtures.join(fixture))?;
+        std::fs::write(
+            modules.join(format!("{}.rs", module)),
+            format!(
+                "#[allow(unused_imports)]\nuse crate::types::*;\n\n{}",
+                content
+            ),
+        )?;
+        declarations.push_str(&format!("    pub mod {};\n", module));
+    }
+
+    let lib = dir.join("src").join("lib.rs");
+    let mut content = std::fs::read_to_string(&lib)?;
+    content.push_str(&format!(
+        "\n#[allow(dead_code)]\nmod fixtures {{\n{}}}\n",
+        declarations
+    ));
+    std::fs::write(lib, content)?;
+
+    Ok(())
+}
+
+/// The fixture a compiler error is in, from the path at the start of its line.
+fn error_fixture(cases: &[Case], path: &str) -> Option<String> {
+    let path = path.replace('\\', "/");
+    let case = cases
+        .iter()
+        .find(|case| path.starts_with(&format!("{}/", case.name)))?;
+
+    // In a module of its own.
+    if let Some(fixture) = case
+        .types
+        .iter()
+        .find(|fixture| path == format!("{}/src/fixtures/{}.rs", case.name, module_name(fixture)))
+    {
+        return Some(fixture.to_string());
+    }
+
+    // Anywhere else it is in the crate as it is generated.
+    Some(if case.generated.is_empty() {
+        format!("the crate of {}", case.types.join(", "))
+    } else {
+        case.generated.join(", ")
+    })
+}
+
+/// The first compiler error of every fixture that does not compile, from the
+/// output of `cargo check --message-format short`.
+fn first_errors(cases: &[Case], output: &str) -> BTreeMap<String, String> {
+    let mut errors = BTreeMap::new();
+    for line in output.lines() {
+        let Some((location, message)) = line.split_once(": error") else {
+            continue;
+        };
+        let path = location.split(':').next().unwrap_or_default();
+        if let Some(fixture) = error_fixture(cases, path) {
+            errors
+                .entry(fixture)
+                .or_insert_with(|| format!("{}: error{}", location, message));
+        }
+    }
+
+    errors
+}
+
+#[test]
+#[ignore] // Generates and checks a few crates, CI runs it on its own.
+fn test_fixtures_compile() {
+    let fixtures = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/types");
+    let cases = cases();
+
+    // Every fixture is compiled, or skipped for a reason.
+    let mut expected = std::fs::read_dir(&fixtures)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+        .filter(|name| name.ends_with(".rs.gen"))
+        .collect::<BTreeSet<_>>();
+    for fixture in cases
+        .iter()
+        .flat_map(|case| case.generated.iter().chain(case.types))
+        .chain(SKIPPED.iter().map(|(fixture, _)| fixture))
+    {
+        assert!(
+            expected.remove(*fixture),
+            "{} is not a fixture, or is in more than one case",
+            fixture
+        );
+    }
+    assert!(
+        expected.is_empty(),
+        "add {:?} to a case, or skip them for a reason",
+        expected
+    );
+
+    let workspace =
+        std::env::temp_dir().join(format!("openapitor-fixtures-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&workspace).unwrap();
+
+    // Generating the big specs takes a while, do them all at once.
+    std::thread::scope(|scope| {
+        let (workspace, fixtures) = (&workspace, &fixtures);
+        let handles = cases
+            .iter()
+            .map(|case| scope.spawn(move || (case.name, generate_case(case, workspace, fixtures))))
+            .collect::<Vec<_>>();
+        for handle in handles {
+            let (name, result) = handle.join().unwrap();
+            if let Err(err) = result {
+                panic!("generating {} failed: {:?}", name, err);
+            }
+        }
+    });
+
+    let members = cases
+        .iter()
+        .map(|case| format!("{:?}", case.name))
+        .collect::<Vec<_>>()
+        .join(", ");
+    std::fs::write(
+        workspace.join("Cargo.toml"),
+        format!("[workspace]\nmembers = [{}]\nresolver = \"2\"\n", members),
+    )
+    .unwrap();
+
+    // Start from our own lockfile, the crates resolve to the versions we already
+    // have, which is all that `CARGO_NET_OFFLINE=true` can work with.
+    std::fs::copy(
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../Cargo.lock"),
+        workspace.join("Cargo.lock"),
+    )
+    .unwrap();
+
+    // One target directory for every run, so the dependencies are only built
+    // once. Cargo checks the crates in parallel.
+    let target = std::env::var_os("OPENAPITOR_FIXTURES_TARGET_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../target/fixtures"));
+    let features = cases
+        .iter()
+        .flat_map(|case| {
+            FEATURES
+                .iter()
+                .map(move |feature| format!("{}/{}", case.name, feature))
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+    let output = std::process::Command::new("cargo")
+        .args(["check", "--workspace", "--message-format", "short"])
+        .args(["--features", &features])
+        .current_dir(&workspace)
+        .env("CARGO_TARGET_DIR", &target)
+        .output()
+        .unwrap();
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let errors = first_errors(&cases, &stderr);
+        if errors.is_empty() {
+            panic!("cargo check failed in {}:\n{}", workspace.display(), stderr);
+        }
+
+        // Keep the workspace around to look at.
+        panic!(
+            "fixtures do not compile, see {}:\n{}",
+            workspace.display(),
+            errors
+                .iter()
+                .map(|(fixture, error)| format!("{}: {}", fixture, error))
+                .collect::<Vec<_>>()
+                .join("\n")
+        );
+    }
+
+    std::fs::remove_dir_all(&workspace).unwrap();
+}
+
+#[test]
+fn test_first_errors() {
+    let cases = cases();
+    let output = r#"    Checking fixtures_oxide v1.0.0 (/tmp/openapitor-fixtures/fixtures_oxide)
+fixtures_oxide/src/fixtures/oxide_ip_net.rs:12:5: error[E0412]: cannot find type `Ipv4Net` in this scope
+fixtures_oxide/src/fixtures/oxide_ip_net.rs:20:5: error[E0412]: cannot find type `Ipv6Net` in this scope
+fixtures_oxide/src/types.rs:1:1: warning: unused import
+fixtures_mock_split_by_tag/src/types/things.rs:3:9: error[E0432]: unresolved import `super::shared`
+fixtures_kittycad_date_time/src/utils.rs:1:1: error: expected item, found `}`
+error: could not compile `fixtures_oxide` (lib) due to 2 previous errors
+"#;
+
+    let errors = first_errors(&cases, output);
+    assert_eq!(
+        errors.keys().collect::<Vec<_>>(),
+        vec![
+            "mock.split-by-tag.rs.gen",
+            "oxide.ip-net.rs.gen",
+            "the crate of kittycad.file-density-date-time-override-output.rs.gen",
+        ]
+    );
+    assert_eq!(
+        errors["oxide.ip-net.rs.gen"],
+        "fixtures_oxide/src/fixtures/oxide_ip_net.rs:12:5: error[E0412]: cannot find type `Ipv4Net` in this scope"
+    );
+}
@@ -0,0 +1,453 @@
+//! Compare the public API of generated crates with and without the `retry`
+//! feature.
+//!
+//! The middleware of the `retry` feature must not show in the API, so turning it
+//! off never breaks a build. There is no stable rustdoc JSON to compare, so we
+//! list the public items of the crate from its source with `syn`, the way
+//! `cargo public-api` does: the items the `#[cfg]`s keep with the features on,
+//! with their signatures. The `#[doc(hidden)]` items are for the generated code
+//! and are left out.
+
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use quote::ToTokens;
+use test_context::test_context;
+
+use super::TestContext;
+
+/// An item of the crate the `#[cfg]`s keep, with the path of its module and if
+/// the module is reachable from outside of the crate.
+struct Item {
+    path: String,
+    public: bool,
+    item: syn::Item,
+}
+
+/// List the public API of the crate in `src_dir` with `features` on, sorted.
+fn list(src_dir: &Path, features: &[&str]) -> Result<Vec<String>> {
+    let mut items = Vec::new();
+    collect_file(
+        &src_dir.join("lib.rs"),
+        src_dir,
+        "crate",
+        true,
+        features,
+        &mut items,
+    )?;
+
+    // The impls of the types of the crate only count when the type is public, the
+    // types of other crates always are.
+    let mut public_types = BTreeSet::new();
+    let mut private_types = BTreeSet::new();
+    for Item { public, item, .. } in &items {
+        let (ident, vis) = match item {
+            syn::Item::Struct(s) => (&s.ident, &s.vis),
+            syn::Item::Enum(e) => (&e.ident, &e.vis),
+            syn::Item::Type(t) => (&t.ident, &t.vis),
+            syn::Item::Trait(t) => (&t.ident, &t.vis),
+            _ => continue,
+        };
+        if *public && is_public(vis) {
+            public_types.insert(ident.to_string());
+        } else {
+            private_types.insert(ident.to_string());
+        }
+    }
+
+    let mut lines = BTreeSet::new();
+    for Item { path, public, item } in &items {
+        if let syn::Item::Impl(i) = item {
+            let self_ty = match &*i.self_ty {
+                syn::Type::Path(p) => p.path.segments.last().map(|s| s.ident.to_string()),
+                _ => None,
+            };
+            if matches!(&self_ty, Some(ty) if private_types.contains(ty) && !public_types.contains(ty))
+            {
+                continue;
+            }
+            list_impl(path, i, features, &mut lines);
+            continue;
+        }
+        if *public {
+            list_item(path, item, features, &mut lines);
+        }
+    }
+
+    Ok(lines.into_iter().collect())
+}
+
+/// Collect the items of the module in `file`, the modules it declares are in
+/// `dir`.
+fn collect_file(
+    file: &Path,
+    dir: &Path,
+    path: &str,
+    public: bool,
+    features: &[&str],
+    items: &mut Vec<Item>,
+) -> Result<()> {
+    let contents = std::fs::read_to_string(file)
+        .map_err(|e| anyhow::anyhow!("failed to read `{}`: {}", file.display(), e))?;
+    let file = syn::parse_file(&contents)?;
+    collect_items(file.items, dir, path, public, features, items)
+}
+
+fn collect_items(
+    module_items: Vec<syn::Item>,
+    dir: &Path,
+    path: &str,
+    public: bool,
+    features: &[&str],
+    items: &mut Vec<Item>,
+) -> Result<()> {
+    for item in module_items {
+        if !cfg_enabled(attrs(&item), features) {
+            continue;
+        }
+
+        let syn::Item::Mod(m) = item else {
+            items.push(Item {
+                path: path.to_string(),
+                public,
+                item,
+            });
+            continue;
+        };
+        let name = m.ident.to_string();
+        let mod_path = format!("{}::{}", path, name);
+        let mod_public = public && is_public(&m.vis) && !is_hidden(&m.attrs);
+        let mod_dir = dir.join(&name);
+        match m.content {
+            Some((_, content)) => {
+                collect_items(content, &mod_dir, &mod_path, mod_public, features, items)?
+            }
+            None => {
+                let file = vec![dir.join(format!("{}.rs", name)), mod_dir.join("mod.rs")]
+                    .into_iter()
+                    .find(|file| file.exists())
+                    .ok_or_else(|| anyhow::anyhow!("module `{}` has no file", mod_path))?;
+                collect_file(&file, &mod_dir, &mod_path, mod_public, features, items)?
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn list_item(path: &str, item: &syn::Item, features: &[&str], lines: &mut BTreeSet<String>) {
+    match item {
+        syn::Item::Fn(f) if is_listed(&f.vis, &f.attrs) => {
+            lines.insert(format!("{}::{}", path, tokens(&f.sig)));
+        }
+        syn::Item::Const(c) if is_listed(&c.vis, &c.attrs) => {
+            lines.insert(format!("{}::const {}: {}", path, c.ident, tokens(&c.ty)));
+        }
+        syn::Item::Static(s) if is_listed(&s.vis, &s.attrs) => {
+            lines.insert(format!("{}::static {}: {}", path, s.ident, tokens(&s.ty)));
+        }
+        syn::Item::Type(t) if is_listed(&t.vis, &t.attrs) => {
+            lines.insert(format!(
+                "{}::type {}{} = {}",
+                path,
+                t.ident,
+                tokens(&t.generics),
+                tokens(&t.ty)
+            ));
+        }
+        syn::Item::Use(u) if is_listed(&u.vis, &u.attrs) => {
+            lines.insert(format!("{}::use {}", path, tokens(&u.tree)));
+        }
+        syn::Item::Struct(s) if is_listed(&s.vis, &s.attrs) => {
+            let name = format!("{}::{}", path, s.ident);
+            lines.insert(format!(
+                "{}struct {}{}",
+                derives(&s.attrs, features),
+                name,
+                tokens(&s.generics)
+            ));
+            list_fields(&name, &s.fields, false, features, lines);
+        }
+        syn::Item::Enum(e) if is_listed(&e.vis, &e.attrs) => {
+            let name = format!("{}::{}", path, e.ident);
+            lines.insert(format!(
+                "{}enum {}{}",
+                derives(&e.attrs, features),
+                name,
+                tokens(&e.generics)
+            ));
+            for variant in &e.variants {
+                if !cfg_enabled(&variant.attrs, features) {
+                    continue;
+                }
+                let variant_name = format!("{}::{}", name, variant.ident);
+                lines.insert(variant_name.clone());
+                list_fields(&variant_name, &variant.fields, true, features, lines);
+            }
+        }
+        syn::Item::Trait(t) if is_listed(&t.vis, &t.attrs) => {
+            let name = format!("{}::{}", path, t.ident);
+            lines.insert(format!(
+                "trait {}{}: {}",
+                name,
+                tokens(&t.generics),
+                tokens(&t.supertraits)
+            ));
+            for item in &t.items {
+                if let syn::TraitItem::Fn(f) = item {
+                    if cfg_enabled(&f.attrs, features) {
+                        lines.insert(format!("{}::{}", name, tokens(&f.sig)));
+                    }
+                }
+            }
+        }
+        syn::Item::Macro(m) if m.attrs.iter().any(|a| a.path().is_ident("macro_export")) => {
+            lines.insert(format!("crate::macro {}", tokens(&m.ident)));
+        }
+        _ => {}
+    }
+}
+
+/// The fields of a struct or of a variant, the fields of a variant are all
+/// public.
+fn list_fields(
+    name: &str,
+    fields: &syn::Fields,
+    variant: bool,
+    features: &[&str],
+    lines: &mut BTreeSet<String>,
+) {
+    let mut private = false;
+    for (i, field) in fields.iter().enumerate() {
+        if !cfg_enabled(&field.attrs, features) {
+            continue;
+        }
+        if !variant && !is_public(&field.vis) {
+            private = true;
+            continue;
+        }
+        let field_name = match &field.ident {
+            Some(ident) => ident.to_string(),
+            None => i.to_string(),
+        };
+        lines.insert(format!("{}::{}: {}", name, field_name, tokens(&field.ty)));
+    }
+    if private {
+        lines.insert(format!("{} (private fields)", name));
+    }
+}
+
+fn list_impl(path: &str, i: &syn::ItemImpl, features: &[&str], lines: &mut BTreeSet<String>) {
+    let header = match &i.trait_ {
+        Some((bang, trait_, _)) => format!(
+            "{}::impl{} {}{} for {}",
+            path,
+            tokens(&i.generics),
+            if bang.is_some() { "!" } else { "" },
+            tokens(trait_),
+            tokens(&i.self_ty)
+        ),
+        None => format!(
+            "{}::impl{} {}",
+            path,
+            tokens(&i.generics),
+            tokens(&i.self_ty)
+        ),
+    };
+    if i.trait_.is_some() {
+        lines.insert(header);
+        return;
+    }
+
+    for item in &i.items {
+        match item {
+            syn::ImplItem::Fn(f)
+                if is_listed(&f.vis, &f.attrs) && cfg_enabled(&f.attrs, features) =>
+            {
+                lines.insert(format!("{}::{}", header, tokens(&f.sig)));
+            }
+            syn::ImplItem::Const(c)
+                if is_listed(&c.vis, &c.attrs) && cfg_enabled(&c.attrs, features) =>
+            {
+                lines.insert(format!("{}::const {}: {}", header, c.ident, tokens(&c.ty)));
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The derives of an item, with the ones `#[cfg_attr]` adds.
+fn derives(attrs: &[syn::Attribute], features: &[&str]) -> String {
+    let mut derives = Vec::new();
+    for attr in attrs {
+        if attr.path().is_ident("derive") {
+            derives.push(tokens(&attr.meta));
+        } else if attr.path().is_ident("cfg_attr") {
+            let Ok(args) = attr.parse_args_with(
+                syn::punctuated::Punctuated::<syn::Meta, syn::Token![,]>::parse_terminated,
+            ) else {
+                continue;
+            };
+            let mut args = args.into_iter();
+            if args.next().is_some_and(|cfg| eval_cfg(&cfg, features)) {
+                derives.extend(
+                    args.filter(|meta| meta.path().is_ident("derive"))
+                        .map(|meta| tokens(&meta)),
+                );
+            }
+        }
+    }
+    derives
+        .into_iter()
+        .map(|derive| format!("#[{}] ", derive))
+        .collect()
+}
+
+fn attrs(item: &syn::Item) -> &[syn::Attribute] {
+    match item {
+        syn::Item::Const(i) => &i.attrs,
+        syn::Item::Enum(i) => &i.attrs,
+        syn::Item::Fn(i) => &i.attrs,
+        syn::Item::Impl(i) => &i.attrs,
+        syn::Item::Macro(i) => &i.attrs,
+        syn::Item::Mod(i) => &i.attrs,
+        syn::Item::Static(i) => &i.attrs,
+        syn::Item::Struct(i) => &i.attrs,
+        syn::Item::Trait(i) => &i.attrs,
+        syn::Item::Type(i) => &i.attrs,
+        syn::Item::Use(i) => &i.attrs,
+        _ => &[],
+    }
+}
+
+/// If the `#[cfg]`s keep an item with `features` on. Only the features are set,
+/// `not(target_arch = "wasm32")` holds as it does on any other target.
+fn cfg_enabled(attrs: &[syn::Attribute], features: &[&str]) -> bool {
+    attrs.iter().filter(|a| a.path().is_ident("cfg")).all(|a| {
+        a.parse_args::<syn::Meta>()
+            .map(|cfg| eval_cfg(&cfg, features))
+            .unwrap_or(false)
+    })
+}
+
+fn eval_cfg(cfg: &syn::Meta, features: &[&str]) -> bool {
+    match cfg {
+        syn::Meta::NameValue(nv) if nv.path.is_ident("feature") => match &nv.value {
+            syn::Expr::Lit(syn::ExprLit {
+                lit: syn::Lit::Str(s),
+                ..
+            }) => features.contains(&s.value().as_str()),
+            _ => false,
+        },
+        syn::Meta::List(list) => {
+            let Ok(args) = list.parse_args_with(
+                syn::punctuated::Punctuated::<syn::Meta, syn::Token![,]>::parse_terminated,
+            ) else {
+                return false;
+            };
+            if list.path.is_ident("not") {
+                !args.iter().all(|cfg| eval_cfg(cfg, features))
+            } else if list.path.is_ident("all") {
+                args.iter().all(|cfg| eval_cfg(cfg, features))
+            } else if list.path.is_ident("any") {
+                args.iter().any(|cfg| eval_cfg(cfg, features))
+            } else {
+                false
+            }
+        }
+        // `test`, `target_arch` and the like.
+        _ => false,
+    }
+}
+
+fn is_public(vis: &syn::Visibility) -> bool {
+    matches!(vis, syn::Visibility::Public(_))
+}
+
+fn is_hidden(attrs: &[syn::Attribute]) -> bool {
+    attrs.iter().any(|a| {
+        matches!(&a.meta, syn::Meta::List(list)
+            if list.path.is_ident("doc") && list.tokens.to_string() == "hidden")
+    })
+}
+
+fn is_listed(vis: &syn::Visibility, attrs: &[syn::Attribute]) -> bool {
+    is_public(vis) && !is_hidden(attrs)
+}
+
+fn tokens<T: ToTokens>(t: &T) -> String {
+    t.to_token_stream().to_string()
+}
+
+/// The lines only `a` or `b` have, prefixed with `-` and `+`.
+fn diff(a: &[String], b: &[String]) -> Vec<String> {
+    let only_a = a.iter().filter(|line| !b.contains(line));
+    let only_b = b.iter().filter(|line| !a.contains(line));
+    only_a
+        .map(|line| format!("- {}", line))
+        .chain(only_b.map(|line| format!("+ {}", line)))
+        .collect()
+}
+
+fn assert_same_api(src_dir: &Path) {
+    let with_retry = list(src_dir, &["requests", "retry", "websocket"]).unwrap();
+    let without_retry = list(src_dir, &["requests", "websocket"]).unwrap();
+    // Make sure we list what we should, the client is always there.
+    assert!(
+        with_retry
+            .iter()
+            .any(|line| line.starts_with("crate::impl Client::fn set_retry_config")),
+        "{:#?}",
+        with_retry
+    );
+
+    let diff = diff(&with_retry, &without_retry);
+    assert!(
+        diff.is_empty(),
+        "the public API changes without the `retry` feature:\n{}",
+        diff.join("\n")
+    );
+}
+
+#[test_context(TestContext)]
+#[test]
+fn test_public_api_without_retry(ctx: &mut TestContext) {
+    let kittycad = crate::load_json_spec(include_str!("../../../spec.json")).unwrap();
+    let mock = crate::load_json_spec(include_str!("../../tests/mock.json")).unwrap();
+    let cases: Vec<(&str, &openapiv3::OpenAPI, crate::Opts)> = vec![
+        ("token", &kittycad, Default::default()),
+        (
+            "basic-auth",
+            &mock,
+            crate::Opts {
+                basic_auth: true,
+                ..Default::default()
+            },
+        ),
+        (
+            "oauth",
+            &mock,
+            crate::Opts {
+                token_endpoint: Some("https://example.com/token".parse().unwrap()),
+                user_consent_endpoint: Some("https://example.com/consent".parse().unwrap()),
+                ..Default::default()
+            },
+        ),
+    ];
+
+    for (name, spec, opts) in cases {
+        let output: PathBuf = ctx.tmp_dir.join(name);
+        let opts = crate::Opts {
+            output: output.clone(),
+            name: "api".to_string(),
+            target_version: "1.0.0".to_string(),
+            base_url: Some("https://api.example.com".parse().unwrap()),
+            ..opts
+        };
+        crate::generate_library(spec, &opts, false).unwrap();
+        assert_same_api(&output.join("src"));
+    }
+}
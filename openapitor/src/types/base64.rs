@@ -17,9 +17,10 @@ static ALLOWED_DECODING_FORMATS: &[data_encoding::Encoding] = &[
     data_encoding::BASE64_NOPAD,
 ];
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 /// A container for binary that should be base64 encoded in serialisation. In reverse
 /// when deserializing, will decode from many different types of base64 possible.
+/// It compares and hashes by its bytes, whatever base64 it was decoded from.
 pub struct Base64Data(pub Vec<u8>);
 
 impl Base64Data {
@@ -138,4 +139,29 @@ mod tests {
         assert!(Base64Data::try_from("aGVsbG8=").is_ok());
         assert!(Base64Data::try_from("abcdefghij").is_err());
     }
+
+    #[test]
+    fn test_base64_hash_and_order_by_bytes() {
+        // The same bytes, padded and not, standard and url safe.
+        let set: std::collections::HashSet<Base64Data> = ["+/8=", "+/8", "-_8"]
+            .iter()
+            .map(|s| Base64Data::try_from(*s).unwrap())
+            .collect();
+        assert_eq!(set.len(), 1);
+
+        let mut data = vec![
+            Base64Data(vec![2]),
+            Base64Data(vec![1, 2]),
+            Base64Data(vec![1]),
+        ];
+        data.sort();
+        assert_eq!(
+            data,
+            vec![
+                Base64Data(vec![1]),
+                Base64Data(vec![1, 2]),
+                Base64Data(vec![2]),
+            ]
+        );
+    }
 }
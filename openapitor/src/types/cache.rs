@@ -0,0 +1,277 @@
+//! Caches of the responses to `GET` requests, see `Client::set_response_cache`.
+
+use std::{
+    collections::HashMap,
+    hash::{BuildHasher, Hash, Hasher},
+    sync::Mutex,
+    time::{Duration, SystemTime},
+};
+
+/// A response kept in a `ResponseCache`.
+#[derive(Debug, Clone)]
+pub struct CachedResponse {
+    /// The body of the response.
+    pub body: bytes::Bytes,
+    /// The headers of the response.
+    pub headers: reqwest::header::HeaderMap,
+    /// When the response is no longer fresh, from the `max-age` of its
+    /// `Cache-Control` header.
+    pub expires: SystemTime,
+}
+
+impl CachedResponse {
+    /// If the response can still be used instead of asking the server.
+    pub fn is_fresh(&self) -> bool {
+        SystemTime::now() < self.expires
+    }
+
+    /// The response to give back for the cached one, only a `200 OK` is cached.
+    fn response(&self) -> reqwest::Response {
+        let mut response = http::Response::new(self.body.clone());
+        *response.headers_mut() = self.headers.clone();
+        reqwest::Response::from(response)
+    }
+}
+
+/// Where the client keeps the responses to `GET` requests the server says can be
+/// cached, with a `max-age` in their `Cache-Control` header.
+///
+/// Implement this to keep them in the cache of your choice, then install it with
+/// `Client::set_response_cache`. The client only uses a response while it is
+/// fresh, the cache may drop it whenever it likes.
+#[async_trait::async_trait]
+pub trait ResponseCache: std::fmt::Debug + Send + Sync {
+    /// The response kept under `key`, if there is one.
+    async fn get(&self, key: &str) -> Option<CachedResponse>;
+
+    /// Keep `response` under `key`, until `response.expires` at least.
+    async fn put(&self, key: &str, response: CachedResponse);
+}
+
+/// A `ResponseCache` in memory, it keeps at most `capacity` responses. Once it is
+/// full it drops the responses that are no longer fresh, then the ones that
+/// expire first.
+#[derive(Debug)]
+pub struct MemoryCache {
+    capacity: usize,
+    responses: Mutex<HashMap<String, CachedResponse>>,
+}
+
+impl MemoryCache {
+    /// A cache keeping at most `capacity` responses.
+    pub fn new(capacity: usize) -> Self {
+        MemoryCache {
+            capacity,
+            responses: Default::default(),
+        }
+    }
+}
+
+impl Default for MemoryCache {
+    /// A cache keeping at most 1024 responses.
+    fn default() -> Self {
+        MemoryCache::new(1024)
+    }
+}
+
+#[async_trait::async_trait]
+impl ResponseCache for MemoryCache {
+    async fn get(&self, key: &str) -> Option<CachedResponse> {
+        self.responses.lock().ok()?.get(key).cloned()
+    }
+
+    async fn put(&self, key: &str, response: CachedResponse) {
+        let Ok(mut responses) = self.responses.lock() else {
+            return;
+        };
+        if self.capacity == 0 {
+            return;
+        }
+        if !responses.contains_key(key) && responses.len() >= self.capacity {
+            responses.retain(|_, response| response.is_fresh());
+        }
+        while !responses.contains_key(key) && responses.len() >= self.capacity {
+            let Some(first) = responses
+                .iter()
+                .min_by_key(|(_, response)| response.expires)
+                .map(|(key, _)| key.clone())
+            else {
+                break;
+            };
+            responses.remove(&first);
+        }
+        responses.insert(key.to_string(), response);
+    }
+}
+
+/// The key of the response to `request`, `None` if it is not a `GET`. The key is
+/// the URL and a hash of the credentials, so clients with other tokens do not
+/// share their responses.
+///
+/// The hash is keyed with random keys picked once per process, so the credentials
+/// can not be guessed from the keys a `ResponseCache` sees. It also means a cache
+/// kept across processes only finds the responses of the process that put them.
+pub(crate) fn key(request: &reqwest::Request) -> Option<String> {
+    static HASH_KEYS: std::sync::OnceLock<std::collections::hash_map::RandomState> =
+        std::sync::OnceLock::new();
+
+    if request.method() != reqwest::Method::GET {
+        return None;
+    }
+
+    let mut hasher = HASH_KEYS.get_or_init(Default::default).build_hasher();
+    for name in [reqwest::header::AUTHORIZATION, reqwest::header::COOKIE] {
+        request
+            .headers()
+            .get(name)
+            .map(|value| value.as_bytes())
+            .hash(&mut hasher);
+    }
+    Some(format!("{} {:016x}", request.url(), hasher.finish()))
+}
+
+/// How long a response can be cached, from the `max-age` of its `Cache-Control`
+/// header. `None` if it has no `max-age`, or says not to store it.
+pub fn max_age(headers: &reqwest::header::HeaderMap) -> Option<Duration> {
+    let mut max_age = None;
+    for directive in headers
+        .get_all(reqwest::header::CACHE_CONTROL)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+    {
+        let (name, value) = directive
+            .split_once('=')
+            .map_or((directive, None), |(name, value)| (name, Some(value)));
+        match name.trim().to_ascii_lowercase().as_str() {
+            "no-store" | "no-cache" => return None,
+            "max-age" => {
+                max_age = value
+                    .and_then(|value| value.trim().trim_matches('"').parse().ok())
+                    .map(Duration::from_secs);
+            }
+            _ => {}
+        }
+    }
+    max_age.filter(|max_age| !max_age.is_zero())
+}
+
+/// The fresh response kept under `key`, if there is one.
+pub(crate) async fn get(cache: &dyn ResponseCache, key: &str) -> Option<reqwest::Response> {
+    let cached = cache.get(key).await?;
+    cached.is_fresh().then(|| cached.response())
+}
+
+/// Keep `response` under `key` if it is a `200 OK` that can be cached, and give it
+/// back. A response with a `Vary` header is not kept, the key does not have the
+/// headers it varies with.
+pub(crate) async fn put(
+    cache: &dyn ResponseCache,
+    key: &str,
+    response: reqwest::Response,
+) -> Result<reqwest::Response, crate::types::error::Error> {
+    let Some(max_age) = max_age(response.headers())
+        .filter(|_| response.status() == 200 && !response.headers().contains_key(reqwest::header::VARY))
+    else {
+        return Ok(response);
+    };
+
+    let headers = response.headers().clone();
+    let cached = CachedResponse {
+        body: response.bytes().await?,
+        headers,
+        expires: SystemTime::now() + max_age,
+    };
+    cache.put(key, cached.clone()).await;
+    Ok(cached.response())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use super::{max_age, CachedResponse, MemoryCache, ResponseCache};
+
+    #[test]
+    fn test_max_age() {
+        for (cache_control, expected) in [
+            (vec!["max-age=60"], Some(60)),
+            (vec!["public, max-age=60"], Some(60)),
+            (vec!["private", "Max-Age = \"30\""], Some(30)),
+            (vec!["max-age=0"], None),
+            (vec!["max-age=60, no-store"], None),
+            (vec!["no-cache", "max-age=60"], None),
+            (vec!["max-age=soon"], None),
+            (vec!["public"], None),
+            (vec![], None),
+        ] {
+            let mut headers = reqwest::header::HeaderMap::new();
+            for value in &cache_control {
+                headers.append(reqwest::header::CACHE_CONTROL, value.parse().unwrap());
+            }
+            assert_eq!(
+                max_age(&headers),
+                expected.map(Duration::from_secs),
+                "{:?}",
+                cache_control
+            );
+        }
+    }
+
+    #[test]
+    fn test_key() {
+        let request = |method: reqwest::Method, token: &str| {
+            reqwest::Client::new()
+                .request(method, "https://api.example.com/things/good")
+                .bearer_auth(token)
+                .build()
+                .unwrap()
+        };
+
+        let key = super::key(&request(reqwest::Method::GET, "alice")).unwrap();
+        assert!(key.starts_with("https://api.example.com/things/good "));
+        assert_eq!(
+            super::key(&request(reqwest::Method::GET, "alice")),
+            Some(key.clone())
+        );
+        assert_ne!(super::key(&request(reqwest::Method::GET, "bob")), Some(key));
+        assert_eq!(super::key(&request(reqwest::Method::PUT, "alice")), None);
+    }
+
+    #[tokio::test]
+    async fn test_memory_cache_capacity() {
+        let response = |expires_in: i64| CachedResponse {
+            body: Default::default(),
+            headers: Default::default(),
+            expires: if expires_in < 0 {
+                SystemTime::now() - Duration::from_secs(expires_in.unsigned_abs())
+            } else {
+                SystemTime::now() + Duration::from_secs(expires_in as u64)
+            },
+        };
+
+        // Once full, the stale responses go first.
+        let cache = MemoryCache::new(2);
+        cache.put("stale", response(-60)).await;
+        cache.put("fresh", response(60)).await;
+        cache.put("new", response(30)).await;
+        assert!(cache.get("stale").await.is_none());
+        assert!(cache.get("fresh").await.is_some());
+        assert!(cache.get("new").await.is_some());
+
+        // Then the ones that expire first.
+        cache.put("newer", response(90)).await;
+        assert!(cache.get("new").await.is_none());
+        assert!(cache.get("fresh").await.is_some());
+        assert!(cache.get("newer").await.is_some());
+
+        // Replacing a response does not drop another one.
+        cache.put("fresh", response(10)).await;
+        assert!(cache.get("fresh").await.is_some());
+        assert!(cache.get("newer").await.is_some());
+
+        let cache = MemoryCache::new(0);
+        cache.put("fresh", response(60)).await;
+        assert!(cache.get("fresh").await.is_none());
+    }
+}
@@ -0,0 +1,95 @@
+//! How a client changes the headers of its calls.
+
+/// What the client does with a header it sets by default.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum DefaultHeader {
+    /// Send the header the client sets.
+    #[default]
+    Default,
+    /// Do not send the header.
+    Suppress,
+    /// Send this value instead.
+    Override(reqwest::header::HeaderValue),
+}
+
+/// Options of the calls of a client, see `Client::with_call_options`. The default
+/// changes nothing.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CallOptions {
+    /// The `Accept` header, the one of the operation by default.
+    ///
+    /// Without an `Accept` header reqwest sends its own, `*/*`.
+    pub accept: DefaultHeader,
+    /// The `Content-Type` header, the one of the body by default. A call without a
+    /// body has none.
+    pub content_type: DefaultHeader,
+    /// More headers to send, they replace the headers of the same name the client
+    /// sets.
+    pub headers: reqwest::header::HeaderMap,
+}
+
+impl CallOptions {
+    /// Change the headers of `request`.
+    pub(crate) fn apply(&self, mut request: reqwest::Request) -> reqwest::Request {
+        let headers = request.headers_mut();
+        for (name, header) in [
+            (reqwest::header::ACCEPT, &self.accept),
+            (reqwest::header::CONTENT_TYPE, &self.content_type),
+        ] {
+            match header {
+                DefaultHeader::Default => {}
+                DefaultHeader::Suppress => {
+                    headers.remove(name);
+                }
+                DefaultHeader::Override(value) => {
+                    headers.insert(name, value.clone());
+                }
+            }
+        }
+        for (name, value) in &self.headers {
+            headers.insert(name, value.clone());
+        }
+        request
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CallOptions, DefaultHeader};
+
+    fn request() -> reqwest::Request {
+        reqwest::Client::new()
+            .put("https://api.example.com/things")
+            .header(reqwest::header::ACCEPT, "application/json")
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header("x-tenant", "zoo")
+            .body("{}")
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_apply() {
+        // The default changes nothing.
+        let applied = CallOptions::default().apply(request());
+        assert_eq!(applied.headers(), request().headers());
+
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert("x-tenant", "acme".parse().unwrap());
+        headers.insert("x-signature", "abc".parse().unwrap());
+        let applied = CallOptions {
+            accept: DefaultHeader::Override("application/vnd.thing+json".parse().unwrap()),
+            content_type: DefaultHeader::Suppress,
+            headers,
+        }
+        .apply(request());
+        let headers = applied.headers();
+        assert_eq!(
+            headers[reqwest::header::ACCEPT],
+            "application/vnd.thing+json"
+        );
+        assert!(!headers.contains_key(reqwest::header::CONTENT_TYPE));
+        assert_eq!(headers["x-tenant"], "acme");
+        assert_eq!(headers["x-signature"], "abc");
+    }
+}
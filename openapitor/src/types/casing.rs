@@ -0,0 +1,231 @@
+//! Turn the names of the spec into snake case names.
+//!
+//! inflector splits some acronyms apart: `OAuth2Client` becomes `o_auth_2_client`
+//! and `userIDs` becomes `user_i_ds`. With `--acronym-casing` the acronyms of
+//! `--acronym` stay one word, `oauth2_client` and `user_ids`, for the functions, the
+//! fields and the tags. The fields keep their wire names with serde.
+//!
+//! The casing is set for the generation running on the thread, see `scoped`.
+
+use std::cell::RefCell;
+
+/// The acronyms that stay one word with `--acronym-casing`, unless `--acronym`
+/// gives others.
+pub const DEFAULT_ACRONYMS: &[&str] = &["OAuth2", "AI", "API", "ID", "ML", "URL", "GPU"];
+
+thread_local! {
+    /// The acronyms of the generation running on this thread, `None` for the
+    /// casing of inflector.
+    static ACRONYMS: RefCell<Option<Vec<String>>> = const { RefCell::new(None) };
+}
+
+/// Puts back the casing from before `scoped` when it is dropped.
+#[must_use]
+pub struct CasingGuard {
+    previous: Option<Vec<String>>,
+}
+
+impl Drop for CasingGuard {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        ACRONYMS.with(|acronyms| *acronyms.borrow_mut() = previous);
+    }
+}
+
+/// Use the casing of `opts` on this thread until the guard is dropped.
+pub fn scoped(opts: &crate::Opts) -> CasingGuard {
+    let acronyms = if opts.acronym_casing {
+        Some(opts.acronyms.clone())
+    } else {
+        None
+    };
+    CasingGuard {
+        previous: ACRONYMS.with(|current| current.replace(acronyms)),
+    }
+}
+
+/// Snake case `s` with the casing of the generation.
+pub fn to_snake_case(s: &str) -> String {
+    ACRONYMS.with(|acronyms| match &*acronyms.borrow() {
+        Some(acronyms) => to_snake_case_with_acronyms(s, acronyms),
+        None => inflector::cases::snakecase::to_snake_case(s),
+    })
+}
+
+/// Snake case `s`, keeping `acronyms` as one word. An acronym is found as it is
+/// written, as in `userID` or in the plural `userIDs`, capitalized, as in
+/// `Oauth2Client`, or in lower case between word boundaries, as in
+/// `get_oauth2_token`.
+pub fn to_snake_case_with_acronyms<S: AsRef<str>>(s: &str, acronyms: &[S]) -> String {
+    let chars: Vec<char> = s.chars().collect();
+    let mut words = Vec::new();
+    let mut rest = String::new();
+
+    let mut i = 0;
+    while i < chars.len() {
+        match find_acronym(&chars, i, acronyms) {
+            Some(len) => {
+                words.push(inflector::cases::snakecase::to_snake_case(&rest));
+                rest.clear();
+                words.push(chars[i..i + len].iter().collect::<String>().to_lowercase());
+                i += len;
+            }
+            None => {
+                rest.push(chars[i]);
+                i += 1;
+            }
+        }
+    }
+    words.push(inflector::cases::snakecase::to_snake_case(&rest));
+
+    words.retain(|word| !word.is_empty());
+    words.join("_")
+}
+
+/// The length of the acronym starting a word at `i`, if any, with its plural `s`.
+fn find_acronym<S: AsRef<str>>(chars: &[char], i: usize, acronyms: &[S]) -> Option<usize> {
+    acronyms.iter().find_map(|acronym| {
+        let acronym: Vec<char> = acronym.as_ref().chars().collect();
+        let end = i + acronym.len();
+        let found = chars.get(i..end)?;
+        if acronym.is_empty() {
+            return None;
+        }
+
+        let lower: Vec<char> = acronym.iter().map(|c| c.to_ascii_lowercase()).collect();
+        let capitalized: Vec<char> = lower
+            .iter()
+            .enumerate()
+            .map(|(j, c)| if j == 0 { c.to_ascii_uppercase() } else { *c })
+            .collect();
+        let before = i.checked_sub(1).map(|j| chars[j]);
+        let after = chars.get(end).copied();
+        let is_boundary = |c: Option<char>| c.is_none_or(|c| !c.is_alphanumeric());
+
+        let starts_word = if found == lower.as_slice() {
+            // In lower case only whole words count, `id` is not in `valid`.
+            is_boundary(before) && (is_boundary(after) || after.is_some_and(char::is_uppercase))
+        } else if found == acronym.as_slice() || found == capitalized.as_slice() {
+            // In camel case the acronym follows a lower case word or a digit, and
+            // the next word starts with a capital, `AI` is in `AIPrompt` but not in
+            // `AID` or `EMAIL`.
+            let starts = is_boundary(before)
+                || before.is_some_and(|c| c.is_lowercase() || c.is_ascii_digit());
+            let ends_upper = found[found.len() - 1].is_uppercase();
+            // The word after an acronym in capitals starts with a capital and goes
+            // on in lower case, unless it is an acronym too.
+            let ends_at = |end: usize| match chars.get(end) {
+                None => true,
+                Some(c) if !c.is_alphanumeric() => true,
+                Some(c) if c.is_uppercase() => {
+                    !ends_upper
+                        || chars.get(end + 1).is_some_and(|c| c.is_lowercase())
+                        || find_acronym(chars, end, acronyms).is_some()
+                }
+                Some(_) => false,
+            };
+            if starts && ends_upper && after == Some('s') && ends_at(end + 1) {
+                return Some(acronym.len() + 1);
+            }
+            starts && ends_at(end)
+        } else {
+            false
+        };
+
+        starts_word.then_some(acronym.len())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::{to_snake_case_with_acronyms, DEFAULT_ACRONYMS};
+
+    #[test]
+    fn test_to_snake_case_with_acronyms() {
+        for (name, expected) in [
+            // The acronyms as they are written.
+            ("OAuth2Client", "oauth2_client"),
+            ("OAuth2", "oauth2"),
+            ("AIPrompt", "ai_prompt"),
+            ("userID", "user_id"),
+            ("UserID", "user_id"),
+            ("getAPIKey", "get_api_key"),
+            ("MLModel", "ml_model"),
+            ("imageURL", "image_url"),
+            ("GPUCount", "gpu_count"),
+            ("OAuth2APIKey", "oauth2_api_key"),
+            ("listAIPromptIDs", "list_ai_prompt_ids"),
+            ("APIs", "apis"),
+            ("getUserIDsAndNames", "get_user_ids_and_names"),
+            // Capitalized.
+            ("Oauth2Client", "oauth2_client"),
+            ("ApiToken", "api_token"),
+            ("userId", "user_id"),
+            // In lower case, between word boundaries.
+            ("oauth2_client", "oauth2_client"),
+            ("get_oauth2_token", "get_oauth2_token"),
+            ("oauth2", "oauth2"),
+            ("user_id", "user_id"),
+            ("ai-prompt", "ai_prompt"),
+            ("oauth2Client", "oauth2_client"),
+            ("device_auth_verify", "device_auth_verify"),
+            // Not acronyms.
+            ("valid", "valid"),
+            ("idle_timeout", "idle_timeout"),
+            ("EMAIL", "email"),
+            ("email_address", "email_address"),
+            ("AID", "aid"),
+            ("UUID", "uuid"),
+            ("HTMLParser", "html_parser"),
+            ("rapid", "rapid"),
+            ("IDse", "i_dse"),
+            ("curl_options", "curl_options"),
+            // Everything else is cased by inflector.
+            ("", ""),
+            ("createdAt", "created_at"),
+            ("some-thing else", "some_thing_else"),
+            ("v1", "v_1"),
+            ("Md5", "md_5"),
+            ("_links", "links"),
+        ] {
+            assert_eq!(
+                to_snake_case_with_acronyms(name, DEFAULT_ACRONYMS),
+                expected,
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn test_scoped() {
+        let opts = crate::Opts {
+            acronym_casing: true,
+            ..Default::default()
+        };
+
+        assert_eq!(super::to_snake_case("OAuth2Client"), "o_auth_2_client");
+        {
+            let _casing = super::scoped(&opts);
+            assert_eq!(super::to_snake_case("OAuth2Client"), "oauth2_client");
+            {
+                // Generations nest, the inner one puts back the outer casing.
+                let _casing = super::scoped(&Default::default());
+                assert_eq!(super::to_snake_case("userIDs"), "user_i_ds");
+            }
+            assert_eq!(super::to_snake_case("userIDs"), "user_ids");
+        }
+        assert_eq!(super::to_snake_case("userIDs"), "user_i_ds");
+
+        // The acronyms can be others.
+        let opts = crate::Opts {
+            acronyms: vec!["SSO".to_string()],
+            ..opts
+        };
+        let _casing = super::scoped(&opts);
+        assert_eq!(super::to_snake_case("listSSOs"), "list_ssos");
+        assert_eq!(super::to_snake_case("OAuth2"), "o_auth_2");
+    }
+}
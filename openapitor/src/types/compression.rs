@@ -0,0 +1,158 @@
+//! How the client compresses the bodies of its requests.
+
+use std::io::Write;
+
+/// The size of the smallest body we compress by default, 64 KiB. Smaller bodies
+/// are not worth the time it takes.
+pub const DEFAULT_MIN_SIZE: usize = 64 * 1024;
+
+/// How the client compresses the JSON bodies of its requests, the server must
+/// accept the `Content-Encoding`. Multipart, binary and streamed bodies are always
+/// sent as they are, and so are the responses, which are not ours to compress.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CompressionMode {
+    /// Send the bodies as they are.
+    #[default]
+    None,
+    /// Compress with gzip the bodies of at least `min_size` bytes.
+    Gzip {
+        /// The size of the smallest body to compress, in bytes.
+        min_size: usize,
+    },
+    /// Compress with deflate the bodies of at least `min_size` bytes.
+    Deflate {
+        /// The size of the smallest body to compress, in bytes.
+        min_size: usize,
+    },
+}
+
+impl CompressionMode {
+    /// Compress with gzip the bodies of at least `DEFAULT_MIN_SIZE` bytes.
+    pub fn gzip() -> Self {
+        CompressionMode::Gzip {
+            min_size: DEFAULT_MIN_SIZE,
+        }
+    }
+
+    /// Compress with deflate the bodies of at least `DEFAULT_MIN_SIZE` bytes.
+    pub fn deflate() -> Self {
+        CompressionMode::Deflate {
+            min_size: DEFAULT_MIN_SIZE,
+        }
+    }
+
+    /// Compress the body of `request` if it is JSON and big enough, setting the
+    /// `Content-Encoding`. A body that fails to compress is sent as it is.
+    pub(crate) fn compress(&self, mut request: reqwest::Request) -> reqwest::Request {
+        let (encoding, min_size) = match *self {
+            CompressionMode::None => return request,
+            CompressionMode::Gzip { min_size } => ("gzip", min_size),
+            CompressionMode::Deflate { min_size } => ("deflate", min_size),
+        };
+
+        let headers = request.headers();
+        let is_json = headers
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.starts_with("application/json"));
+        if !is_json || headers.contains_key(reqwest::header::CONTENT_ENCODING) {
+            return request;
+        }
+        // A streamed body has no bytes to compress.
+        let Some(body) = request.body().and_then(|body| body.as_bytes()) else {
+            return request;
+        };
+        if body.len() < min_size {
+            return request;
+        }
+
+        let compressed = if encoding == "gzip" {
+            let mut encoder =
+                flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(body).and_then(|_| encoder.finish())
+        } else {
+            let mut encoder =
+                flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(body).and_then(|_| encoder.finish())
+        };
+        let Ok(compressed) = compressed else {
+            return request;
+        };
+
+        request.headers_mut().insert(
+            reqwest::header::CONTENT_ENCODING,
+            reqwest::header::HeaderValue::from_static(encoding),
+        );
+        *request.body_mut() = Some(compressed.into());
+        request
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use super::CompressionMode;
+
+    fn request(content_type: &str, body: &str) -> reqwest::Request {
+        reqwest::Client::new()
+            .put("https://api.example.com/things")
+            .header(reqwest::header::CONTENT_TYPE, content_type)
+            .body(body.to_string())
+            .build()
+            .unwrap()
+    }
+
+    fn body(request: &reqwest::Request) -> &[u8] {
+        request.body().unwrap().as_bytes().unwrap()
+    }
+
+    #[test]
+    fn test_compress() {
+        let json = serde_json::to_string(&vec!["thing"; 100]).unwrap();
+
+        // Big enough JSON is compressed.
+        let compressed =
+            CompressionMode::Gzip { min_size: 100 }.compress(request("application/json", &json));
+        assert_eq!(
+            compressed.headers()[reqwest::header::CONTENT_ENCODING],
+            "gzip"
+        );
+        let mut decompressed = String::new();
+        flate2::read::GzDecoder::new(body(&compressed))
+            .read_to_string(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, json);
+
+        let compressed =
+            CompressionMode::Deflate { min_size: 100 }.compress(request("application/json", &json));
+        assert_eq!(
+            compressed.headers()[reqwest::header::CONTENT_ENCODING],
+            "deflate"
+        );
+        let mut decompressed = String::new();
+        flate2::read::ZlibDecoder::new(body(&compressed))
+            .read_to_string(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, json);
+
+        // Small bodies, other content types and no compression are sent as they are.
+        for (mode, content_type) in [
+            (
+                CompressionMode::Gzip { min_size: 10_000 },
+                "application/json",
+            ),
+            (
+                CompressionMode::Gzip { min_size: 100 },
+                "application/octet-stream",
+            ),
+            (CompressionMode::None, "application/json"),
+        ] {
+            let request = mode.compress(request(content_type, &json));
+            assert!(!request
+                .headers()
+                .contains_key(reqwest::header::CONTENT_ENCODING));
+            assert_eq!(body(&request), json.as_bytes());
+        }
+    }
+}
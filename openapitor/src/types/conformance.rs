@@ -0,0 +1,195 @@
+//! The checks of the wire compatibility tests of `--generate-conformance-tests`:
+//! an example of the spec must read into its type and be written back the same.
+
+/// Check that the JSON `example` of `schema`, at `pointer` in the spec, reads into
+/// a `T` that is written back as the same JSON.
+pub fn assert_round_trip<T>(schema: &str, pointer: &str, example: &str)
+where
+    T: serde::de::DeserializeOwned + serde::Serialize,
+{
+    let expected: serde_json::Value = serde_json::from_str(example).unwrap_or_else(|err| {
+        panic!(
+            "the example of `{}` at `{}` is not JSON: {}",
+            schema, pointer, err
+        )
+    });
+    let value: T = serde_json::from_str(example).unwrap_or_else(|err| {
+        panic!(
+            "the example of `{}` at `{}` does not deserialize: {}",
+            schema, pointer, err
+        )
+    });
+    let actual = serde_json::to_value(&value).unwrap_or_else(|err| {
+        panic!(
+            "the example of `{}` at `{}` does not serialize: {}",
+            schema, pointer, err
+        )
+    });
+    if let Some(difference) = difference(&expected, &actual) {
+        panic!(
+            "the example of `{}` at `{}` is not written back the same: {}",
+            schema, pointer, difference
+        );
+    }
+}
+
+/// Where the JSON we wrote first differs from the example, if it does. The order
+/// of the fields does not count, a `null` field is the same as a missing one,
+/// numbers are compared by value, so `1` is `1.0`, and so are the UTC date-times,
+/// so `2024-08-20T00:00:00.000+00:00` is `2024-08-20T00:00:00Z`.
+pub fn difference(expected: &serde_json::Value, actual: &serde_json::Value) -> Option<String> {
+    difference_at(expected, actual, "")
+}
+
+fn difference_at(
+    expected: &serde_json::Value,
+    actual: &serde_json::Value,
+    pointer: &str,
+) -> Option<String> {
+    use serde_json::Value;
+
+    match (expected, actual) {
+        (Value::Object(expected), Value::Object(actual)) => {
+            let mut keys = expected.keys().collect::<Vec<_>>();
+            keys.extend(actual.keys().filter(|key| !expected.contains_key(*key)));
+            keys.into_iter().find_map(|key| {
+                let pointer = format!("{}/{}", pointer, key.replace('~', "~0").replace('/', "~1"));
+                difference_at(
+                    expected.get(key).unwrap_or(&Value::Null),
+                    actual.get(key).unwrap_or(&Value::Null),
+                    &pointer,
+                )
+            })
+        }
+        (Value::Array(expected_items), Value::Array(actual_items))
+            if expected_items.len() == actual_items.len() =>
+        {
+            expected_items
+                .iter()
+                .zip(actual_items)
+                .enumerate()
+                .find_map(|(i, (expected, actual))| {
+                    difference_at(expected, actual, &format!("{}/{}", pointer, i))
+                })
+        }
+        (Value::Number(expected), Value::Number(actual))
+            if expected.as_f64() == actual.as_f64() =>
+        {
+            None
+        }
+        (Value::String(expected), Value::String(actual))
+            if utc_date_time(expected) == utc_date_time(actual) =>
+        {
+            None
+        }
+        _ if expected == actual => None,
+        _ => Some(format!(
+            "at `{}` the example has {} but we wrote {}",
+            if pointer.is_empty() { "/" } else { pointer },
+            expected,
+            actual
+        )),
+    }
+}
+
+/// A date-time in UTC written the way `chrono` writes it, with `Z` and without a
+/// fraction of zeros, or the string as it is.
+fn utc_date_time(s: &str) -> String {
+    let Some(local) = s
+        .strip_suffix("+00:00")
+        .or_else(|| s.strip_suffix("-00:00"))
+        .or_else(|| s.strip_suffix('Z'))
+    else {
+        return s.to_string();
+    };
+    if !local.contains('T') {
+        return s.to_string();
+    }
+    let local = match local.split_once('.') {
+        Some((seconds, fraction)) if fraction.bytes().all(|b| b == b'0') => seconds,
+        _ => local,
+    };
+    format!("{}Z", local)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{assert_round_trip, difference};
+
+    #[derive(serde::Serialize, serde::Deserialize)]
+    struct Point {
+        x: f64,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        label: Option<String>,
+    }
+
+    #[test]
+    fn test_difference() {
+        let value = |s: &str| serde_json::from_str::<serde_json::Value>(s).unwrap();
+
+        // The order of the fields, the missing ones and the kind of numbers do not
+        // count.
+        assert_eq!(
+            difference(
+                &value(r#"{"a": 1, "b": [1.0, 2], "c": null}"#),
+                &value(r#"{"b": [1, 2.0], "a": 1.0}"#)
+            ),
+            None
+        );
+
+        assert_eq!(
+            difference(
+                &value(r#"["2024-08-20T00:00:00.000+00:00", "2024-08-20T10:00:00.5Z"]"#),
+                &value(r#"["2024-08-20T00:00:00Z", "2024-08-20T10:00:00.5Z"]"#)
+            ),
+            None
+        );
+
+        // The rest points at where it is.
+        assert_eq!(
+            difference(
+                &value(r#"{"a": {"b/c": [1, "x"]}}"#),
+                &value(r#"{"a": {"b/c": [1, "y"]}}"#)
+            )
+            .as_deref(),
+            Some(r#"at `/a/b~1c/1` the example has "x" but we wrote "y""#)
+        );
+        assert_eq!(
+            difference(&value(r#"[1]"#), &value(r#"[1, 2]"#)).as_deref(),
+            Some("at `/` the example has [1] but we wrote [1,2]")
+        );
+        assert_eq!(
+            difference(
+                &value(r#""2024-08-20T00:00:00+01:00""#),
+                &value(r#""2024-08-20T00:00:00Z""#)
+            )
+            .as_deref(),
+            Some(
+                r#"at `/` the example has "2024-08-20T00:00:00+01:00" but we wrote "2024-08-20T00:00:00Z""#
+            )
+        );
+        assert_eq!(
+            difference(&value(r#"{}"#), &value(r#"{"extra": true}"#)).as_deref(),
+            Some("at `/extra` the example has null but we wrote true")
+        );
+    }
+
+    #[test]
+    fn test_assert_round_trip() {
+        assert_round_trip::<Point>("Point", "#/components/schemas/Point/example", r#"{"x": 1}"#);
+        assert_round_trip::<Point>(
+            "Point",
+            "#/components/schemas/Point/example",
+            r#"{"x": 1.5, "label": null}"#,
+        );
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "the example of `Point` at `#/components/schemas/Point/example` does not \
+                    deserialize: missing field `x`"
+    )]
+    fn test_assert_round_trip_missing_field() {
+        assert_round_trip::<Point>("Point", "#/components/schemas/Point/example", r#"{"y": 1}"#);
+    }
+}
@@ -0,0 +1,16 @@
+//! The date and time types used by the API, backed by `chrono`.
+//!
+//! Every generated type refers to these aliases rather than to `chrono` directly,
+//! so the backing crate can be switched by regenerating the library.
+
+/// A date and time in UTC, `date-time` in the spec.
+pub type DateTime = chrono::DateTime<chrono::Utc>;
+
+/// A calendar date, `date` in the spec.
+pub type Date = chrono::NaiveDate;
+
+/// A time of day, `time` in the spec.
+pub type Time = chrono::NaiveTime;
+
+/// A date and time without a time zone, `partial-date-time` in the spec.
+pub type PartialDateTime = chrono::NaiveDateTime;
@@ -0,0 +1,165 @@
+//! The date and time types used by the API, backed by `time`.
+//!
+//! Every generated type refers to these types rather than to `time` directly, so
+//! the backing crate can be switched by regenerating the library. They serialize
+//! the same way as the spec describes them, `date-time` as RFC 3339 for example.
+
+use time::{
+    format_description::{well_known::Rfc3339, BorrowedFormatItem},
+    macros::format_description,
+};
+
+const DATE: &[BorrowedFormatItem<'_>] = format_description!("[year]-[month]-[day]");
+const TIME: &[BorrowedFormatItem<'_>] =
+    format_description!("[hour]:[minute]:[second][optional [.[subsecond]]]");
+const WHOLE_TIME: &[BorrowedFormatItem<'_>] = format_description!("[hour]:[minute]:[second]");
+const PARTIAL_DATE_TIME: &[BorrowedFormatItem<'_>] =
+    format_description!("[year]-[month]-[day]T[hour]:[minute]:[second][optional [.[subsecond]]]");
+const WHOLE_PARTIAL_DATE_TIME: &[BorrowedFormatItem<'_>] =
+    format_description!("[year]-[month]-[day]T[hour]:[minute]:[second]");
+
+/// Implement the traits every generated type needs for a wrapper around a `time` type.
+macro_rules! time_wrapper {
+    ($name:ident, $inner:ty, $format:literal, $default:expr, $parse:expr, $fmt:expr) => {
+        impl Default for $name {
+            fn default() -> Self {
+                Self($default)
+            }
+        }
+
+        impl From<$inner> for $name {
+            fn from(inner: $inner) -> Self {
+                Self(inner)
+            }
+        }
+
+        impl From<$name> for $inner {
+            fn from(wrapper: $name) -> Self {
+                wrapper.0
+            }
+        }
+
+        impl std::ops::Deref for $name {
+            type Target = $inner;
+
+            fn deref(&self) -> &Self::Target {
+                &self.0
+            }
+        }
+
+        impl std::str::FromStr for $name {
+            type Err = time::error::Parse;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                let parse: fn(&str) -> Result<$inner, time::error::Parse> = $parse;
+                parse(s).map(Self)
+            }
+        }
+
+        impl std::fmt::Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                let format: fn(&$inner) -> Result<String, time::error::Format> = $fmt;
+                let s = format(&self.0).map_err(|_| std::fmt::Error)?;
+                f.write_str(&s)
+            }
+        }
+
+        impl serde::Serialize for $name {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: serde::Serializer,
+            {
+                serializer.collect_str(self)
+            }
+        }
+
+        impl<'de> serde::Deserialize<'de> for $name {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: serde::Deserializer<'de>,
+            {
+                let s = String::deserialize(deserializer)?;
+                s.parse().map_err(serde::de::Error::custom)
+            }
+        }
+
+        impl schemars::JsonSchema for $name {
+            fn schema_name() -> String {
+                stringify!($name).to_string()
+            }
+
+            fn json_schema(gen: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+                let mut obj = gen.root_schema_for::<String>().schema;
+                obj.format = Some($format.to_string());
+                schemars::schema::Schema::Object(obj)
+            }
+
+            fn is_referenceable() -> bool {
+                false
+            }
+        }
+    };
+}
+
+/// A date and time in UTC, `date-time` in the spec.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct DateTime(pub time::OffsetDateTime);
+
+time_wrapper!(
+    DateTime,
+    time::OffsetDateTime,
+    "date-time",
+    time::OffsetDateTime::UNIX_EPOCH,
+    |s| time::OffsetDateTime::parse(s, &Rfc3339).map(|t| t.to_offset(time::UtcOffset::UTC)),
+    |t| t.format(&Rfc3339)
+);
+
+/// A calendar date, `date` in the spec.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Date(pub time::Date);
+
+time_wrapper!(
+    Date,
+    time::Date,
+    "date",
+    time::OffsetDateTime::UNIX_EPOCH.date(),
+    |s| time::Date::parse(s, DATE),
+    |d| d.format(DATE)
+);
+
+/// A time of day, `time` in the spec.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Time(pub time::Time);
+
+time_wrapper!(
+    Time,
+    time::Time,
+    "time",
+    time::Time::MIDNIGHT,
+    |s| time::Time::parse(s, TIME),
+    |t| if t.nanosecond() == 0 {
+        t.format(WHOLE_TIME)
+    } else {
+        t.format(TIME)
+    }
+);
+
+/// A date and time without a time zone, `partial-date-time` in the spec.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PartialDateTime(pub time::PrimitiveDateTime);
+
+time_wrapper!(
+    PartialDateTime,
+    time::PrimitiveDateTime,
+    "partial-date-time",
+    time::PrimitiveDateTime::new(
+        time::OffsetDateTime::UNIX_EPOCH.date(),
+        time::Time::MIDNIGHT
+    ),
+    |s| time::PrimitiveDateTime::parse(s, PARTIAL_DATE_TIME),
+    |t| if t.nanosecond() == 0 {
+        t.format(WHOLE_PARTIAL_DATE_TIME)
+    } else {
+        t.format(PARTIAL_DATE_TIME)
+    }
+);
@@ -0,0 +1,112 @@
+//! The deprecation of the operations reported by the API, with the `Deprecation`
+//! header of RFC 9745 and the `Sunset` header of RFC 8594.
+
+/// What a response said about the deprecation of its operation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Deprecation {
+    /// The `Deprecation` header, when the operation was deprecated, like
+    /// `@1688169599`.
+    pub deprecation: Option<String>,
+    /// The `Sunset` header, when the operation stops working, like
+    /// `Sun, 01 Jun 2025 00:00:00 GMT`.
+    pub sunset: Option<String>,
+}
+
+impl Deprecation {
+    /// Read the `Deprecation` and `Sunset` headers. Returns `None` if there are
+    /// neither.
+    pub fn from_headers(headers: &reqwest::header::HeaderMap) -> Option<Self> {
+        let get = |name: &str| -> Option<String> {
+            Some(headers.get(name)?.to_str().ok()?.trim().to_string())
+        };
+
+        match (get("Deprecation"), get("Sunset")) {
+            (None, None) => None,
+            (deprecation, sunset) => Some(Deprecation {
+                deprecation,
+                sunset,
+            }),
+        }
+    }
+}
+
+impl std::fmt::Display for Deprecation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.sunset {
+            Some(sunset) => write!(f, "it will be removed on {}", sunset),
+            None => write!(f, "it will be removed"),
+        }
+    }
+}
+
+/// The operations we warned about, for the whole process.
+static WARNED: std::sync::OnceLock<std::sync::Mutex<std::collections::BTreeSet<String>>> =
+    std::sync::OnceLock::new();
+
+/// Warn that the API deprecated an operation if its response says so, the first
+/// time only: the calls after it would repeat the same warning. Returns if we
+/// warned.
+pub fn warn_once(operation_id: &str, headers: &reqwest::header::HeaderMap) -> bool {
+    let Some(deprecation) = Deprecation::from_headers(headers) else {
+        return false;
+    };
+    let Ok(mut warned) = WARNED.get_or_init(Default::default).lock() else {
+        return false;
+    };
+    if !warned.insert(operation_id.to_string()) {
+        return false;
+    }
+
+    log::warn!(
+        "the API deprecated the operation `{}`, {}",
+        operation_id,
+        deprecation
+    );
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Deprecation;
+
+    fn headers(values: &[(&'static str, &'static str)]) -> reqwest::header::HeaderMap {
+        let mut headers = reqwest::header::HeaderMap::new();
+        for (name, value) in values {
+            headers.insert(*name, reqwest::header::HeaderValue::from_static(value));
+        }
+        headers
+    }
+
+    #[test]
+    fn test_deprecation_from_headers() {
+        let deprecation = Deprecation::from_headers(&headers(&[
+            ("deprecation", "@1688169599"),
+            ("sunset", " Sun, 01 Jun 2025 00:00:00 GMT"),
+        ]))
+        .unwrap();
+        assert_eq!(deprecation.deprecation.as_deref(), Some("@1688169599"));
+        assert_eq!(
+            deprecation.to_string(),
+            "it will be removed on Sun, 01 Jun 2025 00:00:00 GMT"
+        );
+
+        let deprecation = Deprecation::from_headers(&headers(&[("deprecation", "true")])).unwrap();
+        assert_eq!(deprecation.sunset, None);
+        assert_eq!(deprecation.to_string(), "it will be removed");
+
+        assert_eq!(
+            Deprecation::from_headers(&headers(&[("x-request-id", "1")])),
+            None
+        );
+    }
+
+    #[test]
+    fn test_deprecation_warn_once() {
+        let deprecated = headers(&[("sunset", "Sun, 01 Jun 2025 00:00:00 GMT")]);
+
+        assert!(!super::warn_once("test_warn_once", &headers(&[])));
+        assert!(super::warn_once("test_warn_once", &deprecated));
+        assert!(!super::warn_once("test_warn_once", &deprecated));
+        assert!(super::warn_once("test_warn_once_other", &deprecated));
+    }
+}
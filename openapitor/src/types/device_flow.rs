@@ -0,0 +1,260 @@
+//! The OAuth 2.0 device authorization grant of RFC 8628, on top of its operations.
+//!
+//! `DeviceFlow::start` asks for a code, that the user enters at the verification
+//! uri, then `DeviceFlow::wait` polls for the token until they authorize the
+//! device, deny it, or the code expires.
+
+/// How much longer to wait between the polls every time the server asks us to slow
+/// down, as RFC 8628 says.
+pub const SLOW_DOWN: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// How long to wait between the polls when the server does not say, as RFC 8628
+/// says.
+pub const DEFAULT_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// What the server answered when we started the flow, show the user the code and
+/// where to enter it.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize)]
+pub struct DeviceAuthorization {
+    /// The code of the device, we send it back when we poll.
+    pub device_code: String,
+    /// The code the user enters at the verification uri.
+    pub user_code: String,
+    /// Where the user enters the code.
+    pub verification_uri: String,
+    /// Where the user goes to authorize the device without entering the code, if
+    /// the server has it.
+    #[serde(default)]
+    pub verification_uri_complete: Option<String>,
+    /// How many seconds the codes are good for.
+    pub expires_in: u64,
+    /// How many seconds to wait between the polls, if the server says.
+    #[serde(default)]
+    pub interval: Option<u64>,
+}
+
+/// The token the server granted once the user authorized the device.
+#[derive(Clone, PartialEq, Eq, serde::Deserialize)]
+pub struct DeviceToken {
+    /// The access token.
+    pub access_token: String,
+    /// The type of the token, most often `Bearer`.
+    pub token_type: String,
+    /// How many seconds the access token is good for, if the server says.
+    #[serde(default)]
+    pub expires_in: Option<u64>,
+    /// The token to get a new access token with, if the server granted one.
+    #[serde(default)]
+    pub refresh_token: Option<String>,
+    /// The scopes of the access token, if they are not the ones we asked for.
+    #[serde(default)]
+    pub scope: Option<String>,
+}
+
+impl std::fmt::Debug for DeviceToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DeviceToken")
+            .field("token_type", &self.token_type)
+            .field("expires_in", &self.expires_in)
+            .field("scope", &self.scope)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Why the device flow did not end with a token.
+#[derive(Debug, thiserror::Error)]
+pub enum DeviceFlowError {
+    /// The user denied the authorization.
+    #[error("the user denied the authorization of the device")]
+    AccessDenied,
+    /// The codes expired before the user authorized the device, start over.
+    #[error("the device code expired before the user authorized the device")]
+    Expired,
+    /// The server ended the flow with another error of the OAuth 2.0 token
+    /// endpoint, like `invalid_client`.
+    #[error("the device flow failed with `{error}`")]
+    OAuth {
+        /// The error code, like `invalid_client`.
+        error: String,
+        /// What went wrong, if the server says.
+        error_description: Option<String>,
+    },
+    /// The request failed, or the server did not answer as RFC 8628 says.
+    #[error(transparent)]
+    Request(#[from] crate::types::error::Error),
+}
+
+/// The error body of the OAuth 2.0 token endpoint.
+#[derive(serde::Deserialize)]
+struct OAuthError {
+    error: String,
+    #[serde(default)]
+    error_description: Option<String>,
+}
+
+/// What a poll for the token got.
+enum Poll {
+    /// The user has not authorized the device yet.
+    Pending,
+    /// The user has not authorized the device yet, and we poll too often.
+    SlowDown,
+    /// The user authorized the device.
+    Token(DeviceToken),
+}
+
+/// A device flow we started, the user has to enter the code of `authorization`
+/// at its verification uri while we `wait`.
+#[derive(Debug, Clone)]
+pub struct DeviceFlow {
+    client: crate::Client,
+    token_path: &'static str,
+    client_id: String,
+    authorization: DeviceAuthorization,
+    expires_at: std::time::Instant,
+}
+
+impl DeviceFlow {
+    /// Ask the server for the codes at `auth_path`, the tokens are polled at
+    /// `token_path`.
+    pub(crate) async fn start(
+        client: crate::Client,
+        auth_path: &'static str,
+        token_path: &'static str,
+        client_id: String,
+    ) -> Result<Self, DeviceFlowError> {
+        let (status, body, request_id) =
+            post_form(&client, auth_path, &[("client_id", client_id.as_str())]).await?;
+        if !status.is_success() {
+            return Err(error(status, body, request_id));
+        }
+        let authorization: DeviceAuthorization = parse(status, &body, request_id)?;
+
+        Ok(DeviceFlow {
+            expires_at: std::time::Instant::now()
+                + std::time::Duration::from_secs(authorization.expires_in),
+            client,
+            token_path,
+            client_id,
+            authorization,
+        })
+    }
+
+    /// The codes, and where the user enters them.
+    pub fn authorization(&self) -> &DeviceAuthorization {
+        &self.authorization
+    }
+
+    /// Poll for the token until the user authorizes the device, with the interval
+    /// of the server, waiting `SLOW_DOWN` longer every time it asks us to. It fails
+    /// if the user denies the authorization, or the codes expire first.
+    pub async fn wait(&self) -> Result<DeviceToken, DeviceFlowError> {
+        let mut interval = self
+            .authorization
+            .interval
+            .map(std::time::Duration::from_secs)
+            .unwrap_or(DEFAULT_INTERVAL);
+        loop {
+            // There is no point in a poll after the codes expired.
+            if std::time::Instant::now() + interval >= self.expires_at {
+                return Err(DeviceFlowError::Expired);
+            }
+            tokio::time::sleep(interval).await;
+
+            match self.poll().await? {
+                Poll::Pending => {}
+                Poll::SlowDown => interval += SLOW_DOWN,
+                Poll::Token(token) => return Ok(token),
+            }
+        }
+    }
+
+    /// Like `wait`, with a copy of the client that sends the token.
+    pub async fn wait_for_client(&self) -> Result<crate::Client, DeviceFlowError> {
+        let token = self.wait().await?;
+        Ok(self.client.with_token_override(token.access_token))
+    }
+
+    /// Ask for the token once.
+    async fn poll(&self) -> Result<Poll, DeviceFlowError> {
+        let (status, body, request_id) = post_form(
+            &self.client,
+            self.token_path,
+            &[
+                ("grant_type", "urn:ietf:params:oauth:grant-type:device_code"),
+                ("device_code", self.authorization.device_code.as_str()),
+                ("client_id", self.client_id.as_str()),
+            ],
+        )
+        .await?;
+        if status.is_success() {
+            return Ok(Poll::Token(parse(status, &body, request_id)?));
+        }
+
+        match serde_json::from_str::<OAuthError>(&body) {
+            Ok(OAuthError { error, .. }) if error == "authorization_pending" => Ok(Poll::Pending),
+            Ok(OAuthError { error, .. }) if error == "slow_down" => Ok(Poll::SlowDown),
+            _ => Err(error(status, body, request_id)),
+        }
+    }
+}
+
+/// Send `form` to `path`, without the token of the client: the device does not
+/// have one yet.
+async fn post_form(
+    client: &crate::Client,
+    path: &str,
+    form: &[(&str, &str)],
+) -> Result<(reqwest::StatusCode, String, Option<String>), crate::types::error::Error> {
+    let resp = client
+        .client
+        .request(http::Method::POST, format!("{}/{}", client.base_url, path))
+        .form(form)
+        .send()
+        .await
+        .map_err(crate::Client::send_error)?;
+    let status = resp.status();
+    let request_id = resp
+        .headers()
+        .get(crate::types::error::REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_string());
+    let body = resp.text().await?;
+    Ok((status, body, request_id))
+}
+
+/// Read a successful response.
+fn parse<T: serde::de::DeserializeOwned>(
+    status: reqwest::StatusCode,
+    body: &str,
+    request_id: Option<String>,
+) -> Result<T, crate::types::error::Error> {
+    serde_json::from_str(body).map_err(|err| {
+        crate::types::error::Error::from_serde_error(
+            format_serde_error::SerdeError::new(body.to_string(), err),
+            status,
+            request_id,
+        )
+    })
+}
+
+/// The error of a failed response, the ones of RFC 8628 and the OAuth 2.0 token
+/// endpoint have their own.
+fn error(status: reqwest::StatusCode, body: String, request_id: Option<String>) -> DeviceFlowError {
+    match serde_json::from_str::<OAuthError>(&body) {
+        Ok(OAuthError { error, .. }) if error == "access_denied" => DeviceFlowError::AccessDenied,
+        Ok(OAuthError { error, .. }) if error == "expired_token" => DeviceFlowError::Expired,
+        Ok(OAuthError {
+            error,
+            error_description,
+        }) => DeviceFlowError::OAuth {
+            error,
+            error_description,
+        },
+        Err(_) => DeviceFlowError::Request(crate::types::error::Error::Server {
+            error_code: crate::types::error::error_code_of(&body),
+            body,
+            status,
+            request_id,
+        }),
+    }
+}
@@ -0,0 +1,453 @@
+//! Turn the descriptions in the spec into docs rustdoc is happy with.
+//!
+//! The descriptions are markdown written for a website, not for rustdoc: the
+//! indented lines and the code blocks without a language are run as doctests, the
+//! brackets are read as intra-doc links, and some of them are several kilobytes
+//! long. The brackets around the name of a type we generate become a link to it.
+//!
+//! With `--strict-lints` the crate denies missing docs, so the items the spec does
+//! not describe get docs made up from their names.
+
+use std::collections::BTreeSet;
+
+use once_cell::sync::Lazy;
+use quote::quote;
+use regex::Regex;
+
+/// The info strings rustdoc reads as attributes of a Rust code block, a code block
+/// with only these is run as a doctest.
+const RUSTDOC_ATTRIBUTES: &[&str] = &[
+    "rust",
+    "ignore",
+    "no_run",
+    "compile_fail",
+    "should_panic",
+    "test_harness",
+    "standalone_crate",
+];
+
+/// Regex to find the definitions of reference links, like `[label]: https://...`.
+static REFERENCE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\[([^\]]+)\]:\s*\S").unwrap());
+
+/// Sanitize a description from the spec for a doc comment, `name` is used when the
+/// description is empty.
+pub fn sanitize_description(s: &str, name: &str, type_space: &super::TypeSpace) -> String {
+    let opts = &type_space.opts;
+    if s.trim().is_empty() {
+        return format!("{}.", name);
+    }
+
+    let (s, truncated) = truncate(s, opts.max_description_length);
+    let mut docs = sanitize_markdown(s, type_space);
+    if truncated {
+        docs.push_str("\n\n");
+        match &opts.spec_url {
+            Some(spec_url) => docs.push_str(&format!(
+                "*Truncated, the full description is in [the spec]({}).*",
+                spec_url
+            )),
+            None => docs.push_str("*Truncated, the full description is in the spec.*"),
+        }
+    }
+
+    docs
+}
+
+/// The docs of an item the spec does not describe, `docs` with `--strict-lints` and
+/// none otherwise.
+pub fn fallback(
+    type_space: &super::TypeSpace,
+    docs: impl FnOnce() -> String,
+) -> proc_macro2::TokenStream {
+    if !type_space.opts.strict_lints {
+        return quote!();
+    }
+
+    let docs = docs();
+    quote!(#[doc = #docs])
+}
+
+/// Cut the description at the end of the last line that fits in `max_length`
+/// characters, and tell if we did.
+fn truncate(s: &str, max_length: Option<usize>) -> (&str, bool) {
+    let end = match max_length.and_then(|max_length| s.char_indices().nth(max_length)) {
+        Some((end, _)) => end,
+        None => return (s, false),
+    };
+
+    let cut = &s[..end];
+    let end = match cut.rfind('\n') {
+        _ if s[end..].starts_with('\n') => end,
+        Some(line_end) => line_end,
+        // A single line is cut between words.
+        None if s[end..].starts_with(char::is_whitespace) => end,
+        None => cut.rfind(char::is_whitespace).unwrap_or(end),
+    };
+    (s[..end].trim_end(), true)
+}
+
+/// Make the markdown safe for rustdoc:
+/// - collapse the leading spaces, rustdoc runs the indented lines as code,
+/// - mark the code blocks as `text`, or as `rust,ignore` for Rust, so none of
+///   them is a doctest, and close the one left open,
+/// - escape the brackets that are not links, rustdoc reads them as intra-doc links.
+fn sanitize_markdown(s: &str, type_space: &super::TypeSpace) -> String {
+    let references = references(s);
+
+    let mut lines = Vec::new();
+    let mut open_fence: Option<(char, usize)> = None;
+    for line in s.lines() {
+        match (open_fence, fence(line)) {
+            (Some((c, n)), Some((fence_c, fence_n, info)))
+                if fence_c == c && fence_n >= n && info.is_empty() =>
+            {
+                open_fence = None;
+                lines.push(line.trim().to_string());
+            }
+            (Some(_), _) => lines.push(line.to_string()),
+            (None, Some((c, n, info))) => {
+                open_fence = Some((c, n));
+                let fence = c.to_string().repeat(n);
+                lines.push(format!("{}{}", fence, fence_language(info)));
+            }
+            (None, None) => lines.push(escape_brackets(line.trim_start(), &references, type_space)),
+        }
+    }
+    if let Some((c, n)) = open_fence {
+        lines.push(c.to_string().repeat(n));
+    }
+
+    lines.join("\n")
+}
+
+/// The labels of the reference links defined in the description, lowercase.
+fn references(s: &str) -> BTreeSet<String> {
+    s.lines()
+        .filter_map(|line| REFERENCE.captures(line.trim_start()))
+        .map(|captures| captures[1].trim().to_lowercase())
+        .collect()
+}
+
+/// The character, length and info string of a code fence.
+fn fence(line: &str) -> Option<(char, usize, &str)> {
+    let line = line.trim_start();
+    let c = line.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let n = line.chars().take_while(|x| *x == c).count();
+    let info = &line[n..];
+    if n < 3 || (c == '`' && info.contains('`')) {
+        return None;
+    }
+
+    Some((c, n, info.trim()))
+}
+
+/// The language of a code block with the info string `info`.
+fn fence_language(info: &str) -> &'static str {
+    let mut attributes = info
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|attribute| !attribute.is_empty())
+        .peekable();
+    if attributes.peek().is_some()
+        && attributes.all(|attribute| {
+            RUSTDOC_ATTRIBUTES.contains(&attribute)
+                || attribute.starts_with("edition")
+                || attribute.starts_with("ignore-")
+        })
+    {
+        "rust,ignore"
+    } else {
+        "text"
+    }
+}
+
+/// Escape the brackets of a line that are not part of a link, outside of code.
+fn escape_brackets(
+    line: &str,
+    references: &BTreeSet<String>,
+    type_space: &super::TypeSpace,
+) -> String {
+    let chars: Vec<char> = line.chars().collect();
+    let code = code_spans(&chars);
+
+    let mut escaped = String::with_capacity(line.len());
+    let mut i = 0;
+    while i < chars.len() {
+        if code[i] {
+            escaped.push(chars[i]);
+            i += 1;
+            continue;
+        }
+
+        match chars[i] {
+            '\\' if i + 1 < chars.len() => {
+                escaped.extend(&chars[i..i + 2]);
+                i += 2;
+            }
+            '[' => {
+                if let Some(end) = link_end(&chars, &code, i, references) {
+                    escaped.extend(&chars[i..end]);
+                    i = end;
+                } else if let Some((end, link)) = type_link(&chars, &code, i, type_space) {
+                    escaped.push_str(&link);
+                    i = end;
+                } else {
+                    escaped.push_str("\\[");
+                    i += 1;
+                }
+            }
+            ']' => {
+                escaped.push_str("\\]");
+                i += 1;
+            }
+            c => {
+                escaped.push(c);
+                i += 1;
+            }
+        }
+    }
+
+    escaped
+}
+
+/// Which characters of a line are in inline code.
+fn code_spans(chars: &[char]) -> Vec<bool> {
+    let backticks = |from: usize| chars[from..].iter().take_while(|c| **c == '`').count();
+
+    let mut code = vec![false; chars.len()];
+    let mut i = 0;
+    while i < chars.len() {
+        if chars[i] != '`' {
+            i += 1;
+            continue;
+        }
+
+        // The span ends at the next run of as many backticks.
+        let n = backticks(i);
+        let mut j = i + n;
+        let mut end = None;
+        while j < chars.len() {
+            if chars[j] == '`' {
+                let m = backticks(j);
+                if m == n {
+                    end = Some(j + m);
+                    break;
+                }
+                j += m;
+            } else {
+                j += 1;
+            }
+        }
+
+        match end {
+            Some(end) => {
+                code[i..end].iter_mut().for_each(|c| *c = true);
+                i = end;
+            }
+            None => i += n,
+        }
+    }
+
+    code
+}
+
+/// The end of the link starting at `start`, if the brackets there are a link:
+/// `[text](url)`, `[text][label]`, `[label]` or the definition `[label]: url`.
+fn link_end(
+    chars: &[char],
+    code: &[bool],
+    start: usize,
+    references: &BTreeSet<String>,
+) -> Option<usize> {
+    let label = |from: usize, to: usize| -> String {
+        chars[from..to]
+            .iter()
+            .collect::<String>()
+            .trim()
+            .to_lowercase()
+    };
+
+    let close = matching(chars, code, start, '[', ']')?;
+    match chars.get(close + 1) {
+        Some('(') => Some(matching(chars, code, close + 1, '(', ')')? + 1),
+        Some('[') => {
+            let label_close = matching(chars, code, close + 1, '[', ']')?;
+            let mut l = label(close + 2, label_close);
+            if l.is_empty() {
+                l = label(start + 1, close);
+            }
+            references.contains(&l).then_some(label_close + 1)
+        }
+        Some(':') if start == 0 && references.contains(&label(start + 1, close)) => {
+            Some(chars.len())
+        }
+        _ => references
+            .contains(&label(start + 1, close))
+            .then_some(close + 1),
+    }
+}
+
+/// The end of the brackets starting at `start` and a link to the type in them, if
+/// they have the name of a type we generate, like `[Thing]` or ``[`Thing`]``.
+fn type_link(
+    chars: &[char],
+    code: &[bool],
+    start: usize,
+    type_space: &super::TypeSpace,
+) -> Option<(usize, String)> {
+    let close = matching(chars, code, start, '[', ']')?;
+    let text: String = chars[start + 1..close].iter().collect();
+    let name = text.trim_matches('`');
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return None;
+    }
+
+    let components = type_space.spec.components.as_ref()?;
+    if !components
+        .schemas
+        .keys()
+        .any(|schema| super::proper_name(schema) == name)
+    {
+        return None;
+    }
+
+    Some((close + 1, format!("[{}](crate::types::{})", text, name)))
+}
+
+/// The index of the `close` matching the `open` at `start`, outside of code.
+fn matching(chars: &[char], code: &[bool], start: usize, open: char, close: char) -> Option<usize> {
+    let mut depth = 0;
+    let mut i = start;
+    while i < chars.len() {
+        if code[i] {
+            i += 1;
+            continue;
+        }
+
+        match chars[i] {
+            '\\' => i += 1,
+            c if c == open => depth += 1,
+            c if c == close => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::sanitize_description;
+
+    /// A type space with the type `Thing`.
+    fn type_space(opts: crate::Opts) -> crate::types::TypeSpace {
+        let mut spec = openapiv3::OpenAPI::default();
+        let mut components = openapiv3::Components::default();
+        components.schemas.insert(
+            "thing".to_string(),
+            serde_json::from_str(r#"{"type": "string"}"#).unwrap(),
+        );
+        spec.components = Some(components);
+
+        crate::types::TypeSpace {
+            spec,
+            rendered: Default::default(),
+            types: Default::default(),
+            opts,
+        }
+    }
+
+    fn sanitize(s: &str) -> String {
+        sanitize_description(s, "Thing", &type_space(Default::default()))
+    }
+
+    #[test]
+    fn test_sanitize_code_blocks() {
+        assert_eq!(
+            sanitize("Example:\n```\nlet x = {;\n```"),
+            "Example:\n```text\nlet x = {;\n```"
+        );
+        assert_eq!(
+            sanitize("```rust\nfn main() {\n    oops\n}\n```"),
+            "```rust,ignore\nfn main() {\n    oops\n}\n```"
+        );
+        assert_eq!(
+            sanitize("```json\n{\n  \"a\": [1]\n}\n```"),
+            "```text\n{\n  \"a\": [1]\n}\n```"
+        );
+        assert_eq!(
+            sanitize("~~~~ no_run\n```\n~~~~"),
+            "~~~~rust,ignore\n```\n~~~~"
+        );
+        // The code block left open is closed.
+        assert_eq!(sanitize("```\n[1, 2]"), "```text\n[1, 2]\n```");
+        // The indented lines are not code.
+        assert_eq!(
+            sanitize("    The thing.\n    \tlet x = 1;"),
+            "The thing.\nlet x = 1;"
+        );
+        assert_eq!(sanitize(""), "Thing.");
+    }
+
+    #[test]
+    fn test_sanitize_brackets() {
+        assert_eq!(
+            sanitize(
+                "A [link](https://example.com/a_(b)) and [`Vec<T>`](https://doc.rust-lang.org)."
+            ),
+            "A [link](https://example.com/a_(b)) and [`Vec<T>`](https://doc.rust-lang.org)."
+        );
+        assert_eq!(
+            sanitize("An array like `[1, 2]` or string[], see [Stuff] and [a]b]."),
+            "An array like `[1, 2]` or string\\[\\], see \\[Stuff\\] and \\[a\\]b\\]."
+        );
+        // The types we generate are linked.
+        assert_eq!(
+            sanitize("A [`Thing`], a [Thing] but not a [`Thing::new`] or [`Other`]."),
+            "A [`Thing`](crate::types::Thing), a [Thing](crate::types::Thing) but not a \\[`Thing::new`\\] or \\[`Other`\\]."
+        );
+        assert_eq!(
+            sanitize("See [the docs] and [more][docs].\n\n[the docs]: https://example.com\n[docs]: https://example.com/more"),
+            "See [the docs] and [more][docs].\n\n[the docs]: https://example.com\n[docs]: https://example.com/more"
+        );
+        assert_eq!(sanitize(r"Already \[escaped\]."), r"Already \[escaped\].");
+    }
+
+    #[test]
+    fn test_truncate_description() {
+        let mut type_space = type_space(crate::Opts {
+            max_description_length: Some(30),
+            ..Default::default()
+        });
+        let description = "The first line.\nThe second line, too long.\n```\ncode\n```";
+        assert_eq!(
+            sanitize_description(description, "Thing", &type_space),
+            "The first line.\n\n*Truncated, the full description is in the spec.*"
+        );
+
+        type_space.opts.spec_url = Some("https://example.com/spec.json".to_string());
+        type_space.opts.max_description_length = Some(50);
+        assert_eq!(
+            sanitize_description(description, "Thing", &type_space),
+            "The first line.\nThe second line, too long.\n```text\n```\n\n*Truncated, the full description is in [the spec](https://example.com/spec.json).*"
+        );
+
+        // A single line is cut between words.
+        type_space.opts.max_description_length = Some(13);
+        assert_eq!(
+            sanitize_description("Ünïcödé words everywhere", "Thing", &type_space),
+            "Ünïcödé words\n\n*Truncated, the full description is in [the spec](https://example.com/spec.json).*"
+        );
+
+        type_space.opts.max_description_length = Some(description.chars().count());
+        assert_eq!(
+            sanitize_description(description, "Thing", &type_space),
+            "The first line.\nThe second line, too long.\n```text\ncode\n```"
+        );
+    }
+}
@@ -0,0 +1,175 @@
+//! The files an operation answers with, see `--download-responses`.
+
+/// A file the server sent, with the name and the content type it gave it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Download {
+    /// The bytes of the file.
+    pub bytes: bytes::Bytes,
+    /// The name of the file from the `Content-Disposition` header, if there is one.
+    pub filename: Option<String>,
+    /// The `Content-Type` header, if there is one.
+    pub content_type: Option<String>,
+}
+
+impl Download {
+    /// Read the file of `resp`.
+    pub(crate) async fn from_response(resp: reqwest::Response) -> Result<Self, reqwest::Error> {
+        let header = |name: reqwest::header::HeaderName| {
+            resp.headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        };
+        let filename = header(reqwest::header::CONTENT_DISPOSITION)
+            .and_then(|value| filename_from_content_disposition(&value));
+        let content_type = header(reqwest::header::CONTENT_TYPE);
+
+        Ok(Download {
+            bytes: resp.bytes().await?,
+            filename,
+            content_type,
+        })
+    }
+}
+
+/// The file name of a `Content-Disposition` header, as in
+/// `attachment; filename="part.step"`. The encoded `filename*` of RFC 5987, as in
+/// `filename*=UTF-8''pi%C3%A8ce.step`, comes first when it can be decoded.
+pub fn filename_from_content_disposition(value: &str) -> Option<String> {
+    let mut filename = None;
+    let mut encoded = None;
+    for param in split_params(value).into_iter().skip(1) {
+        let Some((name, value)) = param.split_once('=') else {
+            continue;
+        };
+        match name.trim().to_ascii_lowercase().as_str() {
+            "filename" => filename = Some(unquote(value.trim())),
+            "filename*" => encoded = decode_ext_value(value.trim()),
+            _ => {}
+        }
+    }
+
+    encoded.or(filename).filter(|filename| !filename.is_empty())
+}
+
+/// Split a header value at the `;` that are not in a quoted string.
+fn split_params(value: &str) -> Vec<&str> {
+    let mut params = Vec::new();
+    let mut start = 0;
+    let mut quoted = false;
+    let mut escaped = false;
+    for (i, c) in value.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            ';' if !quoted => {
+                params.push(&value[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    params.push(&value[start..]);
+    params
+}
+
+/// The value of a quoted string, or the token as it is.
+fn unquote(value: &str) -> String {
+    let Some(inner) = value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+    else {
+        return value.to_string();
+    };
+
+    let mut unquoted = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unquoted.extend(chars.next()),
+            c => unquoted.push(c),
+        }
+    }
+    unquoted
+}
+
+/// Decode a `charset'language'value` of RFC 5987. Only UTF-8 and ISO-8859-1 are
+/// supported, as the RFC asks.
+fn decode_ext_value(value: &str) -> Option<String> {
+    let mut parts = value.splitn(3, '\'');
+    let charset = parts.next()?.to_ascii_lowercase();
+    let _language = parts.next()?;
+    let bytes = percent_decode(parts.next()?)?;
+
+    match charset.as_str() {
+        "utf-8" => String::from_utf8(bytes).ok(),
+        "iso-8859-1" => Some(bytes.into_iter().map(char::from).collect()),
+        _ => None,
+    }
+}
+
+/// Decode the `%XX` of `value`, `None` if one is not valid.
+fn percent_decode(value: &str) -> Option<Vec<u8>> {
+    let mut decoded = Vec::with_capacity(value.len());
+    let mut bytes = value.bytes();
+    while let Some(b) = bytes.next() {
+        if b == b'%' {
+            let hex = [bytes.next()?, bytes.next()?];
+            let hex = std::str::from_utf8(&hex).ok()?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+        } else {
+            decoded.push(b);
+        }
+    }
+    Some(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::filename_from_content_disposition;
+
+    #[test]
+    fn test_filename_from_content_disposition() {
+        for (value, expected) in [
+            (r#"attachment; filename="part.step""#, Some("part.step")),
+            ("attachment; filename=part.step", Some("part.step")),
+            (r#"attachment; FileName = "part.step" "#, Some("part.step")),
+            (
+                r#"attachment; filename="my \"best\"; part.step""#,
+                Some(r#"my "best"; part.step"#),
+            ),
+            (
+                "attachment; filename*=UTF-8''pi%C3%A8ce%20%E2%82%AC.step",
+                Some("pièce €.step"),
+            ),
+            ("attachment; filename*=iso-8859-1'fr'pi%E8ce.step", Some("pièce.step")),
+            // The encoded name comes first, wherever it is.
+            (
+                r#"attachment; filename*=UTF-8''pi%C3%A8ce.step; filename="piece.step""#,
+                Some("pièce.step"),
+            ),
+            // The plain name is used when the encoded one cannot be decoded.
+            (
+                r#"attachment; filename="piece.step"; filename*=UTF-8''pi%ZZce.step"#,
+                Some("piece.step"),
+            ),
+            (
+                r#"attachment; filename="piece.step"; filename*=koi8-r''%C1.step"#,
+                Some("piece.step"),
+            ),
+            // No name.
+            ("attachment", None),
+            ("inline", None),
+            (r#"attachment; filename="""#, None),
+            ("", None),
+        ] {
+            assert_eq!(
+                filename_from_content_disposition(value).as_deref(),
+                expected,
+                "{}",
+                value
+            );
+        }
+    }
+}
@@ -0,0 +1,307 @@
+//! Compare schemas by the types they generate.
+//!
+//! The same schema is often inlined in more than one place with another
+//! description or example, we generate a single type for all of them. Only the
+//! metadata of `--compare-schema-metadata` is compared, the shape always is: the
+//! properties, what is required, the enum values, the formats, and so on.
+
+use openapiv3::{AdditionalProperties, ReferenceOr, Schema, SchemaKind, Type};
+
+/// The extensions that change the generated type, they are always compared.
+const GENERATED_EXTENSIONS: &[&str] = &["x-rust-name"];
+
+/// The metadata of a schema that does not change the type we generate for it.
+#[derive(
+    clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum SchemaMetadata {
+    /// The `description`, the docs of the type.
+    Description,
+    /// The `example`.
+    Example,
+    /// The `title`.
+    Title,
+    /// The `x-` extensions, other than the ones naming the type or its fields.
+    Extensions,
+}
+
+/// If `a` and `b` generate the same type, comparing only the metadata of
+/// `compared`.
+pub fn same_shape(a: &Schema, b: &Schema, compared: &[SchemaMetadata]) -> bool {
+    a == b || normalize(a, compared) == normalize(b, compared)
+}
+
+/// The schema without the metadata that is not `compared`, in it and in the
+/// schemas it has inline.
+pub fn normalize(schema: &Schema, compared: &[SchemaMetadata]) -> Schema {
+    let mut schema = schema.clone();
+    normalize_in_place(&mut schema, compared);
+    schema
+}
+
+fn normalize_in_place(schema: &mut Schema, compared: &[SchemaMetadata]) {
+    let data = &mut schema.schema_data;
+    if !compared.contains(&SchemaMetadata::Description) {
+        data.description = None;
+    }
+    if !compared.contains(&SchemaMetadata::Example) {
+        data.example = None;
+    }
+    if !compared.contains(&SchemaMetadata::Title) {
+        data.title = None;
+    }
+    if !compared.contains(&SchemaMetadata::Extensions) {
+        data.extensions
+            .retain(|name, _| GENERATED_EXTENSIONS.contains(&name.as_str()));
+    }
+
+    match &mut schema.schema_kind {
+        SchemaKind::Type(Type::Object(o)) => {
+            for property in o.properties.values_mut() {
+                normalize_boxed(property, compared);
+            }
+            normalize_additional(&mut o.additional_properties, compared);
+        }
+        SchemaKind::Type(Type::Array(a)) => {
+            if let Some(items) = &mut a.items {
+                normalize_boxed(items, compared);
+            }
+        }
+        SchemaKind::Type(_) => {}
+        SchemaKind::OneOf { one_of: schemas }
+        | SchemaKind::AllOf { all_of: schemas }
+        | SchemaKind::AnyOf { any_of: schemas } => {
+            for schema in schemas {
+                normalize_item(schema, compared);
+            }
+        }
+        SchemaKind::Not { not } => normalize_item(not, compared),
+        SchemaKind::Any(any) => {
+            for property in any.properties.values_mut() {
+                normalize_boxed(property, compared);
+            }
+            normalize_additional(&mut any.additional_properties, compared);
+            if let Some(items) = &mut any.items {
+                normalize_boxed(items, compared);
+            }
+            for schema in any
+                .one_of
+                .iter_mut()
+                .chain(any.all_of.iter_mut())
+                .chain(any.any_of.iter_mut())
+            {
+                normalize_item(schema, compared);
+            }
+            if let Some(not) = &mut any.not {
+                normalize_item(not, compared);
+            }
+        }
+    }
+}
+
+fn normalize_item(schema: &mut ReferenceOr<Schema>, compared: &[SchemaMetadata]) {
+    if let ReferenceOr::Item(schema) = schema {
+        normalize_in_place(schema, compared);
+    }
+}
+
+fn normalize_boxed(schema: &mut ReferenceOr<Box<Schema>>, compared: &[SchemaMetadata]) {
+    if let ReferenceOr::Item(schema) = schema {
+        normalize_in_place(schema, compared);
+    }
+}
+
+fn normalize_additional(
+    additional: &mut Option<AdditionalProperties>,
+    compared: &[SchemaMetadata],
+) {
+    if let Some(AdditionalProperties::Schema(schema)) = additional {
+        normalize_item(schema, compared);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{same_shape, SchemaMetadata};
+
+    fn schema(value: serde_json::Value) -> openapiv3::Schema {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_same_shape() {
+        let base = schema(serde_json::json!({
+            "type": "object",
+            "description": "A user.",
+            "required": ["id"],
+            "properties": {
+                "id": {"type": "string", "format": "uuid", "description": "The id."},
+                "tags": {"type": "array", "items": {"type": "string", "example": "a"}},
+                "kind": {"type": "string", "enum": ["admin", "member"]}
+            }
+        }));
+
+        // Only the metadata differs.
+        for other in [
+            serde_json::json!({
+                "type": "object",
+                "description": "Another user.",
+                "title": "User",
+                "x-internal": true,
+                "required": ["id"],
+                "properties": {
+                    "id": {"type": "string", "format": "uuid", "description": "The user id."},
+                    "tags": {"type": "array", "items": {"type": "string", "example": "b"}},
+                    "kind": {"type": "string", "enum": ["admin", "member"], "title": "Kind"}
+                }
+            }),
+            serde_json::json!({
+                "type": "object",
+                "required": ["id"],
+                "properties": {
+                    "id": {"type": "string", "format": "uuid"},
+                    "tags": {"type": "array", "items": {"type": "string"}},
+                    "kind": {"type": "string", "enum": ["admin", "member"]}
+                }
+            }),
+        ] {
+            assert!(same_shape(&base, &schema(other), &[]));
+        }
+
+        // The shape differs.
+        for other in [
+            // Another property.
+            serde_json::json!({
+                "type": "object",
+                "required": ["id"],
+                "properties": {
+                    "id": {"type": "string", "format": "uuid"},
+                    "tags": {"type": "array", "items": {"type": "string"}},
+                    "kind": {"type": "string", "enum": ["admin", "member"]},
+                    "name": {"type": "string"}
+                }
+            }),
+            // Nothing required.
+            serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "id": {"type": "string", "format": "uuid"},
+                    "tags": {"type": "array", "items": {"type": "string"}},
+                    "kind": {"type": "string", "enum": ["admin", "member"]}
+                }
+            }),
+            // Another enum value.
+            serde_json::json!({
+                "type": "object",
+                "required": ["id"],
+                "properties": {
+                    "id": {"type": "string", "format": "uuid"},
+                    "tags": {"type": "array", "items": {"type": "string"}},
+                    "kind": {"type": "string", "enum": ["admin", "owner"]}
+                }
+            }),
+            // Another format.
+            serde_json::json!({
+                "type": "object",
+                "required": ["id"],
+                "properties": {
+                    "id": {"type": "string"},
+                    "tags": {"type": "array", "items": {"type": "string"}},
+                    "kind": {"type": "string", "enum": ["admin", "member"]}
+                }
+            }),
+            // Another item type.
+            serde_json::json!({
+                "type": "object",
+                "required": ["id"],
+                "properties": {
+                    "id": {"type": "string", "format": "uuid"},
+                    "tags": {"type": "array", "items": {"type": "integer"}},
+                    "kind": {"type": "string", "enum": ["admin", "member"]}
+                }
+            }),
+            // Another name for a field.
+            serde_json::json!({
+                "type": "object",
+                "required": ["id"],
+                "properties": {
+                    "id": {"type": "string", "format": "uuid", "x-rust-name": "user_id"},
+                    "tags": {"type": "array", "items": {"type": "string"}},
+                    "kind": {"type": "string", "enum": ["admin", "member"]}
+                }
+            }),
+        ] {
+            assert!(!same_shape(&base, &schema(other), &[]));
+        }
+    }
+
+    #[test]
+    fn test_same_shape_compared_metadata() {
+        let a = schema(serde_json::json!({
+            "type": "string",
+            "description": "A name.",
+            "example": "a"
+        }));
+        let b = schema(serde_json::json!({
+            "type": "string",
+            "description": "Another name.",
+            "example": "a"
+        }));
+        assert!(same_shape(&a, &b, &[]));
+        assert!(same_shape(&a, &b, &[SchemaMetadata::Example]));
+        assert!(!same_shape(&a, &b, &[SchemaMetadata::Description]));
+
+        // The metadata of the inline schemas is compared too.
+        let one_of = |description: &str| {
+            schema(serde_json::json!({
+                "oneOf": [{"type": "string", "description": description}, {"type": "integer"}]
+            }))
+        };
+        assert!(same_shape(&one_of("a"), &one_of("b"), &[]));
+        assert!(!same_shape(
+            &one_of("a"),
+            &one_of("b"),
+            &[SchemaMetadata::Description]
+        ));
+    }
+
+    fn render(spec: &openapiv3::OpenAPI, opts: &crate::Opts) -> String {
+        let mut type_space = crate::types::TypeSpace {
+            types: indexmap::map::IndexMap::new(),
+            spec: spec.clone(),
+            rendered: quote!(),
+            opts: opts.clone(),
+        };
+        for (name, schema) in &spec.components.as_ref().unwrap().schemas {
+            type_space
+                .render_schema(name, schema.as_item().unwrap())
+                .unwrap();
+        }
+        crate::types::get_text_fmt(&type_space.rendered).unwrap()
+    }
+
+    #[test]
+    fn test_render_schema_metadata() {
+        let spec =
+            crate::load_json_spec(include_str!("../../tests/types/input/schema-metadata.json"))
+                .unwrap();
+
+        // The addresses of the invoice and the receipt are the same type.
+        let rendered = render(&spec, &Default::default());
+        expectorate::assert_contents("tests/types/schema-metadata.rs.gen", &rendered);
+        assert!(rendered.contains("pub struct Address {"));
+        assert!(!rendered.contains("pub struct ReceiptAddress {"));
+        assert!(rendered.contains("pub struct ShipmentAddress {"));
+
+        // Unless their descriptions are compared.
+        let rendered = render(
+            &spec,
+            &crate::Opts {
+                compared_schema_metadata: vec![SchemaMetadata::Description],
+                ..Default::default()
+            },
+        );
+        assert!(rendered.contains("pub struct ReceiptAddress {"));
+    }
+}
@@ -1,16 +1,24 @@
 //! Error methods.
 
+/// The header of the responses with the id the server gave the request.
+pub const REQUEST_ID_HEADER: &str = "X-Request-Id";
+
+/// The `error_code` of the bodies of the error responses. It is the enum of the
+/// codes when the API lists them, with an `Unknown` variant for the ones it adds
+/// later, and the code as it is otherwise.
+pub type ErrorCode = String;
+
 /// Error produced by generated client methods.
 pub enum Error {
     /// The request did not conform to API requirements.
     InvalidRequest(String),
 
-    #[cfg(feature = "retry")]
-    /// A server error either due to the data, or with the connection.
-    CommunicationError(reqwest_middleware::Error),
+    /// A server error either due to the data, or with the connection. Only the
+    /// middleware of the `retry` feature produces it.
+    CommunicationError(TransportError),
 
     /// A request error, caused when building the request.
-    RequestError(reqwest::Error),
+    RequestError(TransportError),
 
     /// An expected response whose deserialization failed.
     SerdeError {
@@ -18,18 +26,16 @@ pub enum Error {
         error: format_serde_error::SerdeError,
         /// The response status.
         status: reqwest::StatusCode,
+        /// The id the server gave the request.
+        request_id: Option<String>,
     },
 
     /// An expected error response.
     InvalidResponsePayload {
-        #[cfg(feature = "retry")]
-        /// The error.
-        error: reqwest_middleware::Error,
-        #[cfg(not(feature = "retry"))]
         /// The error.
-        error: reqwest::Error,
+        error: TransportError,
         /// The full response.
-        response: reqwest::Response,
+        response: ApiResponse,
     },
 
     /// An error from the server.
@@ -38,11 +44,38 @@ pub enum Error {
         body: String,
         /// The response status.
         status: reqwest::StatusCode,
+        /// The id the server gave the request.
+        request_id: Option<String>,
+        /// The `error_code` of the body, if it has one.
+        error_code: Option<ErrorCode>,
+    },
+
+    /// An error from the server, with the body of the `default` response of the
+    /// operation, for a status the operation does not list on its own.
+    Api {
+        /// The body of the response.
+        body: ErrorBody,
+        /// The response status.
+        status: reqwest::StatusCode,
+        /// The id the server gave the request.
+        request_id: Option<String>,
+        /// The `error_code` of the body, if it has one.
+        error_code: Option<ErrorCode>,
     },
 
     /// A response not listed in the API description. This may represent a
     /// success or failure response; check `status().is_success()`.
-    UnexpectedResponse(reqwest::Response),
+    UnexpectedResponse(ApiResponse),
+
+    /// A paginated stream fetched the maximum number of pages of the client
+    /// without reaching the last page, the server probably hands out page
+    /// tokens in a loop.
+    TooManyPages {
+        /// The maximum number of pages.
+        max_pages: usize,
+        /// The next page token of the last page we fetched.
+        next_page_token: Option<String>,
+    },
 }
 
 impl Error {
@@ -50,15 +83,37 @@ impl Error {
     pub fn status(&self) -> Option<reqwest::StatusCode> {
         match self {
             Error::InvalidRequest(_) => None,
-            Error::RequestError(e) => e.status(),
-            #[cfg(feature = "retry")]
-            Error::CommunicationError(reqwest_middleware::Error::Reqwest(e)) => e.status(),
-            #[cfg(feature = "retry")]
-            Error::CommunicationError(reqwest_middleware::Error::Middleware(_)) => None,
-            Error::SerdeError { error: _, status } => Some(*status),
+            Error::RequestError(e) | Error::CommunicationError(e) => e.status(),
+            Error::SerdeError { status, .. } => Some(*status),
             Error::InvalidResponsePayload { error: _, response } => Some(response.status()),
-            Error::Server { body: _, status } => Some(*status),
+            Error::Server { status, .. } => Some(*status),
+            Error::Api { status, .. } => Some(*status),
             Error::UnexpectedResponse(r) => Some(r.status()),
+            Error::TooManyPages { .. } => None,
+        }
+    }
+
+    /// Returns the id the server gave the request, if the error was generated from
+    /// a response that had one. Support asks for it.
+    pub fn request_id(&self) -> Option<&str> {
+        match self {
+            Error::SerdeError { request_id, .. }
+            | Error::Server { request_id, .. }
+            | Error::Api { request_id, .. } => request_id.as_deref(),
+            Error::InvalidResponsePayload { error: _, response }
+            | Error::UnexpectedResponse(response) => {
+                response.headers().get(REQUEST_ID_HEADER)?.to_str().ok()
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the `error_code` of the body of the error response, if it has one,
+    /// to match on the codes of the API rather than on the status.
+    pub fn api_error_code(&self) -> Option<&ErrorCode> {
+        match self {
+            Error::Server { error_code, .. } | Error::Api { error_code, .. } => error_code.as_ref(),
+            _ => None,
         }
     }
 
@@ -66,21 +121,315 @@ impl Error {
     pub fn from_serde_error(
         e: format_serde_error::SerdeError,
         status: reqwest::StatusCode,
+        request_id: Option<String>,
     ) -> Self {
-        Self::SerdeError { error: e, status }
+        Self::SerdeError {
+            error: e,
+            status,
+            request_id,
+        }
+    }
+
+    /// Creates the error of a response with an error status, from its text. The
+    /// operations that have a `default` response get theirs from the
+    /// `from_*_response` functions of its type instead.
+    #[doc(hidden)]
+    pub async fn from_response(resp: reqwest::Response, request_id: Option<String>) -> Self {
+        let status = resp.status();
+        let text = resp.text().await.unwrap_or_default();
+        let request_id = request_id_of(request_id, &text);
+        let error_code = error_code_of(&text);
+        Error::Server {
+            body: text,
+            status,
+            request_id,
+            error_code,
+        }
+    }
+
+    /// Returns the class of the error, see [`ErrorClass`] for how it is decided.
+    pub fn classification(&self) -> ErrorClass {
+        match self {
+            Error::InvalidRequest(_) => ErrorClass::ClientBug,
+            Error::RequestError(e) | Error::CommunicationError(e) => {
+                ErrorClass::from_transport_error(e)
+            }
+            Error::SerdeError { .. } => ErrorClass::Unknown,
+            Error::InvalidResponsePayload { error: _, response } => {
+                ErrorClass::from_status(response.status())
+            }
+            Error::Server { body, status, .. } => {
+                ErrorClass::from_body(serde_json::from_str(body).ok(), *status)
+            }
+            Error::Api { body, status, .. } => {
+                ErrorClass::from_body(serde_json::to_value(body).ok(), *status)
+            }
+            Error::UnexpectedResponse(r) => ErrorClass::from_status(r.status()),
+            Error::TooManyPages { .. } => ErrorClass::ServerBug,
+        }
+    }
+
+    /// Returns true if the same request could succeed when it is sent again later,
+    /// that is the error is [`ErrorClass::Transient`] or [`ErrorClass::RateLimited`].
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self.classification(),
+            ErrorClass::Transient | ErrorClass::RateLimited
+        )
+    }
+}
+
+/// The body of an error response, one variant for every type of the `default`
+/// responses of the operations. Every generated crate gets its own.
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(untagged)]
+pub enum ErrorBody {}
+
+/// The class of an error, to decide what to do about it without matching on
+/// status codes.
+///
+/// | Class | Produced by |
+/// |-------|-------------|
+/// | `Transient` | timeouts, connection failures, `408`, `502`, `503`, `504`, `202` after waiting |
+/// | `RateLimited` | `429` |
+/// | `AuthExpired` | `401` |
+/// | `ClientBug` | invalid requests, requests that could not be built, any other `4xx` |
+/// | `ServerBug` | any other `5xx`, paginated streams that never reach the last page |
+/// | `Unknown` | anything else, like a response we could not deserialize |
+///
+/// When the body of an error response is JSON with an `error_code` field, a known
+/// code takes precedence over the status, if the spec declares it:
+///
+/// | Error code | Class |
+/// |------------|-------|
+/// | `internal_api`, `internal_engine`, `connection_problem` | `Transient` |
+/// | `auth_token_missing`, `auth_token_invalid` | `AuthExpired` |
+/// | `bad_request`, `invalid_json`, `invalid_bson`, `wrong_protocol` | `ClientBug` |
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorClass {
+    /// A temporary failure, the request can be retried.
+    Transient,
+    /// We sent too many requests, the request can be retried after a while.
+    RateLimited,
+    /// The credentials are missing, invalid or expired.
+    AuthExpired,
+    /// The request is wrong and will fail again, it should not be retried.
+    ClientBug,
+    /// The server failed in a way that is not temporary.
+    ServerBug,
+    /// We could not tell.
+    Unknown,
+}
+
+impl ErrorClass {
+    fn from_status(status: reqwest::StatusCode) -> Self {
+        match status.as_u16() {
+            // An operation that is still not done after waiting.
+            202 | 408 | 502 | 503 | 504 => ErrorClass::Transient,
+            429 => ErrorClass::RateLimited,
+            401 => ErrorClass::AuthExpired,
+            400..=499 => ErrorClass::ClientBug,
+            500..=599 => ErrorClass::ServerBug,
+            _ => ErrorClass::Unknown,
+        }
+    }
+
+    fn from_body(body: Option<serde_json::Value>, status: reqwest::StatusCode) -> Self {
+        body.and_then(|body| ErrorClass::from_error_code(body.get("error_code")?.as_str()?))
+            .unwrap_or_else(|| ErrorClass::from_status(status))
+    }
+
+    /// The class of a known code the spec declares. It depends on the spec, every
+    /// generated crate gets its own.
+    fn from_error_code(_code: &str) -> Option<Self> {
+        None
+    }
+
+    fn from_transport_error(e: &TransportError) -> Self {
+        if e.is_timeout() || e.is_connect() {
+            return ErrorClass::Transient;
+        }
+
+        if let Some(status) = e.status() {
+            ErrorClass::from_status(status)
+        } else if e.is_builder() {
+            ErrorClass::ClientBug
+        } else {
+            ErrorClass::Unknown
+        }
+    }
+}
+
+/// The error of a request that got no response, the same with or without the
+/// middleware of the `retry` feature.
+pub struct TransportError(TransportErrorKind);
+
+enum TransportErrorKind {
+    Reqwest(reqwest::Error),
+    Middleware(anyhow::Error),
+}
+
+impl TransportError {
+    /// The error of a middleware, for the client.
+    #[doc(hidden)]
+    pub fn from_middleware(e: anyhow::Error) -> Self {
+        TransportError(TransportErrorKind::Middleware(e))
+    }
+
+    /// Returns the status code, if the error was generated from a response.
+    pub fn status(&self) -> Option<reqwest::StatusCode> {
+        match &self.0 {
+            TransportErrorKind::Reqwest(e) => e.status(),
+            TransportErrorKind::Middleware(_) => None,
+        }
+    }
+
+    /// Returns true if the request timed out.
+    pub fn is_timeout(&self) -> bool {
+        match &self.0 {
+            TransportErrorKind::Reqwest(e) => e.is_timeout(),
+            TransportErrorKind::Middleware(_) => false,
+        }
+    }
+
+    /// Returns true if we could not connect to the server, never on wasm.
+    pub fn is_connect(&self) -> bool {
+        match &self.0 {
+            #[cfg(not(target_arch = "wasm32"))]
+            TransportErrorKind::Reqwest(e) => e.is_connect(),
+            _ => false,
+        }
+    }
+
+    /// Returns true if the request could not be built.
+    pub fn is_builder(&self) -> bool {
+        match &self.0 {
+            TransportErrorKind::Reqwest(e) => e.is_builder(),
+            TransportErrorKind::Middleware(_) => false,
+        }
     }
 }
 
-#[cfg(feature = "retry")]
-impl From<reqwest_middleware::Error> for Error {
-    fn from(e: reqwest_middleware::Error) -> Self {
-        Self::CommunicationError(e)
+impl From<reqwest::Error> for TransportError {
+    fn from(e: reqwest::Error) -> Self {
+        TransportError(TransportErrorKind::Reqwest(e))
+    }
+}
+
+impl std::fmt::Display for TransportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.0 {
+            TransportErrorKind::Reqwest(e) => write!(f, "{}", e),
+            TransportErrorKind::Middleware(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::fmt::Debug for TransportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.0 {
+            TransportErrorKind::Reqwest(e) => write!(f, "{:?}", e),
+            TransportErrorKind::Middleware(e) => write!(f, "{:?}", e),
+        }
+    }
+}
+
+impl std::error::Error for TransportError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.0 {
+            TransportErrorKind::Reqwest(e) => e.source(),
+            TransportErrorKind::Middleware(e) => e.source(),
+        }
+    }
+}
+
+/// A response of the server, the same with or without the middleware of the
+/// `retry` feature.
+#[derive(Debug)]
+pub struct ApiResponse(reqwest::Response);
+
+impl ApiResponse {
+    /// Wrap a response, for the client.
+    #[doc(hidden)]
+    pub fn new(response: reqwest::Response) -> Self {
+        ApiResponse(response)
+    }
+
+    /// The status of the response.
+    pub fn status(&self) -> reqwest::StatusCode {
+        self.0.status()
+    }
+
+    /// The headers of the response.
+    pub fn headers(&self) -> &reqwest::header::HeaderMap {
+        self.0.headers()
+    }
+
+    /// The url of the response, after the redirects.
+    pub fn url(&self) -> &reqwest::Url {
+        self.0.url()
+    }
+
+    /// The body of the response, as text.
+    pub async fn text(self) -> Result<String, Error> {
+        Ok(self.0.text().await?)
+    }
+
+    /// The body of the response.
+    pub async fn bytes(self) -> Result<Vec<u8>, Error> {
+        Ok(self.0.bytes().await?.to_vec())
+    }
+}
+
+/// The id the server gave a request, from the `REQUEST_ID_HEADER` header of the
+/// response or else from the `request_id` field of its body.
+pub fn request_id_of(header: Option<String>, body: &str) -> Option<String> {
+    header.or_else(|| {
+        serde_json::from_str::<serde_json::Value>(body)
+            .ok()?
+            .get("request_id")?
+            .as_str()
+            .map(str::to_string)
+    })
+}
+
+/// The `error_code` of the body of an error response, if it is JSON with one.
+pub fn error_code_of(body: &str) -> Option<ErrorCode> {
+    #[derive(serde::Deserialize)]
+    struct Body {
+        error_code: Option<ErrorCode>,
+    }
+
+    serde_json::from_str::<Body>(body).ok()?.error_code
+}
+
+/// Check the items of an array that must be unique before we send it, the error
+/// lists every duplicated value once.
+#[allow(clippy::result_large_err)]
+pub fn check_unique_items<T: PartialEq + std::fmt::Debug>(
+    name: &str,
+    items: &[T],
+) -> Result<(), Error> {
+    let mut duplicates: Vec<&T> = Vec::new();
+    for (i, item) in items.iter().enumerate() {
+        if items[..i].contains(item) && !duplicates.contains(&item) {
+            duplicates.push(item);
+        }
+    }
+
+    if duplicates.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::InvalidRequest(format!(
+            "the items of `{}` must be unique, got duplicates: {:?}",
+            name, duplicates
+        )))
     }
 }
 
 impl From<reqwest::Error> for Error {
     fn from(e: reqwest::Error) -> Self {
-        Self::RequestError(e)
+        Self::RequestError(e.into())
     }
 }
 
@@ -89,35 +438,58 @@ impl From<serde_json::Error> for Error {
         Self::SerdeError {
             error: format_serde_error::SerdeError::new(String::new(), e),
             status: reqwest::StatusCode::INTERNAL_SERVER_ERROR,
+            request_id: None,
         }
     }
 }
 
 impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.fmt_error(f)?;
+        match self.request_id() {
+            Some(request_id) => write!(f, " (request id: {})", request_id),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Error {
+    fn fmt_error(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Error::InvalidRequest(s) => {
                 write!(f, "Invalid Request: {}", s)
             }
-            #[cfg(feature = "retry")]
             Error::CommunicationError(e) => {
                 write!(f, "Communication Error: {}", e)
             }
             Error::RequestError(e) => {
                 write!(f, "Request Error: {}", e)
             }
-            Error::SerdeError { error, status: _ } => {
+            Error::SerdeError { error, .. } => {
                 write!(f, "Serde Error: {}", error)
             }
             Error::InvalidResponsePayload { error, response: _ } => {
                 write!(f, "Invalid Response Payload: {}", error)
             }
-            Error::Server { body, status } => {
+            Error::Server { body, status, .. } => {
                 write!(f, "Server Error: {} {}", status, body)
             }
+            Error::Api { body, status, .. } => {
+                write!(f, "Server Error: {} {:?}", status, body)
+            }
             Error::UnexpectedResponse(r) => {
                 write!(f, "Unexpected Response: {:?}", r)
             }
+            Error::TooManyPages {
+                max_pages,
+                next_page_token,
+            } => {
+                write!(
+                    f,
+                    "Too Many Pages: fetched {} pages without reaching the last one, the server probably returns page tokens in a loop (next page token: {:?})",
+                    max_pages, next_page_token
+                )
+            }
         }
     }
 }
@@ -131,11 +503,150 @@ impl std::fmt::Debug for Error {
 impl std::error::Error for Error {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
-            #[cfg(feature = "retry")]
             Error::CommunicationError(e) => Some(e),
-            Error::SerdeError { error, status: _ } => Some(error),
+            Error::SerdeError { error, .. } => Some(error),
             Error::InvalidResponsePayload { error, response: _ } => Some(error),
             _ => None,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        check_unique_items, error_code_of, request_id_of, ApiResponse, Error, ErrorClass,
+        TransportError,
+    };
+
+    fn server(status: u16, body: &str) -> Error {
+        Error::Server {
+            body: body.to_string(),
+            status: reqwest::StatusCode::from_u16(status).unwrap(),
+            request_id: None,
+            error_code: error_code_of(body),
+        }
+    }
+
+    fn response(status: u16) -> Error {
+        Error::UnexpectedResponse(ApiResponse::new(reqwest::Response::from(
+            http::Response::builder().status(status).body("").unwrap(),
+        )))
+    }
+
+    #[test]
+    fn test_error_classification() {
+        let cases = vec![
+            (server(503, ""), ErrorClass::Transient),
+            (response(504), ErrorClass::Transient),
+            (response(202), ErrorClass::Transient),
+            (server(429, "slow down"), ErrorClass::RateLimited),
+            (response(401), ErrorClass::AuthExpired),
+            (server(404, ""), ErrorClass::ClientBug),
+            (
+                Error::InvalidRequest("bad".to_string()),
+                ErrorClass::ClientBug,
+            ),
+            (server(500, "oops"), ErrorClass::ServerBug),
+            (
+                Error::TooManyPages {
+                    max_pages: 10,
+                    next_page_token: Some("a".to_string()),
+                },
+                ErrorClass::ServerBug,
+            ),
+            (
+                server(501, r#"{"error_code": "something_new", "message": "no"}"#),
+                ErrorClass::ServerBug,
+            ),
+            (response(302), ErrorClass::Unknown),
+            (
+                Error::CommunicationError(TransportError::from_middleware(anyhow::anyhow!(
+                    "no more retries"
+                ))),
+                ErrorClass::Unknown,
+            ),
+            (
+                Error::from(serde_json::from_str::<u8>("x").unwrap_err()),
+                ErrorClass::Unknown,
+            ),
+        ];
+
+        for (err, class) in cases {
+            assert_eq!(err.classification(), class, "{}", err);
+            assert_eq!(
+                err.is_retryable(),
+                matches!(class, ErrorClass::Transient | ErrorClass::RateLimited),
+                "{}",
+                err
+            );
+        }
+    }
+
+    #[test]
+    fn test_request_id() {
+        let body = r#"{"message": "oops", "request_id": "from-body"}"#;
+        assert_eq!(
+            request_id_of(Some("from-header".to_string()), body).as_deref(),
+            Some("from-header")
+        );
+        assert_eq!(request_id_of(None, body).as_deref(), Some("from-body"));
+        assert_eq!(request_id_of(None, "oops"), None);
+
+        let err = Error::Server {
+            body: "oops".to_string(),
+            status: reqwest::StatusCode::INTERNAL_SERVER_ERROR,
+            request_id: Some("req-1".to_string()),
+            error_code: None,
+        };
+        assert_eq!(err.request_id(), Some("req-1"));
+        assert_eq!(
+            err.to_string(),
+            "Server Error: 500 Internal Server Error oops (request id: req-1)"
+        );
+        assert_eq!(
+            server(500, "oops").to_string(),
+            "Server Error: 500 Internal Server Error oops"
+        );
+
+        let err = Error::UnexpectedResponse(ApiResponse::new(reqwest::Response::from(
+            http::Response::builder()
+                .status(302)
+                .header("x-request-id", "req-2")
+                .body("")
+                .unwrap(),
+        )));
+        assert_eq!(err.request_id(), Some("req-2"));
+    }
+
+    #[test]
+    fn test_api_error_code() {
+        let err = server(500, r#"{"error_code": "internal_api", "message": "oops"}"#);
+        assert_eq!(
+            err.api_error_code().map(|code| code.to_string()).as_deref(),
+            Some("internal_api")
+        );
+
+        // No code, or no body to have one.
+        assert_eq!(server(500, r#"{"message": "oops"}"#).api_error_code(), None);
+        assert_eq!(
+            server(500, r#"{"error_code": null}"#).api_error_code(),
+            None
+        );
+        assert_eq!(server(500, "oops").api_error_code(), None);
+        assert_eq!(response(500).api_error_code(), None);
+    }
+
+    #[test]
+    fn test_check_unique_items() {
+        assert!(check_unique_items("ids", &["a", "b", "c"]).is_ok());
+        assert!(check_unique_items::<u8>("ids", &[]).is_ok());
+
+        match check_unique_items("ids", &["a", "b", "a", "c", "b", "a"]) {
+            Err(Error::InvalidRequest(msg)) => assert_eq!(
+                msg,
+                r#"the items of `ids` must be unique, got duplicates: ["a", "b"]"#
+            ),
+            other => panic!("unexpected result: {:?}", other.map_err(|e| e.to_string())),
+        }
+    }
+}
@@ -294,6 +294,96 @@ pub fn generate_example_json_from_schema(
     })
 }
 
+/// Generate an example value for a date or time format.
+fn generate_example_datetime(
+    type_space: &crate::types::TypeSpace,
+    format: &str,
+) -> proc_macro2::TokenStream {
+    match type_space.opts.datetime_crate {
+        crate::DateTimeCrate::Chrono => match format {
+            "time" => quote!(chrono::Utc::now().time()),
+            "date" => quote!(chrono::Utc::now().date_naive()),
+            "partial-date-time" => quote!(chrono::Utc::now().naive_utc()),
+            _ => quote!(chrono::Utc::now()),
+        },
+        // The wrapper types don't know what time it is, so parse a fixed one.
+        crate::DateTimeCrate::Time => match format {
+            "time" => quote!(crate::types::datetime::Time::from_str("12:00:00")?),
+            "date" => quote!(crate::types::datetime::Date::from_str("2024-01-01")?),
+            "partial-date-time" => {
+                quote!(crate::types::datetime::PartialDateTime::from_str(
+                    "2024-01-01T12:00:00"
+                )?)
+            }
+            _ => quote!(crate::types::datetime::DateTime::from_str(
+                "2024-01-01T12:00:00Z"
+            )?),
+        },
+    }
+}
+
+/// Generate the Rust code of a value the spec gives as an example of `schema`. We
+/// only do it for the strings without a format, the values of the string enums,
+/// the numbers and the booleans, `None` for anything else, so the callers make one
+/// up instead.
+pub fn generate_example_rust_from_value(
+    type_space: &crate::types::TypeSpace,
+    name: &str,
+    schema: &openapiv3::Schema,
+    value: &serde_json::Value,
+    in_crate: bool,
+) -> Result<Option<proc_macro2::TokenStream>> {
+    Ok(match (&schema.schema_kind, value) {
+        (openapiv3::SchemaKind::Type(Type::String(s)), serde_json::Value::String(v))
+            if !s.enumeration.is_empty() =>
+        {
+            if !s.enumeration.contains(&Some(v.to_string())) {
+                return Ok(None);
+            }
+            // The enum of only the value gives the variant of the value.
+            let schema = openapiv3::Schema {
+                schema_data: schema.schema_data.clone(),
+                schema_kind: openapiv3::SchemaKind::Type(Type::String(openapiv3::StringType {
+                    enumeration: vec![Some(v.to_string())],
+                    ..s.clone()
+                })),
+            };
+            Some(generate_example_rust_from_schema(
+                type_space, name, &schema, in_crate,
+            )?)
+        }
+        (openapiv3::SchemaKind::Type(Type::String(s)), serde_json::Value::String(v))
+            if s.format.is_empty() =>
+        {
+            Some(quote!(#v.to_string()))
+        }
+        (openapiv3::SchemaKind::Type(Type::Number(_)), serde_json::Value::Number(v)) => {
+            let Some(v) = v.as_f64() else {
+                return Ok(None);
+            };
+            let v = proc_macro2::Literal::f64_unsuffixed(v);
+            let t =
+                crate::types::get_type_name_for_schema(name, schema, &type_space.spec, in_crate)?
+                    .strip_option()?;
+            Some(quote!(#v as #t))
+        }
+        (openapiv3::SchemaKind::Type(Type::Integer(_)), serde_json::Value::Number(v)) => {
+            let Some(v) = v.as_i64() else {
+                return Ok(None);
+            };
+            let v = proc_macro2::Literal::i64_unsuffixed(v);
+            let t =
+                crate::types::get_type_name_for_schema(name, schema, &type_space.spec, in_crate)?
+                    .strip_option()?;
+            Some(quote!(#v as #t))
+        }
+        (openapiv3::SchemaKind::Type(Type::Boolean { .. }), serde_json::Value::Bool(v)) => {
+            Some(quote!(#v))
+        }
+        _ => None,
+    })
+}
+
 /// Generates example rust code for creating a specific type.
 pub fn generate_example_rust_from_schema(
     type_space: &crate::types::TypeSpace,
@@ -325,10 +415,10 @@ pub fn generate_example_rust_from_schema(
             } else {
                 match &s.format {
                     openapiv3::VariantOrUnknownOrEmpty::Item(openapiv3::StringFormat::DateTime) => {
-                        quote!(chrono::Utc::now())
+                        generate_example_datetime(type_space, "date-time")
                     }
                     openapiv3::VariantOrUnknownOrEmpty::Item(openapiv3::StringFormat::Date) => {
-                        quote!(chrono::Utc::now().date_naive())
+                        generate_example_datetime(type_space, "date")
                     }
                     openapiv3::VariantOrUnknownOrEmpty::Item(openapiv3::StringFormat::Password) => {
                         quote!("some-password".to_string())
@@ -370,15 +460,8 @@ pub fn generate_example_rust_from_schema(
                         "hostname" => {
                             quote!("localhost")
                         }
-                        "time" => {
-                            quote!(chrono::Utc::now().time())
-                        }
-                        "date" => {
-                            quote!(chrono::Utc::now().date_naive())
-                        }
-                        "date-time" => quote!(chrono::Utc::now()),
-                        "partial-date-time" => {
-                            quote!(chrono::Utc::now().naive_utc())
+                        "time" | "date" | "date-time" | "partial-date-time" => {
+                            generate_example_datetime(type_space, f)
                         }
                         "id" => quote!("d9797f8d-9ad6-4e08-90d7-2ec17e13471c"),
                         f => {
@@ -464,7 +547,15 @@ pub fn generate_example_rust_from_schema(
                             true,
                         )?;
                         // Check if we should render the schema.
-                        if v.should_render()? {
+                        if crate::types::has_inline_one_of_items(s) {
+                            // The union in the items is always named after the struct.
+                            t_name = crate::types::get_type_name_for_schema(
+                                &format!("{} {}", name, k),
+                                s,
+                                &type_space.spec,
+                                true,
+                            )?;
+                        } else if v.should_render()? {
                             // Check if we already have a type with this name.
                             if let Some(rendered) = type_space
                                 .types
@@ -519,6 +610,19 @@ pub fn generate_example_rust_from_schema(
 
                 let k_ident = format_ident!("{}", crate::types::clean_property_name(k));
 
+                // The array is a set on the struct.
+                let example = if type_space
+                    .get_property_unique_items(&crate::types::clean_property_name(k), v)
+                    == Some(crate::types::UniqueItems::Set)
+                {
+                    quote!(#example.into_iter().collect())
+                } else if type_space.is_secret_property(&inner_schema) {
+                    // The secrets are kept in a `Secret`.
+                    quote!(#example.into())
+                } else {
+                    example
+                };
+
                 // Check if this type is required.
                 if (!o.required.contains(k) || inner_schema.schema_data.nullable)
                     && !is_default_property(&type_name, &inner_schema.schema_data)?
@@ -858,6 +962,19 @@ pub fn generate_example_rust_from_schema(
                 let any_of_item_schema =
                     any_of_item.get_schema_from_reference(&type_space.spec, true)?;
                 generate_example_rust_from_schema(type_space, name, &any_of_item_schema, in_crate)?
+            } else if crate::types::is_tagged_any_of(any_of, &type_space.spec)? {
+                // A tagged any of is rendered as a one of.
+                generate_example_rust_from_schema(
+                    type_space,
+                    name,
+                    &openapiv3::Schema {
+                        schema_data: schema.schema_data.clone(),
+                        schema_kind: openapiv3::SchemaKind::OneOf {
+                            one_of: any_of.clone(),
+                        },
+                    },
+                    in_crate,
+                )?
             } else {
                 // The any of needs to be an object with optional values since it can be any (one or more) of multiple types.
                 // We want to iterate over each of the subschemas and combine all of the types.
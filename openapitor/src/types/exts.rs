@@ -373,6 +373,9 @@ pub trait TokenStreamExt {
     /// Return `true` if the token stream is a string.
     fn is_string(&self) -> Result<bool>;
 
+    /// Return `true` if the token stream is a boolean or a number.
+    fn is_scalar(&self) -> Result<bool>;
+
     /// Strip the `.to_string()` from the token such that it becomes a `&str`.
     fn strip_to_string(&self) -> Result<proc_macro2::TokenStream>;
 
@@ -431,6 +434,28 @@ impl TokenStreamExt for proc_macro2::TokenStream {
             || rendered == "&'_str")
     }
 
+    fn is_scalar(&self) -> Result<bool> {
+        let rendered = self.rendered()?;
+        Ok(matches!(
+            rendered.as_str(),
+            "bool"
+                | "i8"
+                | "i16"
+                | "i32"
+                | "i64"
+                | "i128"
+                | "isize"
+                | "u8"
+                | "u16"
+                | "u32"
+                | "u64"
+                | "u128"
+                | "usize"
+                | "f32"
+                | "f64"
+        ))
+    }
+
     fn strip_to_string(&self) -> Result<proc_macro2::TokenStream> {
         let rendered = self.rendered()?;
         let rendered = rendered.trim_end_matches(".to_string()");
@@ -489,10 +514,15 @@ impl SchemaRenderExt for openapiv3::ReferenceOr<Box<openapiv3::Schema>> {
                         }
                     }
                     openapiv3::SchemaKind::Type(openapiv3::Type::Object(o)) => {
-                        if o.properties.is_empty() {
-                            Ok(false)
-                        } else {
+                        if !o.properties.is_empty() {
                             Ok(true)
+                        } else if let Some(openapiv3::AdditionalProperties::Schema(values)) =
+                            &o.additional_properties
+                        {
+                            // A map, we might need to render the type of the values.
+                            values.should_render()
+                        } else {
+                            Ok(false)
                         }
                     }
                     // If it is an array, we might need to render the inner type.
@@ -522,9 +552,43 @@ pub trait OperationExt {
     fn get_fn_name_ident(&self) -> Result<proc_macro2::Ident>;
     /// Get the first tag for the operation.
     fn get_tag(&self) -> Result<String>;
+    /// Returns `true` if the operation upgrades the connection to a websocket. The
+    /// other methods of its path are plain requests unless they say so too.
+    fn is_websocket(&self) -> bool;
+    /// Get the parameters of the operation expanded, then the parameters of its
+    /// path it does not override with one of the same name and location.
+    fn get_parameters(
+        &self,
+        spec: &openapiv3::OpenAPI,
+        path_params: &[openapiv3::ReferenceOr<openapiv3::Parameter>],
+    ) -> Result<Vec<openapiv3::Parameter>>;
 }
 
 impl OperationExt for openapiv3::Operation {
+    fn get_parameters(
+        &self,
+        spec: &openapiv3::OpenAPI,
+        path_params: &[openapiv3::ReferenceOr<openapiv3::Parameter>],
+    ) -> Result<Vec<openapiv3::Parameter>> {
+        let mut parameters = self
+            .parameters
+            .iter()
+            .map(|parameter| parameter.expand(spec))
+            .collect::<Result<Vec<_>>>()?;
+        for parameter in path_params {
+            let parameter = parameter.expand(spec)?;
+            let is_overridden = parameters.iter().any(|other| {
+                std::mem::discriminant(other) == std::mem::discriminant(&parameter)
+                    && other.parameter_data_ref().name == parameter.parameter_data_ref().name
+            });
+            if !is_overridden {
+                parameters.push(parameter);
+            }
+        }
+
+        Ok(parameters)
+    }
+
     fn get_tag(&self) -> Result<String> {
         match self.tags.first() {
             Some(tag) => Ok(crate::clean_tag_name(tag)),
@@ -532,7 +596,23 @@ impl OperationExt for openapiv3::Operation {
         }
     }
 
+    fn is_websocket(&self) -> bool {
+        self.extensions.contains_key("x-dropshot-websocket")
+    }
+
     fn get_fn_name(&self) -> Result<String> {
+        // The spec can name the function itself.
+        if let Some(name) = self.extensions.get("x-rust-name") {
+            return match name.as_str() {
+                Some(name) if is_fn_name(name) => Ok(name.to_string()),
+                _ => anyhow::bail!(
+                    "`x-rust-name` of operation `{}` must be a snake case function name, not {}",
+                    self.operation_id.as_deref().unwrap_or_default(),
+                    name
+                ),
+            };
+        }
+
         let tag = self.get_tag()?;
 
         let mut name = self
@@ -542,7 +622,7 @@ impl OperationExt for openapiv3::Operation {
             .to_string();
 
         // Convert to snake case.
-        name = inflector::cases::snakecase::to_snake_case(&name);
+        name = crate::types::casing::to_snake_case(&name);
 
         // Remove any stutters with the tag name.
         name = remove_stutters(&name, &tag);
@@ -561,6 +641,11 @@ impl OperationExt for openapiv3::Operation {
     }
 }
 
+/// Check if a name can be used for a function as it is.
+fn is_fn_name(name: &str) -> bool {
+    syn::parse_str::<syn::Ident>(name).is_ok() && crate::types::casing::to_snake_case(name) == name
+}
+
 /// Remove any stutters with a string.
 fn remove_stutters(whole: &str, s: &str) -> String {
     let mut whole = whole.to_string();
@@ -585,3 +670,65 @@ fn singular(s: &str) -> String {
 
     s.to_string()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{OperationExt, ReferenceOrExt};
+
+    /// The operation and the parameters of the path of `path` in the fixture.
+    fn get_operation(
+        spec: &openapiv3::OpenAPI,
+        path: &str,
+    ) -> (
+        openapiv3::Operation,
+        Vec<openapiv3::ReferenceOr<openapiv3::Parameter>>,
+    ) {
+        let item = spec.paths.paths[path].item().unwrap();
+        (item.get.clone().unwrap(), item.parameters.clone())
+    }
+
+    #[test]
+    fn test_referenced_parameters_are_expanded() {
+        let spec = crate::load_json_spec(include_str!(
+            "../../tests/types/input/referenced-params.json"
+        ))
+        .unwrap();
+
+        let (inline, _) = get_operation(&spec, "/inline/things");
+        let inline = inline.get_parameters(&spec, &[]).unwrap();
+        for path in ["/referenced/things", "/aliased/things"] {
+            let (op, path_params) = get_operation(&spec, path);
+            for parameter in op.get_parameters(&spec, &path_params).unwrap() {
+                // The same as the inline one, `required`, docs, deprecation and
+                // style included.
+                let name = &parameter.parameter_data_ref().name;
+                let expected = inline
+                    .iter()
+                    .find(|inline| inline.parameter_data_ref().name == *name)
+                    .unwrap();
+                assert_eq!(&parameter, expected, "{} of {}", name, path);
+            }
+        }
+    }
+
+    #[test]
+    fn test_operation_parameters_override_the_path() {
+        let spec = crate::load_json_spec(include_str!(
+            "../../tests/types/input/referenced-params.json"
+        ))
+        .unwrap();
+        let (op, path_params) = get_operation(&spec, "/orgs/{org}/things");
+
+        let parameters = op.get_parameters(&spec, &path_params).unwrap();
+        let names = parameters
+            .iter()
+            .map(|parameter| {
+                let data = parameter.parameter_data_ref();
+                (data.name.as_str(), data.required)
+            })
+            .collect::<Vec<_>>();
+        // The required `project` of the operation wins over the optional one of the
+        // path.
+        assert_eq!(names, [("project", true), ("org", true)]);
+    }
+}
@@ -0,0 +1,6 @@
+//! The identifier types used by the API.
+//!
+//! Every generated type refers to these aliases rather than to `uuid` directly.
+
+/// A UUID, `uuid` in the spec.
+pub type Uuid = uuid::Uuid;
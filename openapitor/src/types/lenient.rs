@@ -0,0 +1,114 @@
+//! Deserializers that accept numbers and booleans encoded as strings, e.g. `"42"`
+//! as well as `42`. They are used on the fields of the types when the `lenient`
+//! feature is enabled, serializing always writes the native values.
+
+use serde::Deserialize;
+
+/// A value, or the value encoded as a string.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum OrString<T> {
+    Value(T),
+    String(String),
+}
+
+impl<T> OrString<T>
+where
+    T: std::str::FromStr,
+    T::Err: std::fmt::Display,
+{
+    fn into_value<E: serde::de::Error>(self) -> Result<T, E> {
+        match self {
+            OrString::Value(value) => Ok(value),
+            OrString::String(s) => s
+                .trim()
+                .parse()
+                .map_err(|e| E::custom(format!("invalid value `{}`: {}", s, e))),
+        }
+    }
+}
+
+/// Deserialize a number or a boolean, or the same encoded as a string.
+pub fn deserialize<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de> + std::str::FromStr,
+    T::Err: std::fmt::Display,
+{
+    OrString::<T>::deserialize(deserializer)?.into_value()
+}
+
+/// Deserialize an optional number or boolean, or the same encoded as a string.
+pub fn deserialize_option<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de> + std::str::FromStr,
+    T::Err: std::fmt::Display,
+{
+    Option::<OrString<T>>::deserialize(deserializer)?
+        .map(OrString::into_value)
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    struct Counts {
+        #[serde(deserialize_with = "super::deserialize")]
+        count: i64,
+        #[serde(deserialize_with = "super::deserialize")]
+        ratio: f64,
+        #[serde(deserialize_with = "super::deserialize")]
+        done: bool,
+        #[serde(
+            default,
+            skip_serializing_if = "Option::is_none",
+            deserialize_with = "super::deserialize_option"
+        )]
+        limit: Option<u32>,
+    }
+
+    #[test]
+    fn test_lenient_accepts_both_encodings() {
+        let expected = Counts {
+            count: 42,
+            ratio: 0.5,
+            done: true,
+            limit: Some(7),
+        };
+
+        let native: Counts =
+            serde_json::from_str(r#"{"count": 42, "ratio": 0.5, "done": true, "limit": 7}"#)
+                .unwrap();
+        assert_eq!(native, expected);
+        let strings: Counts = serde_json::from_str(
+            r#"{"count": "42", "ratio": "0.5", "done": "true", "limit": " 7 "}"#,
+        )
+        .unwrap();
+        assert_eq!(strings, expected);
+
+        // We always write the native values.
+        assert_eq!(
+            serde_json::to_string(&strings).unwrap(),
+            r#"{"count":42,"ratio":0.5,"done":true,"limit":7}"#
+        );
+    }
+
+    #[test]
+    fn test_lenient_optional_values() {
+        let missing: Counts =
+            serde_json::from_str(r#"{"count": 1, "ratio": 1, "done": false}"#).unwrap();
+        assert_eq!(missing.limit, None);
+        let null: Counts =
+            serde_json::from_str(r#"{"count": 1, "ratio": 1, "done": false, "limit": null}"#)
+                .unwrap();
+        assert_eq!(null.limit, None);
+    }
+
+    #[test]
+    fn test_lenient_rejects_garbage() {
+        let err = serde_json::from_str::<Counts>(r#"{"count": "many", "ratio": 1, "done": true}"#)
+            .unwrap_err();
+        assert!(err.to_string().contains("invalid value `many`"), "{}", err);
+    }
+}
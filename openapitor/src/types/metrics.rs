@@ -0,0 +1,64 @@
+//! Hooks for recording metrics about API calls.
+
+/// A sink for metrics about the API calls made by the client.
+///
+/// Implement this to forward request counts, error counts and latencies to the
+/// metrics library of your choice, then install it with `Client::set_metrics`.
+pub trait ApiMetrics: std::fmt::Debug + Send + Sync {
+    /// Record a finished API call, successful or not.
+    fn record(&self, _call: &ApiCall) {}
+}
+
+/// The default metrics sink, it does nothing.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoopMetrics;
+
+impl ApiMetrics for NoopMetrics {}
+
+/// A single call to an API operation, one request sent by the client. A paginated
+/// stream makes one call for every page it gets.
+#[derive(Debug, Clone)]
+pub struct ApiCall {
+    /// The id of the operation in the spec.
+    pub operation_id: String,
+    /// The HTTP method of the request.
+    pub method: reqwest::Method,
+    /// The status of the response, if we got one.
+    pub status: Option<reqwest::StatusCode>,
+    /// If the API answered with a success status.
+    pub success: bool,
+    /// How long the call took, including any retries.
+    pub duration: std::time::Duration,
+
+    started: std::time::Instant,
+}
+
+impl ApiCall {
+    /// Start timing a call to an operation.
+    #[doc(hidden)]
+    pub fn start(operation_id: &str, method: &reqwest::Method) -> Self {
+        Self {
+            operation_id: operation_id.to_string(),
+            method: method.clone(),
+            status: None,
+            success: false,
+            duration: std::time::Duration::ZERO,
+            started: std::time::Instant::now(),
+        }
+    }
+
+    /// Stop timing the call and record its outcome.
+    #[doc(hidden)]
+    pub fn finish(
+        mut self,
+        result: &Result<reqwest::Response, crate::types::error::Error>,
+    ) -> Self {
+        self.duration = self.started.elapsed();
+        self.status = match result {
+            Ok(response) => Some(response.status()),
+            Err(err) => err.status(),
+        };
+        self.success = self.status.is_some_and(|status| status.is_success());
+        self
+    }
+}
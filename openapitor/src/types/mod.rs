@@ -1,43 +1,48 @@
 //! A library for converting OpenAPI schemas to Rust types.
 
 pub mod base64;
+pub mod casing;
+pub mod conformance;
+pub mod datetime;
+pub mod deprecation;
+pub mod docs;
+pub mod equality;
 pub mod error;
 pub mod example;
 pub mod exts;
+pub mod ids;
+pub mod lenient;
+pub mod metrics;
 pub mod multipart;
 pub mod paginate;
+pub mod paths;
 pub mod phone_number;
+pub mod poll;
 pub mod random;
+pub mod rate_limit;
+pub mod record;
+pub mod references;
+pub mod required;
+pub mod scopes;
+pub mod secret;
+pub mod servers;
+pub mod split;
+pub mod standalone;
+pub mod suggest;
+pub mod validation;
 
 use std::{collections::BTreeMap, str::FromStr};
 
 use anyhow::Result;
 use indexmap::map::IndexMap;
 use numeral::Cardinal;
-use once_cell::sync::Lazy;
 use openapiv3::{AnySchema, Schema, SchemaData, SchemaKind};
-use regex::Regex;
 
 use crate::types::exts::{
     ParameterExt, ParameterSchemaOrContentExt, ReferenceOrExt, SchemaRenderExt, StatusCodeExt,
     TokenStreamExt,
 };
 
-/// Regex to detect weird descriptions in OpenAPI specs.
-/// Rustdoc interprets the indented lines as a code block, and tries to run them
-/// as code. They aren't always code though.
-static LEADING_SPACES: Lazy<Regex> = Lazy::new(|| Regex::new(r"\n +").unwrap());
-
-/// Collapse leading spaces at the beginning of lines.
-pub fn sanitize_indents(s: &str, name: String) -> std::borrow::Cow<'_, str> {
-    let new_str = LEADING_SPACES.replace_all(s, "\n");
-    if new_str.is_empty() {
-        format!("{}.", name).into()
-    } else {
-        new_str
-    }
-}
-
 /// Our collection of all our parsed types.
 #[derive(Debug, Clone)]
 pub struct TypeSpace {
@@ -51,22 +56,312 @@ pub struct TypeSpace {
     pub opts: crate::Opts,
 }
 
-/// Generate Rust types from an OpenAPI v3 spec.
+/// Rename component schemas whose Rust type names would only differ by case.
+///
+/// A schema with an `x-rust-name` extension is renamed to that, it is an error if
+/// the name is not a Rust type name or collides with another schema.
+/// For the others, the first schema keeps its name and every later one gets a
+/// numeric suffix (`OauthToken` => `OauthToken2`). All `$ref`s pointing at a
+/// renamed schema are rewritten as well, so every lookup by reference resolves to
+/// the new name. The names of the schemas are not part of the wire format, so this
+/// is safe to do.
+///
+/// Returns the updated spec along with a map of the original names to the new ones.
+pub fn dedupe_schema_names(
+    spec: &openapiv3::OpenAPI,
+) -> Result<(openapiv3::OpenAPI, BTreeMap<String, String>)> {
+    let mut renamed: BTreeMap<String, String> = BTreeMap::new();
+
+    let Some(components) = &spec.components else {
+        return Ok((spec.clone(), renamed));
+    };
+
+    // The names the spec picked itself.
+    let mut overrides: BTreeMap<&str, String> = BTreeMap::new();
+    for (name, schema) in &components.schemas {
+        let openapiv3::ReferenceOr::Item(schema) = schema else {
+            continue;
+        };
+        let Some(rust_name) = schema.schema_data.extensions.get("x-rust-name") else {
+            continue;
+        };
+        match rust_name.as_str() {
+            Some(rust_name) if !rust_name.is_empty() && proper_name(rust_name) == rust_name => {
+                overrides.insert(name, rust_name.to_string());
+            }
+            _ => anyhow::bail!(
+                "`x-rust-name` of schema `{}` must be a Rust type name, not {}",
+                name,
+                rust_name
+            ),
+        }
+    }
+
+    // Reserve all the original names, so a new name never shadows a real schema.
+    let mut originals: BTreeMap<String, &str> = BTreeMap::new();
+    for name in components.schemas.keys() {
+        let key = proper_name(overrides.get(name.as_str()).unwrap_or(name)).to_lowercase();
+        let other = originals.insert(key, name);
+        // Only the schemas without a name of their own get renamed.
+        if let Some(other) = other {
+            if let Some(rust_name) = overrides
+                .get(name.as_str())
+                .or_else(|| overrides.get(other))
+            {
+                anyhow::bail!(
+                    "schemas `{}` and `{}` would both be named `{}`, check their `x-rust-name`",
+                    other,
+                    name,
+                    rust_name
+                );
+            }
+        }
+    }
+    let mut taken: std::collections::HashSet<String> = Default::default();
+
+    for name in components.schemas.keys() {
+        if let Some(rust_name) = overrides.get(name.as_str()) {
+            taken.insert(proper_name(rust_name).to_lowercase());
+            if rust_name != name {
+                renamed.insert(name.to_string(), rust_name.to_string());
+            }
+            continue;
+        }
+
+        if taken.insert(proper_name(name).to_lowercase()) {
+            continue;
+        }
+
+        // We have a collision, find the next free suffix.
+        let mut n = 2;
+        let new_name = loop {
+            let candidate = format!("{}{}", name, n);
+            let key = proper_name(&candidate).to_lowercase();
+            if !taken.contains(&key) && !originals.contains_key(&key) {
+                taken.insert(key);
+                break candidate;
+            }
+            n += 1;
+        };
+
+        log::warn!(
+            "schema `{}` collides with another schema when converted to a Rust name, renaming it \
+             to `{}`",
+            name,
+            new_name
+        );
+        renamed.insert(name.to_string(), new_name);
+    }
+
+    if renamed.is_empty() {
+        return Ok((spec.clone(), renamed));
+    }
+
+    // Rename the schemas themselves, keeping their order.
+    let mut spec = spec.clone();
+    if let Some(components) = &mut spec.components {
+        components.schemas = std::mem::take(&mut components.schemas)
+            .into_iter()
+            .map(|(name, schema)| match renamed.get(&name) {
+                Some(new_name) => (new_name.to_string(), schema),
+                None => (name, schema),
+            })
+            .collect();
+    }
+
+    // Point the references to the new names.
+    references::rename_schema_references(&mut spec, &renamed);
+
+    Ok((spec, renamed))
+}
+
+/// Generate Rust types from an OpenAPI v3 spec, the error lists every schema that
+/// failed.
 pub fn generate_types(spec: &openapiv3::OpenAPI, opts: crate::Opts) -> Result<TypeSpace> {
+    // Make sure none of our component names collide once they are Rust names.
+    let (spec, _) = dedupe_schema_names(spec)?;
+    let mut failures = crate::failures::Failures::default();
+    let type_space = generate_types_collecting(&spec, opts, &mut failures)?;
+    failures.into_result()?;
+    Ok(type_space)
+}
+
+/// Generate Rust types from an OpenAPI v3 spec, going on past the schemas that fail
+/// and adding them to `failures`, with the components that depend on them. The
+/// names of the schemas must not collide, see `dedupe_schema_names`.
+pub fn generate_types_collecting(
+    spec: &openapiv3::OpenAPI,
+    opts: crate::Opts,
+    failures: &mut crate::failures::Failures,
+) -> Result<TypeSpace> {
+    // Name the fields with the casing of the options.
+    let _casing = casing::scoped(&opts);
+
+    // Find the dangling references first, with where they are.
+    references::check_references(spec)?;
+
+    // The codes of the errors are an enum with a catch-all, see `error_codes`.
+    let spec = &crate::error_codes::prepare(spec)?;
+
     // Include the base64 data type for byte data.
     let base64_mod = get_base64_mod()?;
 
+    // Include the date and time types, backed by the crate of our choice.
+    let datetime_mod = get_datetime_mod(&opts)?;
+
+    // Include the identifier types.
+    let ids_mod = get_ids_mod()?;
+
+    // Include the errors suggesting the values of the enums.
+    let suggest_mod = get_suggest_mod()?;
+
+    // Include the scopes of the OAuth 2.0 access tokens, if the spec has any.
+    let scope_mod = scopes::get_scope_mod(spec)?;
+
+    // Include the secrets, if we keep the passwords and the tokens in them.
+    let secret_mod = if opts.secret_fields {
+        get_secret_mod()?
+    } else {
+        quote!()
+    };
+
+    // Include the checks of the request bodies, if we generate them.
+    let validation_mod = if opts.validate_requests {
+        get_validation_mod()?
+    } else {
+        quote!()
+    };
+
+    // Include the deserializers for numbers and booleans encoded as strings.
+    let lenient_mod = get_lenient_mod()?;
+
     // Include the multipart type for multipart data.
     let multipart_mod = get_multipart_mod()?;
 
     // Include the paginate data type for pagination.
     let paginate_mod = get_paginate_mod()?;
 
+    // Include the paths of the operations, and the matching of paths against them.
+    let paths_mod = get_paths_mod(spec)?;
+
+    // Include the variables of the url of the server, if it has any.
+    let servers_mod = match crate::servers::find(spec) {
+        Some(server) => crate::servers::render_module(server)?,
+        None => quote!(),
+    };
+
     // Include the phone number data type for phone numbers.
     let phone_number_mod = get_phone_number_mod()?;
 
     // Include the error data type for phone numbers.
-    let error_mod = get_error_mod()?;
+    let error_mod = get_error_mod(spec, &opts)?;
+
+    // Include the metrics hooks for recording api calls.
+    let metrics_mod = get_metrics_mod()?;
+
+    // Include the rate limit reported by the responses.
+    let rate_limit_mod = get_rate_limit_mod()?;
+
+    // Include the deprecation reported by the responses.
+    let deprecation_mod = get_deprecation_mod()?;
+
+    // Include the recording and replaying of api calls.
+    let record_mod = get_record_mod()?;
+
+    // Include the retry policy of the client.
+    let retry_mod = get_retry_mod()?;
+
+    // Include the compression of the request bodies.
+    let compression_mod = get_compression_mod()?;
+
+    // Include the options changing the headers of the calls.
+    let call_options_mod = get_call_options_mod()?;
+
+    // Include the caches of the responses.
+    let cache_mod = get_cache_mod()?;
+
+    // Include the files the operations answer with, if they are downloads.
+    let download_mod = if opts.download_responses {
+        get_download_mod()?
+    } else {
+        quote!()
+    };
+
+    // Include the polling of the jobs, for the `_and_wait` functions.
+    let poll_mod = get_poll_mod()?;
+
+    // Include the chunked uploads, if the spec has upload sessions.
+    let upload_mod = if crate::upload_sessions::find(spec)?.is_empty() {
+        quote!()
+    } else {
+        get_upload_mod()?
+    };
+
+    // Include the managed websocket connections.
+    let websocket_mod = get_websocket_mod()?;
+
+    // Include the sessions checking the order of the commands, if we generate one.
+    let session_mod = if opts.session_config.is_some() {
+        let session_mod = get_session_mod()?;
+        quote!(
+            #[cfg(feature = "websocket")]
+            #[cfg(not(target_arch = "wasm32"))]
+            #session_mod
+        )
+    } else {
+        quote!()
+    };
+
+    // The modules for sending requests, a crate of only the types has none of them.
+    let requests_only = |module: proc_macro2::TokenStream| {
+        if opts.types_only {
+            quote!()
+        } else {
+            module
+        }
+    };
+    let multipart_mod = requests_only(quote!(#[cfg(feature = "requests")] #multipart_mod));
+    let paginate_mod = requests_only(quote!(#[cfg(feature = "requests")] #paginate_mod));
+    let paths_mod = requests_only(quote!(#[cfg(feature = "requests")] #paths_mod));
+    let servers_mod = if servers_mod.is_empty() {
+        quote!()
+    } else {
+        requests_only(quote!(#[cfg(feature = "requests")] #servers_mod))
+    };
+    let error_mod = requests_only(quote!(#[cfg(feature = "requests")] #error_mod));
+    let metrics_mod = requests_only(quote!(#[cfg(feature = "metrics")] #metrics_mod));
+    let rate_limit_mod = requests_only(quote!(#[cfg(feature = "requests")] #rate_limit_mod));
+    let deprecation_mod = requests_only(quote!(#[cfg(feature = "requests")] #deprecation_mod));
+    let record_mod = requests_only(quote!(#[cfg(feature = "record")] #record_mod));
+    let retry_mod = requests_only(quote!(#[cfg(feature = "requests")] #retry_mod));
+    let compression_mod = requests_only(quote!(#[cfg(feature = "compression")] #compression_mod));
+    let call_options_mod = requests_only(quote!(#[cfg(feature = "requests")] #call_options_mod));
+    let cache_mod = requests_only(quote!(#[cfg(feature = "requests")] #cache_mod));
+    let download_mod = if opts.download_responses {
+        requests_only(quote!(#[cfg(feature = "requests")] #download_mod))
+    } else {
+        quote!()
+    };
+    let poll_mod = requests_only(quote!(
+        #[cfg(feature = "requests")]
+        #[cfg(not(target_arch = "wasm32"))]
+        #poll_mod
+    ));
+    let upload_mod = if upload_mod.is_empty() {
+        quote!()
+    } else {
+        requests_only(quote!(
+            #[cfg(feature = "requests")]
+            #[cfg(not(target_arch = "wasm32"))]
+            #upload_mod
+        ))
+    };
+    let websocket_mod = requests_only(quote!(
+        #[cfg(feature = "websocket")]
+        #[cfg(not(target_arch = "wasm32"))]
+        #websocket_mod
+    ));
+    let session_mod = requests_only(session_mod);
 
     // Let's start with the components if there are any.
 
@@ -82,16 +377,58 @@ pub fn generate_types(spec: &openapiv3::OpenAPI, opts: crate::Opts) -> Result<Ty
 
             #base64_mod
 
-            #[cfg(feature = "requests")]
+            #datetime_mod
+
+            #ids_mod
+
+            #suggest_mod
+
+            #scope_mod
+
+            #secret_mod
+
+            #validation_mod
+
+            #[cfg(feature = "lenient")]
+            #lenient_mod
+
             #multipart_mod
 
-            #[cfg(feature = "requests")]
             #paginate_mod
 
+            #paths_mod
+
+            #servers_mod
+
             #phone_number_mod
 
-            #[cfg(feature = "requests")]
             #error_mod
+
+            #metrics_mod
+
+            #rate_limit_mod
+
+            #deprecation_mod
+
+            #record_mod
+
+            #retry_mod
+
+            #compression_mod
+
+            #call_options_mod
+
+            #cache_mod
+
+            #download_mod
+
+            #poll_mod
+
+            #upload_mod
+
+            #websocket_mod
+
+            #session_mod
         ),
         opts,
     };
@@ -119,59 +456,129 @@ pub fn generate_types(spec: &openapiv3::OpenAPI, opts: crate::Opts) -> Result<Ty
     // We need to search each of these 4 places in the spec for schemae, and
     // generate Rust types for them.
 
+    // Each schema is kept with the reference of its component, for the failures.
     let mut schemas = Vec::new();
     // First, search for shared schemae that are reused across
     // parameters/bodies
     for (name, schema) in &components.schemas {
+        let reference = format!("#/components/schemas/{}", name);
         // Let's get the schema from the reference.
-        let schema = schema.get_schema_from_reference(spec, true)?;
-        schemas.push((name.to_owned(), schema));
+        match schema.get_schema_from_reference(spec, true) {
+            Ok(schema) => schemas.push((reference, name.to_owned(), schema)),
+            Err(err) => failures.component(&reference, &err),
+        }
     }
 
     // Search the parameters for schemae
     for (name, parameter) in &components.parameters {
-        let schema = (&parameter.expand(spec)?).data()?.format.schema()?;
-        // Let's get the schema from the reference.
-        let schema = schema.get_schema_from_reference(spec, true)?;
-        schemas.push((name.to_owned(), schema));
+        let reference = format!("#/components/parameters/{}", name);
+        let schema = (|| -> Result<openapiv3::Schema> {
+            let schema = (&parameter.expand(spec)?).data()?.format.schema()?;
+            // Let's get the schema from the reference.
+            schema.get_schema_from_reference(spec, true)
+        })();
+        match schema {
+            Ok(schema) => schemas.push((reference, name.to_owned(), schema)),
+            Err(err) => failures.component(&reference, &err),
+        }
     }
 
     // Search the responses for schemae
     for (name, response) in &components.responses {
-        for (content_name, content) in response.expand(spec)?.content {
-            if let Some(openapiv3::ReferenceOr::Item(i)) = content.schema {
-                // If the schema is a reference we don't care, since we would have already rendered
-                // that reference.
-                schemas.push((format!("{}_{}", name, content_name), i));
+        let reference = format!("#/components/responses/{}", name);
+        match response.expand(spec) {
+            Ok(response) => {
+                for (content_name, content) in response.content {
+                    if let Some(openapiv3::ReferenceOr::Item(i)) = content.schema {
+                        // If the schema is a reference we don't care, since we would have
+                        // already rendered that reference.
+                        schemas.push((reference.clone(), format!("{}_{}", name, content_name), i));
+                    }
+                }
             }
+            Err(err) => failures.component(&reference, &err),
         }
     }
 
     // Search the requests for schemae
     for (name, request_body) in &components.request_bodies {
-        for (content_name, content) in request_body.expand(spec)?.content {
-            if let Some(openapiv3::ReferenceOr::Item(i)) = content.schema {
-                // If the schema is a reference we don't care, since we would have already rendered
-                // that reference.
-                schemas.push((format!("{}_{}", name, content_name), i));
+        let reference = format!("#/components/requestBodies/{}", name);
+        match request_body.expand(spec) {
+            Ok(request_body) => {
+                for (content_name, content) in request_body.content {
+                    if let Some(openapiv3::ReferenceOr::Item(i)) = content.schema {
+                        // If the schema is a reference we don't care, since we would have
+                        // already rendered that reference.
+                        schemas.push((reference.clone(), format!("{}_{}", name, content_name), i));
+                    }
+                }
             }
+            Err(err) => failures.component(&reference, &err),
         }
     }
 
-    // Each schema becomes a Rust type
-    for (name, schema) in schemas {
-        type_space.render_schema(&name, &schema)?;
+    // Each schema becomes a Rust type, one that fails does not stop the others.
+    for (reference, name, schema) in schemas {
+        let rendered = required::check_required(&schema, &name)
+            .and_then(|_| type_space.render_schema(&name, &schema));
+        if let Err(err) = rendered {
+            failures.component(&reference, &err);
+        }
     }
 
+    // What references a failed schema can't be generated either.
+    failures.add_dependents(spec)?;
+
     Ok(type_space)
 }
 
 impl TypeSpace {
+    /// The alias of a type for the name of its schema, if proper_name changed it.
+    fn get_type_doc_alias(
+        &self,
+        name: &str,
+        type_name: &proc_macro2::Ident,
+    ) -> proc_macro2::TokenStream {
+        match &self.spec.components {
+            Some(components) if components.schemas.contains_key(name) => {
+                get_doc_alias(name, &type_name.to_string())
+            }
+            _ => quote!(),
+        }
+    }
+
     /// Pretty render the type space.
     pub fn render(&self) -> Result<String> {
         get_text_fmt(&self.rendered)
     }
 
+    /// Render a `schema_registry` function returning the JSON schema of every
+    /// type we rendered from the spec, sorted by name.
+    /// Since it is built from our type space it can not fall out of sync.
+    pub fn render_schema_registry(&self) -> proc_macro2::TokenStream {
+        // Every type we add to the rendered types derives `schemars::JsonSchema`.
+        let mut names: Vec<&String> = self.types.keys().collect();
+        names.sort();
+        let entries = names.into_iter().map(|name| {
+            let ident = format_ident!("{}", name);
+            quote! {
+                registry.push((#name, schemars::schema_for!(#ident)));
+            }
+        });
+
+        quote! {
+            /// The JSON schema of every type in this module, sorted by name.
+            #[cfg(feature = "registry")]
+            #[allow(clippy::vec_init_then_push)]
+            pub fn schema_registry() -> Vec<(&'static str, schemars::schema::RootSchema)> {
+                #[allow(unused_mut)]
+                let mut registry = Vec::new();
+                #(#entries)*
+                registry
+            }
+        }
+    }
+
     /// Add to our rendered types.
     pub fn add_to_rendered(
         &mut self,
@@ -180,8 +587,8 @@ impl TypeSpace {
     ) -> Result<()> {
         if let Some(item) = self.types.get(&name) {
             // We have a schema with the name already.
-            // Let's check if it's the same.
-            if &s != item {
+            // Let's check if it generates the same type.
+            if !equality::same_shape(&s, item, &self.opts.compared_schema_metadata) {
                 // Get the diff of the schemas.
                 let new = serde_json::to_string(&s)?;
                 let old = serde_json::to_string(&item)?;
@@ -227,7 +634,7 @@ impl TypeSpace {
                 // Let's ensure the items are a reference, otherwise we should render it.
                 if let Some(openapiv3::ReferenceOr::Item(s)) = &a.items {
                     // We need to render the item.
-                    return self.render_schema(name, s);
+                    return self.render_schema(&get_nested_type_name(name, "item", s), s);
                 }
 
                 Ok(())
@@ -269,6 +676,17 @@ impl TypeSpace {
                 let (p, r) = self.get_all_of_properties(name, all_of)?;
                 properties.extend(p);
                 required.extend(r);
+            } else if let SchemaKind::Any(any) = &schema.schema_kind {
+                if !is_all_of_override(any) {
+                    anyhow::bail!(
+                        "The all of {} is not an object, it is a {:?}",
+                        name,
+                        schema.schema_kind
+                    );
+                }
+                // This member only overrides the others, like making some of their
+                // properties required.
+                required.extend(any.required.iter().cloned());
             } else {
                 anyhow::bail!(
                     "The all of {} is not an object, it is a {:?}",
@@ -317,6 +735,14 @@ impl TypeSpace {
             }
         };
 
+        // Apply the description and nullable overrides from the members.
+        let mut data = data.clone();
+        let (description, nullable) = get_all_of_overrides(all_ofs);
+        if data.description.is_none() {
+            data.description = description;
+        }
+        data.nullable |= nullable;
+
         // Let's render the object.
         self.render_object(
             name,
@@ -325,7 +751,7 @@ impl TypeSpace {
                 required,
                 ..Default::default()
             },
-            data,
+            &data,
         )
     }
 
@@ -343,6 +769,12 @@ impl TypeSpace {
             return self.render_schema(name, first);
         }
 
+        // Objects that all have the same tag can only ever be one of them, that is a
+        // one of whatever the spec says.
+        if is_tagged_any_of(any_ofs, &self.spec)? {
+            return self.render_one_of(name, any_ofs, data);
+        }
+
         // The any of needs to be an object with optional values since it can be any (one or more) of multiple types.
         // We want to iterate over each of the subschemas and combine all of the types.
         // We assume all of the subschemas are objects.
@@ -385,12 +817,13 @@ impl TypeSpace {
     ) -> Result<()> {
         // Get the proper name version of the type.
         let one_of_name = get_type_name(name, data)?;
+        let doc_alias = self.get_type_doc_alias(name, &one_of_name);
 
         let description = if let Some(d) = &data.description {
-            let d_sanitized = sanitize_indents(d, one_of_name.to_string());
+            let d_sanitized = docs::sanitize_description(d, &one_of_name.to_string(), self);
             quote!(#[doc = #d_sanitized])
         } else {
-            quote!()
+            docs::fallback(self, || format!("Enum generated from schema `{}`.", name))
         };
 
         let mut values = quote!();
@@ -410,8 +843,12 @@ impl TypeSpace {
                         {
                             let inner_values =
                                 self.get_object_values(&inner_name_ident, o, false, None)?;
+                            let inner_description = docs::fallback(self, || {
+                                format!("Variant `{}` of `{}`.", inner_name, name)
+                            });
                             values = quote! {
                                 #values
+                                #inner_description
                                 #inner_name_ident {
                                     #inner_values
                                 },
@@ -423,6 +860,7 @@ impl TypeSpace {
         }
         let rendered = quote! {
             #description
+            #doc_alias
             #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug, Clone, schemars::JsonSchema)]
             pub enum #one_of_name {
                 #values
@@ -455,12 +893,13 @@ impl TypeSpace {
     ) -> Result<()> {
         // Get the proper name version of the type.
         let one_of_name = get_type_name(name, data)?;
+        let doc_alias = self.get_type_doc_alias(name, &one_of_name);
 
         let description = if let Some(d) = &data.description {
-            let d_sanitized = sanitize_indents(d, one_of_name.to_string());
+            let d_sanitized = docs::sanitize_description(d, &one_of_name.to_string(), self);
             quote!(#[doc = #d_sanitized])
         } else {
-            quote!()
+            docs::fallback(self, || format!("Enum generated from schema `{}`.", name))
         };
 
         // Check if this is a one_of with only one enum in each.
@@ -560,6 +999,7 @@ impl TypeSpace {
 
         let rendered = quote! {
             #description
+            #doc_alias
             #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug, Clone, schemars::JsonSchema)]
             #[cfg_attr(feature = "tabled", derive(tabled::Tabled))]
             #serde_options
@@ -624,14 +1064,18 @@ impl TypeSpace {
             );
         }
 
+        let sorted = self.sort_properties(o);
+        let o = sorted.as_ref().unwrap_or(o);
+
         // Get the proper name version of the name of the object.
         let struct_name = get_type_name(name, data)?;
+        let doc_alias = self.get_type_doc_alias(name, &struct_name);
 
         let description = if let Some(d) = &data.description {
-            let d_sanitized = sanitize_indents(d, struct_name.to_string());
+            let d_sanitized = docs::sanitize_description(d, &struct_name.to_string(), self);
             quote!(#[doc = #d_sanitized])
         } else {
-            quote!()
+            docs::fallback(self, || format!("Struct generated from schema `{}`.", name))
         };
 
         // If the object has no properties, but has additional_properties, just use that
@@ -647,7 +1091,8 @@ impl TypeSpace {
                     }
                     openapiv3::AdditionalProperties::Schema(schema) => match schema.item() {
                         Ok(item) => {
-                            return self.render_schema(name, item);
+                            return self
+                                .render_schema(&get_nested_type_name(name, "value", item), item);
                         }
                         Err(_) => {
                             // We have a reference, ignore this, since it is a reference we don't
@@ -661,14 +1106,19 @@ impl TypeSpace {
 
         let values = self.get_object_values(&struct_name, o, true, None)?;
 
+        let unique_items = self.render_unique_items_check(&struct_name, o)?;
+
+        let validate = self.render_validate(&struct_name, o)?;
+
         // Implement pagination for this type if we should.
         let mut pagination = quote!();
         let pagination_properties = PaginationProperties::from_object(o, &self.spec)?;
-        if pagination_properties.can_paginate() {
+        // A crate of only the types has nothing to page through.
+        if pagination_properties.can_paginate() && !self.opts.types_only {
             let page_item = pagination_properties.item_type(true)?;
             let item_ident = pagination_properties.item_ident()?;
             let next_page_str = pagination_properties.next_page_str()?;
-            let next_page_ident = format_ident!("{}", next_page_str);
+            let next_page_token = pagination_properties.next_page_token()?;
 
             pagination = quote!(
                 #[cfg(feature = "requests")]
@@ -676,23 +1126,28 @@ impl TypeSpace {
                     type Item = #page_item;
 
                     fn has_more_pages(&self) -> bool {
-                        self.#next_page_ident.is_some()
+                        // Some servers send an empty token with the last page.
+                        self.next_page_token().is_some()
                     }
 
                     fn next_page_token(&self) -> Option<String> {
-                        self.#next_page_ident.clone()
+                        #next_page_token.filter(|token| !token.is_empty())
                     }
 
-                    fn next_page(&self, req: reqwest::Request) -> anyhow::Result<reqwest::Request, crate::types::error::Error> {
+                    fn next_page_request(req: reqwest::Request, token: &str) -> anyhow::Result<reqwest::Request, crate::types::error::Error> {
                         let mut req = req.try_clone().ok_or_else(|| crate::types::error::Error::InvalidRequest(format!("failed to clone request: {:?}", req)))?;
                         req.url_mut().query_pairs_mut()
-                            .append_pair(#next_page_str, self.#next_page_ident.as_deref().unwrap_or(""));
+                            .append_pair(#next_page_str, token);
 
                         Ok(req)
                     }
 
-                    fn items(&self) -> Vec<Self::Item> {
-                        self.#item_ident.clone()
+                    fn items(&self) -> &[Self::Item] {
+                        &self.#item_ident
+                    }
+
+                    fn into_items(self) -> Vec<Self::Item> {
+                        self.#item_ident
                     }
                 }
             );
@@ -725,7 +1180,8 @@ impl TypeSpace {
             // Check if this type is required.
             let required = o.required.contains(k)
                 || is_default_property(&type_name, &inner_schema.schema_data)?;
-            if required && type_name.is_string()? {
+            // A secret shows as its `Debug`, `***`.
+            if required && type_name.is_string()? && !self.is_secret_property(&inner_schema) {
                 fields.push(quote!(
                     self.#prop_ident.clone().into()
                 ));
@@ -764,8 +1220,16 @@ impl TypeSpace {
             }
         };
 
+        // The secrets, of this type or of the ones in it, show as `***`.
+        let display = if self.opts.secret_fields {
+            quote!(secret::redacted(|| serde_json::to_string_pretty(self)))
+        } else {
+            quote!(serde_json::to_string_pretty(self))
+        };
+
         let rendered = quote! {
             #description
+            #doc_alias
             #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug, Clone, schemars::JsonSchema)]
             pub struct #struct_name {
                 #values
@@ -773,12 +1237,16 @@ impl TypeSpace {
 
             impl std::fmt::Display for #struct_name {
                 fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
-                    write!(f, "{}", serde_json::to_string_pretty(self).map_err(|_| std::fmt::Error)?)
+                    write!(f, "{}", #display.map_err(|_| std::fmt::Error)?)
                 }
             }
 
             #pagination
 
+            #unique_items
+
+            #validate
+
             #tabled
         };
 
@@ -797,6 +1265,17 @@ impl TypeSpace {
         Ok(())
     }
 
+    /// The object with its properties in the order of their names, with
+    /// `Opts::sort_fields`.
+    fn sort_properties(&self, o: &openapiv3::ObjectType) -> Option<openapiv3::ObjectType> {
+        if !self.opts.sort_fields {
+            return None;
+        }
+        let mut sorted = o.clone();
+        sorted.properties.sort_keys();
+        Some(sorted)
+    }
+
     fn get_object_values(
         &mut self,
         struct_name: &proc_macro2::Ident,
@@ -804,6 +1283,8 @@ impl TypeSpace {
         is_pub: bool,
         ignore_key: Option<&str>,
     ) -> Result<proc_macro2::TokenStream> {
+        let sorted = self.sort_properties(o);
+        let o = sorted.as_ref().unwrap_or(o);
         let mut values = quote!();
         for (k, v) in &o.properties {
             if let Some(ignore_key) = ignore_key {
@@ -822,17 +1303,21 @@ impl TypeSpace {
             };
 
             let prop_desc = if let Some(d) = &inner_schema.schema_data.description {
-                let d_sanitized = sanitize_indents(d, prop.to_string());
+                let d_sanitized = docs::sanitize_description(d, &prop, self);
                 quote!(#[doc = #d_sanitized])
             } else {
-                quote!()
+                docs::fallback(self, || format!("Field `{}` of `{}`.", k, struct_name))
             };
 
             // Get the type name for the schema.
             let mut type_name = if v.should_render()? {
                 // Check if the name for the property is already taken.
                 // Make sure there isn't an existing reference with this name.
-                let mut t = if let Some(components) = &self.spec.components {
+                // The union in the items of an array or the values of a map is named
+                // after the struct as well, e.g. `{Struct}{Prop}Item`.
+                let mut t = if has_inline_one_of_items(&inner_schema) {
+                    proper_name(&format!("{} {}", struct_name, prop))
+                } else if let Some(components) = &self.spec.components {
                     if components.schemas.contains_key(&proper_name(&prop)) {
                         proper_name(&format!("{} {}", struct_name, prop))
                     } else {
@@ -855,7 +1340,11 @@ impl TypeSpace {
                             compare_inner_schema = item_schema.clone();
                         }
                     }
-                    if *rendered != *compare_inner_schema {
+                    if !equality::same_shape(
+                        &compare_inner_schema,
+                        rendered,
+                        &self.opts.compared_schema_metadata,
+                    ) {
                         // The name is already taken, so we need to make a new name.
                         t = proper_name(&format!("{} {}", struct_name, prop));
                     } else {
@@ -877,6 +1366,32 @@ impl TypeSpace {
                 get_type_name_from_reference(&v.reference()?, &self.spec, true)?
             };
 
+            // An array of unique items that can be hashed becomes a set, it is still
+            // a json array on the wire.
+            if self.get_property_unique_items(&prop, v) == Some(UniqueItems::Set) {
+                let rendered = type_name.rendered()?;
+                let item: proc_macro2::TokenStream = rendered
+                    .trim_start_matches("Option<")
+                    .trim_start_matches("Vec<")
+                    .trim_end_matches('>')
+                    .parse()
+                    .map_err(|e| anyhow::anyhow!("{}", e))?;
+                type_name = if type_name.is_option()? {
+                    quote!(Option<indexmap::IndexSet<#item>>)
+                } else {
+                    quote!(indexmap::IndexSet<#item>)
+                };
+            }
+
+            // A password or a token is kept in a `Secret`.
+            if self.is_secret_property(&inner_schema) {
+                type_name = if type_name.is_option()? {
+                    quote!(Option<secret::Secret<String>>)
+                } else {
+                    quote!(secret::Secret<String>)
+                };
+            }
+
             if *struct_name == type_name.rendered()? && is_pub {
                 // We have a self reference.
                 // We need to box it.
@@ -956,6 +1471,18 @@ impl TypeSpace {
                 quote!(#[serde(#(#serde_props),*)])
             };
 
+            // Numbers and booleans can also be strings with the `lenient` feature.
+            let lenient = if type_name.strip_option()?.is_scalar()? {
+                let deserialize_with = if type_name.is_option()? {
+                    "crate::types::lenient::deserialize_option"
+                } else {
+                    "crate::types::lenient::deserialize"
+                };
+                quote!(#[cfg_attr(feature = "lenient", serde(deserialize_with = #deserialize_with))])
+            } else {
+                quote!()
+            };
+
             let deprecated = if inner_schema.schema_data.deprecated {
                 quote!(#[deprecated])
             } else {
@@ -967,6 +1494,7 @@ impl TypeSpace {
 
                 #prop_desc
                 #serde_full
+                #lenient
                 #deprecated
                 #prop_value
             );
@@ -975,98 +1503,321 @@ impl TypeSpace {
         Ok(values)
     }
 
-    /// Render a string type.
-    fn render_string_type(
-        &mut self,
-        name: &str,
-        s: &openapiv3::StringType,
-        data: &openapiv3::SchemaData,
-    ) -> Result<()> {
-        if !s.enumeration.is_empty() {
-            return self.render_enum(name, s, data, vec![]);
+    /// Returns `true` if the property is a secret we keep in a `Secret`, a string of
+    /// `format: password` or `writeOnly`, see `Opts::secret_fields`.
+    pub(crate) fn is_secret_property(&self, schema: &openapiv3::Schema) -> bool {
+        if !self.opts.secret_fields {
+            return false;
         }
-
-        if let Some(ref max_length) = s.max_length {
-            log::warn!(
-                "XXX max_length not supported here yet: {} => {:?}",
-                name,
-                max_length
-            );
+        let SchemaKind::Type(openapiv3::Type::String(s)) = &schema.schema_kind else {
+            return false;
+        };
+        if !s.enumeration.is_empty() {
+            return false;
         }
 
-        if let Some(ref min_length) = s.min_length {
-            log::warn!(
-                "XXX min_length not supported here yet: {} => {:?}",
-                name,
-                min_length
-            );
+        match &s.format {
+            openapiv3::VariantOrUnknownOrEmpty::Item(openapiv3::StringFormat::Password) => true,
+            openapiv3::VariantOrUnknownOrEmpty::Empty => schema.schema_data.write_only,
+            _ => false,
         }
-
-        // We don't render primitives yet.
-        Ok(())
     }
 
-    /// Render the full type for an enum.
-    fn render_enum(
-        &mut self,
-        name: &str,
-        s: &openapiv3::StringType,
-        data: &openapiv3::SchemaData,
-        // The additional doc strings for the enum if they exist.
-        additional_docs: Vec<String>,
-    ) -> Result<()> {
-        if s.enumeration.is_empty() {
-            anyhow::bail!("Cannot render empty string enumeration: {}", name);
+    /// Returns how a property keeps its items unique, if it is an inline array of
+    /// `uniqueItems` and we were asked to honor them.
+    pub(crate) fn get_property_unique_items(
+        &self,
+        prop: &str,
+        v: &openapiv3::ReferenceOr<Box<openapiv3::Schema>>,
+    ) -> Option<UniqueItems> {
+        if !self.opts.unique_items_as_sets {
+            return None;
         }
-
-        // Get the proper name version of the name of the enum.
-        let enum_name = get_type_name(name, data)?;
-
-        let description = if let Some(d) = &data.description {
-            let d_sanitized = sanitize_indents(d, enum_name.to_string());
-            quote!(#[doc = #d_sanitized])
-        } else {
-            quote!()
+        let openapiv3::ReferenceOr::Item(schema) = v else {
+            return None;
         };
 
-        let mut values = quote!();
-        for (index, e) in s.enumeration.iter().enumerate() {
-            if e.is_none() {
-                // GitHub will sometimes put in a null value.
-                // But it's fine because they also mark it as null.
-                // Just in case tho let's ensure it's marked as nullable.
-                if !data.nullable {
-                    anyhow::bail!("enum `{}` is not nullable, but it has a null value", name);
-                }
+        match get_unique_items(schema)? {
+            // The pagination needs the items in a `Vec`.
+            UniqueItems::Set if is_pagination_property_items_name(prop) => {
+                Some(UniqueItems::Validate)
+            }
+            unique_items => Some(unique_items),
+        }
+    }
 
-                // We can continue early.
+    /// Render the method that checks the arrays of unique items that stay a `Vec`
+    /// before the object is sent.
+    fn render_unique_items_check(
+        &self,
+        struct_name: &proc_macro2::Ident,
+        o: &openapiv3::ObjectType,
+    ) -> Result<proc_macro2::TokenStream> {
+        let mut checks = Vec::new();
+        for (k, v) in &o.properties {
+            let prop = clean_property_name(k);
+            if self.get_property_unique_items(&prop, v) != Some(UniqueItems::Validate) {
                 continue;
             }
 
-            let e = e.as_ref().unwrap().to_string();
-
-            let e_name = format_ident!("{}", proper_name(&e));
-            let mut e_value = quote!(
-                #e_name,
-            );
+            let schema = v.item()?;
+            let prop_ident = format_ident!("{}", prop);
+            // This matches how `get_object_values` makes the property optional.
+            if schema.schema_data.nullable
+                || (!o.required.contains(k) && schema.schema_data.default.is_none())
+            {
+                checks.push(quote! {
+                    if let Some(items) = &self.#prop_ident {
+                        crate::types::error::check_unique_items(#k, items)?;
+                    }
+                });
+            } else {
+                checks.push(quote! {
+                    crate::types::error::check_unique_items(#k, &self.#prop_ident)?;
+                });
+            }
+        }
+
+        // A crate of only the types sends nothing to check.
+        if checks.is_empty() || self.opts.types_only {
+            return Ok(quote!());
+        }
+
+        Ok(quote! {
+            #[cfg(feature = "requests")]
+            impl #struct_name {
+                /// Check the arrays that must not have duplicate items.
+                pub fn check_unique_items(&self) -> Result<(), crate::types::error::Error> {
+                    #(#checks)*
+                    Ok(())
+                }
+            }
+        })
+    }
+
+    /// Returns the checks of the constraints of the properties of an object we can
+    /// know before sending it: the lengths of the strings and of the arrays, and the
+    /// bounds of the numbers. The enums need none, one without a default in the spec
+    /// has no `Default` either, so it is always set. See `Opts::validate_requests`.
+    pub(crate) fn get_validation_checks(
+        &self,
+        o: &openapiv3::ObjectType,
+    ) -> Result<Vec<proc_macro2::TokenStream>> {
+        if !self.opts.validate_requests {
+            return Ok(Vec::new());
+        }
+
+        let mut checks = Vec::new();
+        for (k, v) in &o.properties {
+            let openapiv3::ReferenceOr::Item(schema) = v else {
+                continue;
+            };
+            let prop = clean_property_name(k);
+            let check = match &schema.schema_kind {
+                SchemaKind::Type(openapiv3::Type::String(s))
+                    if s.enumeration.is_empty()
+                        && s.format == openapiv3::VariantOrUnknownOrEmpty::Empty
+                        && !self.is_secret_property(schema)
+                        && (s.min_length.is_some() || s.max_length.is_some()) =>
+                {
+                    let min = option_tokens(s.min_length);
+                    let max = option_tokens(s.max_length);
+                    quote! {
+                        crate::types::validation::check_length(
+                            &mut errors, #k, "character", value.chars().count(), #min, #max,
+                        );
+                    }
+                }
+                SchemaKind::Type(openapiv3::Type::Array(a))
+                    if a.min_items.is_some() || a.max_items.is_some() =>
+                {
+                    let min = option_tokens(a.min_items);
+                    let max = option_tokens(a.max_items);
+                    quote! {
+                        crate::types::validation::check_length(
+                            &mut errors, #k, "item", value.len(), #min, #max,
+                        );
+                    }
+                }
+                SchemaKind::Type(openapiv3::Type::Integer(i))
+                    if i.minimum.is_some() || i.maximum.is_some() =>
+                {
+                    let min = option_tokens(i.minimum.map(|min| min as f64));
+                    let max = option_tokens(i.maximum.map(|max| max as f64));
+                    let (exclusive_min, exclusive_max) = (i.exclusive_minimum, i.exclusive_maximum);
+                    quote! {
+                        crate::types::validation::check_range(
+                            &mut errors, #k, *value as f64, (#min, #exclusive_min), (#max, #exclusive_max),
+                        );
+                    }
+                }
+                SchemaKind::Type(openapiv3::Type::Number(n))
+                    if n.minimum.is_some() || n.maximum.is_some() =>
+                {
+                    let min = option_tokens(n.minimum);
+                    let max = option_tokens(n.maximum);
+                    let (exclusive_min, exclusive_max) = (n.exclusive_minimum, n.exclusive_maximum);
+                    quote! {
+                        crate::types::validation::check_range(
+                            &mut errors, #k, *value as f64, (#min, #exclusive_min), (#max, #exclusive_max),
+                        );
+                    }
+                }
+                _ => continue,
+            };
+
+            // This matches how `get_object_values` makes the property optional.
+            let type_name = get_type_name_for_schema(&prop, schema, &self.spec, true)?;
+            let required =
+                o.required.contains(k) || is_default_property(&type_name, &schema.schema_data)?;
+            let prop_ident = format_ident!("{}", prop);
+            if type_name.is_option()? || !required {
+                checks.push(quote! {
+                    if let Some(value) = &self.#prop_ident {
+                        #check
+                    }
+                });
+            } else {
+                checks.push(quote! {
+                    let value = &self.#prop_ident;
+                    #check
+                });
+            }
+        }
+        Ok(checks)
+    }
+
+    /// Render the method that checks an object against the constraints of the spec,
+    /// see `get_validation_checks`.
+    fn render_validate(
+        &self,
+        struct_name: &proc_macro2::Ident,
+        o: &openapiv3::ObjectType,
+    ) -> Result<proc_macro2::TokenStream> {
+        let checks = self.get_validation_checks(o)?;
+        if checks.is_empty() {
+            return Ok(quote!());
+        }
+
+        Ok(quote! {
+            impl #struct_name {
+                /// Check the fields against the constraints of the spec, the lengths
+                /// and the bounds, and return every field that breaks one.
+                pub fn validate(
+                    &self,
+                ) -> Result<(), Vec<crate::types::validation::ValidationError>> {
+                    let mut errors = Vec::new();
+                    #({ #checks })*
+                    if errors.is_empty() {
+                        Ok(())
+                    } else {
+                        Err(errors)
+                    }
+                }
+            }
+        })
+    }
+
+    /// Render a string type.
+    fn render_string_type(
+        &mut self,
+        name: &str,
+        s: &openapiv3::StringType,
+        data: &openapiv3::SchemaData,
+    ) -> Result<()> {
+        if !s.enumeration.is_empty() {
+            return self.render_enum(name, s, data, vec![]);
+        }
+
+        if let Some(ref max_length) = s.max_length {
+            log::warn!(
+                "XXX max_length not supported here yet: {} => {:?}",
+                name,
+                max_length
+            );
+        }
+
+        if let Some(ref min_length) = s.min_length {
+            log::warn!(
+                "XXX min_length not supported here yet: {} => {:?}",
+                name,
+                min_length
+            );
+        }
+
+        // We don't render primitives yet.
+        Ok(())
+    }
+
+    /// Render the full type for an enum.
+    fn render_enum(
+        &mut self,
+        name: &str,
+        s: &openapiv3::StringType,
+        data: &openapiv3::SchemaData,
+        // The additional doc strings for the enum if they exist.
+        additional_docs: Vec<String>,
+    ) -> Result<()> {
+        if s.enumeration.is_empty() {
+            anyhow::bail!("Cannot render empty string enumeration: {}", name);
+        }
+
+        // Get the proper name version of the name of the enum.
+        let enum_name = get_type_name(name, data)?;
+        let doc_alias = self.get_type_doc_alias(name, &enum_name);
+
+        let description = if let Some(d) = &data.description {
+            let d_sanitized = docs::sanitize_description(d, &enum_name.to_string(), self);
+            quote!(#[doc = #d_sanitized])
+        } else {
+            docs::fallback(self, || format!("Enum generated from schema `{}`.", name))
+        };
+
+        let mut values = quote!();
+        let mut wire_values = Vec::new();
+        for (index, e) in s.enumeration.iter().enumerate() {
+            if e.is_none() {
+                // GitHub will sometimes put in a null value.
+                // But it's fine because they also mark it as null.
+                // Just in case tho let's ensure it's marked as nullable.
+                if !data.nullable {
+                    anyhow::bail!("enum `{}` is not nullable, but it has a null value", name);
+                }
+
+                // We can continue early.
+                continue;
+            }
+
+            let e = e.as_ref().unwrap().to_string();
+
+            let e_name = enum_variant_ident(&e);
+            wire_values.push((e.clone(), e_name.clone()));
+            // Always display the wire value, so `Display` and `FromStr` round-trip
+            // whatever serde sends.
+            let mut e_value = quote!(
+                #[display(#e)]
+                #e_name,
+            );
             if proper_name(&e) != e {
                 e_value = quote!(
                     #[serde(rename = #e)]
-                    #[display(#e)]
                     #e_value
                 );
             }
 
             // Check if we have a description for the enum.
-            if let Some(description) = additional_docs.get(index) {
-                if !description.is_empty() {
-                    let description_sanitized = sanitize_indents(description, proper_name(&e));
-                    e_value = quote!(
-                        #[doc = #description_sanitized]
-                        #e_value
-                    );
+            let description = match additional_docs.get(index) {
+                Some(description) if !description.is_empty() => {
+                    let description_sanitized =
+                        docs::sanitize_description(description, &proper_name(&e), self);
+                    quote!(#[doc = #description_sanitized])
                 }
-            }
+                _ => docs::fallback(self, || format!("Variant `{}` of `{}`.", e, enum_name)),
+            };
+            e_value = quote!(
+                #description
+                #e_value
+            );
             values = quote!(
                 #values
 
@@ -1075,19 +1826,8 @@ impl TypeSpace {
         }
 
         // If the data for the enum has a default value, implement default for the enum.
-        let default = if let Some(default) = &data.default {
-            let default = default.to_string();
-            let default = format_ident!("{}", proper_name(&default));
-            quote!(
-                impl std::default::Default for #enum_name {
-                    fn default() -> Self {
-                        #enum_name::#default
-                    }
-                }
-            )
-        } else if s.enumeration.len() == 1 {
-            let default = s.enumeration[0].as_ref().unwrap().to_string();
-            let default = format_ident!("{}", proper_name(&default));
+        let default = if let Some(default) = get_enum_default(name, s, data)? {
+            let default = enum_variant_ident(&default);
             quote!(
                 impl std::default::Default for #enum_name {
                     fn default() -> Self {
@@ -1099,16 +1839,89 @@ impl TypeSpace {
             quote!()
         };
 
-        let rendered = quote! {
-            #description
-            #[derive(serde::Serialize, serde::Deserialize, PartialEq, Hash, Debug, Clone, schemars::JsonSchema, parse_display::FromStr, parse_display::Display)]
-            #[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
-            #[cfg_attr(feature = "tabled", derive(tabled::Tabled))]
-            pub enum #enum_name {
-                #values
+        // A crate of only the types has no command line.
+        let clap = if self.opts.types_only {
+            quote!()
+        } else {
+            quote!(#[cfg_attr(feature = "clap", derive(clap::ValueEnum))])
+        };
+
+        // Parsing what people type, with the values close to it when it is none of
+        // them.
+        let enum_name_str = enum_name.to_string();
+        let wires = wire_values.iter().map(|(wire, _)| wire);
+        let parse_with_suggestions = quote!(
+            impl #enum_name {
+                /// Parse a value typed by a person, the error suggests the values close to it.
+                pub fn parse_with_suggestions(s: &str) -> Result<Self, crate::types::suggest::ParseEnumError> {
+                    s.parse().map_err(|_| {
+                        crate::types::suggest::ParseEnumError::new(#enum_name_str, s, &[#(#wires),*])
+                    })
+                }
+            }
+        );
+
+        let rendered = if crate::error_codes::is_catch_all(data) {
+            if wire_values.iter().any(|(_, variant)| variant == "Unknown") {
+                anyhow::bail!(
+                    "enum `{}` has a value named `Unknown`, the name of its catch-all variant",
+                    name
+                );
+            }
+            let catch_all =
+                render_catch_all_enum(&enum_name, &wire_values, self.opts.case_insensitive_enums);
+            quote! {
+                #description
+                #doc_alias
+                #[derive(serde::Serialize, PartialEq, Hash, Debug, Clone, schemars::JsonSchema, parse_display::Display)]
+                pub enum #enum_name {
+                    #values
+
+                    /// A value this version of the crate does not know, as it is.
+                    #[serde(untagged)]
+                    #[display("{0}")]
+                    Unknown(String),
+                }
+
+                #default
+
+                #parse_with_suggestions
+
+                #catch_all
+            }
+        } else if self.opts.case_insensitive_enums {
+            let case_insensitive = render_case_insensitive_enum(&enum_name, &wire_values);
+            quote! {
+                #description
+                #doc_alias
+                #[derive(serde::Serialize, PartialEq, Hash, Debug, Clone, schemars::JsonSchema, parse_display::Display)]
+                #clap
+                #[cfg_attr(feature = "tabled", derive(tabled::Tabled))]
+                pub enum #enum_name {
+                    #values
+                }
+
+                #default
+
+                #parse_with_suggestions
+
+                #case_insensitive
             }
+        } else {
+            quote! {
+                #description
+                #doc_alias
+                #[derive(serde::Serialize, serde::Deserialize, PartialEq, Hash, Debug, Clone, schemars::JsonSchema, parse_display::FromStr, parse_display::Display)]
+                #clap
+                #[cfg_attr(feature = "tabled", derive(tabled::Tabled))]
+                pub enum #enum_name {
+                    #values
+                }
 
-            #default
+                #default
+
+                #parse_with_suggestions
+            }
         };
 
         // Add the type to the list of types, if it doesn't already exist.
@@ -1181,11 +1994,11 @@ impl TypeSpace {
 
         // If we have a tag and/or content this is pretty simple.
         if let Some(tag) = &tag_result.tag {
-            for one_of in one_ofs {
+            for (i, one_of) in one_ofs.iter().enumerate() {
                 // Get the schema for this OneOf.
                 let schema = one_of.get_schema_from_reference(&self.spec, true)?;
                 let mut description = if let Some(d) = &schema.schema_data.description {
-                    let d_sanitized = sanitize_indents(d, name.to_string());
+                    let d_sanitized = docs::sanitize_description(d, &name, self);
                     quote!(#[doc = #d_sanitized])
                 } else {
                     quote!()
@@ -1196,9 +2009,28 @@ impl TypeSpace {
                     let tag_schema = match o.properties.get(tag) {
                         Some(v) => v,
                         None => {
+                            let variant = match one_of.reference() {
+                                Ok(reference) => format!("`{}`", reference),
+                                Err(_) => i.to_string(),
+                            };
+                            let properties = if o.properties.is_empty() {
+                                "it has none".to_string()
+                            } else {
+                                format!(
+                                    "its properties are {}",
+                                    o.properties
+                                        .keys()
+                                        .map(|p| format!("`{}`", p))
+                                        .collect::<Vec<_>>()
+                                        .join(", ")
+                                )
+                            };
                             anyhow::bail!(
-                                "no property `{}` in object, even through we thought we had a tag",
-                                tag
+                                "the variant {} of `{}` has no property `{}`, the tag of the other variants, {}",
+                                variant,
+                                original_name,
+                                tag,
+                                properties
                             );
                         }
                     };
@@ -1234,10 +2066,12 @@ impl TypeSpace {
 
                     if description.is_empty() {
                         description = if let Some(d) = &inner_schema.schema_data.description {
-                            let d_sanitized = sanitize_indents(d, p.to_string());
+                            let d_sanitized = docs::sanitize_description(d, &p, self);
                             quote!(#[doc = #d_sanitized])
                         } else {
-                            quote!()
+                            docs::fallback(self, || {
+                                format!("Variant `{}` of `{}`.", tag_name, original_name)
+                            })
                         };
                     }
 
@@ -1406,7 +2240,11 @@ impl TypeSpace {
                 let reference_name = format_ident!("{}", reference);
 
                 values.insert(reference.to_string(), one_of.clone());
+                let description = docs::fallback(self, || {
+                    format!("Variant `{}` of `{}`.", reference, original_name)
+                });
                 quote!(
+                    #description
                     #reference_name(#reference_name),
                 )
             } else {
@@ -1415,7 +2253,13 @@ impl TypeSpace {
                 let rendered_type =
                     get_type_name_for_schema(&name, &expanded_one_of, &self.spec, true)?;
 
-                let n = if let Some(title) = &expanded_one_of.schema_data.title {
+                // An opaque schema keeps its name, see `opaque`.
+                let title = expanded_one_of
+                    .schema_data
+                    .title
+                    .as_deref()
+                    .or_else(|| crate::opaque::get_name(&expanded_one_of.schema_data));
+                let n = if let Some(title) = title {
                     let p = proper_name(title);
                     p.parse().map_err(|e| anyhow::anyhow!("{}", e))?
                 } else {
@@ -1427,10 +2271,14 @@ impl TypeSpace {
                 };
 
                 let rendered = n.rendered()?;
+                let description = docs::fallback(self, || {
+                    format!("Variant `{}` of `{}`.", rendered, original_name)
+                });
 
                 values.insert(rendered, one_of.clone());
 
                 quote!(
+                    #description
                     #n(#rendered_type),
                 )
             };
@@ -1510,7 +2358,10 @@ pub fn get_type_name_for_schema(
             if let Some(s) = get_schema_from_any(&schema.schema_data, any) {
                 return get_type_name_for_schema(name, &s, spec, in_crate);
             }
-            log::warn!("got any schema kind `{}`: {:?}", name, any);
+            // The opaque schemas are values on purpose, see `opaque`.
+            if !crate::opaque::is_opaque(&schema.schema_data) {
+                log::warn!("got any schema kind `{}`: {:?}", name, any);
+            }
             quote!(serde_json::Value)
         }
     };
@@ -1542,13 +2393,22 @@ fn get_type_name_for_string(
         return Ok(t);
     }
 
+    // Dates, times and ids go through the aliases in their own modules, so the crate
+    // backing them can change without touching every type.
+    let datetime = |ident: &str| {
+        let ident = format_ident!("{}", ident);
+        if in_crate {
+            quote!(datetime::#ident)
+        } else {
+            quote!(crate::types::datetime::#ident)
+        }
+    };
+
     let t = match &s.format {
         openapiv3::VariantOrUnknownOrEmpty::Item(openapiv3::StringFormat::DateTime) => {
-            quote!(chrono::DateTime<chrono::Utc>)
-        }
-        openapiv3::VariantOrUnknownOrEmpty::Item(openapiv3::StringFormat::Date) => {
-            quote!(chrono::NaiveDate)
+            datetime("DateTime")
         }
+        openapiv3::VariantOrUnknownOrEmpty::Item(openapiv3::StringFormat::Date) => datetime("Date"),
         openapiv3::VariantOrUnknownOrEmpty::Item(openapiv3::StringFormat::Password) => {
             quote!(String)
         }
@@ -1582,12 +2442,18 @@ fn get_type_name_for_string(
                     quote!(crate::types::phone_number::PhoneNumber)
                 }
             }
-            "uuid" => quote!(uuid::Uuid),
+            "uuid" => {
+                if in_crate {
+                    quote!(ids::Uuid)
+                } else {
+                    quote!(crate::types::ids::Uuid)
+                }
+            }
             "hostname" => quote!(String),
-            "time" => quote!(chrono::NaiveTime),
-            "date" => quote!(chrono::NaiveDate),
-            "date-time" => quote!(chrono::DateTime<chrono::Utc>),
-            "partial-date-time" => quote!(chrono::NaiveDateTime),
+            "time" => datetime("Time"),
+            "date" => datetime("Date"),
+            "date-time" => datetime("DateTime"),
+            "partial-date-time" => datetime("PartialDateTime"),
             "money-usd" => quote!(bigdecimal::BigDecimal),
             "id" => quote!(String),
             f => {
@@ -1746,7 +2612,13 @@ fn get_type_name_for_object(
                             quote!(crate::types::#ident)
                         }
                     } else {
-                        get_type_name_for_schema(name, schema.item()?, spec, in_crate)?
+                        let item = schema.item()?;
+                        get_type_name_for_schema(
+                            &get_nested_type_name(name, "value", item),
+                            item,
+                            spec,
+                            in_crate,
+                        )?
                     };
 
                     // The additional properties is a HashMap of the key to the value.
@@ -1801,9 +2673,92 @@ fn get_type_name_for_all_of(
         quote!(crate::types::#ident)
     };
 
+    // A member can make the whole type nullable.
+    let (_, nullable) = get_all_of_overrides(all_ofs);
+    if nullable {
+        return Ok(quote!(Option<#t>));
+    }
+
     Ok(t)
 }
 
+/// Returns true if an all of member has no properties of its own, and only overrides
+/// the other members, e.g. `{"required": ["name"]}`.
+fn is_all_of_override(any: &AnySchema) -> bool {
+    any.typ.as_deref().unwrap_or("object") == "object"
+        && any.properties.is_empty()
+        && any.additional_properties.is_none()
+        && any.items.is_none()
+        && any.enumeration.is_empty()
+        && any.one_of.is_empty()
+        && any.all_of.is_empty()
+        && any.any_of.is_empty()
+        && any.not.is_none()
+}
+
+/// Get the description and nullable overrides declared by the inline members of an
+/// all of that have no properties of their own.
+fn get_all_of_overrides(
+    all_ofs: &[openapiv3::ReferenceOr<openapiv3::Schema>],
+) -> (Option<String>, bool) {
+    let mut description = None;
+    let mut nullable = false;
+    for all_of in all_ofs {
+        let openapiv3::ReferenceOr::Item(schema) = all_of else {
+            continue;
+        };
+        let is_override = match &schema.schema_kind {
+            SchemaKind::Any(any) => is_all_of_override(any),
+            SchemaKind::Type(openapiv3::Type::Object(o)) => {
+                o.properties.is_empty() && o.additional_properties.is_none()
+            }
+            _ => false,
+        };
+        if !is_override {
+            continue;
+        }
+
+        if schema.schema_data.description.is_some() {
+            description.clone_from(&schema.schema_data.description);
+        }
+        nullable |= schema.schema_data.nullable;
+    }
+
+    (description, nullable)
+}
+
+/// Returns the name for the type of the items of an array or the values of a map.
+/// An inline one of becomes its own enum, so it gets its own name with the suffix,
+/// anything else keeps the name of its parent.
+fn get_nested_type_name(name: &str, suffix: &str, schema: &openapiv3::Schema) -> String {
+    if is_inline_one_of(schema) {
+        proper_name(&format!("{} {}", name, suffix))
+    } else {
+        name.to_string()
+    }
+}
+
+/// Returns true if the schema is a one of that is rendered as its own enum.
+fn is_inline_one_of(schema: &openapiv3::Schema) -> bool {
+    matches!(&schema.schema_kind, SchemaKind::OneOf { one_of } if one_of.len() > 1)
+}
+
+/// Returns true if the items of the array, or the values of the map, are an inline
+/// one of.
+pub(crate) fn has_inline_one_of_items(schema: &openapiv3::Schema) -> bool {
+    match &schema.schema_kind {
+        SchemaKind::Type(openapiv3::Type::Array(a)) => {
+            matches!(&a.items, Some(openapiv3::ReferenceOr::Item(item)) if is_inline_one_of(item))
+        }
+        SchemaKind::Type(openapiv3::Type::Object(o)) if o.properties.is_empty() => matches!(
+            o.additional_properties.as_ref(),
+            Some(openapiv3::AdditionalProperties::Schema(values))
+                if matches!(&**values, openapiv3::ReferenceOr::Item(item) if is_inline_one_of(item))
+        ),
+        _ => false,
+    }
+}
+
 /// Get the type name for an array type.
 fn get_type_name_for_array(
     name: &str,
@@ -1818,7 +2773,12 @@ fn get_type_name_for_array(
         } else {
             // We have an item.
             let item = s.item()?;
-            get_type_name_for_schema(name, item, spec, in_crate)?
+            get_type_name_for_schema(
+                &get_nested_type_name(name, "item", item),
+                item,
+                spec,
+                in_crate,
+            )?
         }
     } else {
         anyhow::bail!(
@@ -1831,22 +2791,88 @@ fn get_type_name_for_array(
     Ok(quote!(Vec<#t>))
 }
 
+/// How an array of `uniqueItems` is kept unique, see `--unique-items-as-sets`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum UniqueItems {
+    /// The items can be hashed, so the array is an `indexmap::IndexSet`.
+    Set,
+    /// The array stays a `Vec`, and it is checked for duplicates before we send it.
+    Validate,
+}
+
+/// Returns how to keep the items of an array unique, if they must be.
+/// Only items we know the Rust type of, and that implement `Hash` and `Eq`, can go
+/// in a set.
+pub(crate) fn get_unique_items(schema: &openapiv3::Schema) -> Option<UniqueItems> {
+    let SchemaKind::Type(openapiv3::Type::Array(a)) = &schema.schema_kind else {
+        return None;
+    };
+    if !a.unique_items {
+        return None;
+    }
+
+    let Some(openapiv3::ReferenceOr::Item(item)) = &a.items else {
+        return Some(UniqueItems::Validate);
+    };
+    if item.schema_data.nullable {
+        return Some(UniqueItems::Validate);
+    }
+
+    let hashable = match &item.schema_kind {
+        SchemaKind::Type(openapiv3::Type::Boolean { .. })
+        | SchemaKind::Type(openapiv3::Type::Integer(_)) => true,
+        SchemaKind::Type(openapiv3::Type::String(s)) if s.enumeration.is_empty() => {
+            match &s.format {
+                openapiv3::VariantOrUnknownOrEmpty::Empty
+                | openapiv3::VariantOrUnknownOrEmpty::Item(openapiv3::StringFormat::Password) => {
+                    true
+                }
+                openapiv3::VariantOrUnknownOrEmpty::Unknown(f) => [
+                    "int64",
+                    "uint64",
+                    "ipv4",
+                    "ipv6",
+                    "ip",
+                    "uri",
+                    "uri-template",
+                    "url",
+                    "email",
+                    "hostname",
+                    "id",
+                    "uuid",
+                ]
+                .contains(&f.as_str()),
+                _ => false,
+            }
+        }
+        _ => false,
+    };
+
+    if hashable {
+        Some(UniqueItems::Set)
+    } else {
+        Some(UniqueItems::Validate)
+    }
+}
+
 fn is_default_property(
     type_name: &proc_macro2::TokenStream,
     data: &openapiv3::SchemaData,
 ) -> Result<bool> {
     Ok(data.default.is_some()
-        && (type_name.rendered()? == "bool" || type_name.rendered()?.starts_with("Vec<")))
+        && (type_name.rendered()? == "bool"
+            || type_name.rendered()?.starts_with("Vec<")
+            || type_name.rendered()?.starts_with("indexmap::IndexSet<")))
 }
 
 /// A holder for our tag and content for enums.
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
 pub struct TagContent {
-    tag: Option<String>,
+    pub(crate) tag: Option<String>,
     content: Option<String>,
 }
 
-fn get_one_of_tag(
+pub(crate) fn get_one_of_tag(
     one_ofs: &Vec<openapiv3::ReferenceOr<openapiv3::Schema>>,
     spec: &openapiv3::OpenAPI,
 ) -> Result<TagContent> {
@@ -1932,6 +2958,41 @@ fn get_one_of_tag(
     Ok(result)
 }
 
+/// Whether every subschema of an any of is an object with the same tag, a property
+/// that is a string enum of a single value, and no two of them have the same
+/// value. Such an any of can only ever match one of the subschemas, so it is
+/// rendered like a one of, rather than merged into an object.
+pub(crate) fn is_tagged_any_of(
+    any_ofs: &Vec<openapiv3::ReferenceOr<openapiv3::Schema>>,
+    spec: &openapiv3::OpenAPI,
+) -> Result<bool> {
+    let Some(tag) = get_one_of_tag(any_ofs, spec)?.tag else {
+        return Ok(false);
+    };
+
+    let mut values = std::collections::BTreeSet::new();
+    for any_of in any_ofs {
+        let schema = any_of.get_schema_from_reference(spec, true)?;
+        let SchemaKind::Type(openapiv3::Type::Object(o)) = &schema.schema_kind else {
+            return Ok(false);
+        };
+        let Some(property) = o.properties.get(&tag) else {
+            return Ok(false);
+        };
+        let SchemaKind::Type(openapiv3::Type::String(s)) =
+            property.get_schema_from_reference(spec, true)?.schema_kind
+        else {
+            return Ok(false);
+        };
+        match s.enumeration.as_slice() {
+            [Some(value)] if values.insert(value.to_string()) => {}
+            _ => return Ok(false),
+        }
+    }
+
+    Ok(true)
+}
+
 /// Clean a property name for an object so we can use it in rust.
 pub fn clean_property_name(s: &str) -> String {
     let mut prop = s.trim().to_string();
@@ -1949,7 +3010,7 @@ pub fn clean_property_name(s: &str) -> String {
         prop = "underscore_links".to_string()
     }
 
-    prop = inflector::cases::snakecase::to_snake_case(&prop);
+    prop = casing::to_snake_case(&prop);
 
     // Account for reserved keywords in rust.
     if prop == "ref"
@@ -1979,65 +3040,182 @@ pub fn clean_property_name(s: &str) -> String {
     prop
 }
 
-/// Return a proper rust name for a string.
-/// For example, this gets used as the enum and struct name.
-pub fn proper_name(s: &str) -> String {
-    if s.is_empty() {
-        return "Empty".to_string();
+/// Return the variant of a string enum for one of its values.
+fn enum_variant_ident(value: &str) -> proc_macro2::Ident {
+    format_ident!("{}", proper_name(value))
+}
+
+/// Render the `FromStr` and `Deserialize` of a string enum that match the wire
+/// values whatever their case, see `Opts::case_insensitive_enums`. A value as it is
+/// on the wire comes first, for enums with values that only differ by case.
+fn render_case_insensitive_enum(
+    enum_name: &proc_macro2::Ident,
+    wire_values: &[(String, proc_macro2::Ident)],
+) -> proc_macro2::TokenStream {
+    let wires = wire_values.iter().map(|(wire, _)| wire).collect::<Vec<_>>();
+    let variants = wire_values
+        .iter()
+        .map(|(_, variant)| variant)
+        .collect::<Vec<_>>();
+
+    quote! {
+        impl std::str::FromStr for #enum_name {
+            type Err = parse_display::ParseError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                match s {
+                    #(#wires => Ok(#enum_name::#variants),)*
+                    #(_ if s.eq_ignore_ascii_case(#wires) => Ok(#enum_name::#variants),)*
+                    _ => Err(parse_display::ParseError::new()),
+                }
+            }
+        }
+
+        impl<'de> serde::Deserialize<'de> for #enum_name {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: serde::Deserializer<'de>,
+            {
+                let value = String::deserialize(deserializer)?;
+                value
+                    .parse()
+                    .map_err(|_| serde::de::Error::unknown_variant(&value, &[#(#wires),*]))
+            }
+        }
     }
+}
 
-    // Check if s is a number like 1 or 2, etc.
-    // If it is a number we want to convert it to a string as follows:
-    // 1 => One
+/// The parsing of a string enum with a catch-all, see `error_codes`: a value it
+/// does not know is its `Unknown` variant rather than an error.
+fn render_catch_all_enum(
+    enum_name: &proc_macro2::Ident,
+    wire_values: &[(String, proc_macro2::Ident)],
+    case_insensitive: bool,
+) -> proc_macro2::TokenStream {
+    let wires = wire_values.iter().map(|(wire, _)| wire).collect::<Vec<_>>();
+    let variants = wire_values
+        .iter()
+        .map(|(_, variant)| variant)
+        .collect::<Vec<_>>();
+    let case_insensitive = case_insensitive
+        .then(|| quote!(#(_ if s.eq_ignore_ascii_case(#wires) => #enum_name::#variants,)*));
+
+    quote! {
+        impl std::str::FromStr for #enum_name {
+            type Err = std::convert::Infallible;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                Ok(match s {
+                    #(#wires => #enum_name::#variants,)*
+                    #case_insensitive
+                    _ => #enum_name::Unknown(s.to_string()),
+                })
+            }
+        }
+
+        impl<'de> serde::Deserialize<'de> for #enum_name {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: serde::Deserializer<'de>,
+            {
+                let value = String::deserialize(deserializer)?;
+                value.parse().map_err(serde::de::Error::custom)
+            }
+        }
+    }
+}
+
+/// Return the value of a string enum its `Default` is, if it has one. That is the
+/// default in the spec, or the only value.
+fn get_enum_default(
+    name: &str,
+    s: &openapiv3::StringType,
+    data: &openapiv3::SchemaData,
+) -> Result<Option<String>> {
+    let default = match &data.default {
+        // A null default means there is no value, so no variant either.
+        None | Some(serde_json::Value::Null) => {
+            if s.enumeration.len() == 1 {
+                s.enumeration[0].clone()
+            } else {
+                None
+            }
+        }
+        // Use the string itself, not its JSON with the quotes.
+        Some(serde_json::Value::String(default)) => Some(default.to_string()),
+        // Some specs leave the quotes off the default of numeric values.
+        Some(default @ serde_json::Value::Number(_))
+        | Some(default @ serde_json::Value::Bool(_)) => Some(default.to_string()),
+        Some(default) => anyhow::bail!(
+            "the default of enum `{}` is not a string: {}",
+            name,
+            default
+        ),
+    };
+
+    if let Some(default) = &default {
+        if !s.enumeration.iter().flatten().any(|e| e == default) {
+            anyhow::bail!(
+                "the default of enum `{}` is `{}`, which is not one of its values: {}",
+                name,
+                default,
+                s.enumeration
+                    .iter()
+                    .flatten()
+                    .map(|e| format!("`{}`", e))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+    }
+
+    Ok(default)
+}
+
+/// Return a proper rust name for a string.
+/// For example, this gets used as the enum and struct name.
+pub fn proper_name(s: &str) -> String {
+    if s.is_empty() {
+        return "Empty".to_string();
+    }
+
+    // Check if s is a number like 1 or 2, etc.
+    // If it is a number we want to convert it to a string as follows:
+    // 1 => One
     // 2 => Two
     // 100 => OneHundred
     // 2FaDisabled => TwoFaDisabled
     // etc.
-    let s = if let Ok(num) = s.parse::<i32>() {
+    let unsigned = s.strip_prefix('-').unwrap_or(s);
+    let s = if !unsigned.is_empty() && unsigned.chars().all(|c| c.is_ascii_digit()) {
+        if unsigned.len() < s.len() {
+            format!("minus {}", spell_number(unsigned))
+        } else {
+            spell_number(s)
+        }
+    } else if let Ok(num) = s.parse::<i32>() {
         num.cardinal()
     } else {
         s.to_string()
     };
 
-    // Fixes for MailChimp, probably a better way to do this.
-    // They have enums like:
-    // 18-24
-    // 55+
-    let s = if s == "18-24" {
-        "EighteenToTwentyFour".to_string()
-    } else if s == "25-34" {
-        "TwentyFiveToThirtyFour".to_string()
-    } else if s == "35-44" {
-        "ThirtyFiveToFourtyFour".to_string()
-    } else if s == "45-54" {
-        "FourtyFiveToFiftyFour".to_string()
-    } else if s == "35-54" {
-        "ThirtyFiveToFiftyFour".to_string()
-    } else if s == "55-64" {
-        "FiftyFiveToSixtyFour".to_string()
-    } else if s == "55+" {
-        "FiftyFivePlus".to_string()
-    } else if s == "65+" {
-        "SixtyFivePlus".to_string()
-    } else if s == "-" {
+    // Spell out numeric ranges, like the age ranges of MailChimp:
+    // 18-24 => EighteenToTwentyFour
+    // 55+ => FiftyFivePlus
+    let s = if s == "-" {
         "Dash".to_string()
+    } else if let Some(range) = range_name(&s) {
+        range
     } else {
         s
     };
 
-    // Check if just the first character is a number.
-    // Get the first character of the string.
-    let first_char = s.chars().next().unwrap();
-    let s = if let Ok(num) = first_char.to_string().parse::<i32>() {
-        if s.len() == 1 {
-            num.cardinal()
-        } else if !s.chars().nth(1).unwrap().is_numeric() {
-            // Make sure the second character is not a number.
-            // If it is, we want to add an underscore to the front of the string.
-            s.replace(first_char, &num.cardinal())
-        } else {
-            s
-        }
+    // Spell out the number at the start, an identifier can't start with it:
+    // 2FaDisabled => TwoFaDisabled
+    // 10Gb => TenGb
+    let digits = s.chars().take_while(|c| c.is_ascii_digit()).count();
+    let s = if digits > 0 {
+        format!("{}{}", spell_number(&s[..digits]), &s[digits..])
     } else {
         s
     };
@@ -2049,6 +3227,68 @@ pub fn proper_name(s: &str) -> String {
         .replace("V1", "")
 }
 
+/// Spell out a number of ASCII digits, keeping its leading zeros so `007` and `7`
+/// get other names. A number too big for a `u64` is spelled digit by digit.
+fn spell_number(digits: &str) -> String {
+    let rest = digits.trim_start_matches('0');
+    let mut words = vec!["zero".to_string(); digits.len() - rest.len()];
+    match rest.parse::<u64>() {
+        Ok(num) => words.push(num.cardinal()),
+        Err(_) => words.extend(
+            rest.chars()
+                .filter_map(|c| c.to_digit(10))
+                .map(|digit| u64::from(digit).cardinal()),
+        ),
+    }
+    words.join(" ")
+}
+
+/// The ranges we named by hand before we had a rule for them, they keep their
+/// spelling so the generated names don't change.
+const LEGACY_RANGE_NAMES: &[(&str, &str)] = &[
+    ("35-44", "ThirtyFiveToFourtyFour"),
+    ("45-54", "FourtyFiveToFiftyFour"),
+];
+
+/// Spell out a numeric range like `18-24` or `55+`, `None` if `s` is not one.
+fn range_name(s: &str) -> Option<String> {
+    if let Some((_, name)) = LEGACY_RANGE_NAMES.iter().find(|(range, _)| *range == s) {
+        return Some(name.to_string());
+    }
+
+    let cardinal = |n: &str| -> Option<String> {
+        if n.is_empty() || !n.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        Some(inflector::cases::pascalcase::to_pascal_case(&spell_number(
+            n,
+        )))
+    };
+
+    if let Some(from) = s.strip_suffix('+') {
+        return Some(format!("{}Plus", cardinal(from)?));
+    }
+    let (from, to) = s.split_once('-')?;
+    Some(format!("{}To{}", cardinal(from)?, cardinal(to)?))
+}
+
+/// Return the `#[doc(alias = ...)]` attribute that lets the docs search find an
+/// item by `alias`, a name of the spec. There is none if it only differs from the
+/// name of the item by its case, the search ignores it, or if rustdoc refuses it.
+pub fn get_doc_alias(alias: &str, item_name: &str) -> proc_macro2::TokenStream {
+    if alias.is_empty()
+        || alias.eq_ignore_ascii_case(item_name)
+        || alias.trim() != alias
+        || alias
+            .chars()
+            .any(|c| c == '"' || c == '\'' || (c.is_whitespace() && c != ' '))
+    {
+        return quote!();
+    }
+
+    quote!(#[doc(alias = #alias)])
+}
+
 /// Return the name for a type based on a name if passed or the title of the schema data.
 fn get_type_name(name: &str, data: &openapiv3::SchemaData) -> Result<proc_macro2::Ident> {
     let t = if !name.is_empty() {
@@ -2080,10 +3320,39 @@ pub fn get_text(output: &proc_macro2::TokenStream) -> Result<String> {
     Ok(clean_text(&content).replace(' ', ""))
 }
 
+/// Code we generated that does not parse as a Rust file.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("{line}:{column}: {message}")]
+pub struct ParseError {
+    /// The error from the parser.
+    pub message: String,
+    /// The line of the error in the unformatted code, starting at 1.
+    pub line: usize,
+    /// The column of the error in the unformatted code, starting at 0.
+    pub column: usize,
+}
+
+/// Check the generated code parses as a Rust file, so we fail with a useful
+/// error instead of handing rustfmt something it cannot make sense of.
+pub fn validate_code(output: &proc_macro2::TokenStream) -> std::result::Result<(), ParseError> {
+    syn::parse_file(&output.to_string())
+        .map(|_| ())
+        .map_err(|err| {
+            let start = err.span().start();
+            ParseError {
+                message: err.to_string(),
+                line: start.line,
+                column: start.column,
+            }
+        })
+}
+
 /// Format a TokenStream as a string and run `rustfmt` on the result.
 pub fn get_text_fmt(output: &proc_macro2::TokenStream) -> Result<String> {
+    validate_code(output)?;
+
     // Format the file with rustfmt.
-    let content = rustfmt_wrapper::rustfmt(output).unwrap();
+    let content = rustfmt_wrapper::rustfmt(output)?;
 
     Ok(clean_text(&content))
 }
@@ -2098,6 +3367,49 @@ fn get_base64_mod() -> Result<proc_macro2::TokenStream> {
     ))
 }
 
+fn get_datetime_mod(opts: &crate::Opts) -> Result<proc_macro2::TokenStream> {
+    let file = match opts.datetime_crate {
+        crate::DateTimeCrate::Chrono => include_str!("datetime.rs"),
+        crate::DateTimeCrate::Time => include_str!("datetime_time.rs"),
+    };
+    let stream = proc_macro2::TokenStream::from_str(file).map_err(|e| anyhow::anyhow!("{}", e))?;
+    Ok(quote!(
+        pub mod datetime {
+            #stream
+        }
+    ))
+}
+
+fn get_ids_mod() -> Result<proc_macro2::TokenStream> {
+    let file = include_str!("ids.rs");
+    let stream = proc_macro2::TokenStream::from_str(file).map_err(|e| anyhow::anyhow!("{}", e))?;
+    Ok(quote!(
+        pub mod ids {
+            #stream
+        }
+    ))
+}
+
+fn get_suggest_mod() -> Result<proc_macro2::TokenStream> {
+    let file = include_str!("suggest.rs");
+    let stream = proc_macro2::TokenStream::from_str(file).map_err(|e| anyhow::anyhow!("{}", e))?;
+    Ok(quote!(
+        pub mod suggest {
+            #stream
+        }
+    ))
+}
+
+fn get_lenient_mod() -> Result<proc_macro2::TokenStream> {
+    let file = include_str!("lenient.rs");
+    let stream = proc_macro2::TokenStream::from_str(file).map_err(|e| anyhow::anyhow!("{}", e))?;
+    Ok(quote!(
+        pub mod lenient {
+            #stream
+        }
+    ))
+}
+
 fn get_multipart_mod() -> Result<proc_macro2::TokenStream> {
     let file = include_str!("multipart.rs");
     let stream = proc_macro2::TokenStream::from_str(file).map_err(|e| anyhow::anyhow!("{}", e))?;
@@ -2118,6 +3430,165 @@ fn get_paginate_mod() -> Result<proc_macro2::TokenStream> {
     ))
 }
 
+/// The operations of the spec, by method and path, with the name of the constant
+/// of their path in the `paths` module. It is the operation id in screaming snake
+/// case, with a number when two operations have the same one.
+pub fn get_path_consts(spec: &openapiv3::OpenAPI) -> Result<BTreeMap<(String, String), String>> {
+    let mut taken = std::collections::HashSet::new();
+    let mut consts = BTreeMap::new();
+    for (path, item) in spec.paths.iter() {
+        let item = item.item()?;
+        for (method, op) in [
+            (http::Method::GET, &item.get),
+            (http::Method::PUT, &item.put),
+            (http::Method::POST, &item.post),
+            (http::Method::DELETE, &item.delete),
+            (http::Method::HEAD, &item.head),
+            (http::Method::PATCH, &item.patch),
+            (http::Method::OPTIONS, &item.options),
+            (http::Method::TRACE, &item.trace),
+        ] {
+            let Some(operation_id) = op.as_ref().and_then(|op| op.operation_id.as_ref()) else {
+                continue;
+            };
+            let mut name =
+                inflector::cases::screamingsnakecase::to_screaming_snake_case(operation_id);
+            if syn::parse_str::<syn::Ident>(&name).is_err() {
+                name = format!("PATH_{}", name);
+            }
+            let mut constant = name.clone();
+            let mut n = 2;
+            while !taken.insert(constant.clone()) {
+                constant = format!("{}_{}", name, n);
+                n += 1;
+            }
+            consts.insert((method.to_string(), path.to_string()), constant);
+        }
+    }
+
+    Ok(consts)
+}
+
+/// The segments of a path template for the route table of the `paths` module,
+/// `None` if a segment has more than one parameter.
+fn get_path_segments(path: &str) -> Option<Vec<proc_macro2::TokenStream>> {
+    let path = path.strip_prefix('/').unwrap_or(path);
+    let mut segments = Vec::new();
+    for segment in path.split('/') {
+        let Some((prefix, rest)) = segment.split_once('{') else {
+            segments.push(quote!(Segment::Literal(#segment)));
+            continue;
+        };
+        let (name, suffix) = rest.split_once('}')?;
+        if suffix.contains('{') {
+            return None;
+        }
+        segments.push(quote!(Segment::Param {
+            name: #name,
+            prefix: #prefix,
+            suffix: #suffix,
+        }));
+    }
+    Some(segments)
+}
+
+/// Render the `paths` module, with a constant for the path of every operation, the
+/// functions build their urls from them, and `match_path` matching a path against
+/// them.
+fn get_paths_mod(spec: &openapiv3::OpenAPI) -> Result<proc_macro2::TokenStream> {
+    let file = include_str!("paths.rs");
+    let stream = proc_macro2::TokenStream::from_str(file).map_err(|e| anyhow::anyhow!("{}", e))?;
+
+    let mut consts = Vec::new();
+    let mut routes = Vec::new();
+    for ((method, path), constant) in get_path_consts(spec)? {
+        let item = spec
+            .paths
+            .paths
+            .get(&path)
+            .ok_or_else(|| anyhow::anyhow!("path `{}` not found", path))?
+            .item()?;
+        let op = match method.as_str() {
+            "GET" => &item.get,
+            "PUT" => &item.put,
+            "POST" => &item.post,
+            "DELETE" => &item.delete,
+            "HEAD" => &item.head,
+            "PATCH" => &item.patch,
+            "OPTIONS" => &item.options,
+            _ => &item.trace,
+        };
+        let operation_id = op
+            .as_ref()
+            .and_then(|op| op.operation_id.clone())
+            .unwrap_or_default();
+        let constant = format_ident!("{}", constant);
+        let docs = format!("The path of `{}`, `{} {}`.", operation_id, method, path);
+        consts.push(quote! {
+            #[doc = #docs]
+            pub const #constant: &str = #path;
+        });
+
+        // A segment with more than one parameter can't be matched on its own.
+        if let Some(segments) = get_path_segments(&path) {
+            routes.push(quote! {
+                Route {
+                    method: #method,
+                    operation_id: #operation_id,
+                    template: #constant,
+                    segments: &[#(#segments),*],
+                }
+            });
+        }
+    }
+
+    Ok(quote!(
+        pub mod paths {
+            #stream
+
+            #(#consts)*
+
+            /// The route table of the operations, for `match_path`.
+            pub const ROUTES: &[Route] = &[#(#routes),*];
+
+            /// Match the method and the path of a request, the part of the url after
+            /// the base url, against the operations of the API. It agrees with the
+            /// urls the functions build: the path parameters are percent-decoded.
+            pub fn match_path(method: &http::Method, path: &str) -> Option<MatchedOperation> {
+                match_routes(ROUTES, method, path)
+            }
+        }
+    ))
+}
+
+fn get_secret_mod() -> Result<proc_macro2::TokenStream> {
+    let file = include_str!("secret.rs");
+    let stream = proc_macro2::TokenStream::from_str(file).map_err(|e| anyhow::anyhow!("{}", e))?;
+    Ok(quote!(
+        pub mod secret {
+            #stream
+        }
+    ))
+}
+
+/// The tokens of an optional constant, `Some(1usize)` or `None`.
+fn option_tokens<T: quote::ToTokens>(value: Option<T>) -> proc_macro2::TokenStream {
+    match value {
+        Some(value) => quote!(Some(#value)),
+        None => quote!(None),
+    }
+}
+
+fn get_validation_mod() -> Result<proc_macro2::TokenStream> {
+    let file = include_str!("validation.rs");
+    let stream = proc_macro2::TokenStream::from_str(file).map_err(|e| anyhow::anyhow!("{}", e))?;
+    Ok(quote!(
+        pub mod validation {
+            #stream
+        }
+    ))
+}
+
 fn get_phone_number_mod() -> Result<proc_macro2::TokenStream> {
     let file = include_str!("phone_number.rs");
     let stream = proc_macro2::TokenStream::from_str(file).map_err(|e| anyhow::anyhow!("{}", e))?;
@@ -2128,11 +3599,335 @@ fn get_phone_number_mod() -> Result<proc_macro2::TokenStream> {
     ))
 }
 
-fn get_error_mod() -> Result<proc_macro2::TokenStream> {
-    let file = include_str!("error.rs");
-    let stream = proc_macro2::TokenStream::from_str(file).map_err(|e| anyhow::anyhow!("{}", e))?;
+fn get_error_mod(
+    spec: &openapiv3::OpenAPI,
+    opts: &crate::Opts,
+) -> Result<proc_macro2::TokenStream> {
+    let mut file = syn::parse_file(include_str!("error.rs"))?;
+
+    // The body of the errors depends on the spec, it replaces the empty one.
+    let error_body: syn::Item = syn::parse2(render_error_body(spec, opts)?)?;
+    let request_id_header = &opts.request_id_header;
+    // The codes of the errors are their enum, if the spec lists them.
+    let error_code = match crate::error_codes::find(spec)? {
+        Some(error_codes) => get_type_name_from_reference(&error_codes.codes, spec, false)?,
+        None => quote!(String),
+    };
+    for item in &mut file.items {
+        match item {
+            syn::Item::Enum(e) if e.ident == "ErrorBody" => *item = error_body.clone(),
+            syn::Item::Const(c) if c.ident == "REQUEST_ID_HEADER" => {
+                *c.expr = syn::parse2(quote!(#request_id_header))?;
+            }
+            syn::Item::Type(t) if t.ident == "ErrorCode" => {
+                *t.ty = syn::parse2(error_code.clone())?;
+            }
+            syn::Item::Impl(i)
+                if matches!(&*i.self_ty, syn::Type::Path(p) if p.path.is_ident("ErrorClass")) =>
+            {
+                for item in &mut i.items {
+                    match item {
+                        syn::ImplItem::Fn(f) if f.sig.ident == "from_error_code" => {
+                            if let Some(from_error_code) = render_from_error_code(spec, &f.attrs)? {
+                                *f = syn::parse2(from_error_code)?;
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    // The functions that decode the bodies go with them, before the tests.
+    let error_responses = render_error_responses(spec)?;
+    if !error_responses.is_empty() {
+        let position = file
+            .items
+            .iter()
+            .position(|item| matches!(item, syn::Item::Enum(e) if e.ident == "ErrorBody"))
+            .map_or(file.items.len(), |position| position + 1);
+        file.items.insert(position, syn::parse2(error_responses)?);
+    }
+
     Ok(quote!(
         pub mod error {
+            #file
+        }
+    ))
+}
+
+/// The classes of the known codes the spec declares, see
+/// `crate::error_codes::CLASSES`, `None` if it declares none of them.
+fn render_from_error_code(
+    spec: &openapiv3::OpenAPI,
+    attrs: &[syn::Attribute],
+) -> Result<Option<proc_macro2::TokenStream>> {
+    let declared = crate::error_codes::declared(spec)?;
+    let mut arms = Vec::new();
+    for (class, codes) in crate::error_codes::CLASSES {
+        let codes = codes
+            .iter()
+            .filter(|code| declared.iter().any(|declared| declared == *code))
+            .collect::<Vec<_>>();
+        if codes.is_empty() {
+            continue;
+        }
+        let class = format_ident!("{}", class);
+        arms.push(quote!(#(#codes)|* => Some(ErrorClass::#class),));
+    }
+    if arms.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(quote!(
+        #(#attrs)*
+        fn from_error_code(code: &str) -> Option<Self> {
+            match code {
+                #(#arms)*
+                _ => None,
+            }
+        }
+    )))
+}
+
+/// The types of the `default` responses of the operations, by their variant of
+/// `ErrorBody`.
+fn get_error_body_types(
+    spec: &openapiv3::OpenAPI,
+) -> Result<IndexMap<String, (proc_macro2::Ident, proc_macro2::TokenStream)>> {
+    let mut bodies = IndexMap::new();
+    for (_name, path) in spec.paths.iter() {
+        for (_method, op) in path.item()?.iter() {
+            if let Some((variant, type_name)) = crate::functions::get_default_error_type(spec, op)?
+            {
+                bodies.insert(variant.to_string(), (variant, type_name));
+            }
+        }
+    }
+    Ok(bodies)
+}
+
+/// Render the body of the errors, one variant for every type of the `default`
+/// responses of the operations.
+fn render_error_body(
+    spec: &openapiv3::OpenAPI,
+    opts: &crate::Opts,
+) -> Result<proc_macro2::TokenStream> {
+    let bodies = get_error_body_types(spec)?;
+    let bodies = bodies.values().map(|(variant, type_name)| {
+        let docs = opts.strict_lints.then(|| {
+            let docs = format!("An error response with a `{}`.", variant);
+            quote!(#[doc = #docs])
+        });
+        quote!(#docs #variant(#type_name))
+    });
+
+    Ok(quote!(
+        /// The body of an error response, one variant for every type of the
+        /// `default` responses of the operations.
+        #[derive(Debug, Clone, serde::Serialize)]
+        #[serde(untagged)]
+        pub enum ErrorBody {
+            #(#bodies),*
+        }
+    ))
+}
+
+/// The name of the function that makes the error of a response, for the
+/// operations whose `default` response is the `variant` of `ErrorBody`.
+pub fn get_error_response_fn_name(variant: &proc_macro2::Ident) -> proc_macro2::Ident {
+    format_ident!(
+        "from_{}_response",
+        crate::types::casing::to_snake_case(&variant.to_string())
+    )
+}
+
+/// Render the functions that make the error of a response, one for every type of
+/// the `default` responses of the operations, so the operations share them
+/// rather than each having its own copy.
+fn render_error_responses(spec: &openapiv3::OpenAPI) -> Result<proc_macro2::TokenStream> {
+    let functions = get_error_body_types(spec)?
+        .into_values()
+        .map(|(variant, type_name)| {
+            let fn_name = get_error_response_fn_name(&variant);
+            let docs = format!(
+                "Creates the error of a response with an error status, for the operations \
+                 whose `default` response is a [`{}`]({}). Its body is the `{}` unless the \
+                 status is one of `listed`, the ones the operation has responses of its own \
+                 for, or it does not parse.",
+                variant,
+                get_text(&type_name)?,
+                variant
+            );
+            Ok(quote! {
+                #[doc = #docs]
+                #[doc(hidden)]
+                pub async fn #fn_name(
+                    resp: reqwest::Response,
+                    request_id: Option<String>,
+                    listed: &[std::ops::RangeInclusive<u16>],
+                ) -> Self {
+                    let status = resp.status();
+                    let text = resp.text().await.unwrap_or_default();
+                    let request_id = request_id_of(request_id, &text);
+                    let error_code = error_code_of(&text);
+                    match serde_json::from_str::<#type_name>(&text) {
+                        Ok(body) if !listed.iter().any(|codes| codes.contains(&status.as_u16())) => {
+                            Error::Api {
+                                body: ErrorBody::#variant(body),
+                                status,
+                                request_id,
+                                error_code,
+                            }
+                        }
+                        _ => Error::Server {
+                            body: text,
+                            status,
+                            request_id,
+                            error_code,
+                        },
+                    }
+                }
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    if functions.is_empty() {
+        return Ok(quote!());
+    }
+
+    Ok(quote!(
+        impl Error {
+            #(#functions)*
+        }
+    ))
+}
+
+fn get_metrics_mod() -> Result<proc_macro2::TokenStream> {
+    let file = include_str!("metrics.rs");
+    let stream = proc_macro2::TokenStream::from_str(file).map_err(|e| anyhow::anyhow!("{}", e))?;
+    Ok(quote!(
+        pub mod metrics {
+            #stream
+        }
+    ))
+}
+
+fn get_record_mod() -> Result<proc_macro2::TokenStream> {
+    let file = include_str!("record.rs");
+    let stream = proc_macro2::TokenStream::from_str(file).map_err(|e| anyhow::anyhow!("{}", e))?;
+    Ok(quote!(
+        pub mod record {
+            #stream
+        }
+    ))
+}
+
+fn get_rate_limit_mod() -> Result<proc_macro2::TokenStream> {
+    let file = include_str!("rate_limit.rs");
+    let stream = proc_macro2::TokenStream::from_str(file).map_err(|e| anyhow::anyhow!("{}", e))?;
+    Ok(quote!(
+        pub mod rate_limit {
+            #stream
+        }
+    ))
+}
+
+fn get_deprecation_mod() -> Result<proc_macro2::TokenStream> {
+    let file = include_str!("deprecation.rs");
+    let stream = proc_macro2::TokenStream::from_str(file).map_err(|e| anyhow::anyhow!("{}", e))?;
+    Ok(quote!(
+        pub mod deprecation {
+            #stream
+        }
+    ))
+}
+
+fn get_retry_mod() -> Result<proc_macro2::TokenStream> {
+    let file = include_str!("retry.rs");
+    let stream = proc_macro2::TokenStream::from_str(file).map_err(|e| anyhow::anyhow!("{}", e))?;
+    Ok(quote!(
+        pub mod retry {
+            #stream
+        }
+    ))
+}
+
+fn get_compression_mod() -> Result<proc_macro2::TokenStream> {
+    let file = include_str!("compression.rs");
+    let stream = proc_macro2::TokenStream::from_str(file).map_err(|e| anyhow::anyhow!("{}", e))?;
+    Ok(quote!(
+        pub mod compression {
+            #stream
+        }
+    ))
+}
+
+fn get_call_options_mod() -> Result<proc_macro2::TokenStream> {
+    let file = include_str!("call_options.rs");
+    let stream = proc_macro2::TokenStream::from_str(file).map_err(|e| anyhow::anyhow!("{}", e))?;
+    Ok(quote!(
+        pub mod call_options {
+            #stream
+        }
+    ))
+}
+
+fn get_cache_mod() -> Result<proc_macro2::TokenStream> {
+    let file = include_str!("cache.rs");
+    let stream = proc_macro2::TokenStream::from_str(file).map_err(|e| anyhow::anyhow!("{}", e))?;
+    Ok(quote!(
+        pub mod cache {
+            #stream
+        }
+    ))
+}
+
+fn get_download_mod() -> Result<proc_macro2::TokenStream> {
+    let file = include_str!("download.rs");
+    let stream = proc_macro2::TokenStream::from_str(file).map_err(|e| anyhow::anyhow!("{}", e))?;
+    Ok(quote!(
+        pub mod download {
+            #stream
+        }
+    ))
+}
+
+fn get_session_mod() -> Result<proc_macro2::TokenStream> {
+    let file = include_str!("session.rs");
+    let stream = proc_macro2::TokenStream::from_str(file).map_err(|e| anyhow::anyhow!("{}", e))?;
+    Ok(quote!(
+        pub mod session {
+            #stream
+        }
+    ))
+}
+
+fn get_poll_mod() -> Result<proc_macro2::TokenStream> {
+    let file = include_str!("poll.rs");
+    let stream = proc_macro2::TokenStream::from_str(file).map_err(|e| anyhow::anyhow!("{}", e))?;
+    Ok(quote!(
+        pub mod poll {
+            #stream
+        }
+    ))
+}
+
+fn get_upload_mod() -> Result<proc_macro2::TokenStream> {
+    let file = include_str!("upload.rs");
+    let stream = proc_macro2::TokenStream::from_str(file).map_err(|e| anyhow::anyhow!("{}", e))?;
+    Ok(quote!(
+        pub mod upload {
+            #stream
+        }
+    ))
+}
+
+fn get_websocket_mod() -> Result<proc_macro2::TokenStream> {
+    let file = include_str!("websocket.rs");
+    let stream = proc_macro2::TokenStream::from_str(file).map_err(|e| anyhow::anyhow!("{}", e))?;
+    Ok(quote!(
+        pub mod websocket {
             #stream
         }
     ))
@@ -2143,6 +3938,9 @@ fn get_error_mod() -> Result<proc_macro2::TokenStream> {
 pub struct PaginationProperties {
     /// The property for the next page.
     pub next_page: Option<(String, proc_macro2::TokenStream)>,
+    /// The object property the next page is nested in, like `meta` for
+    /// `{"meta": {"next_cursor": "..."}}`, and if it is optional.
+    pub next_page_parent: Option<(String, bool)>,
     /// The parameter we send back to the server to get the next page.
     pub page_param: Option<(String, proc_macro2::TokenStream)>,
     /// The property for the items.
@@ -2151,6 +3949,9 @@ pub struct PaginationProperties {
     pub path: Option<String>,
     /// The method of the operation.
     pub method: Option<http::Method>,
+    /// If the operation answers with a bare array and the url of the next page in
+    /// its `Link` header.
+    pub link_header: bool,
 }
 
 impl PaginationProperties {
@@ -2181,6 +3982,7 @@ impl PaginationProperties {
 
             if is_pagination_property_next_page(&prop) {
                 properties.next_page = Some((prop, type_name));
+                properties.next_page_parent = None;
             } else if is_pagination_property_items(&prop, &type_name)? {
                 let ident = format_ident!(
                     "{}",
@@ -2189,12 +3991,45 @@ impl PaginationProperties {
                         .trim_end_matches('>')
                 );
                 properties.items = Some((prop, quote!(#ident)));
+            } else if properties.next_page.is_none() {
+                // Some APIs keep the next page in an object of their own, like
+                // `meta`, we look one level deep.
+                if let SchemaKind::Type(openapiv3::Type::Object(inner)) = &inner_schema.schema_kind
+                {
+                    if let Some(next_page) = Self::next_page_of(inner, spec)? {
+                        properties.next_page = Some(next_page);
+                        properties.next_page_parent = Some((prop, type_name.is_option()?));
+                    }
+                }
             }
         }
 
         Ok(properties)
     }
 
+    /// Get the property for the next page of an object, if it has one.
+    fn next_page_of(
+        o: &openapiv3::ObjectType,
+        spec: &openapiv3::OpenAPI,
+    ) -> Result<Option<(String, proc_macro2::TokenStream)>> {
+        for (k, v) in &o.properties {
+            let prop = crate::types::clean_property_name(k);
+            if !is_pagination_property_next_page(&prop) {
+                continue;
+            }
+
+            let inner_schema = v.get_schema_from_reference(spec, true)?;
+            let mut type_name =
+                crate::types::get_type_name_for_schema(&prop, &inner_schema, spec, true)?;
+            if !o.required.contains(k) && !type_name.is_option()? {
+                type_name = quote!(Option<#type_name>);
+            }
+            return Ok(Some((prop, type_name)));
+        }
+
+        Ok(None)
+    }
+
     /// Get the pagination properties for an operation.
     pub fn from_operation(
         name: &str,
@@ -2209,6 +4044,7 @@ impl PaginationProperties {
 
         // Get the return type for the operation.
         let mut schema = None;
+        let mut has_link_header = false;
         for (status_code, response) in &op.responses.responses {
             // We only care if the response is a success since this is for the function
             // to return upon success.
@@ -2220,6 +4056,10 @@ impl PaginationProperties {
                 for (_name, content) in &response.content {
                     if let Some(s) = &content.schema {
                         schema = Some(s.get_schema_from_reference(spec, true)?);
+                        has_link_header = response
+                            .headers
+                            .keys()
+                            .any(|header| header.eq_ignore_ascii_case("link"));
                         break;
                     }
                 }
@@ -2272,14 +4112,22 @@ impl PaginationProperties {
             return Ok(PaginationProperties::default());
         };
 
-        let mut properties =
-            if let SchemaKind::Type(openapiv3::Type::Object(o)) = &schema.schema_kind {
-                // Get the pagination properties for the object.
+        let mut properties = match &schema.schema_kind {
+            // Get the pagination properties for the object.
+            SchemaKind::Type(openapiv3::Type::Object(o)) => {
                 PaginationProperties::from_object(o, spec)?
-            } else {
-                // We don't have an object, so we can't get the pagination properties.
-                return Ok(PaginationProperties::default());
-            };
+            }
+            // A bare array has no properties for the next page, so it can only be
+            // paginated by the `Link` header of the response.
+            SchemaKind::Type(openapiv3::Type::Array(_)) if has_link_header => {
+                PaginationProperties {
+                    link_header: true,
+                    ..Default::default()
+                }
+            }
+            // We don't have an object, so we can't get the pagination properties.
+            _ => return Ok(PaginationProperties::default()),
+        };
 
         properties.path = Some(name.to_string());
         properties.method = Some(method.clone());
@@ -2293,6 +4141,12 @@ impl PaginationProperties {
         self.next_page.is_some() && self.items.is_some()
     }
 
+    /// Return if we can generate a stream of the items of this operation, either
+    /// from the properties of its pages or from the `Link` header of its responses.
+    pub fn has_stream(&self) -> bool {
+        self.can_paginate() || self.link_header
+    }
+
     /// Get the item type for this object.
     pub fn item_type(&self, in_crate: bool) -> Result<proc_macro2::TokenStream> {
         if let Some((_k, v)) = &self.items {
@@ -2315,6 +4169,35 @@ impl PaginationProperties {
         anyhow::bail!("No item type found, {:?}", self)
     }
 
+    /// Get the token of the next page of `self`, as an `Option<String>`, through
+    /// the object it is nested in if any.
+    pub fn next_page_token(&self) -> Result<proc_macro2::TokenStream> {
+        let Some((k, t)) = &self.next_page else {
+            anyhow::bail!("No next page property found, {:?}", self)
+        };
+        let next_page_ident = format_ident!("{}", k);
+        let token = |parent: proc_macro2::TokenStream| -> Result<proc_macro2::TokenStream> {
+            if t.is_option()? {
+                Ok(quote!(#parent.#next_page_ident.clone()))
+            } else {
+                Ok(quote!(Some(#parent.#next_page_ident.clone())))
+            }
+        };
+
+        match &self.next_page_parent {
+            None => token(quote!(self)),
+            Some((parent, false)) => {
+                let parent_ident = format_ident!("{}", parent);
+                token(quote!(self.#parent_ident))
+            }
+            Some((parent, true)) => {
+                let parent_ident = format_ident!("{}", parent);
+                let token = token(quote!(#parent_ident))?;
+                Ok(quote!(self.#parent_ident.as_ref().and_then(|#parent_ident| #token)))
+            }
+        }
+    }
+
     /// Get the item type for this object.
     pub fn next_page_str(&self) -> Result<String> {
         if let Some((k, _v)) = &self.next_page {
@@ -2332,10 +4215,20 @@ impl PaginationProperties {
 
         anyhow::bail!("No page param property found: {:?}", self)
     }
+
+    /// Get the page param the stream function does not take, the `Link` header
+    /// does not need one.
+    pub fn stream_page_param_str(&self) -> Result<String> {
+        match &self.page_param {
+            Some((k, _v)) => Ok(k.to_string()),
+            None if self.link_header => Ok(String::new()),
+            None => anyhow::bail!("No page param property found: {:?}", self),
+        }
+    }
 }
 
 fn is_pagination_property_next_page(s: &str) -> bool {
-    ["next_page", "next", "next_link"].contains(&s)
+    ["next_page", "next", "next_link", "next_cursor"].contains(&s)
 }
 
 fn is_pagination_property_param_page(s: &str) -> bool {
@@ -2343,7 +4236,11 @@ fn is_pagination_property_param_page(s: &str) -> bool {
 }
 
 fn is_pagination_property_items(s: &str, t: &proc_macro2::TokenStream) -> Result<bool> {
-    Ok(["items", "data", "results"].contains(&s) && get_text(t)?.starts_with("Vec<"))
+    Ok(is_pagination_property_items_name(s) && get_text(t)?.starts_with("Vec<"))
+}
+
+fn is_pagination_property_items_name(s: &str) -> bool {
+    ["items", "data", "results"].contains(&s)
 }
 
 pub(crate) fn get_schema_from_any(data: &SchemaData, any: &AnySchema) -> Option<Schema> {
@@ -2423,6 +4320,35 @@ pub(crate) fn get_schema_from_any(data: &SchemaData, any: &AnySchema) -> Option<
 mod test {
     use pretty_assertions::assert_eq;
 
+    #[test]
+    fn test_error_classes_of_the_declared_codes() {
+        let from_error_code = |spec: &str| {
+            let spec = crate::error_codes::prepare(&crate::load_json_spec(spec).unwrap()).unwrap();
+            super::render_from_error_code(&spec, &[])
+                .unwrap()
+                .map(|tokens| super::get_text_fmt(&tokens).unwrap())
+        };
+
+        // The spec lists the codes in an enum of its own.
+        let kittycad = from_error_code(include_str!("../../../spec.json")).unwrap();
+        for arm in [
+            r#""internal_api" | "internal_engine""#,
+            r#""auth_token_missing" | "auth_token_invalid" => Some(ErrorClass::AuthExpired)"#,
+            r#""bad_request" | "invalid_json" | "invalid_bson" | "wrong_protocol""#,
+        ] {
+            assert!(kittycad.contains(arm), "{}: {}", arm, kittycad);
+        }
+
+        // Only the codes the spec declares are classified.
+        let mock = from_error_code(include_str!("../../tests/mock.json")).unwrap();
+        assert!(mock.contains(r#""internal_api" => Some(ErrorClass::Transient)"#), "{}", mock);
+        assert!(!mock.contains("internal_engine"), "{}", mock);
+        assert!(!mock.contains("AuthExpired"), "{}", mock);
+
+        // No codes, the status decides.
+        assert_eq!(from_error_code(include_str!("../../tests/oxide.json")), None);
+    }
+
     #[test]
     fn test_generate_kittycad_types() {
         let result = super::generate_types(
@@ -2451,23 +4377,281 @@ mod test {
             Default::default(),
         )
         .unwrap();
-        expectorate::assert_contents("tests/types/oxide.rs.gen", &result.render().unwrap());
-    }
+        expectorate::assert_contents("tests/types/oxide.rs.gen", &result.render().unwrap());
+    }
+
+    #[test]
+    fn test_proper_name_number() {
+        assert_eq!(super::proper_name("1"), "One");
+        assert_eq!(super::proper_name("2"), "Two");
+        assert_eq!(super::proper_name("100"), "OneHundred");
+        assert_eq!(super::proper_name("2FaDisabled"), "TwoFaDisabled");
+        assert_eq!(super::proper_name("2fa_disabled"), "TwofaDisabled");
+        assert_eq!(super::proper_name("10Gb"), "TenGb");
+        assert_eq!(super::proper_name("128bit"), "OneHundredTwentyEightbit");
+        assert_eq!(super::proper_name("3d-model"), "ThreedModel");
+        assert_eq!(super::proper_name("-1"), "MinusOne");
+        assert_eq!(super::proper_name("-"), "Dash");
+    }
+
+    #[test]
+    fn test_proper_name_leading_zeros() {
+        // The zeros are kept, so the names do not collide.
+        assert_eq!(super::proper_name("007x"), "ZeroZeroSevenx");
+        assert_eq!(super::proper_name("7x"), "Sevenx");
+        assert_eq!(super::proper_name("007"), "ZeroZeroSeven");
+        assert_eq!(super::proper_name("7"), "Seven");
+        assert_eq!(super::proper_name("0x"), "Zerox");
+        assert_eq!(super::proper_name("00"), "ZeroZero");
+        assert_eq!(super::proper_name("0"), "Zero");
+        assert_eq!(super::proper_name("01-05"), "ZeroOneToZeroFive");
+        assert_eq!(super::proper_name("1-5"), "OneToFive");
+    }
+
+    #[test]
+    fn test_proper_name_overflow() {
+        // A number too big for a `u64` is spelled digit by digit, the name is
+        // still an identifier.
+        let digits = "123456789012345678901";
+        assert!(digits.parse::<u64>().is_err());
+        for s in [
+            digits.to_string(),
+            format!("{}x", digits),
+            format!("-{}", digits),
+            format!("{}+", digits),
+        ] {
+            let name = super::proper_name(&s);
+            assert!(
+                name.starts_with("OneTwoThree") || name.starts_with("MinusOneTwoThree"),
+                "{}",
+                name
+            );
+            assert!(syn::parse_str::<syn::Ident>(&name).is_ok(), "{}", name);
+        }
+        assert_eq!(
+            super::proper_name(&format!("{}x", digits)),
+            "OneTwoThreeFourFiveSixSevenEightNineZeroOneTwoThreeFourFiveSixSevenEightNineZeroOnex"
+        );
+    }
+
+    #[test]
+    fn test_proper_name_range() {
+        // The ranges we named by hand keep their names.
+        for (range, name) in [
+            ("18-24", "EighteenToTwentyFour"),
+            ("25-34", "TwentyFiveToThirtyFour"),
+            ("35-44", "ThirtyFiveToFourtyFour"),
+            ("45-54", "FourtyFiveToFiftyFour"),
+            ("35-54", "ThirtyFiveToFiftyFour"),
+            ("55-64", "FiftyFiveToSixtyFour"),
+            ("55+", "FiftyFivePlus"),
+            ("65+", "SixtyFivePlus"),
+        ] {
+            assert_eq!(super::proper_name(range), name);
+        }
+
+        // The others follow the same rule.
+        for (range, name) in [
+            ("0-17", "ZeroToSeventeen"),
+            ("65-74", "SixtyFiveToSeventyFour"),
+            ("40-49", "FortyToFortyNine"),
+            ("100-199", "OneHundredToOneHundredNinetyNine"),
+            ("75+", "SeventyFivePlus"),
+            ("0+", "ZeroPlus"),
+            ("1000+", "OneThousandPlus"),
+        ] {
+            assert_eq!(super::proper_name(range), name);
+        }
+
+        // Anything else is not a range.
+        assert_eq!(super::proper_name("2-factor"), "TwoFactor");
+        assert_eq!(super::proper_name("a-b"), "AB");
+        assert_eq!(super::proper_name("18-"), "Eighteen");
+    }
+
+    #[test]
+    fn test_render_enum_of_ranges() {
+        let schema = include_str!("../../tests/types/input/AgeRange.json");
+
+        let schema = serde_json::from_str::<openapiv3::Schema>(schema).unwrap();
+
+        let mut type_space = super::TypeSpace {
+            types: indexmap::map::IndexMap::new(),
+            spec: Default::default(),
+            rendered: quote!(),
+            opts: Default::default(),
+        };
+
+        type_space.render_schema("AgeRange", &schema).unwrap();
+
+        expectorate::assert_contents(
+            "tests/types/age-range.rs.gen",
+            &super::get_text_fmt(&type_space.rendered).unwrap(),
+        );
+    }
+
+    #[test]
+    fn test_proper_name_kebab() {
+        assert_eq!(super::proper_name("kebab-case"), "KebabCase");
+        assert_eq!(
+            super::proper_name("webhook-config-insecure-ssl"),
+            "WebhookConfigInsecureSsl"
+        );
+    }
+
+    #[test]
+    fn test_dedupe_schema_names_case_collision() {
+        let spec = crate::load_json_spec(include_str!("../../tests/case-collision.json")).unwrap();
+
+        let (deduped, renamed) = super::dedupe_schema_names(&spec).unwrap();
+        assert_eq!(
+            renamed.get("OauthToken").map(|s| s.as_str()),
+            Some("OauthToken2")
+        );
+        assert_eq!(renamed.len(), 1);
+
+        let schemas = &deduped.components.as_ref().unwrap().schemas;
+        assert_eq!(
+            schemas.keys().collect::<Vec<_>>(),
+            vec!["OAuthToken", "OauthToken2", "Session"]
+        );
+
+        let result = super::generate_types(&spec, Default::default()).unwrap();
+        let rendered = result.render().unwrap();
+        assert!(rendered.contains("pub struct OauthToken {"));
+        assert!(rendered.contains("pub struct OauthToken2 {"));
+        assert!(rendered.contains("pub external: OauthToken2,"));
+        assert!(rendered.contains("pub history: Vec<OauthToken2>,"));
+        assert!(rendered.contains("pub internal: OauthToken,"));
+    }
+
+    #[test]
+    fn test_dedupe_schema_names_only_renames_references() {
+        let spec: openapiv3::OpenAPI = serde_json::from_value(serde_json::json!({
+            "openapi": "3.0.3",
+            "info": { "title": "References", "version": "0.0.1" },
+            "paths": {
+                "/tokens": {
+                    "get": {
+                        "parameters": [{
+                            "in": "query",
+                            "name": "like",
+                            "schema": { "$ref": "#/components/schemas/token" }
+                        }],
+                        "responses": {
+                            "200": {
+                                "description": "See `#/components/schemas/token`.",
+                                "content": {
+                                    "application/json": {
+                                        "schema": { "$ref": "#/components/schemas/tokenList" },
+                                        "example": { "$ref": "#/components/schemas/token" }
+                                    }
+                                }
+                            }
+                        },
+                        "callbacks": {
+                            "issued": {
+                                "{$request.query.url}": {
+                                    "post": {
+                                        "requestBody": {
+                                            "content": {
+                                                "application/json": {
+                                                    "schema": { "$ref": "#/components/schemas/token" }
+                                                }
+                                            }
+                                        },
+                                        "responses": {}
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            },
+            "components": {
+                "schemas": {
+                    "Token": { "type": "string" },
+                    "token": {
+                        "description": "Not `#/components/schemas/Token`, see #/components/schemas/token.",
+                        "type": "object",
+                        "properties": { "kind": { "type": "string" } }
+                    },
+                    "tokenList": {
+                        "type": "array",
+                        "items": { "$ref": "#/components/schemas/token" }
+                    },
+                    "AnyToken": {
+                        "oneOf": [
+                            { "$ref": "#/components/schemas/Token" },
+                            { "$ref": "#/components/schemas/token" }
+                        ],
+                        "discriminator": {
+                            "propertyName": "kind",
+                            "mapping": {
+                                "full": "#/components/schemas/token",
+                                "bare": "token",
+                                "other": "#/components/schemas/tokenList"
+                            }
+                        }
+                    }
+                }
+            }
+        }))
+        .unwrap();
 
-    #[test]
-    fn test_proper_name_number() {
-        assert_eq!(super::proper_name("1"), "One");
-        assert_eq!(super::proper_name("2"), "Two");
-        assert_eq!(super::proper_name("100"), "OneHundred");
-        assert_eq!(super::proper_name("2FaDisabled"), "TwoFaDisabled");
-    }
+        let (deduped, renamed) = super::dedupe_schema_names(&spec).unwrap();
+        assert_eq!(
+            renamed.into_iter().collect::<Vec<_>>(),
+            vec![("token".to_string(), "token2".to_string())]
+        );
 
-    #[test]
-    fn test_proper_name_kebab() {
-        assert_eq!(super::proper_name("kebab-case"), "KebabCase");
+        // Every reference to the renamed schema follows it, the others stay.
+        let value = serde_json::to_value(&deduped).unwrap();
+        let op = &value["paths"]["/tokens"]["get"];
+        let schemas = &value["components"]["schemas"];
         assert_eq!(
-            super::proper_name("webhook-config-insecure-ssl"),
-            "WebhookConfigInsecureSsl"
+            op["parameters"][0]["schema"]["$ref"],
+            "#/components/schemas/token2"
+        );
+        assert_eq!(
+            op["callbacks"]["issued"]["{$request.query.url}"]["post"]["requestBody"]["content"]
+                ["application/json"]["schema"]["$ref"],
+            "#/components/schemas/token2"
+        );
+        assert_eq!(
+            schemas["tokenList"]["items"]["$ref"],
+            "#/components/schemas/token2"
+        );
+        assert_eq!(
+            schemas["AnyToken"]["oneOf"],
+            serde_json::json!([
+                { "$ref": "#/components/schemas/Token" },
+                { "$ref": "#/components/schemas/token2" }
+            ])
+        );
+        assert_eq!(
+            schemas["AnyToken"]["discriminator"]["mapping"],
+            serde_json::json!({
+                "full": "#/components/schemas/token2",
+                "bare": "token2",
+                "other": "#/components/schemas/tokenList"
+            })
+        );
+
+        // The text and the examples mentioning it are left alone.
+        let response = &op["responses"]["200"];
+        assert_eq!(response["description"], "See `#/components/schemas/token`.");
+        assert_eq!(
+            response["content"]["application/json"]["schema"]["$ref"],
+            "#/components/schemas/tokenList"
+        );
+        assert_eq!(
+            response["content"]["application/json"]["example"]["$ref"],
+            "#/components/schemas/token"
+        );
+        assert_eq!(
+            schemas["token2"]["description"],
+            "Not `#/components/schemas/Token`, see #/components/schemas/token."
         );
     }
 
@@ -2645,12 +4829,342 @@ mod test {
             .to_string();
 
         assert!(source_code.contains("pub async fn example_api_websocket_counter"));
+        // The POST on the same path is not a websocket.
+        assert!(source_code.contains("pub async fn example_api_counter_increment"));
+        assert_eq!(source_code.matches("upgrade ()").count(), 1);
         expectorate::assert_contents(
             "tests/types/websocket.rs.gen",
             &rustfmt_wrapper::rustfmt(&source_code).unwrap(),
         );
     }
 
+    #[test]
+    fn test_head_options_and_trace() {
+        let spec: openapiv3::OpenAPI =
+            serde_json::from_str(include_str!("../../tests/types/input/head-options.json"))
+                .unwrap();
+        let mut type_space = super::generate_types(&spec, Default::default()).unwrap();
+
+        let (files, spec, _) =
+            crate::functions::generate_files(&mut type_space, &Default::default()).unwrap();
+
+        // The examples end up in the spec for every method.
+        let path = spec.paths.paths["/files/{name}"].as_item().unwrap();
+        for op in [&path.head, &path.options, &path.trace] {
+            assert!(op.as_ref().unwrap().extensions.contains_key("x-rust"));
+        }
+
+        expectorate::assert_contents(
+            "tests/types/head-options.rs.gen",
+            &rustfmt_wrapper::rustfmt(files["default"].to_string()).unwrap(),
+        );
+    }
+
+    #[test]
+    fn test_datetime_aliases() {
+        let spec = crate::load_json_spec(include_str!("../../tests/datetime.json")).unwrap();
+
+        // Leave out the runtime modules, we only care about the types.
+        let mut type_space = super::TypeSpace {
+            types: indexmap::map::IndexMap::new(),
+            spec: spec.clone(),
+            rendered: quote!(),
+            opts: Default::default(),
+        };
+        for (name, schema) in &spec.components.as_ref().unwrap().schemas {
+            type_space
+                .render_schema(name, schema.as_item().unwrap())
+                .unwrap();
+        }
+        let (files, _, _) =
+            crate::functions::generate_files(&mut type_space, &Default::default()).unwrap();
+
+        // Every format goes through the aliases, only the examples name `chrono`.
+        let types = super::get_text_fmt(&type_space.rendered).unwrap();
+        let events = rustfmt_wrapper::rustfmt(files["events"].to_string()).unwrap();
+        for format in ["DateTime", "Date", "Time", "PartialDateTime"] {
+            assert!(
+                types.contains(&format!("datetime::{},", format)),
+                "{}",
+                format
+            );
+        }
+        assert!(types.contains("ids::Uuid,"));
+        assert!(!types.contains("chrono::") && !types.contains("uuid::"));
+        assert!(events.contains("id: crate::types::ids::Uuid,"));
+        assert!(events.contains("day: crate::types::datetime::Date,"));
+
+        expectorate::assert_contents(
+            "tests/types/datetime.rs.gen",
+            &format!("{}\n{}", types, events),
+        );
+    }
+
+    #[test]
+    fn test_unique_items() {
+        let spec: openapiv3::OpenAPI =
+            serde_json::from_str(include_str!("../../tests/types/input/unique-items.json"))
+                .unwrap();
+        let opts = crate::Opts {
+            unique_items_as_sets: true,
+            ..Default::default()
+        };
+
+        // Leave out the runtime modules, we only care about the types.
+        let mut type_space = super::TypeSpace {
+            types: indexmap::map::IndexMap::new(),
+            spec: spec.clone(),
+            rendered: quote!(),
+            opts: opts.clone(),
+        };
+        for (name, schema) in &spec.components.as_ref().unwrap().schemas {
+            type_space
+                .render_schema(name, schema.as_item().unwrap())
+                .unwrap();
+        }
+        let (files, _, _) = crate::functions::generate_files(&mut type_space, &opts).unwrap();
+
+        expectorate::assert_contents(
+            "tests/types/unique-items.rs.gen",
+            &format!(
+                "{}\n{}",
+                super::get_text_fmt(&type_space.rendered).unwrap(),
+                rustfmt_wrapper::rustfmt(files["default"].to_string()).unwrap()
+            ),
+        );
+
+        // Without the option the arrays are left alone.
+        let mut type_space = super::generate_types(&spec, Default::default()).unwrap();
+        let (files, _, _) =
+            crate::functions::generate_files(&mut type_space, &Default::default()).unwrap();
+        assert!(!type_space.rendered.to_string().contains("IndexSet"));
+        assert!(!files["default"].to_string().contains("check_unique_items"));
+    }
+
+    #[test]
+    fn test_validate_requests() {
+        let spec = crate::load_json_spec(include_str!("../../tests/mock.json")).unwrap();
+        let opts = crate::Opts {
+            validate_requests: true,
+            ..Default::default()
+        };
+        let mut type_space = super::generate_types(&spec, opts.clone()).unwrap();
+        let (files, _, _) = crate::functions::generate_files(&mut type_space, &opts).unwrap();
+
+        // The optional fields are checked when they are set, the others always.
+        let rendered = super::get_text_fmt(&type_space.rendered).unwrap();
+        let validate = &rendered[rendered.find("impl ThingMembers {").unwrap()..];
+        let validate = &validate[..validate.find("\n}\n").unwrap()];
+        assert!(
+            validate.contains("let value = &self.user_ids;"),
+            "{}",
+            validate
+        );
+        assert!(
+            validate.contains("if let Some(value) = &self.note {"),
+            "{}",
+            validate
+        );
+        assert!(
+            validate.contains("if let Some(value) = &self.limit {"),
+            "{}",
+            validate
+        );
+        // Only the objects with constraints have one, the attachments of a
+        // multipart body count too.
+        assert_eq!(rendered.matches("pub fn validate(").count(), 2);
+        assert!(files["things"].to_string().contains("body . validate ()"));
+
+        // Without the option there is nothing to check.
+        let mut type_space = super::generate_types(&spec, Default::default()).unwrap();
+        let (files, _, _) =
+            crate::functions::generate_files(&mut type_space, &Default::default()).unwrap();
+        assert!(!type_space.rendered.to_string().contains("validation"));
+        assert!(!files["things"].to_string().contains("validate"));
+    }
+
+    #[test]
+    fn test_default_error_response() {
+        let spec: openapiv3::OpenAPI =
+            serde_json::from_str(include_str!("../../tests/types/input/default-error.json"))
+                .unwrap();
+        let mut type_space = super::generate_types(&spec, Default::default()).unwrap();
+        let (files, _, _) =
+            crate::functions::generate_files(&mut type_space, &Default::default()).unwrap();
+
+        // The errors the operation does not list have the body of its `default`
+        // response.
+        expectorate::assert_contents(
+            "tests/types/default-error.rs.gen",
+            &format!(
+                "{}\n{}\n{}",
+                super::get_text_fmt(&super::render_error_body(&spec, &Default::default()).unwrap())
+                    .unwrap(),
+                super::get_text_fmt(&super::render_error_responses(&spec).unwrap()).unwrap(),
+                rustfmt_wrapper::rustfmt(files["widgets"].to_string()).unwrap()
+            ),
+        );
+
+        // Without one there is nothing to parse.
+        let spec = crate::load_json_spec(include_str!("../../tests/types/input/head-options.json"))
+            .unwrap();
+        assert!(super::get_text_fmt(
+            &super::render_error_body(&spec, &Default::default()).unwrap()
+        )
+        .unwrap()
+        .contains("pub enum ErrorBody {}"));
+        assert!(super::render_error_responses(&spec).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_rust_name() {
+        let spec: openapiv3::OpenAPI =
+            serde_json::from_str(include_str!("../../tests/types/input/rust-name.json")).unwrap();
+
+        // The schema is renamed, and so are the references to it.
+        let (spec, renamed) = super::dedupe_schema_names(&spec).unwrap();
+        assert_eq!(
+            renamed["oauth2_client_grant_post_body_application_json"],
+            "ClientGrant"
+        );
+
+        // Leave out the runtime modules, we only care about the names.
+        let mut type_space = super::TypeSpace {
+            types: indexmap::map::IndexMap::new(),
+            spec: spec.clone(),
+            rendered: quote!(),
+            opts: Default::default(),
+        };
+        for (name, schema) in &spec.components.as_ref().unwrap().schemas {
+            type_space
+                .render_schema(name, schema.as_item().unwrap())
+                .unwrap();
+        }
+        let (files, _, _) =
+            crate::functions::generate_files(&mut type_space, &Default::default()).unwrap();
+
+        let rendered = super::get_text_fmt(&type_space.rendered).unwrap();
+        let oauth2 = rustfmt_wrapper::rustfmt(files["oauth2"].to_string()).unwrap();
+        expectorate::assert_contents(
+            "tests/types/rust-name.rs.gen",
+            &format!("{}\n{}", rendered, oauth2),
+        );
+
+        // The docs search finds the function by its operation id and its path, and
+        // the type by the name of its schema.
+        assert!(oauth2.contains(
+            "#[doc(alias = \"oauth2_client_grant_post_application_json\")]\n#[doc(alias = \"/oauth2/client/grant\")]\n#[tracing::instrument]\npub async fn grant<'a>("
+        ));
+        assert!(rendered.contains("#[doc(alias = \"oauth2_client\")]\n#[derive("));
+        assert!(rendered.contains("pub struct Oauth2Client {"));
+    }
+
+    #[test]
+    fn test_acronym_casing() {
+        let spec: openapiv3::OpenAPI =
+            serde_json::from_str(include_str!("../../tests/types/input/acronyms.json")).unwrap();
+        let opts = crate::Opts {
+            acronym_casing: true,
+            ..Default::default()
+        };
+        let _casing = super::casing::scoped(&opts);
+
+        // Leave out the runtime modules, we only care about the names.
+        let mut type_space = super::TypeSpace {
+            types: indexmap::map::IndexMap::new(),
+            spec: spec.clone(),
+            rendered: quote!(),
+            opts: opts.clone(),
+        };
+        for (name, schema) in &spec.components.as_ref().unwrap().schemas {
+            type_space
+                .render_schema(name, schema.as_item().unwrap())
+                .unwrap();
+        }
+        let (files, _, _) = crate::functions::generate_files(&mut type_space, &opts).unwrap();
+        assert_eq!(files.keys().collect::<Vec<_>>(), ["ai", "oauth2_clients"]);
+
+        let rendered = super::get_text_fmt(&type_space.rendered).unwrap();
+        expectorate::assert_contents(
+            "tests/types/acronym-casing.rs.gen",
+            &format!(
+                "{}\n{}\n{}",
+                rendered,
+                rustfmt_wrapper::rustfmt(files["ai"].to_string()).unwrap(),
+                rustfmt_wrapper::rustfmt(files["oauth2_clients"].to_string()).unwrap()
+            ),
+        );
+
+        // The fields keep their wire names.
+        assert!(
+            rendered.contains("#[serde(rename = \"userIDs\""),
+            "{}",
+            rendered
+        );
+        assert!(rendered.contains("pub user_ids:"), "{}", rendered);
+        assert!(rendered.contains("pub oauth2_scopes:"), "{}", rendered);
+        assert!(rendered.contains("pub ml_model_id:"), "{}", rendered);
+    }
+
+    #[test]
+    fn test_rust_name_errors() {
+        let spec: serde_json::Value =
+            serde_json::from_str(include_str!("../../tests/types/input/rust-name.json")).unwrap();
+        let generate = |patch: serde_json::Value| {
+            let mut spec = spec.clone();
+            json_patch::merge(&mut spec, &patch);
+            let spec: openapiv3::OpenAPI = serde_json::from_value(spec).unwrap();
+            super::generate_types(&spec, Default::default())
+                .and_then(|mut type_space| {
+                    crate::functions::generate_files(&mut type_space, &Default::default())
+                })
+                .map(|_| ())
+                .unwrap_err()
+                .to_string()
+        };
+
+        // The names must be Rust names.
+        let err = generate(serde_json::json!({"components": {"schemas": {
+            "GrantResponse": {"x-rust-name": "grant_response"}
+        }}}));
+        assert_eq!(
+            err,
+            r#"`x-rust-name` of schema `GrantResponse` must be a Rust type name, not "grant_response""#
+        );
+        let err = generate(
+            serde_json::json!({"paths": {"/oauth2/client/grant": {"post": {
+                "x-rust-name": "type"
+            }}}}),
+        );
+        assert_eq!(
+            err,
+            r#"`x-rust-name` of operation `oauth2_client_grant_post_application_json` must be a snake case function name, not "type""#
+        );
+
+        // And they can't collide with another name.
+        let err = generate(serde_json::json!({"components": {"schemas": {
+            "oauth2_client_grant_post_body_application_json": {"x-rust-name": "GrantResponse"}
+        }}}));
+        assert_eq!(
+            err,
+            "schemas `oauth2_client_grant_post_body_application_json` and `GrantResponse` would \
+             both be named `GrantResponse`, check their `x-rust-name`"
+        );
+        let err = generate(
+            serde_json::json!({"paths": {"/oauth2/client/revoke": {"post": {
+                "tags": ["oauth2"],
+                "operationId": "revoke_grant",
+                "x-rust-name": "grant",
+                "responses": {"204": {"description": "The grant is revoked."}}
+            }}}}),
+        );
+        assert_eq!(
+            err,
+            "`POST /oauth2/client/grant` and `POST /oauth2/client/revoke` would both be the \
+             function `grant` of the `oauth2` tag, check their `x-rust-name`"
+        );
+    }
+
     #[test]
     fn test_render_object_with_custom_date_format() {
         let schema = include_str!("../../tests/types/input/FileDensity.json");
@@ -2676,6 +5190,246 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_render_all_of_with_required_override() {
+        let spec = crate::load_json_spec(include_str!("../../tests/mock.json")).unwrap();
+        let schema = spec.components.as_ref().unwrap().schemas["NamedThing"]
+            .as_item()
+            .unwrap()
+            .clone();
+
+        let mut type_space = super::TypeSpace {
+            types: indexmap::map::IndexMap::new(),
+            spec,
+            rendered: quote!(),
+            opts: Default::default(),
+        };
+
+        type_space.render_schema("NamedThing", &schema).unwrap();
+
+        expectorate::assert_contents(
+            "tests/types/mock.named-thing.rs.gen",
+            &super::get_text_fmt(&type_space.rendered).unwrap(),
+        );
+    }
+
+    #[test]
+    fn test_render_inline_one_of_items() {
+        let spec = crate::load_json_spec(include_str!("../../tests/mock.json")).unwrap();
+        let schema = spec.components.as_ref().unwrap().schemas["Shelf"]
+            .as_item()
+            .unwrap()
+            .clone();
+
+        let mut type_space = super::TypeSpace {
+            types: indexmap::map::IndexMap::new(),
+            spec,
+            rendered: quote!(),
+            opts: Default::default(),
+        };
+
+        type_space.render_schema("Shelf", &schema).unwrap();
+
+        expectorate::assert_contents(
+            "tests/types/mock.shelf.rs.gen",
+            &super::get_text_fmt(&type_space.rendered).unwrap(),
+        );
+    }
+
+    #[test]
+    fn test_render_schema_registry() {
+        let spec = crate::load_json_spec(include_str!("../../../spec.json")).unwrap();
+        let type_space = super::generate_types(&spec, Default::default()).unwrap();
+
+        let registry = super::get_text(&type_space.render_schema_registry()).unwrap();
+        assert!(registry.contains(
+            r#"registry.push(("FileConversion",schemars::schema_for!(FileConversion)));"#
+        ));
+        // The runtime modules are not in the registry.
+        assert!(!registry.contains(r#""Base64Data""#));
+
+        // The entries are sorted by name.
+        let names: Vec<&str> = registry
+            .split("registry.push((\"")
+            .skip(1)
+            .map(|entry| entry.split('"').next().unwrap())
+            .collect();
+        let mut sorted = names.clone();
+        sorted.sort();
+        assert_eq!(names, sorted);
+        assert!(names.len() > 100);
+    }
+
+    #[test]
+    fn test_all_of_nullable_override() {
+        let spec = crate::load_json_spec(include_str!("../../tests/mock.json")).unwrap();
+        let schema: openapiv3::Schema = serde_json::from_str(
+            r##"{
+            "allOf": [
+                { "$ref": "#/components/schemas/ThingDraft" },
+                { "nullable": true }
+            ]
+        }"##,
+        )
+        .unwrap();
+
+        let t = super::get_type_name_for_schema("draft", &schema, &spec, true).unwrap();
+        assert_eq!(super::get_text(&t).unwrap(), "Option<Draft>");
+    }
+
+    #[test]
+    fn test_render_case_insensitive_enums() {
+        let render = |case_insensitive_enums: bool| {
+            let schema = serde_json::from_value::<openapiv3::Schema>(serde_json::json!({
+                "description": "The format of an export.",
+                "type": "string",
+                "enum": ["step", "obj", "big-box", "Gltf", null],
+                "nullable": true
+            }))
+            .unwrap();
+            let mut type_space = super::TypeSpace {
+                types: indexmap::map::IndexMap::new(),
+                spec: Default::default(),
+                rendered: quote!(),
+                opts: crate::Opts {
+                    case_insensitive_enums,
+                    ..Default::default()
+                },
+            };
+            type_space.render_schema("ExportFormat", &schema).unwrap();
+            super::get_text_fmt(&type_space.rendered).unwrap()
+        };
+
+        // The default derives the strict serde.
+        let rendered = render(false).replace(' ', "");
+        assert!(rendered.contains("serde::Deserialize,"), "{}", rendered);
+        assert!(!rendered.contains("implstd::str::FromStr"), "{}", rendered);
+
+        expectorate::assert_contents("tests/types/case-insensitive-enums.rs.gen", &render(true));
+    }
+
+    #[test]
+    fn test_render_secret_fields() {
+        let render = |secret_fields: bool| {
+            let schema = serde_json::from_value::<openapiv3::Schema>(serde_json::json!({
+                "description": "The credentials of an OAuth 2.0 client.",
+                "type": "object",
+                "required": ["client_id", "client_secret"],
+                "properties": {
+                    "client_id": {"type": "string"},
+                    "client_secret": {"type": "string", "format": "password"},
+                    "token": {"type": "string", "writeOnly": true},
+                    "kind": {"type": "string", "enum": ["public", "confidential"], "writeOnly": true}
+                }
+            }))
+            .unwrap();
+            let mut type_space = super::TypeSpace {
+                types: indexmap::map::IndexMap::new(),
+                spec: Default::default(),
+                rendered: quote!(),
+                opts: crate::Opts {
+                    secret_fields,
+                    ..Default::default()
+                },
+            };
+            type_space
+                .render_schema("ClientCredentials", &schema)
+                .unwrap();
+            super::get_text_fmt(&type_space.rendered).unwrap()
+        };
+
+        // By default the secrets are strings.
+        let rendered = render(false);
+        assert!(!rendered.contains("Secret"), "{}", rendered);
+
+        let rendered = render(true);
+        expectorate::assert_contents("tests/types/secret-fields.rs.gen", &rendered);
+        assert!(rendered.contains("pub client_secret: secret::Secret<String>,"));
+        assert!(rendered.contains("pub token: Option<secret::Secret<String>>,"));
+        assert!(rendered.contains("pub client_id: String,"));
+        assert!(rendered.contains("secret::redacted(|| serde_json::to_string_pretty(self))"));
+    }
+
+    #[test]
+    fn test_render_enum_defaults() {
+        let render = |schema: serde_json::Value| {
+            let schema = serde_json::from_value::<openapiv3::Schema>(schema).unwrap();
+            let mut type_space = super::TypeSpace {
+                types: indexmap::map::IndexMap::new(),
+                spec: Default::default(),
+                rendered: quote!(),
+                opts: Default::default(),
+            };
+            type_space
+                .render_schema("UnitLength", &schema)
+                .map(|_| super::get_text_fmt(&type_space.rendered).unwrap())
+        };
+
+        // A string default is the value, not its JSON with the quotes.
+        let rendered = render(serde_json::json!({
+            "type": "string",
+            "enum": ["cm", "in", "mm"],
+            "default": "mm"
+        }))
+        .unwrap();
+        assert!(rendered.contains("UnitLength::Mm"), "{}", rendered);
+
+        // The values are renamed the same way as the variants.
+        let rendered = render(serde_json::json!({
+            "type": "string",
+            "enum": ["1", "2", "in-house"],
+            "default": "in-house"
+        }))
+        .unwrap();
+        assert!(rendered.contains("UnitLength::InHouse"), "{}", rendered);
+
+        // Some specs leave the quotes off.
+        let rendered = render(serde_json::json!({
+            "type": "string",
+            "enum": ["1", "2"],
+            "default": 2
+        }))
+        .unwrap();
+        assert!(rendered.contains("UnitLength::Two"), "{}", rendered);
+
+        // A single value is the default, a null default is no default.
+        let rendered = render(serde_json::json!({
+            "type": "string",
+            "enum": ["only"]
+        }))
+        .unwrap();
+        assert!(rendered.contains("UnitLength::Only"), "{}", rendered);
+        let rendered = render(serde_json::json!({
+            "type": "string",
+            "enum": ["cm", "mm", null],
+            "nullable": true,
+            "default": null
+        }))
+        .unwrap();
+        assert!(!rendered.contains("Default for"), "{}", rendered);
+
+        assert_eq!(
+            render(serde_json::json!({
+                "type": "string",
+                "enum": ["cm", "mm"],
+                "default": "yd"
+            }))
+            .unwrap_err()
+            .to_string(),
+            "the default of enum `UnitLength` is `yd`, which is not one of its values: `cm`, `mm`"
+        );
+        assert_eq!(
+            render(serde_json::json!({
+                "type": "string",
+                "enum": ["cm", "mm"],
+                "default": ["cm"]
+            }))
+            .unwrap_err()
+            .to_string(),
+            r#"the default of enum `UnitLength` is not a string: ["cm"]"#
+        );
+    }
+
     #[test]
     fn test_render_one_of_enum_types() {
         let schema = include_str!("../../tests/types/input/AccountProvider.json");
@@ -2697,6 +5451,90 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_render_sort_fields() {
+        let spec = crate::load_json_spec(include_str!("../../tests/mock.json")).unwrap();
+
+        // The same spec with the properties of every object the other way around.
+        let mut shuffled = spec.clone();
+        for schema in shuffled.components.as_mut().unwrap().schemas.values_mut() {
+            if let openapiv3::ReferenceOr::Item(openapiv3::Schema {
+                schema_kind: openapiv3::SchemaKind::Type(openapiv3::Type::Object(o)),
+                ..
+            }) = schema
+            {
+                o.properties.reverse();
+            }
+        }
+
+        let render = |spec: &openapiv3::OpenAPI, sort_fields: bool| {
+            let opts = crate::Opts {
+                sort_fields,
+                ..Default::default()
+            };
+            let type_space = super::generate_types(spec, opts).unwrap();
+            super::get_text_fmt(&type_space.rendered).unwrap()
+        };
+
+        assert_ne!(render(&spec, false), render(&shuffled, false));
+        let sorted = render(&spec, true);
+        assert_eq!(sorted, render(&shuffled, true));
+        let thing = &sorted[sorted.find("pub struct Thing {").unwrap()..];
+        let thing = &thing[..thing.find("\n}").unwrap()];
+        let fields = thing
+            .lines()
+            .skip(1)
+            .filter_map(|line| line.trim().strip_prefix("pub "))
+            .filter_map(|line| line.split(':').next())
+            .collect::<Vec<_>>();
+        let mut expected = fields.clone();
+        expected.sort();
+        assert_eq!(fields, expected);
+    }
+
+    #[test]
+    fn test_render_tagged_any_of() {
+        let schema = include_str!("../../tests/types/input/tagged-any-of.json");
+        let schema = serde_json::from_str::<openapiv3::Schema>(schema).unwrap();
+        let render = |schema: &openapiv3::Schema| {
+            let mut type_space = super::TypeSpace {
+                types: indexmap::map::IndexMap::new(),
+                spec: crate::load_json_spec(include_str!("../../tests/mock.json")).unwrap(),
+                rendered: quote!(),
+                opts: Default::default(),
+            };
+            type_space.render_schema("Shape", schema).unwrap();
+            super::get_text_fmt(&type_space.rendered).unwrap()
+        };
+
+        // The objects all have a `kind` of their own, so it is an enum tagged by it.
+        let rendered = render(&schema);
+        expectorate::assert_contents("tests/types/tagged-any-of.rs.gen", &rendered);
+
+        // Without a tag of its own in each, it is still merged into one object.
+        let mut untagged = schema.clone();
+        if let openapiv3::SchemaKind::AnyOf { any_of } = &mut untagged.schema_kind {
+            let mut square = any_of[1].as_item().unwrap().clone();
+            if let openapiv3::SchemaKind::Type(openapiv3::Type::Object(o)) = &mut square.schema_kind
+            {
+                o.properties["kind"] = openapiv3::ReferenceOr::Item(Box::new(
+                    serde_json::from_value(
+                        serde_json::json!({"type": "string", "enum": ["circle"]}),
+                    )
+                    .unwrap(),
+                ));
+            }
+            any_of[1] = openapiv3::ReferenceOr::Item(square);
+        }
+        let rendered = render(&untagged);
+        assert!(rendered.contains("pub struct Shape {"), "{}", rendered);
+        assert!(
+            rendered.contains("pub radius: Option<f64>,"),
+            "{}",
+            rendered
+        );
+    }
+
     #[test]
     fn test_render_sum_enum_types() {
         let schema = include_str!("../../tests/types/input/ModelingCmd.json");
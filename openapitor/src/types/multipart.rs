@@ -13,6 +13,14 @@ pub struct Attachment {
     pub data: Vec<u8>,
 }
 
+impl Attachment {
+    /// The size of the attachment in bytes, if it is known before it is sent.
+    /// Size limits declared by the API are only checked when this is known.
+    pub fn size_hint(&self) -> Option<u64> {
+        Some(self.data.len() as u64)
+    }
+}
+
 impl std::convert::TryFrom<Attachment> for reqwest::multipart::Part {
     type Error = reqwest::Error;
 
@@ -2,23 +2,139 @@
 
 use anyhow::Result;
 
+/// The default maximum number of pages a paginated stream fetches, see
+/// `Client::set_max_pages`.
+pub const DEFAULT_MAX_PAGES: usize = 10_000;
+
 /// A trait for types that allow pagination.
 pub trait Pagination {
     /// The item that is paginated.
     type Item: serde::de::DeserializeOwned;
 
-    /// Returns true if the response has more pages.
+    /// Returns true if the response has more pages, that is it has a next page
+    /// token that is not empty.
     fn has_more_pages(&self) -> bool;
 
-    /// Returns the next page token.
+    /// Returns the next page token, an empty token is `None`.
     fn next_page_token(&self) -> Option<String>;
 
+    /// Modify a request to get the page after the one with this token.
+    #[allow(clippy::result_large_err)]
+    fn next_page_request(
+        req: reqwest::Request,
+        token: &str,
+    ) -> Result<reqwest::Request, crate::types::error::Error>;
+
     /// Modify a request to get the next page.
+    #[allow(clippy::result_large_err)]
     fn next_page(
         &self,
         req: reqwest::Request,
-    ) -> Result<reqwest::Request, crate::types::error::Error>;
+    ) -> Result<reqwest::Request, crate::types::error::Error> {
+        Self::next_page_request(req, self.next_page_token().as_deref().unwrap_or(""))
+    }
 
     /// Get the items from a page.
-    fn items(&self) -> Vec<Self::Item>;
+    fn items(&self) -> &[Self::Item];
+
+    /// Take the items out of a page, without copying them.
+    fn into_items(self) -> Vec<Self::Item>;
+}
+
+/// The url of the next page in the `Link` header of a response, as in
+/// `<https://api.example.com/things?page=2>; rel="next"`, for the operations that
+/// answer with a bare array.
+pub fn next_page_link(headers: &reqwest::header::HeaderMap) -> Option<String> {
+    headers
+        .get_all(reqwest::header::LINK)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .find_map(|link| {
+            let mut params = link.split(';');
+            let url = params.next()?.trim().strip_prefix('<')?.strip_suffix('>')?;
+            let is_next = params.any(|param| {
+                param
+                    .trim()
+                    .strip_prefix("rel=")
+                    .map(|rel| {
+                        rel.trim_matches('"')
+                            .split_whitespace()
+                            .any(|rel| rel.eq_ignore_ascii_case("next"))
+                    })
+                    .unwrap_or(false)
+            });
+            is_next.then(|| url.to_string())
+        })
+}
+
+/// Modify a request to get the page at `link`, the url of the `Link` header of the
+/// previous page, relative to the url of the request.
+///
+/// The request has our credentials, so a link to another origin (scheme, host and
+/// port) is an error rather than a request sending them there.
+#[allow(clippy::result_large_err)]
+pub fn link_page_request(
+    mut req: reqwest::Request,
+    link: &str,
+) -> Result<reqwest::Request, crate::types::error::Error> {
+    let url = req.url().join(link).map_err(|err| {
+        crate::types::error::Error::InvalidRequest(format!(
+            "invalid link to the next page `{}`: {}",
+            link, err
+        ))
+    })?;
+    if url.origin() != req.url().origin() {
+        return Err(crate::types::error::Error::InvalidRequest(format!(
+            "the link to the next page `{}` is not on the origin of the request `{}`",
+            link,
+            req.url().origin().ascii_serialization()
+        )));
+    }
+    *req.url_mut() = url;
+    Ok(req)
+}
+
+#[cfg(test)]
+mod tests {
+    fn request(url: &str) -> reqwest::Request {
+        reqwest::Request::new(reqwest::Method::GET, url.parse().unwrap())
+    }
+
+    #[test]
+    fn test_link_page_request() {
+        let req = super::link_page_request(
+            request("https://api.example.com/things?limit=2"),
+            "/things?page=2",
+        )
+        .unwrap();
+        assert_eq!(req.url().as_str(), "https://api.example.com/things?page=2");
+
+        let req = super::link_page_request(
+            request("https://api.example.com/things"),
+            "https://api.example.com:443/things?page=2",
+        )
+        .unwrap();
+        assert_eq!(req.url().as_str(), "https://api.example.com/things?page=2");
+
+        // Another host, scheme or port would get our credentials.
+        for link in [
+            "https://evil.example.com/things?page=2",
+            "//evil.example.com/things?page=2",
+            "http://api.example.com/things?page=2",
+            "https://api.example.com:8443/things?page=2",
+        ] {
+            let err = super::link_page_request(request("https://api.example.com/things"), link)
+                .unwrap_err();
+            match &err {
+                crate::types::error::Error::InvalidRequest(message) => assert!(
+                    message.contains("not on the origin of the request `https://api.example.com`"),
+                    "{}: {}",
+                    link,
+                    message
+                ),
+                _ => panic!("{}: {:?}", link, err),
+            }
+        }
+    }
 }
@@ -0,0 +1,290 @@
+//! The paths of the operations, as the templates of the spec, and the matching of
+//! the path of a request against them.
+
+use std::collections::BTreeMap;
+
+/// A segment of the path template of an operation, between two `/`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Segment {
+    /// A segment that only matches itself.
+    Literal(&'static str),
+    /// A segment with a path parameter, between a `prefix` and a `suffix` that are
+    /// most often empty.
+    Param {
+        /// The name of the parameter.
+        name: &'static str,
+        /// The text before the parameter.
+        prefix: &'static str,
+        /// The text after the parameter.
+        suffix: &'static str,
+    },
+}
+
+/// An operation in the route table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Route {
+    /// The method of the operation, as `http::Method::as_str` has it.
+    pub method: &'static str,
+    /// The id of the operation.
+    pub operation_id: &'static str,
+    /// The path template of the operation, as in the spec.
+    pub template: &'static str,
+    /// The segments of the template.
+    pub segments: &'static [Segment],
+}
+
+/// The operation a path matched, with the values of its path parameters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatchedOperation {
+    /// The id of the operation.
+    pub operation_id: &'static str,
+    /// The path template of the operation, as in the spec.
+    pub template: &'static str,
+    /// The values of the path parameters, percent-decoded, by their names.
+    pub params: BTreeMap<&'static str, String>,
+}
+
+/// Match the method and the path of a request, the part of the url after the base
+/// url, against `routes`. The query and the fragment are ignored, the segments of
+/// the path are percent-decoded before they are compared, and a parameter never
+/// matches an empty value. When more than one route matches, the one with a
+/// literal segment where the others have a parameter wins.
+pub fn match_routes(
+    routes: &[Route],
+    method: &http::Method,
+    path: &str,
+) -> Option<MatchedOperation> {
+    let path = path.split(['?', '#']).next().unwrap_or_default();
+    let segments = path
+        .strip_prefix('/')
+        .unwrap_or(path)
+        .split('/')
+        .map(percent_decode)
+        .collect::<Option<Vec<_>>>()?;
+
+    let mut best: Option<(Vec<bool>, MatchedOperation)> = None;
+    for route in routes {
+        if route.method != method.as_str() || route.segments.len() != segments.len() {
+            continue;
+        }
+        let Some(params) = match_segments(route.segments, &segments) else {
+            continue;
+        };
+
+        // The literal segments are more specific than the parameters.
+        let specificity = route
+            .segments
+            .iter()
+            .map(|segment| matches!(segment, Segment::Literal(_)))
+            .collect::<Vec<_>>();
+        if best.as_ref().is_some_and(|(best, _)| *best >= specificity) {
+            continue;
+        }
+        best = Some((
+            specificity,
+            MatchedOperation {
+                operation_id: route.operation_id,
+                template: route.template,
+                params,
+            },
+        ));
+    }
+
+    best.map(|(_, matched)| matched)
+}
+
+/// The values of the parameters of `template`, if `segments` match it.
+fn match_segments(
+    template: &[Segment],
+    segments: &[String],
+) -> Option<BTreeMap<&'static str, String>> {
+    let mut params = BTreeMap::new();
+    for (expected, segment) in template.iter().zip(segments) {
+        match expected {
+            Segment::Literal(literal) => {
+                if literal != segment {
+                    return None;
+                }
+            }
+            Segment::Param {
+                name,
+                prefix,
+                suffix,
+            } => {
+                let value = segment.strip_prefix(prefix)?.strip_suffix(suffix)?;
+                if value.is_empty() {
+                    return None;
+                }
+                params.insert(*name, value.to_string());
+            }
+        }
+    }
+
+    Some(params)
+}
+
+/// Encode the value of a path parameter for the url of a request, so a `/`, a `%`
+/// or a space in it stays in its segment and `match_routes` decodes the same
+/// value. The unreserved characters, the sub-delimiters, `:` and `@` are kept as
+/// they are, every other byte is percent-encoded.
+pub fn encode_param(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for b in value.bytes() {
+        if b.is_ascii_alphanumeric() || b"-._~!$&'()*+,;=:@".contains(&b) {
+            encoded.push(b as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", b));
+        }
+    }
+    encoded
+}
+
+/// Decode the `%XX` of a segment, `None` if one is not valid or the segment is not
+/// UTF-8 once decoded.
+fn percent_decode(segment: &str) -> Option<String> {
+    let mut decoded = Vec::with_capacity(segment.len());
+    let mut bytes = segment.bytes();
+    while let Some(b) = bytes.next() {
+        if b == b'%' {
+            let hex = [bytes.next()?, bytes.next()?];
+            let hex = std::str::from_utf8(&hex).ok()?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+        } else {
+            decoded.push(b);
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{encode_param, match_routes, Route, Segment};
+
+    const ROUTES: &[Route] = &[
+        Route {
+            method: "GET",
+            operation_id: "list_things",
+            template: "/things",
+            segments: &[Segment::Literal("things")],
+        },
+        Route {
+            method: "GET",
+            operation_id: "get_thing",
+            template: "/things/{id}",
+            segments: &[
+                Segment::Literal("things"),
+                Segment::Param {
+                    name: "id",
+                    prefix: "",
+                    suffix: "",
+                },
+            ],
+        },
+        Route {
+            method: "GET",
+            operation_id: "get_thing_stats",
+            template: "/things/stats",
+            segments: &[Segment::Literal("things"), Segment::Literal("stats")],
+        },
+        Route {
+            method: "GET",
+            operation_id: "get_thing_part",
+            template: "/things/{id}/parts/{part}.step",
+            segments: &[
+                Segment::Literal("things"),
+                Segment::Param {
+                    name: "id",
+                    prefix: "",
+                    suffix: "",
+                },
+                Segment::Literal("parts"),
+                Segment::Param {
+                    name: "part",
+                    prefix: "",
+                    suffix: ".step",
+                },
+            ],
+        },
+    ];
+
+    fn matched(method: http::Method, path: &str) -> Option<(&'static str, Vec<(&str, String)>)> {
+        let matched = match_routes(ROUTES, &method, path)?;
+        Some((matched.operation_id, matched.params.into_iter().collect()))
+    }
+
+    #[test]
+    fn test_match_routes() {
+        assert_eq!(
+            matched(http::Method::GET, "/things"),
+            Some(("list_things", vec![]))
+        );
+        assert_eq!(
+            matched(http::Method::GET, "/things/abc?limit=4"),
+            Some(("get_thing", vec![("id", "abc".to_string())]))
+        );
+        assert_eq!(
+            matched(http::Method::GET, "things/a%20b%2Fc"),
+            Some(("get_thing", vec![("id", "a b/c".to_string())]))
+        );
+        assert_eq!(
+            matched(http::Method::GET, "/things/abc/parts/wheel%20left.step"),
+            Some((
+                "get_thing_part",
+                vec![
+                    ("id", "abc".to_string()),
+                    ("part", "wheel left".to_string())
+                ]
+            ))
+        );
+
+        // The literal segments win over the parameters.
+        assert_eq!(
+            matched(http::Method::GET, "/things/stats"),
+            Some(("get_thing_stats", vec![]))
+        );
+    }
+
+    #[test]
+    fn test_encoded_params_match_back() {
+        assert_eq!(encode_param("abc-1.2_3~"), "abc-1.2_3~");
+        assert_eq!(encode_param("a b/c%d"), "a%20b%2Fc%25d");
+        assert_eq!(encode_param("café"), "caf%C3%A9");
+
+        // The url a function builds matches back to the same values.
+        for id in ["a b/c%d", "../etc", "a?b#c", "100%", "café", "a:b@c+d"] {
+            let url = reqwest::Url::parse(&format!(
+                "https://api.example.com{}",
+                "/things/{id}/parts/{part}.step"
+                    .replace("{id}", &encode_param(id))
+                    .replace("{part}", &encode_param(id))
+            ))
+            .unwrap();
+            assert_eq!(
+                matched(http::Method::GET, url.path()),
+                Some((
+                    "get_thing_part",
+                    vec![("id", id.to_string()), ("part", id.to_string())]
+                )),
+                "{}",
+                url
+            );
+        }
+    }
+
+    #[test]
+    fn test_match_routes_not_found() {
+        // Not a path of the routes.
+        assert_eq!(matched(http::Method::GET, "/widgets/abc"), None);
+        assert_eq!(matched(http::Method::GET, "/things/abc/parts"), None);
+        assert_eq!(
+            matched(http::Method::GET, "/things/abc/parts/wheel.stl"),
+            None
+        );
+        // A parameter is never empty.
+        assert_eq!(matched(http::Method::GET, "/things/"), None);
+        // Not a valid escape.
+        assert_eq!(matched(http::Method::GET, "/things/a%2"), None);
+        // Not the method of the routes.
+        assert_eq!(matched(http::Method::DELETE, "/things/abc"), None);
+    }
+}
@@ -4,10 +4,47 @@ use std::str::FromStr;
 
 use schemars::JsonSchema;
 
-/// A phone number.
-#[derive(Debug, Default, Clone, PartialEq, Hash, Eq)]
+/// A phone number. Two phone numbers are equal, and hash the same, when they have
+/// the same E.164 form and extension, however they were written.
+#[derive(Debug, Default, Clone)]
 pub struct PhoneNumber(pub Option<phonenumber::PhoneNumber>);
 
+impl PhoneNumber {
+    /// The number in its E.164 form, like `+15555555555`, or `None` if empty.
+    pub fn e164(&self) -> Option<String> {
+        self.0
+            .as_ref()
+            .map(|phone| phone.format().mode(phonenumber::Mode::E164).to_string())
+    }
+
+    /// What makes the number: its E.164 form, as the country code, the national
+    /// number and its leading zeros, and its extension.
+    fn key(&self) -> Option<(u16, u64, u8, Option<&str>)> {
+        self.0.as_ref().map(|phone| {
+            (
+                phone.code().value(),
+                phone.national().value(),
+                phone.national().zeros(),
+                phone.extension().map(|extension| extension.as_ref()),
+            )
+        })
+    }
+}
+
+impl PartialEq for PhoneNumber {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for PhoneNumber {}
+
+impl std::hash::Hash for PhoneNumber {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.key().hash(state)
+    }
+}
+
 impl From<phonenumber::PhoneNumber> for PhoneNumber {
     fn from(id: phonenumber::PhoneNumber) -> PhoneNumber {
         PhoneNumber(Some(id))
@@ -162,4 +199,50 @@ mod test {
         expected_str = "+49 30 12341234";
         assert_eq!(expected_str, serde_json::json!(phone_parsed));
     }
+
+    #[test]
+    fn test_phone_number_hash_by_e164() {
+        let set: std::collections::HashSet<PhoneNumber> = [
+            "+1-555-555-5555",
+            "555-555-5555",
+            "(555) 555 5555",
+            "+15555555555",
+        ]
+        .iter()
+        .map(|phone| phone.parse().unwrap())
+        .collect();
+        assert_eq!(set.len(), 1);
+        assert_eq!(
+            set.iter().next().unwrap().e164().as_deref(),
+            Some("+15555555555")
+        );
+
+        // Parsed as it is, without our cleanup, it is still the same number.
+        let raw = PhoneNumber(Some(phonenumber::parse(None, "+1 (555) 555-5555").unwrap()));
+        assert!(set.contains(&raw));
+
+        // Other numbers and no number are not.
+        assert!(!set.contains(&"+1 510-864-1234".parse().unwrap()));
+        assert!(!set.contains(&PhoneNumber(None)));
+        assert_eq!(PhoneNumber(None), "".parse().unwrap());
+    }
+
+    #[test]
+    fn test_phone_number_extension() {
+        let parse = |phone: &str| PhoneNumber(Some(phonenumber::parse(None, phone).unwrap()));
+        let number = parse("+1 (555) 555-5555");
+        let desk = parse("+1 555-555-5555 ext. 123");
+        assert_eq!(desk, parse("+1 (555) 555-5555 ext 123"));
+        assert_ne!(desk, number);
+        assert_ne!(desk, parse("+1 555-555-5555 ext. 456"));
+        assert_eq!(desk.e164(), number.e164());
+
+        let set: std::collections::HashSet<PhoneNumber> =
+            vec![number.clone(), desk.clone()].into_iter().collect();
+        assert_eq!(set.len(), 2);
+        assert!(set.contains(&parse("+1 555-555-5555 ext. 123")));
+
+        // The leading zeros are a part of the number.
+        assert_ne!(parse("+39 06 1234 5678"), parse("+39 6 1234 5678"));
+    }
 }
@@ -0,0 +1,227 @@
+//! Waiting for a job that reports its status on the resource it created, see the
+//! `_and_wait` functions.
+
+/// How often and for how long to poll a job.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PollOptions {
+    /// How long to wait before the first poll.
+    pub initial_interval: std::time::Duration,
+    /// The longest wait between two polls, the `Retry-After` of the server can
+    /// ask for more.
+    pub max_interval: std::time::Duration,
+    /// How much longer every wait is than the previous one.
+    pub multiplier: f64,
+    /// How long to wait for the job in total, from the request creating it.
+    pub timeout: std::time::Duration,
+}
+
+impl Default for PollOptions {
+    fn default() -> Self {
+        Self {
+            initial_interval: std::time::Duration::from_millis(500),
+            max_interval: std::time::Duration::from_secs(10),
+            multiplier: 2.0,
+            timeout: std::time::Duration::from_secs(300),
+        }
+    }
+}
+
+/// The waits between the polls of a job, growing until `max_interval`, and
+/// never past the `timeout`.
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    options: PollOptions,
+    interval: std::time::Duration,
+    started: std::time::Instant,
+}
+
+impl Backoff {
+    /// Start waiting now.
+    pub fn new(options: PollOptions) -> Self {
+        Self {
+            interval: options.initial_interval.min(options.max_interval),
+            options,
+            started: std::time::Instant::now(),
+        }
+    }
+
+    /// The wait before the next poll, the `Retry-After` of the last response if
+    /// it is longer. `None` once the timeout is over. The last wait is cut short
+    /// so we poll one last time right at the timeout.
+    pub fn next_delay(
+        &mut self,
+        retry_after: Option<std::time::Duration>,
+    ) -> Option<std::time::Duration> {
+        let left = self.options.timeout.checked_sub(self.started.elapsed())?;
+        if left.is_zero() {
+            return None;
+        }
+
+        let delay = match retry_after {
+            Some(retry_after) if retry_after > self.interval => retry_after,
+            _ => self.interval,
+        };
+        self.interval = self
+            .interval
+            .mul_f64(self.options.multiplier.max(1.0))
+            .min(self.options.max_interval);
+        Some(delay.min(left))
+    }
+}
+
+/// The wait the server asks for with `Retry-After`, only in seconds.
+pub fn retry_after(headers: &reqwest::header::HeaderMap) -> Option<std::time::Duration> {
+    let seconds = headers
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+    Some(std::time::Duration::from_secs(seconds))
+}
+
+/// Why waiting for a job failed.
+#[derive(Debug)]
+pub enum PollError<T> {
+    /// A request failed, creating the job or polling it.
+    Request(crate::types::error::Error),
+    /// The job failed.
+    Failed {
+        /// The status of the job.
+        status: String,
+        /// The job, as we last saw it.
+        resource: Box<T>,
+    },
+    /// The job was not done before the timeout.
+    TimedOut {
+        /// The last status of the job.
+        status: String,
+        /// The job, as we last saw it.
+        resource: Box<T>,
+    },
+}
+
+impl<T> PollError<T> {
+    /// The last status of the job, if we got it.
+    pub fn status(&self) -> Option<&str> {
+        match self {
+            PollError::Request(_) => None,
+            PollError::Failed { status, .. } | PollError::TimedOut { status, .. } => Some(status),
+        }
+    }
+
+    /// The job as we last saw it, if we got it.
+    pub fn resource(&self) -> Option<&T> {
+        match self {
+            PollError::Request(_) => None,
+            PollError::Failed { resource, .. } | PollError::TimedOut { resource, .. } => {
+                Some(resource)
+            }
+        }
+    }
+}
+
+impl<T> From<crate::types::error::Error> for PollError<T> {
+    fn from(err: crate::types::error::Error) -> Self {
+        PollError::Request(err)
+    }
+}
+
+impl<T> std::fmt::Display for PollError<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PollError::Request(err) => write!(f, "{}", err),
+            PollError::Failed { status, .. } => {
+                write!(f, "the job failed with the status `{}`", status)
+            }
+            PollError::TimedOut { status, .. } => {
+                write!(
+                    f,
+                    "the job was not done in time, its last status is `{}`",
+                    status
+                )
+            }
+        }
+    }
+}
+
+impl<T: std::fmt::Debug> std::error::Error for PollError<T> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PollError::Request(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{retry_after, Backoff, PollError, PollOptions};
+
+    #[test]
+    fn test_backoff() {
+        let mut backoff = Backoff::new(PollOptions {
+            initial_interval: Duration::from_secs(1),
+            max_interval: Duration::from_secs(5),
+            multiplier: 2.0,
+            timeout: Duration::from_secs(3600),
+        });
+        let delays = (0..5)
+            .map(|_| backoff.next_delay(None).unwrap().as_secs())
+            .collect::<Vec<_>>();
+        assert_eq!(delays, vec![1, 2, 4, 5, 5]);
+
+        // The server can ask for a longer wait, not a shorter one.
+        assert_eq!(
+            backoff.next_delay(Some(Duration::from_secs(30))),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            backoff.next_delay(Some(Duration::from_secs(1))),
+            Some(Duration::from_secs(5))
+        );
+    }
+
+    #[test]
+    fn test_backoff_timeout() {
+        let mut backoff = Backoff::new(PollOptions {
+            initial_interval: Duration::from_secs(1),
+            timeout: Duration::from_millis(100),
+            ..Default::default()
+        });
+        // The wait stops at the timeout.
+        assert!(backoff.next_delay(None).unwrap() <= Duration::from_millis(100));
+        std::thread::sleep(Duration::from_millis(100));
+        assert_eq!(backoff.next_delay(None), None);
+    }
+
+    #[test]
+    fn test_retry_after() {
+        let mut headers = reqwest::header::HeaderMap::new();
+        assert_eq!(retry_after(&headers), None);
+        headers.insert(reqwest::header::RETRY_AFTER, "3".parse().unwrap());
+        assert_eq!(retry_after(&headers), Some(Duration::from_secs(3)));
+        headers.insert(
+            reqwest::header::RETRY_AFTER,
+            "Wed, 21 Oct 2015 07:28:00 GMT".parse().unwrap(),
+        );
+        assert_eq!(retry_after(&headers), None);
+    }
+
+    #[test]
+    fn test_poll_error() {
+        let err = PollError::TimedOut {
+            status: "in_progress".to_string(),
+            resource: Box::new(7),
+        };
+        assert_eq!(err.status(), Some("in_progress"));
+        assert_eq!(err.resource(), Some(&7));
+        assert_eq!(
+            err.to_string(),
+            "the job was not done in time, its last status is `in_progress`"
+        );
+    }
+}
@@ -0,0 +1,105 @@
+//! The rate limit reported by the API.
+
+/// A snapshot of the rate limit, as reported by the headers of a response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    /// The number of requests allowed in the current window.
+    pub limit: u64,
+    /// The number of requests left in the current window.
+    pub remaining: u64,
+    /// When the current window resets, as the API reports it. Depending on the
+    /// API this is either seconds from now or a unix timestamp.
+    pub reset: Option<u64>,
+}
+
+impl RateLimit {
+    /// Parse the rate limit from the `{prefix}-Limit`, `{prefix}-Remaining` and
+    /// `{prefix}-Reset` headers. Returns `None` if the limit or remaining headers
+    /// are missing or cannot be parsed.
+    pub fn from_headers(headers: &reqwest::header::HeaderMap, prefix: &str) -> Option<Self> {
+        let get = |name: &str| -> Option<u64> {
+            headers
+                .get(format!("{}-{}", prefix, name))?
+                .to_str()
+                .ok()?
+                .trim()
+                .parse()
+                .ok()
+        };
+
+        Some(RateLimit {
+            limit: get("Limit")?,
+            remaining: get("Remaining")?,
+            reset: get("Reset"),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RateLimit;
+
+    fn headers(values: &[(&'static str, &'static str)]) -> reqwest::header::HeaderMap {
+        let mut headers = reqwest::header::HeaderMap::new();
+        for (name, value) in values {
+            headers.insert(*name, reqwest::header::HeaderValue::from_static(value));
+        }
+        headers
+    }
+
+    #[test]
+    fn test_rate_limit_from_headers() {
+        let rate_limit = RateLimit::from_headers(
+            &headers(&[
+                ("x-ratelimit-limit", "100"),
+                ("x-ratelimit-remaining", " 42"),
+                ("x-ratelimit-reset", "30"),
+            ]),
+            "X-RateLimit",
+        );
+        assert_eq!(
+            rate_limit,
+            Some(RateLimit {
+                limit: 100,
+                remaining: 42,
+                reset: Some(30),
+            })
+        );
+
+        // Other prefixes.
+        let rate_limit = RateLimit::from_headers(
+            &headers(&[("ratelimit-limit", "10"), ("ratelimit-remaining", "9")]),
+            "RateLimit",
+        );
+        assert_eq!(
+            rate_limit,
+            Some(RateLimit {
+                limit: 10,
+                remaining: 9,
+                reset: None,
+            })
+        );
+    }
+
+    #[test]
+    fn test_rate_limit_from_bad_headers() {
+        assert_eq!(RateLimit::from_headers(&headers(&[]), "X-RateLimit"), None);
+        assert_eq!(
+            RateLimit::from_headers(
+                &headers(&[
+                    ("x-ratelimit-limit", "lots"),
+                    ("x-ratelimit-remaining", "9")
+                ]),
+                "X-RateLimit",
+            ),
+            None
+        );
+        assert_eq!(
+            RateLimit::from_headers(
+                &headers(&[("x-ratelimit-limit", "10"), ("x-ratelimit-remaining", "9")]),
+                "RateLimit",
+            ),
+            None
+        );
+    }
+}
@@ -0,0 +1,467 @@
+//! Find the references to components that are not in the spec.
+//!
+//! A typo in a `$ref` would otherwise fail deep in the generation, with no idea of
+//! where the reference is. We check the whole spec first and report every dangling
+//! reference with where it is, like
+//! `missing component schema 'FileConverion' referenced from POST /file/conversion
+//! requestBody.content[application/json].properties.options`.
+
+use std::borrow::Borrow;
+
+use anyhow::Result;
+use openapiv3::ReferenceOr;
+
+use crate::types::exts::ReferenceOrExt;
+
+/// Make sure every `#/components/...` reference of the spec points to a component,
+/// the error lists all the ones that do not.
+pub fn check_references(spec: &openapiv3::OpenAPI) -> Result<()> {
+    let mut checker = Checker {
+        components: spec.components.as_ref(),
+        missing: Vec::new(),
+    };
+
+    for (path, item) in spec.paths.iter() {
+        let item = item.item()?;
+        for (method, op) in item.iter() {
+            let location = format!("{} {}", method.to_uppercase(), path);
+            for parameter in item.parameters.iter().chain(op.parameters.iter()) {
+                checker.parameter(parameter, &format!("{} parameters", location));
+            }
+            if let Some(request_body) = &op.request_body {
+                checker.request_body(request_body, &format!("{} requestBody", location));
+            }
+            if let Some(response) = &op.responses.default {
+                checker.response(response, &format!("{} responses[default]", location));
+            }
+            for (status, response) in &op.responses.responses {
+                checker.response(response, &format!("{} responses[{}]", location, status));
+            }
+        }
+    }
+
+    if let Some(components) = &spec.components {
+        for (name, schema) in &components.schemas {
+            checker.schema(schema, &format!("components.schemas.{}", name));
+        }
+        for (name, parameter) in &components.parameters {
+            checker.parameter(parameter, &format!("components.parameters.{}", name));
+        }
+        for (name, request_body) in &components.request_bodies {
+            checker.request_body(request_body, &format!("components.requestBodies.{}", name));
+        }
+        for (name, response) in &components.responses {
+            checker.response(response, &format!("components.responses.{}", name));
+        }
+        for (name, header) in &components.headers {
+            checker.header(header, &format!("components.headers.{}", name));
+        }
+    }
+
+    if !checker.missing.is_empty() {
+        anyhow::bail!("{}", checker.missing.join("\n"));
+    }
+
+    Ok(())
+}
+
+/// Walks the spec, keeping the dangling references it finds.
+struct Checker<'a> {
+    components: Option<&'a openapiv3::Components>,
+    missing: Vec<String>,
+}
+
+impl Checker<'_> {
+    /// Keep `reference` if it points to a component that does not exist.
+    /// References outside of the components are not ours to check.
+    fn reference(&mut self, reference: &str, location: &str) {
+        let Some((section, name)) = reference
+            .strip_prefix("#/components/")
+            .and_then(|reference| reference.split_once('/'))
+        else {
+            return;
+        };
+
+        let (kind, found) = match section {
+            "schemas" => ("schema", self.has(|c| c.schemas.contains_key(name))),
+            "parameters" => ("parameter", self.has(|c| c.parameters.contains_key(name))),
+            "requestBodies" => (
+                "request body",
+                self.has(|c| c.request_bodies.contains_key(name)),
+            ),
+            "responses" => ("response", self.has(|c| c.responses.contains_key(name))),
+            "headers" => ("header", self.has(|c| c.headers.contains_key(name))),
+            _ => return,
+        };
+        if !found {
+            self.missing.push(format!(
+                "missing component {} '{}' referenced from {}",
+                kind, name, location
+            ));
+        }
+    }
+
+    fn has(&self, f: impl Fn(&openapiv3::Components) -> bool) -> bool {
+        self.components.map(f).unwrap_or(false)
+    }
+
+    fn schema<S: Borrow<openapiv3::Schema>>(&mut self, schema: &ReferenceOr<S>, location: &str) {
+        let schema = match schema {
+            ReferenceOr::Reference { reference } => return self.reference(reference, location),
+            ReferenceOr::Item(schema) => schema.borrow(),
+        };
+
+        match &schema.schema_kind {
+            openapiv3::SchemaKind::Type(openapiv3::Type::Object(o)) => {
+                self.properties(&o.properties, location);
+                self.additional_properties(o.additional_properties.as_ref(), location);
+            }
+            openapiv3::SchemaKind::Type(openapiv3::Type::Array(a)) => {
+                if let Some(items) = &a.items {
+                    self.schema(items, &format!("{}.items", location));
+                }
+            }
+            openapiv3::SchemaKind::Type(_) => {}
+            openapiv3::SchemaKind::OneOf { one_of } => self.schemas(one_of, "oneOf", location),
+            openapiv3::SchemaKind::AllOf { all_of } => self.schemas(all_of, "allOf", location),
+            openapiv3::SchemaKind::AnyOf { any_of } => self.schemas(any_of, "anyOf", location),
+            openapiv3::SchemaKind::Not { not } => self.schema(not, &format!("{}.not", location)),
+            openapiv3::SchemaKind::Any(any) => {
+                self.properties(&any.properties, location);
+                self.additional_properties(any.additional_properties.as_ref(), location);
+                if let Some(items) = &any.items {
+                    self.schema(items, &format!("{}.items", location));
+                }
+                self.schemas(&any.one_of, "oneOf", location);
+                self.schemas(&any.all_of, "allOf", location);
+                self.schemas(&any.any_of, "anyOf", location);
+                if let Some(not) = &any.not {
+                    self.schema(not, &format!("{}.not", location));
+                }
+            }
+        }
+    }
+
+    fn schemas(&mut self, schemas: &[ReferenceOr<openapiv3::Schema>], key: &str, location: &str) {
+        for (i, schema) in schemas.iter().enumerate() {
+            self.schema(schema, &format!("{}.{}[{}]", location, key, i));
+        }
+    }
+
+    fn properties(
+        &mut self,
+        properties: &indexmap::IndexMap<String, ReferenceOr<Box<openapiv3::Schema>>>,
+        location: &str,
+    ) {
+        for (name, schema) in properties {
+            self.schema(schema, &format!("{}.properties.{}", location, name));
+        }
+    }
+
+    fn additional_properties(
+        &mut self,
+        additional_properties: Option<&openapiv3::AdditionalProperties>,
+        location: &str,
+    ) {
+        if let Some(openapiv3::AdditionalProperties::Schema(schema)) = additional_properties {
+            self.schema(schema, &format!("{}.additionalProperties", location));
+        }
+    }
+
+    fn content(&mut self, content: &openapiv3::Content, location: &str) {
+        for (media_type, content) in content {
+            if let Some(schema) = &content.schema {
+                self.schema(schema, &format!("{}.content[{}]", location, media_type));
+            }
+        }
+    }
+
+    fn parameter_format(&mut self, format: &openapiv3::ParameterSchemaOrContent, location: &str) {
+        match format {
+            openapiv3::ParameterSchemaOrContent::Schema(schema) => self.schema(schema, location),
+            openapiv3::ParameterSchemaOrContent::Content(content) => {
+                self.content(content, location)
+            }
+        }
+    }
+
+    fn parameter(&mut self, parameter: &ReferenceOr<openapiv3::Parameter>, location: &str) {
+        match parameter {
+            ReferenceOr::Reference { reference } => self.reference(reference, location),
+            ReferenceOr::Item(parameter) => {
+                let data = parameter.parameter_data_ref();
+                self.parameter_format(&data.format, &format!("{}[{}]", location, data.name));
+            }
+        }
+    }
+
+    fn request_body(&mut self, request_body: &ReferenceOr<openapiv3::RequestBody>, location: &str) {
+        match request_body {
+            ReferenceOr::Reference { reference } => self.reference(reference, location),
+            ReferenceOr::Item(request_body) => self.content(&request_body.content, location),
+        }
+    }
+
+    fn response(&mut self, response: &ReferenceOr<openapiv3::Response>, location: &str) {
+        match response {
+            ReferenceOr::Reference { reference } => self.reference(reference, location),
+            ReferenceOr::Item(response) => {
+                for (name, header) in &response.headers {
+                    self.header(header, &format!("{}.headers[{}]", location, name));
+                }
+                self.content(&response.content, location);
+            }
+        }
+    }
+
+    fn header(&mut self, header: &ReferenceOr<openapiv3::Header>, location: &str) {
+        match header {
+            ReferenceOr::Reference { reference } => self.reference(reference, location),
+            ReferenceOr::Item(header) => self.parameter_format(&header.format, location),
+        }
+    }
+}
+
+/// Point the references to the schemas renamed in `renamed`, from their old name to
+/// their new one, to the new name. Only the `$ref`s and the mappings of the
+/// discriminators change, a description or an example mentioning a reference stays
+/// as it is.
+pub fn rename_schema_references(
+    spec: &mut openapiv3::OpenAPI,
+    renamed: &std::collections::BTreeMap<String, String>,
+) {
+    let renamer = Renamer { renamed };
+
+    for item in spec.paths.paths.values_mut() {
+        if let ReferenceOr::Item(item) = item {
+            renamer.path_item(item);
+        }
+    }
+
+    if let Some(components) = &mut spec.components {
+        for schema in components.schemas.values_mut() {
+            renamer.schema(schema);
+        }
+        for parameter in components.parameters.values_mut() {
+            renamer.parameter(parameter);
+        }
+        for request_body in components.request_bodies.values_mut() {
+            renamer.request_body(request_body);
+        }
+        for response in components.responses.values_mut() {
+            renamer.response(response);
+        }
+        for header in components.headers.values_mut() {
+            renamer.header(header);
+        }
+        for callback in components.callbacks.values_mut() {
+            if let ReferenceOr::Item(callback) = callback {
+                renamer.callback(callback);
+            }
+        }
+    }
+}
+
+/// Walks the spec, renaming the references to schemas it finds.
+struct Renamer<'a> {
+    renamed: &'a std::collections::BTreeMap<String, String>,
+}
+
+impl Renamer<'_> {
+    /// Point `reference` to the new name of its schema, if it was renamed.
+    fn reference(&self, reference: &mut String) {
+        let new_name = reference
+            .strip_prefix("#/components/schemas/")
+            .and_then(|name| self.renamed.get(name));
+        if let Some(new_name) = new_name {
+            *reference = format!("#/components/schemas/{}", new_name);
+        }
+    }
+
+    fn path_item(&self, item: &mut openapiv3::PathItem) {
+        for parameter in &mut item.parameters {
+            self.parameter(parameter);
+        }
+        let ops = [
+            &mut item.get,
+            &mut item.put,
+            &mut item.post,
+            &mut item.delete,
+            &mut item.options,
+            &mut item.head,
+            &mut item.patch,
+            &mut item.trace,
+        ];
+        for op in IntoIterator::into_iter(ops).flatten() {
+            for parameter in &mut op.parameters {
+                self.parameter(parameter);
+            }
+            if let Some(request_body) = &mut op.request_body {
+                self.request_body(request_body);
+            }
+            if let Some(response) = &mut op.responses.default {
+                self.response(response);
+            }
+            for response in op.responses.responses.values_mut() {
+                self.response(response);
+            }
+            for callback in op.callbacks.values_mut() {
+                self.callback(callback);
+            }
+        }
+    }
+
+    fn callback(&self, callback: &mut openapiv3::Callback) {
+        for item in callback.values_mut() {
+            self.path_item(item);
+        }
+    }
+
+    fn schema<S: std::borrow::BorrowMut<openapiv3::Schema>>(&self, schema: &mut ReferenceOr<S>) {
+        let schema = match schema {
+            ReferenceOr::Reference { reference } => return self.reference(reference),
+            ReferenceOr::Item(schema) => schema.borrow_mut(),
+        };
+
+        // A mapping is either a reference or the name of a schema.
+        if let Some(discriminator) = &mut schema.schema_data.discriminator {
+            for target in discriminator.mapping.values_mut() {
+                match self.renamed.get(target.as_str()) {
+                    Some(new_name) => *target = new_name.to_string(),
+                    None => self.reference(target),
+                }
+            }
+        }
+
+        match &mut schema.schema_kind {
+            openapiv3::SchemaKind::Type(openapiv3::Type::Object(o)) => {
+                self.properties(&mut o.properties);
+                self.additional_properties(o.additional_properties.as_mut());
+            }
+            openapiv3::SchemaKind::Type(openapiv3::Type::Array(a)) => {
+                if let Some(items) = &mut a.items {
+                    self.schema(items);
+                }
+            }
+            openapiv3::SchemaKind::Type(_) => {}
+            openapiv3::SchemaKind::OneOf { one_of: schemas }
+            | openapiv3::SchemaKind::AllOf { all_of: schemas }
+            | openapiv3::SchemaKind::AnyOf { any_of: schemas } => self.schemas(schemas),
+            openapiv3::SchemaKind::Not { not } => self.schema(not),
+            openapiv3::SchemaKind::Any(any) => {
+                self.properties(&mut any.properties);
+                self.additional_properties(any.additional_properties.as_mut());
+                if let Some(items) = &mut any.items {
+                    self.schema(items);
+                }
+                self.schemas(&mut any.one_of);
+                self.schemas(&mut any.all_of);
+                self.schemas(&mut any.any_of);
+                if let Some(not) = &mut any.not {
+                    self.schema(not);
+                }
+            }
+        }
+    }
+
+    fn schemas(&self, schemas: &mut [ReferenceOr<openapiv3::Schema>]) {
+        for schema in schemas {
+            self.schema(schema);
+        }
+    }
+
+    fn properties(
+        &self,
+        properties: &mut indexmap::IndexMap<String, ReferenceOr<Box<openapiv3::Schema>>>,
+    ) {
+        for schema in properties.values_mut() {
+            self.schema(schema);
+        }
+    }
+
+    fn additional_properties(
+        &self,
+        additional_properties: Option<&mut openapiv3::AdditionalProperties>,
+    ) {
+        if let Some(openapiv3::AdditionalProperties::Schema(schema)) = additional_properties {
+            self.schema(schema.as_mut());
+        }
+    }
+
+    fn content(&self, content: &mut openapiv3::Content) {
+        for content in content.values_mut() {
+            if let Some(schema) = &mut content.schema {
+                self.schema(schema);
+            }
+        }
+    }
+
+    fn parameter_format(&self, format: &mut openapiv3::ParameterSchemaOrContent) {
+        match format {
+            openapiv3::ParameterSchemaOrContent::Schema(schema) => self.schema(schema),
+            openapiv3::ParameterSchemaOrContent::Content(content) => self.content(content),
+        }
+    }
+
+    fn parameter(&self, parameter: &mut ReferenceOr<openapiv3::Parameter>) {
+        if let ReferenceOr::Item(
+            openapiv3::Parameter::Query { parameter_data, .. }
+            | openapiv3::Parameter::Header { parameter_data, .. }
+            | openapiv3::Parameter::Path { parameter_data, .. }
+            | openapiv3::Parameter::Cookie { parameter_data, .. },
+        ) = parameter
+        {
+            self.parameter_format(&mut parameter_data.format);
+        }
+    }
+
+    fn request_body(&self, request_body: &mut ReferenceOr<openapiv3::RequestBody>) {
+        if let ReferenceOr::Item(request_body) = request_body {
+            self.content(&mut request_body.content);
+        }
+    }
+
+    fn response(&self, response: &mut ReferenceOr<openapiv3::Response>) {
+        if let ReferenceOr::Item(response) = response {
+            for header in response.headers.values_mut() {
+                self.header(header);
+            }
+            self.content(&mut response.content);
+        }
+    }
+
+    fn header(&self, header: &mut ReferenceOr<openapiv3::Header>) {
+        if let ReferenceOr::Item(header) = header {
+            self.parameter_format(&mut header.format);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::check_references;
+
+    #[test]
+    fn test_check_references() {
+        let spec =
+            crate::load_json_spec(include_str!("../../tests/types/input/dangling-refs.json"))
+                .unwrap();
+
+        assert_eq!(
+            check_references(&spec).unwrap_err().to_string(),
+            "missing component schema 'FileConverion' referenced from POST /file/conversion \
+             requestBody.content[application/json].properties.options\n\
+             missing component schema 'Unit' referenced from components.schemas.Conversion.\
+             properties.output_units.items"
+        );
+
+        // The specs we test with have none.
+        for spec in [
+            include_str!("../../tests/mock.json"),
+            include_str!("../../tests/oxide.json"),
+            include_str!("../../../spec.json"),
+        ] {
+            check_references(&crate::load_json_spec(spec).unwrap()).unwrap();
+        }
+    }
+}
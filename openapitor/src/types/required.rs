@@ -0,0 +1,210 @@
+//! Find the `required` entries of the objects that are not one of their properties.
+//!
+//! A property removed from `properties` and left in `required` used to give a
+//! struct without the field, so we took the payloads the server rejects, and a
+//! `oneOf` tagged with it failed late with no idea of where. We check every schema
+//! before we render it, and report every orphaned entry with where it is in the
+//! schema, like `Thing.properties.parts.items`.
+//!
+//! The members of an `allOf` can require the properties of one another, and an
+//! object without properties or with `additionalProperties` can have any, so we
+//! leave them out.
+
+use anyhow::Result;
+use openapiv3::{ReferenceOr, Schema, SchemaKind};
+
+/// Make sure the objects of `schema` only require their own properties, the error
+/// lists every entry that is not. `location` is where the schema is, like its name.
+pub fn check_required(schema: &Schema, location: &str) -> Result<()> {
+    let mut orphaned = Vec::new();
+    walk(schema, location, &mut orphaned);
+    if !orphaned.is_empty() {
+        anyhow::bail!("{}", orphaned.join("\n"));
+    }
+
+    Ok(())
+}
+
+fn walk(schema: &Schema, location: &str, orphaned: &mut Vec<String>) {
+    match &schema.schema_kind {
+        SchemaKind::Type(openapiv3::Type::Object(o)) => {
+            check_object(
+                &o.properties,
+                &o.required,
+                o.additional_properties.as_ref(),
+                location,
+                orphaned,
+            );
+            properties(&o.properties, location, orphaned);
+        }
+        SchemaKind::Type(openapiv3::Type::Array(a)) => {
+            if let Some(items) = &a.items {
+                item(items, &format!("{}.items", location), orphaned);
+            }
+        }
+        SchemaKind::Type(_) => {}
+        SchemaKind::OneOf { one_of } => items(one_of, "oneOf", location, orphaned),
+        SchemaKind::AnyOf { any_of } => items(any_of, "anyOf", location, orphaned),
+        SchemaKind::AllOf { all_of } => {
+            // The members themselves are not checked, only what they nest.
+            for (i, member) in all_of.iter().enumerate() {
+                if let ReferenceOr::Item(member) = member {
+                    if let SchemaKind::Type(openapiv3::Type::Object(o)) = &member.schema_kind {
+                        let location = format!("{}.allOf[{}]", location, i);
+                        properties(&o.properties, &location, orphaned);
+                    }
+                }
+            }
+        }
+        SchemaKind::Not { not } => item(not, &format!("{}.not", location), orphaned),
+        SchemaKind::Any(any) => {
+            if any.all_of.is_empty() {
+                check_object(
+                    &any.properties,
+                    &any.required,
+                    any.additional_properties.as_ref(),
+                    location,
+                    orphaned,
+                );
+            }
+            properties(&any.properties, location, orphaned);
+            if let Some(items) = &any.items {
+                item(items, &format!("{}.items", location), orphaned);
+            }
+            self::items(&any.one_of, "oneOf", location, orphaned);
+            self::items(&any.any_of, "anyOf", location, orphaned);
+        }
+    }
+}
+
+/// Keep the entries of `required` that are not in `properties`.
+fn check_object(
+    properties: &indexmap::IndexMap<String, ReferenceOr<Box<Schema>>>,
+    required: &[String],
+    additional_properties: Option<&openapiv3::AdditionalProperties>,
+    location: &str,
+    orphaned: &mut Vec<String>,
+) {
+    if properties.is_empty() || additional_properties.is_some() {
+        return;
+    }
+
+    for name in required {
+        if !properties.contains_key(name) {
+            orphaned.push(format!(
+                "{} requires `{}`, which is not one of its properties: {}",
+                location,
+                name,
+                properties
+                    .keys()
+                    .map(|p| format!("`{}`", p))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+    }
+}
+
+fn properties(
+    properties: &indexmap::IndexMap<String, ReferenceOr<Box<Schema>>>,
+    location: &str,
+    orphaned: &mut Vec<String>,
+) {
+    for (name, schema) in properties {
+        item(
+            schema,
+            &format!("{}.properties.{}", location, name),
+            orphaned,
+        );
+    }
+}
+
+fn items(schemas: &[ReferenceOr<Schema>], key: &str, location: &str, orphaned: &mut Vec<String>) {
+    for (i, schema) in schemas.iter().enumerate() {
+        item(schema, &format!("{}.{}[{}]", location, key, i), orphaned);
+    }
+}
+
+/// The references are checked as components of their own.
+fn item<S: std::borrow::Borrow<Schema>>(
+    schema: &ReferenceOr<S>,
+    location: &str,
+    orphaned: &mut Vec<String>,
+) {
+    if let ReferenceOr::Item(schema) = schema {
+        walk(schema.borrow(), location, orphaned);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::check_required;
+    use crate::types::exts::ReferenceOrExt;
+
+    fn load() -> openapiv3::OpenAPI {
+        crate::load_json_spec(include_str!(
+            "../../tests/types/input/orphaned-required.json"
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_check_required() {
+        let spec = load();
+        let schemas = &spec.components.as_ref().unwrap().schemas;
+        let check = |name: &str| {
+            let schema = schemas[name]
+                .get_schema_from_reference(&spec, true)
+                .unwrap();
+            check_required(&schema, name)
+        };
+
+        assert_eq!(
+            check("Widget").unwrap_err().to_string(),
+            "Widget requires `size`, which is not one of its properties: `id`, `name`, \
+             `parts`\nWidget.properties.parts.items requires `count`, which is not one of its \
+             properties: `name`"
+        );
+        // The members of an `allOf` require the properties of one another.
+        check("Gadget").unwrap();
+        check("Circle").unwrap();
+
+        // The specs we test with have none.
+        for spec in [
+            include_str!("../../tests/mock.json"),
+            include_str!("../../tests/oxide.json"),
+            include_str!("../../../spec.json"),
+        ] {
+            let spec = crate::load_json_spec(spec).unwrap();
+            for (name, schema) in &spec.components.as_ref().unwrap().schemas {
+                let schema = schema.get_schema_from_reference(&spec, true).unwrap();
+                check_required(&schema, name).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn test_orphaned_required_failures() {
+        let mut failures = crate::failures::Failures::default();
+        crate::types::generate_types_collecting(&load(), Default::default(), &mut failures)
+            .unwrap();
+
+        let errors = failures
+            .failures
+            .iter()
+            .map(|failure| format!("{}: {}", failure.item, failure.error))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            errors,
+            vec![
+                "`#/components/schemas/Widget`: Widget requires `size`, which is not one of \
+                 its properties: `id`, `name`, `parts`\nWidget.properties.parts.items requires \
+                 `count`, which is not one of its properties: `name`",
+                "`#/components/schemas/Shape`: the variant `Square` of `Shape` has no property \
+                 `kind`, the tag of the other variants, its properties are `side`",
+            ]
+        );
+    }
+}
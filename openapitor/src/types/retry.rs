@@ -0,0 +1,91 @@
+//! How the client retries failed requests.
+
+/// How the client retries a request that failed with a transient error: a failed
+/// connection, a timeout, a server error, a `408 Request Timeout` or a
+/// `429 Too Many Requests`. Only requests that can be cloned are retried, a request
+/// with a streamed body is sent once.
+///
+/// The wait before each retry grows exponentially from `min_backoff` to
+/// `max_backoff`, with a random jitter that never goes below `min_backoff`.
+///
+/// Without the `retry` feature the client keeps its config, but sends every
+/// request once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryConfig {
+    /// The number of retries after the first attempt, `0` never retries.
+    pub max_retries: u32,
+    /// The shortest wait before a retry.
+    pub min_backoff: std::time::Duration,
+    /// The longest wait before a retry, it must not be shorter than `min_backoff`.
+    pub max_backoff: std::time::Duration,
+    /// Whether to retry a request that timed out before the server answered.
+    pub retry_on_timeout: bool,
+}
+
+impl Default for RetryConfig {
+    /// Retry 3 times, waiting between 1 second and 30 minutes.
+    fn default() -> Self {
+        RetryConfig {
+            max_retries: 3,
+            min_backoff: std::time::Duration::from_secs(1),
+            max_backoff: std::time::Duration::from_secs(30 * 60),
+            retry_on_timeout: true,
+        }
+    }
+}
+
+#[cfg(feature = "retry")]
+impl RetryConfig {
+    /// Wrap `client` in the middleware of the API client, tracing and retries.
+    ///
+    /// Panics if `max_backoff` is shorter than `min_backoff`.
+    pub(crate) fn middleware(
+        &self,
+        client: reqwest::Client,
+    ) -> reqwest_middleware::ClientWithMiddleware {
+        let retry_policy = reqwest_retry::policies::ExponentialBackoff::builder()
+            .retry_bounds(self.min_backoff, self.max_backoff)
+            .jitter(reqwest_retry::Jitter::Bounded)
+            .build_with_max_retries(self.max_retries);
+        let strategy = Strategy {
+            retry_on_timeout: self.retry_on_timeout,
+        };
+
+        reqwest_middleware::ClientBuilder::new(client)
+            // Trace HTTP requests. See the tracing crate to make use of these traces.
+            .with(reqwest_tracing::TracingMiddleware::default())
+            // Retry failed requests.
+            .with(reqwest_conditional_middleware::ConditionalMiddleware::new(
+                reqwest_retry::RetryTransientMiddleware::new_with_policy_and_strategy(
+                    retry_policy,
+                    strategy,
+                ),
+                |req: &reqwest::Request| req.try_clone().is_some(),
+            ))
+            .build()
+    }
+}
+
+/// The default strategy of `reqwest_retry`, except that timeouts can be fatal.
+#[cfg(feature = "retry")]
+struct Strategy {
+    retry_on_timeout: bool,
+}
+
+#[cfg(feature = "retry")]
+impl reqwest_retry::RetryableStrategy for Strategy {
+    fn handle(
+        &self,
+        res: &Result<reqwest::Response, reqwest_middleware::Error>,
+    ) -> Option<reqwest_retry::Retryable> {
+        match res {
+            Ok(response) => reqwest_retry::default_on_request_success(response),
+            Err(reqwest_middleware::Error::Reqwest(err))
+                if err.is_timeout() && !self.retry_on_timeout =>
+            {
+                Some(reqwest_retry::Retryable::Fatal)
+            }
+            Err(err) => reqwest_retry::default_on_request_failure(err),
+        }
+    }
+}
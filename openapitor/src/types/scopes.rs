@@ -0,0 +1,259 @@
+//! The scopes of the OAuth 2.0 security schemes of the spec, as a `Scope` enum, so
+//! a typo in a scope does not compile instead of showing up on a consent screen.
+
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use quote::{format_ident, quote};
+
+/// The scopes the OAuth 2.0 security schemes of the spec declare in any of their
+/// flows, with their descriptions.
+pub fn get_oauth2_scopes(spec: &openapiv3::OpenAPI) -> Result<BTreeMap<String, String>> {
+    let mut scopes = BTreeMap::new();
+    for flows in get_oauth2_flows(spec)?.values() {
+        // The scopes of the flows are private, so we read them from the json.
+        let flows = serde_json::to_value(flows)?;
+        let Some(flows) = flows.as_object() else {
+            continue;
+        };
+        for flow in flows.values() {
+            let Some(flow_scopes) = flow.get("scopes").and_then(|s| s.as_object()) else {
+                continue;
+            };
+            for (scope, description) in flow_scopes {
+                if scope.is_empty() {
+                    continue;
+                }
+                let description = description.as_str().unwrap_or_default().to_string();
+                let existing = scopes.entry(scope.to_string()).or_insert_with(String::new);
+                if existing.is_empty() {
+                    *existing = description;
+                }
+            }
+        }
+    }
+
+    Ok(scopes)
+}
+
+/// The flows of the OAuth 2.0 security schemes of the spec, by the name of the
+/// scheme.
+fn get_oauth2_flows(spec: &openapiv3::OpenAPI) -> Result<BTreeMap<String, openapiv3::OAuth2Flows>> {
+    let mut flows = BTreeMap::new();
+    let Some(components) = &spec.components else {
+        return Ok(flows);
+    };
+    for (name, scheme) in &components.security_schemes {
+        // We only know the schemes of the spec itself.
+        let openapiv3::ReferenceOr::Item(scheme) = scheme else {
+            continue;
+        };
+        if let openapiv3::SecurityScheme::OAuth2 { flows: f, .. } = scheme {
+            flows.insert(name.to_string(), f.clone());
+        }
+    }
+
+    Ok(flows)
+}
+
+/// The scopes an operation requires, one list for every alternative requirement
+/// of the operation, or of the spec if the operation has none of its own. Only the
+/// requirements of OAuth 2.0 schemes with scopes are listed.
+pub fn get_required_scopes(
+    spec: &openapiv3::OpenAPI,
+    op: &openapiv3::Operation,
+) -> Result<Vec<Vec<String>>> {
+    let flows = get_oauth2_flows(spec)?;
+    let requirements = op.security.as_ref().or(spec.security.as_ref());
+    let mut required = Vec::new();
+    for requirement in requirements.into_iter().flatten() {
+        let scopes = requirement
+            .iter()
+            .filter(|(scheme, _)| flows.contains_key(*scheme))
+            .flat_map(|(_, scopes)| scopes.iter().cloned())
+            .collect::<Vec<_>>();
+        if !scopes.is_empty() {
+            required.push(scopes);
+        }
+    }
+
+    Ok(required)
+}
+
+/// The name of the variant of `Scope` for every scope, with a number when two
+/// scopes have the same name.
+fn get_scope_variants(scopes: &BTreeMap<String, String>) -> BTreeMap<String, proc_macro2::Ident> {
+    let mut taken = std::collections::HashSet::new();
+    let mut variants = BTreeMap::new();
+    for scope in scopes.keys() {
+        let name = match crate::types::proper_name(scope) {
+            name if name.is_empty() => "Scope".to_string(),
+            name => name,
+        };
+        let mut variant = name.clone();
+        let mut n = 2;
+        while !taken.insert(variant.clone()) {
+            variant = format!("{}{}", name, n);
+            n += 1;
+        }
+        variants.insert(scope.to_string(), format_ident!("{}", variant));
+    }
+
+    variants
+}
+
+/// Render the `scope` module, with the `Scope` enum and the `Scopes` of a token,
+/// there is none if the spec declares no scopes.
+pub fn get_scope_mod(spec: &openapiv3::OpenAPI) -> Result<proc_macro2::TokenStream> {
+    let scopes = get_oauth2_scopes(spec)?;
+    if scopes.is_empty() {
+        return Ok(quote!());
+    }
+
+    let variants = get_scope_variants(&scopes);
+    let mut values = Vec::new();
+    let mut as_str = Vec::new();
+    let mut from_str = Vec::new();
+    for (scope, description) in &scopes {
+        let variant = &variants[scope];
+        let docs = if description.trim().is_empty() {
+            format!("The `{}` scope.", scope)
+        } else {
+            description.trim().to_string()
+        };
+        values.push(quote! {
+            #[doc = #docs]
+            #[serde(rename = #scope)]
+            #variant
+        });
+        as_str.push(quote!(Scope::#variant => #scope));
+        from_str.push(quote!(#scope => Ok(Scope::#variant)));
+    }
+    let all = scopes.keys();
+
+    Ok(quote! {
+        pub mod scope {
+            //! The scopes of the OAuth 2.0 access tokens of the API.
+
+            /// A scope of an OAuth 2.0 access token, it displays as the scope the API
+            /// expects.
+            #[derive(serde::Serialize, serde::Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy, schemars::JsonSchema)]
+            pub enum Scope {
+                #(#values),*
+            }
+
+            impl Scope {
+                /// The scope, as the API expects it.
+                pub fn as_str(&self) -> &'static str {
+                    match self {
+                        #(#as_str),*
+                    }
+                }
+            }
+
+            impl std::fmt::Display for Scope {
+                fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                    f.write_str(self.as_str())
+                }
+            }
+
+            impl std::str::FromStr for Scope {
+                type Err = crate::types::suggest::ParseEnumError;
+
+                fn from_str(s: &str) -> Result<Self, Self::Err> {
+                    match s {
+                        #(#from_str,)*
+                        _ => Err(crate::types::suggest::ParseEnumError::new("Scope", s, &[#(#all),*])),
+                    }
+                }
+            }
+
+            /// The scopes of an OAuth 2.0 access token, they display separated by
+            /// spaces, as OAuth 2.0 sends them.
+            #[derive(PartialEq, Eq, Debug, Clone, Default)]
+            pub struct Scopes(pub Vec<Scope>);
+
+            impl std::fmt::Display for Scopes {
+                fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                    for (i, scope) in self.0.iter().enumerate() {
+                        if i > 0 {
+                            f.write_str(" ")?;
+                        }
+                        f.write_str(scope.as_str())?;
+                    }
+                    Ok(())
+                }
+            }
+
+            impl FromIterator<Scope> for Scopes {
+                fn from_iter<I: IntoIterator<Item = Scope>>(iter: I) -> Self {
+                    Scopes(iter.into_iter().collect())
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_scope_mod() {
+        let spec = crate::load_json_spec(include_str!("../../tests/types/input/oauth-scopes.json"))
+            .unwrap();
+        let mut type_space = crate::types::TypeSpace {
+            types: indexmap::map::IndexMap::new(),
+            spec: spec.clone(),
+            rendered: quote::quote!(),
+            opts: Default::default(),
+        };
+        let (files, _, _) =
+            crate::functions::generate_files(&mut type_space, &Default::default()).unwrap();
+        let scope_mod = crate::types::get_text_fmt(&super::get_scope_mod(&spec).unwrap()).unwrap();
+        let users = rustfmt_wrapper::rustfmt(files["users"].to_string()).unwrap();
+        let files = rustfmt_wrapper::rustfmt(files["files"].to_string()).unwrap();
+        expectorate::assert_contents(
+            "tests/types/oauth-scopes.rs.gen",
+            &format!("{}\n{}\n{}", scope_mod, users, files),
+        );
+
+        // The operations list the scopes they need, of their own or of the spec.
+        assert!(users.contains("**Scopes:** `read:users`."), "{}", users);
+        assert!(
+            files.contains("**Scopes:** `write:files`, `read:files`."),
+            "{}",
+            files
+        );
+
+        // A spec without scopes has no module for them.
+        let spec = crate::load_json_spec(include_str!("../../tests/mock.json")).unwrap();
+        assert!(super::get_scope_mod(&spec).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_scope_variants() {
+        let scopes = [
+            ("read:users", ""),
+            ("read_users", ""),
+            ("cards:read_vault", ""),
+            ("2fa", ""),
+        ]
+        .iter()
+        .map(|(scope, description)| (scope.to_string(), description.to_string()))
+        .collect();
+        let variants = super::get_scope_variants(&scopes)
+            .into_iter()
+            .map(|(scope, variant)| (scope, variant.to_string()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            variants,
+            vec![
+                ("2fa".to_string(), "Twofa".to_string()),
+                ("cards:read_vault".to_string(), "CardsReadVault".to_string()),
+                ("read:users".to_string(), "ReadUsers".to_string()),
+                ("read_users".to_string(), "ReadUsers2".to_string()),
+            ]
+        );
+    }
+}
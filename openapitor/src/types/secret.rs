@@ -0,0 +1,174 @@
+//! The secrets of the API, the passwords and the tokens, kept out of the logs and
+//! wiped from memory when they are dropped.
+
+use std::cell::Cell;
+
+thread_local! {
+    /// If the secrets serialize as `***` on this thread, see `redacted`.
+    static REDACT: Cell<bool> = const { Cell::new(false) };
+}
+
+/// What a secret shows instead of its value.
+pub const REDACTED: &str = "***";
+
+/// A value that can be wiped from memory.
+pub trait Wipe {
+    /// Overwrite the value with zeroes.
+    fn wipe(&mut self);
+}
+
+#[cfg(feature = "zeroize")]
+impl<T: zeroize::Zeroize> Wipe for T {
+    fn wipe(&mut self) {
+        self.zeroize();
+    }
+}
+
+/// Without the `zeroize` feature, the secrets are only redacted.
+#[cfg(not(feature = "zeroize"))]
+impl Wipe for String {
+    fn wipe(&mut self) {}
+}
+
+/// A secret, a field of `format: password` or `writeOnly`. It derefs to its value
+/// and is sent as it is, but its `Debug` is `***`, the `Display` and the tables of
+/// the types show `***` too, and it is wiped from memory when it is dropped, with
+/// the `zeroize` feature.
+#[derive(Clone, PartialEq, Eq, Hash, Default)]
+pub struct Secret<T: Wipe>(T);
+
+impl<T: Wipe> Secret<T> {
+    /// Keep `value` as a secret.
+    pub fn new(value: T) -> Self {
+        Secret(value)
+    }
+
+    /// The value of the secret, the same as through `Deref`, but easier to find
+    /// when looking for where the secrets are used.
+    pub fn expose(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: Wipe> From<T> for Secret<T> {
+    fn from(value: T) -> Self {
+        Secret(value)
+    }
+}
+
+impl<T: Wipe> std::ops::Deref for Secret<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: Wipe> Drop for Secret<T> {
+    fn drop(&mut self) {
+        self.0.wipe();
+    }
+}
+
+impl<T: Wipe> std::fmt::Debug for Secret<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl<T: Wipe + serde::Serialize> serde::Serialize for Secret<T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if REDACT.with(|redact| redact.get()) {
+            serializer.serialize_str(REDACTED)
+        } else {
+            self.0.serialize(serializer)
+        }
+    }
+}
+
+impl<'de, T: Wipe + serde::Deserialize<'de>> serde::Deserialize<'de> for Secret<T> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        T::deserialize(deserializer).map(Secret)
+    }
+}
+
+impl<T: Wipe + schemars::JsonSchema> schemars::JsonSchema for Secret<T> {
+    fn schema_name() -> String {
+        T::schema_name()
+    }
+
+    fn json_schema(gen: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        T::json_schema(gen)
+    }
+
+    fn is_referenceable() -> bool {
+        T::is_referenceable()
+    }
+}
+
+/// Puts back whether the secrets were redacted, when `redacted` is done.
+struct RedactGuard(bool);
+
+impl Drop for RedactGuard {
+    fn drop(&mut self) {
+        REDACT.with(|redact| redact.set(self.0));
+    }
+}
+
+/// Run `f` with the secrets serializing as `***`, as the `Display` of the types
+/// does.
+pub fn redacted<R>(f: impl FnOnce() -> R) -> R {
+    let _guard = RedactGuard(REDACT.with(|redact| redact.replace(true)));
+    f()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{redacted, Secret};
+
+    #[derive(serde::Serialize, serde::Deserialize, Debug)]
+    struct Login {
+        user: String,
+        password: Secret<String>,
+    }
+
+    #[test]
+    fn test_secret_debug_is_redacted() {
+        let login = Login {
+            user: "ada".to_string(),
+            password: "hunter2".to_string().into(),
+        };
+        assert_eq!(
+            format!("{:?}", login),
+            r#"Login { user: "ada", password: *** }"#
+        );
+        assert_eq!(login.password.as_str(), "hunter2");
+    }
+
+    #[test]
+    fn test_secret_serializes_its_value() {
+        let login: Login = serde_json::from_str(r#"{"user":"ada","password":"hunter2"}"#).unwrap();
+        assert_eq!(*login.password, "hunter2");
+        assert_eq!(
+            serde_json::to_string(&login).unwrap(),
+            r#"{"user":"ada","password":"hunter2"}"#
+        );
+
+        // Unless we redact it, and only then.
+        assert_eq!(
+            redacted(|| serde_json::to_string(&login)).unwrap(),
+            r#"{"user":"ada","password":"***"}"#
+        );
+        assert_eq!(
+            serde_json::to_string(&login).unwrap(),
+            r#"{"user":"ada","password":"hunter2"}"#
+        );
+    }
+
+    #[test]
+    fn test_secret_equality() {
+        assert_eq!(Secret::new("a".to_string()), Secret::from("a".to_string()));
+        assert_ne!(Secret::new("a".to_string()), Secret::new("b".to_string()));
+        assert_eq!(Secret::new("a".to_string()).expose(), "a");
+    }
+}
@@ -0,0 +1,125 @@
+//! The variables of the templated URL of the server of the API, like the
+//! `{region}` of `https://{region}.api.example.com`.
+
+/// A variable of the URL of the server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ServerVariable {
+    /// The name of the variable, it is `{name}` in the URL.
+    pub name: &'static str,
+    /// The value of the variable when it is not set.
+    pub default: &'static str,
+    /// The values the variable can take, any value if there are none.
+    pub allowed: &'static [&'static str],
+}
+
+/// An error setting a variable of the URL of the server.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ServerVariableError {
+    /// The URL of the server has no such variable.
+    #[error("the URL of the server has no variable `{0}`")]
+    Unknown(String),
+    /// The value is not one of the values the variable can take.
+    #[error("`{value}` is not a value of the server variable `{name}`, use one of: {allowed}")]
+    NotAllowed {
+        /// The name of the variable.
+        name: String,
+        /// The value that was set.
+        value: String,
+        /// The values the variable can take.
+        allowed: String,
+    },
+    /// The base URL of the client is not the URL of the server, it was set with
+    /// `set_base_url`.
+    #[error("the base URL `{0}` is not the URL of the server, it has no variables to set")]
+    CustomBaseUrl(String),
+}
+
+/// Check that `value` is a value the variable `name` can take.
+pub fn check(
+    variables: &[ServerVariable],
+    name: &str,
+    value: &str,
+) -> Result<(), ServerVariableError> {
+    let variable = variables
+        .iter()
+        .find(|variable| variable.name == name)
+        .ok_or_else(|| ServerVariableError::Unknown(name.to_string()))?;
+    if variable.allowed.is_empty() || variable.allowed.contains(&value) {
+        Ok(())
+    } else {
+        Err(ServerVariableError::NotAllowed {
+            name: name.to_string(),
+            value: value.to_string(),
+            allowed: variable.allowed.join(", "),
+        })
+    }
+}
+
+/// Expand the variables of `url` with their `values`, the variables that are not
+/// set get their default. The URL has no trailing slash.
+pub fn expand(
+    url: &str,
+    variables: &[ServerVariable],
+    values: &std::collections::BTreeMap<String, String>,
+) -> String {
+    let mut url = url.to_string();
+    for (name, value) in values {
+        url = url.replace(&format!("{{{}}}", name), value);
+    }
+    for variable in variables {
+        url = url.replace(&format!("{{{}}}", variable.name), variable.default);
+    }
+    url.trim_end_matches('/').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check, expand, ServerVariable, ServerVariableError};
+
+    const VARIABLES: &[ServerVariable] = &[
+        ServerVariable {
+            name: "region",
+            default: "us-east",
+            allowed: &["us-east", "eu-west"],
+        },
+        ServerVariable {
+            name: "basePath",
+            default: "v1",
+            allowed: &[],
+        },
+    ];
+
+    #[test]
+    fn test_expand() {
+        let url = "https://{region}.api.example.com/{basePath}/";
+        let mut values = std::collections::BTreeMap::new();
+        assert_eq!(
+            expand(url, VARIABLES, &values),
+            "https://us-east.api.example.com/v1"
+        );
+
+        values.insert("region".to_string(), "eu-west".to_string());
+        assert_eq!(
+            expand(url, VARIABLES, &values),
+            "https://eu-west.api.example.com/v1"
+        );
+    }
+
+    #[test]
+    fn test_check() {
+        assert_eq!(check(VARIABLES, "region", "eu-west"), Ok(()));
+        assert_eq!(check(VARIABLES, "basePath", "v2"), Ok(()));
+        assert_eq!(
+            check(VARIABLES, "region", "mars"),
+            Err(ServerVariableError::NotAllowed {
+                name: "region".to_string(),
+                value: "mars".to_string(),
+                allowed: "us-east, eu-west".to_string(),
+            })
+        );
+        assert_eq!(
+            check(VARIABLES, "zone", "a"),
+            Err(ServerVariableError::Unknown("zone".to_string()))
+        );
+    }
+}
@@ -0,0 +1,143 @@
+//! A websocket session that checks the order of the commands before sending them.
+
+use crate::types::websocket::{Message, WebSocket, WebSocketError};
+
+/// The order a websocket protocol accepts its commands in, generated from the
+/// session config.
+pub trait Order {
+    /// The messages sent over the websocket.
+    type Request: serde::Serialize;
+    /// The key of the commands in the messages, at any depth.
+    const COMMAND_FIELD: &'static str;
+    /// The key of the tag naming a command.
+    const TAG: &'static str;
+    /// The commands that must come before every other command.
+    const BEFORE_ALL: &'static [&'static str];
+    /// The commands that must come before a command, by the tag of the command.
+    const PREREQUISITES: &'static [(&'static str, &'static [&'static str])];
+}
+
+/// An error from a session.
+#[derive(Debug, thiserror::Error)]
+pub enum SessionError {
+    /// A command came before the commands that must come before it, the message
+    /// was not sent.
+    #[error("cannot send `{command}` before {missing:?}")]
+    OutOfOrder {
+        /// The command.
+        command: String,
+        /// The commands that must come before it and were not sent yet.
+        missing: Vec<&'static str>,
+    },
+    /// The message could not be serialized.
+    #[error(transparent)]
+    Serialize(#[from] serde_json::Error),
+    /// The websocket failed.
+    #[error(transparent)]
+    WebSocket(#[from] WebSocketError),
+}
+
+/// A websocket connection that refuses to send a command before the commands that
+/// must come before it. The checks are local, a refused message never reaches the
+/// server.
+///
+/// The connection itself stays available with `websocket`, to receive messages or
+/// to send messages without any check.
+pub struct Session<O: Order> {
+    websocket: WebSocket,
+    sent: std::collections::BTreeSet<String>,
+    order: std::marker::PhantomData<O>,
+}
+
+impl<O: Order> std::fmt::Debug for Session<O> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Session")
+            .field("websocket", &self.websocket)
+            .field("sent", &self.sent)
+            .finish()
+    }
+}
+
+impl<O: Order> Session<O> {
+    /// Start a session on a connection, no command has been sent yet.
+    pub fn new(websocket: WebSocket) -> Self {
+        Self {
+            websocket,
+            sent: Default::default(),
+            order: std::marker::PhantomData,
+        }
+    }
+
+    /// Send a message, unless one of its commands comes before the commands that
+    /// must come before it. The commands of a message count in order, so a batch
+    /// can start with the commands the rest of it needs.
+    pub async fn send(&mut self, request: &O::Request) -> Result<(), SessionError> {
+        let message = serde_json::to_value(request)?;
+        let mut commands = Vec::new();
+        collect_commands::<O>(&message, &mut commands);
+
+        let mut sent = self.sent.clone();
+        for command in commands {
+            let missing: Vec<&'static str> = O::PREREQUISITES
+                .iter()
+                .filter(|(c, _)| *c == command)
+                .flat_map(|(_, prerequisites)| prerequisites.iter())
+                .chain(
+                    O::BEFORE_ALL
+                        .iter()
+                        .filter(|_| !O::BEFORE_ALL.contains(&command.as_str())),
+                )
+                .filter(|prerequisite| !sent.contains(**prerequisite))
+                .copied()
+                .collect();
+            if !missing.is_empty() {
+                return Err(SessionError::OutOfOrder { command, missing });
+            }
+            sent.insert(command);
+        }
+
+        self.websocket
+            .send(Message::Text(serde_json::to_string(&message)?))
+            .await?;
+        self.sent = sent;
+        Ok(())
+    }
+
+    /// If the command was sent in this session.
+    pub fn has_sent(&self, command: &str) -> bool {
+        self.sent.contains(command)
+    }
+
+    /// The connection, sending through it skips the checks.
+    pub fn websocket(&mut self) -> &mut WebSocket {
+        &mut self.websocket
+    }
+
+    /// End the session, keeping the connection.
+    pub fn into_inner(self) -> WebSocket {
+        self.websocket
+    }
+}
+
+/// The tags of the commands in a message, in order.
+fn collect_commands<O: Order>(value: &serde_json::Value, commands: &mut Vec<String>) {
+    match value {
+        serde_json::Value::Object(object) => {
+            for (key, value) in object {
+                if key == O::COMMAND_FIELD {
+                    if let Some(tag) = value.get(O::TAG).and_then(|tag| tag.as_str()) {
+                        commands.push(tag.to_string());
+                        continue;
+                    }
+                }
+                collect_commands::<O>(value, commands);
+            }
+        }
+        serde_json::Value::Array(values) => {
+            for value in values {
+                collect_commands::<O>(value, commands);
+            }
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,314 @@
+//! Split the generated types into multiple files.
+//!
+//! A single `types.rs` for a large API is tens of thousands of lines, which is slow
+//! to compile and painful for editors. When splitting, `types/mod.rs` keeps the
+//! runtime modules and re-exports every file, so `crate::types::Foo` paths do not
+//! change.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::Result;
+
+/// The most types we put in a single file, bigger groups are split in chunks.
+const MAX_TYPES_PER_FILE: usize = 100;
+
+/// The file for types used by more than one tag, or by none.
+const SHARED_FILE: &str = "shared";
+
+/// The generated types split into files.
+#[derive(Debug, Clone)]
+pub struct SplitTypes {
+    /// The contents of `types/mod.rs`.
+    pub module: proc_macro2::TokenStream,
+    /// The contents of the other files, by module name.
+    pub files: BTreeMap<String, proc_macro2::TokenStream>,
+}
+
+/// Split the rendered types into files.
+/// The tag files are used to find which tag each type belongs to.
+pub fn split_types(
+    rendered: &proc_macro2::TokenStream,
+    tag_files: &BTreeMap<String, proc_macro2::TokenStream>,
+    split: crate::TypesSplit,
+) -> Result<SplitTypes> {
+    let file = syn::parse2::<syn::File>(rendered.clone())
+        .map_err(|e| anyhow::anyhow!("failed to parse the generated types: {}", e))?;
+
+    // Group the items by the type they define or implement, everything else stays in
+    // the module.
+    let mut types: BTreeMap<String, Vec<syn::Item>> = BTreeMap::new();
+    let mut module_items = Vec::new();
+    for item in &file.items {
+        if let Some(name) = get_type_name(item) {
+            types.entry(name).or_default().push(item.clone());
+        }
+    }
+    for item in file.items {
+        match get_impl_type_name(&item) {
+            Some(name) if types.contains_key(&name) => {
+                types.entry(name).or_default().push(item);
+            }
+            _ if get_type_name(&item).is_none() => module_items.push(item),
+            _ => {}
+        }
+    }
+
+    // Decide which group each type goes in.
+    let mut groups: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    match split {
+        crate::TypesSplit::None => anyhow::bail!("the types are not being split"),
+        crate::TypesSplit::Alphabetical => {
+            for name in types.keys() {
+                let first = name
+                    .chars()
+                    .next()
+                    .filter(|c| c.is_ascii_alphabetic())
+                    .map(|c| c.to_ascii_lowercase().to_string())
+                    .unwrap_or_else(|| SHARED_FILE.to_string());
+                groups.entry(first).or_default().push(name.to_string());
+            }
+        }
+        crate::TypesSplit::Tag => {
+            for (name, tags) in get_type_tags(&types, tag_files) {
+                let group = if tags.len() == 1 {
+                    tags.into_iter().next().unwrap_or_default()
+                } else {
+                    SHARED_FILE.to_string()
+                };
+                groups.entry(group).or_default().push(name);
+            }
+        }
+    }
+
+    // Make sure no group is too big, and no module name collides with one we
+    // already have.
+    let taken: BTreeSet<String> = module_items
+        .iter()
+        .filter_map(|item| match item {
+            syn::Item::Mod(m) => Some(m.ident.to_string()),
+            _ => None,
+        })
+        .collect();
+    let mut files = BTreeMap::new();
+    for (group, names) in groups {
+        let mut group = group;
+        if taken.contains(&group) {
+            group = format!("{}_types", group);
+        }
+
+        for (i, chunk) in names.chunks(MAX_TYPES_PER_FILE).enumerate() {
+            let file_name = if i == 0 {
+                group.to_string()
+            } else {
+                format!("{}_{}", group, i + 1)
+            };
+
+            let items = chunk.iter().flat_map(|name| types[name].iter());
+            files.insert(
+                file_name,
+                quote! {
+                    //! This module contains some of the generated types for the library.
+
+                    use super::*;
+
+                    #(#items)*
+                },
+            );
+        }
+    }
+
+    let attrs = &file.attrs;
+    let mods = files.keys().map(|name| format_ident!("{}", name));
+    let uses = mods.clone();
+    let module = quote! {
+        #(#attrs)*
+
+        #(#module_items)*
+
+        #(mod #mods;)*
+
+        #(pub use self::#uses::*;)*
+    };
+
+    Ok(SplitTypes { module, files })
+}
+
+/// Returns the name of the type an item defines.
+fn get_type_name(item: &syn::Item) -> Option<String> {
+    match item {
+        syn::Item::Struct(s) => Some(s.ident.to_string()),
+        syn::Item::Enum(e) => Some(e.ident.to_string()),
+        syn::Item::Type(t) => Some(t.ident.to_string()),
+        syn::Item::Union(u) => Some(u.ident.to_string()),
+        _ => None,
+    }
+}
+
+/// Returns the name of the type an `impl` block is for.
+fn get_impl_type_name(item: &syn::Item) -> Option<String> {
+    let syn::Item::Impl(i) = item else {
+        return None;
+    };
+    let syn::Type::Path(p) = &*i.self_ty else {
+        return None;
+    };
+    p.path.segments.last().map(|s| s.ident.to_string())
+}
+
+/// Find the tags using each type, either directly in their functions or through
+/// another type.
+fn get_type_tags(
+    types: &BTreeMap<String, Vec<syn::Item>>,
+    tag_files: &BTreeMap<String, proc_macro2::TokenStream>,
+) -> BTreeMap<String, BTreeSet<String>> {
+    // The types each type refers to.
+    let references: BTreeMap<&String, BTreeSet<String>> = types
+        .iter()
+        .map(|(name, items)| {
+            let mut idents = BTreeSet::new();
+            for item in items {
+                collect_idents(quote!(#item), &mut idents);
+            }
+            idents.retain(|ident| ident != name && types.contains_key(ident));
+            (name, idents)
+        })
+        .collect();
+
+    let mut type_tags: BTreeMap<String, BTreeSet<String>> = types
+        .keys()
+        .map(|name| (name.to_string(), BTreeSet::new()))
+        .collect();
+    for (tag, content) in tag_files {
+        let mut idents = BTreeSet::new();
+        collect_idents(content.clone(), &mut idents);
+
+        // Walk everything reachable from the types the functions use.
+        let mut queue: Vec<String> = idents
+            .into_iter()
+            .filter(|ident| types.contains_key(ident))
+            .collect();
+        while let Some(name) = queue.pop() {
+            let Some(tags) = type_tags.get_mut(&name) else {
+                continue;
+            };
+            if !tags.insert(tag.to_string()) {
+                continue;
+            }
+            if let Some(references) = references.get(&name) {
+                queue.extend(references.iter().cloned());
+            }
+        }
+    }
+
+    type_tags
+}
+
+/// Collect every identifier in a token stream.
+fn collect_idents(tokens: proc_macro2::TokenStream, idents: &mut BTreeSet<String>) {
+    for token in tokens {
+        match token {
+            proc_macro2::TokenTree::Ident(ident) => {
+                idents.insert(ident.to_string());
+            }
+            proc_macro2::TokenTree::Group(g) => collect_idents(g.stream(), idents),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_split_types_by_tag() {
+        let spec = crate::load_json_spec(include_str!("../../tests/mock.json")).unwrap();
+        let spec = crate::error_codes::prepare(&spec).unwrap();
+
+        // Leave out the runtime modules, they always stay in `types/mod.rs`.
+        let mut type_space = crate::types::TypeSpace {
+            types: indexmap::map::IndexMap::new(),
+            spec: spec.clone(),
+            rendered: quote!(),
+            opts: Default::default(),
+        };
+        for (name, schema) in &spec.components.as_ref().unwrap().schemas {
+            type_space
+                .render_schema(name, schema.as_item().unwrap())
+                .unwrap();
+        }
+        let (files, _, _) =
+            crate::functions::generate_files(&mut type_space, &Default::default()).unwrap();
+
+        let split =
+            super::split_types(&type_space.rendered, &files, crate::TypesSplit::Tag).unwrap();
+        assert_eq!(
+            split.files.keys().collect::<Vec<_>>(),
+            vec!["oauth2", "shared", "things", "tokens"]
+        );
+
+        let mut output = format!(
+            "// types/mod.rs\n{}",
+            crate::types::get_text_fmt(&split.module).unwrap()
+        );
+        for (name, content) in &split.files {
+            output.push_str(&format!(
+                "\n// types/{}.rs\n{}",
+                name,
+                crate::types::get_text_fmt(content).unwrap()
+            ));
+        }
+        expectorate::assert_contents("tests/types/mock.split-by-tag.rs.gen", &output);
+    }
+
+    #[test]
+    fn test_split_types_alphabetical() {
+        let rendered = quote! {
+            pub mod a {}
+
+            pub struct Error {}
+
+            impl Error {
+                pub fn new() -> Self {
+                    Self {}
+                }
+            }
+
+            pub enum Apple {}
+
+            pub struct Avocado {}
+
+            impl std::fmt::Display for Avocado {
+                fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                    write!(f, "avocado")
+                }
+            }
+        };
+
+        let split = super::split_types(
+            &rendered,
+            &Default::default(),
+            crate::TypesSplit::Alphabetical,
+        )
+        .unwrap();
+
+        let files: Vec<(String, String)> = split
+            .files
+            .iter()
+            .map(|(name, content)| (name.to_string(), content.to_string()))
+            .collect();
+        assert_eq!(files.len(), 2);
+        // The module `a` is taken, so the file gets a suffix.
+        assert_eq!(files[0].0, "a_types");
+        assert!(files[0].1.contains("pub enum Apple"));
+        assert!(files[0]
+            .1
+            .contains("impl std :: fmt :: Display for Avocado"));
+        assert_eq!(files[1].0, "e");
+        assert!(files[1].1.contains("impl Error"));
+
+        let module = split.module.to_string();
+        assert!(module.contains("pub mod a { }"));
+        assert!(module.contains("pub use self :: a_types :: *"));
+        assert!(module.contains("pub use self :: e :: *"));
+        assert!(!module.contains("struct Error"));
+    }
+}
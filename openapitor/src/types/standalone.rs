@@ -0,0 +1,269 @@
+//! Render a single schema to Rust, for tooling that embeds the types elsewhere.
+//!
+//! `generate_types` renders every schema of a spec, with the runtime modules of a
+//! generated crate in front. `render_standalone_schema` only renders one schema and
+//! the component schemas it refers to, directly or not, and nothing else.
+
+use std::collections::{BTreeSet, VecDeque};
+
+use anyhow::Result;
+
+use crate::types::exts::ReferenceOrExt;
+
+/// How `render_standalone_schema` renders the types, the options of the generator
+/// that change them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenderOptions {
+    /// The format of the date-times, see `Opts::date_time_format`. Only with
+    /// `chrono`.
+    pub date_time_format: Option<String>,
+    /// The crate of the dates and the times.
+    pub datetime_crate: crate::DateTimeCrate,
+    /// Render the arrays of unique items as sets.
+    pub unique_items_as_sets: bool,
+    /// Parse the values of the string enums whatever their case.
+    pub case_insensitive_enums: bool,
+    /// Keep the passwords and the tokens in a `secret::Secret`.
+    pub secret_fields: bool,
+    /// Declare the fields in the order of their names in the JSON.
+    pub sort_fields: bool,
+    /// Keep the acronyms of `acronyms` as one word in the names of the fields.
+    pub acronym_casing: bool,
+    /// The acronyms kept as one word with `acronym_casing`.
+    pub acronyms: Vec<String>,
+    /// Truncate the descriptions longer than this, in characters.
+    pub max_description_length: Option<usize>,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions {
+            date_time_format: None,
+            datetime_crate: crate::DateTimeCrate::Chrono,
+            unique_items_as_sets: false,
+            case_insensitive_enums: false,
+            secret_fields: false,
+            sort_fields: false,
+            acronym_casing: false,
+            acronyms: crate::default_acronyms(),
+            max_description_length: None,
+        }
+    }
+}
+
+impl RenderOptions {
+    /// The options of the generator that render the types the same way.
+    fn opts(&self) -> crate::Opts {
+        crate::Opts {
+            date_time_format: self.date_time_format.clone(),
+            datetime_crate: self.datetime_crate,
+            unique_items_as_sets: self.unique_items_as_sets,
+            case_insensitive_enums: self.case_insensitive_enums,
+            secret_fields: self.secret_fields,
+            sort_fields: self.sort_fields,
+            acronym_casing: self.acronym_casing,
+            acronyms: self.acronyms.clone(),
+            max_description_length: self.max_description_length,
+            types_only: true,
+            ..Default::default()
+        }
+    }
+}
+
+/// Render the schema `name` to Rust, with the types of the component schemas it
+/// refers to, directly or not. The references are looked up in `components`.
+///
+/// There are none of the runtime modules of a generated crate, so the module the
+/// types go in must have the ones they need next to them: `base64`, `ids`,
+/// `phone_number` and the like, as in `sketch: ids::Uuid`. The links of the docs
+/// point to `crate::types`.
+pub fn render_standalone_schema(
+    name: &str,
+    schema: &openapiv3::Schema,
+    components: &openapiv3::Components,
+    options: &RenderOptions,
+) -> Result<String> {
+    if options.date_time_format.is_some() && options.datetime_crate != crate::DateTimeCrate::Chrono
+    {
+        anyhow::bail!("`date_time_format` is only supported with the `chrono` datetime crate");
+    }
+    if let Some(format) = &options.date_time_format {
+        crate::template::check_date_time_format(format)?;
+    }
+
+    let opts = options.opts();
+    let _casing = crate::types::casing::scoped(&opts);
+    let spec = openapiv3::OpenAPI {
+        components: Some(components.clone()),
+        ..Default::default()
+    };
+    let mut type_space = crate::types::TypeSpace {
+        types: Default::default(),
+        spec,
+        rendered: quote::quote!(),
+        opts,
+    };
+
+    let mut referenced = Vec::new();
+    for reference in get_referenced_schemas(schema, components)? {
+        let Some(schema) = components.schemas.get(&reference) else {
+            anyhow::bail!(
+                "schema `{}` refers to `#/components/schemas/{}`, which is not in the components",
+                name,
+                reference
+            );
+        };
+        referenced.push((
+            reference,
+            schema.get_schema_from_reference(&type_space.spec, true)?,
+        ));
+    }
+
+    type_space.render_schema(name, schema)?;
+    for (reference, schema) in referenced {
+        type_space.render_schema(&reference, &schema)?;
+    }
+
+    crate::types::get_text_fmt(&type_space.rendered)
+}
+
+/// The names of the component schemas `schema` refers to, directly or not, in the
+/// order they are found.
+fn get_referenced_schemas(
+    schema: &openapiv3::Schema,
+    components: &openapiv3::Components,
+) -> Result<Vec<String>> {
+    let mut found = Vec::new();
+    let mut seen = BTreeSet::new();
+    let mut queue = VecDeque::from([serde_json::to_value(schema)?]);
+    while let Some(value) = queue.pop_front() {
+        let mut references = Vec::new();
+        collect_references(&value, &mut references);
+        for reference in references {
+            if !seen.insert(reference.clone()) {
+                continue;
+            }
+            if let Some(referenced) = components.schemas.get(&reference) {
+                queue.push_back(serde_json::to_value(referenced)?);
+            }
+            found.push(reference);
+        }
+    }
+    Ok(found)
+}
+
+/// Push the names of the component schemas of the `$ref`s in `value`.
+fn collect_references(value: &serde_json::Value, references: &mut Vec<String>) {
+    match value {
+        serde_json::Value::Object(object) => {
+            for (key, value) in object {
+                match (key.as_str(), value) {
+                    ("$ref", serde_json::Value::String(reference)) => {
+                        if let Some(name) = reference.strip_prefix("#/components/schemas/") {
+                            references.push(name.to_string());
+                        }
+                    }
+                    _ => collect_references(value, references),
+                }
+            }
+        }
+        serde_json::Value::Array(values) => {
+            for value in values {
+                collect_references(value, references);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{render_standalone_schema, RenderOptions};
+
+    fn render(schema: &str, spec: &str, name: &str) -> String {
+        let schema = serde_json::from_str::<openapiv3::Schema>(schema).unwrap();
+        let spec = crate::load_json_spec(spec).unwrap();
+        render_standalone_schema(
+            name,
+            &schema,
+            spec.components.as_ref().unwrap(),
+            &RenderOptions::default(),
+        )
+        .unwrap()
+    }
+
+    /// The names of the types defined in `rendered`.
+    fn type_names(rendered: &str) -> Vec<&str> {
+        rendered
+            .lines()
+            .filter_map(|line| {
+                line.strip_prefix("pub struct ")
+                    .or_else(|| line.strip_prefix("pub enum "))
+            })
+            .filter_map(|line| line.split([' ', '(', '{', ';']).next())
+            .collect()
+    }
+
+    #[test]
+    fn test_render_standalone_router_route() {
+        let rendered = render(
+            include_str!("../../tests/types/input/RouterRoute.json"),
+            include_str!("../../tests/oxide.json"),
+            "RouterRoute",
+        );
+        expectorate::assert_contents("tests/types/standalone.router-route.rs.gen", &rendered);
+
+        // The type and the ones it needs, without the runtime modules. `Name` and the
+        // networks of `IpNet` are strings, they have no type of their own.
+        assert_eq!(
+            type_names(&rendered),
+            [
+                "RouterRoute",
+                "RouteDestination",
+                "RouterRouteKind",
+                "RouteTarget",
+                "IpNet",
+            ]
+        );
+        assert!(!rendered.contains("pub mod base64"), "{}", rendered);
+        assert!(!rendered.contains("pub mod error"), "{}", rendered);
+    }
+
+    #[test]
+    fn test_render_standalone_modeling_cmd() {
+        let rendered = render(
+            include_str!("../../tests/types/input/ModelingCmd.json"),
+            include_str!("../../../spec.json"),
+            "ModelingCmd",
+        );
+        expectorate::assert_contents("tests/types/standalone.modeling-cmd.rs.gen", &rendered);
+
+        // `ModelingCmdId` is a UUID, it has no type of its own.
+        assert_eq!(type_names(&rendered), ["ModelingCmd"]);
+        assert!(rendered.contains("sketch: ids::Uuid,"), "{}", rendered);
+        assert!(!rendered.contains("pub mod base64"), "{}", rendered);
+    }
+
+    #[test]
+    fn test_render_standalone_missing_reference() {
+        let schema = serde_json::from_value::<openapiv3::Schema>(serde_json::json!({
+            "type": "object",
+            "properties": {
+                "part": { "$ref": "#/components/schemas/Part" }
+            }
+        }))
+        .unwrap();
+
+        assert_eq!(
+            render_standalone_schema(
+                "Thing",
+                &schema,
+                &Default::default(),
+                &RenderOptions::default()
+            )
+            .unwrap_err()
+            .to_string(),
+            "schema `Thing` refers to `#/components/schemas/Part`, which is not in the components"
+        );
+    }
+}
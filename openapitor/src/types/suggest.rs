@@ -0,0 +1,192 @@
+//! The error of `parse_with_suggestions` on the string enums, for values typed by
+//! people: it names the values close to the one that did not parse.
+
+/// How many values we suggest at most.
+const MAX_SUGGESTIONS: usize = 3;
+
+/// We list every value of the enums with at most this many.
+const MAX_LISTED_VALUES: usize = 10;
+
+/// The error when a value is none of the values of an enum.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseEnumError {
+    /// The name of the enum.
+    pub type_name: &'static str,
+    /// The value that did not parse.
+    pub value: String,
+    /// The values close to it, the closest first.
+    pub suggestions: Vec<&'static str>,
+    /// Every value of the enum.
+    pub values: &'static [&'static str],
+}
+
+impl ParseEnumError {
+    /// The error for `value`, which is none of `values` of the enum `type_name`.
+    pub fn new(type_name: &'static str, value: &str, values: &'static [&'static str]) -> Self {
+        ParseEnumError {
+            type_name,
+            value: value.to_string(),
+            suggestions: suggestions(value, values),
+            values,
+        }
+    }
+}
+
+impl std::fmt::Display for ParseEnumError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "`{}` is not a valid `{}`", self.value, self.type_name)?;
+        if !self.suggestions.is_empty() {
+            write!(f, ", did you mean {}?", join(&self.suggestions, "or"))?;
+        }
+        if self.values.len() <= MAX_LISTED_VALUES {
+            let values = join(self.values, "and");
+            if self.suggestions.is_empty() {
+                write!(f, ", the values are {}", values)?;
+            } else {
+                write!(f, " The values are {}.", values)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ParseEnumError {}
+
+/// The values close to `value`, the closest first: the ones it is the start of,
+/// then the ones a few edits away. Case, dashes and underscores do not count.
+pub fn suggestions(value: &str, values: &[&'static str]) -> Vec<&'static str> {
+    let value = normalize(value);
+    if value.is_empty() {
+        return Vec::new();
+    }
+
+    let mut scored = values
+        .iter()
+        .filter_map(|candidate| {
+            let normalized = normalize(candidate);
+            let distance = edit_distance(&value, &normalized);
+            let longest = value.chars().count().max(normalized.chars().count());
+            let is_prefix = normalized.starts_with(&value);
+            // A third of the characters can be wrong.
+            if is_prefix || distance * 3 <= longest {
+                Some((!is_prefix, distance, *candidate))
+            } else {
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+    // The sort is stable, the ties keep the order of the enum.
+    scored.sort_by_key(|(not_prefix, distance, _)| (*not_prefix, *distance));
+    scored
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, _, candidate)| candidate)
+        .collect()
+}
+
+/// Lowercase, without the separators.
+fn normalize(s: &str) -> String {
+    s.chars()
+        .filter(|c| !matches!(c, '-' | '_' | ' ' | '.'))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// The Levenshtein distance between two strings, in characters.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// `a`, `b` or `c`.
+fn join(values: &[&str], last: &str) -> String {
+    let quoted = values
+        .iter()
+        .map(|value| format!("`{}`", value))
+        .collect::<Vec<_>>();
+    match quoted.split_last() {
+        Some((tail, init)) if !init.is_empty() => {
+            format!("{} {} {}", init.join(", "), last, tail)
+        }
+        _ => quoted.join(""),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{edit_distance, suggestions, ParseEnumError};
+
+    const UNIT_MASS: &[&str] = &["g", "kg", "lb"];
+    const UNIT_VOLUME: &[&str] = &[
+        "cm3", "ft3", "in3", "m3", "yd3", "usfloz", "usgal", "l", "ml",
+    ];
+    const UNIT_TEMPERATURE: &[&str] = &["celsius", "fahrenheit", "kelvin", "rankine"];
+    const FILE_EXPORT_FORMAT: &[&str] = &["fbx", "glb", "gltf", "obj", "ply", "step", "stl"];
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("", ""), 0);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("celcius", "celsius"), 1);
+        assert_eq!(edit_distance("mm", ""), 2);
+    }
+
+    #[test]
+    fn test_misspelled_unit() {
+        let err = ParseEnumError::new("UnitTemperature", "farenheit", UNIT_TEMPERATURE);
+        assert_eq!(err.suggestions, ["fahrenheit"]);
+        assert_eq!(
+            err.to_string(),
+            "`farenheit` is not a valid `UnitTemperature`, did you mean `fahrenheit`? The values \
+             are `celsius`, `fahrenheit`, `kelvin` and `rankine`."
+        );
+
+        // Case and separators do not count.
+        assert_eq!(suggestions("Celcius", UNIT_TEMPERATURE), ["celsius"]);
+        assert_eq!(suggestions("US-GAL", UNIT_VOLUME), ["usgal"]);
+    }
+
+    #[test]
+    fn test_ambiguous_prefix() {
+        // The values it is the start of come first, the closest first.
+        let err = ParseEnumError::new("FileExportFormat", "gl", FILE_EXPORT_FORMAT);
+        assert_eq!(err.suggestions, ["glb", "gltf"]);
+        assert_eq!(
+            err.to_string(),
+            "`gl` is not a valid `FileExportFormat`, did you mean `glb` or `gltf`? The values are \
+             `fbx`, `glb`, `gltf`, `obj`, `ply`, `step` and `stl`."
+        );
+
+        assert_eq!(suggestions("st", FILE_EXPORT_FORMAT), ["stl", "step"]);
+        assert_eq!(suggestions("m", UNIT_VOLUME), ["m3", "ml"]);
+    }
+
+    #[test]
+    fn test_no_suggestions() {
+        let err = ParseEnumError::new("UnitMass", "stone", UNIT_MASS);
+        assert!(err.suggestions.is_empty());
+        assert_eq!(
+            err.to_string(),
+            "`stone` is not a valid `UnitMass`, the values are `g`, `kg` and `lb`"
+        );
+
+        // Nothing is close to an empty value.
+        assert!(suggestions("", UNIT_MASS).is_empty());
+
+        // The values of the big enums are not all listed.
+        let values: &'static [&'static str] = &[
+            "a1", "a2", "a3", "a4", "a5", "a6", "a7", "a8", "a9", "a10", "a11",
+        ];
+        let err = ParseEnumError::new("Big", "zzz", values);
+        assert_eq!(err.to_string(), "`zzz` is not a valid `Big`");
+    }
+}
@@ -0,0 +1,279 @@
+//! Uploading a file in chunks to a resumable upload session, see the
+//! `upload_resumable` functions.
+
+/// How many times a chunk is sent before the upload gives up on it.
+pub const MAX_ATTEMPTS: u32 = 3;
+
+/// The wait before the second attempt of a chunk, every other one waits as much
+/// longer.
+pub const RETRY_DELAY: std::time::Duration = std::time::Duration::from_millis(100);
+
+/// A chunk of the file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chunk {
+    /// Where the chunk starts in the file.
+    pub offset: u64,
+    /// The bytes of the chunk, never empty.
+    pub data: bytes::Bytes,
+    /// The size of the whole file, we only know it for the last chunk.
+    pub total: Option<u64>,
+}
+
+impl Chunk {
+    /// The `Content-Range` of the chunk, like `bytes 0-1023/*`, the last chunk has
+    /// the size of the file instead of `*`.
+    pub fn content_range(&self) -> String {
+        let end = self.offset + self.data.len() as u64 - 1;
+        match self.total {
+            Some(total) => format!("bytes {}-{}/{}", self.offset, end, total),
+            None => format!("bytes {}-{}/*", self.offset, end),
+        }
+    }
+}
+
+/// How much of the file is uploaded, the progress callback gets it after every
+/// chunk.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct UploadProgress {
+    /// The number of chunks uploaded.
+    pub chunks: usize,
+    /// The number of bytes uploaded.
+    pub bytes: u64,
+}
+
+/// Why an upload failed.
+#[derive(Debug)]
+pub enum UploadError {
+    /// A request failed, creating the session or finalizing it.
+    Request(crate::types::error::Error),
+    /// Reading the file failed.
+    Read(std::io::Error),
+    /// A chunk failed, the upload stopped.
+    Chunk {
+        /// Where the chunk starts in the file.
+        offset: u64,
+        /// How many times we sent it.
+        attempts: u32,
+        /// The error of the last attempt.
+        error: crate::types::error::Error,
+    },
+}
+
+impl From<crate::types::error::Error> for UploadError {
+    fn from(err: crate::types::error::Error) -> Self {
+        UploadError::Request(err)
+    }
+}
+
+impl From<std::io::Error> for UploadError {
+    fn from(err: std::io::Error) -> Self {
+        UploadError::Read(err)
+    }
+}
+
+impl std::fmt::Display for UploadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UploadError::Request(err) => write!(f, "{}", err),
+            UploadError::Read(err) => write!(f, "failed to read the file: {}", err),
+            UploadError::Chunk {
+                offset,
+                attempts,
+                error,
+            } => write!(
+                f,
+                "the chunk at {} failed after {} attempts: {}",
+                offset, attempts, error
+            ),
+        }
+    }
+}
+
+impl std::error::Error for UploadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            UploadError::Request(err) | UploadError::Chunk { error: err, .. } => Some(err),
+            UploadError::Read(err) => Some(err),
+        }
+    }
+}
+
+/// Read `source` in chunks of `chunk_size` bytes and send them with `send`, up to
+/// `concurrency` at a time. A chunk failing with an error worth retrying is sent
+/// again on its own, up to `MAX_ATTEMPTS` times, any other failure stops the
+/// upload. `progress` is called after every chunk, in the order they are done.
+///
+/// We read a chunk ahead to know which one is the last, so at most
+/// `concurrency + 1` chunks are in memory.
+pub async fn upload_chunks<R, S, F, P>(
+    mut source: R,
+    chunk_size: usize,
+    concurrency: usize,
+    mut progress: P,
+    send: S,
+) -> Result<UploadProgress, UploadError>
+where
+    R: tokio::io::AsyncRead + Unpin,
+    S: Fn(Chunk) -> F,
+    F: std::future::Future<Output = Result<(), crate::types::error::Error>>,
+    P: FnMut(UploadProgress),
+{
+    use futures::StreamExt;
+
+    let chunk_size = chunk_size.max(1);
+    let concurrency = concurrency.max(1);
+    let send = &send;
+    let mut in_flight = futures::stream::FuturesUnordered::new();
+    let mut uploaded = UploadProgress::default();
+    let mut offset = 0;
+    let mut next = read_chunk(&mut source, chunk_size).await?;
+    loop {
+        while !next.is_empty() && in_flight.len() < concurrency {
+            let data = std::mem::replace(&mut next, read_chunk(&mut source, chunk_size).await?);
+            let size = data.len() as u64;
+            let chunk = Chunk {
+                offset,
+                data: data.into(),
+                total: next.is_empty().then_some(offset + size),
+            };
+            offset += size;
+            in_flight.push(send_chunk(send, chunk));
+        }
+
+        let Some(sent) = in_flight.next().await else {
+            return Ok(uploaded);
+        };
+        uploaded.bytes += sent?;
+        uploaded.chunks += 1;
+        progress(uploaded);
+    }
+}
+
+/// Send a chunk until it is uploaded, returns its size.
+async fn send_chunk<S, F>(send: &S, chunk: Chunk) -> Result<u64, UploadError>
+where
+    S: Fn(Chunk) -> F,
+    F: std::future::Future<Output = Result<(), crate::types::error::Error>>,
+{
+    let mut attempts = 1;
+    loop {
+        match send(chunk.clone()).await {
+            Ok(()) => return Ok(chunk.data.len() as u64),
+            Err(err) if attempts < MAX_ATTEMPTS && err.is_retryable() => {
+                tokio::time::sleep(RETRY_DELAY * attempts).await;
+                attempts += 1;
+            }
+            Err(error) => {
+                return Err(UploadError::Chunk {
+                    offset: chunk.offset,
+                    attempts,
+                    error,
+                })
+            }
+        }
+    }
+}
+
+/// Read the next `size` bytes of `source`, fewer at its end.
+async fn read_chunk<R>(source: &mut R, size: usize) -> Result<Vec<u8>, std::io::Error>
+where
+    R: tokio::io::AsyncRead + Unpin,
+{
+    use tokio::io::AsyncReadExt;
+
+    let mut chunk = Vec::with_capacity(size);
+    source.take(size as u64).read_to_end(&mut chunk).await?;
+    Ok(chunk)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::{upload_chunks, Chunk, UploadError, UploadProgress};
+
+    #[test]
+    fn test_content_range() {
+        let chunk = Chunk {
+            offset: 4,
+            data: bytes::Bytes::from_static(b"abcd"),
+            total: None,
+        };
+        assert_eq!(chunk.content_range(), "bytes 4-7/*");
+        let last = Chunk {
+            total: Some(8),
+            ..chunk
+        };
+        assert_eq!(last.content_range(), "bytes 4-7/8");
+    }
+
+    #[tokio::test]
+    async fn test_upload_chunks() {
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let mut progress = Vec::new();
+        let uploaded = upload_chunks(
+            &b"0123456789"[..],
+            4,
+            2,
+            |p| progress.push(p),
+            |chunk| {
+                let sent = sent.clone();
+                async move {
+                    sent.lock().unwrap().push(chunk.content_range());
+                    Ok(())
+                }
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            uploaded,
+            UploadProgress {
+                chunks: 3,
+                bytes: 10
+            }
+        );
+        assert_eq!(progress.len(), 3);
+        let mut sent = sent.lock().unwrap().clone();
+        sent.sort();
+        assert_eq!(sent, vec!["bytes 0-3/*", "bytes 4-7/*", "bytes 8-9/10"]);
+
+        // Nothing to upload.
+        let uploaded = upload_chunks(&b""[..], 4, 2, |_| {}, |_| async { Ok(()) })
+            .await
+            .unwrap();
+        assert_eq!(uploaded, UploadProgress::default());
+    }
+
+    #[tokio::test]
+    async fn test_upload_chunks_gives_up() {
+        let err = upload_chunks(
+            &b"0123456789"[..],
+            4,
+            1,
+            |_| {},
+            |chunk| async move {
+                if chunk.offset == 4 {
+                    Err(crate::types::error::Error::InvalidRequest("bad".to_string()))
+                } else {
+                    Ok(())
+                }
+            },
+        )
+        .await
+        .unwrap_err();
+        // A client error is not worth retrying.
+        assert!(
+            matches!(
+                err,
+                UploadError::Chunk {
+                    offset: 4,
+                    attempts: 1,
+                    ..
+                }
+            ),
+            "{:?}",
+            err
+        );
+    }
+}
@@ -0,0 +1,156 @@
+//! The checks of the request bodies against the constraints of the spec, so a
+//! body the server would reject fails before we send it.
+
+/// A field of a request body that breaks a constraint of the spec.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationError {
+    /// The name of the field, as it is sent.
+    pub field: String,
+    /// What is wrong with its value.
+    pub message: String,
+}
+
+impl std::fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "`{}` {}", self.field, self.message)
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// Check the length of a string, in characters, or of an array, in items. The
+/// `unit` is singular, like `character`.
+pub fn check_length(
+    errors: &mut Vec<ValidationError>,
+    field: &str,
+    unit: &str,
+    length: usize,
+    min: Option<usize>,
+    max: Option<usize>,
+) {
+    if let Some(min) = min.filter(|min| length < *min) {
+        errors.push(ValidationError {
+            field: field.to_string(),
+            message: format!("must have at least {}, it has {}", count(min, unit), length),
+        });
+    }
+    if let Some(max) = max.filter(|max| length > *max) {
+        errors.push(ValidationError {
+            field: field.to_string(),
+            message: format!("must have at most {}, it has {}", count(max, unit), length),
+        });
+    }
+}
+
+/// A number of things, like `1 item` or `2 items`.
+fn count(n: usize, unit: &str) -> String {
+    if n == 1 {
+        format!("{} {}", n, unit)
+    } else {
+        format!("{} {}s", n, unit)
+    }
+}
+
+/// Check that a number is within its bounds, the exclusive ones leave the bound
+/// itself out.
+pub fn check_range(
+    errors: &mut Vec<ValidationError>,
+    field: &str,
+    value: f64,
+    (min, exclusive_min): (Option<f64>, bool),
+    (max, exclusive_max): (Option<f64>, bool),
+) {
+    if let Some(min) = min {
+        if value < min || (exclusive_min && value == min) {
+            let bound = if exclusive_min {
+                "more than"
+            } else {
+                "at least"
+            };
+            errors.push(ValidationError {
+                field: field.to_string(),
+                message: format!("must be {} {}, it is {}", bound, min, value),
+            });
+        }
+    }
+    if let Some(max) = max {
+        if value > max || (exclusive_max && value == max) {
+            let bound = if exclusive_max {
+                "less than"
+            } else {
+                "at most"
+            };
+            errors.push(ValidationError {
+                field: field.to_string(),
+                message: format!("must be {} {}, it is {}", bound, max, value),
+            });
+        }
+    }
+}
+
+/// Describe the errors of a body in one message, for `Error::InvalidRequest`.
+pub fn describe(errors: &[ValidationError]) -> String {
+    let errors = errors
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("; ");
+    format!("the request body is not valid: {}", errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_length, check_range, describe, ValidationError};
+
+    #[test]
+    fn test_check_length() {
+        let mut errors = Vec::new();
+        check_length(&mut errors, "name", "character", 3, Some(1), Some(3));
+        check_length(&mut errors, "ids", "item", 3, None, None);
+        assert!(errors.is_empty());
+
+        check_length(&mut errors, "name", "character", 0, Some(1), Some(3));
+        check_length(&mut errors, "ids", "item", 4, Some(1), Some(3));
+        assert_eq!(
+            errors,
+            vec![
+                ValidationError {
+                    field: "name".to_string(),
+                    message: "must have at least 1 character, it has 0".to_string(),
+                },
+                ValidationError {
+                    field: "ids".to_string(),
+                    message: "must have at most 3 items, it has 4".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_range() {
+        let mut errors = Vec::new();
+        check_range(&mut errors, "limit", 1.0, (Some(1.0), false), (None, false));
+        check_range(
+            &mut errors,
+            "ratio",
+            0.5,
+            (Some(0.0), true),
+            (Some(1.0), true),
+        );
+        assert!(errors.is_empty());
+
+        check_range(&mut errors, "limit", 0.0, (Some(1.0), false), (None, false));
+        check_range(
+            &mut errors,
+            "ratio",
+            1.0,
+            (Some(0.0), true),
+            (Some(1.0), true),
+        );
+        assert_eq!(
+            describe(&errors),
+            "the request body is not valid: `limit` must be at least 1, it is 0; `ratio` must \
+             be less than 1, it is 1"
+        );
+    }
+}
@@ -0,0 +1,295 @@
+//! A managed websocket connection that answers pings and reconnects.
+
+use futures::{SinkExt, StreamExt};
+pub use tokio_tungstenite::tungstenite::{protocol::frame::coding::CloseCode, Message};
+
+/// Opens the raw upgraded connection, this is the generated websocket function.
+type Connector = Box<
+    dyn Fn() -> futures::future::BoxFuture<
+            'static,
+            Result<(reqwest::Upgraded, http::HeaderMap), crate::types::error::Error>,
+        > + Send
+        + Sync,
+>;
+
+/// How to reconnect when the server drops the connection.
+#[derive(Debug, Clone)]
+pub struct ReconnectPolicy {
+    /// The number of reconnection attempts in a row before giving up.
+    pub max_attempts: u32,
+    /// The time to wait before the first reconnection attempt.
+    pub initial_backoff: std::time::Duration,
+    /// The longest time to wait between reconnection attempts.
+    pub max_backoff: std::time::Duration,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_backoff: std::time::Duration::from_millis(500),
+            max_backoff: std::time::Duration::from_secs(30),
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// The time to wait before the given attempt, starting at 1.
+    fn backoff(&self, attempt: u32) -> std::time::Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+/// An error from a managed websocket connection.
+#[derive(Debug, thiserror::Error)]
+pub enum WebSocketError {
+    /// The server closed the connection.
+    #[error("the server closed the connection with code {code}: {reason}")]
+    Closed {
+        /// The close code sent by the server.
+        code: CloseCode,
+        /// The reason sent by the server.
+        reason: String,
+    },
+    /// We could not open the connection.
+    #[error(transparent)]
+    Connect(#[from] crate::types::error::Error),
+    /// The websocket protocol failed.
+    #[error(transparent)]
+    Protocol(#[from] tokio_tungstenite::tungstenite::Error),
+    /// We gave up reconnecting.
+    #[error("gave up reconnecting after {attempts} attempts, the last close code was {code}")]
+    ReconnectsExhausted {
+        /// The number of attempts we made.
+        attempts: u32,
+        /// The close code of the connection we were trying to replace.
+        code: CloseCode,
+    },
+}
+
+/// Build a managed websocket connection.
+pub struct WebSocketBuilder {
+    connect: Connector,
+    auto_pong: bool,
+    reconnect: Option<ReconnectPolicy>,
+    resume: Option<Message>,
+}
+
+impl std::fmt::Debug for WebSocketBuilder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WebSocketBuilder")
+            .field("auto_pong", &self.auto_pong)
+            .field("reconnect", &self.reconnect)
+            .field("resume", &self.resume)
+            .finish()
+    }
+}
+
+impl WebSocketBuilder {
+    /// Create a builder from the function that opens the raw connection.
+    #[doc(hidden)]
+    pub fn new<F>(connect: F) -> Self
+    where
+        F: Fn() -> futures::future::BoxFuture<
+                'static,
+                Result<(reqwest::Upgraded, http::HeaderMap), crate::types::error::Error>,
+            > + Send
+            + Sync
+            + 'static,
+    {
+        Self {
+            connect: Box::new(connect),
+            auto_pong: true,
+            reconnect: None,
+            resume: None,
+        }
+    }
+
+    /// Answer pings from the server automatically, this is on by default.
+    /// When off, pings are returned from `WebSocket::recv`.
+    pub fn auto_pong(mut self, enabled: bool) -> Self {
+        self.auto_pong = enabled;
+        self
+    }
+
+    /// Reconnect when the server drops the connection.
+    pub fn reconnect(mut self, policy: ReconnectPolicy) -> Self {
+        self.reconnect = Some(policy);
+        self
+    }
+
+    /// Send this message every time we reconnect, so the server can pick up where we
+    /// left off.
+    pub fn resume_message(mut self, message: Message) -> Self {
+        self.resume = Some(message);
+        self
+    }
+
+    /// Open the connection.
+    pub async fn connect(self) -> Result<WebSocket, WebSocketError> {
+        let (stream, headers) = open(&self.connect).await?;
+        Ok(WebSocket {
+            stream,
+            headers,
+            builder: self,
+            attempts: 0,
+            closed: false,
+        })
+    }
+}
+
+/// A managed websocket connection.
+pub struct WebSocket {
+    stream: tokio_tungstenite::WebSocketStream<reqwest::Upgraded>,
+    headers: http::HeaderMap,
+    builder: WebSocketBuilder,
+    attempts: u32,
+    closed: bool,
+}
+
+impl std::fmt::Debug for WebSocket {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WebSocket")
+            .field("headers", &self.headers)
+            .field("builder", &self.builder)
+            .field("closed", &self.closed)
+            .finish()
+    }
+}
+
+impl WebSocket {
+    /// The headers of the response that opened the current connection.
+    pub fn headers(&self) -> &http::HeaderMap {
+        &self.headers
+    }
+
+    /// Send a message to the server.
+    pub async fn send(&mut self, message: Message) -> Result<(), WebSocketError> {
+        self.stream.send(message).await?;
+        Ok(())
+    }
+
+    /// Receive the next message from the server.
+    /// Returns `None` once the connection is closed for good.
+    pub async fn recv(&mut self) -> Option<Result<Message, WebSocketError>> {
+        loop {
+            if self.closed {
+                return None;
+            }
+
+            let (code, reason) = match self.stream.next().await {
+                Some(Ok(Message::Ping(data))) if self.builder.auto_pong => {
+                    // The pong is queued for us when the ping is read, make sure it goes out.
+                    if let Err(err) = self.stream.flush().await {
+                        tracing::warn!("failed to answer ping ({} bytes): {}", data.len(), err);
+                    }
+                    continue;
+                }
+                Some(Ok(Message::Close(frame))) => match frame {
+                    Some(frame) => (frame.code, frame.reason.to_string()),
+                    None => (CloseCode::Status, String::new()),
+                },
+                Some(Ok(message)) => {
+                    // We got a real message, so the connection is healthy again.
+                    self.attempts = 0;
+                    return Some(Ok(message));
+                }
+                Some(Err(err)) => (CloseCode::Abnormal, err.to_string()),
+                None => (CloseCode::Abnormal, "connection dropped".to_string()),
+            };
+
+            if let Err(err) = self.handle_close(code, reason).await {
+                self.closed = true;
+                return Some(Err(err));
+            }
+        }
+    }
+
+    /// Close the connection.
+    pub async fn close(mut self) -> Result<(), WebSocketError> {
+        self.closed = true;
+        self.stream.close(None).await?;
+        Ok(())
+    }
+
+    /// Reconnect if the policy allows it, otherwise return the close as an error.
+    async fn handle_close(
+        &mut self,
+        code: CloseCode,
+        reason: String,
+    ) -> Result<(), WebSocketError> {
+        // Send the close reply that was queued when the close was read, the connection
+        // might already be gone so ignore any error.
+        let _ = self.stream.flush().await;
+
+        let Some(policy) = self.builder.reconnect.clone() else {
+            return Err(WebSocketError::Closed { code, reason });
+        };
+        if !should_reconnect(code) {
+            return Err(WebSocketError::Closed { code, reason });
+        }
+
+        while self.attempts < policy.max_attempts {
+            self.attempts += 1;
+            tokio::time::sleep(policy.backoff(self.attempts)).await;
+            tracing::info!(
+                "reconnecting websocket after close code {}, attempt {}",
+                code,
+                self.attempts
+            );
+
+            match open(&self.builder.connect).await {
+                Ok((stream, headers)) => {
+                    self.stream = stream;
+                    self.headers = headers;
+                    if let Some(resume) = self.builder.resume.clone() {
+                        self.stream.send(resume).await?;
+                    }
+                    return Ok(());
+                }
+                Err(err) => tracing::warn!("failed to reconnect websocket: {}", err),
+            }
+        }
+
+        Err(WebSocketError::ReconnectsExhausted {
+            attempts: self.attempts,
+            code,
+        })
+    }
+}
+
+/// If a close code means the server went away and we should try again.
+/// Any other close is a decision by the server we respect.
+fn should_reconnect(code: CloseCode) -> bool {
+    matches!(
+        code,
+        CloseCode::Away
+            | CloseCode::Abnormal
+            | CloseCode::Error
+            | CloseCode::Restart
+            | CloseCode::Again
+    )
+}
+
+/// Open the raw connection and start the websocket protocol on it.
+async fn open(
+    connect: &Connector,
+) -> Result<
+    (
+        tokio_tungstenite::WebSocketStream<reqwest::Upgraded>,
+        http::HeaderMap,
+    ),
+    WebSocketError,
+> {
+    let (upgraded, headers) = connect().await?;
+    let stream = tokio_tungstenite::WebSocketStream::from_raw_socket(
+        upgraded,
+        tokio_tungstenite::tungstenite::protocol::Role::Client,
+        None,
+    )
+    .await;
+    Ok((stream, headers))
+}
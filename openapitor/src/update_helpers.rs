@@ -0,0 +1,210 @@
+//! Read-modify-write helpers for the resources that can be fetched and replaced.
+//!
+//! For every `PUT` operation taking the type that a `GET` operation on the same path
+//! returns, with the same arguments, we generate `{put}_with`: it fetches the
+//! resource, applies a closure to it and sends it back with `If-Match` set to the
+//! `ETag` of what it fetched, if there was one. When the server answers `409 Conflict`
+//! or `412 Precondition Failed` the whole cycle starts over.
+//!
+//! The `--update-helpers` file overrides the pairing, for example:
+//!
+//! ```toml
+//! # How many times to run the whole cycle before giving up, the default is 3.
+//! max_attempts = 5
+//! # Only generate the helpers for the pairs below.
+//! heuristics = false
+//! # Never generate helpers for these `PUT` operations.
+//! skip = ["update_org"]
+//!
+//! # The `GET` operation to pair with a `PUT` operation, by operation id.
+//! [pairs]
+//! update_user_settings = "get_user_settings"
+//! ```
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::Result;
+use serde::Deserialize;
+
+/// How we pair the operations for the update helpers.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UpdateHelpers {
+    /// How many times to run the whole cycle before giving up.
+    #[serde(default = "default_max_attempts")]
+    pub max_attempts: u32,
+    /// Pair the operations on the same path by themselves.
+    #[serde(default = "default_heuristics")]
+    pub heuristics: bool,
+    /// The `PUT` operations to never generate helpers for, by operation id.
+    #[serde(default)]
+    pub skip: BTreeSet<String>,
+    /// The `GET` operation for a `PUT` operation, by operation id.
+    #[serde(default)]
+    pub pairs: BTreeMap<String, String>,
+}
+
+fn default_max_attempts() -> u32 {
+    3
+}
+
+fn default_heuristics() -> bool {
+    true
+}
+
+impl Default for UpdateHelpers {
+    fn default() -> Self {
+        Self {
+            max_attempts: default_max_attempts(),
+            heuristics: default_heuristics(),
+            skip: Default::default(),
+            pairs: Default::default(),
+        }
+    }
+}
+
+/// A `GET` operation we pair with a `PUT` operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pairing<'a> {
+    /// Picked by the heuristics, we skip it if the operations don't fit.
+    Guessed(&'a str),
+    /// Picked in the config, it is an error if the operations don't fit.
+    Configured(&'a str),
+}
+
+impl UpdateHelpers {
+    /// Read the config from a file.
+    pub fn load<P: AsRef<std::path::Path>>(p: P) -> Result<Self> {
+        let p = p.as_ref();
+        let contents = std::fs::read_to_string(p).map_err(|e| {
+            anyhow::anyhow!("failed to read update helpers `{}`: {}", p.display(), e)
+        })?;
+        let config: UpdateHelpers = toml::from_str(&contents).map_err(|e| {
+            anyhow::anyhow!("failed to parse update helpers `{}`: {}", p.display(), e)
+        })?;
+        if config.max_attempts == 0 {
+            anyhow::bail!(
+                "invalid update helpers `{}`: `max_attempts` must be at least 1",
+                p.display()
+            );
+        }
+
+        Ok(config)
+    }
+
+    /// The `GET` operation to pair with the `PUT` operation `put`, given the `GET`
+    /// operation on the same path if there is one.
+    pub fn pairing<'a>(&'a self, put: &str, same_path_get: Option<&'a str>) -> Option<Pairing<'a>> {
+        if self.skip.contains(put) {
+            None
+        } else if let Some(get) = self.pairs.get(put) {
+            Some(Pairing::Configured(get))
+        } else if self.heuristics {
+            same_path_get.map(Pairing::Guessed)
+        } else {
+            None
+        }
+    }
+
+    /// Make sure every operation in the config exists, `gets` and `puts` are the
+    /// operation ids in the spec.
+    pub fn check_operations(&self, gets: &BTreeSet<String>, puts: &BTreeSet<String>) -> Result<()> {
+        for put in self.skip.iter().chain(self.pairs.keys()) {
+            if !puts.contains(put) {
+                anyhow::bail!(
+                    "the update helpers have `{}`, which is not a PUT operation",
+                    put
+                );
+            }
+        }
+        for get in self.pairs.values() {
+            if !gets.contains(get) {
+                anyhow::bail!(
+                    "the update helpers have `{}`, which is not a GET operation",
+                    get
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Pairing, UpdateHelpers};
+
+    fn config(contents: &str) -> UpdateHelpers {
+        toml::from_str(contents).unwrap()
+    }
+
+    #[test]
+    fn test_update_helpers_pairing() {
+        let default = UpdateHelpers::default();
+        assert_eq!(default, config(""));
+        assert_eq!(default.max_attempts, 3);
+        assert_eq!(
+            default.pairing("update_org", Some("get_org")),
+            Some(Pairing::Guessed("get_org"))
+        );
+        assert_eq!(default.pairing("update_org", None), None);
+
+        let config = config(
+            r#"
+            heuristics = false
+            skip = ["update_org"]
+
+            [pairs]
+            update_settings = "get_settings"
+            "#,
+        );
+        assert_eq!(config.pairing("update_org", Some("get_org")), None);
+        assert_eq!(config.pairing("update_user", Some("get_user")), None);
+        assert_eq!(
+            config.pairing("update_settings", None),
+            Some(Pairing::Configured("get_settings"))
+        );
+    }
+
+    #[test]
+    fn test_update_helpers_unknown_operations() {
+        let gets = vec!["get_org".to_string()].into_iter().collect();
+        let puts = vec!["update_org".to_string()].into_iter().collect();
+
+        assert!(config(r#"skip = ["update_org"]"#)
+            .check_operations(&gets, &puts)
+            .is_ok());
+        assert_eq!(
+            config(r#"skip = ["get_org"]"#)
+                .check_operations(&gets, &puts)
+                .unwrap_err()
+                .to_string(),
+            "the update helpers have `get_org`, which is not a PUT operation"
+        );
+        assert_eq!(
+            config("pairs = { update_org = \"get_orgs\" }")
+                .check_operations(&gets, &puts)
+                .unwrap_err()
+                .to_string(),
+            "the update helpers have `get_orgs`, which is not a GET operation"
+        );
+    }
+
+    #[test]
+    fn test_load_update_helpers() {
+        let config = UpdateHelpers::load("tests/update-helpers.toml").unwrap();
+        assert_eq!(config.max_attempts, 2);
+        assert!(config.heuristics);
+
+        let path =
+            std::env::temp_dir().join(format!("update-helpers-{}.toml", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "max_attempts = 0").unwrap();
+        let err = UpdateHelpers::load(&path).unwrap_err().to_string();
+        std::fs::remove_file(&path).unwrap();
+        assert!(
+            err.ends_with("`max_attempts` must be at least 1"),
+            "{}",
+            err
+        );
+    }
+}
@@ -0,0 +1,163 @@
+//! Helpers uploading a large file in chunks to a resumable upload session.
+//!
+//! An API with resumable uploads has three operations for them: one creating the
+//! session, one sending a chunk of the file to it with its `Content-Range`, and
+//! one finalizing it once every chunk is there. The operation creating the session
+//! names the other two, by operation id, with the `x-upload-session` extension:
+//!
+//! ```json
+//! "x-upload-session": {"chunk": "upload_chunk", "finalize": "finalize_upload"}
+//! ```
+//!
+//! We generate an `upload_resumable` function on the tag of the operation creating
+//! the session, or the function named by `helper` in the extension. It creates the
+//! session, reads the file in chunks and sends them with up to `concurrency` in
+//! flight, sends a chunk that failed again on its own, and finalizes the session.
+//! The upload itself is in the `upload` module of the types, see `types/upload.rs`.
+//!
+//! The response creating the session must always have its `id`. The operation
+//! sending a chunk takes that `id` in its path, the `Content-Range` header and the
+//! chunk as an `application/octet-stream` body, the one finalizing the session
+//! takes the `id` alone.
+
+use std::collections::BTreeSet;
+
+use anyhow::Result;
+use serde::Deserialize;
+
+use crate::types::exts::ReferenceOrExt;
+
+/// The name of the extension.
+pub const EXTENSION: &str = "x-upload-session";
+
+/// An upload session of the spec, with the operation ids of its operations.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UploadSession {
+    /// The operation creating the session, it has the extension.
+    pub create: String,
+    /// The operation sending a chunk to the session.
+    pub chunk: String,
+    /// The operation finalizing the session.
+    pub finalize: String,
+    /// The name of the generated function.
+    pub helper: String,
+}
+
+/// The `x-upload-session` extension of the operation creating a session.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Extension {
+    chunk: String,
+    finalize: String,
+    #[serde(default = "default_helper")]
+    helper: String,
+}
+
+fn default_helper() -> String {
+    "upload_resumable".to_string()
+}
+
+/// The upload sessions of the spec, by the operations with the extension. Errors if
+/// an extension is invalid or names an operation that does not exist.
+pub fn find(spec: &openapiv3::OpenAPI) -> Result<Vec<UploadSession>> {
+    let mut operation_ids = BTreeSet::new();
+    let mut creates = Vec::new();
+    for (_, item) in spec.paths.iter() {
+        for (_, op) in item.item()?.iter() {
+            let Some(operation_id) = &op.operation_id else {
+                continue;
+            };
+            operation_ids.insert(operation_id.to_string());
+            if let Some(extension) = op.extensions.get(EXTENSION) {
+                creates.push((operation_id.to_string(), extension.clone()));
+            }
+        }
+    }
+
+    creates
+        .into_iter()
+        .map(|(create, extension)| {
+            let extension = serde_json::from_value::<Extension>(extension)
+                .map_err(|e| anyhow::anyhow!("invalid `{}` of `{}`: {}", EXTENSION, create, e))?;
+            for operation_id in [&extension.chunk, &extension.finalize] {
+                if !operation_ids.contains(operation_id) {
+                    anyhow::bail!(
+                        "the `{}` of `{}` names `{}`, which is not an operation id of the spec",
+                        EXTENSION,
+                        create,
+                        operation_id
+                    );
+                }
+            }
+            Ok(UploadSession {
+                create,
+                chunk: extension.chunk,
+                finalize: extension.finalize,
+                helper: extension.helper,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::UploadSession;
+
+    #[test]
+    fn test_find_upload_sessions() {
+        let spec = crate::load_json_spec(include_str!("../tests/mock.json")).unwrap();
+        assert_eq!(
+            super::find(&spec).unwrap(),
+            vec![UploadSession {
+                create: "create_thing_upload".to_string(),
+                chunk: "upload_thing_chunk".to_string(),
+                finalize: "finalize_thing_upload".to_string(),
+                helper: "upload_resumable".to_string(),
+            }]
+        );
+
+        // The extension names operations of the spec.
+        let find_with = |extension: serde_json::Value| {
+            let mut spec = spec.clone();
+            let Some(openapiv3::ReferenceOr::Item(item)) =
+                spec.paths.paths.get_mut("/things/{id}/uploads")
+            else {
+                unreachable!();
+            };
+            let create = item.post.as_mut().unwrap();
+            create
+                .extensions
+                .insert(super::EXTENSION.to_string(), extension);
+            super::find(&spec)
+        };
+        assert_eq!(
+            find_with(serde_json::json!({"chunk": "upload_thing_chunk", "finalize": "nope"}))
+                .unwrap_err()
+                .to_string(),
+            "the `x-upload-session` of `create_thing_upload` names `nope`, which is not an \
+             operation id of the spec"
+        );
+        assert_eq!(
+            find_with(serde_json::json!({"chunk": "upload_thing_chunk"}))
+                .unwrap_err()
+                .to_string(),
+            "invalid `x-upload-session` of `create_thing_upload`: missing field `finalize`"
+        );
+        assert_eq!(
+            find_with(serde_json::json!({
+                "chunk": "upload_thing_chunk",
+                "finalize": "finalize_thing_upload",
+                "helper": "upload_file"
+            }))
+            .unwrap()[0]
+                .helper,
+            "upload_file"
+        );
+
+        // The specs without the extension have no sessions.
+        let spec = crate::load_json_spec(include_str!("../../spec.json")).unwrap();
+        assert_eq!(super::find(&spec).unwrap(), vec![]);
+    }
+}
@@ -0,0 +1,238 @@
+//! Generate the library again whenever the spec changes, see `--watch`.
+//!
+//! The generations skip `cargo fmt` and clippy, and each prints what changed in the
+//! public API since the last one that worked. A spec that does not load or
+//! generate prints its error, and the watch goes on until Ctrl-C.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc::{Receiver, RecvTimeoutError, Sender},
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+
+/// How long the spec has to stay untouched before we generate, editors write a
+/// file in more than one go.
+pub const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// What the watch hears about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchEvent {
+    /// The spec changed.
+    Changed,
+    /// Ctrl-C, time to stop.
+    Stop,
+}
+
+/// Watch the input spec of `opts` and generate the library into the output
+/// directory whenever it changes, until Ctrl-C. The library is generated once
+/// first.
+pub fn watch(opts: &crate::Opts) -> Result<()> {
+    let (events, received) = std::sync::mpsc::channel();
+    let stop = events.clone();
+    ctrlc::set_handler(move || {
+        let _ = stop.send(WatchEvent::Stop);
+    })?;
+    let _watcher = watch_spec(&opts.input, events)?;
+
+    let mut generator = Generator::new(opts.clone());
+    println!("{}", generator.generate());
+    println!("Watching {} for changes...", opts.input.display());
+    run(&received, DEBOUNCE, || println!("{}", generator.generate()));
+    println!("Stopped watching {}", opts.input.display());
+
+    Ok(())
+}
+
+/// Send a `WatchEvent::Changed` to `events` whenever the spec at `path` is
+/// written, created or removed. The directory of the spec is watched, since
+/// editors often replace the file rather than write to it. The spec is watched
+/// until the watcher is dropped.
+pub fn watch_spec(path: &Path, events: Sender<WatchEvent>) -> Result<notify::RecommendedWatcher> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| anyhow::anyhow!("`{}` is not a file", path.display()))?
+        .to_os_string();
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+
+    let mut watcher =
+        notify::recommended_watcher(move |event: notify::Result<notify::Event>| match event {
+            Ok(event) => {
+                let is_change = matches!(
+                    event.kind,
+                    notify::EventKind::Create(_)
+                        | notify::EventKind::Modify(_)
+                        | notify::EventKind::Remove(_)
+                );
+                if is_change
+                    && event
+                        .paths
+                        .iter()
+                        .any(|path| path.file_name() == Some(file_name.as_os_str()))
+                {
+                    let _ = events.send(WatchEvent::Changed);
+                }
+            }
+            Err(err) => log::warn!("watching the spec failed: {}", err),
+        })?;
+    notify::Watcher::watch(&mut watcher, &dir, notify::RecursiveMode::NonRecursive)?;
+
+    Ok(watcher)
+}
+
+/// Call `on_change` once the changes of `events` settle, once they are `debounce`
+/// apart, until a `WatchEvent::Stop` or the senders are all gone. Returns how many
+/// times it was called.
+pub fn run<F>(events: &Receiver<WatchEvent>, debounce: Duration, mut on_change: F) -> usize
+where
+    F: FnMut(),
+{
+    let mut changes = 0;
+    loop {
+        match events.recv() {
+            Ok(WatchEvent::Changed) => {}
+            Ok(WatchEvent::Stop) | Err(_) => return changes,
+        }
+
+        // Wait for the spec to be quiet, a stop wins over the changes before it.
+        loop {
+            match events.recv_timeout(debounce) {
+                Ok(WatchEvent::Changed) => {}
+                Ok(WatchEvent::Stop) | Err(RecvTimeoutError::Disconnected) => return changes,
+                Err(RecvTimeoutError::Timeout) => break,
+            }
+        }
+
+        on_change();
+        changes += 1;
+    }
+}
+
+/// Generates the library and describes what changed since the last generation
+/// that worked.
+#[derive(Debug)]
+pub struct Generator {
+    opts: crate::Opts,
+    previous: Option<crate::changelog::Manifest>,
+}
+
+impl Generator {
+    /// A generator with the options of `opts`, that has not generated anything yet.
+    pub fn new(opts: crate::Opts) -> Self {
+        Generator {
+            opts,
+            previous: None,
+        }
+    }
+
+    /// Load the spec and generate the library, then describe the outcome in a few
+    /// lines.
+    pub fn generate(&mut self) -> String {
+        let start = Instant::now();
+        let manifest = crate::load_api(&self.opts.input)
+            .and_then(|spec| crate::generate_library(&spec, &self.opts, false));
+        let elapsed = start.elapsed();
+
+        match manifest {
+            Ok(manifest) => {
+                let summary = self.summary(&manifest, elapsed);
+                self.previous = Some(manifest);
+                summary
+            }
+            Err(err) => format!(
+                "Generating {} failed after {:.1?}: {:#}",
+                self.opts.output.display(),
+                elapsed,
+                err
+            ),
+        }
+    }
+
+    /// Describe the generation of `manifest`, with the changes since the previous
+    /// one.
+    fn summary(&self, manifest: &crate::changelog::Manifest, elapsed: Duration) -> String {
+        let mut summary = format!(
+            "Generated {} in {:.1?}: {} functions, {} types",
+            self.opts.output.display(),
+            elapsed,
+            manifest.functions.len(),
+            manifest.types.len()
+        );
+        let Some(previous) = &self.previous else {
+            return summary;
+        };
+
+        let changes = crate::changelog::diff(previous, manifest);
+        if changes.is_empty() {
+            summary.push_str("\nNo changes to the public API.");
+            return summary;
+        }
+        for change in &changes {
+            if change.breaking {
+                summary.push_str(&format!("\n- BREAKING: {}", change.description));
+            } else {
+                summary.push_str(&format!("\n- {}", change.description));
+            }
+        }
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::mpsc::channel, time::Duration};
+
+    use super::{run, WatchEvent};
+
+    /// Send `events` with the pauses between them, in the background.
+    fn simulate(events: Vec<(WatchEvent, u64)>) -> std::sync::mpsc::Receiver<WatchEvent> {
+        let (sender, receiver) = channel();
+        std::thread::spawn(move || {
+            for (event, pause) in events {
+                std::thread::sleep(Duration::from_millis(pause));
+                if sender.send(event).is_err() {
+                    return;
+                }
+            }
+        });
+        receiver
+    }
+
+    #[test]
+    fn test_run_debounces_changes() {
+        // A burst of writes is one change, the quiet spell between the bursts
+        // makes two.
+        let events = simulate(vec![
+            (WatchEvent::Changed, 0),
+            (WatchEvent::Changed, 10),
+            (WatchEvent::Changed, 10),
+            (WatchEvent::Changed, 300),
+            (WatchEvent::Changed, 10),
+            (WatchEvent::Stop, 300),
+        ]);
+        let mut calls = 0;
+        assert_eq!(run(&events, Duration::from_millis(100), || calls += 1), 2);
+        assert_eq!(calls, 2);
+    }
+
+    #[test]
+    fn test_run_stops() {
+        // A stop in the middle of a burst wins, nothing is generated for it.
+        let events = simulate(vec![
+            (WatchEvent::Changed, 0),
+            (WatchEvent::Stop, 10),
+            (WatchEvent::Changed, 10),
+        ]);
+        assert_eq!(run(&events, Duration::from_millis(100), || {}), 0);
+
+        // So does the end of the events.
+        let events = simulate(vec![(WatchEvent::Changed, 0)]);
+        assert_eq!(run(&events, Duration::from_millis(100), || {}), 0);
+        let events = simulate(vec![]);
+        assert_eq!(run(&events, Duration::from_millis(100), || {}), 0);
+    }
+}
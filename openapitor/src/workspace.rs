@@ -0,0 +1,456 @@
+//! Generate the library as a workspace of crates, one per tag, with
+//! `--split-by-tag`.
+//!
+//! The workspace has:
+//!
+//! - `{name}-types`, the `types` module, with the helpers for sending requests.
+//! - `{name}-core`, the `Client`, with the `types` of `{name}-types`. Its library is
+//!   named like the crate we generate without `--split-by-tag`, so the docs and
+//!   examples we generate are the same.
+//! - `{name}-{tag}` for every tag, the module of the tag and an extension trait
+//!   giving the `Client` access to it.
+//!
+//! The tag crates use the internals of the `Client`, so they are public, but
+//! hidden from the docs.
+
+use anyhow::Result;
+
+/// The crates of a workspace generated with `--split-by-tag`.
+#[derive(Debug, Clone)]
+pub struct Workspace {
+    root: std::path::PathBuf,
+    package_name: String,
+    code_package_name: String,
+}
+
+impl Workspace {
+    /// The workspace of the options, if they split the library by tag.
+    pub fn new(opts: &crate::Opts) -> Option<Self> {
+        opts.split_by_tag.then(|| Workspace {
+            root: opts.output.clone(),
+            package_name: opts.package_name(),
+            code_package_name: opts.code_package_name(),
+        })
+    }
+
+    /// The name of the crate with the `Client`.
+    pub fn core_package(&self) -> String {
+        format!("{}-core", self.package_name)
+    }
+
+    /// The name of the crate with the types.
+    pub fn types_package(&self) -> String {
+        format!("{}-types", self.package_name)
+    }
+
+    /// The name of the crate of the tag with the module `module`.
+    pub fn tag_package(&self, module: &str) -> String {
+        format!("{}-{}", self.package_name, module.replace('_', "-"))
+    }
+
+    /// The directory of a crate.
+    pub fn crate_dir(&self, package: &str) -> std::path::PathBuf {
+        self.root.join(package)
+    }
+
+    /// The name the code of the crates uses for the types crate.
+    fn types_lib(&self) -> String {
+        inflector::cases::snakecase::to_snake_case(&self.types_package())
+    }
+
+    /// The `Cargo.toml` of the workspace.
+    pub fn cargo_toml(&self, tag_modules: &[String]) -> String {
+        let mut members = vec![self.core_package(), self.types_package()];
+        members.extend(tag_modules.iter().map(|module| self.tag_package(module)));
+        let members = members
+            .iter()
+            .map(|member| format!("    \"{}\",", member))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        format!(
+            r#"[workspace]
+resolver = "2"
+members = [
+{}
+]
+"#,
+            members
+        )
+    }
+
+    /// The `Cargo.toml` of the types crate, from the one of the whole library.
+    pub fn types_cargo_toml(&self, library: &str, opts: &crate::Opts) -> Result<String> {
+        let toml = self.member_cargo_toml(
+            library,
+            &self.types_package(),
+            Some(&format!("The types of the `{}` crate.", opts.name)),
+        )?;
+        to_cargo_toml(toml)
+    }
+
+    /// The `Cargo.toml` of the core crate, from the one of the whole library.
+    pub fn core_cargo_toml(&self, library: &str, opts: &crate::Opts) -> Result<String> {
+        let mut toml = self.member_cargo_toml(library, &self.core_package(), None)?;
+        let mut lib = toml::Table::new();
+        lib.insert(
+            "name".to_string(),
+            toml::Value::String(self.code_package_name.clone()),
+        );
+        toml.insert("lib".to_string(), toml::Value::Table(lib));
+        self.add_dependency(&mut toml, &self.types_package(), None, opts)?;
+        to_cargo_toml(toml)
+    }
+
+    /// The `Cargo.toml` of the crate of a tag, from the one of the whole library.
+    pub fn tag_cargo_toml(
+        &self,
+        library: &str,
+        module: &str,
+        opts: &crate::Opts,
+    ) -> Result<String> {
+        let mut toml = self.member_cargo_toml(
+            library,
+            &self.tag_package(module),
+            Some(&format!(
+                "The `{}` operations of the `{}` crate.",
+                module, opts.name
+            )),
+        )?;
+        // The code of the tag calls the core crate by the name of the library.
+        self.add_dependency(
+            &mut toml,
+            &self.code_package_name,
+            Some(&self.core_package()),
+            opts,
+        )?;
+        to_cargo_toml(toml)
+    }
+
+    /// The `Cargo.toml` of a crate of the workspace, with the dependencies and
+    /// features of the whole library, and its description unless one is given.
+    fn member_cargo_toml(
+        &self,
+        library: &str,
+        package: &str,
+        description: Option<&str>,
+    ) -> Result<toml::Table> {
+        let mut toml: toml::Table = library.parse()?;
+        let Some(toml::Value::Table(table)) = toml.get_mut("package") else {
+            anyhow::bail!("the generated `Cargo.toml` has no `[package]`");
+        };
+        table.insert("name".to_string(), toml::Value::String(package.to_string()));
+        if let Some(description) = description {
+            table.insert(
+                "description".to_string(),
+                toml::Value::String(description.to_string()),
+            );
+        }
+        table.insert(
+            "documentation".to_string(),
+            toml::Value::String(format!("https://docs.rs/{}", package)),
+        );
+        Ok(toml)
+    }
+
+    /// Depend on another crate of the workspace, under the name `name`, and turn
+    /// on its features with ours.
+    fn add_dependency(
+        &self,
+        toml: &mut toml::Table,
+        name: &str,
+        package: Option<&str>,
+        opts: &crate::Opts,
+    ) -> Result<()> {
+        let package = package.unwrap_or(name);
+        let mut dependency = toml::Table::new();
+        if package != name {
+            dependency.insert(
+                "package".to_string(),
+                toml::Value::String(package.to_string()),
+            );
+        }
+        dependency.insert(
+            "path".to_string(),
+            toml::Value::String(format!("../{}", package)),
+        );
+        dependency.insert(
+            "version".to_string(),
+            toml::Value::String(opts.target_version.to_string()),
+        );
+        // The features we turn on decide which ones it has.
+        dependency.insert("default-features".to_string(), toml::Value::Boolean(false));
+
+        let Some(toml::Value::Table(dependencies)) = toml.get_mut("dependencies") else {
+            anyhow::bail!("the generated `Cargo.toml` has no `[dependencies]`");
+        };
+        dependencies.insert(name.to_string(), toml::Value::Table(dependency));
+
+        let Some(toml::Value::Table(features)) = toml.get_mut("features") else {
+            anyhow::bail!("the generated `Cargo.toml` has no `[features]`");
+        };
+        for (feature, enables) in features.iter_mut() {
+            if feature == "default" {
+                continue;
+            }
+            if let toml::Value::Array(enables) = enables {
+                enables.push(toml::Value::String(format!("{}/{}", name, feature)));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The `lib.rs` of the types crate.
+    pub fn types_lib_rs(&self, opts: &crate::Opts) -> String {
+        let mut lib = format!(
+            "//! The types of the [`{}`](https://docs.rs/{}) crate.\n\n\
+             #![allow(elided_named_lifetimes)]\n\
+             {}\n\
+             #![allow(unused_imports)]\n\
+             #![cfg_attr(docsrs, feature(doc_cfg))]\n\n\
+             pub mod types;\n",
+            opts.name,
+            self.core_package(),
+            crate::lint_attributes(opts)
+        );
+        if opts.needs_utils_file() {
+            lib.push_str("pub mod utils;\n");
+        }
+        lib
+    }
+
+    /// The modules the core crate uses from the types crate, in place of the ones
+    /// it would have.
+    pub fn core_modules(&self, opts: &crate::Opts) -> Vec<String> {
+        let mut modules = vec![format!("pub use {}::types;", self.types_lib())];
+        if opts.needs_utils_file() {
+            modules.push(format!("pub use {}::utils;", self.types_lib()));
+        }
+        modules
+    }
+
+    /// The `lib.rs` of the crate of the tag with the module `module`.
+    pub fn tag_lib_rs(
+        &self,
+        module: &str,
+        description: Option<&str>,
+        opts: &crate::Opts,
+    ) -> String {
+        let proper_name = crate::types::proper_name(module);
+        let ext = format!("{}Ext", proper_name);
+        let core = &self.code_package_name;
+
+        let mut docs = format!("The `{}` operations of the `{}` API.", module, opts.name);
+        if let Some(d) = description {
+            docs = format!("{}\n\n{}.", docs, d.trim_end_matches('.'));
+        }
+
+        let utils = if opts.needs_utils_file() {
+            format!("pub use {}::utils;\n", core)
+        } else {
+            String::new()
+        };
+        let module_docs = if opts.strict_lints {
+            format!("/// The `{}` operations.\n", module)
+        } else {
+            String::new()
+        };
+
+        format!(
+            r#"//! {docs}
+//!
+//! Bring [`{ext}`] into scope to get them from the client, as in
+//! `client.{module}()`.
+
+#![allow(elided_named_lifetimes)]
+{lints}
+#![allow(unused_imports)]
+#![allow(clippy::needless_lifetimes)]
+#![allow(clippy::too_many_arguments)]
+#![cfg_attr(docsrs, feature(doc_cfg))]
+
+#[cfg(feature = "requests")]
+pub use {core}::Client;
+pub use {core}::types;
+{utils}
+{module_docs}#[cfg(feature = "requests")]
+pub mod {module};
+
+#[cfg(feature = "requests")]
+pub use {module}::{proper_name};
+
+/// Access to the `{module}` operations from the client.
+#[cfg(feature = "requests")]
+pub trait {ext} {{
+    /// Return a reference to an interface that provides access to {module} operations.
+    fn {module}(&self) -> {proper_name};
+}}
+
+#[cfg(feature = "requests")]
+impl {ext} for Client {{
+    fn {module}(&self) -> {proper_name} {{
+        {proper_name}::new(self.clone())
+    }}
+}}
+"#,
+            docs = docs.replace('\n', "\n//! "),
+            lints = crate::lint_attributes(opts),
+        )
+    }
+
+    /// The `README.md` of a crate of the workspace.
+    pub fn readme(&self, package: &str, description: &str) -> String {
+        format!(
+            "# `{}`\n\n{}\n\nPart of [`{}`](https://docs.rs/{}).\n",
+            package,
+            description,
+            self.core_package(),
+            self.core_package()
+        )
+    }
+}
+
+/// Write a `Cargo.toml` with its sections in the usual order, rather than the
+/// alphabetical one of `toml`.
+fn to_cargo_toml(mut toml: toml::Table) -> Result<String> {
+    let mut sections = Vec::new();
+    for key in [
+        "package",
+        "lib",
+        "dependencies",
+        "target",
+        "dev-dependencies",
+        "features",
+    ] {
+        if let Some(value) = toml.remove(key) {
+            let mut section = toml::Table::new();
+            section.insert(key.to_string(), value);
+            sections.push(toml::to_string(&section)?);
+        }
+    }
+    // Anything else goes last.
+    if !toml.is_empty() {
+        sections.push(toml::to_string(&toml)?);
+    }
+    Ok(sections.join("\n"))
+}
+
+/// Make the `pub(crate)` items of the types public but hidden, for the `Client` of
+/// the core crate to use.
+pub fn publish_crate_items(tokens: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+    let mut out = proc_macro2::TokenStream::new();
+    let mut tokens = tokens.into_iter().peekable();
+    while let Some(token) = tokens.next() {
+        match token {
+            proc_macro2::TokenTree::Ident(ident) if ident == "pub" => {
+                let is_crate = matches!(
+                    tokens.peek(),
+                    Some(proc_macro2::TokenTree::Group(g))
+                        if g.delimiter() == proc_macro2::Delimiter::Parenthesis
+                            && g.stream().to_string() == "crate"
+                );
+                if is_crate {
+                    tokens.next();
+                    out.extend(quote!(#[doc(hidden)]));
+                }
+                out.extend(quote!(#ident));
+            }
+            proc_macro2::TokenTree::Group(g) => {
+                let mut group =
+                    proc_macro2::Group::new(g.delimiter(), publish_crate_items(g.stream()));
+                group.set_span(g.span());
+                out.extend(quote!(#group));
+            }
+            token => out.extend(quote!(#token)),
+        }
+    }
+    out
+}
+
+/// Make the fields and helpers of the `Client` public but hidden, for the tag
+/// crates to send their requests with.
+pub fn publish_client_internals(client: &str) -> String {
+    let mut out = String::new();
+    let mut in_struct = false;
+    for line in client.split_inclusive('\n') {
+        if line.starts_with("pub struct Client {") {
+            in_struct = true;
+        } else if in_struct && line.starts_with('}') {
+            in_struct = false;
+        } else if in_struct
+            && line.starts_with("    ")
+            && !line.starts_with("     ")
+            && !line.trim_start().starts_with('#')
+            && !line.trim_start().starts_with("//")
+            && !line.trim_start().starts_with("pub ")
+        {
+            out.push_str("    #[doc(hidden)]\n    pub ");
+            out.push_str(line.trim_start());
+            continue;
+        }
+
+        if let Some(rest) = line.strip_prefix("    pub(crate) ") {
+            out.push_str("    #[doc(hidden)]\n    pub ");
+            out.push_str(rest);
+            continue;
+        }
+
+        out.push_str(line);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_publish_crate_items() {
+        let types = quote!(
+            pub mod cache {
+                pub(crate) fn key(request: &reqwest::Request) -> Option<String> {
+                    None
+                }
+
+                pub(super) fn get() {}
+            }
+        );
+
+        assert_eq!(
+            super::publish_crate_items(types).to_string(),
+            quote!(
+                pub mod cache {
+                    #[doc(hidden)]
+                    pub fn key(request: &reqwest::Request) -> Option<String> {
+                        None
+                    }
+
+                    pub(super) fn get() {}
+                }
+            )
+            .to_string()
+        );
+    }
+
+    #[test]
+    fn test_publish_client_internals() {
+        let client = r#"pub struct Client {
+    token: String,
+    #[cfg(feature = "retry")]
+    client: reqwest_middleware::ClientWithMiddleware,
+    pub max_pages: usize,
+}
+
+impl Client {
+    pub(crate) fn cookie_header(&self) -> Option<String> {
+        let token: String = self.token.clone();
+        None
+    }
+}
+"#;
+
+        expectorate::assert_contents(
+            "tests/types/split-by-tag.client.rs.gen",
+            &super::publish_client_internals(client),
+        );
+    }
+}
@@ -0,0 +1,41 @@
+//! Types of another crate, that look like the mock types but are not quite the same.
+
+use std::collections::HashMap;
+
+/// A thing, the mock `Thing` calls its title a name.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Item {
+    pub id: String,
+    pub title: String,
+    pub kind: Option<Kind>,
+}
+
+/// The kind of a thing, with one the mock does not know about.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    Widget,
+    Gadget,
+    BigBox,
+    Gizmo,
+}
+
+/// A shelf to put things on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Shelf {
+    pub contents: Vec<ShelfItem>,
+    pub labels: HashMap<String, Label>,
+}
+
+/// Something on a shelf.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ShelfItem {
+    Book { title: String },
+    Plant { species: String },
+}
+
+/// The label of a position on a shelf.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Label {
+    Text { text: String },
+    Number { number: i64 },
+}
@@ -0,0 +1,25 @@
+use pretty_assertions::assert_eq;
+
+#[test]
+fn test_colliding_schemas_are_distinct_types() {
+    let session: crate::types::Session = serde_json::from_str(
+        r#"{
+            "internal": {"access_token": "abc", "expires_in": 3600},
+            "external": {"providerToken": "xyz", "provider": "github"},
+            "history": [{"providerToken": "old", "provider": "google"}]
+        }"#,
+    )
+    .unwrap();
+
+    let internal: crate::types::OauthToken = session.internal.clone();
+    let external: crate::types::OauthToken2 = session.external.clone();
+    assert_eq!(internal.access_token, "abc");
+    assert_eq!(internal.expires_in, 3600);
+    assert_eq!(external.provider_token, "xyz");
+    assert_eq!(external.provider, "github");
+    assert_eq!(session.history[0].provider_token, "old");
+
+    // Make sure the renamed type still uses the wire names.
+    let value = serde_json::to_value(&external).unwrap();
+    assert_eq!(value["providerToken"], "xyz");
+}
@@ -0,0 +1,51 @@
+#![allow(deprecated)]
+
+use pretty_assertions::assert_eq;
+
+#[test]
+fn test_renamed_types_keep_their_old_names() {
+    let widget: crate::compat::Widget = crate::types::Gadget {
+        id: "a-widget".to_string(),
+        parts: vec![crate::compat::Part {
+            name: "a-part".to_string(),
+            count: 2,
+        }],
+    };
+    let gadget: crate::types::Gadget = widget;
+    assert_eq!(gadget.parts[0].count, 2);
+}
+
+#[tokio::test]
+async fn test_renamed_functions_keep_their_old_names() {
+    let (base_url, request_line) = {
+        use std::io::{BufRead, BufReader, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let handle = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut line = String::new();
+            BufReader::new(&stream).read_line(&mut line).unwrap();
+            let body = r#"{"id": "a-widget", "parts": []}"#;
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            stream.write_all(response.as_bytes()).unwrap();
+            line
+        });
+        (format!("http://{addr}"), handle)
+    };
+
+    let mut client = crate::Client::new("some-token");
+    client.set_base_url(base_url);
+
+    // The old name calls the new one.
+    let widget = client.widgets().get("a-widget").await.unwrap();
+    assert_eq!(widget.id, "a-widget");
+    assert_eq!(
+        request_line.join().unwrap().trim_end(),
+        "GET /widgets/a-widget HTTP/1.1"
+    );
+}
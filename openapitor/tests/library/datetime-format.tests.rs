@@ -0,0 +1,76 @@
+use std::{
+    io::{BufRead, BufReader, Write},
+    str::FromStr,
+    sync::mpsc,
+};
+
+use chrono::TimeZone;
+use pretty_assertions::assert_eq;
+
+/// Start a tiny HTTP server on localhost that answers every request with an event,
+/// its date-times in the format of the API, and sends back the request line of
+/// every request.
+fn mock_server() -> (String, mpsc::Receiver<String>) {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (sender, receiver) = mpsc::channel();
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let lines = BufReader::new(&stream)
+                .lines()
+                .map(|line| line.unwrap())
+                .take_while(|line| !line.is_empty())
+                .collect::<Vec<_>>();
+            sender.send(lines[0].clone()).unwrap();
+
+            let body = r#"{
+                "id": "d9797f8d-9ad6-4e08-90d7-2ec17e13471c",
+                "created_at": "20240102T030405Z",
+                "day": "2024-01-02",
+                "starts": "03:04:05",
+                "local": "2024-01-02T03:04:05"
+            }"#;
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            stream.write_all(response.as_bytes()).unwrap();
+        }
+    });
+
+    (format!("http://{addr}"), receiver)
+}
+
+#[tokio::test]
+async fn test_date_time_params_use_the_format() {
+    let (base_url, requests) = mock_server();
+    let mut client = crate::Client::new("some-token");
+    client.set_base_url(base_url);
+
+    let after = chrono::Utc.with_ymd_and_hms(2024, 1, 2, 3, 4, 5).unwrap();
+    let before = chrono::Utc.with_ymd_and_hms(2024, 2, 3, 4, 5, 6).unwrap();
+    let event = client.events().get_after(after, before).await.unwrap();
+    assert_eq!(event.created_at, after);
+    assert_eq!(
+        requests.recv().unwrap(),
+        "GET /days/20240102T030405Z/events?before=20240203T040506Z HTTP/1.1"
+    );
+
+    let id = crate::types::ids::Uuid::from_str("d9797f8d-9ad6-4e08-90d7-2ec17e13471c").unwrap();
+    let day = crate::types::datetime::Date::from_str("2024-01-02").unwrap();
+    client.events().get(day, id, Some(after)).await.unwrap();
+    assert_eq!(
+        requests.recv().unwrap(),
+        "GET /events/d9797f8d-9ad6-4e08-90d7-2ec17e13471c?day=2024-01-02&since=20240102T030405Z HTTP/1.1"
+    );
+
+    // Without a date-time there is nothing to format.
+    client.events().get(day, id, None).await.unwrap();
+    assert_eq!(
+        requests.recv().unwrap(),
+        "GET /events/d9797f8d-9ad6-4e08-90d7-2ec17e13471c?day=2024-01-02 HTTP/1.1"
+    );
+}
@@ -0,0 +1,29 @@
+use std::str::FromStr;
+
+use pretty_assertions::assert_eq;
+
+#[test]
+fn test_datetime_types_round_trip() {
+    let json = serde_json::json!({
+        "id": "d9797f8d-9ad6-4e08-90d7-2ec17e13471c",
+        "created_at": "2024-01-02T03:04:05Z",
+        "day": "2024-01-02",
+        "starts": "03:04:05",
+        "local": "2024-01-02T03:04:05"
+    });
+
+    let event: crate::types::Event = serde_json::from_value(json.clone()).unwrap();
+    assert_eq!(serde_json::to_value(&event).unwrap(), json);
+
+    // Whatever backs the types, the dates and times display as they are sent.
+    assert_eq!(event.day.to_string(), "2024-01-02");
+    assert_eq!(event.starts.to_string(), "03:04:05");
+    assert_eq!(
+        crate::types::datetime::Date::from_str("2024-01-02").unwrap(),
+        event.day
+    );
+    assert_eq!(
+        crate::types::ids::Uuid::from_str("d9797f8d-9ad6-4e08-90d7-2ec17e13471c").unwrap(),
+        event.id
+    );
+}
@@ -0,0 +1,9 @@
+use pretty_assertions::assert_eq;
+
+#[test]
+fn test_report_with_odd_descriptions() {
+    let report: crate::types::Report =
+        serde_json::from_str(r#"{"id": "abc", "status": "done"}"#).unwrap();
+    assert_eq!(report.id, "abc");
+    assert_eq!(report.status, crate::types::ReportStatus::Done);
+}
@@ -0,0 +1,117 @@
+use std::{
+    io::{BufRead, BufReader, Write},
+    sync::mpsc,
+};
+
+use futures::TryStreamExt;
+use pretty_assertions::assert_eq;
+
+/// Start a tiny HTTP server on localhost that answers every request with a thing,
+/// or two pages of things for the parts, and sends back the request line and the
+/// headers of every request.
+fn mock_server() -> (String, mpsc::Receiver<Vec<String>>) {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (sender, receiver) = mpsc::channel();
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let lines = BufReader::new(&stream)
+                .lines()
+                .map(|line| line.unwrap())
+                .take_while(|line| !line.is_empty())
+                .collect::<Vec<_>>();
+            let thing = r#"{"id": "a-thing", "name": "A thing"}"#;
+            let body = if lines[0].contains("next_page=p2") {
+                format!(r#"{{"items": [{}]}}"#, thing)
+            } else if lines[0].contains("/parts") {
+                format!(r#"{{"items": [{}], "next_page": "p2"}}"#, thing)
+            } else {
+                thing.to_string()
+            };
+            sender.send(lines).unwrap();
+
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            stream.write_all(response.as_bytes()).unwrap();
+        }
+    });
+
+    (format!("http://{addr}"), receiver)
+}
+
+#[tokio::test]
+async fn test_strings_are_passed_as_they_are() {
+    let (base_url, requests) = mock_server();
+    let mut client = crate::Client::new("some-token");
+    client.set_base_url(base_url);
+
+    let id = uuid::Uuid::parse_str("6b3b5b3a-8f0f-4a2e-9d35-cb4e3bd3d6a1").unwrap();
+    let owned = id.to_string();
+
+    // A `&str`, a `String`, a `&String` and the UUID itself.
+    client.things().get(owned.as_str()).await.unwrap();
+    client.things().get(owned.clone()).await.unwrap();
+    client.things().get(&id.to_string()).await.unwrap();
+    client.things().get(id).await.unwrap();
+
+    // They are all sent the same.
+    for _ in 0..4 {
+        assert_eq!(
+            requests.recv().unwrap()[0],
+            format!("GET /things/{} HTTP/1.1", id)
+        );
+    }
+
+    // So are the ones of the streams, for every page.
+    let parts = client
+        .things()
+        .list_parts_stream(id)
+        .try_collect::<Vec<_>>()
+        .await
+        .unwrap();
+    assert_eq!(parts.len(), 2);
+    assert_eq!(
+        requests.recv().unwrap()[0],
+        format!("GET /things/{}/parts HTTP/1.1", id)
+    );
+    assert_eq!(
+        requests.recv().unwrap()[0],
+        format!("GET /things/{}/parts?next_page=p2 HTTP/1.1", id)
+    );
+
+    // The optional strings are borrowed.
+    let request_id = String::from("request-1");
+    client
+        .things()
+        .get_secret(&request_id, id, "session-1", Some("dark"), None)
+        .await
+        .unwrap();
+    let request = requests.recv().unwrap();
+    assert_eq!(request[0], format!("GET /things/{}/secret HTTP/1.1", id));
+    assert!(request.contains(&"x-request-id: request-1".to_string()));
+    assert!(request.contains(&"cookie: session=session-1; theme=dark".to_string()));
+}
+
+#[test]
+fn test_strict_enums() {
+    use crate::types::ThingKind;
+
+    // Without `--case-insensitive-enums` only the values of the spec parse.
+    assert_eq!(
+        serde_json::from_str::<ThingKind>(r#""Gadget""#).unwrap(),
+        ThingKind::Gadget
+    );
+    for payload in [r#""gadget""#, r#""WIDGET""#, r#""Big-Box""#] {
+        assert!(
+            serde_json::from_str::<ThingKind>(payload).is_err(),
+            "{}",
+            payload
+        );
+    }
+    assert!("gadget".parse::<ThingKind>().is_err());
+}
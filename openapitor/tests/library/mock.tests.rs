@@ -0,0 +1,3250 @@
+use std::{
+    io::{Read, Write},
+    sync::{Arc, Mutex},
+};
+
+use pretty_assertions::assert_eq;
+
+/// A request the mock server received.
+#[derive(Debug, Clone)]
+struct MockRequest {
+    method: String,
+    path: String,
+    headers: Vec<(String, String)>,
+    body: String,
+    /// The body as it was sent, it may not be text.
+    raw_body: Vec<u8>,
+    /// The index of the connection the request came on.
+    connection: usize,
+    received_at: std::time::Instant,
+}
+
+impl MockRequest {
+    /// Get the value of a header, if it was sent.
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Every value of the header `name`, in the order they were sent.
+    fn header_values(&self, name: &str) -> Vec<&str> {
+        self.headers
+            .iter()
+            .filter(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+            .collect()
+    }
+}
+
+/// Start a tiny HTTP server on localhost that answers every request with the
+/// status and body returned by `respond`, the same `ETag`, a rate limit that
+/// goes down by one with every request, and a request id `mock-{count}`. Connections are kept alive, and every
+/// request records which connection it came on.
+fn mock_server<F>(respond: F) -> (String, Arc<Mutex<Vec<MockRequest>>>)
+where
+    F: Fn(&str) -> (u16, String) + Send + Sync + 'static,
+{
+    mock_server_with_headers(move |path| {
+        let (status, body) = respond(path);
+        (status, body, Vec::new())
+    })
+}
+
+/// Like `mock_server`, with the headers returned by `respond` too. A
+/// `Content-Type` there replaces the JSON one.
+fn mock_server_with_headers<F>(respond: F) -> (String, Arc<Mutex<Vec<MockRequest>>>)
+where
+    F: Fn(&str) -> (u16, String, Vec<(&'static str, String)>) + Send + Sync + 'static,
+{
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let requests: Arc<Mutex<Vec<MockRequest>>> = Default::default();
+
+    let received = requests.clone();
+    let respond = Arc::new(respond);
+    std::thread::spawn(move || {
+        for (connection, stream) in listener.incoming().enumerate() {
+            let mut stream = stream.unwrap();
+            let received = received.clone();
+            let respond = respond.clone();
+            std::thread::spawn(move || {
+                while let Some(request) = read_request(&mut stream, connection) {
+                    let path = request.path.clone();
+                    let count = {
+                        let mut received = received.lock().unwrap();
+                        received.push(request);
+                        received.len()
+                    };
+
+                    // Every request uses up some of the rate limit.
+                    let (status, body, headers) = respond(&path);
+                    let mut extra = String::new();
+                    if !headers
+                        .iter()
+                        .any(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+                    {
+                        extra.push_str("Content-Type: application/json\r\n");
+                    }
+                    for (name, value) in headers {
+                        extra.push_str(&format!("{name}: {value}\r\n"));
+                    }
+                    let response = format!(
+                        "HTTP/1.1 {} Mock\r\n{}Content-Length: {}\r\nX-RateLimit-Limit: 100\r\nX-RateLimit-Remaining: {}\r\nX-RateLimit-Reset: 60\r\nX-Request-Id: mock-{}\r\nETag: \"v1\"\r\n\r\n{}",
+                        status,
+                        extra,
+                        body.len(),
+                        100 - count,
+                        count,
+                        body
+                    );
+                    if stream.write_all(response.as_bytes()).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+    });
+
+    (format!("http://{addr}"), requests)
+}
+
+/// Read the next request on a connection, `None` once the client closed it.
+fn read_request(stream: &mut std::net::TcpStream, connection: usize) -> Option<MockRequest> {
+    // Read the request head, and the body if it has a length.
+    let mut head = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !head.windows(4).any(|w| w == b"\r\n\r\n") {
+        match stream.read(&mut buf) {
+            Ok(0) | Err(_) if head.is_empty() => return None,
+            Ok(0) | Err(_) => break,
+            Ok(n) => head.extend_from_slice(&buf[..n]),
+        }
+    }
+    let end = head
+        .windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .map_or(head.len(), |p| p + 4);
+    let mut body = head.split_off(end);
+    let head = String::from_utf8_lossy(&head);
+    let mut request_line = head.split_whitespace();
+    let method = request_line.next().unwrap_or_default().to_string();
+    let path = request_line.next().unwrap_or_default().to_string();
+    let headers = head
+        .lines()
+        .skip(1)
+        .filter_map(|line| line.split_once(':'))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .collect::<Vec<(String, String)>>();
+    let length = headers
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, value)| value.parse::<usize>().ok())
+        .unwrap_or_default();
+    while body.len() < length {
+        match stream.read(&mut buf) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => body.extend_from_slice(&buf[..n]),
+        }
+    }
+
+    Some(MockRequest {
+        method,
+        path,
+        headers,
+        body: String::from_utf8_lossy(&body).to_string(),
+        raw_body: body,
+        connection,
+        received_at: std::time::Instant::now(),
+    })
+}
+
+#[tokio::test]
+async fn test_accept_header_per_operation() {
+    let (base_url, requests) = mock_server(|path| {
+        if path.ends_with("/note") {
+            (200, "a note".to_string())
+        } else if path.ends_with("/blob") {
+            (200, String::new())
+        } else {
+            (200, r#"{"id": "good", "name": "A good thing"}"#.to_string())
+        }
+    });
+
+    let mut client = crate::Client::new("some-token");
+    client.set_base_url(base_url);
+
+    client.things().get("good").await.unwrap();
+    client.things().get_blob("good").await.unwrap();
+    let note = client.things().get_note("good").await.unwrap();
+    assert_eq!(note, "a note");
+
+    let requests = requests.lock().unwrap().clone();
+    let accepts: Vec<_> = requests
+        .iter()
+        .map(|r| (r.path.as_str(), r.header("accept")))
+        .collect();
+    assert_eq!(
+        accepts,
+        vec![
+            ("/things/good", Some("application/json")),
+            (
+                "/things/good/blob",
+                Some("application/octet-stream, application/pdf")
+            ),
+            ("/things/good/note", Some("text/plain")),
+        ]
+    );
+
+    // None of these requests have a body, so they should not claim to.
+    for request in &requests {
+        assert_eq!(request.header("content-type"), None, "{}", request.path);
+    }
+}
+
+#[tokio::test]
+async fn test_request_raw_content_type_only_with_body() {
+    let (base_url, requests) = mock_server(|_| (200, String::new()));
+
+    let mut client = crate::Client::new("some-token");
+    client.set_base_url(base_url);
+
+    client
+        .request_raw(reqwest::Method::GET, "/things/good", None)
+        .await
+        .unwrap()
+        .send()
+        .await
+        .unwrap();
+    client
+        .request_raw(
+            reqwest::Method::POST,
+            "/things/good",
+            Some(reqwest::Body::from("{}")),
+        )
+        .await
+        .unwrap()
+        .send()
+        .await
+        .unwrap();
+
+    let requests = requests.lock().unwrap().clone();
+    assert_eq!(requests[0].header("content-type"), None);
+    assert_eq!(requests[1].header("content-type"), Some("application/json"));
+}
+
+#[tokio::test]
+async fn test_server_variables() {
+    let (base_url, requests) =
+        mock_server(|_| (200, r#"{"id": "good", "name": "A good thing"}"#.to_string()));
+
+    // The base url defaults to the url of the server with the defaults of its
+    // variables.
+    let mut client = crate::Client::new("some-token");
+    assert_eq!(client.base_url, "http://api.example.com/us-east");
+
+    // Another region is another path on the mock server.
+    client
+        .set_server_variable("host", base_url.trim_start_matches("http://"))
+        .unwrap();
+    client.set_server_variable("region", "eu-west").unwrap();
+    client.things().get("good").await.unwrap();
+
+    // A region that is not one of the regions of the server is an error, and the
+    // base url stays the same.
+    assert_eq!(
+        client.set_server_variable("region", "mars").unwrap_err(),
+        crate::types::servers::ServerVariableError::NotAllowed {
+            name: "region".to_string(),
+            value: "mars".to_string(),
+            allowed: "us-east, eu-west".to_string(),
+        }
+    );
+    assert!(matches!(
+        client.set_server_variable("zone", "a"),
+        Err(crate::types::servers::ServerVariableError::Unknown(_))
+    ));
+    client.things().get("good").await.unwrap();
+
+    // A base url set by hand is not the url of the server, it stays as it is.
+    client.set_base_url(format!("{}/proxy/eu-west", base_url));
+    assert_eq!(
+        client.set_server_variable("region", "us-east").unwrap_err(),
+        crate::types::servers::ServerVariableError::CustomBaseUrl(format!(
+            "{}/proxy/eu-west",
+            base_url
+        ))
+    );
+    client.things().get("good").await.unwrap();
+
+    let requests = requests.lock().unwrap().clone();
+    let paths: Vec<_> = requests.iter().map(|r| r.path.as_str()).collect();
+    assert_eq!(
+        paths,
+        vec![
+            "/eu-west/things/good",
+            "/eu-west/things/good",
+            "/proxy/eu-west/things/good"
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_call_options() {
+    use crate::types::call_options::{CallOptions, DefaultHeader};
+
+    let (base_url, requests) = mock_server(|path| {
+        if path.ends_with("/tags") {
+            (204, String::new())
+        } else {
+            (200, r#"{"id": "good", "name": "A good thing"}"#.to_string())
+        }
+    });
+
+    let mut client = crate::Client::new("some-token");
+    client.set_base_url(base_url);
+
+    // A normal call.
+    client.things().get("good").await.unwrap();
+    // Without our headers, reqwest sends its own `Accept`.
+    let bare = client.with_call_options(CallOptions {
+        accept: DefaultHeader::Suppress,
+        content_type: DefaultHeader::Suppress,
+        ..Default::default()
+    });
+    bare.things().get("good").await.unwrap();
+    bare.things()
+        .set_tags("good", &vec!["tag".to_string()])
+        .await
+        .unwrap();
+    // Other headers.
+    let mut headers = reqwest::header::HeaderMap::new();
+    headers.insert("x-signature", "abc".parse().unwrap());
+    client
+        .with_call_options(CallOptions {
+            accept: DefaultHeader::Override("application/vnd.thing+json".parse().unwrap()),
+            headers,
+            ..Default::default()
+        })
+        .things()
+        .get("good")
+        .await
+        .unwrap();
+    // The client keeps its headers.
+    client
+        .things()
+        .set_tags("good", &vec!["tag".to_string()])
+        .await
+        .unwrap();
+
+    let requests = requests.lock().unwrap().clone();
+    let headers: Vec<_> = requests
+        .iter()
+        .map(|r| {
+            (
+                r.method.as_str(),
+                r.header("accept"),
+                r.header("content-type"),
+                r.header("x-signature"),
+            )
+        })
+        .collect();
+    assert_eq!(
+        headers,
+        vec![
+            ("GET", Some("application/json"), None, None),
+            ("GET", Some("*/*"), None, None),
+            ("PUT", Some("*/*"), None, None),
+            ("GET", Some("application/vnd.thing+json"), None, Some("abc")),
+            ("PUT", Some("*/*"), Some("application/json"), None),
+        ]
+    );
+    assert_eq!(requests[2].body, r#"["tag"]"#);
+}
+
+#[cfg(feature = "metrics")]
+#[derive(Debug, Default)]
+struct CountingMetrics {
+    calls: Mutex<Vec<crate::types::metrics::ApiCall>>,
+}
+
+#[cfg(feature = "metrics")]
+impl crate::types::metrics::ApiMetrics for CountingMetrics {
+    fn record(&self, call: &crate::types::metrics::ApiCall) {
+        self.calls.lock().unwrap().push(call.clone());
+    }
+}
+
+#[cfg(feature = "metrics")]
+#[tokio::test]
+async fn test_metrics_record_success_and_error() {
+    let (base_url, requests) = mock_server(|path| {
+        if path == "/things/good" {
+            (200, r#"{"id": "good", "name": "A good thing"}"#.to_string())
+        } else {
+            (500, r#"{"message": "oops"}"#.to_string())
+        }
+    });
+
+    let metrics = Arc::new(CountingMetrics::default());
+    let mut client = crate::Client::new("some-token");
+    client.set_base_url(base_url);
+    client.set_metrics(metrics.clone());
+
+    let thing = client.things().get("good").await.unwrap();
+    assert_eq!(thing.name, "A good thing");
+
+    let err = client.things().get("bad").await.unwrap_err();
+    assert_eq!(
+        err.status(),
+        Some(reqwest::StatusCode::INTERNAL_SERVER_ERROR)
+    );
+
+    let calls = metrics.calls.lock().unwrap().clone();
+    assert_eq!(calls.len(), 2);
+
+    let success = &calls[0];
+    assert!(success.success);
+    assert_eq!(success.operation_id, "get_thing");
+    assert_eq!(success.method, reqwest::Method::GET);
+    assert_eq!(success.status, Some(reqwest::StatusCode::OK));
+
+    let error = &calls[1];
+    assert!(!error.success);
+    assert_eq!(error.operation_id, "get_thing");
+    assert_eq!(
+        error.status,
+        Some(reqwest::StatusCode::INTERNAL_SERVER_ERROR)
+    );
+
+    // The failed call is retried, the timer should cover all the attempts.
+    let requests = requests.lock().unwrap().clone();
+    let retries: Vec<_> = requests
+        .iter()
+        .filter(|r| r.path == "/things/bad")
+        .collect();
+    assert!(retries.len() > 1);
+    let first = retries.first().unwrap().received_at;
+    let last = retries.last().unwrap().received_at;
+    assert!(error.duration >= last - first);
+}
+
+#[cfg(feature = "websocket")]
+#[tokio::test]
+async fn test_websocket_reconnects_and_resumes() {
+    use tokio_tungstenite::tungstenite::{
+        protocol::{frame::coding::CloseCode, CloseFrame},
+        Message,
+    };
+
+    use crate::types::websocket::{ReconnectPolicy, WebSocketError};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let pongs: Arc<Mutex<Vec<Vec<u8>>>> = Default::default();
+
+    let received_pongs = pongs.clone();
+    std::thread::spawn(move || {
+        // The first connection pings, sends a message and then goes away.
+        let (stream, _) = listener.accept().unwrap();
+        let mut ws = tokio_tungstenite::tungstenite::accept(stream).unwrap();
+        ws.send(Message::Ping(b"are you there".to_vec())).unwrap();
+        match ws.read().unwrap() {
+            Message::Pong(data) => received_pongs.lock().unwrap().push(data),
+            msg => panic!("expected a pong, got {msg:?}"),
+        }
+        ws.send(Message::Text("one".to_string())).unwrap();
+        ws.close(Some(CloseFrame {
+            code: CloseCode::Away,
+            reason: "restarting".into(),
+        }))
+        .unwrap();
+        // Wait for the client to acknowledge the close.
+        while ws.read().is_ok() {}
+
+        // The second connection gets the resume message and then finishes for good.
+        let (stream, _) = listener.accept().unwrap();
+        let mut ws = tokio_tungstenite::tungstenite::accept(stream).unwrap();
+        match ws.read().unwrap() {
+            Message::Text(text) => ws.send(Message::Text(format!("resumed: {text}"))).unwrap(),
+            msg => panic!("expected the resume message, got {msg:?}"),
+        }
+        ws.send(Message::Text("two".to_string())).unwrap();
+        ws.close(Some(CloseFrame {
+            code: CloseCode::Normal,
+            reason: "done".into(),
+        }))
+        .unwrap();
+        while ws.read().is_ok() {}
+    });
+
+    let mut client = crate::Client::new("some-token");
+    client.set_base_url(format!("http://{addr}"));
+
+    let mut ws = client
+        .things()
+        .ws_builder(None)
+        .reconnect(ReconnectPolicy {
+            max_attempts: 3,
+            initial_backoff: std::time::Duration::from_millis(10),
+            max_backoff: std::time::Duration::from_millis(100),
+        })
+        .resume_message(Message::Text("from one".to_string()))
+        .connect()
+        .await
+        .unwrap();
+
+    let mut texts = Vec::new();
+    let err = loop {
+        match ws.recv().await {
+            Some(Ok(Message::Text(text))) => texts.push(text),
+            Some(Ok(msg)) => panic!("unexpected message {msg:?}"),
+            Some(Err(err)) => break err,
+            None => panic!("the connection ended without an error"),
+        }
+    };
+
+    assert_eq!(texts, vec!["one", "resumed: from one", "two"]);
+    assert_eq!(*pongs.lock().unwrap(), vec![b"are you there".to_vec()]);
+    match err {
+        WebSocketError::Closed { code, reason } => {
+            assert_eq!(code, CloseCode::Normal);
+            assert_eq!(reason, "done");
+        }
+        err => panic!("expected a close, got {err:?}"),
+    }
+    assert!(ws.recv().await.is_none());
+}
+
+#[cfg(feature = "websocket")]
+#[tokio::test]
+async fn test_upgrade_websocket() {
+    use futures::{SinkExt, StreamExt};
+    use tokio_tungstenite::tungstenite::{
+        handshake::server::{Request, Response},
+        protocol::Role,
+        Message,
+    };
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut seen = Vec::new();
+        let mut ws = tokio_tungstenite::tungstenite::accept_hdr(
+            stream,
+            |request: &Request, mut response: Response| {
+                seen.push(request.uri().path().to_string());
+                for name in ["authorization", "cookie", "sec-websocket-protocol"] {
+                    seen.push(
+                        request
+                            .headers()
+                            .get(name)
+                            .map(|value| value.to_str().unwrap().to_string())
+                            .unwrap_or_default(),
+                    );
+                }
+                response
+                    .headers_mut()
+                    .insert("Sec-WebSocket-Protocol", "echo".parse().unwrap());
+                Ok(response)
+            },
+        )
+        .unwrap();
+
+        // Echo a frame.
+        let message = ws.read().unwrap();
+        ws.send(message).unwrap();
+        seen
+    });
+
+    let mut client = crate::Client::new("some-token");
+    client.set_base_url(format!("http://{addr}"));
+    client.set_cookie("tenant", "zoo");
+    let mut headers = reqwest::header::HeaderMap::new();
+    headers.insert(
+        reqwest::header::SEC_WEBSOCKET_PROTOCOL,
+        "echo".parse().unwrap(),
+    );
+
+    // The client can be moved to another task to upgrade there.
+    let upgraded =
+        tokio::spawn(async move { client.upgrade_websocket("/custom/echo", headers).await })
+            .await
+            .unwrap()
+            .unwrap();
+    let mut ws =
+        tokio_tungstenite::WebSocketStream::from_raw_socket(upgraded, Role::Client, None).await;
+    ws.send(Message::Text("hello".to_string())).await.unwrap();
+    assert_eq!(
+        ws.next().await.unwrap().unwrap(),
+        Message::Text("hello".to_string())
+    );
+
+    assert_eq!(
+        server.join().unwrap(),
+        ["/custom/echo", "Bearer some-token", "tenant=zoo", "echo"]
+    );
+}
+
+#[cfg(feature = "websocket")]
+#[tokio::test]
+async fn test_upgrade_websocket_refused() {
+    let (base_url, requests) = mock_server(|_| (404, r#"{"message": "nope"}"#.to_string()));
+
+    let mut client = crate::Client::new("some-token");
+    client.set_base_url(base_url);
+    let err = client
+        .upgrade_websocket("custom/echo", Default::default())
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(&err, crate::types::error::Error::UnexpectedResponse(resp) if resp.status() == 404),
+        "{:?}",
+        err
+    );
+
+    let requests = requests.lock().unwrap().clone();
+    assert_eq!(requests[0].path, "/custom/echo");
+    assert_eq!(requests[0].header("upgrade"), Some("websocket"));
+    assert_eq!(requests[0].header("sec-websocket-version"), Some("13"));
+    assert_eq!(
+        requests[0].header("sec-websocket-key").map(|key| key.len()),
+        Some(24)
+    );
+}
+
+#[tokio::test]
+async fn test_post_beside_websocket() {
+    let (base_url, requests) = mock_server(|_| (200, r#"{"id": "a", "name": "A"}"#.to_string()));
+    let mut client = crate::Client::new("some-token");
+    client.set_base_url(base_url);
+
+    // The POST on the path of the websocket is a plain JSON request.
+    let cmd_id = uuid::Uuid::new_v4();
+    let thing = client
+        .things()
+        .ws_command(&crate::types::ThingCommandReq {
+            cmd: crate::types::ThingCommand::StartSession {},
+            cmd_id,
+        })
+        .await
+        .unwrap();
+    assert_eq!(thing.id, "a");
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests[0].method, "POST");
+    assert_eq!(requests[0].path, "/ws/things");
+    assert_eq!(requests[0].header("upgrade"), None);
+    assert_eq!(requests[0].header("content-type"), Some("application/json"));
+    assert_eq!(
+        requests[0].body,
+        format!(r#"{{"cmd":{{"type":"start_session"}},"cmd_id":"{}"}}"#, cmd_id)
+    );
+}
+
+#[cfg(feature = "websocket")]
+#[tokio::test]
+async fn test_session_checks_the_order_of_commands() {
+    use tokio_tungstenite::tungstenite::Message;
+
+    use crate::types::{session::SessionError, ThingCommand, ThingCommandReq, ThingRequest};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let received: Arc<Mutex<Vec<serde_json::Value>>> = Default::default();
+
+    let messages = received.clone();
+    let server = std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut ws = tokio_tungstenite::tungstenite::accept(stream).unwrap();
+        while let Ok(message) = ws.read() {
+            if let Message::Text(text) = message {
+                messages
+                    .lock()
+                    .unwrap()
+                    .push(serde_json::from_str(&text).unwrap());
+            }
+        }
+    });
+
+    let mut client = crate::Client::new("some-token");
+    client.set_base_url(format!("http://{addr}"));
+    let mut session =
+        crate::things::Session::new(client.things().ws_builder(None).connect().await.unwrap());
+
+    let command = |cmd| ThingRequest::Command {
+        cmd,
+        cmd_id: uuid::Uuid::new_v4(),
+    };
+    let draw = || ThingCommand::Draw {
+        shape: "circle".to_string(),
+    };
+
+    // Nothing goes before the session starts.
+    match session.send(&command(draw())).await {
+        Err(SessionError::OutOfOrder { command, missing }) => {
+            assert_eq!(command, "draw");
+            assert_eq!(missing, vec!["set_units", "start_session"]);
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+    session
+        .send(&command(ThingCommand::StartSession {}))
+        .await
+        .unwrap();
+
+    // Shapes need units.
+    match session.send(&command(draw())).await {
+        Err(SessionError::OutOfOrder { command, missing }) => {
+            assert_eq!(command, "draw");
+            assert_eq!(missing, vec!["set_units"]);
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+    assert!(!session.has_sent("draw"));
+
+    // A batch can start with what the rest of it needs.
+    session
+        .send(&ThingRequest::Batch {
+            requests: vec![
+                ThingCommandReq {
+                    cmd: ThingCommand::SetUnits {
+                        units: "mm".to_string(),
+                    },
+                    cmd_id: uuid::Uuid::new_v4(),
+                },
+                ThingCommandReq {
+                    cmd: draw(),
+                    cmd_id: uuid::Uuid::new_v4(),
+                },
+            ],
+        })
+        .await
+        .unwrap();
+    session.send(&command(draw())).await.unwrap();
+
+    // The connection itself sends without any check.
+    session
+        .websocket()
+        .send(Message::Text(r#"{"type": "ping"}"#.to_string()))
+        .await
+        .unwrap();
+    session.into_inner().close().await.unwrap();
+    server.join().unwrap();
+
+    // The refused messages never got to the server.
+    let received = received.lock().unwrap();
+    let types: Vec<_> = received
+        .iter()
+        .map(|m| m["type"].as_str().unwrap())
+        .collect();
+    assert_eq!(types, vec!["command", "batch", "command", "ping"]);
+    assert_eq!(received[0]["cmd"]["type"], "start_session");
+    assert_eq!(received[2]["cmd"]["type"], "draw");
+}
+
+#[tokio::test]
+async fn test_header_and_cookie_params() {
+    let (base_url, requests) =
+        mock_server(|_| (200, r#"{"id": "good", "name": "A good thing"}"#.to_string()));
+
+    let mut client = crate::Client::new("some-token");
+    client.set_base_url(base_url);
+
+    client
+        .things()
+        .get_secret("req-1", "good", "abc", Some("dark".to_string()), Some(true))
+        .await
+        .unwrap();
+
+    // The client cookies are sent with every request, the cookie params win.
+    client.set_cookie("tenant", "zoo");
+    client.set_cookie("session", "from-the-client");
+    client
+        .things()
+        .get_secret("req-2", "good", "def", None, None)
+        .await
+        .unwrap();
+    client.things().get("good").await.unwrap();
+
+    let requests = requests.lock().unwrap().clone();
+    assert_eq!(requests[0].path, "/things/good/secret?verbose=true");
+    assert_eq!(requests[0].header("x-request-id"), Some("req-1"));
+    assert_eq!(
+        requests[0].header("cookie"),
+        Some("session=abc; theme=dark")
+    );
+
+    assert_eq!(requests[1].path, "/things/good/secret");
+    assert_eq!(requests[1].header("x-request-id"), Some("req-2"));
+    assert_eq!(
+        requests[1].header("cookie"),
+        Some("session=def; tenant=zoo")
+    );
+
+    assert_eq!(requests[2].header("x-request-id"), None);
+    assert_eq!(
+        requests[2].header("cookie"),
+        Some("session=from-the-client; tenant=zoo")
+    );
+}
+
+#[tokio::test]
+async fn test_array_header_and_cookie_params() {
+    let (base_url, requests) =
+        mock_server(|_| (200, r#"{"id": "good", "name": "A good thing"}"#.to_string()));
+
+    let mut client = crate::Client::new("some-token");
+    client.set_base_url(base_url);
+
+    client
+        .things()
+        .get_fields(
+            &["id".to_string(), "name".to_string()],
+            Some(&[1, 2]),
+            Some(&["new".to_string(), "shiny".to_string()]),
+            "good",
+        )
+        .await
+        .unwrap();
+    client
+        .things()
+        .get_fields(&[], None, None, "good")
+        .await
+        .unwrap();
+
+    // A comma in a value cannot be sent, it would be two values.
+    let err = client
+        .things()
+        .get_fields(&["id,name".to_string()], None, None, "good")
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(&err, crate::types::error::Error::InvalidRequest(message) if message.contains("X-Fields")),
+        "{:?}",
+        err
+    );
+    let err = client
+        .things()
+        .get_fields(&[], None, Some(&["a,b".to_string()]), "good")
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(&err, crate::types::error::Error::InvalidRequest(message) if message.contains("flag")),
+        "{:?}",
+        err
+    );
+
+    let requests = requests.lock().unwrap().clone();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0].path, "/things/good/fields");
+    // Without `explode` the items are joined by commas in one header, with it
+    // every item gets a header of its own.
+    assert_eq!(requests[0].header_values("x-fields"), ["id,name"]);
+    assert_eq!(requests[0].header_values("x-tags"), ["1", "2"]);
+    assert_eq!(requests[0].header("cookie"), Some("flag=new; flag=shiny"));
+
+    assert_eq!(requests[1].header_values("x-fields"), [""]);
+    assert!(requests[1].header_values("x-tags").is_empty());
+    assert_eq!(requests[1].header("cookie"), None);
+}
+
+#[tokio::test]
+async fn test_attachment_limits_checked_before_upload() {
+    let (base_url, requests) = mock_server(|_| (204, String::new()));
+
+    let mut client = crate::Client::new("some-token");
+    client.set_base_url(base_url);
+
+    let body = crate::types::ThingUpload {
+        description: Some("Some files".to_string()),
+        files: None,
+    };
+    let attachment = |name: &str, size: usize| crate::types::multipart::Attachment {
+        name: name.to_string(),
+        filename: Some(format!("{name}.txt")),
+        content_type: Some("text/plain".to_string()),
+        data: vec![b'a'; size],
+    };
+
+    // Too many attachments.
+    let err = client
+        .things()
+        .upload_files(
+            vec![attachment("a", 1), attachment("b", 1), attachment("c", 1)],
+            "good",
+            &body,
+        )
+        .await
+        .unwrap_err();
+    match err {
+        crate::types::error::Error::InvalidRequest(msg) => {
+            assert_eq!(msg, "at most 2 attachments are allowed, got 3")
+        }
+        err => panic!("unexpected error: {err}"),
+    }
+
+    // An attachment that is too big.
+    let err = client
+        .things()
+        .upload_files(
+            vec![attachment("a", 16), attachment("b", 17)],
+            "good",
+            &body,
+        )
+        .await
+        .unwrap_err();
+    match err {
+        crate::types::error::Error::InvalidRequest(msg) => {
+            assert_eq!(msg, "attachment `b` is 17 bytes, the limit is 16 bytes")
+        }
+        err => panic!("unexpected error: {err}"),
+    }
+
+    // Nothing was sent to the server.
+    assert!(requests.lock().unwrap().is_empty());
+}
+
+/// The parts of a multipart request, as their headers and their body.
+fn multipart_parts(request: &MockRequest) -> Vec<(String, Vec<u8>)> {
+    let boundary = request
+        .header("content-type")
+        .and_then(|value| value.split_once("boundary="))
+        .map(|(_, boundary)| format!("--{boundary}"))
+        .unwrap();
+    let body = &request.raw_body;
+
+    let mut starts = Vec::new();
+    let mut i = 0;
+    while let Some(p) = body[i..]
+        .windows(boundary.len())
+        .position(|w| w == boundary.as_bytes())
+    {
+        starts.push(i + p);
+        i += p + boundary.len();
+    }
+
+    starts
+        .windows(2)
+        .map(|w| {
+            // Skip the boundary and its line break, and the line break before the next.
+            let part = &body[w[0] + boundary.len() + 2..w[1] - 2];
+            let end = part.windows(4).position(|w| w == b"\r\n\r\n").unwrap();
+            (
+                String::from_utf8_lossy(&part[..end]).to_string(),
+                part[end + 4..].to_vec(),
+            )
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_multipart_options_and_files() {
+    let (base_url, requests) = mock_server(|_| (204, String::new()));
+
+    let mut client = crate::Client::new("some-token");
+    client.set_base_url(base_url);
+
+    let options = crate::types::ThingImportOptions {
+        units: "mm".to_string(),
+        scale: Some(2.0),
+    };
+    let attachment =
+        |name: &str, filename: &str, content_type: &str| crate::types::multipart::Attachment {
+            name: name.to_string(),
+            filename: Some(filename.to_string()),
+            content_type: Some(content_type.to_string()),
+            data: filename.as_bytes().to_vec(),
+        };
+
+    client
+        .things()
+        .import_files(
+            vec![
+                attachment("model", "cube.obj", "model/obj"),
+                attachment("textures", "wood.png", "image/png"),
+            ],
+            "good",
+            &options,
+        )
+        .await
+        .unwrap();
+
+    let requests = requests.lock().unwrap().clone();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].path, "/things/good/import");
+    let parts = multipart_parts(&requests[0]);
+    assert_eq!(parts.len(), 3);
+
+    // The options are a JSON part of their own.
+    let (headers, body) = &parts[0];
+    assert!(
+        headers.contains(r#"name="options"; filename="options.json""#),
+        "{headers}"
+    );
+    assert!(headers.contains("application/json"), "{headers}");
+    let sent: crate::types::ThingImportOptions = serde_json::from_slice(body).unwrap();
+    assert_eq!(sent, options);
+
+    // Then the files, as they were given.
+    let (headers, body) = &parts[1];
+    assert!(
+        headers.contains(r#"name="model"; filename="cube.obj""#),
+        "{headers}"
+    );
+    assert!(headers.contains("model/obj"), "{headers}");
+    assert_eq!(body, b"cube.obj");
+    let (headers, body) = &parts[2];
+    assert!(
+        headers.contains(r#"name="textures"; filename="wood.png""#),
+        "{headers}"
+    );
+    assert!(headers.contains("image/png"), "{headers}");
+    assert_eq!(body, b"wood.png");
+
+    // A file that is not a part of the spec is not sent.
+    let err = client
+        .things()
+        .import_files(
+            vec![attachment("thing", "cube.obj", "model/obj")],
+            "good",
+            &options,
+        )
+        .await
+        .unwrap_err();
+    match err {
+        crate::types::error::Error::InvalidRequest(msg) => assert_eq!(
+            msg,
+            "attachment `thing` is not a file of the request, expected one of `model`, `textures`"
+        ),
+        err => panic!("unexpected error: {err}"),
+    }
+}
+
+#[test]
+fn test_tagged_any_of() {
+    let shape: crate::types::Shape =
+        serde_json::from_str(r#"{"kind": "square", "side": 2.0}"#).unwrap();
+    assert_eq!(shape, crate::types::Shape::Square { side: 2.0 });
+
+    // A kind that is none of the objects is not read into an empty object.
+    let err =
+        serde_json::from_str::<crate::types::Shape>(r#"{"kind": "triangle", "side": 2.0}"#)
+            .unwrap_err();
+    assert!(err.to_string().contains("unknown variant `triangle`"), "{err}");
+}
+
+#[test]
+fn test_all_of_required_override() {
+    let thing: crate::types::NamedThing =
+        serde_json::from_str(r#"{"id": "a", "name": "A thing"}"#).unwrap();
+    assert_eq!(thing.name, "A thing");
+
+    let err = serde_json::from_str::<crate::types::NamedThing>(r#"{"id": "a"}"#).unwrap_err();
+    assert!(err.to_string().contains("missing field `name`"), "{err}");
+
+    // Without the override the name is optional.
+    let draft: crate::types::ThingDraft = serde_json::from_str(r#"{"id": "a"}"#).unwrap();
+    assert_eq!(draft.name, None);
+}
+
+#[test]
+fn test_nested_accessors() {
+    let mut stats = crate::types::ThingStats {
+        views: 3,
+        rating: None,
+        featured: false,
+        rank: None,
+    };
+    assert_eq!(stats.rank_position(), None);
+    assert_eq!(stats.rank_category(), None);
+    assert_eq!(stats.rank_category_name(), None);
+
+    stats.rank = Some(crate::types::ThingRank {
+        position: 2,
+        category: None,
+    });
+    assert_eq!(stats.rank_position(), Some(2));
+    assert_eq!(stats.rank_category_name(), None);
+
+    stats.rank = Some(crate::types::ThingRank {
+        position: 1,
+        category: Some(crate::types::ThingCategory {
+            name: Some("Gadgets".to_string()),
+        }),
+    });
+    assert_eq!(stats.rank_position(), Some(1));
+    assert_eq!(
+        stats.rank_category(),
+        Some(&crate::types::ThingCategory {
+            name: Some("Gadgets".to_string()),
+        })
+    );
+    assert_eq!(stats.rank_category_name(), Some("Gadgets"));
+}
+
+#[tokio::test]
+async fn test_last_rate_limit() {
+    let (base_url, _requests) = mock_server(|path| {
+        if path.starts_with("/things/bad") {
+            (404, "not found".to_string())
+        } else {
+            (200, r#"{"id": "good", "name": "A good thing"}"#.to_string())
+        }
+    });
+
+    let mut client = crate::Client::new("some-token");
+    client.set_base_url(base_url);
+    assert_eq!(client.last_rate_limit(), None);
+
+    client.things().get("good").await.unwrap();
+    assert_eq!(
+        client.last_rate_limit(),
+        Some(crate::types::rate_limit::RateLimit {
+            limit: 100,
+            remaining: 99,
+            reset: Some(60),
+        })
+    );
+
+    // Errors update the rate limit too.
+    client.things().get("bad").await.unwrap_err();
+    assert_eq!(client.last_rate_limit().unwrap().remaining, 98);
+
+    // Concurrent calls share the snapshot with every clone of the client.
+    let calls = (0..8).map(|_| {
+        let client = client.clone();
+        tokio::spawn(async move { client.things().get("good").await.map(|_| ()) })
+    });
+    for call in calls.collect::<Vec<_>>() {
+        call.await.unwrap().unwrap();
+    }
+    // The calls can finish in any order, so any of them can be the last one.
+    let remaining = client.last_rate_limit().unwrap().remaining;
+    assert!((90..98).contains(&remaining), "{remaining}");
+}
+
+#[tokio::test]
+async fn test_token_override_shares_connections() {
+    let (base_url, requests) =
+        mock_server(|_| (200, r#"{"id": "good", "name": "A good thing"}"#.to_string()));
+
+    let mut client = crate::Client::new("service-token");
+    client.set_base_url(base_url);
+    client.set_cookie("session", "service-session");
+    let alice = client.with_token_override("alice-token");
+    let mut bob = client.with_token_override("bob-token");
+    bob.set_cookie("session", "bob-session");
+
+    alice.things().get("good").await.unwrap();
+    bob.things().get("good").await.unwrap();
+    client.things().get("good").await.unwrap();
+
+    let requests = requests.lock().unwrap().clone();
+    let tokens: Vec<_> = requests.iter().map(|r| r.header("authorization")).collect();
+    assert_eq!(
+        tokens,
+        vec![
+            Some("Bearer alice-token"),
+            Some("Bearer bob-token"),
+            Some("Bearer service-token"),
+        ]
+    );
+
+    // The cookies of the service are not sent for the users.
+    let cookies: Vec<_> = requests.iter().map(|r| r.header("cookie")).collect();
+    assert_eq!(
+        cookies,
+        vec![
+            None,
+            Some("session=bob-session"),
+            Some("session=service-session"),
+        ]
+    );
+
+    // The calls went through the same pool, so they reused the first connection.
+    let connections: Vec<_> = requests.iter().map(|r| r.connection).collect();
+    assert_eq!(connections, vec![0, 0, 0]);
+
+    // The rate limits are per token.
+    assert_eq!(alice.last_rate_limit().unwrap().remaining, 99);
+    assert_eq!(bob.last_rate_limit().unwrap().remaining, 98);
+}
+
+/// A page of things with the given ids and next page token.
+fn things_page(ids: &[&str], next_page: Option<&str>) -> String {
+    let items: Vec<_> = ids
+        .iter()
+        .map(|id| serde_json::json!({"id": id, "name": id}))
+        .collect();
+    serde_json::json!({"items": items, "next_page": next_page}).to_string()
+}
+
+/// Collect the ids of a stream of things, and the error that ended it if any.
+async fn collect_thing_ids(
+    stream: impl futures::Stream<Item = Result<crate::types::Thing, crate::types::error::Error>>,
+) -> (Vec<String>, Option<crate::types::error::Error>) {
+    use futures::StreamExt;
+
+    let mut ids = Vec::new();
+    let mut stream = Box::pin(stream);
+    while let Some(thing) = stream.next().await {
+        match thing {
+            Ok(thing) => ids.push(thing.id),
+            Err(err) => return (ids, Some(err)),
+        }
+    }
+    (ids, None)
+}
+
+#[tokio::test]
+async fn test_stream_stops_on_empty_next_page() {
+    let (base_url, requests) = mock_server(|path| {
+        if path.contains("next_page=p2") {
+            (200, things_page(&["c"], Some("")))
+        } else {
+            (200, things_page(&["a", "b"], Some("p2")))
+        }
+    });
+
+    let mut client = crate::Client::new("some-token");
+    client.set_base_url(base_url);
+
+    let (ids, err) = collect_thing_ids(client.things().list_stream()).await;
+    assert!(err.is_none(), "{:?}", err);
+    assert_eq!(ids, vec!["a", "b", "c"]);
+    assert_eq!(requests.lock().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn test_stream_stops_on_repeated_next_page() {
+    // The server hands out the token it was just given.
+    let (base_url, requests) = mock_server(|_| (200, things_page(&["a"], Some("same"))));
+
+    let mut client = crate::Client::new("some-token");
+    client.set_base_url(base_url);
+
+    let (ids, err) = collect_thing_ids(client.things().list_stream()).await;
+    assert!(err.is_none(), "{:?}", err);
+    assert_eq!(ids, vec!["a", "a"]);
+    assert_eq!(requests.lock().unwrap().len(), 2);
+
+    // The server goes back and forth between two tokens, only the maximum number
+    // of pages stops it.
+    let (base_url, requests) = mock_server(|path| {
+        if path.contains("next_page=x") {
+            (200, things_page(&["b"], Some("y")))
+        } else {
+            (200, things_page(&["a"], Some("x")))
+        }
+    });
+
+    let mut client = crate::Client::new("some-token");
+    client.set_base_url(base_url);
+    client.set_max_pages(5);
+
+    let (ids, err) = collect_thing_ids(client.things().list_stream()).await;
+    assert_eq!(ids, vec!["a", "b", "a", "b", "a"]);
+    match err {
+        Some(crate::types::error::Error::TooManyPages {
+            max_pages,
+            next_page_token,
+        }) => {
+            assert_eq!(max_pages, 5);
+            assert_eq!(next_page_token.as_deref(), Some("x"));
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+    assert_eq!(requests.lock().unwrap().len(), 5);
+}
+
+/// An array of things with the given ids.
+fn things_array(ids: &[&str]) -> String {
+    let items: Vec<_> = ids
+        .iter()
+        .map(|id| serde_json::json!({"id": id, "name": id}))
+        .collect();
+    serde_json::json!(items).to_string()
+}
+
+#[tokio::test]
+async fn test_stream_follows_link_header() {
+    let (base_url, requests) = mock_server_with_headers(|path| {
+        if path.contains("page=3") {
+            // The last page links back to itself.
+            (
+                200,
+                things_array(&["e"]),
+                vec![("Link", r#"</recent-things?page=3>; rel="next""#.to_string())],
+            )
+        } else if path.contains("page=2") {
+            (
+                200,
+                things_array(&["c", "d"]),
+                vec![(
+                    "Link",
+                    r#"</recent-things?page=1>; rel="prev", </recent-things?page=3>; rel="next""#
+                        .to_string(),
+                )],
+            )
+        } else {
+            (
+                200,
+                things_array(&["a", "b"]),
+                vec![("Link", r#"</recent-things?page=2>; rel="next""#.to_string())],
+            )
+        }
+    });
+
+    let mut client = crate::Client::new("some-token");
+    client.set_base_url(base_url);
+
+    // The function returns the array, as it is.
+    let things = client.things().list_recent(Some(2)).await.unwrap();
+    assert_eq!(things.len(), 2);
+
+    let (ids, err) = collect_thing_ids(client.things().list_recent_stream(Some(2))).await;
+    assert!(err.is_none(), "{:?}", err);
+    assert_eq!(ids, vec!["a", "b", "c", "d", "e"]);
+
+    // The first page has our arguments, the next ones are at the links.
+    let paths: Vec<_> = requests
+        .lock()
+        .unwrap()
+        .iter()
+        .skip(1)
+        .map(|r| r.path.clone())
+        .collect();
+    assert_eq!(
+        paths,
+        vec![
+            "/recent-things?limit=2",
+            "/recent-things?page=2",
+            "/recent-things?page=3"
+        ]
+    );
+
+    // Without a link to the next page there is only the first one.
+    let (base_url, requests) = mock_server(|_| (200, things_array(&["a"])));
+    client.set_base_url(base_url);
+
+    let (ids, err) = collect_thing_ids(client.things().list_recent_stream(None)).await;
+    assert!(err.is_none(), "{:?}", err);
+    assert_eq!(ids, vec!["a"]);
+    assert_eq!(requests.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_stream_does_not_follow_link_to_another_origin() {
+    let (base_url, requests) = mock_server_with_headers(|_| {
+        (
+            200,
+            things_array(&["a", "b"]),
+            vec![(
+                "Link",
+                r#"<https://evil.example.com/recent-things?page=2>; rel="next""#.to_string(),
+            )],
+        )
+    });
+
+    let mut client = crate::Client::new("some-token");
+    client.set_base_url(base_url);
+
+    // The token is not sent to the other host, the stream ends with an error.
+    let (ids, err) = collect_thing_ids(client.things().list_recent_stream(Some(2))).await;
+    assert_eq!(ids, vec!["a", "b"]);
+    match err {
+        Some(crate::types::error::Error::InvalidRequest(message)) => {
+            assert!(message.contains("evil.example.com"), "{}", message);
+            assert!(message.contains("not on the origin"), "{}", message);
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+    assert_eq!(requests.lock().unwrap().len(), 1);
+}
+
+/// A page of things with the cursor of the next page in its meta.
+fn cursor_page(ids: &[&str], next_cursor: Option<&str>) -> String {
+    let items: Vec<_> = ids
+        .iter()
+        .map(|id| serde_json::json!({"id": id, "name": id}))
+        .collect();
+    serde_json::json!({"data": items, "meta": {"next_cursor": next_cursor, "total": 3}}).to_string()
+}
+
+#[tokio::test]
+async fn test_stream_follows_nested_cursor() {
+    let (base_url, requests) = mock_server(|path| {
+        if path.contains("cursor=c2") {
+            (200, cursor_page(&["c"], None))
+        } else {
+            (200, cursor_page(&["a", "b"], Some("c2")))
+        }
+    });
+
+    let mut client = crate::Client::new("some-token");
+    client.set_base_url(base_url);
+
+    let (ids, err) = collect_thing_ids(client.things().list_cursor_stream(Some(2))).await;
+    assert!(err.is_none(), "{:?}", err);
+    assert_eq!(ids, vec!["a", "b", "c"]);
+
+    // The cursor of the meta is sent back as the cursor of the operation.
+    let paths: Vec<_> = requests
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|r| r.path.clone())
+        .collect();
+    assert_eq!(paths, vec!["/cursor-things?limit=2", "/cursor-things?cursor=c2"]);
+
+    // A page without its meta is the last one.
+    let (base_url, requests) =
+        mock_server(|_| (200, serde_json::json!({"data": [{"id": "a", "name": "a"}]}).to_string()));
+    client.set_base_url(base_url);
+
+    let (ids, err) = collect_thing_ids(client.things().list_cursor_stream(None)).await;
+    assert!(err.is_none(), "{:?}", err);
+    assert_eq!(ids, vec!["a"]);
+    assert_eq!(requests.lock().unwrap().len(), 1);
+}
+
+/// Counts the allocations of one size on every thread, so a test can tell how
+/// often it copied something of that size.
+struct CountingAllocator;
+
+thread_local! {
+    /// The size we count, and how many allocations of it there were.
+    static WATCHED: std::cell::Cell<(usize, usize)> = const { std::cell::Cell::new((0, 0)) };
+}
+
+unsafe impl std::alloc::GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: std::alloc::Layout) -> *mut u8 {
+        let _ = WATCHED.try_with(|watched| {
+            let (size, count) = watched.get();
+            if layout.size() == size {
+                watched.set((size, count + 1));
+            }
+        });
+        std::alloc::System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: std::alloc::Layout) {
+        std::alloc::System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// The allocations of `size` bytes on this thread while running `f`.
+async fn allocations_of<F: std::future::Future>(size: usize, f: F) -> (F::Output, usize) {
+    WATCHED.with(|watched| watched.set((size, 0)));
+    let output = f.await;
+    let (_, count) = WATCHED.with(|watched| watched.replace((0, 0)));
+    (output, count)
+}
+
+#[tokio::test]
+async fn test_stream_does_not_copy_items() {
+    use futures::TryStreamExt;
+
+    // One page of things with big names, of a size nothing else allocates.
+    let name = "x".repeat(4099);
+    let ids = (0..100).map(|i| format!("thing-{}", i)).collect::<Vec<_>>();
+    let page = serde_json::json!({
+        "items": ids
+            .iter()
+            .map(|id| serde_json::json!({"id": id, "name": name}))
+            .collect::<Vec<_>>(),
+    })
+    .to_string();
+    let (base_url, _) = mock_server(move |_| (200, page.clone()));
+
+    let mut client = crate::Client::new("some-token");
+    client.set_base_url(base_url);
+
+    // Getting the page allocates every name once.
+    let (page, fetched) = allocations_of(name.len(), client.things().list(None)).await;
+    assert_eq!(page.unwrap().items.len(), 100);
+    assert_eq!(fetched, 100);
+
+    // And so does streaming it, the items are moved out of the page.
+    let (things, streamed) = allocations_of(
+        name.len(),
+        client.things().list_stream().try_collect::<Vec<_>>(),
+    )
+    .await;
+    assert_eq!(things.unwrap().len(), 100);
+    assert_eq!(streamed, 100);
+}
+
+#[tokio::test]
+async fn test_wait_until_done() {
+    let (base_url, requests) = mock_server(|path| {
+        if path.ends_with("?wait=true") {
+            (
+                200,
+                r#"{"id": "job-1", "url": "https://a.b/c.png"}"#.to_string(),
+            )
+        } else {
+            (202, r#"{"id": "job-1", "status": "running"}"#.to_string())
+        }
+    });
+    let mut client = crate::Client::new("some-token");
+    client.set_base_url(base_url);
+
+    // Right away we get the job.
+    let job = client.things().render("good").await.unwrap();
+    assert_eq!(job.status, "running");
+
+    // Or we wait for what it made.
+    let render = client
+        .things()
+        .render_wait("good", std::time::Duration::from_secs(30))
+        .await
+        .unwrap();
+    assert_eq!(render.url, "https://a.b/c.png");
+
+    // The same with a `Prefer` header, the export is never done in time.
+    let job = client.things().export("good").await.unwrap();
+    assert_eq!(job.id, "job-1");
+    let err = client
+        .things()
+        .export_wait("good", std::time::Duration::from_secs(30))
+        .await
+        .unwrap_err();
+    assert_eq!(err.status(), Some(reqwest::StatusCode::ACCEPTED));
+    assert!(err.is_retryable());
+
+    let requests = requests.lock().unwrap();
+    let paths = requests
+        .iter()
+        .map(|request| request.path.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        paths,
+        vec![
+            "/things/good/render",
+            "/things/good/render?wait=true",
+            "/things/good/export",
+            "/things/good/export",
+        ]
+    );
+    assert_eq!(requests[2].header("Prefer"), Some("respond-async"));
+    assert_eq!(requests[3].header("Prefer"), Some("wait=30"));
+}
+
+#[tokio::test]
+async fn test_poll_until_done() {
+    let polls = Arc::new(Mutex::new(0));
+    let counted = polls.clone();
+    let (base_url, requests) = mock_server(move |path| match path {
+        "/things/good/scans" => (201, r#"{"id": "scan-1", "status": "queued"}"#.to_string()),
+        "/things/bad/scans" => (201, r#"{"id": "scan-2", "status": "queued"}"#.to_string()),
+        "/scans/scan-1" => {
+            // In progress for three polls, then done.
+            let mut polls = counted.lock().unwrap();
+            *polls += 1;
+            let status = if *polls > 3 { "completed" } else { "in_progress" };
+            (200, format!(r#"{{"id": "scan-1", "status": "{}"}}"#, status))
+        }
+        _ => (200, r#"{"id": "scan-2", "status": "failed"}"#.to_string()),
+    });
+    let mut client = crate::Client::new("some-token");
+    client.set_base_url(base_url);
+
+    let options = crate::types::poll::PollOptions {
+        initial_interval: std::time::Duration::from_millis(50),
+        max_interval: std::time::Duration::from_secs(1),
+        multiplier: 2.0,
+        timeout: std::time::Duration::from_secs(30),
+    };
+    let scan = client
+        .things()
+        .create_scan_and_wait("good", options)
+        .await
+        .unwrap();
+    assert_eq!(scan.status, crate::types::ThingScanStatus::Completed);
+
+    // Every wait is longer than the one before.
+    {
+        let requests = requests.lock().unwrap();
+        let paths = requests
+            .iter()
+            .map(|request| request.path.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            vec![
+                "/things/good/scans",
+                "/scans/scan-1",
+                "/scans/scan-1",
+                "/scans/scan-1",
+                "/scans/scan-1",
+            ]
+        );
+        let gaps = requests
+            .windows(2)
+            .map(|pair| pair[1].received_at - pair[0].received_at)
+            .collect::<Vec<_>>();
+        assert!(gaps[0] >= options.initial_interval, "{:?}", gaps);
+        assert!(gaps.windows(2).all(|pair| pair[1] > pair[0]), "{:?}", gaps);
+    }
+
+    // A failed scan is an error, with the scan.
+    let err = client
+        .things()
+        .create_scan_and_wait("bad", options)
+        .await
+        .unwrap_err();
+    assert_eq!(err.status(), Some("failed"));
+    assert_eq!(err.resource().unwrap().id, "scan-2");
+}
+
+#[tokio::test]
+async fn test_upload_resumable() {
+    let chunks = Arc::new(Mutex::new(0));
+    let counted = chunks.clone();
+    let (base_url, requests) = mock_server(move |path| match path {
+        "/things/good/uploads" => (201, r#"{"id": "upload-1"}"#.to_string()),
+        "/uploads/upload-1/chunks" => {
+            // The third chunk we get fails once.
+            let mut chunks = counted.lock().unwrap();
+            *chunks += 1;
+            if *chunks == 3 {
+                (503, String::new())
+            } else {
+                (204, String::new())
+            }
+        }
+        _ => (200, r#"{"id": "file-1", "size": 37}"#.to_string()),
+    });
+    let mut client = crate::Client::new("some-token");
+    client.set_base_url(base_url);
+    // The chunk is retried by the helper, not the client.
+    client.set_retry_config(crate::types::retry::RetryConfig {
+        max_retries: 0,
+        ..Default::default()
+    });
+
+    let payload = (0..37u8).collect::<Vec<_>>();
+    let mut progress = Vec::new();
+    let file = client
+        .things()
+        .upload_resumable("good", &payload[..], 8, 2, |p| progress.push(p))
+        .await
+        .unwrap();
+    assert_eq!(file.size, 37);
+    assert_eq!(progress.len(), 5);
+    assert_eq!(
+        progress.last(),
+        Some(&crate::types::upload::UploadProgress {
+            chunks: 5,
+            bytes: 37
+        })
+    );
+
+    let requests = requests.lock().unwrap().clone();
+    assert_eq!(requests[0].path, "/things/good/uploads");
+    assert_eq!(requests.last().unwrap().path, "/uploads/upload-1/finalize");
+
+    // Five chunks, one of them sent twice, put back together by their range.
+    let mut ranges = std::collections::BTreeMap::<u64, Vec<Vec<u8>>>::new();
+    for request in requests.iter().filter(|r| r.method == "PUT") {
+        let range = request.header("Content-Range").unwrap();
+        let start = range
+            .trim_start_matches("bytes ")
+            .split('-')
+            .next()
+            .unwrap()
+            .parse::<u64>()
+            .unwrap();
+        if start == 32 {
+            assert_eq!(range, "bytes 32-36/37");
+        } else {
+            assert!(range.ends_with("/*"), "{}", range);
+        }
+        ranges
+            .entry(start)
+            .or_default()
+            .push(request.raw_body.clone());
+    }
+    assert_eq!(ranges.len(), 5);
+    let retried = ranges
+        .values()
+        .filter(|bodies| bodies.len() == 2)
+        .collect::<Vec<_>>();
+    assert_eq!(retried.len(), 1);
+    assert_eq!(retried[0][0], retried[0][1]);
+    let reassembled = ranges
+        .values()
+        .flat_map(|bodies| bodies[0].clone())
+        .collect::<Vec<_>>();
+    assert_eq!(reassembled, payload);
+}
+
+#[tokio::test]
+async fn test_empty_object_bodies() {
+    let (base_url, requests) = mock_server(|_| (204, String::new()));
+    let mut client = crate::Client::new("some-token");
+    client.set_base_url(base_url);
+
+    // A required empty object is sent for us, an optional one is not sent at all.
+    client.things().archive("good").await.unwrap();
+    client.things().restore("good").await.unwrap();
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests[0].path, "/things/good/archive");
+    assert_eq!(requests[0].body.as_bytes(), b"{}");
+    assert_eq!(requests[0].header("content-type"), Some("application/json"));
+    assert_eq!(requests[1].path, "/things/good/restore");
+    assert_eq!(requests[1].body, "");
+    assert_eq!(requests[1].header("content-type"), None);
+}
+
+#[tokio::test]
+async fn test_optional_request_body() {
+    let (base_url, requests) = mock_server(|_| (204, String::new()));
+    let mut client = crate::Client::new("some-token");
+    client.set_base_url(base_url);
+
+    // Without a body nothing is sent, not even an empty object.
+    client.things().patch("good", None).await.unwrap();
+    client
+        .things()
+        .patch(
+            "good",
+            Some(&crate::types::ThingPatch {
+                name: Some("better".to_string()),
+            }),
+        )
+        .await
+        .unwrap();
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests[0].method, "PATCH");
+    assert_eq!(requests[0].path, "/things/good");
+    assert!(requests[0].raw_body.is_empty());
+    assert_eq!(requests[0].header("content-type"), None);
+    assert_eq!(requests[1].body, r#"{"name":"better"}"#);
+    assert_eq!(requests[1].header("content-type"), Some("application/json"));
+}
+
+#[tokio::test]
+async fn test_match_path_agrees_with_the_functions() {
+    let (base_url, requests) = mock_server(|_| (204, String::new()));
+    let mut client = crate::Client::new("some-token");
+    client.set_base_url(base_url);
+
+    // The paths the functions request match their operations, with the params.
+    client.things().patch("a b", None).await.unwrap();
+    client.things().restore("café").await.unwrap();
+    client.things().patch("a/b%2F c", None).await.unwrap();
+
+    let requests = requests.lock().unwrap();
+    let matched = crate::types::paths::match_path(&http::Method::PATCH, &requests[0].path).unwrap();
+    assert_eq!(matched.operation_id, "patch_thing");
+    assert_eq!(matched.template, crate::types::paths::PATCH_THING);
+    assert_eq!(matched.params["id"], "a b");
+    let matched = crate::types::paths::match_path(&http::Method::POST, &requests[1].path).unwrap();
+    assert_eq!(matched.operation_id, "restore_thing");
+    assert_eq!(matched.params["id"], "café");
+    // The values are encoded, so they stay in their segment.
+    assert_eq!(requests[2].path, "/things/a%2Fb%252F%20c");
+    let matched = crate::types::paths::match_path(&http::Method::PATCH, &requests[2].path).unwrap();
+    assert_eq!(matched.params["id"], "a/b%2F c");
+
+    // Not a path of the API, or not its method.
+    assert_eq!(crate::types::paths::match_path(&http::Method::GET, "/nothing/here"), None);
+    assert_eq!(crate::types::paths::match_path(&http::Method::PATCH, "/things"), None);
+}
+
+#[tokio::test]
+async fn test_secret_fields() {
+    let (base_url, requests) = mock_server(|_| (201, r#"{"id":"t1","token":"s3cret"}"#.to_string()));
+    let mut client = crate::Client::new("some-token");
+    client.set_base_url(base_url);
+
+    let token = client
+        .tokens()
+        .create(&crate::types::TokenRequest {
+            name: "ci".to_string(),
+            password: "hunter2".to_string().into(),
+        })
+        .await
+        .unwrap();
+
+    // The secrets are sent and received as they are.
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests[0].body, r#"{"name":"ci","password":"hunter2"}"#);
+    assert_eq!(token.token.as_str(), "s3cret");
+
+    // But they are redacted everywhere else.
+    assert_eq!(
+        format!("{:?}", token),
+        r#"ApiToken { id: "t1", token: *** }"#
+    );
+    assert!(token.to_string().contains(r#""token": "***""#), "{}", token);
+    assert_eq!(
+        serde_json::to_string(&token).unwrap(),
+        r#"{"id":"t1","token":"s3cret"}"#
+    );
+}
+
+#[tokio::test]
+async fn test_timeout_is_transient() {
+    let (base_url, _requests) = mock_server(|_| {
+        std::thread::sleep(std::time::Duration::from_millis(500));
+        (200, r#"{"id": "good", "name": "A good thing"}"#.to_string())
+    });
+
+    // A plain reqwest client, so the retry middleware does not retry the timeout.
+    let err = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_millis(50))
+        .build()
+        .unwrap()
+        .get(format!("{base_url}/things/good"))
+        .send()
+        .await
+        .unwrap_err();
+    let err = crate::types::error::Error::from(err);
+
+    assert_eq!(
+        err.classification(),
+        crate::types::error::ErrorClass::Transient
+    );
+    assert!(err.is_retryable());
+}
+
+#[test]
+fn test_inline_one_of_items_round_trip() {
+    use crate::types::{Shelf, ShelfContentsItem, ShelfLabelsValue};
+
+    let json = serde_json::json!({
+        "contents": [
+            {"type": "book", "title": "A good book"},
+            {"type": "plant", "species": "Fern"}
+        ],
+        "labels": {
+            "left": {"kind": "text", "text": "Reading"},
+            "right": {"kind": "number", "number": 7}
+        }
+    });
+
+    let shelf: Shelf = serde_json::from_value(json.clone()).unwrap();
+    assert_eq!(
+        shelf.contents,
+        vec![
+            ShelfContentsItem::Book {
+                title: "A good book".to_string()
+            },
+            ShelfContentsItem::Plant {
+                species: "Fern".to_string()
+            },
+        ]
+    );
+    assert_eq!(
+        shelf.labels["left"],
+        ShelfLabelsValue::Text {
+            text: "Reading".to_string()
+        }
+    );
+    assert_eq!(
+        shelf.labels["right"],
+        ShelfLabelsValue::Number { number: 7 }
+    );
+
+    assert_eq!(serde_json::to_value(&shelf).unwrap(), json);
+}
+
+#[cfg(feature = "registry")]
+#[test]
+fn test_schema_registry() {
+    let registry = crate::types::schema_registry();
+
+    let names: Vec<&str> = registry.iter().map(|(name, _)| *name).collect();
+    for name in [
+        "NamedThing",
+        "Shelf",
+        "ShelfContentsItem",
+        "Thing",
+        "ThingKind",
+    ] {
+        assert!(names.contains(&name), "{name} is missing from {names:?}");
+    }
+    let mut sorted = names.clone();
+    sorted.sort();
+    assert_eq!(names, sorted);
+
+    for (name, schema) in &registry {
+        let json = serde_json::to_string(schema).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["title"], *name, "{json}");
+    }
+}
+
+#[test]
+fn test_operations_table() {
+    let operations = crate::operations();
+    let render = operations
+        .iter()
+        .find(|operation| operation.tag == "things" && operation.function == "render")
+        .unwrap();
+    assert_eq!(
+        *render,
+        crate::OperationInfo {
+            function: "render",
+            tag: "things",
+            operation_id: "render_thing",
+            method: "POST",
+            path: "/things/{id}/render",
+            paginated: false,
+            streaming: false,
+            request_type: None,
+            response_type: Some("crate::types::ThingJob"),
+        }
+    );
+
+    // The operations of the paginated and websocket functions are marked.
+    let find = |operation_id: &str| {
+        operations
+            .iter()
+            .find(|operation| operation.operation_id == operation_id)
+            .unwrap()
+    };
+    assert!(find("list_things").paginated);
+    assert!(find("things_ws").streaming);
+    assert_eq!(find("set_thing_tags").request_type, Some("Vec<String>"));
+}
+
+#[test]
+fn test_new_from_env_lookup_order() {
+    // The environment is shared by every test, so this is the only test touching it.
+    for name in [
+        "MOCK_API_TOKEN",
+        "ACME_API_BASE_URL",
+        "ACME_HOST",
+        "MOCK_HOST",
+    ] {
+        std::env::remove_var(name);
+    }
+
+    let err = std::panic::catch_unwind(crate::Client::new_from_env).unwrap_err();
+    let message = err
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| err.downcast_ref::<String>().cloned())
+        .unwrap();
+    assert!(message.contains("MOCK_API_TOKEN"), "{message}");
+
+    std::env::set_var("MOCK_API_TOKEN", "token");
+    assert_eq!(
+        crate::Client::new_from_env().base_url,
+        "http://api.example.com/us-east"
+    );
+
+    // The variables named after the package come last.
+    std::env::set_var("MOCK_HOST", "https://mock.example.com");
+    assert_eq!(
+        crate::Client::new_from_env().base_url,
+        "https://mock.example.com"
+    );
+
+    // Then the ones we were given, in order.
+    std::env::set_var("ACME_HOST", "https://host.acme.com");
+    assert_eq!(
+        crate::Client::new_from_env().base_url,
+        "https://host.acme.com"
+    );
+
+    std::env::set_var("ACME_API_BASE_URL", "https://base-url.acme.com");
+    let client = crate::Client::new_from_env();
+    assert_eq!(client.base_url, "https://base-url.acme.com");
+    assert_eq!(client.token, "token");
+
+    for name in [
+        "MOCK_API_TOKEN",
+        "ACME_API_BASE_URL",
+        "ACME_HOST",
+        "MOCK_HOST",
+    ] {
+        std::env::remove_var(name);
+    }
+}
+
+#[tokio::test]
+async fn test_head_and_options_return_headers() {
+    let (base_url, requests) = mock_server(|_| (200, String::new()));
+
+    let mut client = crate::Client::new("some-token");
+    client.set_base_url(base_url);
+
+    let headers = client.things().head("good").await.unwrap();
+    assert_eq!(headers.get(http::header::ETAG).unwrap(), "\"v1\"");
+
+    let headers = client.things().options().await.unwrap();
+    assert!(headers.contains_key("x-ratelimit-limit"));
+
+    let requests = requests.lock().unwrap().clone();
+    let methods: Vec<_> = requests
+        .iter()
+        .map(|r| (r.method.as_str(), r.path.as_str()))
+        .collect();
+    assert_eq!(
+        methods,
+        vec![("HEAD", "/things/good"), ("OPTIONS", "/things")]
+    );
+}
+
+#[tokio::test]
+async fn test_head_error_has_no_body() {
+    let (base_url, _) = mock_server(|_| (404, String::new()));
+
+    let mut client = crate::Client::new("some-token");
+    client.set_base_url(base_url);
+
+    match client.things().head("missing").await {
+        Err(crate::types::error::Error::Server {
+            body,
+            status,
+            request_id,
+            error_code: None,
+        }) => {
+            assert_eq!(status, reqwest::StatusCode::NOT_FOUND);
+            assert_eq!(body, "");
+            assert_eq!(request_id.as_deref(), Some("mock-1"));
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_default_error_response() {
+    let (base_url, _) = mock_server(|path| {
+        if path.starts_with("/things/broken") {
+            (
+                500,
+                r#"{"error_code": "internal_api", "message": "oops", "request_id": "req-1"}"#
+                    .to_string(),
+            )
+        } else if path.starts_with("/things/missing") {
+            (404, r#"{"message": "no such thing"}"#.to_string())
+        } else {
+            (502, "Bad Gateway".to_string())
+        }
+    });
+
+    let mut client = crate::Client::new("some-token");
+    client.set_base_url(base_url);
+
+    // A status the operation does not list is parsed into its `default` response.
+    let err = client.things().check("broken").await.unwrap_err();
+    assert_eq!(
+        err.classification(),
+        crate::types::error::ErrorClass::Transient
+    );
+    match err {
+        crate::types::error::Error::Api {
+            body: crate::types::error::ErrorBody::Error(body),
+            status,
+            ..
+        } => {
+            assert_eq!(status, reqwest::StatusCode::INTERNAL_SERVER_ERROR);
+            assert_eq!(body.message, "oops");
+            assert_eq!(body.request_id.as_deref(), Some("req-1"));
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    // A listed status, or a body that is not the error type, keeps the text.
+    for (id, expected) in [
+        ("missing", reqwest::StatusCode::NOT_FOUND),
+        ("gateway", reqwest::StatusCode::BAD_GATEWAY),
+    ] {
+        match client.things().check(id).await {
+            Err(crate::types::error::Error::Server { status, .. }) => {
+                assert_eq!(status, expected);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_api_error_code() {
+    use crate::types::ErrorCode;
+
+    let (base_url, _) = mock_server(|path| {
+        if path.starts_with("/things/locked") {
+            (500, r#"{"error_code": "thing_locked", "message": "locked"}"#.to_string())
+        } else if path.starts_with("/things/missing") {
+            (404, r#"{"error_code": "thing_not_found", "message": "no"}"#.to_string())
+        } else if path.starts_with("/things/new") {
+            (500, r#"{"error_code": "thing_melted", "message": "new"}"#.to_string())
+        } else {
+            (502, "Bad Gateway".to_string())
+        }
+    });
+
+    let mut client = crate::Client::new("some-token");
+    client.set_base_url(base_url);
+
+    // The codes the spec lists are variants, in the body of the `default` response
+    // or in the text of a listed one.
+    let err = client.things().check("locked").await.unwrap_err();
+    assert!(matches!(err, crate::types::error::Error::Api { .. }), "{:?}", err);
+    assert_eq!(err.api_error_code(), Some(&ErrorCode::ThingLocked));
+    let err = client.things().check("missing").await.unwrap_err();
+    assert!(matches!(err, crate::types::error::Error::Server { .. }), "{:?}", err);
+    assert_eq!(err.api_error_code(), Some(&ErrorCode::ThingNotFound));
+
+    // A code the spec does not list yet is kept as it is.
+    let err = client.things().check("new").await.unwrap_err();
+    match err.api_error_code() {
+        Some(ErrorCode::Unknown(code)) => assert_eq!(code, "thing_melted"),
+        other => panic!("unexpected code: {:?}", other),
+    }
+    assert_eq!(
+        serde_json::to_string(&ErrorCode::Unknown("thing_melted".to_string())).unwrap(),
+        r#""thing_melted""#
+    );
+    assert_eq!(ErrorCode::Unknown("thing_melted".to_string()).to_string(), "thing_melted");
+
+    // No body, no code.
+    let err = client.things().check("gateway").await.unwrap_err();
+    assert_eq!(err.api_error_code(), None);
+}
+
+#[tokio::test]
+async fn test_request_id() {
+    let (base_url, _) = mock_server(|path| {
+        if path.starts_with("/ping") {
+            (200, r#""pong""#.to_string())
+        } else {
+            (
+                500,
+                r#"{"message": "oops", "request_id": "from-body"}"#.to_string(),
+            )
+        }
+    });
+
+    // Do not retry the error, every attempt gets its own id.
+    let mut client = crate::Client::new("some-token");
+    client.set_base_url(base_url);
+    client.set_retry_config(crate::types::retry::RetryConfig {
+        max_retries: 0,
+        ..Default::default()
+    });
+    assert_eq!(client.last_request_id(), None);
+
+    // A success keeps the id for support.
+    client.meta().ping().await.unwrap();
+    assert_eq!(client.last_request_id().as_deref(), Some("mock-1"));
+
+    // An error carries it, the header wins over the body.
+    let err = client.things().check("broken").await.unwrap_err();
+    assert_eq!(err.request_id(), Some("mock-2"));
+    assert!(
+        err.to_string().ends_with(" (request id: mock-2)"),
+        "{}",
+        err
+    );
+    assert_eq!(client.last_request_id().as_deref(), Some("mock-2"));
+}
+
+/// The warnings of the `deprecation` module, once the logger is set.
+static DEPRECATION_WARNINGS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// A logger that keeps the warnings of the `deprecation` module.
+struct DeprecationLogger;
+
+impl log::Log for DeprecationLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= log::Level::Warn
+    }
+
+    fn log(&self, record: &log::Record) {
+        if record.target().ends_with("::deprecation") {
+            DEPRECATION_WARNINGS
+                .lock()
+                .unwrap()
+                .push(record.args().to_string());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+#[tokio::test]
+#[allow(deprecated)]
+async fn test_deprecation_warned_once() {
+    let _ = log::set_logger(&DeprecationLogger);
+    log::set_max_level(log::LevelFilter::Warn);
+    let (base_url, _) = mock_server_with_headers(|_| {
+        (
+            200,
+            r#"{"views": 1, "featured": false}"#.to_string(),
+            vec![
+                ("Deprecation", "@1688169599".to_string()),
+                ("Sunset", "Sun, 01 Jun 2025 00:00:00 GMT".to_string()),
+            ],
+        )
+    });
+    let warnings = |operation_id: &str| {
+        DEPRECATION_WARNINGS
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|warning| warning.contains(&format!("`{operation_id}`")))
+            .cloned()
+            .collect::<Vec<_>>()
+    };
+
+    let mut client = crate::Client::new("some-token");
+    client.set_base_url(&base_url);
+    for _ in 0..3 {
+        client.things().get_stats("good").await.unwrap();
+    }
+    assert_eq!(
+        warnings("get_thing_stats"),
+        vec![
+            "the API deprecated the operation `get_thing_stats`, it will be removed on Sun, 01 Jun \
+             2025 00:00:00 GMT"
+        ]
+    );
+
+    // The warnings can be turned off.
+    let mut client = crate::Client::new("some-token");
+    client.set_base_url(&base_url);
+    client.set_deprecation_warnings(false);
+    client.things().check("good").await.unwrap();
+    assert_eq!(warnings("check_thing"), Vec::<String>::new());
+}
+
+#[cfg(feature = "compression")]
+#[tokio::test]
+async fn test_request_compression() {
+    use crate::types::compression::CompressionMode;
+
+    let (base_url, requests) = mock_server(|_| (204, String::new()));
+
+    let mut client = crate::Client::new("some-token");
+    client.set_base_url(base_url);
+    client.set_request_compression(CompressionMode::Gzip { min_size: 1024 });
+
+    // A big body is compressed, the server gets the same JSON back.
+    let tags = (0..500).map(|i| format!("tag-{i}")).collect::<Vec<_>>();
+    client.things().set_tags("good", &tags).await.unwrap();
+    // A small one is sent as it is.
+    let few = vec!["tag".to_string()];
+    client.things().set_tags("good", &few).await.unwrap();
+    // A client with another token compresses as well.
+    client
+        .with_token_override("bob-token")
+        .things()
+        .set_tags("good", &tags)
+        .await
+        .unwrap();
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests[0].header("content-encoding"), Some("gzip"));
+    let mut json = Vec::new();
+    std::io::Read::read_to_end(
+        &mut flate2::read::GzDecoder::new(requests[0].raw_body.as_slice()),
+        &mut json,
+    )
+    .unwrap();
+    assert_eq!(json, serde_json::to_vec(&tags).unwrap());
+    assert!(requests[0].raw_body.len() < json.len());
+
+    assert_eq!(requests[1].header("content-encoding"), None);
+    assert_eq!(requests[1].body, serde_json::to_string(&few).unwrap());
+
+    assert_eq!(requests[2].header("authorization"), Some("Bearer bob-token"));
+    assert_eq!(requests[2].header("content-encoding"), Some("gzip"));
+    assert_eq!(requests[2].raw_body, requests[0].raw_body);
+}
+
+#[tokio::test]
+async fn test_retry_config() {
+    use crate::types::retry::RetryConfig;
+
+    // Every server fails the first 5 requests.
+    let flaky = || {
+        let failures = std::sync::atomic::AtomicUsize::new(0);
+        mock_server(move |_| {
+            if failures.fetch_add(1, std::sync::atomic::Ordering::SeqCst) < 5 {
+                (503, String::new())
+            } else {
+                (200, r#""pong""#.to_string())
+            }
+        })
+    };
+
+    let mut client = crate::Client::new("some-token");
+    assert_eq!(client.retry_config(), RetryConfig::default());
+    let fast = RetryConfig {
+        min_backoff: std::time::Duration::from_millis(20),
+        max_backoff: std::time::Duration::from_millis(40),
+        ..client.retry_config()
+    };
+
+    // The default gives up after 3 retries.
+    let (base_url, requests) = flaky();
+    client.set_base_url(base_url);
+    client.set_retry_config(fast);
+    match client.meta().ping().await {
+        Err(crate::types::error::Error::Server { status, .. }) => {
+            assert_eq!(status, reqwest::StatusCode::SERVICE_UNAVAILABLE);
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+    assert_eq!(requests.lock().unwrap().len(), 4);
+
+    // More retries get through, waiting at least the shortest backoff every time.
+    let (base_url, requests) = flaky();
+    client.set_base_url(base_url);
+    client.set_retry_config(RetryConfig {
+        max_retries: 8,
+        ..fast
+    });
+    let start = std::time::Instant::now();
+    assert_eq!(client.meta().ping().await.unwrap(), "pong");
+    assert!(start.elapsed() >= 5 * fast.min_backoff);
+    assert_eq!(requests.lock().unwrap().len(), 6);
+
+    // No retries at all.
+    let (base_url, requests) = flaky();
+    client.set_base_url(base_url);
+    client.set_retry_config(RetryConfig {
+        max_retries: 0,
+        ..fast
+    });
+    assert!(client.meta().ping().await.is_err());
+    assert_eq!(requests.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_scalar_responses() {
+    let (base_url, _) = mock_server(|path| {
+        if path == "/ping" {
+            (200, r#""pong""#.to_string())
+        } else if path.starts_with("/things/bad") {
+            (200, "many".to_string())
+        } else if path.ends_with("/count") {
+            (200, "42\n".to_string())
+        } else if path.ends_with("/visible") {
+            (200, "true".to_string())
+        } else if path.ends_with("/note") {
+            (200, r#""a quoted note""#.to_string())
+        } else {
+            (404, String::new())
+        }
+    });
+
+    let mut client = crate::Client::new("some-token");
+    client.set_base_url(base_url);
+
+    let pong: String = client.meta().ping().await.unwrap();
+    assert_eq!(pong, "pong");
+    let count: i64 = client.things().get_count("good").await.unwrap();
+    assert_eq!(count, 42);
+    let visible: bool = client.things().get_visible("good").await.unwrap();
+    assert!(visible);
+    let note = client.things().get_note("good").await.unwrap();
+    assert_eq!(note, "a quoted note");
+
+    // Anything that is not a number is an error, not a default.
+    match client.things().get_count("bad").await {
+        Err(crate::types::error::Error::SerdeError { status, .. }) => {
+            assert_eq!(status, reqwest::StatusCode::OK);
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_unique_items() {
+    let (base_url, requests) = mock_server(|_| (204, String::new()));
+
+    let mut client = crate::Client::new("some-token");
+    client.set_base_url(base_url);
+
+    // Items that can be hashed are a set, which is still an array on the wire.
+    let members = crate::types::ThingMembers {
+        user_ids: ["b", "a", "b"].into_iter().map(String::from).collect(),
+        kinds: Some(vec![
+            crate::types::ThingKind::Widget,
+            crate::types::ThingKind::Gadget,
+        ]),
+        note: None,
+        limit: None,
+    };
+    assert_eq!(
+        serde_json::to_value(&members).unwrap(),
+        serde_json::json!({"user_ids": ["b", "a"], "kinds": ["widget", "Gadget"]})
+    );
+    let parsed: crate::types::ThingMembers =
+        serde_json::from_str(r#"{"user_ids": ["a", "a", "c"]}"#).unwrap();
+    assert_eq!(parsed.user_ids.iter().collect::<Vec<_>>(), vec!["a", "c"]);
+    client.things().set_members("good", &members).await.unwrap();
+
+    // The others are checked before we send anything.
+    let duplicates = crate::types::ThingMembers {
+        kinds: Some(vec![
+            crate::types::ThingKind::Widget,
+            crate::types::ThingKind::Gadget,
+            crate::types::ThingKind::Widget,
+        ]),
+        ..members
+    };
+    match client.things().set_members("good", &duplicates).await {
+        Err(crate::types::error::Error::InvalidRequest(msg)) => {
+            assert!(msg.contains("`kinds`"), "{}", msg);
+            assert!(msg.contains("[Widget]"), "{}", msg);
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+    match client
+        .things()
+        .set_tags("good", &vec!["x".to_string(), "x".to_string()])
+        .await
+    {
+        Err(crate::types::error::Error::InvalidRequest(msg)) => {
+            assert!(msg.contains("`body`"), "{}", msg);
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+    client
+        .things()
+        .set_tags("good", &vec!["x".to_string(), "y".to_string()])
+        .await
+        .unwrap();
+
+    let requests = requests.lock().unwrap().clone();
+    let paths: Vec<_> = requests.iter().map(|r| r.path.as_str()).collect();
+    assert_eq!(paths, vec!["/things/good/members", "/things/good/tags"]);
+}
+
+#[tokio::test]
+async fn test_validate_requests() {
+    let (base_url, requests) = mock_server(|_| (204, String::new()));
+
+    let mut client = crate::Client::new("some-token");
+    client.set_base_url(base_url);
+
+    let members = crate::types::ThingMembers {
+        user_ids: Default::default(),
+        kinds: None,
+        note: Some("a note far too long for the thing".to_string()),
+        limit: Some(0),
+    };
+    let errors = members.validate().unwrap_err();
+    assert_eq!(
+        errors
+            .iter()
+            .map(|error| error.field.as_str())
+            .collect::<Vec<_>>(),
+        vec!["user_ids", "note", "limit"]
+    );
+    assert_eq!(
+        errors[0].to_string(),
+        "`user_ids` must have at least 1 item, it has 0"
+    );
+
+    // The body is checked before we send it.
+    match client.things().set_members("good", &members).await {
+        Err(crate::types::error::Error::InvalidRequest(msg)) => {
+            assert!(msg.contains("`note` must have at most 20 characters"), "{}", msg);
+            assert!(msg.contains("`limit` must be at least 1, it is 0"), "{}", msg);
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+    assert!(requests.lock().unwrap().is_empty());
+
+    // So is an optional body, when there is one.
+    match client.things().update_members("good", Some(&members)).await {
+        Err(crate::types::error::Error::InvalidRequest(msg)) => {
+            assert!(msg.contains("`limit` must be at least 1, it is 0"), "{}", msg);
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+    assert!(requests.lock().unwrap().is_empty());
+    client.things().update_members("good", None).await.unwrap();
+    assert_eq!(requests.lock().unwrap().len(), 1);
+
+    let members = crate::types::ThingMembers {
+        user_ids: ["a".to_string()].into_iter().collect(),
+        note: Some("a note".to_string()),
+        limit: Some(100),
+        ..members
+    };
+    assert!(members.validate().is_ok());
+    client.things().set_members("good", &members).await.unwrap();
+    client
+        .things()
+        .update_members("good", Some(&members))
+        .await
+        .unwrap();
+    assert_eq!(requests.lock().unwrap().len(), 3);
+}
+
+#[cfg(feature = "interop")]
+#[test]
+fn test_interop_round_trip() {
+    let thing = crate::types::Thing {
+        id: "good".to_string(),
+        name: "A good thing".to_string(),
+        kind: Some(crate::types::ThingKind::BigBox),
+    };
+    let item = mock_interop::Item::from(thing.clone());
+    assert_eq!(
+        item,
+        mock_interop::Item {
+            id: "good".to_string(),
+            title: "A good thing".to_string(),
+            kind: Some(mock_interop::Kind::BigBox),
+        }
+    );
+    assert_eq!(crate::types::Thing::try_from(item).unwrap(), thing);
+
+    let shelf = crate::types::Shelf {
+        contents: vec![crate::types::ShelfContentsItem::Book {
+            title: "Dune".to_string(),
+        }],
+        labels: [(
+            "top".to_string(),
+            crate::types::ShelfLabelsValue::Number { number: 1 },
+        )]
+        .into_iter()
+        .collect(),
+    };
+    let external = mock_interop::Shelf::from(shelf.clone());
+    assert_eq!(
+        external.contents,
+        vec![mock_interop::ShelfItem::Book {
+            title: "Dune".to_string()
+        }]
+    );
+    assert_eq!(crate::types::Shelf::try_from(external).unwrap(), shelf);
+
+    // A variant we don't have cannot be converted back.
+    let err = crate::types::Thing::try_from(mock_interop::Item {
+        id: "new".to_string(),
+        title: "A new thing".to_string(),
+        kind: Some(mock_interop::Kind::Gizmo),
+    })
+    .unwrap_err();
+    assert_eq!(err.type_name, "ThingKind");
+}
+
+#[cfg(feature = "lenient")]
+#[test]
+fn test_lenient_numbers_and_booleans() {
+    let expected = crate::types::ThingStats {
+        views: 42,
+        rating: Some(4.5),
+        featured: true,
+        rank: None,
+    };
+    for json in [
+        r#"{"views": 42, "rating": 4.5, "featured": true}"#,
+        r#"{"views": "42", "rating": "4.5", "featured": "true"}"#,
+    ] {
+        let stats: crate::types::ThingStats = serde_json::from_str(json).unwrap();
+        assert_eq!(stats, expected);
+        // We always send the native values.
+        assert_eq!(
+            serde_json::to_value(&stats).unwrap(),
+            serde_json::json!({"views": 42, "rating": 4.5, "featured": true})
+        );
+    }
+
+    let stats: crate::types::ThingStats =
+        serde_json::from_str(r#"{"views": "1", "featured": false}"#).unwrap();
+    assert_eq!(stats.rating, None);
+}
+
+#[cfg(not(feature = "lenient"))]
+#[test]
+fn test_strict_numbers_and_booleans() {
+    let stats: crate::types::ThingStats =
+        serde_json::from_str(r#"{"views": 42, "rating": 4.5, "featured": true}"#).unwrap();
+    assert_eq!(stats.views, 42);
+
+    for json in [
+        r#"{"views": "42", "featured": true}"#,
+        r#"{"views": 42, "rating": "4.5", "featured": true}"#,
+        r#"{"views": 42, "featured": "true"}"#,
+    ] {
+        assert!(
+            serde_json::from_str::<crate::types::ThingStats>(json).is_err(),
+            "{}",
+            json
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_update_with_retries_on_conflict() {
+    // Someone else renames the thing between our first fetch and update.
+    let calls = std::sync::atomic::AtomicUsize::new(0);
+    let (base_url, requests) =
+        mock_server(
+            move |_| match calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst) {
+                0 => (200, r#"{"id": "good", "name": "A good thing"}"#.to_string()),
+                1 => (412, String::new()),
+                2 => (
+                    200,
+                    r#"{"id": "good", "name": "A renamed thing"}"#.to_string(),
+                ),
+                _ => (
+                    200,
+                    r#"{"id": "good", "name": "A renamed thing!"}"#.to_string(),
+                ),
+            },
+        );
+
+    let mut client = crate::Client::new("some-token");
+    client.set_base_url(base_url);
+
+    let mut seen = Vec::new();
+    let thing = client
+        .things()
+        .replace_with("good", |thing| {
+            seen.push(thing.name.clone());
+            thing.name.push('!');
+        })
+        .await
+        .unwrap();
+    assert_eq!(thing.name, "A renamed thing!");
+    assert_eq!(seen, vec!["A good thing", "A renamed thing"]);
+
+    let requests = requests.lock().unwrap().clone();
+    let methods: Vec<_> = requests
+        .iter()
+        .map(|r| (r.method.as_str(), r.path.as_str(), r.header("if-match")))
+        .collect();
+    assert_eq!(
+        methods,
+        vec![
+            ("GET", "/things/good", None),
+            ("PUT", "/things/good", Some("\"v1\"")),
+            ("GET", "/things/good", None),
+            ("PUT", "/things/good", Some("\"v1\"")),
+        ]
+    );
+    let sent: crate::types::Thing = serde_json::from_str(&requests[3].body).unwrap();
+    assert_eq!(sent.name, "A renamed thing!");
+}
+
+#[tokio::test]
+async fn test_update_with_gives_up() {
+    let (base_url, requests) = mock_server(|_| (409, String::new()));
+
+    let mut client = crate::Client::new("some-token");
+    client.set_base_url(base_url);
+
+    // The thing can't even be fetched, we don't retry that.
+    let err = client
+        .things()
+        .replace_with("good", |_| panic!("nothing to update"))
+        .await
+        .unwrap_err();
+    assert_eq!(err.status(), Some(reqwest::StatusCode::CONFLICT));
+    assert_eq!(requests.lock().unwrap().len(), 1);
+
+    // Every update conflicts, we stop after `max_attempts`.
+    let calls = std::sync::atomic::AtomicUsize::new(0);
+    let (base_url, requests) = mock_server(move |_| {
+        // The fetches succeed, the updates don't.
+        if matches!(
+            calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst),
+            0 | 2
+        ) {
+            (200, r#"{"id": "good", "name": "A good thing"}"#.to_string())
+        } else {
+            (409, String::new())
+        }
+    });
+    client.set_base_url(base_url);
+
+    let mut updates = 0;
+    let err = client
+        .things()
+        .replace_with("good", |_| updates += 1)
+        .await
+        .unwrap_err();
+    assert_eq!(err.status(), Some(reqwest::StatusCode::CONFLICT));
+    assert_eq!(updates, 2);
+    assert_eq!(requests.lock().unwrap().len(), 4);
+}
+
+/// A mock server for the device flow: it hands out codes good for `expires_in`
+/// seconds, then answers the polls for the token with `polls` in turn, the last
+/// one again and again.
+fn device_flow_server(
+    expires_in: u64,
+    polls: Vec<(u16, &'static str)>,
+) -> (String, Arc<Mutex<Vec<MockRequest>>>) {
+    let calls = std::sync::atomic::AtomicUsize::new(0);
+    mock_server(move |path| match path {
+        "/oauth2/device/auth" => (
+            200,
+            serde_json::json!({
+                "device_code": "device-1",
+                "user_code": "ABCD-EFGH",
+                "verification_uri": "https://a.b/device",
+                "expires_in": expires_in,
+                "interval": 0,
+            })
+            .to_string(),
+        ),
+        "/oauth2/device/token" => {
+            let call = calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            let (status, body) = polls[call.min(polls.len() - 1)];
+            (status, body.to_string())
+        }
+        _ => (200, r#""pong""#.to_string()),
+    })
+}
+
+#[tokio::test]
+async fn test_device_flow() {
+    // The user takes a while, and we poll too often.
+    let (base_url, requests) = device_flow_server(
+        60,
+        vec![
+            (400, r#"{"error": "authorization_pending"}"#),
+            (400, r#"{"error": "slow_down"}"#),
+            (
+                200,
+                r#"{"access_token": "device-token", "token_type": "Bearer", "expires_in": 3600}"#,
+            ),
+        ],
+    );
+    let mut client = crate::Client::new("");
+    client.set_base_url(base_url);
+
+    let flow = client
+        .oauth2()
+        .device_flow("d9797f8d-9ad6-4e08-90d7-2ec17e13471c")
+        .await
+        .unwrap();
+    assert_eq!(flow.authorization().user_code, "ABCD-EFGH");
+    assert_eq!(flow.authorization().verification_uri, "https://a.b/device");
+
+    // The server asked us to slow down once.
+    let started = std::time::Instant::now();
+    let client = flow.wait_for_client().await.unwrap();
+    assert!(started.elapsed() >= crate::device_flow::SLOW_DOWN);
+
+    // The client we got sends the token.
+    assert_eq!(client.meta().ping().await.unwrap(), "pong");
+
+    let requests = requests.lock().unwrap().clone();
+    let paths: Vec<_> = requests
+        .iter()
+        .map(|r| (r.path.as_str(), r.header("authorization")))
+        .collect();
+    assert_eq!(
+        paths,
+        vec![
+            ("/oauth2/device/auth", None),
+            ("/oauth2/device/token", None),
+            ("/oauth2/device/token", None),
+            ("/oauth2/device/token", None),
+            ("/ping", Some("Bearer device-token")),
+        ]
+    );
+    assert_eq!(
+        requests[0].body,
+        "client_id=d9797f8d-9ad6-4e08-90d7-2ec17e13471c"
+    );
+    assert_eq!(
+        requests[1].body,
+        "grant_type=urn%3Aietf%3Aparams%3Aoauth%3Agrant-type%3Adevice_code&device_code=device-1&client_id=d9797f8d-9ad6-4e08-90d7-2ec17e13471c"
+    );
+}
+
+#[tokio::test]
+async fn test_device_flow_ends_without_a_token() {
+    async fn wait(
+        expires_in: u64,
+        polls: Vec<(u16, &'static str)>,
+    ) -> (crate::device_flow::DeviceFlowError, usize) {
+        let (base_url, requests) = device_flow_server(expires_in, polls);
+        let mut client = crate::Client::new("");
+        client.set_base_url(base_url);
+        let flow = client.oauth2().device_flow("some-client").await.unwrap();
+        let err = flow.wait().await.unwrap_err();
+        let polls = requests.lock().unwrap().len() - 1;
+        (err, polls)
+    }
+
+    // The server says the codes expired.
+    let (err, polls) = wait(
+        60,
+        vec![
+            (400, r#"{"error": "authorization_pending"}"#),
+            (400, r#"{"error": "expired_token"}"#),
+        ],
+    )
+    .await;
+    assert!(
+        matches!(err, crate::device_flow::DeviceFlowError::Expired),
+        "{err:?}"
+    );
+    assert_eq!(polls, 2);
+
+    // The codes expire before we poll, we don't.
+    let (err, polls) = wait(0, vec![(400, r#"{"error": "authorization_pending"}"#)]).await;
+    assert!(
+        matches!(err, crate::device_flow::DeviceFlowError::Expired),
+        "{err:?}"
+    );
+    assert_eq!(polls, 0);
+
+    // The user said no.
+    let (err, _) = wait(60, vec![(400, r#"{"error": "access_denied"}"#)]).await;
+    assert!(
+        matches!(err, crate::device_flow::DeviceFlowError::AccessDenied),
+        "{err:?}"
+    );
+
+    // Another error of the token endpoint.
+    let (err, _) = wait(
+        60,
+        vec![(
+            400,
+            r#"{"error": "invalid_client", "error_description": "Who are you?"}"#,
+        )],
+    )
+    .await;
+    match err {
+        crate::device_flow::DeviceFlowError::OAuth {
+            error,
+            error_description,
+        } => {
+            assert_eq!(error, "invalid_client");
+            assert_eq!(error_description.as_deref(), Some("Who are you?"));
+        }
+        err => panic!("{err:?}"),
+    }
+
+    // Not an error of the token endpoint at all.
+    let (err, _) = wait(60, vec![(400, "oops")]).await;
+    match err {
+        crate::device_flow::DeviceFlowError::Request(err) => {
+            assert_eq!(err.status(), Some(reqwest::StatusCode::BAD_REQUEST));
+        }
+        err => panic!("{err:?}"),
+    }
+}
+
+#[cfg(feature = "record")]
+#[tokio::test]
+async fn test_record_and_replay() {
+    let (base_url, requests) = mock_server_with_headers(|path| {
+        if path.ends_with("/count") {
+            (200, "42".to_string(), Vec::new())
+        } else if path.ends_with("/tokens") {
+            (
+                201,
+                r#"{"id":"t1","token":"minted-secret"}"#.to_string(),
+                vec![("set-cookie", "session=cookie-secret".to_string())],
+            )
+        } else {
+            (
+                200,
+                r#"{"id": "good", "name": "A good thing"}"#.to_string(),
+                Vec::new(),
+            )
+        }
+    });
+    let path = std::env::temp_dir().join(format!("mock-{}.jsonl", uuid::Uuid::new_v4()));
+
+    let mut client = crate::Client::new("some-token");
+    client.set_base_url(base_url);
+    client.set_recorder(&path).unwrap();
+    let thing = client.things().get("good").await.unwrap();
+    let count = client.things().get_count("good").await.unwrap();
+    client
+        .things()
+        .get_secret("req-1", "good", "session-secret", None, None)
+        .await
+        .unwrap();
+    // The caller gets the token the API handed out.
+    let token = client
+        .tokens()
+        .create(&crate::types::TokenRequest {
+            name: "ci".to_string(),
+            password: "hunter2".to_string().into(),
+        })
+        .await
+        .unwrap();
+    assert_eq!(token.token.as_str(), "minted-secret");
+    assert_eq!(requests.lock().unwrap().len(), 4);
+
+    // Nothing listens there, so the calls must be replayed.
+    let mut offline = crate::Client::new("another-token");
+    offline.set_base_url("http://127.0.0.1:9");
+    let replay = crate::ReplayClient::new(offline, &path).unwrap();
+    assert_eq!(replay.things().get("good").await.unwrap(), thing);
+    assert_eq!(replay.things().get_count("good").await.unwrap(), count);
+    assert_eq!(replay.things().get("good").await.unwrap(), thing);
+    // The token is replayed as it was handed out, only the password is redacted.
+    let replayed = replay
+        .tokens()
+        .create(&crate::types::TokenRequest {
+            name: "ci".to_string(),
+            password: "another-password".to_string().into(),
+        })
+        .await
+        .unwrap();
+    assert_eq!(replayed.token.as_str(), "minted-secret");
+
+    // The parameters must match too.
+    let err = replay.things().get("other").await.unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("there is no recorded call to `get_thing`"),
+        "{}",
+        err
+    );
+
+    // The headers, with the token and the cookies, are not recorded, nor are the
+    // passwords of the bodies and the cookies of the responses.
+    let recording = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(recording.lines().count(), 4);
+    for secret in [
+        "some-token",
+        "session-secret",
+        "hunter2",
+        "cookie-secret",
+    ] {
+        assert!(!recording.contains(secret), "{}: {}", secret, recording);
+    }
+    let first: serde_json::Value = serde_json::from_str(recording.lines().next().unwrap()).unwrap();
+    assert_eq!(first["operation_id"], "get_thing");
+    assert_eq!(first["method"], "GET");
+    assert_eq!(first["status"], 200);
+}
+
+#[tokio::test]
+async fn test_get_batch() {
+    use futures::StreamExt;
+
+    let (base_url, requests) = mock_server(|path| {
+        if path.ends_with("/thing-4") {
+            (404, r#"{"message": "no such thing"}"#.to_string())
+        } else {
+            let id = path.rsplit('/').next().unwrap();
+            (200, format!(r#"{{"id": "{id}", "name": "A thing"}}"#))
+        }
+    });
+
+    let mut client = crate::Client::new("some-token");
+    client.set_base_url(base_url);
+
+    let ids: Vec<String> = (0..10).map(|i| format!("thing-{i}")).collect();
+    let mut results: Vec<_> = client
+        .things()
+        .get_batch(ids.clone(), 3)
+        .collect::<Vec<_>>()
+        .await;
+    results.sort_by(|a, b| a.0.cmp(&b.0));
+
+    // Every id has its result, the 404 did not stop the others.
+    assert_eq!(results.len(), 10);
+    for (id, result) in &results {
+        match result {
+            Ok(thing) => assert_eq!(&thing.id, id),
+            Err(err) => {
+                assert_eq!(id, "thing-4");
+                assert_eq!(err.status(), Some(reqwest::StatusCode::NOT_FOUND));
+            }
+        }
+    }
+    assert_eq!(results.iter().filter(|(_, r)| r.is_err()).count(), 1);
+
+    // At most three requests were in flight, so at most three connections.
+    let requests = requests.lock().unwrap().clone();
+    assert_eq!(requests.len(), 10);
+    let mut connections: Vec<_> = requests.iter().map(|r| r.connection).collect();
+    connections.sort();
+    connections.dedup();
+    assert!(connections.len() <= 3, "{:?}", connections);
+}
+
+#[tokio::test]
+async fn test_download_filename() {
+    let (base_url, requests) = mock_server_with_headers(|path| {
+        let disposition = match path.rsplit('=').next().unwrap() {
+            "quoted" => r#"attachment; filename="part.step""#,
+            "unquoted" => "attachment; filename=part.step",
+            "encoded" => {
+                r#"attachment; filename="piece.step"; filename*=UTF-8''pi%C3%A8ce%20%E2%82%AC.step"#
+            }
+            _ => "",
+        };
+        let mut headers = vec![("Content-Type", "model/step".to_string())];
+        if !disposition.is_empty() {
+            headers.push(("Content-Disposition", disposition.to_string()));
+        }
+        (200, "ISO-10303-21;".to_string(), headers)
+    });
+
+    let mut client = crate::Client::new("some-token");
+    client.set_base_url(base_url);
+
+    for (format, filename) in [
+        ("quoted", Some("part.step")),
+        ("unquoted", Some("part.step")),
+        ("encoded", Some("pièce €.step")),
+        ("none", None),
+    ] {
+        let download = client.things().download(format, "good").await.unwrap();
+        assert_eq!(download.filename.as_deref(), filename, "{}", format);
+        assert_eq!(download.content_type.as_deref(), Some("model/step"));
+        assert_eq!(download.bytes, "ISO-10303-21;");
+    }
+
+    let requests = requests.lock().unwrap().clone();
+    assert_eq!(
+        requests[0].header("accept"),
+        Some("application/octet-stream")
+    );
+}
+
+#[tokio::test]
+async fn test_response_cache() {
+    let (base_url, requests) = mock_server_with_headers(|path| {
+        let body = r#"{"id": "good", "name": "A good thing"}"#.to_string();
+        if path.ends_with("/plain") {
+            (200, body, Vec::new())
+        } else if path.ends_with("/varied") {
+            (
+                200,
+                body,
+                vec![
+                    ("Cache-Control", "max-age=60".to_string()),
+                    ("Vary", "Accept-Language".to_string()),
+                ],
+            )
+        } else {
+            (200, body, vec![("Cache-Control", "max-age=60".to_string())])
+        }
+    });
+
+    let mut client = crate::Client::new("some-token");
+    client.set_base_url(base_url);
+    client.set_response_cache(Arc::new(crate::types::cache::MemoryCache::default()));
+
+    // A second call within the max-age does not go to the server.
+    let thing = client.things().get("good").await.unwrap();
+    assert_eq!(client.things().get("good").await.unwrap(), thing);
+    assert_eq!(requests.lock().unwrap().len(), 1);
+
+    // Neither other URLs, other tokens, responses without a max-age or with a
+    // `Vary` header nor other methods are answered from the cache.
+    client.things().get("other").await.unwrap();
+    client
+        .with_token_override("bob-token")
+        .things()
+        .get("good")
+        .await
+        .unwrap();
+    client.things().get("plain").await.unwrap();
+    client.things().get("plain").await.unwrap();
+    client.things().get("varied").await.unwrap();
+    client.things().get("varied").await.unwrap();
+    client.things().replace("good", &thing).await.unwrap();
+    client.things().replace("good", &thing).await.unwrap();
+
+    let requests = requests.lock().unwrap().clone();
+    let paths: Vec<_> = requests
+        .iter()
+        .map(|r| (r.method.as_str(), r.path.as_str()))
+        .collect();
+    assert_eq!(
+        paths,
+        vec![
+            ("GET", "/things/good"),
+            ("GET", "/things/other"),
+            ("GET", "/things/good"),
+            ("GET", "/things/plain"),
+            ("GET", "/things/plain"),
+            ("GET", "/things/varied"),
+            ("GET", "/things/varied"),
+            ("PUT", "/things/good"),
+            ("PUT", "/things/good"),
+        ]
+    );
+}
+
+#[test]
+fn test_case_insensitive_enums() {
+    use crate::types::ThingKind;
+
+    for (payload, expected) in [
+        (r#""widget""#, ThingKind::Widget),
+        (r#""WIDGET""#, ThingKind::Widget),
+        (r#""gadget""#, ThingKind::Gadget),
+        (r#""Big-Box""#, ThingKind::BigBox),
+    ] {
+        assert_eq!(
+            serde_json::from_str::<ThingKind>(payload).unwrap(),
+            expected
+        );
+    }
+    assert_eq!("GaDgEt".parse::<ThingKind>().unwrap(), ThingKind::Gadget);
+
+    // We still send the values of the spec.
+    assert_eq!(
+        serde_json::to_string(&ThingKind::Gadget).unwrap(),
+        r#""Gadget""#
+    );
+    assert_eq!(ThingKind::BigBox.to_string(), "big-box");
+
+    let err = serde_json::from_str::<ThingKind>(r#""big_box""#).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "unknown variant `big_box`, expected one of `widget`, `Gadget`, `big-box`"
+    );
+    assert!("big_box".parse::<ThingKind>().is_err());
+}
+
+#[test]
+fn test_borrowed_types() {
+    use std::borrow::Cow;
+
+    let json = br#"{
+        "items": [
+            {"id": "thing-1", "name": "A thing", "kind": "widget"},
+            {"id": "thing-2", "name": "A \"quoted\" thing"}
+        ],
+        "next_page": "page-2"
+    }"#;
+    let page = crate::types::borrowed::ThingResultsPage::from_slice(json).unwrap();
+
+    // The strings point into the JSON, unless they have escapes.
+    assert!(matches!(page.items[0].id, Cow::Borrowed("thing-1")));
+    assert!(matches!(page.items[0].name, Cow::Borrowed("A thing")));
+    assert!(matches!(page.items[1].name, Cow::Owned(ref name) if name == "A \"quoted\" thing"));
+    assert!(matches!(page.next_page, Some(Cow::Borrowed("page-2"))));
+
+    // They are the same as the generated types once owned.
+    let owned: crate::types::ThingResultsPage = serde_json::from_slice(json).unwrap();
+    assert_eq!(page.clone().into_owned(), owned);
+    assert_eq!(
+        serde_json::to_value(&page).unwrap(),
+        serde_json::to_value(&owned).unwrap()
+    );
+
+    let page = crate::types::borrowed::ThingResultsPage::from_slice(br#"{"items": []}"#).unwrap();
+    assert_eq!(page.next_page, None);
+}
+
+#[test]
+fn test_no_std_core_types() {
+    let json = serde_json::json!({
+        "contents": [
+            {"type": "book", "title": "A book"},
+            {"type": "plant", "species": "Fern"}
+        ],
+        "labels": {
+            "1": {"kind": "text", "text": "top"},
+            "2": {"kind": "number", "number": 2}
+        }
+    });
+
+    // The copies in `types::core` read and write the same JSON as the types.
+    let shelf: crate::types::Shelf = serde_json::from_value(json.clone()).unwrap();
+    let core: crate::types::core::Shelf = serde_json::from_value(json.clone()).unwrap();
+    assert_eq!(serde_json::to_value(&shelf).unwrap(), json);
+    assert_eq!(serde_json::to_value(&core).unwrap(), json);
+    assert_eq!(
+        core.labels.keys().collect::<Vec<_>>(),
+        ["1", "2"],
+        "the maps are `BTreeMap`s"
+    );
+    assert_eq!(
+        core.contents[1],
+        crate::types::core::ShelfContentsItem::Plant {
+            species: "Fern".to_string()
+        }
+    );
+}
+
+/// Compare parsing a big page into the generated types and into the borrowed ones,
+/// run with `cargo test --release -- --ignored --nocapture bench_borrowed_types`.
+#[test]
+#[ignore]
+fn bench_borrowed_types() {
+    let items = (0..50_000)
+        .map(|i| {
+            serde_json::json!({
+                "id": format!("0b4e3c52-8a8e-4f3a-9f5c-{:012}", i),
+                "name": format!("A thing with a fairly long name, number {}", i),
+                "kind": "widget",
+            })
+        })
+        .collect::<Vec<_>>();
+    let json =
+        serde_json::to_vec(&serde_json::json!({"items": items, "next_page": "page-2"})).unwrap();
+
+    let runs = 20;
+    let time = |parse: &dyn Fn() -> usize| {
+        let start = std::time::Instant::now();
+        for _ in 0..runs {
+            assert_eq!(parse(), 50_000);
+        }
+        start.elapsed() / runs
+    };
+    let owned = time(&|| {
+        serde_json::from_slice::<crate::types::ThingResultsPage>(&json)
+            .unwrap()
+            .items
+            .len()
+    });
+    let borrowed = time(&|| {
+        crate::types::borrowed::ThingResultsPage::from_slice(&json)
+            .unwrap()
+            .items
+            .len()
+    });
+    println!(
+        "parsing {} bytes: owned {:?}, borrowed {:?} ({:.2}x)",
+        json.len(),
+        owned,
+        borrowed,
+        owned.as_secs_f64() / borrowed.as_secs_f64()
+    );
+}
+
+#[test]
+fn test_enum_parse_with_suggestions() {
+    use crate::types::ThingKind;
+
+    assert_eq!(
+        ThingKind::parse_with_suggestions("gadget").unwrap(),
+        ThingKind::Gadget
+    );
+
+    let err = ThingKind::parse_with_suggestions("widgit").unwrap_err();
+    assert_eq!(err.type_name, "ThingKind");
+    assert_eq!(err.suggestions, ["widget"]);
+    assert_eq!(
+        err.to_string(),
+        "`widgit` is not a valid `ThingKind`, did you mean `widget`? The values are `widget`, \
+         `Gadget` and `big-box`."
+    );
+
+    let err = ThingKind::parse_with_suggestions("big").unwrap_err();
+    assert_eq!(err.suggestions, ["big-box"]);
+}
@@ -0,0 +1,12 @@
+#[tokio::test]
+async fn test_synthesized_function_names() {
+    let client = crate::Client::new("token");
+
+    // The operations without an id still have a function, named after their path.
+    let _ = client.users().get_by_id("1");
+    let _ = client.users().get_by_id_2("1");
+    let _ = client.users().get();
+    let _ = client.users().get_2();
+    let _ = client.users().delete_by_id("1");
+    let _ = client.users().get_by_id_projects("1");
+}
@@ -0,0 +1,66 @@
+use std::str::FromStr;
+
+use pretty_assertions::assert_eq;
+
+use crate::types::scope::{Scope, Scopes};
+
+fn client() -> crate::Client {
+    crate::Client::new(
+        "some-client",
+        "some-secret",
+        "https://example.com/callback",
+        "some-token",
+        "some-refresh-token",
+    )
+}
+
+#[test]
+fn test_scopes_display() {
+    assert_eq!(Scope::ReadUsers.to_string(), "read:users");
+    assert_eq!(Scope::from_str("write:files").unwrap(), Scope::WriteFiles);
+    assert!(Scope::from_str("write:file")
+        .unwrap_err()
+        .to_string()
+        .contains("write:files"));
+
+    let scopes: Scopes = [Scope::ReadUsers, Scope::WriteFiles].into_iter().collect();
+    assert_eq!(scopes.to_string(), "read:users write:files");
+    assert_eq!(
+        serde_json::to_string(&scopes.0).unwrap(),
+        r#"["read:users","write:files"]"#
+    );
+}
+
+#[test]
+fn test_user_consent_url_scopes() {
+    let client = client();
+
+    let url = url::Url::parse(&client.user_consent_url([Scope::ReadUsers, Scope::WriteFiles]))
+        .unwrap();
+    assert_eq!(url.path(), "/oauth/authorize");
+    let query: Vec<_> = url
+        .query_pairs()
+        .filter(|(k, _)| k != "state")
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+    assert_eq!(
+        query,
+        vec![
+            ("client_id".to_string(), "some-client".to_string()),
+            ("response_type".to_string(), "code".to_string()),
+            (
+                "redirect_uri".to_string(),
+                "https://example.com/callback".to_string()
+            ),
+            ("scope".to_string(), "read:users write:files".to_string()),
+        ]
+    );
+
+    // Without scopes there is no scope at all.
+    let url = url::Url::parse(&client.user_consent_url([])).unwrap();
+    assert!(url.query_pairs().all(|(k, _)| k != "scope"));
+
+    // The raw scopes are not checked.
+    let url = client.user_consent_url_raw(&["read:everything".to_string()]);
+    assert!(url.ends_with("&scope=read:everything"), "{}", url);
+}
@@ -0,0 +1,41 @@
+use pretty_assertions::assert_eq;
+
+#[test]
+fn test_opaque_schemas_are_values() {
+    let dump = serde_json::json!({
+        "unit": "mm",
+        "scene": {"nodes": [{"id": "root", "children": []}]},
+        "stats": {"frames": 3, "mode": "gpu", "new_counter": 1}
+    });
+    let session: crate::types::Session = serde_json::from_value(serde_json::json!({
+        "id": "abc",
+        "unit": "mm",
+        "dump": dump.clone(),
+        "history": [dump.clone(), {"anything": "goes"}],
+        "last_error": null,
+        "event": {"EngineDump": dump.clone()}
+    }))
+    .unwrap();
+
+    // The dumps are passed through as they are, fields we know nothing of too.
+    assert_eq!(session.dump, Some(dump.clone()));
+    assert_eq!(session.history[1]["anything"], "goes");
+    assert_eq!(session.last_error, None);
+    assert_eq!(
+        session.event,
+        Some(crate::types::SessionEvent::EngineDump(dump.clone()))
+    );
+    assert_eq!(session.unit, crate::types::Unit::Mm);
+    assert_eq!(
+        serde_json::to_value(&session).unwrap()["dump"]["stats"]["new_counter"],
+        1
+    );
+}
+
+// The responses are values too, it only has to compile.
+#[allow(dead_code)]
+async fn get_dump(
+    client: &crate::Client,
+) -> Result<serde_json::Value, crate::types::error::Error> {
+    client.sessions().get_dump("abc").await
+}
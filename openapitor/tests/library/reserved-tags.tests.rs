@@ -0,0 +1,17 @@
+#[test]
+fn test_reserved_tag_modules() {
+    let client = crate::Client::new("token");
+
+    // The tags with a reserved name have modules of other names.
+    let _: crate::types_api::TypesApi = client.types_api();
+    let _: crate::client_api::ClientApi = client.client_api();
+    let _ = client.types_api().list_types();
+    let _ = client.client_api().get_client("1");
+    let _ = client.widgets().get("1");
+
+    // And `types` is still the types.
+    let widget: crate::types::Widget =
+        serde_json::from_str(r#"{"id": "1", "type": {"name": "round"}}"#).unwrap();
+    assert_eq!(widget.type_.name, "round");
+    let _: crate::types::Client = serde_json::from_str(r#"{"id": "1", "name": "A"}"#).unwrap();
+}